@@ -13,8 +13,13 @@
 //! and since we backup all artifacts instantly after the pool update, there is
 //! no possibility to inject purging (or any other deletion) of artifacts
 //! between the pool update and the backup.
+//!
+//! With [`BackupFormat::Archive`], the purging thread additionally packs
+//! complete ranges of heights into compressed archives, see [`archive`].
+
+pub mod archive;
 
-use ic_config::artifact_pool::BACKUP_GROUP_SIZE;
+use ic_config::artifact_pool::{BackupFormat, BACKUP_GROUP_SIZE};
 use ic_interfaces::{
     consensus_pool::{ConsensusPool, HeightRange},
     time_source::TimeSource,
@@ -57,6 +62,8 @@ pub enum BackupArtifact {
 struct Metrics {
     // Amount of I/O errors. Any number above 0 is critical.
     io_errors: IntCounter,
+    // Number of heights packed into archives.
+    archived_heights: IntCounter,
 }
 
 impl Metrics {
@@ -66,6 +73,10 @@ impl Metrics {
                 "consensus_backup_io_errors",
                 "The number of I/O errors happened during the consensus backup storing or purging.",
             ),
+            archived_heights: registry.int_counter(
+                "consensus_backup_archived_heights",
+                "The number of heights whose backup artifacts were packed into archives.",
+            ),
         }
     }
 }
@@ -141,6 +152,9 @@ struct PurgingThread {
     backup_path: PathBuf,
     // The maximum age backup artifacts can reach before purging.
     age_threshold: Duration,
+    // Path pointing to <backup_dir>/<subnet_id>/<replica_version>, if the artifacts of the
+    // current replica version are packed into archives before purging.
+    archive_path: Option<PathBuf>,
     metrics: Metrics,
     log: ReplicaLogger,
     age: Box<dyn BackupAge>,
@@ -174,6 +188,7 @@ impl PurgingThread {
     fn new(
         backup_path: PathBuf,
         age_threshold: Duration,
+        archive_path: Option<PathBuf>,
         metrics: Metrics,
         log: ReplicaLogger,
         age: Box<dyn BackupAge>,
@@ -181,6 +196,7 @@ impl PurgingThread {
         Self {
             backup_path,
             age_threshold,
+            archive_path,
             metrics,
             log,
             age,
@@ -201,6 +217,15 @@ impl PurgingThread {
             match rx.recv() {
                 Ok(PurgingRequest::Purge) => {
                     let start = std::time::Instant::now();
+                    if let Some(archive_path) = &self.archive_path {
+                        match archive::pack_backup(archive_path) {
+                            Ok(heights) => self.metrics.archived_heights.inc_by(heights as u64),
+                            Err(err) => {
+                                error!(self.log, "Backup packing failed: {:?}", err);
+                                self.metrics.io_errors.inc();
+                            }
+                        }
+                    }
                     if let Err(err) = purge(
                         self.age_threshold,
                         &self.backup_path,
@@ -251,6 +276,7 @@ impl Backup {
         version_path: PathBuf,
         age_threshold: Duration,
         purge_interval: Duration,
+        format: BackupFormat,
         metrics_registry: MetricsRegistry,
         log: ReplicaLogger,
         age: Box<dyn BackupAge>,
@@ -259,9 +285,14 @@ impl Backup {
         let metrics = Metrics::new(&metrics_registry);
         let (backup_queue, backup_thread) =
             BackupThread::new(version_path.clone(), metrics.clone(), log.clone()).start();
+        let archive_path = match format {
+            BackupFormat::Files => None,
+            BackupFormat::Archive => Some(version_path.clone()),
+        };
         let (purging_queue, purging_thread) = PurgingThread::new(
             backup_path,
             age_threshold,
+            archive_path,
             metrics.clone(),
             log.clone(),
             age,
@@ -300,6 +331,7 @@ impl Backup {
         version_path: PathBuf,
         age_threshold: Duration,
         purge_interval: Duration,
+        format: BackupFormat,
         metrics_registry: MetricsRegistry,
         log: ReplicaLogger,
         time_source: Arc<dyn TimeSource>,
@@ -310,6 +342,7 @@ impl Backup {
             version_path,
             age_threshold,
             purge_interval,
+            format,
             metrics_registry,
             log,
             Box::new(FileSystemAge {}),
@@ -0,0 +1,746 @@
+//! Packed format of the consensus artifact backup.
+//!
+//! Storing every artifact in a separate file produces millions of small files,
+//! which are slow to rsync and to purge. In the archive format, artifacts are
+//! still written as separate files first. The purging thread later packs the
+//! files of every complete range of [`BACKUP_ARCHIVE_HEIGHTS`] heights into a
+//! single archive in the group directory of the range:
+//!
+//! <subnet_id>/<replica_version>/<group>/<first height>_<last height>_<index hash>.archive
+//!
+//! An archive consists of
+//!
+//!   * a header: magic bytes, format version, length of the index and the
+//!     SHA-256 hash of the index,
+//!
+//!   * the bincode encoded index, which lists every packed file together with
+//!     the SHA-256 hash of its content, and the location, size and SHA-256 hash
+//!     of every blob,
+//!
+//!   * the zstd compressed blobs.
+//!
+//! Blobs are content addressed, i.e. files with the same content are stored
+//! once. The index hash in the header covers the hashes of all blobs, so it
+//! serves as the integrity manifest of the whole archive.
+//!
+//! Archives are never modified after they were written. Files arriving for a
+//! range that was already packed end up in another archive of the same range.
+//! This keeps the archives compatible with `rsync --ignore-existing`. For the
+//! same reason, an archived file is invalidated by writing a separate
+//! `invalid_<name>` file into the height directory of the file.
+//!
+//! Readers treat a backup directory as the union of the separate files and the
+//! files in all archives.
+
+use ic_config::artifact_pool::{BACKUP_ARCHIVE_HEIGHTS, BACKUP_GROUP_SIZE};
+use ic_crypto_sha2::Sha256;
+use ic_types::Height;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+const ARCHIVE_MAGIC: [u8; 8] = *b"ICBACKUP";
+const ARCHIVE_VERSION: u32 = 1;
+const ARCHIVE_EXTENSION: &str = "archive";
+// Magic bytes, version, index length and index hash.
+const HEADER_LEN: u64 = 8 + 4 + 8 + 32;
+const INVALID_PREFIX: &str = "invalid_";
+
+#[derive(Serialize, Deserialize)]
+struct ArchiveIndex {
+    first_height: u64,
+    last_height: u64,
+    files: Vec<ArchivedFile>,
+    blobs: Vec<Blob>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchivedFile {
+    height: u64,
+    name: String,
+    sha256: [u8; 32],
+}
+
+#[derive(Serialize, Deserialize)]
+struct Blob {
+    sha256: [u8; 32],
+    // Offset of the compressed blob, relative to the end of the index.
+    offset: u64,
+    compressed_len: u64,
+    len: u64,
+}
+
+/// An archive of backup files, whose index was verified against the hash in
+/// the header.
+pub struct BackupArchive {
+    path: PathBuf,
+    first_height: Height,
+    last_height: Height,
+    files: BTreeMap<(Height, String), [u8; 32]>,
+    blobs: BTreeMap<[u8; 32], Blob>,
+    blobs_offset: u64,
+    file_len: u64,
+}
+
+impl BackupArchive {
+    /// Opens the archive at `path` and reads its index.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = fs::File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut header = [0; HEADER_LEN as usize];
+        file.read_exact(&mut header)?;
+        if header[..8] != ARCHIVE_MAGIC {
+            return Err(corrupted(path, "unknown magic bytes"));
+        }
+        let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        if version != ARCHIVE_VERSION {
+            return Err(corrupted(path, &format!("unsupported version {}", version)));
+        }
+        let index_len = u64::from_le_bytes(header[12..20].try_into().unwrap());
+        if HEADER_LEN + index_len > file_len {
+            return Err(corrupted(path, "truncated index"));
+        }
+        let mut index_bytes = vec![0; index_len as usize];
+        file.read_exact(&mut index_bytes)?;
+        if Sha256::hash(&index_bytes)[..] != header[20..] {
+            return Err(corrupted(path, "index hash mismatch"));
+        }
+        let index: ArchiveIndex = bincode::deserialize(&index_bytes)
+            .map_err(|err| corrupted(path, &format!("undecodable index: {}", err)))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            first_height: Height::from(index.first_height),
+            last_height: Height::from(index.last_height),
+            files: index
+                .files
+                .into_iter()
+                .map(|file| ((Height::from(file.height), file.name), file.sha256))
+                .collect(),
+            blobs: index
+                .blobs
+                .into_iter()
+                .map(|blob| (blob.sha256, blob))
+                .collect(),
+            blobs_offset: HEADER_LEN + index_len,
+            file_len,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the lowest and the highest height of the archived files.
+    pub fn height_range(&self) -> (Height, Height) {
+        (self.first_height, self.last_height)
+    }
+
+    /// Returns the height and name of every archived file.
+    pub fn files(&self) -> impl Iterator<Item = (Height, &str)> {
+        self.files
+            .keys()
+            .map(|(height, name)| (*height, name.as_str()))
+    }
+
+    pub fn contains(&self, height: Height, name: &str) -> bool {
+        self.files.contains_key(&(height, name.to_string()))
+    }
+
+    /// Returns the content of the file `name` at `height`, or `None` if the
+    /// archive does not contain such a file.
+    pub fn read(&self, height: Height, name: &str) -> io::Result<Option<Vec<u8>>> {
+        match self.files.get(&(height, name.to_string())) {
+            Some(sha256) => self.read_blob(sha256).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Checks that the archive has the expected size, that every file refers
+    /// to an existing blob and that every blob matches its hash. Returns the
+    /// number of files in the archive.
+    pub fn verify(&self) -> io::Result<usize> {
+        let blobs_len: u64 = self.blobs.values().map(|blob| blob.compressed_len).sum();
+        if self.blobs_offset + blobs_len != self.file_len {
+            return Err(corrupted(&self.path, "unexpected file size"));
+        }
+        for (height, _) in self.files.keys() {
+            if *height < self.first_height || *height > self.last_height {
+                return Err(corrupted(&self.path, "file outside of the height range"));
+            }
+        }
+        for sha256 in self.files.values() {
+            if !self.blobs.contains_key(sha256) {
+                return Err(corrupted(&self.path, "missing blob"));
+            }
+        }
+        for sha256 in self.blobs.keys() {
+            self.read_blob(sha256)?;
+        }
+        Ok(self.files.len())
+    }
+
+    fn read_blob(&self, sha256: &[u8; 32]) -> io::Result<Vec<u8>> {
+        let blob = self
+            .blobs
+            .get(sha256)
+            .ok_or_else(|| corrupted(&self.path, "missing blob"))?;
+        let offset = self.blobs_offset + blob.offset;
+        if offset + blob.compressed_len > self.file_len {
+            return Err(corrupted(&self.path, "truncated blob"));
+        }
+        let mut file = fs::File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut compressed = vec![0; blob.compressed_len as usize];
+        file.read_exact(&mut compressed)?;
+        let content = zstd::bulk::decompress(&compressed, blob.len as usize)
+            .map_err(|err| corrupted(&self.path, &format!("undecodable blob: {}", err)))?;
+        if content.len() as u64 != blob.len || Sha256::hash(&content) != blob.sha256 {
+            return Err(corrupted(&self.path, "blob hash mismatch"));
+        }
+        Ok(content)
+    }
+}
+
+/// Result of [`verify_archives`].
+#[derive(Debug, Default)]
+pub struct ArchiveVerification {
+    pub verified_archives: usize,
+    pub verified_files: usize,
+    pub corrupted_archives: Vec<(PathBuf, io::Error)>,
+}
+
+/// Packs `files`, given as height, file name and content, into a new archive
+/// in `group_dir` and returns the path of the archive.
+pub fn write_archive(
+    group_dir: &Path,
+    files: Vec<(Height, String, Vec<u8>)>,
+) -> io::Result<PathBuf> {
+    let mut index = ArchiveIndex {
+        first_height: u64::MAX,
+        last_height: 0,
+        files: Vec::with_capacity(files.len()),
+        blobs: Vec::new(),
+    };
+    let mut blobs = Vec::new();
+    let mut blobs_len = 0;
+    let mut stored = BTreeSet::new();
+    for (height, name, content) in files {
+        let sha256 = Sha256::hash(&content);
+        index.first_height = index.first_height.min(height.get());
+        index.last_height = index.last_height.max(height.get());
+        index.files.push(ArchivedFile {
+            height: height.get(),
+            name,
+            sha256,
+        });
+        if stored.insert(sha256) {
+            let compressed = zstd::bulk::compress(&content, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+            index.blobs.push(Blob {
+                sha256,
+                offset: blobs_len,
+                compressed_len: compressed.len() as u64,
+                len: content.len() as u64,
+            });
+            blobs_len += compressed.len() as u64;
+            blobs.push(compressed);
+        }
+    }
+    if index.files.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Cannot write an empty archive",
+        ));
+    }
+
+    let index_bytes = bincode::serialize(&index)
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+    let index_hash = Sha256::hash(&index_bytes);
+    let path = group_dir.join(format!(
+        "{}_{}_{}.{}",
+        index.first_height,
+        index.last_height,
+        index_hash[..8]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>(),
+        ARCHIVE_EXTENSION
+    ));
+    fs::create_dir_all(group_dir)?;
+    ic_utils::fs::write_using_tmp_file(&path, |writer| {
+        writer.write_all(&ARCHIVE_MAGIC)?;
+        writer.write_all(&ARCHIVE_VERSION.to_le_bytes())?;
+        writer.write_all(&(index_bytes.len() as u64).to_le_bytes())?;
+        writer.write_all(&index_hash)?;
+        writer.write_all(&index_bytes)?;
+        blobs.iter().try_for_each(|blob| writer.write_all(blob))
+    })?;
+    Ok(path)
+}
+
+/// Returns the lowest height, the highest height and the path of every archive
+/// in `group_dir`, ordered by the lowest height. The heights are taken from
+/// the file names, the archives are not opened.
+pub fn list_archives(group_dir: &Path) -> io::Result<Vec<(Height, Height, PathBuf)>> {
+    let entries = match fs::read_dir(group_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut archives = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if let Some((first_height, last_height)) = parse_archive_name(&path) {
+            archives.push((first_height, last_height, path));
+        }
+    }
+    archives.sort();
+    Ok(archives)
+}
+
+/// Returns the content of the backup file at `path`, which is either a
+/// separate file or contained in an archive of its group.
+pub fn read_backup_file(path: &Path) -> io::Result<Vec<u8>> {
+    match fs::read(path) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => match find_archived_file(path)? {
+            Some((archive, height, name)) => archive
+                .read(height, &name)?
+                .ok_or_else(|| corrupted(archive.path(), "missing file")),
+            None => Err(err),
+        },
+        result => result,
+    }
+}
+
+/// Returns true if the backup file at `path` exists, either as a separate file
+/// or in an archive of its group.
+pub fn backup_file_exists(path: &Path) -> bool {
+    path.exists() || matches!(find_archived_file(path), Ok(Some(_)))
+}
+
+/// Marks the backup file at `path` as invalid and returns the path of the
+/// invalidated file. Separate files are renamed by adding the prefix
+/// `invalid_`. For archived files, a separate file with that name is created.
+pub fn invalidate_backup_file(path: &Path) -> io::Result<PathBuf> {
+    let invalid_path = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => path.with_file_name(format!("{}{}", INVALID_PREFIX, name)),
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a backup file", path.display()),
+            ))
+        }
+    };
+    if path.exists() {
+        fs::rename(path, &invalid_path)?;
+    } else {
+        let content = read_backup_file(path)?;
+        if let Some(height_dir) = path.parent() {
+            fs::create_dir_all(height_dir)?;
+        }
+        ic_utils::fs::write_using_tmp_file(&invalid_path, |writer| writer.write_all(&content))?;
+    }
+    Ok(invalid_path)
+}
+
+/// Lists all backup files in `version_path` at or above `start_height`. For
+/// every height, it returns the path of the height directory, which does not
+/// exist if all files of the height are archived, and the names of the files.
+/// Archived files that were invalidated are omitted.
+pub fn list_backup_files(
+    version_path: &Path,
+    start_height: Height,
+) -> io::Result<BTreeMap<Height, (PathBuf, Vec<String>)>> {
+    let mut heights: BTreeMap<Height, (PathBuf, BTreeSet<String>)> = BTreeMap::new();
+    for group_dir in fs::read_dir(version_path)? {
+        let group_dir = group_dir?.path();
+        for entry in fs::read_dir(&group_dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                let height = parse_height_dir(&path).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Couldn't parse the height directory {}", path.display()),
+                    )
+                })?;
+                if height < start_height {
+                    continue;
+                }
+                let (_, files) = heights
+                    .entry(height)
+                    .or_insert_with(|| (path.clone(), BTreeSet::new()));
+                for file in fs::read_dir(&path)? {
+                    files.insert(file?.file_name().to_string_lossy().into_owned());
+                }
+            } else if let Some((_, last_height)) = parse_archive_name(&path) {
+                if last_height < start_height {
+                    continue;
+                }
+                let archive = BackupArchive::open(&path)?;
+                for (height, name) in archive.files().filter(|(h, _)| *h >= start_height) {
+                    let (_, files) = heights
+                        .entry(height)
+                        .or_insert_with(|| (group_dir.join(height.to_string()), BTreeSet::new()));
+                    files.insert(name.to_string());
+                }
+            }
+        }
+    }
+
+    Ok(heights
+        .into_iter()
+        .map(|(height, (path, files))| {
+            let valid_files = files
+                .iter()
+                .filter(|name| !files.contains(&format!("{}{}", INVALID_PREFIX, name)))
+                .cloned()
+                .collect();
+            (height, (path, valid_files))
+        })
+        .collect())
+}
+
+/// Verifies all archives in `version_path`. Archives that fail the
+/// verification are reported, but not removed.
+pub fn verify_archives(version_path: &Path) -> io::Result<ArchiveVerification> {
+    let mut verification = ArchiveVerification::default();
+    let group_dirs = match fs::read_dir(version_path) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(verification),
+        Err(err) => return Err(err),
+    };
+    for group_dir in group_dirs {
+        for (_, _, path) in list_archives(&group_dir?.path())? {
+            match BackupArchive::open(&path).and_then(|archive| archive.verify()) {
+                Ok(files) => {
+                    verification.verified_archives += 1;
+                    verification.verified_files += files;
+                }
+                Err(err) => verification.corrupted_archives.push((path, err)),
+            }
+        }
+    }
+    Ok(verification)
+}
+
+/// Packs the separate files in `version_path` into archives. A range of heights
+/// is packed once the backup contains a height that is at least one full range
+/// above it, so that no more artifacts are expected for the range. Returns the
+/// number of packed heights.
+pub(crate) fn pack_backup(version_path: &Path) -> io::Result<usize> {
+    let mut height_dirs = BTreeMap::new();
+    let group_dirs = match fs::read_dir(version_path) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err),
+    };
+    for group_dir in group_dirs {
+        let group_dir = group_dir?.path();
+        if !group_dir.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&group_dir)? {
+            let path = entry?.path();
+            if let Some(height) = parse_height_dir(&path).filter(|_| path.is_dir()) {
+                height_dirs.insert(height, path);
+            }
+        }
+    }
+    let max_height = match height_dirs.keys().next_back() {
+        Some(height) => height.get(),
+        None => return Ok(0),
+    };
+
+    let mut ranges: BTreeMap<u64, Vec<(Height, PathBuf)>> = BTreeMap::new();
+    for (height, path) in height_dirs {
+        let range_start = (height.get() / BACKUP_ARCHIVE_HEIGHTS) * BACKUP_ARCHIVE_HEIGHTS;
+        if range_start + 2 * BACKUP_ARCHIVE_HEIGHTS <= max_height + 1 {
+            ranges.entry(range_start).or_default().push((height, path));
+        }
+    }
+    let mut packed_heights = 0;
+    for (range_start, height_dirs) in ranges {
+        packed_heights += pack_range(version_path, range_start, height_dirs)?;
+    }
+    Ok(packed_heights)
+}
+
+fn pack_range(
+    version_path: &Path,
+    range_start: u64,
+    height_dirs: Vec<(Height, PathBuf)>,
+) -> io::Result<usize> {
+    let group_key = (range_start / BACKUP_GROUP_SIZE) * BACKUP_GROUP_SIZE;
+    let group_dir = version_path.join(group_key.to_string());
+    let range_end = Height::from(range_start + BACKUP_ARCHIVE_HEIGHTS - 1);
+
+    // Files of this range that were already archived, e.g. by a previous run
+    // before a restart of the replica.
+    let mut archived = BTreeSet::new();
+    for (first_height, last_height, path) in list_archives(&group_dir)? {
+        if first_height <= range_end && last_height >= Height::from(range_start) {
+            let archive = BackupArchive::open(&path)?;
+            archived.extend(
+                archive
+                    .files
+                    .iter()
+                    .map(|((height, name), sha256)| (*height, name.clone(), *sha256)),
+            );
+        }
+    }
+
+    let mut files = Vec::new();
+    let mut packed_paths = Vec::new();
+    for (height, height_dir) in &height_dirs {
+        for entry in fs::read_dir(height_dir)? {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            // Temporary files and invalidated artifacts stay where they are.
+            if !name.ends_with(".bin") || name.starts_with(INVALID_PREFIX) {
+                continue;
+            }
+            let name = name.into_owned();
+            let content = fs::read(&path)?;
+            if !archived.contains(&(*height, name.clone(), Sha256::hash(&content))) {
+                files.push((*height, name, content));
+            }
+            packed_paths.push(path);
+        }
+    }
+    if !files.is_empty() {
+        write_archive(&group_dir, files)?;
+    }
+    for path in packed_paths {
+        fs::remove_file(path)?;
+    }
+    for (_, height_dir) in &height_dirs {
+        // Fails if the directory still contains other files, which is fine.
+        let _ = fs::remove_dir(height_dir);
+    }
+    Ok(height_dirs.len())
+}
+
+// Finds the archive containing the backup file at
+// <version_path>/<group>/<height>/<name>, unless the file was invalidated.
+fn find_archived_file(path: &Path) -> io::Result<Option<(BackupArchive, Height, String)>> {
+    let (Some(name), Some(height_dir)) = (
+        path.file_name().and_then(|name| name.to_str()),
+        path.parent(),
+    ) else {
+        return Ok(None);
+    };
+    let (Some(height), Some(group_dir)) = (parse_height_dir(height_dir), height_dir.parent())
+    else {
+        return Ok(None);
+    };
+    if height_dir
+        .join(format!("{}{}", INVALID_PREFIX, name))
+        .exists()
+    {
+        return Ok(None);
+    }
+    for (first_height, last_height, archive_path) in list_archives(group_dir)? {
+        if first_height <= height && height <= last_height {
+            let archive = BackupArchive::open(&archive_path)?;
+            if archive.contains(height, name) {
+                return Ok(Some((archive, height, name.to_string())));
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn parse_height_dir(path: &Path) -> Option<Height> {
+    path.file_name()?
+        .to_str()?
+        .parse::<u64>()
+        .ok()
+        .map(Height::from)
+}
+
+fn parse_archive_name(path: &Path) -> Option<(Height, Height)> {
+    if path.extension()? != ARCHIVE_EXTENSION {
+        return None;
+    }
+    let mut parts = path.file_stem()?.to_str()?.split('_');
+    let first_height = parts.next()?.parse::<u64>().ok()?;
+    let last_height = parts.next()?.parse::<u64>().ok()?;
+    Some((Height::from(first_height), Height::from(last_height)))
+}
+
+fn corrupted(path: &Path, reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Corrupted backup archive {}: {}", path.display(), reason),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::FileExt;
+
+    fn write_file(version_path: &Path, height: u64, name: &str, content: &[u8]) -> PathBuf {
+        let group_key = (height / BACKUP_GROUP_SIZE) * BACKUP_GROUP_SIZE;
+        let height_dir = version_path
+            .join(group_key.to_string())
+            .join(height.to_string());
+        fs::create_dir_all(&height_dir).unwrap();
+        let path = height_dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn file_path(version_path: &Path, height: u64, name: &str) -> PathBuf {
+        version_path.join("0").join(height.to_string()).join(name)
+    }
+
+    #[test]
+    fn test_archive_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_archive(
+            dir.path(),
+            vec![
+                (Height::from(3), "random_beacon.bin".into(), vec![1; 1000]),
+                (Height::from(4), "random_beacon.bin".into(), vec![2; 1000]),
+                (Height::from(4), "random_tape.bin".into(), vec![1; 1000]),
+            ],
+        )
+        .unwrap();
+
+        let archive = BackupArchive::open(&path).unwrap();
+        assert_eq!(archive.height_range(), (Height::from(3), Height::from(4)));
+        assert_eq!(archive.verify().unwrap(), 3);
+        // Identical contents are stored once.
+        assert_eq!(archive.blobs.len(), 2);
+        assert_eq!(
+            archive
+                .read(Height::from(4), "random_tape.bin")
+                .unwrap()
+                .unwrap(),
+            vec![1; 1000]
+        );
+        assert!(archive
+            .read(Height::from(3), "random_tape.bin")
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            list_archives(dir.path()).unwrap(),
+            vec![(Height::from(3), Height::from(4), path)]
+        );
+    }
+
+    #[test]
+    fn test_corrupted_archive_is_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_archive(
+            &dir.path().join("0"),
+            vec![(Height::from(1), "random_beacon.bin".into(), vec![7; 100])],
+        )
+        .unwrap();
+
+        // Flip the last byte, which belongs to the compressed blob.
+        let file = fs::OpenOptions::new().write(true).open(&path).unwrap();
+        let len = file.metadata().unwrap().len();
+        file.write_all_at(&[0xff], len - 1).unwrap();
+
+        let archive = BackupArchive::open(&path).unwrap();
+        assert!(archive.verify().is_err());
+        let verification = verify_archives(dir.path()).unwrap();
+        assert_eq!(verification.verified_archives, 0);
+        assert_eq!(verification.corrupted_archives.len(), 1);
+
+        // A corrupted index is detected on opening.
+        file.write_all_at(&[0xff], HEADER_LEN).unwrap();
+        assert!(BackupArchive::open(&path).is_err());
+    }
+
+    #[test]
+    fn test_pack_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let version_path = dir.path();
+        for height in 0..2 * BACKUP_ARCHIVE_HEIGHTS {
+            write_file(
+                version_path,
+                height,
+                "random_beacon.bin",
+                &height.to_le_bytes(),
+            );
+        }
+        write_file(version_path, 5, "catch_up_package.bin.tmp", &[1]);
+
+        // The second range is not complete yet, hence only the first range is packed.
+        assert_eq!(
+            pack_backup(version_path).unwrap(),
+            BACKUP_ARCHIVE_HEIGHTS as usize
+        );
+        let group_dir = version_path.join("0");
+        assert_eq!(list_archives(&group_dir).unwrap().len(), 1);
+        assert!(!group_dir.join("7").exists());
+        assert!(group_dir
+            .join("5")
+            .join("catch_up_package.bin.tmp")
+            .exists());
+        assert!(group_dir.join("150").join("random_beacon.bin").exists());
+
+        // Both formats can be read.
+        for height in [7, 150] {
+            let path = file_path(version_path, height, "random_beacon.bin");
+            assert!(backup_file_exists(&path));
+            assert_eq!(read_backup_file(&path).unwrap(), height.to_le_bytes());
+        }
+        let files = list_backup_files(version_path, Height::from(99)).unwrap();
+        assert_eq!(files.len(), BACKUP_ARCHIVE_HEIGHTS as usize + 1);
+        assert_eq!(
+            files[&Height::from(99)],
+            (group_dir.join("99"), vec!["random_beacon.bin".to_string()])
+        );
+
+        // Files that were already archived are not archived twice, e.g. if they are
+        // backed up again after a restart.
+        write_file(version_path, 7, "random_beacon.bin", &7u64.to_le_bytes());
+        write_file(version_path, 8, "random_tape.bin", &[8]);
+        write_file(
+            version_path,
+            2 * BACKUP_ARCHIVE_HEIGHTS,
+            "random_beacon.bin",
+            &[],
+        );
+        assert_eq!(pack_backup(version_path).unwrap(), 2);
+        let archives = list_archives(&group_dir).unwrap();
+        assert_eq!(archives.len(), 2);
+        let archive = BackupArchive::open(&archives[1].2).unwrap();
+        assert_eq!(
+            archive.files().collect::<Vec<_>>(),
+            vec![(Height::from(8), "random_tape.bin")]
+        );
+    }
+
+    #[test]
+    fn test_invalidate_archived_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let version_path = dir.path();
+        for height in 0..2 * BACKUP_ARCHIVE_HEIGHTS {
+            write_file(version_path, height, "random_beacon.bin", &[1]);
+        }
+        pack_backup(version_path).unwrap();
+
+        let path = file_path(version_path, 3, "random_beacon.bin");
+        let invalid_path = invalidate_backup_file(&path).unwrap();
+        assert_eq!(
+            invalid_path,
+            path.with_file_name("invalid_random_beacon.bin")
+        );
+        assert_eq!(fs::read(invalid_path).unwrap(), vec![1]);
+        assert!(!backup_file_exists(&path));
+        assert!(read_backup_file(&path).is_err());
+        assert_eq!(
+            list_backup_files(version_path, Height::from(3)).unwrap()[&Height::from(3)].1,
+            vec!["invalid_random_beacon.bin".to_string()]
+        );
+    }
+}
@@ -2,8 +2,10 @@ use clap::{arg, Arg, Command};
 use ic_artifact_pool::{
     certification_pool::CertificationPoolImpl,
     consensus_pool::{PoolSectionOps, UncachedConsensusPoolImpl},
+    migration::{compare_pools, migrate_pools},
+    SUPPORTED_POOL_BACKENDS,
 };
-use ic_config::artifact_pool::ArtifactPoolConfig;
+use ic_config::artifact_pool::{ArtifactPoolConfig, ArtifactPoolTomlConfig};
 use ic_interfaces::consensus_pool::*;
 use ic_logger::{LoggerImpl, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
@@ -51,7 +53,40 @@ fn main() {
                         .takes_value(true),
                 ),
         )
-        .arg(arg!(<PATH>       "PATH to the consensus pool directory"));
+        .subcommand(
+            Command::new("migrate")
+                .about("Copy all artifacts to an empty pool, possibly using a different backend")
+                .long_about(
+                    "Copy all artifacts to an empty pool, possibly using a different backend.\n\n\
+                     Only the backends compiled into this binary can be used. The Linux builds \
+                     of the IC don't include the RocksDB backend, hence they can only migrate \
+                     from LMDB to LMDB and can't migrate between LMDB and RocksDB.",
+                )
+                .arg(
+                    Arg::new("target")
+                        .long("target")
+                        .value_name("PATH")
+                        .help("PATH to the target consensus pool directory")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(backend_arg("target-backend", "Backend of the target pool")),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Verify that another pool contains exactly the same artifacts")
+                .arg(
+                    Arg::new("other")
+                        .long("other")
+                        .value_name("PATH")
+                        .help("PATH to the other consensus pool directory")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(backend_arg("other-backend", "Backend of the other pool")),
+        )
+        .arg(arg!(<PATH>       "PATH to the consensus pool directory"))
+        .arg(backend_arg("backend", "Backend of the pool at PATH"));
     let mut help = Vec::new();
     app.write_help(&mut help)
         .expect("Unable to output help message");
@@ -59,12 +94,17 @@ fn main() {
     let path = matches
         .value_of("PATH")
         .expect("Missing PATH to consensus pool directory");
+    let backend = matches.value_of("backend").expect("Missing backend");
     if let Some(matches) = matches.subcommand_matches("export") {
-        export(path, matches)
+        export(path, backend, matches)
     } else if let Some(_matches) = matches.subcommand_matches("import") {
-        import(path)
+        import(path, backend)
     } else if let Some(matches) = matches.subcommand_matches("export-cup-proto") {
-        export_cup_proto(path, matches)
+        export_cup_proto(path, backend, matches)
+    } else if let Some(matches) = matches.subcommand_matches("migrate") {
+        migrate(path, backend, matches)
+    } else if let Some(matches) = matches.subcommand_matches("verify") {
+        verify(path, backend, matches)
     } else {
         eprintln!(
             "{}",
@@ -73,6 +113,18 @@ fn main() {
     }
 }
 
+/// Only the backends compiled into this binary are accepted, so that an
+/// unsupported backend is rejected before any pool is opened.
+fn backend_arg<'a>(name: &'a str, help: &'a str) -> Arg<'a> {
+    Arg::new(name)
+        .long(name)
+        .value_name("BACKEND")
+        .help(help)
+        .possible_values(SUPPORTED_POOL_BACKENDS.iter().copied())
+        .default_value("lmdb")
+        .takes_value(true)
+}
+
 const ALL_ARTIFACT_NAMES: [&str; 13] = [
     "RandomBeacon",
     "Finalization",
@@ -105,23 +157,26 @@ fn parse_artifact_names(names: &[&str]) -> Vec<&'static str> {
         .collect::<Vec<_>>()
 }
 
-fn open_consensus_pool(path: &str, read_only: bool) -> UncachedConsensusPoolImpl {
+fn pool_config(path: &str, backend: &str, read_only: bool) -> ArtifactPoolConfig {
+    let mut toml_config = ArtifactPoolTomlConfig::new(PathBuf::from(path), None);
+    toml_config.consensus_pool_backend = Some(backend.to_string());
+    let mut config = ArtifactPoolConfig::from(toml_config);
+    config.persistent_pool_read_only = read_only;
+    config
+}
+
+fn open_consensus_pool(path: &str, backend: &str, read_only: bool) -> UncachedConsensusPoolImpl {
     let logger = LoggerImpl::new(&Default::default(), "dump_consensus_pool".to_string());
     let log = ReplicaLogger::new(logger.root.clone().into());
 
-    let path = PathBuf::from(path);
-    let mut config = ArtifactPoolConfig::new(path);
-    config.persistent_pool_read_only = read_only;
-    UncachedConsensusPoolImpl::new(config, log)
+    UncachedConsensusPoolImpl::new(pool_config(path, backend, read_only), log)
 }
 
-fn open_certification_pool(path: &str, read_only: bool) -> CertificationPoolImpl {
+fn open_certification_pool(path: &str, backend: &str, read_only: bool) -> CertificationPoolImpl {
     let logger = LoggerImpl::new(&Default::default(), "dump_consensus_pool".to_string());
     let log = ReplicaLogger::new(logger.root.clone().into());
 
-    let path = PathBuf::from(path);
-    let mut config = ArtifactPoolConfig::new(path);
-    config.persistent_pool_read_only = read_only;
+    let config = pool_config(path, backend, read_only);
     let node_id = NodeId::from(PrincipalId::new_node_test_id(0));
     CertificationPoolImpl::new(node_id, config, log, MetricsRegistry::new())
 }
@@ -140,14 +195,14 @@ fn to_string<T: Serialize>(msg: &T) -> String {
     String::from_utf8(out).expect("UTF8 conversion error")
 }
 
-fn export(path: &str, matches: &clap::ArgMatches) {
+fn export(path: &str, backend: &str, matches: &clap::ArgMatches) {
     let artifacts = match matches.values_of("artifact") {
         Some(names) => parse_artifact_names(&names.collect::<Vec<&str>>()),
         None => ALL_ARTIFACT_NAMES.to_vec(),
     };
 
-    let consensus_pool = open_consensus_pool(path, true);
-    let certification_pool = open_certification_pool(path, true);
+    let consensus_pool = open_consensus_pool(path, backend, true);
+    let certification_pool = open_certification_pool(path, backend, true);
 
     for artifact in artifacts {
         match artifact {
@@ -236,9 +291,9 @@ fn export(path: &str, matches: &clap::ArgMatches) {
     }
 }
 
-fn import(path: &str) {
-    let mut consensus_pool = open_consensus_pool(path, false);
-    let certification_pool = open_certification_pool(path, false);
+fn import(path: &str, backend: &str) {
+    let mut consensus_pool = open_consensus_pool(path, backend, false);
+    let certification_pool = open_certification_pool(path, backend, false);
     let stdin = std::io::stdin();
     for line in stdin.lock().lines() {
         let s = line.expect("Cannot read input");
@@ -257,13 +312,13 @@ fn import(path: &str) {
     }
 }
 
-fn export_cup_proto(path: &str, matches: &clap::ArgMatches) {
+fn export_cup_proto(path: &str, backend: &str, matches: &clap::ArgMatches) {
     let filename = matches
         .value_of("output")
         .expect("Expect an output filename");
     let mut file = std::fs::File::create(filename)
         .unwrap_or_else(|err| panic!("Cannot open file {} for write: {:?}", filename, err));
-    let consensus_pool = open_consensus_pool(path, true);
+    let consensus_pool = open_consensus_pool(path, backend, true);
     let mut buf = Vec::<u8>::new();
     let cup_proto = consensus_pool.validated().highest_catch_up_package_proto();
     let cup = CatchUpPackage::try_from(&cup_proto).unwrap_or_else(|err| panic!("{}", err));
@@ -274,3 +329,53 @@ fn export_cup_proto(path: &str, matches: &clap::ArgMatches) {
     file.write_all(&buf)
         .unwrap_or_else(|err| panic!("Cannot write to file {}: {:?}", filename, err));
 }
+
+fn migrate(path: &str, backend: &str, matches: &clap::ArgMatches) {
+    let target = matches.value_of("target").expect("Missing target path");
+    let target_backend = matches
+        .value_of("target-backend")
+        .expect("Missing target backend");
+    let consensus_pool = open_consensus_pool(path, backend, true);
+    let certification_pool = open_certification_pool(path, backend, true);
+    let mut target_consensus_pool = open_consensus_pool(target, target_backend, false);
+    let target_certification_pool = open_certification_pool(target, target_backend, false);
+
+    let stats = migrate_pools(
+        &consensus_pool,
+        &certification_pool,
+        &mut target_consensus_pool,
+        &target_certification_pool,
+    )
+    .unwrap_or_else(|err| panic!("Failed to migrate the pool to {}: {}", target, err));
+    println!(
+        "Migrated {} consensus artifacts and {} certification artifacts to {}",
+        stats.consensus_artifacts, stats.certification_artifacts, target
+    );
+}
+
+fn verify(path: &str, backend: &str, matches: &clap::ArgMatches) {
+    let other = matches.value_of("other").expect("Missing other path");
+    let other_backend = matches
+        .value_of("other-backend")
+        .expect("Missing other backend");
+    let comparison = compare_pools(
+        &open_consensus_pool(path, backend, true),
+        &open_certification_pool(path, backend, true),
+        &open_consensus_pool(other, other_backend, true),
+        &open_certification_pool(other, other_backend, true),
+    );
+    for mismatch in &comparison.mismatches {
+        eprintln!("{}", mismatch);
+    }
+    if !comparison.is_equivalent() {
+        eprintln!(
+            "The pools differ: found {} mismatches",
+            comparison.mismatches.len()
+        );
+        std::process::exit(1);
+    }
+    println!(
+        "The pools are equivalent: compared {} artifacts",
+        comparison.compared_artifacts
+    );
+}
@@ -386,6 +386,7 @@ impl ConsensusPoolImpl {
                     .join(ic_types::ReplicaVersion::default().to_string()),
                 Duration::from_secs(config.retention_time_secs),
                 Duration::from_secs(config.purging_interval_secs),
+                config.format,
                 registry,
                 log,
                 Arc::new(RealClock),
@@ -901,6 +902,7 @@ mod tests {
     use crate::backup::{BackupAge, PurgingError};
 
     use super::*;
+    use ic_config::artifact_pool::BackupFormat;
     use ic_interfaces::p2p::consensus::UnvalidatedArtifact;
     use ic_interfaces::time_source::TimeSource;
     use ic_logger::replica_logger::no_op_logger;
@@ -1525,6 +1527,7 @@ mod tests {
                 Duration::from_millis(100),
                 // We purge every 5 milliseconds.
                 purging_interval,
+                BackupFormat::Files,
                 MetricsRegistry::new(),
                 no_op_logger(),
                 time_source.clone(),
@@ -1897,6 +1900,7 @@ mod tests {
                 // Artifact retention time
                 Duration::from_millis(2700),
                 purging_interval,
+                BackupFormat::Files,
                 MetricsRegistry::new(),
                 no_op_logger(),
                 Box::new(FakeAge { map: map.clone() }),
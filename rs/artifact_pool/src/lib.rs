@@ -15,12 +15,20 @@ mod test_utils;
 pub mod backup;
 mod lmdb_iterator;
 mod lmdb_pool;
+pub mod migration;
 
 #[cfg(feature = "rocksdb_backend")]
 mod rocksdb_iterator;
 #[cfg(feature = "rocksdb_backend")]
 mod rocksdb_pool;
 
+/// The persistent pool backends supported by this build. RocksDB is only
+/// available with the `rocksdb_backend` feature.
+#[cfg(feature = "rocksdb_backend")]
+pub const SUPPORTED_POOL_BACKENDS: &[&str] = &["lmdb", "rocksdb"];
+#[cfg(not(feature = "rocksdb_backend"))]
+pub const SUPPORTED_POOL_BACKENDS: &[&str] = &["lmdb"];
+
 use ic_interfaces::{consensus_pool::ValidatedArtifact, p2p::consensus::UnvalidatedArtifact};
 use ic_types::{ReplicaVersion, Time};
 use std::convert::TryFrom;
@@ -0,0 +1,490 @@
+//! Migration of the persistent pools between backends.
+//!
+//! [`migrate_pools`] copies the validated section of the consensus pool and the
+//! persistent certification pool from one backend to another, e.g. from LMDB to
+//! RocksDB. Artifacts are inserted through the regular pool interfaces, so the
+//! target backend builds its own height indexes. Timestamps of consensus
+//! artifacts are preserved and the highest catch-up package is inserted with
+//! its original protobuf, so that the bytes served to peers stay unchanged.
+//!
+//! [`compare_pools`] checks that two pools contain the same artifacts at the
+//! same heights, which proves that a migration was complete.
+use crate::{
+    certification_pool::{CertificationPoolImpl, MutablePoolSection as CertificationPoolSection},
+    consensus_pool::{InitializablePoolSection, PoolSectionOps, UncachedConsensusPoolImpl},
+};
+use ic_interfaces::consensus_pool::{HeightIndexedPool, PoolSection, ValidatedConsensusArtifact};
+use ic_types::{
+    consensus::{
+        certification::{Certification, CertificationMessage, CertificationShare},
+        CatchUpPackage, ConsensusMessageHashable,
+    },
+    Height,
+};
+
+/// Number of consensus artifacts inserted into the target pool per mutation.
+const MIGRATION_BATCH_SIZE: usize = 1000;
+
+/// Number of artifacts copied by [`migrate_pools`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MigrationStats {
+    pub consensus_artifacts: usize,
+    pub certification_artifacts: usize,
+}
+
+/// Result of [`compare_pools`].
+#[derive(Debug, Default)]
+pub struct PoolComparison {
+    /// Number of artifacts of the first pool that were compared.
+    pub compared_artifacts: usize,
+    /// Human readable description of every difference found.
+    pub mismatches: Vec<String>,
+}
+
+impl PoolComparison {
+    pub fn is_equivalent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Copies all validated consensus artifacts and all certification artifacts
+/// from the source pools to the target pools. The target pools must be empty.
+pub fn migrate_pools(
+    source_consensus: &UncachedConsensusPoolImpl,
+    source_certification: &CertificationPoolImpl,
+    target_consensus: &mut UncachedConsensusPoolImpl,
+    target_certification: &CertificationPoolImpl,
+) -> Result<MigrationStats, String> {
+    if !is_empty_consensus_pool(target_consensus.validated.pool_section())
+        || !is_empty_certification_pool(target_certification.persistent_pool.as_ref())
+    {
+        return Err("The target pool is not empty".to_string());
+    }
+
+    Ok(MigrationStats {
+        consensus_artifacts: migrate_consensus_pool(
+            source_consensus.validated.pool_section(),
+            target_consensus.validated.as_mut(),
+        )?,
+        certification_artifacts: migrate_certification_pool(
+            source_certification.persistent_pool.as_ref(),
+            target_certification.persistent_pool.as_ref(),
+        ),
+    })
+}
+
+/// Compares the validated consensus artifacts, including their timestamps, and
+/// the certification artifacts of two pools height by height.
+pub fn compare_pools(
+    consensus_a: &UncachedConsensusPoolImpl,
+    certification_a: &CertificationPoolImpl,
+    consensus_b: &UncachedConsensusPoolImpl,
+    certification_b: &CertificationPoolImpl,
+) -> PoolComparison {
+    let mut comparison = PoolComparison::default();
+    let a = consensus_a.validated.pool_section();
+    let b = consensus_b.validated.pool_section();
+
+    macro_rules! compare_consensus_artifacts {
+        ($($artifact:ident),*) => {
+            $(
+                compare_artifacts(
+                    stringify!($artifact),
+                    a.$artifact(),
+                    b.$artifact(),
+                    |msg| a.get_timestamp(&msg.get_id()) == b.get_timestamp(&msg.get_id()),
+                    &mut comparison,
+                );
+            )*
+        };
+    }
+    compare_consensus_artifacts!(
+        random_beacon,
+        block_proposal,
+        notarization,
+        finalization,
+        random_beacon_share,
+        notarization_share,
+        finalization_share,
+        random_tape,
+        random_tape_share,
+        catch_up_package_share
+    );
+    // Catch-up packages inserted from their protobuf get the block time as
+    // timestamp, hence their timestamps are not compared. Instead the bytes of
+    // the highest CUP must match.
+    compare_artifacts(
+        "catch_up_package",
+        a.catch_up_package(),
+        b.catch_up_package(),
+        |_: &CatchUpPackage| true,
+        &mut comparison,
+    );
+    if a.catch_up_package().max_height().is_some()
+        && b.catch_up_package().max_height().is_some()
+        && a.highest_catch_up_package_proto() != b.highest_catch_up_package_proto()
+    {
+        comparison
+            .mismatches
+            .push("catch_up_package: the protobufs of the highest CUPs differ".to_string());
+    }
+
+    let a = certification_a.persistent_pool.as_ref();
+    let b = certification_b.persistent_pool.as_ref();
+    compare_artifacts(
+        "certification",
+        a.certifications(),
+        b.certifications(),
+        |_: &Certification| true,
+        &mut comparison,
+    );
+    compare_artifacts(
+        "certification_share",
+        a.certification_shares(),
+        b.certification_shares(),
+        |_: &CertificationShare| true,
+        &mut comparison,
+    );
+
+    comparison
+}
+
+fn migrate_consensus_pool(
+    source: &dyn PoolSection<ValidatedConsensusArtifact>,
+    target: &mut dyn InitializablePoolSection,
+) -> Result<usize, String> {
+    let mut copied = 0;
+    copied += copy_consensus_artifacts(source, target, source.random_beacon().get_all())?;
+    copied += copy_consensus_artifacts(source, target, source.block_proposal().get_all())?;
+    copied += copy_consensus_artifacts(source, target, source.notarization().get_all())?;
+    copied += copy_consensus_artifacts(source, target, source.finalization().get_all())?;
+    copied += copy_consensus_artifacts(source, target, source.random_beacon_share().get_all())?;
+    copied += copy_consensus_artifacts(source, target, source.notarization_share().get_all())?;
+    copied += copy_consensus_artifacts(source, target, source.finalization_share().get_all())?;
+    copied += copy_consensus_artifacts(source, target, source.random_tape().get_all())?;
+    copied += copy_consensus_artifacts(source, target, source.random_tape_share().get_all())?;
+    copied += copy_consensus_artifacts(source, target, source.catch_up_package_share().get_all())?;
+
+    // The highest CUP keeps its original bytes, all other CUPs are re-encoded.
+    let highest_cup = match source.catch_up_package().max_height() {
+        Some(_) => {
+            let proto = source.highest_catch_up_package_proto();
+            let cup = CatchUpPackage::try_from(&proto)
+                .map_err(|err| format!("Failed to deserialize the highest CUP: {:?}", err))?;
+            Some((cup.get_id(), proto))
+        }
+        None => None,
+    };
+    let highest_cup_id = highest_cup.as_ref().map(|(id, _)| id.clone());
+    copied += copy_consensus_artifacts(
+        source,
+        target,
+        Box::new(
+            source
+                .catch_up_package()
+                .get_all()
+                .filter(move |cup| Some(cup.get_id()) != highest_cup_id),
+        ),
+    )?;
+    if let Some((_, cup_proto)) = highest_cup {
+        target.insert_cup_with_proto(cup_proto);
+        copied += 1;
+    }
+
+    Ok(copied)
+}
+
+fn copy_consensus_artifacts<T: ConsensusMessageHashable>(
+    source: &dyn PoolSection<ValidatedConsensusArtifact>,
+    target: &mut dyn InitializablePoolSection,
+    artifacts: Box<dyn Iterator<Item = T>>,
+) -> Result<usize, String> {
+    let mut copied = 0;
+    let mut ops = PoolSectionOps::new();
+    for artifact in artifacts {
+        let id = artifact.get_id();
+        let timestamp = source
+            .get_timestamp(&id)
+            .ok_or_else(|| format!("Missing timestamp of artifact {:?}", id))?;
+        ops.insert(ValidatedConsensusArtifact {
+            msg: artifact.into_message(),
+            timestamp,
+        });
+        copied += 1;
+        if copied % MIGRATION_BATCH_SIZE == 0 {
+            target.mutate(std::mem::replace(&mut ops, PoolSectionOps::new()));
+        }
+    }
+    target.mutate(ops);
+    Ok(copied)
+}
+
+fn migrate_certification_pool(
+    source: &dyn CertificationPoolSection,
+    target: &dyn CertificationPoolSection,
+) -> usize {
+    let mut copied = 0;
+    for certification in source.certifications().get_all() {
+        target.insert(CertificationMessage::Certification(certification));
+        copied += 1;
+    }
+    for share in source.certification_shares().get_all() {
+        target.insert(CertificationMessage::CertificationShare(share));
+        copied += 1;
+    }
+    copied
+}
+
+/// Compares the artifacts of two height indexed pools height by height.
+/// `same_metadata` is called for every artifact present in both pools.
+fn compare_artifacts<T: PartialEq>(
+    name: &str,
+    a: &dyn HeightIndexedPool<T>,
+    b: &dyn HeightIndexedPool<T>,
+    same_metadata: impl Fn(&T) -> bool,
+    comparison: &mut PoolComparison,
+) {
+    let (range_a, range_b) = (a.height_range(), b.height_range());
+    let (min, max) = match (&range_a, &range_b) {
+        (None, None) => return,
+        (Some(range_a), Some(range_b))
+            if range_a.min == range_b.min && range_a.max == range_b.max =>
+        {
+            (range_a.min, range_a.max)
+        }
+        _ => {
+            comparison.mismatches.push(format!(
+                "{}: height ranges differ: {:?} != {:?}",
+                name, range_a, range_b
+            ));
+            let heights = range_a.iter().chain(range_b.iter());
+            (
+                heights.clone().map(|range| range.min).min().unwrap(),
+                heights.map(|range| range.max).max().unwrap(),
+            )
+        }
+    };
+
+    let mut height = min;
+    while height <= max {
+        let artifacts_a: Vec<T> = a.get_by_height(height).collect();
+        let artifacts_b: Vec<T> = b.get_by_height(height).collect();
+        comparison.compared_artifacts += artifacts_a.len();
+        compare_at_height(
+            name,
+            height,
+            &artifacts_a,
+            &artifacts_b,
+            &same_metadata,
+            comparison,
+        );
+        height = height.increment();
+    }
+}
+
+fn compare_at_height<T: PartialEq>(
+    name: &str,
+    height: Height,
+    artifacts_a: &[T],
+    artifacts_b: &[T],
+    same_metadata: &impl Fn(&T) -> bool,
+    comparison: &mut PoolComparison,
+) {
+    let missing_in_b = artifacts_a
+        .iter()
+        .filter(|artifact| !artifacts_b.contains(artifact))
+        .count();
+    let missing_in_a = artifacts_b
+        .iter()
+        .filter(|artifact| !artifacts_a.contains(artifact))
+        .count();
+    if missing_in_a > 0 || missing_in_b > 0 {
+        comparison.mismatches.push(format!(
+            "{} @{}: {} artifacts only in the first pool, {} artifacts only in the second pool",
+            name, height, missing_in_b, missing_in_a
+        ));
+    }
+    let different_metadata = artifacts_a
+        .iter()
+        .filter(|artifact| artifacts_b.contains(artifact) && !same_metadata(artifact))
+        .count();
+    if different_metadata > 0 {
+        comparison.mismatches.push(format!(
+            "{} @{}: {} artifacts have different timestamps",
+            name, height, different_metadata
+        ));
+    }
+}
+
+fn is_empty_consensus_pool(section: &dyn PoolSection<ValidatedConsensusArtifact>) -> bool {
+    section.random_beacon().max_height().is_none()
+        && section.block_proposal().max_height().is_none()
+        && section.notarization().max_height().is_none()
+        && section.finalization().max_height().is_none()
+        && section.random_beacon_share().max_height().is_none()
+        && section.notarization_share().max_height().is_none()
+        && section.finalization_share().max_height().is_none()
+        && section.random_tape().max_height().is_none()
+        && section.random_tape_share().max_height().is_none()
+        && section.catch_up_package().max_height().is_none()
+        && section.catch_up_package_share().max_height().is_none()
+}
+
+fn is_empty_certification_pool(section: &dyn CertificationPoolSection) -> bool {
+    section.certifications().max_height().is_none()
+        && section.certification_shares().max_height().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{fake_block_proposal, fake_random_beacon, make_summary};
+    use ic_config::artifact_pool::ArtifactPoolConfig;
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_metrics::MetricsRegistry;
+    use ic_protobuf::types::v1 as pb;
+    use ic_test_utilities::{
+        artifact_pool_config::with_test_pool_config,
+        consensus::{fake::*, make_genesis},
+        mock_time,
+        types::ids::node_test_id,
+    };
+    use ic_types::{
+        consensus::certification::CertificationContent,
+        crypto::{CryptoHash, Signed},
+        signature::ThresholdSignature,
+        CryptoHashOfPartialState,
+    };
+    use std::time::Duration;
+
+    fn open_pools(
+        config: ArtifactPoolConfig,
+    ) -> (UncachedConsensusPoolImpl, CertificationPoolImpl) {
+        (
+            UncachedConsensusPoolImpl::new(config.clone(), no_op_logger()),
+            CertificationPoolImpl::new(
+                node_test_id(0),
+                config,
+                no_op_logger(),
+                MetricsRegistry::new(),
+            ),
+        )
+    }
+
+    fn fake_certification(height: u64) -> CertificationMessage {
+        CertificationMessage::Certification(Certification {
+            height: Height::from(height),
+            signed: Signed {
+                content: CertificationContent::new(CryptoHashOfPartialState::from(CryptoHash(
+                    vec![],
+                ))),
+                signature: ThresholdSignature::fake(),
+            },
+        })
+    }
+
+    /// Inserts a genesis CUP, 5 random beacons and block proposals with
+    /// distinct timestamps and 3 certifications.
+    fn populate(consensus: &mut UncachedConsensusPoolImpl, certification: &CertificationPoolImpl) {
+        let cup = make_genesis(make_summary(Height::from(0)));
+        consensus
+            .validated
+            .insert_cup_with_proto(pb::CatchUpPackage::from(&cup));
+        let mut ops = PoolSectionOps::new();
+        for i in 1..=5 {
+            ops.insert(ValidatedConsensusArtifact {
+                msg: fake_random_beacon(Height::from(i)).into_message(),
+                timestamp: mock_time() + Duration::from_secs(i),
+            });
+            ops.insert(ValidatedConsensusArtifact {
+                msg: fake_block_proposal(Height::from(i)).into_message(),
+                timestamp: mock_time() + Duration::from_secs(2 * i),
+            });
+        }
+        consensus.validated.mutate(ops);
+        for i in 1..=3 {
+            certification.persistent_pool.insert(fake_certification(i));
+        }
+    }
+
+    /// Migrates a populated pool opened with `source_config` into an empty
+    /// pool opened with `target_config` and checks that the pools are
+    /// equivalent afterwards.
+    fn migrate_and_compare(source_config: ArtifactPoolConfig, target_config: ArtifactPoolConfig) {
+        let (mut source_consensus, source_certification) = open_pools(source_config);
+        let (mut target_consensus, target_certification) = open_pools(target_config);
+        populate(&mut source_consensus, &source_certification);
+
+        let stats = migrate_pools(
+            &source_consensus,
+            &source_certification,
+            &mut target_consensus,
+            &target_certification,
+        )
+        .unwrap();
+        assert_eq!(
+            stats,
+            MigrationStats {
+                consensus_artifacts: 11,
+                certification_artifacts: 3,
+            }
+        );
+
+        let comparison = compare_pools(
+            &source_consensus,
+            &source_certification,
+            &target_consensus,
+            &target_certification,
+        );
+        assert!(comparison.is_equivalent(), "{:?}", comparison.mismatches);
+        assert_eq!(comparison.compared_artifacts, 14);
+
+        // Migrating into a pool that is not empty is refused.
+        assert!(migrate_pools(
+            &source_consensus,
+            &source_certification,
+            &mut target_consensus,
+            &target_certification,
+        )
+        .is_err());
+
+        // An artifact missing from one of the pools is detected.
+        let mut ops = PoolSectionOps::new();
+        ops.insert(ValidatedConsensusArtifact {
+            msg: fake_random_beacon(Height::from(6)).into_message(),
+            timestamp: mock_time(),
+        });
+        target_consensus.validated.mutate(ops);
+        let comparison = compare_pools(
+            &source_consensus,
+            &source_certification,
+            &target_consensus,
+            &target_certification,
+        );
+        assert!(!comparison.is_equivalent());
+        assert!(comparison.mismatches[0].starts_with("random_beacon"));
+    }
+
+    #[test]
+    fn test_migrate_lmdb_pool_to_lmdb() {
+        with_test_pool_config(|source_config| {
+            with_test_pool_config(|target_config| migrate_and_compare(source_config, target_config))
+        })
+    }
+
+    #[cfg(feature = "rocksdb_backend")]
+    #[test]
+    fn test_migrate_lmdb_pool_to_rocksdb() {
+        use ic_config::artifact_pool::PersistentPoolBackend;
+        use ic_test_utilities::artifact_pool_config::with_test_rocksdb_pool_config;
+
+        with_test_pool_config(|lmdb_config| {
+            with_test_rocksdb_pool_config(|rocksdb_config| {
+                let mut target_config = lmdb_config.clone();
+                target_config.persistent_pool_backend =
+                    PersistentPoolBackend::RocksDB(rocksdb_config);
+                migrate_and_compare(lmdb_config, target_config)
+            })
+        })
+    }
+}
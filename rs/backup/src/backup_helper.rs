@@ -1,8 +1,13 @@
 use crate::{
     notification_client::NotificationClient,
+    state_hash_verification::{
+        parse_manifest_root_hash, verify_state_hashes, CheckResult, StateHashReport,
+        STATE_HASH_REPORT_FILE_NAME,
+    },
     util::{block_on, sleep_secs},
 };
 use chrono::{DateTime, Utc};
+use ic_artifact_pool::backup::archive::{backup_file_exists, list_archives, verify_archives};
 use ic_recovery::{
     command_helper::exec_cmd, error::RecoveryError, file_sync_helper::download_binary,
 };
@@ -14,7 +19,7 @@ use slog::{debug, error, info, warn, Logger};
 use std::{
     collections::BTreeMap,
     ffi::OsStr,
-    fs::{create_dir_all, read_dir, remove_dir_all, DirEntry, File},
+    fs::{create_dir_all, read_dir, remove_dir_all, remove_file, DirEntry, File},
     io::Write,
     net::IpAddr,
     path::{Path, PathBuf},
@@ -46,6 +51,7 @@ pub struct BackupHelper {
     pub artifacts_guard: Mutex<bool>,
     pub daily_replays: usize,
     pub do_cold_storage: bool,
+    pub verify_state_hashes: bool,
     pub thread_id: u32,
     pub blacklisted_nodes: Arc<Vec<IpAddr>>,
     pub log: Logger,
@@ -124,6 +130,10 @@ impl BackupHelper {
         )
     }
 
+    fn reports_dir(&self) -> PathBuf {
+        create_if_not_exists(self.root_dir.join(format!("reports/{}", self.subnet_id)))
+    }
+
     fn trash_dir(&self) -> PathBuf {
         create_if_not_exists(self.root_dir.join("trash"))
     }
@@ -152,7 +162,7 @@ impl BackupHelper {
         // already synced from the node.
         // That way it is guaranteed that the node is running the new replica version and
         // has the latest version of the ic.json5 file.
-        while !cup_file.exists() {
+        while !backup_file_exists(&cup_file) {
             debug!(self.log, "CUP file {} not yet present", cup_file.display());
             sleep_secs(30);
         }
@@ -168,6 +178,9 @@ impl BackupHelper {
         self.download_binary("ic-replay", replica_version)?;
         self.download_binary("sandbox_launcher", replica_version)?;
         self.download_binary("canister_sandbox", replica_version)?;
+        if self.verify_state_hashes {
+            self.download_binary("state-tool", replica_version)?;
+        }
 
         if !self.ic_config_file_local(replica_version).exists() {
             // collect nodes from which we will fetch the config
@@ -458,6 +471,7 @@ impl BackupHelper {
         );
         self.download_binaries(replica_version, start_height)?;
         debug!(self.log, "[#{}] Binaries are downloaded.", self.thread_id);
+        self.verify_spool_archives(replica_version)?;
 
         let ic_admin = self.binary_file("ic-replay", replica_version);
         let mut cmd = Command::new(ic_admin);
@@ -478,11 +492,13 @@ impl BackupHelper {
                 error!(self.log, "[#{}] Error: {}", self.thread_id, e.to_string());
                 if let RecoveryError::CommandError(_, ref out_str) = e {
                     self.dump_log_file(start_height, out_str)?;
+                    self.maybe_verify_state_hashes(replica_version, start_height);
                 }
                 Err(e.to_string())
             }
             Ok(Some(stdout)) => {
                 self.dump_log_file(start_height, &stdout)?;
+                self.maybe_verify_state_hashes(replica_version, start_height);
 
                 if let Some(upgrade_version) = self.check_upgrade_request(stdout) {
                     debug!(
@@ -514,6 +530,162 @@ impl BackupHelper {
         }
     }
 
+    /// Verifies the integrity of the archives in the spool of the given replica version. Corrupted
+    /// archives are removed, so that they are synced again from the nodes.
+    fn verify_spool_archives(&self, replica_version: &ReplicaVersion) -> Result<(), String> {
+        let _guard = self
+            .artifacts_guard
+            .lock()
+            .expect("artifacts mutex lock failed");
+        let replica_version_dir = self.spool_dir().join(replica_version.to_string());
+        let verification = verify_archives(&replica_version_dir).map_err(|err| {
+            format!(
+                "Error verifying the archives in {:?}: {:?}",
+                replica_version_dir, err
+            )
+        })?;
+        debug!(
+            self.log,
+            "[#{}] Verified {} archives with {} artifacts.",
+            self.thread_id,
+            verification.verified_archives,
+            verification.verified_files
+        );
+        if verification.corrupted_archives.is_empty() {
+            return Ok(());
+        }
+        for (path, err) in &verification.corrupted_archives {
+            warn!(
+                self.log,
+                "[#{}] Removing corrupted archive {:?}: {:?}", self.thread_id, path, err
+            );
+            remove_file(path).map_err(|err| format!("Error removing {:?}: {:?}", path, err))?;
+        }
+        self.notification_client.report_failure_slack(format!(
+            "Removed {} corrupted backup archives of replica version {}",
+            verification.corrupted_archives.len(),
+            replica_version
+        ));
+        Ok(())
+    }
+
+    /// If enabled, compares the hashes of the checkpoints created by the replay above
+    /// `start_height` with the state hashes in the CUPs of the spool, records the results in the
+    /// subnet's report and pushes the metrics.
+    fn maybe_verify_state_hashes(&self, replica_version: &ReplicaVersion, start_height: u64) {
+        if !self.verify_state_hashes {
+            return;
+        }
+        if let Err(err) = self.verify_replayed_state_hashes(replica_version, start_height) {
+            error!(
+                self.log,
+                "[#{}] Error verifying the state hashes: {}", self.thread_id, err
+            );
+            self.notification_client
+                .report_failure_slack(format!("Couldn't verify the state hashes: {}", err));
+        }
+    }
+
+    fn verify_replayed_state_hashes(
+        &self,
+        replica_version: &ReplicaVersion,
+        start_height: u64,
+    ) -> Result<(), String> {
+        let local_state_hashes = self.compute_checkpoint_hashes(replica_version, start_height)?;
+        self.notification_client
+            .push_metrics_verified_state_hashes(local_state_hashes.len());
+        if local_state_hashes.is_empty() {
+            warn!(
+                self.log,
+                "[#{}] No new checkpoints above height {} to verify the state hashes of.",
+                self.thread_id,
+                start_height
+            );
+            self.notification_client.report_warning_slack(format!(
+                "No state hashes were verified after the replay from height {}",
+                start_height
+            ));
+            return Ok(());
+        }
+
+        // Prevent the CUPs from being moved to the cold storage while reading them.
+        let _guard = self
+            .artifacts_guard
+            .lock()
+            .expect("artifacts mutex lock failed");
+        // The CUP at an upgrade height may be in the spool of another replica version.
+        let current_version_dir = self.spool_dir().join(replica_version.to_string());
+        let mut replica_version_dirs = vec![current_version_dir.clone()];
+        replica_version_dirs.extend(
+            collect_spool_dirs(&self.log, self.spool_dir())
+                .into_iter()
+                .map(|dir| dir.path())
+                .filter(|dir| *dir != current_version_dir),
+        );
+
+        let report_file = self.reports_dir().join(STATE_HASH_REPORT_FILE_NAME);
+        let mut report = StateHashReport::load(&report_file)?;
+        let failed = verify_state_hashes(
+            &mut report,
+            &replica_version.to_string(),
+            &local_state_hashes,
+            &replica_version_dirs,
+        );
+        report.save(&report_file)?;
+        info!(
+            self.log,
+            "[#{}] Verified the state hashes at {} CUP heights, last verified height: {}",
+            self.thread_id,
+            local_state_hashes.len(),
+            report.last_verified_height
+        );
+
+        self.notification_client
+            .push_metrics_last_verified_height(report.last_verified_height);
+        self.notification_client
+            .push_metrics_state_hash_mismatches(report.mismatches().len());
+
+        for (height, result) in failed {
+            match result {
+                CheckResult::Mismatch => self.notification_client.report_failure_slack(format!(
+                    "The replayed state hash differs from the CUP at height *{}*",
+                    height
+                )),
+                _ => self.notification_client.report_warning_slack(format!(
+                    "Couldn't read the CUP at height {} to verify the replayed state hash",
+                    height
+                )),
+            }
+        }
+        Ok(())
+    }
+
+    /// Computes the manifest root hashes of the checkpoints above `start_height` with the
+    /// `state-tool` of the given replica version.
+    fn compute_checkpoint_hashes(
+        &self,
+        replica_version: &ReplicaVersion,
+        start_height: u64,
+    ) -> Result<BTreeMap<u64, String>, String> {
+        let state_tool = self.binary_file("state-tool", replica_version);
+        let mut hashes = BTreeMap::new();
+        for (height, checkpoint_dir) in checkpoints_above(&self.state_dir(), start_height)? {
+            let mut cmd = Command::new(&state_tool);
+            cmd.arg("manifest")
+                .arg("--state")
+                .arg(&checkpoint_dir)
+                .stdout(Stdio::piped());
+            debug!(self.log, "[#{}] Will execute: {:?}", self.thread_id, cmd);
+            let output = exec_cmd(&mut cmd)
+                .map_err(|err| format!("Error computing the manifest at {}: {}", height, err))?
+                .unwrap_or_default();
+            let hash = parse_manifest_root_hash(&output)
+                .ok_or_else(|| format!("No root hash in the manifest at height {}", height))?;
+            hashes.insert(height, hash);
+        }
+        Ok(hashes)
+    }
+
     fn dump_log_file(&self, start_height: u64, stdout: &String) -> Result<(), String> {
         let timestamp = Utc::now().timestamp();
         let log_file_name = format!(
@@ -940,15 +1112,28 @@ fn collect_only_dirs(path: &PathBuf) -> Result<Vec<DirEntry>, String> {
 fn fetch_top_height(replica_version_dir: &DirEntry) -> (u64, PathBuf) {
     let replica_version_path = replica_version_dir.path();
     let height_bucket = last_dir_height(&replica_version_path, 10);
-    let top_height = last_dir_height(&replica_version_path.join(format!("{}", height_bucket)), 10);
+    let bucket_dir = replica_version_path.join(format!("{}", height_bucket));
+    let top_height = last_dir_height(&bucket_dir, 10).max(
+        list_archives(&bucket_dir)
+            .unwrap_or_default()
+            .iter()
+            .map(|(_, last_height, _)| last_height.get())
+            .max()
+            .unwrap_or(0),
+    );
     (top_height, replica_version_path)
 }
 
 fn is_height_in_spool(replica_version_dir: &DirEntry, height: u64) -> bool {
     let replica_version_path = replica_version_dir.path();
     let height_bucket = height / BUCKET_SIZE * BUCKET_SIZE;
-    let path = replica_version_path.join(format!("{}/{}", height_bucket, height));
-    path.exists()
+    let bucket_dir = replica_version_path.join(format!("{}", height_bucket));
+    bucket_dir.join(format!("{}", height)).exists()
+        || list_archives(&bucket_dir).unwrap_or_default().iter().any(
+            |(first_height, last_height, _)| {
+                first_height.get() <= height && height <= last_height.get()
+            },
+        )
 }
 
 fn height_from_dir_entry_radix(filename: &DirEntry, radix: u32) -> u64 {
@@ -979,6 +1164,21 @@ fn last_dir_height(dir: &PathBuf, radix: u32) -> u64 {
     }
 }
 
+/// Returns the heights and paths of the checkpoints in the state `dir` above `height`.
+fn checkpoints_above(dir: &Path, height: u64) -> Result<Vec<(u64, PathBuf)>, String> {
+    let checkpoints_dir = dir.join("checkpoints");
+    if !checkpoints_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut checkpoints: Vec<_> = collect_only_dirs(&checkpoints_dir)?
+        .iter()
+        .map(|entry| (height_from_dir_entry(entry), entry.path()))
+        .filter(|(checkpoint_height, _)| *checkpoint_height > height)
+        .collect();
+    checkpoints.sort();
+    Ok(checkpoints)
+}
+
 pub fn last_checkpoint(dir: &Path) -> u64 {
     last_dir_height(&dir.join("checkpoints"), 16)
 }
@@ -1253,8 +1453,64 @@ mod tests {
         );
     }
 
+    #[test]
+    fn archived_heights_are_in_spool_test() {
+        let dir = tmpdir("test_dir");
+
+        let backup_helper = fake_backup_helper(
+            dir.as_ref(),
+            /*versions_hot=*/ 2,
+            /*daily_replays=*/ 2,
+        );
+        let replica_version_dir = backup_helper.spool_dir().join("replica_version_1");
+        create_artifacts_dir_with_heights(&replica_version_dir, vec![50, 60]);
+        ic_artifact_pool::backup::archive::write_archive(
+            &replica_version_dir.join("0"),
+            vec![
+                (
+                    ic_types::Height::from(100),
+                    "random_beacon.bin".into(),
+                    vec![1],
+                ),
+                (
+                    ic_types::Height::from(150),
+                    "random_beacon.bin".into(),
+                    vec![2],
+                ),
+            ],
+        )
+        .unwrap();
+
+        let spool_dirs = collect_only_dirs(&backup_helper.spool_dir()).unwrap();
+        assert_eq!(fetch_top_height(&spool_dirs[0]).0, 150);
+        assert!(is_height_in_spool(&spool_dirs[0], 60));
+        assert!(is_height_in_spool(&spool_dirs[0], 120));
+        assert!(!is_height_in_spool(&spool_dirs[0], 160));
+    }
+
     // Utility functions below
 
+    #[test]
+    fn checkpoints_above_test() {
+        let dir = tmpdir("test_dir");
+        let checkpoints_dir = dir.as_ref().join("checkpoints");
+        for height in [100, 200, 300] {
+            create_dir_all(checkpoints_dir.join(format!("{:016x}", height))).unwrap();
+        }
+
+        assert_eq!(
+            checkpoints_above(dir.as_ref(), 100).unwrap(),
+            vec![
+                (200, checkpoints_dir.join(format!("{:016x}", 200))),
+                (300, checkpoints_dir.join(format!("{:016x}", 300))),
+            ]
+        );
+        assert!(checkpoints_above(dir.as_ref(), 300).unwrap().is_empty());
+        assert!(checkpoints_above(&dir.as_ref().join("missing"), 0)
+            .unwrap()
+            .is_empty());
+    }
+
     fn create_artifacts_dir_with_heights(replica_version_dir: &Path, heights: Vec<u64>) {
         for height in heights {
             let shard = 100 * (height / 100);
@@ -1308,6 +1564,7 @@ mod tests {
             artifacts_guard: Mutex::new(true),
             daily_replays,
             do_cold_storage: true,
+            verify_state_hashes: false,
             thread_id: 1,
             blacklisted_nodes: Arc::new(vec![]),
             log: ic_recovery::util::make_logger(),
@@ -144,6 +144,7 @@ impl BackupManager {
                 artifacts_guard: Mutex::new(true),
                 daily_replays,
                 do_cold_storage,
+                verify_state_hashes: s.verify_state_hashes,
                 thread_id: s.thread_id,
                 blacklisted_nodes: blacklisted.clone(),
                 log: log.clone(),
@@ -273,6 +274,7 @@ impl BackupManager {
                 replay_period_secs,
                 thread_id,
                 disable_cold_storage: false,
+                verify_state_hashes: false,
             })
         }
 
@@ -566,6 +568,7 @@ mod tests {
                 replay_period_secs: FAKE_REPLAY_PERIOD_MINS * 60,
                 thread_id: 1,
                 disable_cold_storage: false,
+                verify_state_hashes: false,
             }],
             cold_storage: Some(ColdStorage {
                 cold_storage_dir: fake_cold_storage_path,
@@ -17,6 +17,9 @@ pub struct SubnetConfig {
     pub replay_period_secs: u64,
     pub thread_id: u32,
     pub disable_cold_storage: bool,
+    /// Verify the replayed states against the state hashes in the CUPs.
+    #[serde(default)]
+    pub verify_state_hashes: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
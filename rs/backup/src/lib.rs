@@ -3,4 +3,5 @@ pub mod backup_manager;
 pub mod cmd;
 pub mod config;
 pub mod notification_client;
+pub mod state_hash_verification;
 pub mod util;
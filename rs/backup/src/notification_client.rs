@@ -84,6 +84,36 @@ impl NotificationClient {
         self.push_metrics(message)
     }
 
+    pub fn push_metrics_last_verified_height(&self, height: u64) {
+        let message = format!(
+            "# TYPE backup_last_verified_height gauge\n\
+            # HELP backup_last_verified_height The height of the last CUP whose state hash matched the replayed state.\n\
+            backup_last_verified_height{{ic=\"{}\"}} {}\n",
+            self.network_name, height
+        );
+        self.push_metrics(message)
+    }
+
+    pub fn push_metrics_state_hash_mismatches(&self, mismatches: usize) {
+        let message = format!(
+            "# TYPE backup_state_hash_mismatches gauge\n\
+            # HELP backup_state_hash_mismatches The number of CUP heights at which the replayed state hash differs from the CUP.\n\
+            backup_state_hash_mismatches{{ic=\"{}\"}} {}\n",
+            self.network_name, mismatches
+        );
+        self.push_metrics(message)
+    }
+
+    pub fn push_metrics_verified_state_hashes(&self, verified: usize) {
+        let message = format!(
+            "# TYPE backup_verified_state_hashes gauge\n\
+            # HELP backup_verified_state_hashes The number of CUP heights whose state hash was verified after the last replay.\n\
+            backup_verified_state_hashes{{ic=\"{}\"}} {}\n",
+            self.network_name, verified
+        );
+        self.push_metrics(message)
+    }
+
     pub fn push_metrics_synced_height(&self, height: u64) {
         let message = format!(
             "# TYPE backup_last_synced_height gauge\n\
@@ -0,0 +1,297 @@
+//! Verification of the replayed states against the CUPs of the backup.
+//!
+//! After a replay, the backup computes the manifests of the new checkpoints
+//! with `state-tool`. The functions below parse the root hashes of these
+//! manifests, compare them with the state hashes signed in the CUPs of the
+//! spool at the same heights, and keep the results in a persistent per-subnet
+//! report.
+
+use chrono::Utc;
+use ic_artifact_pool::backup::archive::{backup_file_exists, read_backup_file};
+use ic_config::artifact_pool::BACKUP_GROUP_SIZE;
+use ic_protobuf::types::v1 as pb;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The prefix of the line holding the root hash in the output of
+/// `state-tool manifest`, e.g. `ROOT HASH: 0a1b...`.
+const MANIFEST_ROOT_HASH_PREFIX: &str = "ROOT HASH:";
+
+pub const STATE_HASH_REPORT_FILE_NAME: &str = "state_hash_report.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckResult {
+    /// The local state hash equals the state hash in the CUP.
+    Match,
+    /// The local state hash differs from the state hash in the CUP.
+    Mismatch,
+    /// The CUP couldn't be found or decoded.
+    CupUnavailable,
+}
+
+/// The result of comparing the local state hash at a CUP height with the state
+/// hash in the CUP.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateHashCheck {
+    pub replica_version: String,
+    pub local_state_hash: String,
+    pub cup_state_hash: Option<String>,
+    pub result: CheckResult,
+    pub timestamp_secs: i64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateHashReport {
+    /// The highest CUP height at which the local state hash matched the CUP.
+    pub last_verified_height: u64,
+    /// The latest check at each CUP height.
+    pub checks: BTreeMap<u64, StateHashCheck>,
+}
+
+impl StateHashReport {
+    /// Reads the report from `path`, or returns an empty report if there is
+    /// none yet.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("Error reading report {:?}: {:?}", path, err))?;
+        serde_json::from_str(&content)
+            .map_err(|err| format!("Error deserializing report {:?}: {:?}", path, err))
+    }
+
+    /// Writes the report to `path`, replacing the previous one atomically.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|err| format!("Error serializing report: {:?}", err))?;
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, json)
+            .map_err(|err| format!("Error writing report {:?}: {:?}", tmp_path, err))?;
+        fs::rename(&tmp_path, path)
+            .map_err(|err| format!("Error renaming report {:?}: {:?}", tmp_path, err))
+    }
+
+    /// Records the check at the given height, replacing any earlier check at
+    /// that height.
+    pub fn record(&mut self, height: u64, check: StateHashCheck) {
+        if check.result == CheckResult::Match && height > self.last_verified_height {
+            self.last_verified_height = height;
+        }
+        self.checks.insert(height, check);
+    }
+
+    /// Returns the heights at which the local state hash differs from the CUP.
+    pub fn mismatches(&self) -> Vec<u64> {
+        self.checks
+            .iter()
+            .filter(|(_, check)| check.result == CheckResult::Mismatch)
+            .map(|(height, _)| *height)
+            .collect()
+    }
+}
+
+/// Parses the root hash of a checkpoint manifest from the output of
+/// `state-tool manifest`.
+pub fn parse_manifest_root_hash(state_tool_output: &str) -> Option<String> {
+    state_tool_output
+        .lines()
+        .rev()
+        .find_map(|line| line.trim().strip_prefix(MANIFEST_ROOT_HASH_PREFIX))
+        .map(|hash| hash.trim().to_string())
+        .filter(|hash| !hash.is_empty())
+}
+
+/// Returns the state hash, in hex, of the CUP at `height`, found in the first
+/// of the given replica version spool directories containing it.
+pub fn read_cup_state_hash(
+    replica_version_dirs: &[PathBuf],
+    height: u64,
+) -> Result<String, String> {
+    let group = height / BACKUP_GROUP_SIZE * BACKUP_GROUP_SIZE;
+    let cup_file = replica_version_dirs
+        .iter()
+        .map(|dir| {
+            dir.join(group.to_string())
+                .join(height.to_string())
+                .join("catch_up_package.bin")
+        })
+        .find(|file| backup_file_exists(file))
+        .ok_or_else(|| format!("No CUP found at height {}", height))?;
+
+    let bytes = read_backup_file(&cup_file)
+        .map_err(|err| format!("Error reading CUP {:?}: {:?}", cup_file, err))?;
+    let cup = pb::CatchUpPackage::decode(bytes.as_slice())
+        .map_err(|err| format!("Error decoding CUP {:?}: {:?}", cup_file, err))?;
+    let content = pb::CatchUpContent::decode(cup.content.as_slice())
+        .map_err(|err| format!("Error decoding CUP content {:?}: {:?}", cup_file, err))?;
+    let cup_height = content.block.map(|block| block.height).unwrap_or_default();
+    if cup_height != height {
+        return Err(format!(
+            "CUP {:?} has an unexpected height {}",
+            cup_file, cup_height
+        ));
+    }
+    Ok(hex::encode(content.state_hash))
+}
+
+/// Compares the local state hashes with the state hashes of the CUPs at the
+/// same heights and records the results in `report`. Returns the heights and
+/// results of all checks that didn't match.
+pub fn verify_state_hashes(
+    report: &mut StateHashReport,
+    replica_version: &str,
+    local_state_hashes: &BTreeMap<u64, String>,
+    replica_version_dirs: &[PathBuf],
+) -> Vec<(u64, CheckResult)> {
+    let mut failed = Vec::new();
+    for (height, local_state_hash) in local_state_hashes {
+        let cup_state_hash = read_cup_state_hash(replica_version_dirs, *height).ok();
+        let result = match &cup_state_hash {
+            Some(hash) if hash == local_state_hash => CheckResult::Match,
+            Some(_) => CheckResult::Mismatch,
+            None => CheckResult::CupUnavailable,
+        };
+        if result != CheckResult::Match {
+            failed.push((*height, result));
+        }
+        report.record(
+            *height,
+            StateHashCheck {
+                replica_version: replica_version.to_string(),
+                local_state_hash: local_state_hash.clone(),
+                cup_state_hash,
+                result,
+                timestamp_secs: Utc::now().timestamp(),
+            },
+        );
+    }
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_test_utilities_tmpdir::tmpdir;
+
+    const REPLICA_VERSION: &str = "fake_replica_version";
+
+    fn write_cup(replica_version_dir: &Path, height: u64, state_hash: &[u8]) {
+        let content = pb::CatchUpContent {
+            block: Some(pb::Block {
+                height,
+                ..Default::default()
+            }),
+            state_hash: state_hash.to_vec(),
+            ..Default::default()
+        };
+        let cup = pb::CatchUpPackage {
+            content: content.encode_to_vec(),
+            ..Default::default()
+        };
+        let dir = replica_version_dir.join("0").join(height.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("catch_up_package.bin"), cup.encode_to_vec()).unwrap();
+    }
+
+    #[test]
+    fn parses_manifest_root_hash() {
+        let output = "MANIFEST VERSION: V2\n\
+            FILE TABLE\n\
+            CHUNK TABLE\n\
+            \n\
+            ROOT HASH: 0a0b\n";
+
+        assert_eq!(parse_manifest_root_hash(output), Some("0a0b".to_string()));
+        assert_eq!(parse_manifest_root_hash("ROOT HASH: \n"), None);
+        assert_eq!(parse_manifest_root_hash("Failed to compute manifest"), None);
+    }
+
+    #[test]
+    fn records_matching_and_diverging_state_hashes() {
+        let dir = tmpdir("test_dir");
+        let replica_version_dir = dir.as_ref().join(REPLICA_VERSION);
+        write_cup(&replica_version_dir, 500, &[10, 11]);
+        write_cup(&replica_version_dir, 1000, &[12, 13]);
+        let local_state_hashes = BTreeMap::from([
+            (500, "0a0b".to_string()),
+            (1000, "ffff".to_string()),
+            (1500, "0e0f".to_string()),
+        ]);
+
+        let mut report = StateHashReport::default();
+        let failed = verify_state_hashes(
+            &mut report,
+            REPLICA_VERSION,
+            &local_state_hashes,
+            &[replica_version_dir],
+        );
+
+        assert_eq!(
+            failed,
+            vec![
+                (1000, CheckResult::Mismatch),
+                (1500, CheckResult::CupUnavailable)
+            ]
+        );
+        assert_eq!(report.last_verified_height, 500);
+        assert_eq!(report.mismatches(), vec![1000]);
+        assert_eq!(
+            report.checks[&1000].cup_state_hash,
+            Some("0c0d".to_string())
+        );
+        assert_eq!(report.checks[&1500].cup_state_hash, None);
+    }
+
+    #[test]
+    fn finds_cups_in_other_replica_version_dirs() {
+        let dir = tmpdir("test_dir");
+        let old_version_dir = dir.as_ref().join("old_version");
+        let new_version_dir = dir.as_ref().join("new_version");
+        write_cup(&old_version_dir, 500, &[10, 11]);
+
+        assert_eq!(
+            read_cup_state_hash(&[new_version_dir, old_version_dir], 500),
+            Ok("0a0b".to_string())
+        );
+    }
+
+    #[test]
+    fn report_survives_restarts() {
+        let dir = tmpdir("test_dir");
+        let replica_version_dir = dir.as_ref().join(REPLICA_VERSION);
+        let report_file = dir.as_ref().join(STATE_HASH_REPORT_FILE_NAME);
+        write_cup(&replica_version_dir, 500, &[10, 11]);
+        write_cup(&replica_version_dir, 1000, &[12, 13]);
+
+        let mut report = StateHashReport::load(&report_file).unwrap();
+        assert_eq!(report, StateHashReport::default());
+        verify_state_hashes(
+            &mut report,
+            REPLICA_VERSION,
+            &BTreeMap::from([(500, "0a0b".to_string())]),
+            &[replica_version_dir.clone()],
+        );
+        report.save(&report_file).unwrap();
+
+        let mut report = StateHashReport::load(&report_file).unwrap();
+        verify_state_hashes(
+            &mut report,
+            REPLICA_VERSION,
+            &BTreeMap::from([(1000, "0c0d".to_string())]),
+            &[replica_version_dir],
+        );
+        report.save(&report_file).unwrap();
+
+        let report = StateHashReport::load(&report_file).unwrap();
+        assert_eq!(report.last_verified_height, 1000);
+        assert_eq!(report.checks.len(), 2);
+        assert!(report.mismatches().is_empty());
+    }
+}
@@ -2,6 +2,8 @@ mod dts;
 pub mod logging;
 pub mod sandbox_manager;
 pub mod sandbox_server;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+mod syscall_filter;
 
 use ic_canister_sandbox_common::{
     child_process_initialization, controller_client_stub, protocol, rpc,
@@ -74,6 +76,7 @@ pub fn run_canister_sandbox(
     let (log, _log_guard) = new_replica_logger_from_config(&logger_config);
 
     let socket = Arc::new(socket);
+    let syscall_filter = embedder_config.sandbox_syscall_filter;
 
     let out_stream =
         transport::UnixStreamMuxWriter::<protocol::transport::SandboxToController>::new(
@@ -92,7 +95,7 @@ pub fn run_canister_sandbox(
     // Construct RPC server for the  service offered by this binary,
     // namely access to the sandboxed canister runner functions.
     let svc = Arc::new(sandbox_server::SandboxServer::new(
-        sandbox_manager::SandboxManager::new(controller, embedder_config, log),
+        sandbox_manager::SandboxManager::new(controller, embedder_config, log.clone()),
     ));
 
     // Wrap it all up to handle frames received on socket -- either
@@ -113,6 +116,12 @@ pub fn run_canister_sandbox(
         });
     }
 
+    // Restrict the system calls before any canister code runs.
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    syscall_filter::install_syscall_filter(syscall_filter, &log);
+    #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+    let _ = syscall_filter;
+
     // Run RPC operations on the stream socket.
     transport::socket_read_messages::<_, _>(
         move |message| {
@@ -181,6 +181,7 @@ mod tests {
                 NumInstructions::new(INSTRUCTION_LIMIT),
             ),
             canister_memory_limit: NumBytes::new(4 << 30),
+            wasm_memory_limit: None,
             memory_allocation: MemoryAllocation::default(),
             compute_allocation: ComputeAllocation::default(),
             subnet_type: SubnetType::Application,
@@ -218,6 +219,7 @@ mod tests {
             CanisterTimer::Inactive,
             0,
             BTreeSet::from([controller]),
+            BTreeMap::new(),
         )
     }
 
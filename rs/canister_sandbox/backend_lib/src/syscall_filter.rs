@@ -0,0 +1,431 @@
+//! A seccomp-bpf filter that restricts the system calls a sandbox process can
+//! make once it is initialized.
+//!
+//! By the time the filter is installed, the sandbox process has set up its
+//! logger, its thread pools and the RPC socket to the replica. From then on it
+//! only needs to exchange messages over the socket, map memory and run threads.
+use ic_config::embedders::SandboxSyscallFilter;
+use ic_logger::{warn, ReplicaLogger};
+
+// The definitions from `linux/filter.h`, `linux/seccomp.h` and
+// `linux/audit.h` that are needed to build and install the filter.
+#[allow(non_camel_case_types)]
+mod abi {
+    pub(super) const BPF_LD: u16 = 0x00;
+    pub(super) const BPF_W: u16 = 0x00;
+    pub(super) const BPF_ABS: u16 = 0x20;
+    pub(super) const BPF_JMP: u16 = 0x05;
+    pub(super) const BPF_ALU: u16 = 0x04;
+    pub(super) const BPF_AND: u16 = 0x50;
+    pub(super) const BPF_JEQ: u16 = 0x10;
+    pub(super) const BPF_K: u16 = 0x00;
+    pub(super) const BPF_RET: u16 = 0x06;
+
+    pub(super) const SECCOMP_SET_MODE_FILTER: libc::c_ulong = 1;
+    pub(super) const SECCOMP_FILTER_FLAG_TSYNC: libc::c_ulong = 1;
+
+    pub(super) const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+    pub(super) const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+    pub(super) const SECCOMP_RET_LOG: u32 = 0x7ffc_0000;
+    pub(super) const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+    pub(super) const AUDIT_ARCH_X86_64: u32 = 0xc000_003e;
+
+    // Offsets of the fields of `struct seccomp_data`.
+    pub(super) const SECCOMP_DATA_NR_OFFSET: u32 = 0;
+    pub(super) const SECCOMP_DATA_ARCH_OFFSET: u32 = 4;
+    pub(super) const SECCOMP_DATA_ARGS_OFFSET: u32 = 16;
+
+    // The `userfaultfd` ioctls used by the memory tracker, i.e. `_IOWR` or
+    // `_IOR` of type `0xAA` with the size of the argument struct.
+    pub(super) const UFFDIO_API: u32 = 0xc018_aa3f;
+    pub(super) const UFFDIO_REGISTER: u32 = 0xc020_aa00;
+    pub(super) const UFFDIO_WAKE: u32 = 0x8010_aa02;
+    pub(super) const UFFDIO_COPY: u32 = 0xc028_aa03;
+    pub(super) const UFFDIO_WRITEPROTECT: u32 = 0xc018_aa06;
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    pub(super) struct sock_filter {
+        pub code: u16,
+        pub jt: u8,
+        pub jf: u8,
+        pub k: u32,
+    }
+
+    #[repr(C)]
+    pub(super) struct sock_fprog {
+        pub len: libc::c_ushort,
+        pub filter: *const sock_filter,
+    }
+}
+
+/// The system calls a sandbox process is allowed to make after
+/// initialization.
+const ALLOWED_SYSCALLS: &[libc::c_long] = &[
+    // RPC socket to the replica and file descriptors received over it.
+    libc::SYS_read,
+    libc::SYS_write,
+    libc::SYS_readv,
+    libc::SYS_writev,
+    libc::SYS_pread64,
+    libc::SYS_pwrite64,
+    libc::SYS_recvmsg,
+    libc::SYS_sendmsg,
+    libc::SYS_setsockopt,
+    libc::SYS_shutdown,
+    libc::SYS_close,
+    libc::SYS_dup,
+    libc::SYS_fcntl,
+    libc::SYS_fstat,
+    libc::SYS_newfstatat,
+    libc::SYS_statx,
+    libc::SYS_lseek,
+    libc::SYS_ftruncate,
+    libc::SYS_fallocate,
+    libc::SYS_memfd_create,
+    // Memory mapping of Wasm memories, compiled code and page maps.
+    libc::SYS_mmap,
+    libc::SYS_mprotect,
+    libc::SYS_munmap,
+    libc::SYS_mremap,
+    libc::SYS_madvise,
+    libc::SYS_msync,
+    libc::SYS_brk,
+    // Memory tracking with the `SIGSEGV` handler or `userfaultfd`.
+    libc::SYS_rt_sigaction,
+    libc::SYS_rt_sigprocmask,
+    libc::SYS_rt_sigreturn,
+    libc::SYS_sigaltstack,
+    libc::SYS_userfaultfd,
+    libc::SYS_eventfd2,
+    libc::SYS_poll,
+    libc::SYS_ppoll,
+    // Threads and synchronization. `clone` is only allowed for threads, see
+    // `THREAD_CLONE_FLAGS`.
+    libc::SYS_set_robust_list,
+    libc::SYS_rseq,
+    libc::SYS_prctl,
+    libc::SYS_futex,
+    libc::SYS_sched_yield,
+    libc::SYS_sched_getaffinity,
+    libc::SYS_nanosleep,
+    libc::SYS_clock_nanosleep,
+    libc::SYS_restart_syscall,
+    // Time, randomness and process identity.
+    libc::SYS_clock_gettime,
+    libc::SYS_gettimeofday,
+    libc::SYS_getrandom,
+    libc::SYS_getpid,
+    libc::SYS_gettid,
+    // Termination, including `abort()` on panic.
+    libc::SYS_tgkill,
+    libc::SYS_exit,
+    libc::SYS_exit_group,
+];
+
+/// The flags that `clone` must be called with, as `pthread_create` does. This
+/// prevents the sandbox process from forking.
+const THREAD_CLONE_FLAGS: u32 = (libc::CLONE_VM
+    | libc::CLONE_FS
+    | libc::CLONE_FILES
+    | libc::CLONE_SIGHAND
+    | libc::CLONE_THREAD
+    | libc::CLONE_SYSVSEM) as u32;
+
+/// The only `ioctl` requests allowed, i.e. the ones on `userfaultfd`.
+const ALLOWED_IOCTLS: &[u32] = &[
+    abi::UFFDIO_API,
+    abi::UFFDIO_REGISTER,
+    abi::UFFDIO_WAKE,
+    abi::UFFDIO_COPY,
+    abi::UFFDIO_WRITEPROTECT,
+];
+
+/// A compiled seccomp-bpf program that allows `ALLOWED_SYSCALLS` and handles
+/// all other system calls according to the filter mode.
+pub(crate) struct SyscallFilter {
+    program: Vec<abi::sock_filter>,
+}
+
+impl SyscallFilter {
+    /// Builds the filter for the given mode. Returns `None` if the filter is
+    /// disabled.
+    pub(crate) fn new(mode: SandboxSyscallFilter) -> Option<Self> {
+        let violation = match mode {
+            SandboxSyscallFilter::Disabled => return None,
+            SandboxSyscallFilter::Report => abi::SECCOMP_RET_LOG,
+            SandboxSyscallFilter::Enforce => abi::SECCOMP_RET_KILL_PROCESS,
+        };
+
+        let mut program = vec![
+            load(abi::SECCOMP_DATA_ARCH_OFFSET),
+            // System call numbers are only meaningful for the expected
+            // architecture.
+            jump_if_equal(abi::AUDIT_ARCH_X86_64, 1, 0),
+            ret(violation),
+            load(abi::SECCOMP_DATA_NR_OFFSET),
+        ];
+        let allowed = ALLOWED_SYSCALLS.iter();
+        // The checksum of the `SIGSEGV` handler is written to a file on exit.
+        #[cfg(feature = "sigsegv_handler_checksum")]
+        let allowed = allowed.chain(&[libc::SYS_openat]);
+        for syscall in allowed {
+            program.push(jump_if_equal(*syscall as u32, 0, 1));
+            program.push(ret(abi::SECCOMP_RET_ALLOW));
+        }
+
+        // The arguments of `clone3` are passed in memory and cannot be
+        // inspected, so it fails with `ENOSYS` and the C library falls back to
+        // `clone`.
+        program.push(jump_if_equal(libc::SYS_clone3 as u32, 0, 1));
+        program.push(ret(abi::SECCOMP_RET_ERRNO | libc::ENOSYS as u32));
+
+        // The remaining checks load an argument over the system call number,
+        // so they must come last.
+        program.push(jump_if_equal(libc::SYS_clone as u32, 0, 5));
+        program.push(load(arg_offset(0)));
+        program.push(and(THREAD_CLONE_FLAGS));
+        program.push(jump_if_equal(THREAD_CLONE_FLAGS, 0, 1));
+        program.push(ret(abi::SECCOMP_RET_ALLOW));
+        program.push(ret(violation));
+
+        program.push(jump_if_equal(
+            libc::SYS_ioctl as u32,
+            0,
+            (2 * ALLOWED_IOCTLS.len() + 1) as u8,
+        ));
+        program.push(load(arg_offset(1)));
+        for request in ALLOWED_IOCTLS {
+            program.push(jump_if_equal(*request, 0, 1));
+            program.push(ret(abi::SECCOMP_RET_ALLOW));
+        }
+        program.push(ret(violation));
+        Some(Self { program })
+    }
+
+    /// Installs the filter for all threads of the calling process. The filter
+    /// cannot be removed afterwards.
+    ///
+    /// This function does not allocate, so that it can be called in a forked
+    /// child process.
+    pub(crate) fn install(&self) -> std::io::Result<()> {
+        let prog = abi::sock_fprog {
+            len: self.program.len() as libc::c_ushort,
+            filter: self.program.as_ptr(),
+        };
+        // SAFETY: The arguments are valid for `PR_SET_NO_NEW_PRIVS`, which is
+        // required to install a filter without `CAP_SYS_ADMIN`.
+        if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // SAFETY: `prog` points to a valid program that outlives the call. The
+        // kernel copies the program.
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_seccomp,
+                abi::SECCOMP_SET_MODE_FILTER,
+                abi::SECCOMP_FILTER_FLAG_TSYNC,
+                &prog as *const abi::sock_fprog,
+            )
+        };
+        match result {
+            0 => Ok(()),
+            // With `SECCOMP_FILTER_FLAG_TSYNC`, a positive result is the id of
+            // a thread that could not be synchronized.
+            tid if tid > 0 => Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("Failed to synchronize the filter to thread {}", tid),
+            )),
+            _ => Err(std::io::Error::last_os_error()),
+        }
+    }
+}
+
+/// Installs the syscall filter for the given mode.
+///
+/// Panics if the filter is enforced but cannot be installed, because the
+/// sandbox process must not run canister code without it.
+pub(crate) fn install_syscall_filter(mode: SandboxSyscallFilter, log: &ReplicaLogger) {
+    let filter = match SyscallFilter::new(mode) {
+        Some(filter) => filter,
+        None => return,
+    };
+    match (filter.install(), mode) {
+        (Ok(()), _) => {}
+        (Err(err), SandboxSyscallFilter::Enforce) => {
+            panic!("Failed to install the sandbox syscall filter: {}", err)
+        }
+        (Err(err), _) => warn!(log, "Failed to install the sandbox syscall filter: {}", err),
+    }
+}
+
+// Some of the BPF flags are zero, but are kept to match the C definitions.
+#[allow(clippy::identity_op)]
+fn load(offset: u32) -> abi::sock_filter {
+    abi::sock_filter {
+        code: abi::BPF_LD | abi::BPF_W | abi::BPF_ABS,
+        jt: 0,
+        jf: 0,
+        k: offset,
+    }
+}
+
+/// Returns the offset of the lower 32 bits of the given system call argument
+/// in `struct seccomp_data`.
+fn arg_offset(index: u32) -> u32 {
+    abi::SECCOMP_DATA_ARGS_OFFSET + 8 * index
+}
+
+#[allow(clippy::identity_op)]
+fn and(mask: u32) -> abi::sock_filter {
+    abi::sock_filter {
+        code: abi::BPF_ALU | abi::BPF_AND | abi::BPF_K,
+        jt: 0,
+        jf: 0,
+        k: mask,
+    }
+}
+
+#[allow(clippy::identity_op)]
+fn jump_if_equal(value: u32, jt: u8, jf: u8) -> abi::sock_filter {
+    abi::sock_filter {
+        code: abi::BPF_JMP | abi::BPF_JEQ | abi::BPF_K,
+        jt,
+        jf,
+        k: value,
+    }
+}
+
+#[allow(clippy::identity_op)]
+fn ret(action: u32) -> abi::sock_filter {
+    abi::sock_filter {
+        code: abi::BPF_RET | abi::BPF_K,
+        jt: 0,
+        jf: 0,
+        k: action,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Exit code of the child process if the filter could not be installed,
+    // e.g. because seccomp is not available in the test environment.
+    const INSTALL_FAILED: i32 = 2;
+
+    /// Installs the filter in a forked child process, runs `f` and returns the
+    /// wait status of the child. The child exits with 0 if `f` returns true.
+    ///
+    /// `f` must not allocate, because the test process is multi-threaded.
+    fn run_in_child(mode: SandboxSyscallFilter, f: fn() -> bool) -> Option<libc::c_int> {
+        let filter = SyscallFilter::new(mode).unwrap();
+        // SAFETY: The child only makes system calls and does not allocate
+        // before exiting.
+        let pid = unsafe { libc::fork() };
+        assert!(pid >= 0, "fork failed");
+        if pid == 0 {
+            if filter.install().is_err() {
+                unsafe { libc::_exit(INSTALL_FAILED) };
+            }
+            unsafe { libc::_exit(if f() { 0 } else { 1 }) };
+        }
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        if libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == INSTALL_FAILED {
+            return None;
+        }
+        Some(status)
+    }
+
+    fn assert_killed(status: Option<libc::c_int>) {
+        if let Some(status) = status {
+            assert!(libc::WIFSIGNALED(status), "status: {}", status);
+            assert_eq!(libc::WTERMSIG(status), libc::SIGSYS);
+        }
+    }
+
+    fn assert_succeeded(status: Option<libc::c_int>) {
+        if let Some(status) = status {
+            assert!(libc::WIFEXITED(status), "status: {}", status);
+            assert_eq!(libc::WEXITSTATUS(status), 0);
+        }
+    }
+
+    fn getcwd() -> bool {
+        let mut buf = [0_u8; 256];
+        unsafe { libc::syscall(libc::SYS_getcwd, buf.as_mut_ptr(), buf.len()) > 0 }
+    }
+
+    #[test]
+    fn disabled_filter_is_not_built() {
+        assert!(SyscallFilter::new(SandboxSyscallFilter::Disabled).is_none());
+    }
+
+    #[test]
+    fn enforced_filter_kills_process_on_violation() {
+        assert_killed(run_in_child(SandboxSyscallFilter::Enforce, getcwd));
+    }
+
+    #[test]
+    fn reported_violation_does_not_kill_process() {
+        assert_succeeded(run_in_child(SandboxSyscallFilter::Report, getcwd));
+    }
+
+    #[test]
+    fn socket_timeout_can_be_set() {
+        use std::os::unix::{io::AsRawFd, net::UnixStream};
+        use std::sync::atomic::{AtomicI32, Ordering};
+
+        // Sandbox processes receive their socket before the filter is
+        // installed, so the socket is created by the parent.
+        static SOCKET_FD: AtomicI32 = AtomicI32::new(-1);
+        let (socket, _) = UnixStream::pair().unwrap();
+        SOCKET_FD.store(socket.as_raw_fd(), Ordering::SeqCst);
+
+        assert_succeeded(run_in_child(SandboxSyscallFilter::Enforce, || {
+            let tv = libc::timeval {
+                tv_sec: 1,
+                tv_usec: 0,
+            };
+            unsafe {
+                libc::setsockopt(
+                    SOCKET_FD.load(Ordering::SeqCst),
+                    libc::SOL_SOCKET,
+                    libc::SO_RCVTIMEO,
+                    &tv as *const libc::timeval as *const libc::c_void,
+                    std::mem::size_of::<libc::timeval>() as u32,
+                ) == 0
+            }
+        }));
+    }
+
+    #[test]
+    fn forking_clone_is_killed() {
+        assert_killed(run_in_child(SandboxSyscallFilter::Enforce, || {
+            let pid = unsafe { libc::syscall(libc::SYS_clone, libc::SIGCHLD, 0, 0, 0, 0) };
+            if pid == 0 {
+                unsafe { libc::_exit(0) };
+            }
+            pid > 0
+        }));
+    }
+
+    #[test]
+    fn clone3_fails_with_enosys() {
+        assert_succeeded(run_in_child(SandboxSyscallFilter::Enforce, || {
+            let result = unsafe { libc::syscall(libc::SYS_clone3, 0, 0) };
+            result == -1 && std::io::Error::last_os_error().raw_os_error() == Some(libc::ENOSYS)
+        }));
+    }
+
+    #[test]
+    fn non_userfaultfd_ioctl_is_killed() {
+        assert_killed(run_in_child(SandboxSyscallFilter::Enforce, || {
+            let mut bytes: libc::c_int = 0;
+            unsafe { libc::ioctl(0, libc::FIONREAD, &mut bytes) };
+            true
+        }));
+    }
+}
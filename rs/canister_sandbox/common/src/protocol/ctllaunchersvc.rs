@@ -1,13 +1,13 @@
 //! A service provided by the controller to the launcher.
 
-use ic_types::CanisterId;
 use serde::{Deserialize, Serialize};
 
 use crate::fdenum::EnumerateInnerFileDescriptors;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct SandboxExitedRequest {
-    pub canister_id: CanisterId,
+    /// The process id of the sandbox process that exited.
+    pub pid: u32,
 }
 
 impl EnumerateInnerFileDescriptors for SandboxExitedRequest {
@@ -13,7 +13,9 @@ use crate::fdenum::EnumerateInnerFileDescriptors;
 pub struct LaunchSandboxRequest {
     pub sandbox_exec_path: String,
     pub argv: Vec<String>,
-    pub canister_id: CanisterId,
+    /// The canister the process is spawned for or `None` if the process is
+    /// spawned ahead of time to be adopted by a canister later.
+    pub canister_id: Option<CanisterId>,
     pub socket: RawFd,
 }
 
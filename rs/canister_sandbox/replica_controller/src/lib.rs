@@ -4,4 +4,5 @@ pub mod launch_as_process;
 mod process_exe_and_args;
 pub mod process_os_metrics;
 mod sandbox_process_eviction;
+mod sandbox_process_pool;
 pub mod sandboxed_execution_controller;
@@ -0,0 +1,184 @@
+use std::sync::{Arc, Condvar, Mutex};
+
+/// A pool of idle processes that are spawned ahead of time, so that a canister
+/// that does not have a sandbox process yet can adopt one without waiting for
+/// a new process to start.
+///
+/// Whenever a process is taken from the pool, a background thread spawns new
+/// processes until the pool has `target_size` processes again.
+pub(crate) struct SandboxProcessPool<P> {
+    state: Mutex<PoolState<P>>,
+    refilled: Condvar,
+    target_size: usize,
+    spawn: Box<dyn Fn() -> Option<P> + Send + Sync>,
+}
+
+struct PoolState<P> {
+    idle: Vec<P>,
+    // Set while a background thread is spawning new processes.
+    refilling: bool,
+    // Set when the pool is shut down. No new processes are spawned after that.
+    shut_down: bool,
+}
+
+impl<P: Send + 'static> SandboxProcessPool<P> {
+    /// Creates an empty pool. The pool is filled by calling `refill()`.
+    ///
+    /// The `spawn` function spawns a new process and returns `None` if that
+    /// failed, in which case the pool stops refilling until the next call of
+    /// `refill()`.
+    pub(crate) fn new(
+        target_size: usize,
+        spawn: impl Fn() -> Option<P> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            state: Mutex::new(PoolState {
+                idle: Vec::with_capacity(target_size),
+                refilling: false,
+                shut_down: false,
+            }),
+            refilled: Condvar::new(),
+            target_size,
+            spawn: Box::new(spawn),
+        }
+    }
+
+    /// Takes an idle process from the pool and starts refilling the pool in
+    /// the background. Returns `None` if the pool is empty.
+    pub(crate) fn take(self: &Arc<Self>) -> Option<P> {
+        let process = self.state.lock().unwrap().idle.pop();
+        self.refill();
+        process
+    }
+
+    /// Starts spawning processes in a background thread until the pool has
+    /// `target_size` idle processes.
+    pub(crate) fn refill(self: &Arc<Self>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.refilling || state.shut_down || state.idle.len() >= self.target_size {
+                return;
+            }
+            state.refilling = true;
+        }
+        let pool = Arc::clone(self);
+        std::thread::spawn(move || pool.refill_blocking());
+    }
+
+    fn refill_blocking(&self) {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.shut_down || state.idle.len() >= self.target_size {
+                    state.refilling = false;
+                    self.refilled.notify_all();
+                    return;
+                }
+            }
+            // Spawning a process takes a while, so it is done without holding
+            // the lock.
+            let process = (self.spawn)();
+            let mut state = self.state.lock().unwrap();
+            match process {
+                Some(process) if !state.shut_down => state.idle.push(process),
+                Some(_) => {}
+                None => {
+                    state.refilling = false;
+                    self.refilled.notify_all();
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Returns the number of idle processes in the pool.
+    pub(crate) fn len(&self) -> usize {
+        self.state.lock().unwrap().idle.len()
+    }
+
+    /// Drops all idle processes and stops refilling the pool.
+    pub(crate) fn shut_down(&self) {
+        let idle = {
+            let mut state = self.state.lock().unwrap();
+            state.shut_down = true;
+            std::mem::take(&mut state.idle)
+        };
+        // Dropping the processes terminates them, which is done without
+        // holding the lock.
+        drop(idle);
+    }
+
+    /// Blocks until the background refill, if any, has finished.
+    #[cfg(test)]
+    pub(crate) fn wait_for_refill(&self) {
+        let _state = self
+            .refilled
+            .wait_while(self.state.lock().unwrap(), |state| state.refilling)
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn counting_pool(target_size: usize) -> (Arc<SandboxProcessPool<usize>>, Arc<AtomicUsize>) {
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let spawned_copy = Arc::clone(&spawned);
+        let pool = Arc::new(SandboxProcessPool::new(target_size, move || {
+            Some(spawned_copy.fetch_add(1, Ordering::SeqCst))
+        }));
+        (pool, spawned)
+    }
+
+    #[test]
+    fn refill_fills_pool_to_target_size() {
+        let (pool, spawned) = counting_pool(3);
+        assert_eq!(pool.len(), 0);
+        pool.refill();
+        pool.wait_for_refill();
+        assert_eq!(pool.len(), 3);
+        assert_eq!(spawned.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn take_refills_pool() {
+        let (pool, spawned) = counting_pool(2);
+        pool.refill();
+        pool.wait_for_refill();
+        assert!(pool.take().is_some());
+        pool.wait_for_refill();
+        assert_eq!(pool.len(), 2);
+        assert_eq!(spawned.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn take_from_empty_pool_misses() {
+        let (pool, _spawned) = counting_pool(0);
+        assert_eq!(pool.take(), None);
+        pool.wait_for_refill();
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn failed_spawn_stops_refill() {
+        let pool = Arc::new(SandboxProcessPool::<usize>::new(2, || None));
+        pool.refill();
+        pool.wait_for_refill();
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn shut_down_drops_idle_processes() {
+        let (pool, spawned) = counting_pool(2);
+        pool.refill();
+        pool.wait_for_refill();
+        pool.shut_down();
+        assert_eq!(pool.len(), 0);
+        assert_eq!(pool.take(), None);
+        pool.wait_for_refill();
+        assert_eq!(pool.len(), 0);
+        assert_eq!(spawned.load(Ordering::SeqCst), 2);
+    }
+}
@@ -28,9 +28,7 @@ use ic_types::ingress::WasmResult;
 use ic_types::methods::{FuncRef, WasmMethod};
 use ic_types::{CanisterId, NumInstructions};
 use ic_wasm_types::CanisterModule;
-#[cfg(target_os = "linux")]
-use prometheus::IntGauge;
-use prometheus::{Histogram, HistogramVec, IntCounter, IntCounterVec};
+use prometheus::{Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge};
 use std::collections::{HashMap, VecDeque};
 #[cfg(target_os = "linux")]
 use std::convert::TryInto;
@@ -48,6 +46,7 @@ use crate::process_exe_and_args::{create_launcher_argv, create_sandbox_argv};
 #[cfg(target_os = "linux")]
 use crate::process_os_metrics;
 use crate::sandbox_process_eviction::{self, EvictionCandidate};
+use crate::sandbox_process_pool::SandboxProcessPool;
 use ic_replicated_state::page_map::PageAllocatorFileDescriptor;
 
 const SANDBOX_PROCESS_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
@@ -68,6 +67,11 @@ const COMPILATION_CACHE_HIT: &str = "compilation_cache_hit";
 const COMPILATION_CACHE_HIT_COMPILATION_ERROR: &str = "compilation_cache_hit_compilation_error";
 const CACHE_MISS: &str = "cache_miss";
 
+// Metric labels for the outcomes of taking a sandbox process from the pool of
+// idle sandbox processes.
+const SANDBOX_POOL_HIT: &str = "hit";
+const SANDBOX_POOL_MISS: &str = "miss";
+
 struct SandboxedExecutionMetrics {
     sandboxed_execution_replica_execute_duration: HistogramVec,
     sandboxed_execution_replica_execute_prepare_duration: HistogramVec,
@@ -76,6 +80,9 @@ struct SandboxedExecutionMetrics {
     sandboxed_execution_sandbox_execute_duration: HistogramVec,
     sandboxed_execution_sandbox_execute_run_duration: HistogramVec,
     sandboxed_execution_spawn_process: Histogram,
+    sandboxed_execution_sandbox_pool_lookups: IntCounterVec,
+    sandboxed_execution_sandbox_pool_adoption_duration: HistogramVec,
+    sandboxed_execution_sandbox_pool_idle_processes: IntGauge,
     #[cfg(target_os = "linux")]
     sandboxed_execution_subprocess_anon_rss_total: IntGauge,
     #[cfg(target_os = "linux")]
@@ -155,6 +162,21 @@ impl SandboxedExecutionMetrics {
                 "The time to spawn a sandbox process",
                 decimal_buckets_with_zero(-4, 1),
             ),
+            sandboxed_execution_sandbox_pool_lookups: metrics_registry.int_counter_vec(
+                "sandboxed_execution_sandbox_pool_lookups_total",
+                "Results from taking an idle sandbox process from the pool for a canister without a sandbox process",
+                &["result"],
+            ),
+            sandboxed_execution_sandbox_pool_adoption_duration: metrics_registry.histogram_vec(
+                "sandboxed_execution_sandbox_pool_adoption_duration_seconds",
+                "The time until a canister without a sandbox process gets one, by whether it was taken from the pool",
+                decimal_buckets_with_zero(-4, 1),
+                &["result"],
+            ),
+            sandboxed_execution_sandbox_pool_idle_processes: metrics_registry.int_gauge(
+                "sandboxed_execution_sandbox_pool_idle_processes",
+                "The number of idle sandbox processes in the pool",
+            ),
             #[cfg(target_os = "linux")]
             sandboxed_execution_subprocess_anon_rss_total: metrics_registry.int_gauge(
                 "sandboxed_execution_subprocess_anon_rss_total_kib",
@@ -639,7 +661,10 @@ pub struct SandboxedExecutionController {
     /// the same for all canisters.
     sandbox_exec_argv: Vec<String>,
     metrics: Arc<SandboxedExecutionMetrics>,
-    launcher_service: Box<dyn LauncherService>,
+    launcher_service: Arc<dyn LauncherService>,
+    /// Idle sandbox processes that are adopted by canisters that do not have a
+    /// sandbox process yet.
+    sandbox_process_pool: Arc<SandboxProcessPool<Arc<SandboxProcess>>>,
     fd_factory: Arc<dyn PageAllocatorFileDescriptor>,
 }
 
@@ -649,6 +674,9 @@ impl Drop for SandboxedExecutionController {
         let mut guard = self.backends.lock().unwrap();
         evict_sandbox_processes(&mut guard, 0, 0, Duration::default());
 
+        // Terminate the idle sandbox processes.
+        self.sandbox_process_pool.shut_down();
+
         // Terminate the Sandbox Launcher process.
         self.launcher_service
             .terminate(protocol::launchersvc::TerminateRequest {})
@@ -1018,14 +1046,52 @@ impl SandboxedExecutionController {
         let backends = Arc::new(Mutex::new(HashMap::new()));
         let metrics = Arc::new(SandboxedExecutionMetrics::new(metrics_registry));
 
+        let exit_watcher = Arc::new(ExitWatcher {
+            logger: logger.clone(),
+            backends: Arc::clone(&backends),
+        });
+
+        let (launcher_service, mut child) = spawn_launcher_process(
+            &launcher_exec_argv[0],
+            &launcher_exec_argv[1..],
+            exit_watcher,
+        )?;
+        let launcher_service: Arc<dyn LauncherService> = Arc::from(launcher_service);
+
+        let sandbox_process_pool = {
+            let launcher_service = Arc::clone(&launcher_service);
+            let sandbox_exec_argv = sandbox_exec_argv.clone();
+            let metrics = Arc::clone(&metrics);
+            let logger = logger.clone();
+            Arc::new(SandboxProcessPool::new(
+                embedder_config.sandbox_pool_size,
+                move || {
+                    let _timer = metrics.sandboxed_execution_spawn_process.start_timer();
+                    spawn_sandbox_process(
+                        &*launcher_service,
+                        sandbox_exec_argv.clone(),
+                        None,
+                        &logger,
+                    )
+                    .map_err(|err| {
+                        warn!(logger, "Failed to spawn an idle sandbox process: {}", err)
+                    })
+                    .ok()
+                },
+            ))
+        };
+        sandbox_process_pool.refill();
+
         let backends_copy = Arc::clone(&backends);
         let metrics_copy = Arc::clone(&metrics);
         let logger_copy = logger.clone();
+        let sandbox_process_pool_copy = Arc::clone(&sandbox_process_pool);
 
         std::thread::spawn(move || {
             SandboxedExecutionController::monitor_and_evict_sandbox_processes(
                 logger_copy,
                 backends_copy,
+                sandbox_process_pool_copy,
                 metrics_copy,
                 min_sandbox_count,
                 max_sandbox_count,
@@ -1033,17 +1099,6 @@ impl SandboxedExecutionController {
             );
         });
 
-        let exit_watcher = Arc::new(ExitWatcher {
-            logger: logger.clone(),
-            backends: Arc::clone(&backends),
-        });
-
-        let (launcher_service, mut child) = spawn_launcher_process(
-            &launcher_exec_argv[0],
-            &launcher_exec_argv[1..],
-            exit_watcher,
-        )?;
-
         // We spawn a thread to wait for the exit notification of the launcher
         // process.
         thread::spawn(move || {
@@ -1063,6 +1118,7 @@ impl SandboxedExecutionController {
             sandbox_exec_argv,
             metrics,
             launcher_service,
+            sandbox_process_pool,
             fd_factory: Arc::clone(&fd_factory),
         })
     }
@@ -1074,6 +1130,7 @@ impl SandboxedExecutionController {
         // `logger` isn't used on MacOS.
         #[allow(unused_variables)] logger: ReplicaLogger,
         backends: Arc<Mutex<HashMap<CanisterId, Backend>>>,
+        sandbox_process_pool: Arc<SandboxProcessPool<Arc<SandboxProcess>>>,
         metrics: Arc<SandboxedExecutionMetrics>,
         min_sandbox_count: usize,
         max_sandbox_count: usize,
@@ -1177,6 +1234,12 @@ impl SandboxedExecutionController {
                 );
             }
 
+            // Retry refilling the pool in case spawning a process failed.
+            sandbox_process_pool.refill();
+            metrics
+                .sandboxed_execution_sandbox_pool_idle_processes
+                .set(sandbox_process_pool.len() as i64);
+
             // Collect metrics sufficiently infrequently that it does not use
             // excessive compute resources. It might be sensible to scale this
             // based on the time measured to perform the collection and e.g.
@@ -1219,7 +1282,7 @@ impl SandboxedExecutionController {
             }
         }
 
-        let _timer = self.metrics.sandboxed_execution_spawn_process.start_timer();
+        let adoption_start = Instant::now();
         if guard.len() > self.max_sandbox_count {
             let to_evict = self.max_sandbox_count * SANDBOX_PROCESS_EVICTION_PERCENT / 100;
             let max_active_sandboxes = self.max_sandbox_count.saturating_sub(to_evict);
@@ -1231,24 +1294,33 @@ impl SandboxedExecutionController {
             );
         }
 
-        // No sandbox process found for this canister. Start a new one and register it.
-        let reg = Arc::new(ActiveExecutionStateRegistry::new());
-        let controller_service = ControllerServiceImpl::new(Arc::clone(&reg), self.logger.clone());
-
-        let (sandbox_service, pid) = create_sandbox_process(
-            controller_service,
-            &*self.launcher_service,
-            canister_id,
-            self.sandbox_exec_argv.clone(),
-        )
-        .unwrap();
-
-        let sandbox_process = Arc::new(SandboxProcess {
-            execution_states: reg,
-            sandbox_service,
-            pid,
-            history: SandboxProcessRequestHistory::new(),
-        });
+        // No sandbox process found for this canister. Adopt an idle one from
+        // the pool or start a new one and register it.
+        let (sandbox_process, pool_result) = match self.sandbox_process_pool.take() {
+            Some(sandbox_process) => (sandbox_process, SANDBOX_POOL_HIT),
+            None => {
+                let _timer = self.metrics.sandboxed_execution_spawn_process.start_timer();
+                let sandbox_process = spawn_sandbox_process(
+                    &*self.launcher_service,
+                    self.sandbox_exec_argv.clone(),
+                    Some(canister_id),
+                    &self.logger,
+                )
+                .expect("Failed to start sandbox process");
+                (sandbox_process, SANDBOX_POOL_MISS)
+            }
+        };
+        self.metrics
+            .sandboxed_execution_sandbox_pool_lookups
+            .with_label_values(&[pool_result])
+            .inc();
+        self.metrics
+            .sandboxed_execution_sandbox_pool_adoption_duration
+            .with_label_values(&[pool_result])
+            .observe(adoption_start.elapsed().as_secs_f64());
+        self.metrics
+            .sandboxed_execution_sandbox_pool_idle_processes
+            .set(self.sandbox_process_pool.len() as i64);
 
         let now = std::time::Instant::now();
         let backend = Backend::Active {
@@ -1566,6 +1638,32 @@ fn wrap_remote_memory(
     SandboxMemoryHandle::new(Arc::new(opened_memory))
 }
 
+/// Spawns a new sandbox process. If no canister is given, the process is
+/// spawned ahead of time to be adopted by a canister later.
+fn spawn_sandbox_process(
+    launcher_service: &dyn LauncherService,
+    sandbox_exec_argv: Vec<String>,
+    canister_id: Option<CanisterId>,
+    logger: &ReplicaLogger,
+) -> std::io::Result<Arc<SandboxProcess>> {
+    let reg = Arc::new(ActiveExecutionStateRegistry::new());
+    let controller_service = ControllerServiceImpl::new(Arc::clone(&reg), logger.clone());
+
+    let (sandbox_service, pid) = create_sandbox_process(
+        controller_service,
+        launcher_service,
+        canister_id,
+        sandbox_exec_argv,
+    )?;
+
+    Ok(Arc::new(SandboxProcess {
+        execution_states: reg,
+        sandbox_service,
+        pid,
+        history: SandboxProcessRequestHistory::new(),
+    }))
+}
+
 // Evicts some sandbox process backends according to the heuristics of the
 // `sandbox_process_eviction::evict()` function. See the comments of that
 // function for the explanation of the threshold parameters.
@@ -1707,22 +1805,21 @@ impl ControllerLauncherService for ExitWatcher {
         req: protocol::ctllaunchersvc::SandboxExitedRequest,
     ) -> ic_canister_sandbox_common::rpc::Call<protocol::ctllaunchersvc::SandboxExitedReply> {
         let guard = self.backends.lock().unwrap();
-        let sandbox_process = match guard.get(&req.canister_id).unwrap_or_else(|| {
-            panic!(
-                "Sandbox exited for unrecognized canister id {}",
-                req.canister_id,
-            )
-        }) {
-            Backend::Active {
-                sandbox_process, ..
-            } => sandbox_process,
-            Backend::Evicted { .. } | Backend::Empty => {
-                return rpc::Call::new_resolved(Ok(protocol::ctllaunchersvc::SandboxExitedReply));
-            }
-        };
-        sandbox_process
-            .history
-            .replay(&self.logger, req.canister_id, sandbox_process.pid);
+        // Idle sandbox processes from the pool and evicted ones have no
+        // history worth printing, so only active backends are considered.
+        let active_backend = guard
+            .iter()
+            .find_map(|(canister_id, backend)| match backend {
+                Backend::Active {
+                    sandbox_process, ..
+                } if sandbox_process.pid == req.pid => Some((canister_id, sandbox_process)),
+                Backend::Active { .. } | Backend::Evicted { .. } | Backend::Empty => None,
+            });
+        if let Some((canister_id, sandbox_process)) = active_backend {
+            sandbox_process
+                .history
+                .replay(&self.logger, *canister_id, sandbox_process.pid);
+        }
         rpc::Call::new_resolved(Ok(protocol::ctllaunchersvc::SandboxExitedReply))
     }
 }
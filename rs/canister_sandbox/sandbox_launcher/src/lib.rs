@@ -71,7 +71,9 @@ pub fn run_launcher(socket: std::os::unix::net::UnixStream) {
 }
 
 pub struct LauncherServer {
-    pid_to_canister_id: Arc<Mutex<HashMap<Pid, CanisterId>>>,
+    /// The canister id is `None` for the processes that are spawned ahead of
+    /// time to be adopted by a canister later.
+    pid_to_canister_id: Arc<Mutex<HashMap<Pid, Option<CanisterId>>>>,
     has_children: Arc<Condvar>,
 }
 
@@ -108,18 +110,19 @@ impl LauncherServer {
                             .pid()
                             .expect("WaitStatus is not StillAlive so it should have a pid");
                         let mut canister_ids = watcher_canister_id_map.lock().unwrap();
-                        let canister_id = canister_ids.remove(&pid);
+                        let canister_id = canister_ids.remove(&pid).flatten();
                         eprintln!(
                             "Sandbox pid {} for canister {:?} exited unexpectedly with status {:?}",
                             pid, canister_id, status
                         );
-                        // If we have a canister id, tell the replica process to print its history.
-                        if let Some(canister_id) = canister_id {
-                            controller
-                                .sandbox_exited(SandboxExitedRequest { canister_id })
-                                .sync()
-                                .unwrap();
-                        }
+                        // Tell the replica process to print the history of the
+                        // process. The replica knows which canister adopted it.
+                        controller
+                            .sandbox_exited(SandboxExitedRequest {
+                                pid: pid.as_raw() as u32,
+                            })
+                            .sync()
+                            .unwrap();
                         panic!("Launcher detected sandbox exit");
                     }
                 },
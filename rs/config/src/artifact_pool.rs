@@ -14,6 +14,11 @@ const PERSISTENT_POOL_VALIDATED_PURGE_INTERVAL: u64 = 5000;
 /// systems).
 pub const BACKUP_GROUP_SIZE: u64 = 10000;
 
+/// The number of consecutive heights packed into a single archive when the
+/// backup is stored in [`BackupFormat::Archive`]. Must divide
+/// [`BACKUP_GROUP_SIZE`], so that no archive spans two groups.
+pub const BACKUP_ARCHIVE_HEIGHTS: u64 = 100;
+
 /// External configuration for artifact pools meant to be used by replica's
 /// config file.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -58,6 +63,23 @@ pub struct BackupConfig {
     pub retention_time_secs: u64,
     /// Time interval between purges.
     pub purging_interval_secs: u64,
+    /// The format in which the backup artifacts are stored on disk.
+    #[serde(default)]
+    pub format: BackupFormat,
+}
+
+/// The on-disk format of the consensus artifact backup.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupFormat {
+    /// Every artifact is stored in a separate protobuf file, in a directory per
+    /// height.
+    #[default]
+    Files,
+    /// Artifacts are first stored as separate files. Once a range of
+    /// [`BACKUP_ARCHIVE_HEIGHTS`] heights is complete, its files are packed into
+    /// a single compressed archive.
+    Archive,
 }
 
 /// The configuration for the ingress and consensus artifact pools, both the
@@ -116,7 +116,13 @@ pub const SAMPLE_CONFIG: &str = r#"
             // How long the backup artifact stay on the disk before they get purged.
             retention_time_secs: 3600,
             // How often we purge.
-            purging_interval_secs: 3600
+            purging_interval_secs: 3600,
+            // The on-disk format of the backup. Either "files" (default), which stores
+            // every artifact in a separate file, or "archive", which packs ranges of
+            // heights into compressed archives.
+            // Alternatives:
+            // - EXAMPLE: format: "archive",
+            format: "files"
         }
     },
     // ============================================
@@ -51,6 +51,11 @@ pub(crate) const DEFAULT_MAX_SANDBOX_COUNT: usize = 2_000;
 /// duration and sandbox process eviction is activated.
 pub(crate) const DEFAULT_MAX_SANDBOX_IDLE_TIME: Duration = Duration::from_secs(30 * 60);
 
+/// The number of idle sandbox processes that are spawned ahead of time so that
+/// a canister without a sandbox process can adopt one without waiting for a
+/// process to start.
+pub(crate) const DEFAULT_SANDBOX_POOL_SIZE: usize = 4;
+
 #[allow(non_upper_case_globals)]
 const KiB: u64 = 1024;
 #[allow(non_upper_case_globals)]
@@ -96,6 +101,31 @@ pub enum MeteringType {
     None,
 }
 
+/// The mechanism used to track the accessed and dirty pages of the Wasm
+/// memories.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MemoryTrackerBackend {
+    /// Map the memory `PROT_NONE` and handle `SIGSEGV`.
+    Sigsegv,
+    /// Use `userfaultfd` in write-protect mode. Only available on Linux, other
+    /// platforms fall back to `Sigsegv`.
+    Userfaultfd,
+}
+
+/// Whether sandbox processes restrict the system calls they can make with a
+/// seccomp-bpf filter once they are initialized.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SandboxSyscallFilter {
+    /// No filter is installed.
+    Disabled,
+    /// System calls that are not allowed are logged by the kernel, but still
+    /// executed. Used to test the filter without killing the sandbox process.
+    Report,
+    /// The sandbox process is killed if it makes a system call that is not
+    /// allowed.
+    Enforce,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Config {
     pub max_wasm_stack_size: usize,
@@ -136,6 +166,9 @@ pub struct Config {
     /// Instruction counting strategy
     pub metering_type: MeteringType,
 
+    /// The mechanism used to track memory accesses.
+    pub memory_tracker_backend: MemoryTrackerBackend,
+
     // Maximum number of stable memory dirty pages that a single message execution
     // is allowed to produce.
     pub stable_memory_dirty_page_limit: NumPages,
@@ -156,6 +189,15 @@ pub struct Config {
     /// duration and sandbox process eviction is activated.
     pub max_sandbox_idle_time: Duration,
 
+    /// The number of pre-spawned idle sandbox processes that are kept ready to
+    /// be adopted by canisters that do not have a sandbox process yet. These
+    /// processes are not subject to sandbox process eviction.
+    pub sandbox_pool_size: usize,
+
+    /// The seccomp-bpf filter installed by sandbox processes. Only supported
+    /// on Linux on x86_64, other platforms never install a filter.
+    pub sandbox_syscall_filter: SandboxSyscallFilter,
+
     /// The type of the local subnet. The default value here should be replaced
     /// with the correct value at runtime when the hypervisor is created.
     pub subnet_type: SubnetType,
@@ -186,11 +228,14 @@ impl Config {
             num_rayon_compilation_threads: DEFAULT_WASMTIME_RAYON_COMPILATION_THREADS,
             feature_flags: FeatureFlags::const_default(),
             metering_type: MeteringType::New,
+            memory_tracker_backend: MemoryTrackerBackend::Sigsegv,
             stable_memory_dirty_page_limit: NumPages::new(STABLE_MEMORY_DIRTY_PAGE_LIMIT),
             stable_memory_accessed_page_limit: NumPages::new(STABLE_MEMORY_ACCESSED_PAGE_LIMIT),
             min_sandbox_count: DEFAULT_MIN_SANDBOX_COUNT,
             max_sandbox_count: DEFAULT_MAX_SANDBOX_COUNT,
             max_sandbox_idle_time: DEFAULT_MAX_SANDBOX_IDLE_TIME,
+            sandbox_pool_size: DEFAULT_SANDBOX_POOL_SIZE,
+            sandbox_syscall_filter: SandboxSyscallFilter::Enforce,
             subnet_type: SubnetType::Application,
             dirty_page_overhead: NumInstructions::new(0),
             trace_execution: FlagStatus::Disabled,
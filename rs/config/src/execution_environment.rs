@@ -5,7 +5,7 @@ use ic_types::{
     Cycles, NumBytes, NumInstructions, MAX_STABLE_MEMORY_IN_BYTES, MAX_WASM_MEMORY_IN_BYTES,
 };
 use serde::{Deserialize, Serialize};
-use std::{str::FromStr, time::Duration};
+use std::{path::PathBuf, str::FromStr, time::Duration};
 
 const MIB: u64 = 1024 * 1024;
 const GIB: u64 = MIB * 1024;
@@ -116,6 +116,10 @@ const BITCOIN_MAINNET_SOFT_LAUNCH_CANISTER_ID: &str = "gsvzx-syaaa-aaaan-aaabq-c
 /// The capacity of the Wasm compilation cache.
 pub const MAX_COMPILATION_CACHE_SIZE: NumBytes = NumBytes::new(10 * GIB);
 
+/// The maximum size of the compiled Wasm modules that are kept on disk so that
+/// they survive replica restarts and upgrades.
+pub const MAX_COMPILATION_CACHE_DISK_SIZE: NumBytes = NumBytes::new(20 * GIB);
+
 /// Maximum number of controllers allowed in a request (specified in the interface spec).
 pub const MAX_ALLOWED_CONTROLLERS_COUNT: usize = 10;
 
@@ -227,6 +231,13 @@ pub struct Config {
     /// The capacity of the Wasm compilation cache.
     pub max_compilation_cache_size: NumBytes,
 
+    /// The directory in which the Wasm compilation cache persists compiled
+    /// modules. If not set, compiled modules are only kept in memory.
+    pub compilation_cache_dir: Option<PathBuf>,
+
+    /// The capacity of the on-disk tier of the Wasm compilation cache.
+    pub max_compilation_cache_disk_size: NumBytes,
+
     /// Indicate whether query stats should be collected or not.
     pub query_stats_aggregation: FlagStatus,
 
@@ -298,6 +309,8 @@ impl Default for Config {
             query_caching: FlagStatus::Enabled,
             query_cache_capacity: QUERY_CACHE_CAPACITY,
             max_compilation_cache_size: MAX_COMPILATION_CACHE_SIZE,
+            compilation_cache_dir: None,
+            max_compilation_cache_disk_size: MAX_COMPILATION_CACHE_DISK_SIZE,
             query_stats_aggregation: FlagStatus::Disabled,
             wasm_chunk_store: FlagStatus::Disabled,
             stop_canister_timeout_duration: STOP_CANISTER_TIMEOUT_DURATION,
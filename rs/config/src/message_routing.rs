@@ -4,11 +4,17 @@ use serde::{Deserialize, Serialize};
 #[serde(default)]
 /// Message Routing replica config.
 ///
-/// This configuration is only needed so the DC-operator can set the Xnet-port
-/// upon registration of the node.
+/// This configuration is needed so the DC-operator can set the Xnet-port
+/// upon registration of the node; and to optionally serve certified stream
+/// slices to external verifiers.
 pub struct Config {
     pub xnet_ip_addr: String,
     pub xnet_port: u16,
+    /// Port on `xnet_ip_addr` on which certified stream slices are served to
+    /// external verifiers over plain HTTP. Disabled if `None`.
+    pub xnet_public_port: Option<u16>,
+    /// The number of requests per second served on `xnet_public_port`.
+    pub xnet_public_max_requests_per_second: u32,
 }
 
 impl Default for Config {
@@ -16,6 +22,8 @@ impl Default for Config {
         Self {
             xnet_ip_addr: "127.0.0.1".to_string(),
             xnet_port: 2497,
+            xnet_public_port: None,
+            xnet_public_max_requests_per_second: 10,
         }
     }
 }
@@ -12,12 +12,19 @@ use serde::{Deserialize, Serialize};
 pub struct Config {
     /// The duration to
     pub poll_delay_duration_ms: u64,
+    /// If set, the replicator watches the NNS for new registry versions in
+    /// between two polls, querying the latest version at this interval. As soon
+    /// as a new version is observed, the certified changes are fetched
+    /// without waiting for the rest of the poll delay.
+    #[serde(default)]
+    pub watch_interval_ms: Option<u64>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             poll_delay_duration_ms: 5000,
+            watch_interval_ms: None,
         }
     }
 }
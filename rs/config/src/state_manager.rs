@@ -31,6 +31,12 @@ impl Config {
     pub fn page_deltas_dirname(&self) -> String {
         "page_deltas".to_string()
     }
+
+    // The compilation_cache directory stores the compiled Wasm modules that
+    // survive restarts and is a child of the state directory.
+    pub fn compilation_cache_dirname(&self) -> String {
+        "compilation_cache".to_string()
+    }
 }
 
 fn file_backed_memory_allocator_default() -> FlagStatus {
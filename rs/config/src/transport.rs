@@ -14,6 +14,18 @@ pub struct TransportConfig {
     /// Transport creates 'max_streams' logical streams/channels between two peers.
     /// Channel ids should be within [0..max_streams).
     pub max_streams: usize,
+
+    /// Share of the send bandwidth of a quic connection given to consensus
+    /// artifacts while state sync competes for the same connection.
+    pub quic_consensus_weight: u32,
+
+    /// Share of the send bandwidth of a quic connection given to state sync
+    /// chunks while consensus competes for the same connection.
+    pub quic_state_sync_weight: u32,
+
+    /// Port on localhost on which the statistics of all active quic connections
+    /// are served. The endpoint is not started if the port is not set.
+    pub quic_debug_port: Option<u16>,
 }
 
 impl Default for TransportConfig {
@@ -23,6 +35,9 @@ impl Default for TransportConfig {
             node_ip: String::default(),
             listening_port: u16::default(),
             max_streams: 1,
+            quic_consensus_weight: 4,
+            quic_state_sync_weight: 1,
+            quic_debug_port: None,
         }
     }
 }
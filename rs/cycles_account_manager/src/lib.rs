@@ -1154,6 +1154,8 @@ mod tests {
                 None,
                 Some(default_freezing_limit),
                 None,
+                None,
+                None,
             ),
             sender_canister_version: None, // ingress messages are not supposed to set this field
         };
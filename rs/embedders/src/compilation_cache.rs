@@ -1,21 +1,83 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
 use crate::SerializedModule;
+use ic_config::embedders::Config as EmbeddersConfig;
 use ic_interfaces::execution_environment::HypervisorResult;
+use ic_logger::{warn, ReplicaLogger};
+use ic_metrics::MetricsRegistry;
 use ic_types::NumBytes;
 use ic_utils_lru_cache::LruCache;
 use ic_wasm_types::{CanisterModule, WasmHash};
 
+mod disk;
+
+use disk::{DiskCache, DiskCacheWriter};
+
 /// Stores the serialized modules of wasm code that has already been compiled so
 /// that it can be used again without recompiling.
+///
+/// Optionally the successfully compiled modules are also persisted on disk, so
+/// that they survive a restart of the replica. The modules are written on a
+/// background thread.
 pub struct CompilationCache {
     cache: Mutex<LruCache<WasmHash, HypervisorResult<Arc<SerializedModule>>>>,
+    disk_cache: Option<(Arc<DiskCache>, DiskCacheWriter)>,
 }
 
 impl CompilationCache {
     pub fn new(capacity: NumBytes) -> Self {
         Self {
             cache: Mutex::new(LruCache::new(capacity)),
+            disk_cache: None,
+        }
+    }
+
+    /// Creates a cache that additionally persists compiled modules in `dir`
+    /// and uses at most `disk_capacity` bytes there. Entries that were
+    /// compiled with a different `embedder_config`, wasmtime version or
+    /// instrumentation version are removed.
+    ///
+    /// If the directory cannot be used, the cache falls back to keeping the
+    /// modules in memory only.
+    pub fn new_with_disk_cache(
+        capacity: NumBytes,
+        dir: &Path,
+        disk_capacity: NumBytes,
+        embedder_config: &EmbeddersConfig,
+        log: ReplicaLogger,
+        metrics_registry: &MetricsRegistry,
+    ) -> Self {
+        let disk_cache = DiskCache::open(
+            dir,
+            disk_capacity,
+            embedder_config,
+            log.clone(),
+            metrics_registry,
+        )
+        .and_then(|disk_cache| {
+            let disk_cache = Arc::new(disk_cache);
+            let writer = DiskCacheWriter::spawn(Arc::clone(&disk_cache))
+                .map_err(|err| format!("failed to start the writer: {}", err))?;
+            Ok((disk_cache, writer))
+        });
+        let disk_cache = match disk_cache {
+            Ok(disk_cache) => Some(disk_cache),
+            Err(err) => {
+                warn!(
+                    log,
+                    "Failed to open the compilation cache in {}: {}",
+                    dir.display(),
+                    err
+                );
+                None
+            }
+        };
+        Self {
+            cache: Mutex::new(LruCache::new(capacity)),
+            disk_cache,
         }
     }
 
@@ -24,23 +86,41 @@ impl CompilationCache {
         canister_module: &CanisterModule,
         serialized_module: HypervisorResult<Arc<SerializedModule>>,
     ) {
+        let wasm_hash = WasmHash::from(canister_module);
+        if let (Some((_, writer)), Ok(serialized_module)) = (&self.disk_cache, &serialized_module) {
+            writer.insert(wasm_hash.clone(), Arc::clone(serialized_module));
+        }
         self.cache
             .lock()
             .unwrap()
-            .push(WasmHash::from(canister_module), serialized_module);
+            .push(wasm_hash, serialized_module);
     }
 
     pub fn get(
         &self,
         canister_module: &CanisterModule,
     ) -> Option<HypervisorResult<Arc<SerializedModule>>> {
-        self.cache
+        let wasm_hash = WasmHash::from(canister_module);
+        if let Some(result) = self
+            .cache
             .lock()
             .unwrap()
-            .get(&WasmHash::from(canister_module))
+            .get(&wasm_hash)
             .map(|o| o.as_ref().map(Arc::clone).map_err(|e| e.clone()))
+        {
+            return Some(result);
+        }
+
+        let (disk_cache, _) = self.disk_cache.as_ref()?;
+        let serialized_module = disk_cache.get(&wasm_hash)?;
+        self.cache
+            .lock()
+            .unwrap()
+            .push(wasm_hash, Ok(Arc::clone(&serialized_module)));
+        Some(Ok(serialized_module))
     }
 
+    /// Clears the in-memory tier of the cache.
     #[doc(hidden)]
     pub fn clear_for_testing(&self) {
         self.cache.lock().unwrap().clear()
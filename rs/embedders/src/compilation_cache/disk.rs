@@ -0,0 +1,561 @@
+//! The on-disk tier of the [`CompilationCache`](super::CompilationCache).
+//!
+//! Compiled modules are persisted so that canisters don't have to be
+//! recompiled after a replica restart or upgrade. The cache directory contains
+//! a single subdirectory named after the version key, which identifies
+//! everything besides the Wasm binary that the compiled module depends on:
+//! the embedder configuration, the wasmtime version and configuration, the
+//! instrumentation version, and the replica version and binary hash. The latter
+//! guard against changes of the instrumentation or the encoding of the modules
+//! that were not accompanied by a bump of the corresponding version. Subdirectories of other versions are removed when
+//! the cache is opened, so a change of any of them invalidates all entries.
+//!
+//! Every entry is stored in a file named after the hex-encoded Wasm hash. The
+//! file starts with a SHA-256 checksum of the Wasm hash and the encoded module
+//! that follows it, which is verified before the module is returned. An entry that
+//! fails the verification, e.g. because the replica crashed while writing it,
+//! is removed and the module is compiled again.
+//!
+//! The total size of the entries is bounded; the least recently used entries
+//! are evicted first.
+
+use std::{
+    fs,
+    hash::Hash,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{mpsc::SyncSender, Arc, Mutex},
+    thread::JoinHandle,
+    time::SystemTime,
+};
+
+use ic_config::embedders::Config as EmbeddersConfig;
+use ic_crypto_sha2::Sha256;
+use ic_logger::{info, warn, ReplicaLogger};
+use ic_metrics::MetricsRegistry;
+use ic_types::{replica_version::REPLICA_BINARY_HASH, CountBytes, NumBytes, ReplicaVersion};
+use ic_utils_lru_cache::LruCache;
+use ic_wasm_types::WasmHash;
+use prometheus::{IntCounter, IntCounterVec, IntGauge};
+
+use crate::{
+    wasm_utils::instrumentation::INSTRUMENTATION_VERSION, SerializedModule, WasmtimeEmbedder,
+};
+
+/// Must be increased whenever the encoding of the entries changes.
+const FORMAT_VERSION: u32 = 1;
+const ENTRY_EXTENSION: &str = "module";
+const TMP_EXTENSION: &str = "tmp";
+const CHECKSUM_LEN: usize = 32;
+/// The number of modules that can be queued to be persisted.
+const MAX_PENDING_WRITES: usize = 100;
+
+const LOOKUP_HIT: &str = "hit";
+const LOOKUP_MISS: &str = "miss";
+const LOOKUP_INVALID: &str = "invalid";
+
+struct DiskCacheMetrics {
+    lookups: IntCounterVec,
+    evictions: IntCounter,
+    write_errors: IntCounter,
+    dropped_writes: IntCounter,
+    size: IntGauge,
+}
+
+impl DiskCacheMetrics {
+    fn new(metrics_registry: &MetricsRegistry) -> Self {
+        Self {
+            lookups: metrics_registry.int_counter_vec(
+                "execution_compilation_disk_cache_lookups_total",
+                "The number of lookups in the on-disk compilation cache by result: \
+                 hit, miss or invalid (the entry failed the integrity check).",
+                &["status"],
+            ),
+            evictions: metrics_registry.int_counter(
+                "execution_compilation_disk_cache_evictions_total",
+                "The number of entries evicted from the on-disk compilation cache.",
+            ),
+            write_errors: metrics_registry.int_counter(
+                "execution_compilation_disk_cache_write_errors_total",
+                "The number of compiled modules that could not be persisted.",
+            ),
+            dropped_writes: metrics_registry.int_counter(
+                "execution_compilation_disk_cache_dropped_writes_total",
+                "The number of compiled modules that were not persisted because \
+                 too many writes were pending.",
+            ),
+            size: metrics_registry.int_gauge(
+                "execution_compilation_disk_cache_size_bytes",
+                "The total size of the entries in the on-disk compilation cache.",
+            ),
+        }
+    }
+}
+
+/// The size of an entry file.
+struct EntrySize(usize);
+
+impl CountBytes for EntrySize {
+    fn count_bytes(&self) -> usize {
+        self.0
+    }
+}
+
+pub(crate) struct DiskCache {
+    dir: PathBuf,
+    /// The entries that are on disk. Files of registered entries are only
+    /// removed while holding the lock.
+    entries: Mutex<LruCache<WasmHash, EntrySize>>,
+    log: ReplicaLogger,
+    metrics: DiskCacheMetrics,
+}
+
+impl DiskCache {
+    /// Opens the cache in `root`, removing the entries of other versions and
+    /// evicting entries until the size of the cache is below `capacity`.
+    pub(crate) fn open(
+        root: &Path,
+        capacity: NumBytes,
+        embedder_config: &EmbeddersConfig,
+        log: ReplicaLogger,
+        metrics_registry: &MetricsRegistry,
+    ) -> Result<Self, String> {
+        let version = version_key(embedder_config)?;
+        let dir = root.join(&version);
+        fs::create_dir_all(&dir)
+            .map_err(|err| format!("failed to create {}: {}", dir.display(), err))?;
+        remove_other_versions(root, &version, &log);
+
+        let cache = Self {
+            dir,
+            entries: Mutex::new(LruCache::new(capacity)),
+            log,
+            metrics: DiskCacheMetrics::new(metrics_registry),
+        };
+        cache.load_entries()?;
+        Ok(cache)
+    }
+
+    /// Registers the entries on disk with the least recently modified first,
+    /// so that they are evicted first.
+    fn load_entries(&self) -> Result<(), String> {
+        let read_dir = fs::read_dir(&self.dir)
+            .map_err(|err| format!("failed to list {}: {}", self.dir.display(), err))?;
+        let mut found = vec![];
+        for entry in read_dir.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            match (parse_entry_path(&path), entry.metadata()) {
+                (Some(wasm_hash), Ok(metadata)) if metadata.is_file() => {
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    found.push((modified, wasm_hash, metadata.len() as usize));
+                }
+                // Leftovers of interrupted writes and unknown files.
+                _ => self.remove(&path),
+            }
+        }
+        found.sort_by_key(|(modified, _, _)| *modified);
+
+        let mut entries = self.entries.lock().unwrap();
+        for (_, wasm_hash, size) in found {
+            self.push(&mut entries, wasm_hash, size);
+        }
+        info!(
+            self.log,
+            "Opened the compilation cache in {} with {} entries",
+            self.dir.display(),
+            entries.len()
+        );
+        Ok(())
+    }
+
+    /// Reads and verifies the entry without holding the lock, so that lookups
+    /// of other modules and inserts are not blocked by the disk.
+    pub(crate) fn get(&self, wasm_hash: &WasmHash) -> Option<Arc<SerializedModule>> {
+        if self.entries.lock().unwrap().get(wasm_hash).is_none() {
+            self.metrics.lookups.with_label_values(&[LOOKUP_MISS]).inc();
+            return None;
+        }
+
+        let path = self.entry_path(wasm_hash);
+        match fs::read(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| decode_entry(wasm_hash, &bytes))
+        {
+            Ok(serialized_module) => {
+                self.metrics.lookups.with_label_values(&[LOOKUP_HIT]).inc();
+                Some(Arc::new(serialized_module))
+            }
+            Err(err) => {
+                warn!(
+                    self.log,
+                    "Removing invalid compilation cache entry {}: {}",
+                    path.display(),
+                    err
+                );
+                self.metrics
+                    .lookups
+                    .with_label_values(&[LOOKUP_INVALID])
+                    .inc();
+                let mut entries = self.entries.lock().unwrap();
+                // The entry may have been evicted in the meantime.
+                if entries.pop(wasm_hash).is_some() {
+                    self.remove(&path);
+                    self.update_size(&entries);
+                }
+                None
+            }
+        }
+    }
+
+    /// Persists the module. The file is written without holding the lock, so
+    /// inserts must not run concurrently, see [`DiskCacheWriter`].
+    pub(crate) fn insert(&self, wasm_hash: &WasmHash, serialized_module: &SerializedModule) {
+        // The module compiled from the same Wasm binary with the same version
+        // key is the same, so there is no need to write it again.
+        if self.entries.lock().unwrap().get(wasm_hash).is_some() {
+            return;
+        }
+        match self.write_entry(wasm_hash, serialized_module) {
+            Ok(size) => {
+                let mut entries = self.entries.lock().unwrap();
+                self.push(&mut entries, wasm_hash.clone(), size)
+            }
+            Err(err) => {
+                warn!(
+                    self.log,
+                    "Failed to persist the compiled module {}: {}",
+                    hex::encode(wasm_hash.to_slice()),
+                    err
+                );
+                self.metrics.write_errors.inc();
+            }
+        }
+    }
+
+    /// Writes the entry under a temporary name first, so that a crash never
+    /// leaves a truncated entry behind under the final name.
+    fn write_entry(
+        &self,
+        wasm_hash: &WasmHash,
+        serialized_module: &SerializedModule,
+    ) -> Result<usize, String> {
+        let payload = bincode::serialize(serialized_module).map_err(|err| err.to_string())?;
+        let path = self.entry_path(wasm_hash);
+        let tmp_path = path.with_extension(TMP_EXTENSION);
+        let result = fs::File::create(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(&checksum(wasm_hash, &payload))?;
+                file.write_all(&payload)
+            })
+            .and_then(|()| fs::rename(&tmp_path, &path));
+        if let Err(err) = result {
+            self.remove(&tmp_path);
+            return Err(err.to_string());
+        }
+        Ok(CHECKSUM_LEN + payload.len())
+    }
+
+    /// Registers a new entry and removes the files of the evicted entries.
+    fn push(&self, entries: &mut LruCache<WasmHash, EntrySize>, wasm_hash: WasmHash, size: usize) {
+        for (evicted, _) in entries.push(wasm_hash, EntrySize(size)) {
+            self.remove(&self.entry_path(&evicted));
+            self.metrics.evictions.inc();
+        }
+        self.update_size(entries);
+    }
+
+    fn update_size(&self, entries: &LruCache<WasmHash, EntrySize>) {
+        self.metrics.size.set(entries.count_bytes() as i64);
+    }
+
+    fn remove(&self, path: &Path) {
+        if let Err(err) = fs::remove_file(path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                warn!(
+                    self.log,
+                    "Failed to remove {} from the compilation cache: {}",
+                    path.display(),
+                    err
+                );
+            }
+        }
+    }
+
+    fn entry_path(&self, wasm_hash: &WasmHash) -> PathBuf {
+        self.dir
+            .join(hex::encode(wasm_hash.to_slice()))
+            .with_extension(ENTRY_EXTENSION)
+    }
+}
+
+/// Persists compiled modules on a background thread, so that execution doesn't
+/// wait for the disk. The pending writes are completed when the writer is
+/// dropped.
+pub(crate) struct DiskCacheWriter {
+    cache: Arc<DiskCache>,
+    sender: Option<SyncSender<(WasmHash, Arc<SerializedModule>)>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DiskCacheWriter {
+    pub(crate) fn spawn(cache: Arc<DiskCache>) -> std::io::Result<Self> {
+        let (sender, receiver) =
+            std::sync::mpsc::sync_channel::<(WasmHash, Arc<SerializedModule>)>(MAX_PENDING_WRITES);
+        let thread_cache = Arc::clone(&cache);
+        let thread = std::thread::Builder::new()
+            .name("CompilationCacheWriter".to_string())
+            .spawn(move || {
+                for (wasm_hash, serialized_module) in receiver {
+                    thread_cache.insert(&wasm_hash, &serialized_module);
+                }
+            })?;
+        Ok(Self {
+            cache,
+            sender: Some(sender),
+            thread: Some(thread),
+        })
+    }
+
+    /// Queues the module to be persisted. The module is dropped if too many
+    /// writes are pending.
+    pub(crate) fn insert(&self, wasm_hash: WasmHash, serialized_module: Arc<SerializedModule>) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        if sender.try_send((wasm_hash, serialized_module)).is_err() {
+            self.cache.metrics.dropped_writes.inc();
+        }
+    }
+}
+
+impl Drop for DiskCacheWriter {
+    fn drop(&mut self) {
+        // Closing the channel stops the thread after the pending writes.
+        self.sender.take();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+/// Computes the hex-encoded digest of everything the compiled module of a Wasm
+/// binary depends on.
+fn version_key(embedder_config: &EmbeddersConfig) -> Result<String, String> {
+    let engine = wasmtime::Engine::new(&WasmtimeEmbedder::wasmtime_execution_config(
+        embedder_config,
+    ))
+    .map_err(|err| format!("failed to create the wasmtime engine: {}", err))?;
+    let config = bincode::serialize(embedder_config).map_err(|err| err.to_string())?;
+
+    let mut hasher = Sha256::new();
+    hasher.write(&FORMAT_VERSION.to_le_bytes());
+    hasher.write(&INSTRUMENTATION_VERSION.to_le_bytes());
+    // Changes of the replica are not always reflected in the versions above.
+    hasher.write(ReplicaVersion::default().as_ref().as_bytes());
+    if let Some(binary_hash) = REPLICA_BINARY_HASH.get() {
+        hasher.write(binary_hash.as_bytes());
+    }
+    // Covers the wasmtime version and the wasmtime configuration.
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+    hasher.write(&config);
+    Ok(hex::encode(hasher.finish()))
+}
+
+/// Removes everything in `root` except the directory of the current version.
+fn remove_other_versions(root: &Path, version: &str, log: &ReplicaLogger) {
+    let Ok(read_dir) = fs::read_dir(root) else {
+        return;
+    };
+    for entry in read_dir.filter_map(|entry| entry.ok()) {
+        if entry.file_name() == version {
+            continue;
+        }
+        let path = entry.path();
+        let result = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        match result {
+            Ok(()) => info!(log, "Removed outdated compilation cache {}", path.display()),
+            Err(err) => warn!(
+                log,
+                "Failed to remove outdated compilation cache {}: {}",
+                path.display(),
+                err
+            ),
+        }
+    }
+}
+
+fn parse_entry_path(path: &Path) -> Option<WasmHash> {
+    if path.extension()? != ENTRY_EXTENSION {
+        return None;
+    }
+    let bytes = hex::decode(path.file_stem()?.to_str()?).ok()?;
+    let bytes: [u8; CHECKSUM_LEN] = bytes.try_into().ok()?;
+    Some(WasmHash::from(bytes))
+}
+
+fn checksum(wasm_hash: &WasmHash, payload: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let mut hasher = Sha256::new();
+    hasher.write(&wasm_hash.to_slice());
+    hasher.write(payload);
+    hasher.finish()
+}
+
+fn decode_entry(wasm_hash: &WasmHash, bytes: &[u8]) -> Result<SerializedModule, String> {
+    if bytes.len() < CHECKSUM_LEN {
+        return Err(format!("truncated entry of {} bytes", bytes.len()));
+    }
+    let (expected, payload) = bytes.split_at(CHECKSUM_LEN);
+    if checksum(wasm_hash, payload) != expected {
+        return Err("checksum mismatch".to_string());
+    }
+    bincode::deserialize(payload).map_err(|err| format!("failed to decode: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm_utils::compile;
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_wasm_types::BinaryEncodedWasm;
+
+    const CAPACITY: NumBytes = NumBytes::new(1 << 30);
+
+    fn compile_module(wat: &str) -> (WasmHash, SerializedModule) {
+        let embedder = WasmtimeEmbedder::new(EmbeddersConfig::default(), no_op_logger());
+        let wasm = wat::parse_str(wat).unwrap();
+        let (_, result) = compile(&embedder, &BinaryEncodedWasm::new(wasm.clone()));
+        let wasm_hash = WasmHash::from(Sha256::hash(&wasm));
+        (wasm_hash, result.unwrap().1)
+    }
+
+    fn open(root: &Path, capacity: NumBytes, config: &EmbeddersConfig) -> DiskCache {
+        DiskCache::open(
+            root,
+            capacity,
+            config,
+            no_op_logger(),
+            &MetricsRegistry::new(),
+        )
+        .unwrap()
+    }
+
+    fn lookups(cache: &DiskCache, status: &str) -> u64 {
+        cache.metrics.lookups.with_label_values(&[status]).get()
+    }
+
+    #[test]
+    fn entries_survive_reopening() {
+        let root = tempfile::tempdir().unwrap();
+        let config = EmbeddersConfig::default();
+        let (wasm_hash, module) = compile_module("(module)");
+
+        open(root.path(), CAPACITY, &config).insert(&wasm_hash, &module);
+
+        let cache = open(root.path(), CAPACITY, &config);
+        let cached = cache.get(&wasm_hash).unwrap();
+        assert_eq!(cached.bytes.as_slice(), module.bytes.as_slice());
+        assert_eq!(cached.exported_functions, module.exported_functions);
+        assert_eq!(cached.compilation_cost, module.compilation_cost);
+        assert_eq!(lookups(&cache, LOOKUP_HIT), 1);
+    }
+
+    #[test]
+    fn changed_config_invalidates_entries() {
+        let root = tempfile::tempdir().unwrap();
+        let config = EmbeddersConfig::default();
+        let (wasm_hash, module) = compile_module("(module)");
+        open(root.path(), CAPACITY, &config).insert(&wasm_hash, &module);
+
+        let mut other_config = config.clone();
+        other_config.cost_to_compile_wasm_instruction =
+            (2 * config.cost_to_compile_wasm_instruction.get()).into();
+        let cache = open(root.path(), CAPACITY, &other_config);
+        assert!(cache.get(&wasm_hash).is_none());
+        assert_eq!(lookups(&cache, LOOKUP_MISS), 1);
+        // Only the directory of the current version is left.
+        assert_eq!(fs::read_dir(root.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn corrupted_entry_is_removed() {
+        let root = tempfile::tempdir().unwrap();
+        let config = EmbeddersConfig::default();
+        let (wasm_hash, module) = compile_module("(module)");
+        let cache = open(root.path(), CAPACITY, &config);
+        cache.insert(&wasm_hash, &module);
+
+        let path = cache.entry_path(&wasm_hash);
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+
+        assert!(cache.get(&wasm_hash).is_none());
+        assert_eq!(lookups(&cache, LOOKUP_INVALID), 1);
+        assert!(!path.exists());
+        assert!(cache.get(&wasm_hash).is_none());
+        assert_eq!(lookups(&cache, LOOKUP_MISS), 1);
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted() {
+        let root = tempfile::tempdir().unwrap();
+        let config = EmbeddersConfig::default();
+        let (hash_1, module_1) = compile_module("(module (func (export \"canister_query a\")))");
+        let (hash_2, module_2) = compile_module("(module (func (export \"canister_query b\")))");
+        let (hash_3, module_3) = compile_module("(module (func (export \"canister_query c\")))");
+
+        let cache = open(root.path(), CAPACITY, &config);
+        cache.insert(&hash_1, &module_1);
+        let entry_size = fs::metadata(cache.entry_path(&hash_1)).unwrap().len();
+
+        // Room for two entries of roughly the same size.
+        let cache = open(
+            root.path(),
+            NumBytes::new(2 * entry_size + entry_size / 2),
+            &config,
+        );
+        cache.insert(&hash_2, &module_2);
+        assert!(cache.get(&hash_1).is_some());
+        cache.insert(&hash_3, &module_3);
+
+        assert!(cache.get(&hash_1).is_some());
+        assert!(cache.get(&hash_2).is_none());
+        assert!(cache.get(&hash_3).is_some());
+        assert!(!cache.entry_path(&hash_2).exists());
+        assert_eq!(cache.metrics.evictions.get(), 1);
+    }
+
+    #[test]
+    fn writer_persists_modules_in_the_background() {
+        let root = tempfile::tempdir().unwrap();
+        let config = EmbeddersConfig::default();
+        let (wasm_hash, module) = compile_module("(module)");
+        let cache = Arc::new(open(root.path(), CAPACITY, &config));
+
+        let writer = DiskCacheWriter::spawn(Arc::clone(&cache)).unwrap();
+        writer.insert(wasm_hash.clone(), Arc::new(module));
+        // Dropping the writer completes the pending writes.
+        drop(writer);
+
+        assert!(cache.get(&wasm_hash).is_some());
+        assert_eq!(cache.metrics.dropped_writes.get(), 0);
+    }
+
+    #[test]
+    fn leftover_files_are_removed() {
+        let root = tempfile::tempdir().unwrap();
+        let config = EmbeddersConfig::default();
+        let cache = open(root.path(), CAPACITY, &config);
+        let tmp_path = cache.dir.join("0a0b").with_extension(TMP_EXTENSION);
+        fs::write(&tmp_path, b"partial").unwrap();
+
+        let cache = open(root.path(), CAPACITY, &config);
+        assert!(!tmp_path.exists());
+        assert_eq!(cache.entries.lock().unwrap().len(), 0);
+    }
+}
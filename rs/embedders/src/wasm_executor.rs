@@ -20,7 +20,7 @@ use crate::{
 };
 use ic_config::flag_status::FlagStatus;
 use ic_interfaces::execution_environment::{
-    HypervisorError, HypervisorResult, InstanceStats, OutOfInstructionsHandler,
+    HypervisorError, HypervisorResult, InstanceStats, OutOfInstructionsHandler, StateReads,
     SubnetAvailableMemory, SystemApi, WasmExecutionOutput,
 };
 use ic_logger::{warn, ReplicaLogger};
@@ -454,6 +454,7 @@ pub fn wasm_execution_error(
             allocated_bytes: NumBytes::from(0),
             allocated_message_bytes: NumBytes::from(0),
             instance_stats: InstanceStats::default(),
+            state_reads: StateReads::default(),
         },
         None,
     )
@@ -617,6 +618,7 @@ pub fn process(
                     allocated_bytes: NumBytes::from(0),
                     allocated_message_bytes: NumBytes::from(0),
                     instance_stats: InstanceStats::default(),
+                    state_reads: StateReads::default(),
                 },
                 None,
                 Err(system_api.unwrap()), // should be safe because we've passed Some(api) to new_instance
@@ -635,8 +637,42 @@ pub fn process(
     // Get the executed/remaining instructions for the message and the slice.
     let instruction_counter = instance.instruction_counter();
     let instance_stats = instance.get_stats();
+    // Only executions that do not modify the state can have their results
+    // cached, so the accessed pages are not collected for the others.
+    let wasm_memory_pages_read = match modification_tracking {
+        ModificationTracking::Ignore => instance.accessed_pages(CanisterMemoryType::Heap),
+        ModificationTracking::Track => None,
+    };
+    let stable_memory_pages_read = match (
+        modification_tracking,
+        embedder.config().feature_flags.wasm_native_stable_memory,
+    ) {
+        (ModificationTracking::Ignore, FlagStatus::Enabled) => {
+            instance.accessed_pages(CanisterMemoryType::Stable)
+        }
+        _ => None,
+    };
     //unwrap should not fail, because we have passed Some(system_api) to the instance above
     let system_api = instance.store_data_mut().system_api_mut().unwrap();
+    let mut state_reads = system_api.state_reads();
+    state_reads.wasm_memory_pages = wasm_memory_pages_read;
+    if embedder.config().feature_flags.wasm_native_stable_memory == FlagStatus::Enabled {
+        state_reads.stable_memory_pages = match (
+            state_reads.stable_memory_pages.take(),
+            stable_memory_pages_read,
+        ) {
+            (Some(first_access_pages), Some(accessed_pages)) => {
+                let mut pages: Vec<_> = first_access_pages
+                    .into_iter()
+                    .chain(accessed_pages)
+                    .collect();
+                pages.sort_unstable();
+                pages.dedup();
+                Some(pages)
+            }
+            _ => None,
+        };
+    }
     let slice_instruction_limit = system_api.slice_instruction_limit();
     // Capping at the limit to preserve the existing behaviour. It should be
     // possible to remove capping after ensuring that all callers can handle
@@ -725,6 +761,7 @@ pub fn process(
             allocated_bytes,
             allocated_message_bytes,
             instance_stats,
+            state_reads,
         },
         wasm_state_changes,
         Ok(instance),
@@ -136,6 +136,12 @@ use wasmparser::{
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
 
+/// The version of the instrumentation. Compiled modules persisted by a replica
+/// with a different version are never reused, so it must be increased whenever
+/// a change to the instrumentation, the instruction costs or the system API
+/// replacements changes the module compiled from the same Wasm binary.
+pub const INSTRUMENTATION_VERSION: u32 = 1;
+
 // The indices of injected function imports.
 pub(crate) enum InjectedImports {
     OutOfInstructions = 0,
@@ -18,7 +18,10 @@ use wasmtime::{
 };
 
 pub use host_memory::WasmtimeMemoryCreator;
-use ic_config::{embedders::Config as EmbeddersConfig, flag_status::FlagStatus};
+use ic_config::{
+    embedders::{Config as EmbeddersConfig, MemoryTrackerBackend},
+    flag_status::FlagStatus,
+};
 use ic_interfaces::execution_environment::{
     HypervisorError, HypervisorResult, InstanceStats, SystemApi, TrapCode,
 };
@@ -33,6 +36,8 @@ use ic_types::{
     CanisterId, NumInstructions, NumPages,
 };
 use ic_wasm_types::{BinaryEncodedWasm, WasmEngineError};
+#[cfg(target_os = "linux")]
+use memory_tracker::UserfaultfdHandler;
 use memory_tracker::{DirtyPageTracking, PageBitmap, SigsegvMemoryTracker};
 use signal_stack::WasmtimeSignalStack;
 
@@ -441,12 +446,23 @@ impl WasmtimeEmbedder {
             }
         }
 
-        let memory_trackers = sigsegv_memory_tracker(memories, &mut store, self.log.clone());
+        let MemoryTrackers {
+            memory_trackers,
+            #[cfg(target_os = "linux")]
+            userfaultfd_handlers,
+        } = sigsegv_memory_tracker(
+            memories,
+            &mut store,
+            self.config.memory_tracker_backend,
+            self.log.clone(),
+        );
 
         let signal_stack = WasmtimeSignalStack::new();
         Ok(WasmtimeInstance {
             instance,
             memory_trackers,
+            #[cfg(target_os = "linux")]
+            _userfaultfd_handlers: userfaultfd_handlers,
             signal_stack,
             log: self.log.clone(),
             instance_stats: InstanceStats::default(),
@@ -588,13 +604,24 @@ pub struct MemorySigSegvInfo {
     dirty_page_tracking: DirtyPageTracking,
 }
 
+/// The memory trackers of an instance together with the registrations of the
+/// memories tracked with `userfaultfd` with the thread resolving their faults.
+struct MemoryTrackers {
+    memory_trackers: HashMap<CanisterMemoryType, Arc<Mutex<SigsegvMemoryTracker>>>,
+    #[cfg(target_os = "linux")]
+    userfaultfd_handlers: Vec<UserfaultfdHandler>,
+}
+
 fn sigsegv_memory_tracker<S>(
     memories: HashMap<CanisterMemoryType, MemorySigSegvInfo>,
     store: &mut wasmtime::Store<S>,
+    backend: MemoryTrackerBackend,
     log: ReplicaLogger,
-) -> HashMap<CanisterMemoryType, Arc<Mutex<SigsegvMemoryTracker>>> {
+) -> MemoryTrackers {
     let mut tracked_memories = vec![];
     let mut result = HashMap::new();
+    #[cfg(target_os = "linux")]
+    let mut userfaultfd_handlers = vec![];
     for (
         mem_type,
         MemorySigSegvInfo {
@@ -609,8 +636,8 @@ fn sigsegv_memory_tracker<S>(
         let size = instance_memory.data_size(&store);
 
         let sigsegv_memory_tracker = {
-            // For both SIGSEGV and in the future UFFD memory tracking we need
-            // the base address of the heap and its size
+            // For both SIGSEGV and UFFD memory tracking we need the base
+            // address of the heap and its size
             let base = base as *mut libc::c_void;
             if base as usize % PAGE_SIZE != 0 {
                 fatal!(log, "[EXC-BUG] Memory tracker - Heap must be page aligned.");
@@ -622,21 +649,64 @@ fn sigsegv_memory_tracker<S>(
                 );
             }
 
-            Arc::new(Mutex::new(
-                SigsegvMemoryTracker::new(base, size, log.clone(), dirty_page_tracking, page_map)
-                    .expect("failed to instantiate SIGSEGV memory tracker"),
-            ))
+            match backend {
+                #[cfg(target_os = "linux")]
+                MemoryTrackerBackend::Userfaultfd => {
+                    match SigsegvMemoryTracker::new_with_userfaultfd(
+                        base,
+                        size,
+                        log.clone(),
+                        dirty_page_tracking,
+                        page_map.clone(),
+                    ) {
+                        Ok((tracker, handler)) => {
+                            userfaultfd_handlers.push(handler);
+                            tracker
+                        }
+                        Err(err) => {
+                            // E.g. `vm.unprivileged_userfaultfd` forbids it.
+                            ic_logger::warn!(
+                                every_n_seconds => 300,
+                                log,
+                                "Failed to instantiate UFFD memory tracker, falling back to SIGSEGV: {}",
+                                err
+                            );
+                            new_sigsegv_tracker(base, size, &log, dirty_page_tracking, page_map)
+                        }
+                    }
+                }
+                _ => new_sigsegv_tracker(base, size, &log, dirty_page_tracking, page_map),
+            }
         };
         result.insert(mem_type, Arc::clone(&sigsegv_memory_tracker));
         tracked_memories.push((sigsegv_memory_tracker, current_memory_size_in_pages));
     }
 
+    // The handler is also needed for the `userfaultfd` backend because growing
+    // the memory still raises `SIGSEGV`.
     let handler = crate::signal_handler::sigsegv_memory_tracker_handler(tracked_memories);
     // http://man7.org/linux/man-pages/man7/signal-safety.7.html
     unsafe {
         store.set_signal_handler(handler);
     };
-    result
+    MemoryTrackers {
+        memory_trackers: result,
+        #[cfg(target_os = "linux")]
+        userfaultfd_handlers,
+    }
+}
+
+fn new_sigsegv_tracker(
+    base: *mut libc::c_void,
+    size: usize,
+    log: &ReplicaLogger,
+    dirty_page_tracking: DirtyPageTracking,
+    page_map: PageMap,
+) -> Arc<Mutex<SigsegvMemoryTracker>> {
+    Arc::new(Mutex::new(
+        SigsegvMemoryTracker::new(base, size, log.clone(), dirty_page_tracking, page_map)
+            .expect("failed to instantiate SIGSEGV memory tracker"),
+    ))
 }
 
 /// Additional types that need to be owned by the `wasmtime::Store`.
@@ -691,6 +761,10 @@ pub struct PageAccessResults {
 pub struct WasmtimeInstance {
     instance: wasmtime::Instance,
     memory_trackers: HashMap<CanisterMemoryType, Arc<Mutex<SigsegvMemoryTracker>>>,
+    /// Keep the memories tracked with `userfaultfd` registered with the thread
+    /// resolving their page faults.
+    #[cfg(target_os = "linux")]
+    _userfaultfd_handlers: Vec<UserfaultfdHandler>,
     signal_stack: WasmtimeSignalStack,
     log: ReplicaLogger,
     instance_stats: InstanceStats,
@@ -1135,4 +1209,21 @@ impl WasmtimeInstance {
     pub fn get_stats(&self) -> InstanceStats {
         self.instance_stats.clone()
     }
+
+    /// Returns the indices of the pages of the given memory that were accessed
+    /// (read or written) by this instance, or `None` if accesses to the memory
+    /// are not tracked.
+    ///
+    /// Prefetching may mark a few pages that were not actually accessed, so
+    /// the result is a superset of the accessed pages.
+    pub fn accessed_pages(&self, memory_type: CanisterMemoryType) -> Option<Vec<PageIndex>> {
+        self.memory_trackers.get(&memory_type).map(|tracker| {
+            tracker
+                .lock()
+                .unwrap()
+                .accessed_pages()
+                .borrow()
+                .marked_pages()
+        })
+    }
 }
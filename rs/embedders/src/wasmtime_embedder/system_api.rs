@@ -12,11 +12,13 @@ use ic_interfaces::execution_environment::{
 };
 use ic_logger::error;
 use ic_registry_subnet_type::SubnetType;
+use ic_replicated_state::NumWasmPages;
 use ic_sys::PAGE_SIZE;
 use ic_types::{Cycles, NumBytes, NumInstructions, NumPages, Time};
 use ic_wasm_types::WasmEngineError;
 
 use wasmtime::{AsContextMut, Caller, Global, Linker, Val};
+use wasmtime_environ::WASM_PAGE_SIZE;
 
 use crate::InternalErrorCode;
 use std::convert::TryFrom;
@@ -1019,6 +1021,14 @@ pub(crate) fn syscalls(
                   additional_elements: u32,
                   element_size: u32| {
                 with_system_api(&mut caller, |s| {
+                    // `memory.grow` is instrumented with the Wasm page size as
+                    // the element size and returns the previous size in pages.
+                    // `table.grow` is not subject to the Wasm memory limit.
+                    if element_size == WASM_PAGE_SIZE && native_memory_grow_res != -1 {
+                        s.check_wasm_memory_limit(NumWasmPages::from(
+                            native_memory_grow_res as usize + additional_elements as usize,
+                        ))?;
+                    }
                     s.update_available_memory(
                         native_memory_grow_res as i64,
                         additional_elements as u64,
@@ -1157,6 +1167,83 @@ pub(crate) fn syscalls(
         })
         .unwrap();
 
+    linker
+        .func_wrap("ic0", "cost_call", {
+            move |mut caller: Caller<'_, StoreData>,
+                  method_name_size: u64,
+                  payload_size: u64,
+                  dst: u32| {
+                charge_for_cpu(&mut caller, overhead!(COST_CALL, metering_type))?;
+                with_memory_and_system_api(&mut caller, |system_api, memory| {
+                    system_api.ic0_cost_call(method_name_size, payload_size, dst, memory)
+                })?;
+                if feature_flags.write_barrier == FlagStatus::Enabled {
+                    mark_writes_on_bytemap(&mut caller, dst as usize, 16)
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .unwrap();
+
+    linker
+        .func_wrap("ic0", "cost_create_canister", {
+            move |mut caller: Caller<'_, StoreData>, dst: u32| {
+                charge_for_cpu(&mut caller, overhead!(COST_CREATE_CANISTER, metering_type))?;
+                with_memory_and_system_api(&mut caller, |system_api, memory| {
+                    system_api.ic0_cost_create_canister(dst, memory)
+                })?;
+                if feature_flags.write_barrier == FlagStatus::Enabled {
+                    mark_writes_on_bytemap(&mut caller, dst as usize, 16)
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .unwrap();
+
+    linker
+        .func_wrap("ic0", "cost_http_request", {
+            move |mut caller: Caller<'_, StoreData>,
+                  request_size: u64,
+                  max_res_bytes: u64,
+                  dst: u32| {
+                charge_for_cpu(&mut caller, overhead!(COST_HTTP_REQUEST, metering_type))?;
+                with_memory_and_system_api(&mut caller, |system_api, memory| {
+                    system_api.ic0_cost_http_request(request_size, max_res_bytes, dst, memory)
+                })?;
+                if feature_flags.write_barrier == FlagStatus::Enabled {
+                    mark_writes_on_bytemap(&mut caller, dst as usize, 16)
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .unwrap();
+
+    linker
+        .func_wrap("ic0", "cost_sign_with_ecdsa", {
+            move |mut caller: Caller<'_, StoreData>,
+                  src: u32,
+                  size: u32,
+                  ecdsa_curve: u32,
+                  dst: u32| {
+                charge_for_cpu_and_mem(
+                    &mut caller,
+                    overhead!(COST_SIGN_WITH_ECDSA, metering_type),
+                    size as u64,
+                )?;
+                let result = with_memory_and_system_api(&mut caller, |system_api, memory| {
+                    system_api.ic0_cost_sign_with_ecdsa(src, size, ecdsa_curve, dst, memory)
+                })?;
+                if feature_flags.write_barrier == FlagStatus::Enabled {
+                    mark_writes_on_bytemap(&mut caller, dst as usize, 16)?;
+                }
+                Ok(result)
+            }
+        })
+        .unwrap();
+
     linker
         .func_wrap("ic0", "cycles_burn128", {
             move |mut caller: Caller<'_, StoreData>, amount_high: u64, amount_low: u64, dst: u32| {
@@ -938,6 +938,24 @@ fn can_validate_module_cycles_u128_related_imports() {
     );
 }
 
+#[test]
+fn can_validate_cost_introspection_imports() {
+    let wasm = wat2wasm(
+        r#"(module
+        (import "ic0" "cost_call" (func $ic0_cost_call (param i64 i64 i32)))
+        (import "ic0" "cost_create_canister" (func $ic0_cost_create_canister (param i32)))
+        (import "ic0" "cost_http_request" (func $ic0_cost_http_request (param i64 i64 i32)))
+        (import "ic0" "cost_sign_with_ecdsa" (func $ic0_cost_sign_with_ecdsa (param i32 i32 i32 i32) (result i32)))
+    )"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        validate_wasm_binary(&wasm, &EmbeddersConfig::default()),
+        Ok(WasmValidationDetails::default())
+    );
+}
+
 #[test]
 fn can_validate_performance_counter_import() {
     let wasm = wat2wasm(
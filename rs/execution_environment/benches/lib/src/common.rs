@@ -146,6 +146,7 @@ where
             MAX_NUM_INSTRUCTIONS,
         ),
         canister_memory_limit: canister_state.memory_limit(NumBytes::new(std::u64::MAX)),
+        wasm_memory_limit: canister_state.system_state.wasm_memory_limit,
         memory_allocation: canister_state.memory_allocation(),
         compute_allocation: canister_state.compute_allocation(),
         subnet_type: hypervisor.subnet_type(),
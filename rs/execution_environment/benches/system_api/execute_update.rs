@@ -317,6 +317,30 @@ pub fn execute_update_bench(c: &mut Criterion) {
             Module::Test.from_ic0("cycles_burn128", Params3(1_i64, 2_i64, 3_i32), Result::No),
             19000006,
         ),
+        common::Benchmark(
+            "ic0_cost_call()".into(),
+            Module::Test.from_ic0("cost_call", Params3(1_i64, 2_i64, 3_i32), Result::No),
+            519001006,
+        ),
+        common::Benchmark(
+            "ic0_cost_create_canister()".into(),
+            Module::Test.from_ic0("cost_create_canister", Param1(0), Result::No),
+            517001006,
+        ),
+        common::Benchmark(
+            "ic0_cost_http_request()".into(),
+            Module::Test.from_ic0(
+                "cost_http_request",
+                Params3(1_i64, 2_i64, 3_i32),
+                Result::No,
+            ),
+            519001006,
+        ),
+        common::Benchmark(
+            "ic0_cost_sign_with_ecdsa()".into(),
+            Module::Test.from_ic0("cost_sign_with_ecdsa", Params4(0, 10, 0, 0), Result::I32),
+            531001006,
+        ),
     ];
     common::run_benchmarks(
         c,
@@ -534,6 +534,12 @@ impl CanisterManager {
         if let Some(limit) = settings.reserved_cycles_limit() {
             canister.system_state.set_reserved_balance_limit(limit);
         }
+        if let Some(limit) = settings.wasm_memory_limit() {
+            canister.system_state.wasm_memory_limit = (limit.get() > 0).then_some(limit);
+        }
+        if let Some(threshold) = settings.wasm_memory_threshold() {
+            canister.system_state.wasm_memory_threshold = threshold;
+        }
         canister
             .system_state
             .reserve_cycles(settings.reservation_cycles())
@@ -583,6 +589,7 @@ impl CanisterManager {
 
         let is_controllers_change =
             validated_settings.controller().is_some() || validated_settings.controllers().is_some();
+        let settings_changes = settings_changes_for_history(&validated_settings);
 
         let old_usage = canister.memory_usage();
         let old_mem = canister.memory_allocation().allocated_bytes(old_usage);
@@ -622,10 +629,15 @@ impl CanisterManager {
             let new_controllers = canister.system_state.controllers.iter().copied().collect();
             canister.system_state.add_canister_change(
                 timestamp_nanos,
-                origin,
+                origin.clone(),
                 CanisterChangeDetails::controllers_change(new_controllers),
             );
         }
+        for details in settings_changes {
+            canister
+                .system_state
+                .add_canister_change(timestamp_nanos, origin.clone(), details);
+        }
 
         Ok(())
     }
@@ -741,6 +753,7 @@ impl CanisterManager {
             Some(canister) => canister,
         };
         execution_parameters.compute_allocation = old_canister.scheduler_state.compute_allocation;
+        execution_parameters.wasm_memory_limit = old_canister.system_state.wasm_memory_limit;
         execution_parameters.canister_memory_limit = match old_canister.memory_allocation() {
             MemoryAllocation::Reserved(bytes) => bytes,
             MemoryAllocation::BestEffort => execution_parameters.canister_memory_limit,
@@ -1080,6 +1093,8 @@ impl CanisterManager {
         let memory_allocation = canister.memory_allocation();
         let freeze_threshold = canister.system_state.freeze_threshold;
         let reserved_cycles_limit = canister.system_state.reserved_balance_limit();
+        let wasm_memory_limit = canister.system_state.wasm_memory_limit;
+        let wasm_memory_threshold = canister.system_state.wasm_memory_threshold;
 
         Ok(CanisterStatusResultV2::new(
             canister.status(),
@@ -1095,6 +1110,8 @@ impl CanisterManager {
             Some(memory_allocation.bytes().get()),
             freeze_threshold.get(),
             reserved_cycles_limit.map(|x| x.get()),
+            wasm_memory_limit.map(|x| x.get()),
+            wasm_memory_threshold.get(),
             self.cycles_account_manager
                 .idle_cycles_burned_rate(
                     memory_allocation,
@@ -1766,6 +1783,10 @@ pub(crate) enum CanisterManagerError {
     WasmChunkStoreError {
         message: String,
     },
+    WasmMemoryLimitExceeded {
+        bytes: NumBytes,
+        limit: NumBytes,
+    },
 }
 
 impl From<CanisterManagerError> for UserError {
@@ -2002,6 +2023,16 @@ impl From<CanisterManagerError> for UserError {
                     )
                 )
             }
+            WasmMemoryLimitExceeded { bytes, limit } => {
+                Self::new(
+                    ErrorCode::CanisterWasmMemoryLimitExceeded,
+                    format!(
+                        "Canister requires a Wasm memory of {} bytes, which exceeds its \
+                         `wasm_memory_limit` setting of {} bytes.",
+                        bytes, limit,
+                    ),
+                )
+            }
         }
     }
 }
@@ -2116,6 +2147,36 @@ pub fn uninstall_canister(
     rejects
 }
 
+/// Returns the canister history entries describing the settings (other than
+/// controllers) updated by the given validated settings, in the order in which
+/// they are recorded.
+fn settings_changes_for_history(
+    settings: &ValidatedCanisterSettings,
+) -> Vec<CanisterChangeDetails> {
+    let mut changes = vec![];
+    if let Some(compute_allocation) = settings.compute_allocation() {
+        changes.push(CanisterChangeDetails::compute_allocation_change(
+            compute_allocation.as_percent(),
+        ));
+    }
+    if let Some(memory_allocation) = settings.memory_allocation() {
+        changes.push(CanisterChangeDetails::memory_allocation_change(
+            memory_allocation.bytes().get(),
+        ));
+    }
+    if let Some(freezing_threshold) = settings.freezing_threshold() {
+        changes.push(CanisterChangeDetails::freezing_threshold_change(
+            freezing_threshold.get(),
+        ));
+    }
+    if let Some(reserved_cycles_limit) = settings.reserved_cycles_limit() {
+        changes.push(CanisterChangeDetails::reserved_cycles_limit_change(
+            reserved_cycles_limit.get(),
+        ));
+    }
+    changes
+}
+
 /// Holds necessary information for the deterministic time slicing execution of
 /// install code. Install code can be executed in three modes - install,
 /// reinstall and upgrade.
@@ -107,6 +107,7 @@ lazy_static! {
             MAX_NUM_INSTRUCTIONS
         ),
         canister_memory_limit: NumBytes::new(u64::MAX / 2),
+        wasm_memory_limit: None,
         memory_allocation: MemoryAllocation::default(),
         compute_allocation: ComputeAllocation::default(),
         subnet_type: SubnetType::Application,
@@ -3853,8 +3854,9 @@ fn test_upgrade_when_updating_memory_allocation_via_canister_settings() {
         );
         state.put_canister_state(res.2.unwrap());
 
-        // canister history memory usage at the beginning of update_settings
-        let canister_history_memory = 2 * size_of::<CanisterChange>() + size_of::<PrincipalId>();
+        // canister history memory usage at the beginning of the upgrade
+        // (update_settings records the memory allocation change in the canister history)
+        let canister_history_memory = 3 * size_of::<CanisterChange>() + size_of::<PrincipalId>();
         // Update memory allocation to a big enough value via canister settings. The
         // upgrade should succeed.
         let settings = CanisterSettingsBuilder::new()
@@ -6739,6 +6741,80 @@ fn canister_status_contains_reserved_cycles_limit() {
     );
 }
 
+#[test]
+fn canister_status_contains_wasm_memory_limit() {
+    const CYCLES: Cycles = Cycles::new(1_000_000_000_000_000);
+
+    let mut test = ExecutionTestBuilder::new().build();
+
+    let canister_id = test.create_canister(CYCLES);
+    let result = test.canister_status(canister_id);
+    let reply = get_reply(result);
+    let status = CanisterStatusResultV2::decode(&reply).unwrap();
+    assert_eq!(status.settings().wasm_memory_limit(), candid::Nat::from(0));
+
+    test.canister_update_wasm_memory_limit(canister_id, NumBytes::new(1 << 20))
+        .unwrap();
+
+    let result = test.canister_status(canister_id);
+    let reply = get_reply(result);
+    let status = CanisterStatusResultV2::decode(&reply).unwrap();
+    assert_eq!(
+        status.settings().wasm_memory_limit(),
+        candid::Nat::from(1 << 20),
+    );
+
+    // Setting the limit to zero removes it.
+    test.canister_update_wasm_memory_limit(canister_id, NumBytes::new(0))
+        .unwrap();
+    assert_eq!(
+        test.canister_state(canister_id)
+            .system_state
+            .wasm_memory_limit,
+        None
+    );
+}
+
+#[test]
+fn update_settings_rejects_too_large_wasm_memory_limit() {
+    const CYCLES: Cycles = Cycles::new(1_000_000_000_000_000);
+
+    let mut test = ExecutionTestBuilder::new().build();
+
+    let canister_id = test.create_canister(CYCLES);
+    let err = test
+        .canister_update_wasm_memory_limit(canister_id, NumBytes::new((1 << 48) + 1))
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+    assert!(err
+        .description()
+        .contains("Wasm memory limit expected to be in the range"));
+}
+
+#[test]
+fn install_and_upgrade_respect_wasm_memory_limit() {
+    const CYCLES: Cycles = Cycles::new(1_000_000_000_000_000);
+
+    let mut test = ExecutionTestBuilder::new().build();
+
+    let small_wasm = wat::parse_str("(module (memory 1))").unwrap();
+    let large_wasm = wat::parse_str("(module (memory 2))").unwrap();
+
+    let canister_id = test.create_canister(CYCLES);
+    test.canister_update_wasm_memory_limit(canister_id, NumBytes::new(WASM_PAGE_SIZE_IN_BYTES))
+        .unwrap();
+
+    let err = test
+        .install_canister(canister_id, large_wasm.clone())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterWasmMemoryLimitExceeded);
+
+    test.install_canister(canister_id, small_wasm).unwrap();
+
+    let err = test.upgrade_canister(canister_id, large_wasm).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterWasmMemoryLimitExceeded);
+}
+
 #[test]
 fn upload_chunk_works_from_white_list() {
     const CYCLES: Cycles = Cycles::new(1_000_000_000_000_000);
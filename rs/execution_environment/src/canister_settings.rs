@@ -12,6 +12,10 @@ use std::convert::TryFrom;
 
 use crate::canister_manager::CanisterManagerError;
 
+/// The maximum value of the `wasm_memory_limit` and `wasm_memory_threshold`
+/// settings: 2^48 bytes.
+pub(crate) const MAX_WASM_MEMORY_LIMIT: u64 = 1 << 48;
+
 /// Struct used for decoding CanisterSettingsArgs
 #[derive(Default)]
 pub(crate) struct CanisterSettings {
@@ -21,6 +25,8 @@ pub(crate) struct CanisterSettings {
     pub(crate) memory_allocation: Option<MemoryAllocation>,
     pub(crate) freezing_threshold: Option<NumSeconds>,
     pub(crate) reserved_cycles_limit: Option<Cycles>,
+    pub(crate) wasm_memory_limit: Option<NumBytes>,
+    pub(crate) wasm_memory_threshold: Option<NumBytes>,
 }
 
 impl CanisterSettings {
@@ -31,6 +37,8 @@ impl CanisterSettings {
         memory_allocation: Option<MemoryAllocation>,
         freezing_threshold: Option<NumSeconds>,
         reserved_cycles_limit: Option<Cycles>,
+        wasm_memory_limit: Option<NumBytes>,
+        wasm_memory_threshold: Option<NumBytes>,
     ) -> Self {
         Self {
             controller,
@@ -39,6 +47,8 @@ impl CanisterSettings {
             memory_allocation,
             freezing_threshold,
             reserved_cycles_limit,
+            wasm_memory_limit,
+            wasm_memory_threshold,
         }
     }
 
@@ -65,6 +75,14 @@ impl CanisterSettings {
     pub fn reserved_cycles_limit(&self) -> Option<Cycles> {
         self.reserved_cycles_limit
     }
+
+    pub fn wasm_memory_limit(&self) -> Option<NumBytes> {
+        self.wasm_memory_limit
+    }
+
+    pub fn wasm_memory_threshold(&self) -> Option<NumBytes> {
+        self.wasm_memory_threshold
+    }
 }
 
 impl TryFrom<CanisterSettingsArgs> for CanisterSettings {
@@ -102,6 +120,30 @@ impl TryFrom<CanisterSettingsArgs> for CanisterSettings {
             None => None,
         };
 
+        let wasm_memory_limit = match input.wasm_memory_limit {
+            Some(limit) => match limit.0.to_u64() {
+                Some(limit) if limit <= MAX_WASM_MEMORY_LIMIT => Some(NumBytes::from(limit)),
+                _ => {
+                    return Err(UpdateSettingsError::WasmMemoryLimitOutOfRange { provided: limit });
+                }
+            },
+            None => None,
+        };
+
+        let wasm_memory_threshold = match input.wasm_memory_threshold {
+            Some(threshold) => match threshold.0.to_u64() {
+                Some(threshold) if threshold <= MAX_WASM_MEMORY_LIMIT => {
+                    Some(NumBytes::from(threshold))
+                }
+                _ => {
+                    return Err(UpdateSettingsError::WasmMemoryThresholdOutOfRange {
+                        provided: threshold,
+                    });
+                }
+            },
+            None => None,
+        };
+
         Ok(CanisterSettings::new(
             controller,
             input
@@ -111,6 +153,8 @@ impl TryFrom<CanisterSettingsArgs> for CanisterSettings {
             memory_allocation,
             freezing_threshold,
             reserved_cycles_limit,
+            wasm_memory_limit,
+            wasm_memory_threshold,
         ))
     }
 }
@@ -133,6 +177,8 @@ pub(crate) struct CanisterSettingsBuilder {
     memory_allocation: Option<MemoryAllocation>,
     freezing_threshold: Option<NumSeconds>,
     reserved_cycles_limit: Option<Cycles>,
+    wasm_memory_limit: Option<NumBytes>,
+    wasm_memory_threshold: Option<NumBytes>,
 }
 
 #[allow(dead_code)]
@@ -145,6 +191,8 @@ impl CanisterSettingsBuilder {
             memory_allocation: None,
             freezing_threshold: None,
             reserved_cycles_limit: None,
+            wasm_memory_limit: None,
+            wasm_memory_threshold: None,
         }
     }
 
@@ -156,6 +204,8 @@ impl CanisterSettingsBuilder {
             memory_allocation: self.memory_allocation,
             freezing_threshold: self.freezing_threshold,
             reserved_cycles_limit: self.reserved_cycles_limit,
+            wasm_memory_limit: self.wasm_memory_limit,
+            wasm_memory_threshold: self.wasm_memory_threshold,
         }
     }
 
@@ -200,6 +250,20 @@ impl CanisterSettingsBuilder {
             ..self
         }
     }
+
+    pub fn with_wasm_memory_limit(self, wasm_memory_limit: NumBytes) -> Self {
+        Self {
+            wasm_memory_limit: Some(wasm_memory_limit),
+            ..self
+        }
+    }
+
+    pub fn with_wasm_memory_threshold(self, wasm_memory_threshold: NumBytes) -> Self {
+        Self {
+            wasm_memory_threshold: Some(wasm_memory_threshold),
+            ..self
+        }
+    }
 }
 
 pub enum UpdateSettingsError {
@@ -207,6 +271,8 @@ pub enum UpdateSettingsError {
     MemoryAllocation(InvalidMemoryAllocationError),
     FreezingThresholdOutOfRange { provided: candid::Nat },
     ReservedCyclesLimitOutOfRange { provided: candid::Nat },
+    WasmMemoryLimitOutOfRange { provided: candid::Nat },
+    WasmMemoryThresholdOutOfRange { provided: candid::Nat },
 }
 
 impl From<UpdateSettingsError> for UserError {
@@ -242,6 +308,20 @@ impl From<UpdateSettingsError> for UserError {
                     provided
                 ),
             ),
+            UpdateSettingsError::WasmMemoryLimitOutOfRange { provided } => UserError::new(
+                ErrorCode::CanisterContractViolation,
+                format!(
+                    "Wasm memory limit expected to be in the range of [0..2^48], got {}",
+                    provided
+                ),
+            ),
+            UpdateSettingsError::WasmMemoryThresholdOutOfRange { provided } => UserError::new(
+                ErrorCode::CanisterContractViolation,
+                format!(
+                    "Wasm memory threshold expected to be in the range of [0..2^48], got {}",
+                    provided
+                ),
+            ),
         }
     }
 }
@@ -266,6 +346,8 @@ pub(crate) struct ValidatedCanisterSettings {
     freezing_threshold: Option<NumSeconds>,
     reserved_cycles_limit: Option<Cycles>,
     reservation_cycles: Cycles,
+    wasm_memory_limit: Option<NumBytes>,
+    wasm_memory_threshold: Option<NumBytes>,
 }
 
 impl ValidatedCanisterSettings {
@@ -296,6 +378,18 @@ impl ValidatedCanisterSettings {
     pub fn reservation_cycles(&self) -> Cycles {
         self.reservation_cycles
     }
+
+    /// Returns the new Wasm memory limit, where a limit of 0 means that the
+    /// limit is to be removed.
+    pub fn wasm_memory_limit(&self) -> Option<NumBytes> {
+        self.wasm_memory_limit
+    }
+
+    /// Returns the new threshold for the `canister_on_low_wasm_memory` hook,
+    /// where a threshold of 0 disables the hook.
+    pub fn wasm_memory_threshold(&self) -> Option<NumBytes> {
+        self.wasm_memory_threshold
+    }
 }
 
 /// Validates the new canisters settings:
@@ -491,5 +585,7 @@ pub(crate) fn validate_canister_settings(
         freezing_threshold: settings.freezing_threshold(),
         reserved_cycles_limit: settings.reserved_cycles_limit(),
         reservation_cycles,
+        wasm_memory_limit: settings.wasm_memory_limit(),
+        wasm_memory_threshold: settings.wasm_memory_threshold(),
     })
 }
@@ -13,7 +13,7 @@ use ic_interfaces::execution_environment::{
 use ic_logger::{error, fatal, info, warn};
 use ic_replicated_state::canister_state::system_state::ReservationError;
 use ic_replicated_state::metadata_state::subnet_call_context_manager::InstallCodeCallId;
-use ic_replicated_state::{CanisterState, ExecutionState};
+use ic_replicated_state::{num_bytes_try_from, CanisterState, ExecutionState};
 use ic_state_layout::{CanisterLayout, CheckpointLayout, ReadOnly};
 use ic_sys::PAGE_SIZE;
 use ic_system_api::ExecutionParameters;
@@ -433,6 +433,8 @@ impl InstallCodeHelper {
                 memory_allocation: original.requested_memory_allocation,
                 freezing_threshold: None,
                 reserved_cycles_limit: None,
+                wasm_memory_limit: None,
+                wasm_memory_threshold: None,
             },
             self.canister.memory_usage(),
             self.canister.message_memory_usage(),
@@ -551,6 +553,24 @@ impl InstallCodeHelper {
                 memory_usage_needed: new_memory_usage,
             });
         }
+
+        // The initial Wasm memory of the new module has to fit into the Wasm
+        // memory limit. Growing it further is checked on `memory.grow`.
+        if let Some(limit) = self.canister.system_state.wasm_memory_limit {
+            let wasm_memory_size = self
+                .canister
+                .execution_state
+                .as_ref()
+                .map_or(NumBytes::from(0), |es| {
+                    num_bytes_try_from(es.wasm_memory.size).unwrap_or(NumBytes::from(u64::MAX))
+                });
+            if wasm_memory_size > limit {
+                return Err(CanisterManagerError::WasmMemoryLimitExceeded {
+                    bytes: wasm_memory_size,
+                    limit,
+                });
+            }
+        }
         self.update_allocated_bytes(
             old_memory_usage,
             old_memory_allocation,
@@ -9,6 +9,7 @@ use crate::execution::common::{validate_canister, validate_method};
 use crate::execution_environment::RoundLimits;
 use crate::{Hypervisor, NonReplicatedQueryKind};
 use ic_error_types::UserError;
+use ic_interfaces::execution_environment::StateReads;
 use ic_replicated_state::{CallOrigin, CanisterState, NetworkTopology};
 use ic_system_api::{ApiType, ExecutionParameters};
 use ic_types::ingress::WasmResult;
@@ -18,6 +19,9 @@ use ic_types::{Cycles, NumInstructions, Time};
 use prometheus::IntCounter;
 
 // Execute non replicated query.
+//
+// Besides the result, returns the parts of the canister state that the
+// execution read. They are unknown (default) if the query was not executed.
 #[allow(clippy::too_many_arguments)]
 pub fn execute_non_replicated_query(
     query_kind: NonReplicatedQueryKind,
@@ -36,6 +40,7 @@ pub fn execute_non_replicated_query(
     NumInstructions,
     Result<Option<WasmResult>, UserError>,
     Option<CallContextId>,
+    StateReads,
 ) {
     // Validate that the canister is running.
     if let Err(err) = validate_canister(&canister) {
@@ -44,6 +49,7 @@ pub fn execute_non_replicated_query(
             execution_parameters.instruction_limits.message(),
             Err(err),
             None,
+            StateReads::default(),
         );
     }
 
@@ -58,6 +64,7 @@ pub fn execute_non_replicated_query(
             execution_parameters.instruction_limits.message(),
             Err(err.into_user_error(&canister_id)),
             None,
+            StateReads::default(),
         );
     }
 
@@ -126,5 +133,6 @@ pub fn execute_non_replicated_query(
         output.num_instructions_left,
         result,
         call_context_id,
+        output.state_reads,
     )
 }
@@ -54,7 +54,8 @@ use ic_replicated_state::{
         SignWithEcdsaContext, StopCanisterCall, SubnetCallContext,
     },
     page_map::PageAllocatorFileDescriptor,
-    CanisterState, CanisterStatus, ExecutionTask, NetworkTopology, ReplicatedState,
+    CanisterState, CanisterStatus, ExecutionTask, NetworkTopology, OnLowWasmMemoryHookStatus,
+    ReplicatedState,
 };
 use ic_system_api::{ExecutionParameters, InstructionLimits};
 use ic_types::{
@@ -1352,6 +1353,7 @@ impl ExecutionEnvironment {
         ExecutionParameters {
             instruction_limits,
             canister_memory_limit: canister.memory_limit(self.config.max_canister_memory_size),
+            wasm_memory_limit: canister.system_state.wasm_memory_limit,
             memory_allocation: canister.memory_allocation(),
             compute_allocation: canister.compute_allocation(),
             subnet_type: self.own_subnet_type,
@@ -2495,6 +2497,7 @@ impl ExecutionEnvironment {
         match task {
             ExecutionTask::Heartbeat
             | ExecutionTask::GlobalTimer
+            | ExecutionTask::OnLowWasmMemory
             | ExecutionTask::PausedExecution(_)
             | ExecutionTask::AbortedExecution { .. } => {
                 panic!(
@@ -2591,7 +2594,8 @@ impl ExecutionEnvironment {
                     ExecutionTask::AbortedExecution { .. }
                     | ExecutionTask::AbortedInstallCode { .. }
                     | ExecutionTask::Heartbeat
-                    | ExecutionTask::GlobalTimer => task,
+                    | ExecutionTask::GlobalTimer
+                    | ExecutionTask::OnLowWasmMemory => task,
                     ExecutionTask::PausedExecution(id) => {
                         let paused = self.take_paused_execution(id).unwrap();
                         let (input, prepaid_execution_cycles) = paused.abort(log);
@@ -3135,6 +3139,14 @@ pub fn execute_canister(
                 let task = CanisterMessageOrTask::Task(CanisterTask::GlobalTimer);
                 (task, None)
             }
+            ExecutionTask::OnLowWasmMemory => {
+                // The hook runs at most once per crossing of the threshold,
+                // regardless of the outcome of the execution.
+                canister.system_state.on_low_wasm_memory_hook_status =
+                    OnLowWasmMemoryHookStatus::Executed;
+                let task = CanisterMessageOrTask::Task(CanisterTask::OnLowWasmMemory);
+                (task, None)
+            }
             ExecutionTask::AbortedExecution {
                 input,
                 prepaid_execution_cycles,
@@ -1,4 +1,5 @@
 use assert_matches::assert_matches;
+use ic_ic00_types::CanisterSettingsArgsBuilder;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::NumWasmPages;
 use ic_replicated_state::{page_map::PAGE_SIZE, CanisterStatus};
@@ -46,6 +47,23 @@ fn global_timer_is_executed() {
     );
 }
 
+#[test]
+fn on_low_wasm_memory_is_executed() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wat = r#"(module
+            (func (export "canister_on_low_wasm_memory")
+                (drop (memory.grow (i32.const 10)))
+            )
+            (memory 1 20)
+        )"#;
+    let canister_id = test.canister_from_wat(wat).unwrap();
+    test.canister_task(canister_id, CanisterTask::OnLowWasmMemory);
+    assert_eq!(
+        test.execution_state(canister_id).wasm_memory.size,
+        NumWasmPages::new(11)
+    );
+}
+
 #[test]
 fn ic0_global_timer_set_is_supported_in_pre_upgrade() {
     let env = StateMachine::new();
@@ -762,3 +780,100 @@ fn global_timer_resumes_after_canister_is_being_stopped_and_started_again() {
     let result = env.query(canister_id, "query", get_global_counter).unwrap();
     assert_eq!(result, WasmResult::Reply(10_u64.to_le_bytes().into()));
 }
+
+const LOW_WASM_MEMORY_CANISTER_WAT: &str = r#"
+    (module
+        (import "ic0" "msg_reply" (func $msg_reply))
+        (import "ic0" "msg_reply_data_append"
+            (func $msg_reply_data_append (param i32 i32)))
+        (func $grow
+            (drop (memory.grow (i32.const 1)))
+            (call $msg_reply)
+        )
+        (func $on_low_wasm_memory
+            (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+        )
+        (func $counter
+            (i32.store (i32.const 0) (global.get $counter))
+            (call $msg_reply_data_append (i32.const 0) (i32.const 4))
+            (call $msg_reply)
+        )
+        (global $counter (mut i32) (i32.const 0))
+        (memory 1)
+        (export "canister_update grow" (func $grow))
+        (export "canister_query counter" (func $counter))
+        (export "canister_on_low_wasm_memory" (func $on_low_wasm_memory))
+    )"#;
+
+#[test]
+fn on_low_wasm_memory_runs_once_per_threshold_crossing() {
+    const PAGE: u64 = 64 * 1024;
+    let env = StateMachine::new();
+    let settings = CanisterSettingsArgsBuilder::new()
+        .with_wasm_memory_limit(10 * PAGE)
+        .with_wasm_memory_threshold(5 * PAGE)
+        .build();
+    let canister_id =
+        env.install_canister_wat(LOW_WASM_MEMORY_CANISTER_WAT, vec![], Some(settings));
+    let counter = |env: &StateMachine| env.query(canister_id, "counter", vec![]).unwrap();
+
+    // Grow the Wasm memory to 5 pages: the free memory is not yet below the
+    // threshold.
+    for _ in 0..4 {
+        env.execute_ingress(canister_id, "grow", vec![]).unwrap();
+    }
+    env.tick();
+    assert_eq!(counter(&env), WasmResult::Reply(0u32.to_le_bytes().into()));
+
+    // Grow the Wasm memory to 6 pages: the hook runs exactly once.
+    env.execute_ingress(canister_id, "grow", vec![]).unwrap();
+    for _ in 0..5 {
+        env.tick();
+    }
+    assert_eq!(counter(&env), WasmResult::Reply(1u32.to_le_bytes().into()));
+
+    // Growing further does not run the hook again.
+    env.execute_ingress(canister_id, "grow", vec![]).unwrap();
+    env.tick();
+    assert_eq!(counter(&env), WasmResult::Reply(1u32.to_le_bytes().into()));
+
+    // Raising the limit moves the free memory above the threshold and
+    // lowering it again is a new crossing, so the hook runs once more.
+    env.update_settings(
+        &canister_id,
+        CanisterSettingsArgsBuilder::new()
+            .with_wasm_memory_limit(20 * PAGE)
+            .build(),
+    )
+    .unwrap();
+    env.tick();
+    assert_eq!(counter(&env), WasmResult::Reply(1u32.to_le_bytes().into()));
+    env.update_settings(
+        &canister_id,
+        CanisterSettingsArgsBuilder::new()
+            .with_wasm_memory_limit(10 * PAGE)
+            .build(),
+    )
+    .unwrap();
+    env.tick();
+    env.tick();
+    assert_eq!(counter(&env), WasmResult::Reply(2u32.to_le_bytes().into()));
+}
+
+#[test]
+fn on_low_wasm_memory_does_not_run_without_threshold() {
+    const PAGE: u64 = 64 * 1024;
+    let env = StateMachine::new();
+    let settings = CanisterSettingsArgsBuilder::new()
+        .with_wasm_memory_limit(2 * PAGE)
+        .build();
+    let canister_id =
+        env.install_canister_wat(LOW_WASM_MEMORY_CANISTER_WAT, vec![], Some(settings));
+
+    env.execute_ingress(canister_id, "grow", vec![]).unwrap();
+    for _ in 0..5 {
+        env.tick();
+    }
+    let result = env.query(canister_id, "counter", vec![]).unwrap();
+    assert_eq!(result, WasmResult::Reply(0u32.to_le_bytes().into()));
+}
@@ -287,6 +287,7 @@ fn dashboard_label_value_from(code: ErrorCode) -> &'static str {
         ReservedCyclesLimitExceededInMemoryAllocation => "Canister cannot increase memory allocation due to its reserved cycles limit",
         ReservedCyclesLimitExceededInMemoryGrow => "Canister cannot grow memory due to its reserved cycles limit",
         InsufficientCyclesInMessageMemoryGrow => "Canister does not have enough cycles to grow message memory",
+        CanisterWasmMemoryLimitExceeded => "Canister exceeded its Wasm memory limit",
         StopCanisterRequestTimeout => "Stop canister request timed out",
     }
 }
@@ -244,7 +244,7 @@ impl Hypervisor {
             }
             FlagStatus::Disabled => {
                 let executor = WasmExecutorImpl::new(
-                    WasmtimeEmbedder::new(embedder_config, log.clone()),
+                    WasmtimeEmbedder::new(embedder_config.clone(), log.clone()),
                     metrics_registry,
                     log.clone(),
                     Arc::clone(&fd_factory),
@@ -253,6 +253,18 @@ impl Hypervisor {
             }
         };
 
+        let compilation_cache = match &config.compilation_cache_dir {
+            Some(dir) => CompilationCache::new_with_disk_cache(
+                config.max_compilation_cache_size,
+                dir,
+                config.max_compilation_cache_disk_size,
+                &embedder_config,
+                log.clone(),
+                metrics_registry,
+            ),
+            None => CompilationCache::new(config.max_compilation_cache_size),
+        };
+
         Self {
             wasm_executor,
             metrics: Arc::new(HypervisorMetrics::new(metrics_registry)),
@@ -260,7 +272,7 @@ impl Hypervisor {
             own_subnet_type,
             log,
             cycles_account_manager,
-            compilation_cache: Arc::new(CompilationCache::new(config.max_compilation_cache_size)),
+            compilation_cache: Arc::new(compilation_cache),
             deterministic_time_slicing: config.deterministic_time_slicing,
             cost_to_compile_wasm_instruction: config
                 .embedders_config
@@ -6447,6 +6447,51 @@ fn wasm_memory_grow_respects_reserved_cycles_limit() {
         .contains("due to its reserved cycles limit"));
 }
 
+#[test]
+fn wasm_memory_grow_respects_wasm_memory_limit() {
+    let mut test = ExecutionTestBuilder::new().build();
+
+    let wat = r#"
+        (module
+            (import "ic0" "msg_reply" (func $msg_reply))
+            (func $grow_within_limit
+                (if (i32.eq (memory.grow (i32.const 1)) (i32.const -1))
+                  (then (unreachable))
+                )
+                (call $msg_reply)
+            )
+            (func $grow_beyond_limit
+                (drop (memory.grow (i32.const 10)))
+                (call $msg_reply)
+            )
+            (memory $memory 1)
+            (export "canister_update grow_within_limit" (func $grow_within_limit))
+            (export "canister_update grow_beyond_limit" (func $grow_beyond_limit))
+        )"#;
+
+    let wasm = wat::parse_str(wat).unwrap();
+    let canister_id = test.canister_from_binary(wasm).unwrap();
+
+    test.canister_update_wasm_memory_limit(
+        canister_id,
+        NumBytes::from(3 * WASM_PAGE_SIZE_IN_BYTES as u64),
+    )
+    .unwrap();
+
+    test.ingress(canister_id, "grow_within_limit", vec![])
+        .unwrap();
+
+    let err = test
+        .ingress(canister_id, "grow_beyond_limit", vec![])
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterWasmMemoryLimitExceeded);
+    assert!(err.description().contains("exceeded its Wasm memory limit"));
+    assert_eq!(
+        test.execution_state(canister_id).wasm_memory.size,
+        NumWasmPages::from(2)
+    );
+}
+
 #[test]
 fn stable_memory_grow_respects_reserved_cycles_limit() {
     const CYCLES: Cycles = Cycles::new(20_000_000_000_000);
@@ -36,7 +36,7 @@ use ic_types::{
         Blob, Certificate, CertificateDelegation, HttpQueryResponse, HttpQueryResponseReply,
         UserQuery,
     },
-    CanisterId, NumInstructions,
+    CanisterId, NumInstructions, PrincipalId,
 };
 use serde::Serialize;
 use std::convert::Infallible;
@@ -52,7 +52,11 @@ use tower::{util::BoxCloneService, Service};
 
 pub(crate) use self::query_scheduler::{QueryScheduler, QuerySchedulerFlag};
 use self::query_stats::QueryStatsCollector;
-use ic_ic00_types::{BitcoinGetBalanceArgs, BitcoinGetUtxosArgs, Payload, QueryMethod};
+use ic_ic00_types::{
+    BitcoinGetBalanceArgs, BitcoinGetUtxosArgs, CanisterHistoryArgs, CanisterHistoryResponse,
+    Payload, QueryMethod,
+};
+use ic_replicated_state::canister_state::system_state::MAX_CANISTER_HISTORY_CHANGES;
 use ic_replicated_state::NetworkTopology;
 
 /// Convert an object into CBOR binary.
@@ -190,6 +194,58 @@ fn route_bitcoin_message(
     Ok(canister_id)
 }
 
+/// Returns the page of the canister history matching the filters in `args`.
+/// Only the controllers of the canister are allowed to read its history.
+fn get_canister_history(
+    args: CanisterHistoryArgs,
+    sender: PrincipalId,
+    state: &ReplicatedState,
+) -> Result<WasmResult, UserError> {
+    let canister_id = args.get_canister_id();
+    let canister = state.canister_state(&canister_id).ok_or_else(|| {
+        UserError::new(
+            ErrorCode::CanisterNotFound,
+            format!("Canister {} not found.", canister_id),
+        )
+    })?;
+    if !canister.system_state.controllers.contains(&sender) {
+        return Err(UserError::new(
+            ErrorCode::CanisterInvalidController,
+            format!(
+                "Only the controllers of the canister {} can read its history. \
+                Sender's ID is {}.",
+                canister_id, sender
+            ),
+        ));
+    }
+
+    let canister_history = canister.system_state.get_canister_history();
+    let start_index = args.start_index.unwrap_or(0);
+    let max_changes = args
+        .max_changes
+        .unwrap_or(MAX_CANISTER_HISTORY_CHANGES)
+        .min(MAX_CANISTER_HISTORY_CHANGES) as usize;
+    let mut changes = vec![];
+    let mut next_start_index = None;
+    for (index, change) in canister_history
+        .get_indexed_changes()
+        .filter(|(index, change)| *index >= start_index && args.matches(change))
+    {
+        if changes.len() == max_changes {
+            next_start_index = Some(index);
+            break;
+        }
+        changes.push(change.as_ref().clone());
+    }
+
+    let response = CanisterHistoryResponse::new(
+        canister_history.get_total_num_changes(),
+        changes,
+        next_start_index,
+    );
+    Ok(WasmResult::Reply(response.encode()))
+}
+
 impl QueryHandler for InternalHttpQueryHandler {
     type State = ReplicatedState;
 
@@ -212,6 +268,10 @@ impl QueryHandler for InternalHttpQueryHandler {
                     let args = BitcoinGetBalanceArgs::decode(&query.method_payload)?;
                     args.network
                 }
+                Ok(QueryMethod::CanisterHistory) => {
+                    let args = CanisterHistoryArgs::decode(&query.method_payload)?;
+                    return get_canister_history(args, query.source.get(), state.get_ref());
+                }
                 Err(_) => {
                     return Err(UserError::new(
                         ErrorCode::CanisterMethodNotFound,
@@ -226,18 +286,25 @@ impl QueryHandler for InternalHttpQueryHandler {
 
         // Check the query cache first (if the query caching is enabled).
         // If a valid cache entry found, the result will be immediately returned.
-        // Otherwise, the key and the env will be kept for the `insert` below.
-        let (cache_entry_key, cache_entry_env) = if self.config.query_caching == FlagStatus::Enabled
-        {
+        // Otherwise, the key, the env and the state will be kept for the `insert` below.
+        let cache_entry = if self.config.query_caching == FlagStatus::Enabled {
             let key = query_cache::EntryKey::from(&query);
             let env = query_cache::EntryEnv::try_from((&key, state.get_ref().as_ref()))?;
-
-            if let Some(result) = self.query_cache.get_valid_result(&key, &env) {
+            let canister = state.get_ref().get_active_canister(&key.receiver)?;
+
+            if let Some(result) = self.query_cache.get_valid_result(&key, &env, canister) {
+                // Cached results may outlive changes of the cycles balance
+                // that they do not depend on, but not a frozen canister.
+                query_context::validate_canister_not_frozen(
+                    canister,
+                    &self.cycles_account_manager,
+                    &state.get_ref().metadata.network_topology,
+                )?;
                 return result;
             }
-            (Some(key), Some(env))
+            Some((key, env, Arc::clone(state.get_ref())))
         } else {
-            (None, None)
+            None
         };
 
         // Letting the canister grow arbitrarily when executing the
@@ -276,10 +343,23 @@ impl QueryHandler for InternalHttpQueryHandler {
         );
 
         // Add the query execution result to the query cache  (if the query caching is enabled).
+        // Results that only depend on a known part of the receiver's state are
+        // kept until that part changes.
         if self.config.query_caching == FlagStatus::Enabled {
-            if let (Some(key), Some(env)) = (cache_entry_key, cache_entry_env) {
-                self.query_cache
-                    .push(key, query_cache::EntryValue::new(env, result.clone()));
+            if let Some((key, env, state)) = cache_entry {
+                let value = match (
+                    context.state_reads(),
+                    state.get_active_canister(&key.receiver),
+                ) {
+                    (Some(state_reads), Ok(canister)) => query_cache::EntryValue::with_state_reads(
+                        env,
+                        result.clone(),
+                        state_reads,
+                        canister,
+                    ),
+                    _ => query_cache::EntryValue::new(env, result.clone()),
+                };
+                self.query_cache.push(key, value);
             }
         }
         result
@@ -1,11 +1,19 @@
 use ic_base_types::{CanisterId, NumBytes};
+use ic_crypto_sha2::Sha256;
 use ic_error_types::UserError;
+use ic_ic00_types::CanisterStatusType;
+use ic_interfaces::execution_environment::StateReads;
 use ic_metrics::MetricsRegistry;
-use ic_replicated_state::ReplicatedState;
-use ic_types::{ingress::WasmResult, messages::UserQuery, CountBytes, Cycles, Time, UserId};
+use ic_replicated_state::{CanisterState, Global, NumWasmPages, PageIndex, ReplicatedState};
+use ic_types::{
+    ingress::WasmResult, messages::UserQuery, CountBytes, Cycles, MemoryAllocation, Time, UserId,
+};
 use ic_utils_lru_cache::LruCache;
 use prometheus::{Histogram, IntCounter, IntGauge};
-use std::{mem::size_of_val, sync::Mutex};
+use std::{
+    mem::{size_of, size_of_val},
+    sync::{Arc, Mutex},
+};
 
 use crate::metrics::duration_histogram;
 
@@ -20,6 +28,8 @@ pub(crate) struct QueryCacheMetrics {
     pub invalidated_entries_by_time: IntCounter,
     pub invalidated_entries_by_canister_version: IntCounter,
     pub invalidated_entries_by_canister_balance: IntCounter,
+    pub invalidated_entries_by_execution_env: IntCounter,
+    pub invalidated_entries_by_memory_pages: IntCounter,
     pub invalidated_entries_duration: Histogram,
     pub count_bytes: IntGauge,
     pub len: IntGauge,
@@ -61,6 +71,16 @@ impl QueryCacheMetrics {
                 "execution_query_cache_invalidated_entries_by_canister_balance_total",
                 "The total number of invalidated entries due to the changed canister balance",
             ),
+            invalidated_entries_by_execution_env: metrics_registry.int_counter(
+                "execution_query_cache_invalidated_entries_by_execution_env_total",
+                "The total number of invalidated entries due to the changed canister module, \
+                 globals, memory sizes, status or settings",
+            ),
+            invalidated_entries_by_memory_pages: metrics_registry.int_counter(
+                "execution_query_cache_invalidated_entries_by_memory_pages_total",
+                "The total number of invalidated entries due to changed memory pages \
+                 read by the query",
+            ),
             invalidated_entries_duration: duration_histogram(
                 "execution_query_cache_invalidated_entries_duration_seconds",
                 "The duration of invalidated cache entries in seconds",
@@ -116,7 +136,8 @@ impl From<&UserQuery> for EntryKey {
 /// Query Cache entry environment metadata.
 ///
 /// The structure captures the environment metadata. The cache entry is valid
-/// only when its environment metadata matches the current state environment.
+/// only when the parts of its environment metadata that the query depends on
+/// match the current state environment.
 #[derive(PartialEq)]
 pub(crate) struct EntryEnv {
     /// The Consensus-determined time when the cache entry was created.
@@ -125,11 +146,13 @@ pub(crate) struct EntryEnv {
     pub canister_version: u64,
     /// Receiving canister cycles balance.
     pub canister_balance: Cycles,
+    /// Receiving canister execution environment.
+    pub execution_env: ExecutionEnv,
 }
 
 impl CountBytes for EntryEnv {
     fn count_bytes(&self) -> usize {
-        size_of_val(self)
+        size_of_val(self) + self.execution_env.exported_globals.len() * size_of::<Global>()
     }
 }
 
@@ -142,8 +165,139 @@ impl TryFrom<(&EntryKey, &ReplicatedState)> for EntryEnv {
             batch_time: state.metadata.batch_time,
             canister_version: canister.system_state.canister_version,
             canister_balance: canister.system_state.balance(),
+            execution_env: ExecutionEnv::from(canister),
+        })
+    }
+}
+
+/// The parts of the receiving canister state that a query execution depends
+/// on without reading them through the System API or from the memory pages.
+///
+/// Every message execution changes the canister version, so the query cache
+/// compares these explicitly instead.
+#[derive(Default, PartialEq)]
+pub(crate) struct ExecutionEnv {
+    /// Whether the canister is running, otherwise queries are rejected.
+    pub is_running: bool,
+    /// Hash of the Wasm module, `None` if the canister is empty.
+    pub module_hash: Option<[u8; 32]>,
+    /// Values of the exported globals.
+    pub exported_globals: Vec<Global>,
+    /// Sizes of the Wasm and the stable memory.
+    pub wasm_memory_size: NumWasmPages,
+    pub stable_memory_size: NumWasmPages,
+    /// Memory settings and usage, which limit how much memory a query may grow.
+    pub memory_allocation: MemoryAllocation,
+    pub wasm_memory_limit: Option<NumBytes>,
+    pub memory_usage: NumBytes,
+}
+
+impl From<&CanisterState> for ExecutionEnv {
+    fn from(canister: &CanisterState) -> Self {
+        let execution_state = canister.execution_state.as_ref();
+        Self {
+            is_running: canister.status() == CanisterStatusType::Running,
+            module_hash: execution_state.map(|es| es.wasm_binary.binary.module_hash()),
+            exported_globals: execution_state
+                .map(|es| es.exported_globals.clone())
+                .unwrap_or_default(),
+            wasm_memory_size: execution_state
+                .map(|es| es.wasm_memory.size)
+                .unwrap_or_default(),
+            stable_memory_size: execution_state
+                .map(|es| es.stable_memory.size)
+                .unwrap_or_default(),
+            memory_allocation: canister.system_state.memory_allocation,
+            wasm_memory_limit: canister.system_state.wasm_memory_limit,
+            memory_usage: canister.memory_usage(),
+        }
+    }
+}
+
+/// The maximum number of accessed memory pages tracked per cache entry.
+///
+/// The accessed pages are hashed on every cache hit. Queries that access more
+/// pages are cached as if their reads were unknown, i.e. the entry is only
+/// valid for the same canister version.
+const MAX_TRACKED_PAGES: usize = 1024;
+
+////////////////////////////////////////////////////////////////////////
+/// Query Cache entry reads.
+///
+/// The parts of the receiving canister state that the cached query read.
+struct EntryReads {
+    /// Whether the query read the time or the data certificate.
+    time: bool,
+    /// Whether the query read the cycles balance.
+    canister_balance: bool,
+    /// Whether the query read the canister version or the controllers.
+    canister_version: bool,
+    /// The Wasm memory pages accessed by the query.
+    wasm_memory_pages: Vec<PageIndex>,
+    /// The stable memory pages accessed by the query.
+    stable_memory_pages: Vec<PageIndex>,
+    /// Digest of the contents of the accessed pages when the entry was created.
+    pages_digest: [u8; 32],
+}
+
+impl CountBytes for EntryReads {
+    fn count_bytes(&self) -> usize {
+        size_of_val(self)
+            + (self.wasm_memory_pages.len() + self.stable_memory_pages.len())
+                * size_of::<PageIndex>()
+    }
+}
+
+impl EntryReads {
+    /// Returns `None` if the accessed memory pages are not known or there are
+    /// more than [MAX_TRACKED_PAGES] of them.
+    fn new(state_reads: &StateReads, canister: &CanisterState) -> Option<Self> {
+        let wasm_memory_pages = state_reads.wasm_memory_pages.as_ref()?;
+        let stable_memory_pages = state_reads.stable_memory_pages.as_ref()?;
+        if wasm_memory_pages.len() + stable_memory_pages.len() > MAX_TRACKED_PAGES {
+            return None;
+        }
+        let wasm_memory_pages = wasm_memory_pages.clone();
+        let stable_memory_pages = stable_memory_pages.clone();
+        let pages_digest = pages_digest(canister, &wasm_memory_pages, &stable_memory_pages)?;
+        Some(Self {
+            time: state_reads.time,
+            canister_balance: state_reads.cycles_balance,
+            canister_version: state_reads.canister_version,
+            wasm_memory_pages,
+            stable_memory_pages,
+            pages_digest,
         })
     }
+
+    /// Returns true if the accessed memory pages are unchanged. Hashes the
+    /// pages, so it should be called without holding the cache lock.
+    fn is_valid_memory_pages(&self, canister: &CanisterState) -> bool {
+        pages_digest(canister, &self.wasm_memory_pages, &self.stable_memory_pages)
+            == Some(self.pages_digest)
+    }
+}
+
+/// Returns the digest of the contents of the given Wasm and stable memory
+/// pages of the canister, or `None` if the canister is empty.
+fn pages_digest(
+    canister: &CanisterState,
+    wasm_memory_pages: &[PageIndex],
+    stable_memory_pages: &[PageIndex],
+) -> Option<[u8; 32]> {
+    let execution_state = canister.execution_state.as_ref()?;
+    let mut hasher = Sha256::new();
+    for (page_map, pages) in [
+        (&execution_state.wasm_memory.page_map, wasm_memory_pages),
+        (&execution_state.stable_memory.page_map, stable_memory_pages),
+    ] {
+        hasher.write(&(pages.len() as u64).to_le_bytes());
+        for page in pages {
+            hasher.write(&page.get().to_le_bytes());
+            hasher.write(page_map.get_page(*page));
+        }
+    }
+    Some(hasher.finish())
 }
 
 ////////////////////////////////////////////////////////////////////////
@@ -151,33 +305,75 @@ impl TryFrom<(&EntryKey, &ReplicatedState)> for EntryEnv {
 pub(crate) struct EntryValue {
     env: EntryEnv,
     result: Result<WasmResult, UserError>,
+    /// The parts of the state the query read. If unknown, the entry is only
+    /// valid in exactly the same environment. Shared, so that the memory pages
+    /// can be checked outside of the cache lock without copying them.
+    reads: Option<Arc<EntryReads>>,
 }
 
 impl CountBytes for EntryValue {
     fn count_bytes(&self) -> usize {
-        self.env.count_bytes() + self.result.count_bytes()
+        self.env.count_bytes()
+            + self.result.count_bytes()
+            + self.reads.as_ref().map_or(0, |reads| reads.count_bytes())
     }
 }
 
 impl EntryValue {
     pub(crate) fn new(env: EntryEnv, result: Result<WasmResult, UserError>) -> Self {
-        Self { env, result }
+        Self {
+            env,
+            result,
+            reads: None,
+        }
     }
 
-    fn is_valid(&self, env: &EntryEnv) -> bool {
-        self.env == *env
+    /// Creates an entry for a result that only depends on the given parts of
+    /// the receiving canister state.
+    pub(crate) fn with_state_reads(
+        env: EntryEnv,
+        result: Result<WasmResult, UserError>,
+        state_reads: &StateReads,
+        canister: &CanisterState,
+    ) -> Self {
+        Self {
+            env,
+            result,
+            reads: EntryReads::new(state_reads, canister).map(Arc::new),
+        }
+    }
+
+    /// Returns true if the query result depends on the part of the state
+    /// selected by `read`. Everything is a dependency if the reads are unknown.
+    fn depends_on(&self, read: impl Fn(&EntryReads) -> bool) -> bool {
+        self.reads.as_ref().map_or(true, read)
+    }
+
+    /// Checks everything but the memory pages, which are checked separately
+    /// with the [EntryReads] outside of the cache lock.
+    fn is_valid_env(&self, env: &EntryEnv) -> bool {
+        self.is_valid_time(env)
+            && self.is_valid_canister_version(env)
+            && self.is_valid_canister_balance(env)
+            && self.is_valid_execution_env(env)
     }
 
     fn is_valid_time(&self, env: &EntryEnv) -> bool {
-        self.env.batch_time == env.batch_time
+        !self.depends_on(|reads| reads.time) || self.env.batch_time == env.batch_time
     }
 
     fn is_valid_canister_version(&self, env: &EntryEnv) -> bool {
-        self.env.canister_version == env.canister_version
+        !self.depends_on(|reads| reads.canister_version)
+            || self.env.canister_version == env.canister_version
     }
 
     fn is_valid_canister_balance(&self, env: &EntryEnv) -> bool {
-        self.env.canister_balance == env.canister_balance
+        !self.depends_on(|reads| reads.canister_balance)
+            || self.env.canister_balance == env.canister_balance
+    }
+
+    fn is_valid_execution_env(&self, env: &EntryEnv) -> bool {
+        self.env.execution_env == env.execution_env
     }
 
     fn result(&self) -> Result<WasmResult, UserError> {
@@ -213,24 +409,24 @@ impl QueryCache {
         }
     }
 
+    /// Returns the cached result of the query with the given key, if it is
+    /// still valid for the given environment and the current state of the
+    /// receiving canister.
+    ///
+    /// The accessed memory pages are hashed without holding the cache lock, so
+    /// that queries to other canisters are not blocked meanwhile.
     pub(crate) fn get_valid_result(
         &self,
         key: &EntryKey,
         env: &EntryEnv,
+        canister: &CanisterState,
     ) -> Option<Result<WasmResult, UserError>> {
-        let mut cache = self.cache.lock().unwrap();
         let now = env.batch_time;
 
-        if let Some(value) = cache.get(key) {
-            if value.is_valid(env) {
-                let res = value.result();
-                // Update the metrics.
-                self.metrics.hits.inc();
-                let count_bytes = cache.count_bytes() as i64;
-                self.metrics.count_bytes.set(count_bytes);
-                // The cache entry is valid, return it.
-                return Some(res);
-            } else {
+        let (res, reads) = {
+            let mut cache = self.cache.lock().unwrap();
+            let value = cache.get(key)?;
+            if !value.is_valid_env(env) {
                 // Update the metrics.
                 self.metrics.invalidated_entries.inc();
                 self.metrics
@@ -246,6 +442,40 @@ impl QueryCache {
                 if !value.is_valid_canister_balance(env) {
                     self.metrics.invalidated_entries_by_canister_balance.inc();
                 }
+                if !value.is_valid_execution_env(env) {
+                    self.metrics.invalidated_entries_by_execution_env.inc();
+                }
+                // The cache entry is no longer valid, remove it.
+                cache.pop(key);
+                return None;
+            }
+            (value.result(), value.reads.clone())
+        };
+
+        // With unknown reads, changes of the memory are covered by the canister
+        // version, which changes with every message execution.
+        let reads = match reads {
+            Some(reads) if !reads.is_valid_memory_pages(canister) => reads,
+            _ => {
+                // Update the metrics.
+                self.metrics.hits.inc();
+                let count_bytes = self.cache.lock().unwrap().count_bytes() as i64;
+                self.metrics.count_bytes.set(count_bytes);
+                // The cache entry is valid, return it.
+                return Some(res);
+            }
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        // The entry may have been replaced or evicted while the lock was released.
+        if let Some(value) = cache.get(key) {
+            if matches!(&value.reads, Some(value_reads) if Arc::ptr_eq(value_reads, &reads)) {
+                // Update the metrics.
+                self.metrics.invalidated_entries.inc();
+                self.metrics
+                    .invalidated_entries_duration
+                    .observe(value.elapsed_seconds(now));
+                self.metrics.invalidated_entries_by_memory_pages.inc();
                 // The cache entry is no longer valid, remove it.
                 cache.pop(key);
             }
@@ -291,6 +521,7 @@ mod tests {
             batch_time: current_time,
             canister_version: 1,
             canister_balance: Cycles::new(0),
+            execution_env: Default::default(),
         };
         let entry_value = EntryValue::new(entry_env, Result::Ok(WasmResult::Reply(vec![])));
         let forward_time = current_time + Duration::from_secs(2);
@@ -11,7 +11,9 @@ use ic_config::flag_status::FlagStatus;
 use ic_constants::SMALL_APP_SUBNET_MAX_SIZE;
 use ic_cycles_account_manager::{CyclesAccountManager, ResourceSaturation};
 use ic_error_types::{ErrorCode, RejectCode, UserError};
-use ic_interfaces::execution_environment::{ExecutionMode, HypervisorError, SubnetAvailableMemory};
+use ic_interfaces::execution_environment::{
+    ExecutionMode, HypervisorError, StateReads, SubnetAvailableMemory,
+};
 use ic_interfaces_state_manager::Labeled;
 use ic_logger::{error, ReplicaLogger};
 use ic_registry_subnet_type::SubnetType;
@@ -77,6 +79,34 @@ fn wasm_query_method(
     }
 }
 
+/// Returns an error if the given canister is frozen, i.e. its cycles balance
+/// is below its freezing threshold. Frozen canisters do not process queries.
+pub(super) fn validate_canister_not_frozen(
+    canister: &CanisterState,
+    cycles_account_manager: &CyclesAccountManager,
+    network_topology: &NetworkTopology,
+) -> Result<(), UserError> {
+    let subnet_size = network_topology
+        .get_subnet_size(&cycles_account_manager.get_subnet_id())
+        .unwrap_or(SMALL_APP_SUBNET_MAX_SIZE);
+    if cycles_account_manager.freeze_threshold_cycles(
+        canister.system_state.freeze_threshold,
+        canister.system_state.memory_allocation,
+        canister.memory_usage(),
+        canister.message_memory_usage(),
+        canister.scheduler_state.compute_allocation,
+        subnet_size,
+        canister.system_state.reserved_balance(),
+    ) > canister.system_state.balance()
+    {
+        return Err(UserError::new(
+            ErrorCode::CanisterOutOfCycles,
+            format!("Canister {} is unable to process query calls because it's frozen. Please top up the canister with cycles and try again.", canister.canister_id()))
+        );
+    }
+    Ok(())
+}
+
 /// Executes a single user query along with its outgoing query calls.
 pub(super) struct QueryContext<'a> {
     log: &'a ReplicaLogger,
@@ -98,6 +128,9 @@ pub(super) struct QueryContext<'a> {
     query_context_time_limit: Duration,
     query_critical_error: &'a IntCounter,
     local_query_execution_stats: Option<&'a QueryStatsCollector>,
+    // The parts of the state that the result of `run()` depends on. Only known
+    // if the result was produced by a single execution of the root query.
+    state_reads: Option<StateReads>,
 }
 
 impl<'a> QueryContext<'a> {
@@ -146,6 +179,7 @@ impl<'a> QueryContext<'a> {
             query_context_time_limit: max_query_call_walltime,
             query_critical_error,
             local_query_execution_stats,
+            state_reads: None,
         }
     }
 
@@ -169,25 +203,11 @@ impl<'a> QueryContext<'a> {
         let canister_id = query.receiver;
         let old_canister = self.state.get_ref().get_active_canister(&canister_id)?;
 
-        let subnet_size = self
-            .network_topology
-            .get_subnet_size(&cycles_account_manager.get_subnet_id())
-            .unwrap_or(SMALL_APP_SUBNET_MAX_SIZE);
-        if cycles_account_manager.freeze_threshold_cycles(
-            old_canister.system_state.freeze_threshold,
-            old_canister.system_state.memory_allocation,
-            old_canister.memory_usage(),
-            old_canister.message_memory_usage(),
-            old_canister.scheduler_state.compute_allocation,
-            subnet_size,
-            old_canister.system_state.reserved_balance(),
-        ) > old_canister.system_state.balance()
-        {
-            return Err(UserError::new(
-                ErrorCode::CanisterOutOfCycles,
-                format!("Canister {} is unable to process query calls because it's frozen. Please top up the canister with cycles and try again.", canister_id))
-            );
-        }
+        validate_canister_not_frozen(
+            old_canister,
+            &cycles_account_manager,
+            &self.network_topology,
+        )?;
 
         let call_origin = CallOrigin::Query(query.source);
 
@@ -208,7 +228,7 @@ impl<'a> QueryContext<'a> {
             }
         };
 
-        let (mut canister, mut result) = {
+        let (mut canister, mut result, state_reads) = {
             let measurement_scope =
                 MeasurementScope::nested(&metrics.query_initial_call, measurement_scope);
             self.execute_query(
@@ -219,6 +239,7 @@ impl<'a> QueryContext<'a> {
                 &measurement_scope,
             )
         };
+        self.state_reads = Some(state_reads);
 
         // An attempt to call another query will result in `ContractViolation`.
         // If that's the case then retry query execution as `Stateful` if the
@@ -233,7 +254,9 @@ impl<'a> QueryContext<'a> {
                     let measurement_scope =
                         MeasurementScope::nested(&metrics.query_retry_call, measurement_scope);
                     let old_canister = self.state.get_ref().get_active_canister(&canister_id)?;
-                    let (new_canister, new_result) = self.execute_query(
+                    // The result depends on both executions.
+                    self.state_reads = None;
+                    let (new_canister, new_result, _) = self.execute_query(
                         old_canister.clone(),
                         method,
                         query.method_payload.as_slice(),
@@ -258,6 +281,7 @@ impl<'a> QueryContext<'a> {
                 // The query did not produce any response. We need to evaluate
                 // the query call graph. Note that if the call graph is empty,
                 // then a synthetic reject response will be generated.
+                self.state_reads = None;
                 let measurement_scope =
                     MeasurementScope::nested(&metrics.query_spawned_calls, measurement_scope);
                 let mut requests = VecDeque::new();
@@ -283,6 +307,13 @@ impl<'a> QueryContext<'a> {
         }
     }
 
+    /// Returns the parts of the receiver's state that the result of `run()`
+    /// depends on, or `None` if they are unknown, e.g. because the query
+    /// called other canisters.
+    pub(super) fn state_reads(&self) -> Option<&StateReads> {
+        self.state_reads.as_ref()
+    }
+
     // A helper function that extracts the query calls of the given canister and
     // enqueues them onto the given deque.
     fn extract_query_requests(
@@ -365,7 +396,11 @@ impl<'a> QueryContext<'a> {
         method_payload: &[u8],
         query_kind: NonReplicatedQueryKind,
         measurement_scope: &MeasurementScope,
-    ) -> (CanisterState, Result<Option<WasmResult>, UserError>) {
+    ) -> (
+        CanisterState,
+        Result<Option<WasmResult>, UserError>,
+        StateReads,
+    ) {
         if let WasmMethod::CompositeQuery(_) = &method_name {
             if self.composite_queries == FlagStatus::Disabled {
                 return (
@@ -374,6 +409,7 @@ impl<'a> QueryContext<'a> {
                         ErrorCode::CanisterContractViolation,
                         "Composite queries are not enabled yet",
                     )),
+                    StateReads::default(),
                 );
             }
         }
@@ -385,7 +421,7 @@ impl<'a> QueryContext<'a> {
         let execution_parameters = self.execution_parameters(&canister, instruction_limits);
 
         let data_certificate = self.get_data_certificate(&canister.canister_id());
-        let (mut canister, instructions_left, result, call_context_id) =
+        let (mut canister, instructions_left, result, call_context_id, state_reads) =
             execute_non_replicated_query(
                 query_kind,
                 method_name,
@@ -438,7 +474,7 @@ impl<'a> QueryContext<'a> {
                 instructions_executed,
             );
         }
-        (canister, result)
+        (canister, result, state_reads)
     }
 
     fn finish(
@@ -726,7 +762,7 @@ impl<'a> QueryContext<'a> {
             }
         };
 
-        let (mut canister, result) = self.execute_query(
+        let (mut canister, result, _) = self.execute_query(
             canister.clone(),
             method,
             request.method_payload.as_slice(),
@@ -964,6 +1000,7 @@ impl<'a> QueryContext<'a> {
         ExecutionParameters {
             instruction_limits,
             canister_memory_limit: canister.memory_limit(self.max_canister_memory_size),
+            wasm_memory_limit: canister.system_state.wasm_memory_limit,
             memory_allocation: canister.memory_allocation(),
             compute_allocation: canister.compute_allocation(),
             subnet_type: self.own_subnet_type,
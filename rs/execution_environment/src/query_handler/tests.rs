@@ -12,9 +12,7 @@ use ic_test_utilities::{
 };
 use ic_test_utilities_execution_environment::{ExecutionTest, ExecutionTestBuilder};
 use ic_types::{
-    ingress::WasmResult,
-    messages::{CanisterTask, UserQuery},
-    time, CountBytes, Cycles, NumInstructions,
+    ingress::WasmResult, messages::UserQuery, time, CountBytes, Cycles, NumInstructions,
 };
 use std::{sync::Arc, time::Duration};
 
@@ -372,25 +370,25 @@ fn query_cache_metrics_invalidated_entries_work() {
 
     let mut test = ExecutionTestBuilder::new().with_query_caching().build();
 
-    let canister_id = test.universal_canister_with_cycles(CYCLES_BALANCE).unwrap();
+    let canister_id = test.canister_from_wat(QUERY_CACHE_WAT).unwrap();
 
-    for _ in 0..ITERATIONS {
+    for i in 0..ITERATIONS {
         // Every query is the same and should hit the same cache entry.
         let output = test.query(
             UserQuery {
                 source: user_test_id(1),
                 receiver: canister_id,
-                method_name: "query".into(),
-                method_payload: wasm().reply_data(&[42]).build(),
+                method_name: "f1".into(),
+                method_payload: vec![],
                 ingress_expiry: 0,
                 nonce: None,
             },
             Arc::new(test.state().clone()),
             vec![],
         );
-        assert_eq!(output, Ok(WasmResult::Reply([42].into())));
-        // Executing a default UC heartbeat should render the cache entry invalid.
-        test.canister_task(canister_id, CanisterTask::Heartbeat);
+        assert_eq!(output, Ok(WasmResult::Reply(vec![b'4' + i as u8, b'2'])));
+        // Changing the memory read by the query should render the cache entry invalid.
+        test.ingress(canister_id, "write_reply", vec![]).unwrap();
     }
 
     let query_handler = downcast_query_handler(test.query_handler());
@@ -493,6 +491,8 @@ const QUERY_CACHE_WAT: &str = r#"
     (import "ic0" "msg_reply_data_append"
         (func $msg_reply_data_append (param i32 i32)))
     (import "ic0" "canister_cycle_balance" (func $canister_cycle_balance (result i64)))
+    (import "ic0" "canister_version" (func $canister_version (result i64)))
+    (import "ic0" "time" (func $time (result i64)))
 
     (memory 100)
     (data (i32.const 0) "42")
@@ -502,6 +502,38 @@ const QUERY_CACHE_WAT: &str = r#"
         (call $msg_reply)
     )
 
+    (func (export "canister_query read_env")
+        (drop (call $time))
+        (drop (call $canister_version))
+        (drop (call $canister_cycle_balance))
+        (call $f)
+    )
+
+    ;; Changes the reply of the queries by incrementing its first byte.
+    (func (export "canister_update write_reply")
+        (i32.store8 (i32.const 0)
+            (i32.add (i32.load8_u (i32.const 0)) (i32.const 1)))
+        (call $msg_reply)
+    )
+
+    ;; Reads more memory pages than the query cache tracks per entry.
+    (func (export "canister_query read_many_pages")
+        (local $addr i32)
+        (loop $loop
+            (drop (i32.load8_u (local.get $addr)))
+            (local.set $addr (i32.add (local.get $addr) (i32.const 4096)))
+            (br_if $loop (i32.lt_u (local.get $addr) (i32.const 4505600)))
+        )
+        (call $f)
+    )
+
+    ;; Writes to a page that none of the queries reads. The page is far enough
+    ;; from the data that it is not prefetched when the data is read.
+    (func (export "canister_update write_other_page")
+        (i32.store8 (i32.const 655360) (i32.const 1))
+        (call $msg_reply)
+    )
+
     (func (export "canister_query canister_balance_sized_reply")
         ;; Produce a `canister_cycle_balance` sized reply
         (call $msg_reply_data_append
@@ -594,13 +626,13 @@ fn query_cache_key_different_method_payload_returns_different_results() {
 #[test]
 fn query_cache_env_different_batch_time_returns_different_results() {
     let mut test = ExecutionTestBuilder::new().with_query_caching().build();
-    let canister_id = test.universal_canister_with_cycles(CYCLES_BALANCE).unwrap();
+    let canister_id = test.canister_from_wat(QUERY_CACHE_WAT).unwrap();
     let output_1 = test.query(
         UserQuery {
             source: user_test_id(1),
             receiver: canister_id,
-            method_name: "query".into(),
-            method_payload: wasm().reply_data(&[42]).build(),
+            method_name: "read_env".into(),
+            method_payload: vec![],
             ingress_expiry: 0,
             nonce: None,
         },
@@ -610,15 +642,15 @@ fn query_cache_env_different_batch_time_returns_different_results() {
     {
         let query_handler = downcast_query_handler(test.query_handler());
         assert_eq!(query_handler.query_cache.metrics.misses.get(), 1);
-        assert_eq!(output_1, Ok(WasmResult::Reply([42].into())));
+        assert_eq!(output_1, Ok(WasmResult::Reply(b"42".to_vec())));
     }
     test.state_mut().metadata.batch_time += Duration::from_secs(1);
     let output_2 = test.query(
         UserQuery {
             source: user_test_id(1),
             receiver: canister_id,
-            method_name: "query".into(),
-            method_payload: wasm().reply_data(&[42]).build(),
+            method_name: "read_env".into(),
+            method_payload: vec![],
             ingress_expiry: 0,
             nonce: None,
         },
@@ -653,13 +685,13 @@ fn query_cache_env_invalidated_entries_negative_duration_works() {
     // As there are no updates, the default system time is unix epoch, so we explicitly set it here.
     test.state_mut().metadata.batch_time = time::GENESIS;
 
-    let canister_id = test.universal_canister_with_cycles(CYCLES_BALANCE).unwrap();
+    let canister_id = test.canister_from_wat(QUERY_CACHE_WAT).unwrap();
     let output_1 = test.query(
         UserQuery {
             source: user_test_id(1),
             receiver: canister_id,
-            method_name: "query".into(),
-            method_payload: wasm().reply_data(&[42]).build(),
+            method_name: "read_env".into(),
+            method_payload: vec![],
             ingress_expiry: 0,
             nonce: None,
         },
@@ -676,8 +708,8 @@ fn query_cache_env_invalidated_entries_negative_duration_works() {
         UserQuery {
             source: user_test_id(1),
             receiver: canister_id,
-            method_name: "query".into(),
-            method_payload: wasm().reply_data(&[42]).build(),
+            method_name: "read_env".into(),
+            method_payload: vec![],
             ingress_expiry: 0,
             nonce: None,
         },
@@ -705,13 +737,13 @@ fn query_cache_env_invalidated_entries_negative_duration_works() {
 #[test]
 fn query_cache_env_different_canister_version_returns_different_results() {
     let mut test = ExecutionTestBuilder::new().with_query_caching().build();
-    let canister_id = test.universal_canister_with_cycles(CYCLES_BALANCE).unwrap();
+    let canister_id = test.canister_from_wat(QUERY_CACHE_WAT).unwrap();
     let output_1 = test.query(
         UserQuery {
             source: user_test_id(1),
             receiver: canister_id,
-            method_name: "query".into(),
-            method_payload: wasm().reply_data(&[42]).build(),
+            method_name: "read_env".into(),
+            method_payload: vec![],
             ingress_expiry: 0,
             nonce: None,
         },
@@ -721,7 +753,7 @@ fn query_cache_env_different_canister_version_returns_different_results() {
     {
         let query_handler = downcast_query_handler(test.query_handler());
         assert_eq!(query_handler.query_cache.metrics.misses.get(), 1);
-        assert_eq!(output_1, Ok(WasmResult::Reply([42].into())));
+        assert_eq!(output_1, Ok(WasmResult::Reply(b"42".to_vec())));
     }
     test.canister_state_mut(canister_id)
         .system_state
@@ -730,8 +762,8 @@ fn query_cache_env_different_canister_version_returns_different_results() {
         UserQuery {
             source: user_test_id(1),
             receiver: canister_id,
-            method_name: "query".into(),
-            method_payload: wasm().reply_data(&[42]).build(),
+            method_name: "read_env".into(),
+            method_payload: vec![],
             ingress_expiry: 0,
             nonce: None,
         },
@@ -762,13 +794,13 @@ fn query_cache_env_different_canister_version_returns_different_results() {
 #[test]
 fn query_cache_env_different_canister_balance_returns_different_results() {
     let mut test = ExecutionTestBuilder::new().with_query_caching().build();
-    let canister_id = test.universal_canister_with_cycles(CYCLES_BALANCE).unwrap();
+    let canister_id = test.canister_from_wat(QUERY_CACHE_WAT).unwrap();
     let output_1 = test.query(
         UserQuery {
             source: user_test_id(1),
             receiver: canister_id,
-            method_name: "query".into(),
-            method_payload: wasm().reply_data(&[42]).build(),
+            method_name: "read_env".into(),
+            method_payload: vec![],
             ingress_expiry: 0,
             nonce: None,
         },
@@ -778,7 +810,7 @@ fn query_cache_env_different_canister_balance_returns_different_results() {
     {
         let query_handler = downcast_query_handler(test.query_handler());
         assert_eq!(query_handler.query_cache.metrics.misses.get(), 1);
-        assert_eq!(output_1, Ok(WasmResult::Reply([42].into())));
+        assert_eq!(output_1, Ok(WasmResult::Reply(b"42".to_vec())));
     }
     test.canister_state_mut(canister_id)
         .system_state
@@ -787,8 +819,8 @@ fn query_cache_env_different_canister_balance_returns_different_results() {
         UserQuery {
             source: user_test_id(1),
             receiver: canister_id,
-            method_name: "query".into(),
-            method_payload: wasm().reply_data(&[42]).build(),
+            method_name: "read_env".into(),
+            method_payload: vec![],
             ingress_expiry: 0,
             nonce: None,
         },
@@ -819,13 +851,13 @@ fn query_cache_env_different_canister_balance_returns_different_results() {
 #[test]
 fn query_cache_env_combined_invalidation() {
     let mut test = ExecutionTestBuilder::new().with_query_caching().build();
-    let canister_id = test.universal_canister_with_cycles(CYCLES_BALANCE).unwrap();
+    let canister_id = test.canister_from_wat(QUERY_CACHE_WAT).unwrap();
     let output_1 = test.query(
         UserQuery {
             source: user_test_id(1),
             receiver: canister_id,
-            method_name: "query".into(),
-            method_payload: wasm().reply_data(&[42]).build(),
+            method_name: "read_env".into(),
+            method_payload: vec![],
             ingress_expiry: 0,
             nonce: None,
         },
@@ -843,8 +875,8 @@ fn query_cache_env_combined_invalidation() {
         UserQuery {
             source: user_test_id(1),
             receiver: canister_id,
-            method_name: "query".into(),
-            method_payload: wasm().reply_data(&[42]).build(),
+            method_name: "read_env".into(),
+            method_payload: vec![],
             ingress_expiry: 0,
             nonce: None,
         },
@@ -864,6 +896,139 @@ fn query_cache_env_combined_invalidation() {
     }
 }
 
+#[test]
+fn query_cache_survives_changes_of_unread_state() {
+    let mut test = ExecutionTestBuilder::new().with_query_caching().build();
+    let canister_id = test.canister_from_wat(QUERY_CACHE_WAT).unwrap();
+    let output_1 = test.query(
+        UserQuery {
+            source: user_test_id(1),
+            receiver: canister_id,
+            method_name: "f1".into(),
+            method_payload: vec![],
+            ingress_expiry: 0,
+            nonce: None,
+        },
+        Arc::new(test.state().clone()),
+        vec![],
+    );
+    assert_eq!(output_1, Ok(WasmResult::Reply(b"42".to_vec())));
+
+    // The update changes the canister version and a page that the query does
+    // not read, and the query does not read the time or the balance.
+    let canister_version = test
+        .canister_state(canister_id)
+        .system_state
+        .canister_version;
+    test.ingress(canister_id, "write_other_page", vec![])
+        .unwrap();
+    assert_ne!(
+        canister_version,
+        test.canister_state(canister_id)
+            .system_state
+            .canister_version
+    );
+    test.state_mut().metadata.batch_time += Duration::from_secs(1);
+    test.canister_state_mut(canister_id)
+        .system_state
+        .remove_cycles(1_u128.into(), CyclesUseCase::Memory);
+
+    let output_2 = test.query(
+        UserQuery {
+            source: user_test_id(1),
+            receiver: canister_id,
+            method_name: "f1".into(),
+            method_payload: vec![],
+            ingress_expiry: 0,
+            nonce: None,
+        },
+        Arc::new(test.state().clone()),
+        vec![],
+    );
+    assert_eq!(output_1, output_2);
+    let metrics = &downcast_query_handler(test.query_handler())
+        .query_cache
+        .metrics;
+    assert_eq!(1, metrics.hits.get());
+    assert_eq!(1, metrics.misses.get());
+    assert_eq!(0, metrics.invalidated_entries.get());
+}
+
+#[test]
+fn query_cache_changed_memory_pages_return_different_results() {
+    let mut test = ExecutionTestBuilder::new().with_query_caching().build();
+    let canister_id = test.canister_from_wat(QUERY_CACHE_WAT).unwrap();
+    let output_1 = test.query(
+        UserQuery {
+            source: user_test_id(1),
+            receiver: canister_id,
+            method_name: "f1".into(),
+            method_payload: vec![],
+            ingress_expiry: 0,
+            nonce: None,
+        },
+        Arc::new(test.state().clone()),
+        vec![],
+    );
+    assert_eq!(output_1, Ok(WasmResult::Reply(b"42".to_vec())));
+    test.ingress(canister_id, "write_reply", vec![]).unwrap();
+    let output_2 = test.query(
+        UserQuery {
+            source: user_test_id(1),
+            receiver: canister_id,
+            method_name: "f1".into(),
+            method_payload: vec![],
+            ingress_expiry: 0,
+            nonce: None,
+        },
+        Arc::new(test.state().clone()),
+        vec![],
+    );
+    assert_eq!(output_2, Ok(WasmResult::Reply(b"52".to_vec())));
+    let metrics = &downcast_query_handler(test.query_handler())
+        .query_cache
+        .metrics;
+    assert_eq!(2, metrics.misses.get());
+    assert_eq!(1, metrics.invalidated_entries.get());
+    assert_eq!(1, metrics.invalidated_entries_by_memory_pages.get());
+    assert_eq!(0, metrics.invalidated_entries_by_time.get());
+    assert_eq!(0, metrics.invalidated_entries_by_canister_version.get());
+    assert_eq!(0, metrics.invalidated_entries_by_canister_balance.get());
+    assert_eq!(0, metrics.invalidated_entries_by_execution_env.get());
+}
+
+#[test]
+fn query_cache_with_too_many_accessed_pages_depends_on_canister_version() {
+    let mut test = ExecutionTestBuilder::new().with_query_caching().build();
+    let canister_id = test.canister_from_wat(QUERY_CACHE_WAT).unwrap();
+    let query = UserQuery {
+        source: user_test_id(1),
+        receiver: canister_id,
+        method_name: "read_many_pages".into(),
+        method_payload: vec![],
+        ingress_expiry: 0,
+        nonce: None,
+    };
+    let output_1 = test.query(query.clone(), Arc::new(test.state().clone()), vec![]);
+    assert_eq!(output_1, Ok(WasmResult::Reply(b"42".to_vec())));
+    let output_2 = test.query(query.clone(), Arc::new(test.state().clone()), vec![]);
+    assert_eq!(output_1, output_2);
+
+    // The accessed pages are not tracked, so any update invalidates the entry.
+    test.ingress(canister_id, "write_other_page", vec![])
+        .unwrap();
+    let output_3 = test.query(query, Arc::new(test.state().clone()), vec![]);
+    assert_eq!(output_1, output_3);
+    let metrics = &downcast_query_handler(test.query_handler())
+        .query_cache
+        .metrics;
+    assert_eq!(1, metrics.hits.get());
+    assert_eq!(2, metrics.misses.get());
+    assert_eq!(1, metrics.invalidated_entries.get());
+    assert_eq!(1, metrics.invalidated_entries_by_canister_version.get());
+    assert_eq!(0, metrics.invalidated_entries_by_memory_pages.get());
+}
+
 #[test]
 fn query_cache_env_old_invalid_entry_frees_memory() {
     static BIG_RESPONSE_SIZE: usize = 1_000_000;
@@ -560,9 +560,9 @@ impl SchedulerImpl {
         (new_state, message_instructions)
     }
 
-    /// Invoked in the first iteration of the inner round to add the `Heartbeat`
-    /// and `GlobalTimer` tasks that are carried out prior to processing
-    /// any input messages.
+    /// Invoked in the first iteration of the inner round to add the `Heartbeat`,
+    /// `GlobalTimer` and `OnLowWasmMemory` tasks that are carried out prior to
+    /// processing any input messages.
     /// It also returns the list of canisters that have non-zero priority credit.
     fn initialize_inner_round(
         &self,
@@ -583,6 +583,11 @@ impl SchedulerImpl {
                 non_zero_priority_credit_canister_ids.insert(canister.system_state.canister_id);
             }
 
+            // Track crossings of the Wasm memory threshold for all canisters,
+            // including stopped ones, so that a pending hook runs once the
+            // canister is running again.
+            canister.update_on_low_wasm_memory_hook_status();
+
             // Add `Heartbeat` or `GlobalTimer` for running canisters only.
             match canister.system_state.status {
                 CanisterStatus::Running { .. } => {}
@@ -612,6 +617,22 @@ impl SchedulerImpl {
                             break;
                         }
                     }
+
+                    // Like `GlobalTimer`, the `OnLowWasmMemory` task is added
+                    // in every round until it gets executed. It is added last
+                    // so that it runs before any other task or message.
+                    if canister
+                        .system_state
+                        .on_low_wasm_memory_hook_status
+                        .is_ready()
+                        && canister.exports_on_low_wasm_memory_method()
+                    {
+                        canister
+                            .system_state
+                            .task_queue
+                            .push_front(ExecutionTask::OnLowWasmMemory);
+                        heartbeat_and_timer_canister_ids.insert(canister.canister_id());
+                    }
                 }
             }
         }
@@ -800,12 +821,15 @@ impl SchedulerImpl {
                 .metrics
                 .round_inner_heartbeat_overhead_duration
                 .start_timer();
-            // Remove all remaining `Heartbeat` and `GlobalTimer` tasks
-            // because they will be added again in the next round.
+            // Remove all remaining `Heartbeat`, `GlobalTimer` and
+            // `OnLowWasmMemory` tasks because they will be added again in the
+            // next round.
             for canister_id in &heartbeat_and_timer_canister_ids {
                 let canister = state.canister_state_mut(canister_id).unwrap();
                 canister.system_state.task_queue.retain(|task| match task {
-                    ExecutionTask::Heartbeat | ExecutionTask::GlobalTimer => false,
+                    ExecutionTask::Heartbeat
+                    | ExecutionTask::GlobalTimer
+                    | ExecutionTask::OnLowWasmMemory => false,
                     ExecutionTask::PausedExecution(..)
                     | ExecutionTask::PausedInstallCode(..)
                     | ExecutionTask::AbortedExecution { .. }
@@ -1338,8 +1362,8 @@ impl SchedulerImpl {
             .iter()
             .filter(|(_, canister)| !canister.system_state.task_queue.is_empty());
 
-        // 1. Heartbeat and GlobalTimer tasks exist only during the round
-        //    and must not exist after the round.
+        // 1. Heartbeat, GlobalTimer and OnLowWasmMemory tasks exist only
+        //    during the round and must not exist after the round.
         // 2. Paused executions can exist only in ordinary rounds (not checkpoint rounds).
         // 3. If deterministic time slicing is disabled, then there are no paused tasks.
         //    Aborted tasks may still exist if DTS was disabled in recent checkpoints.
@@ -1360,6 +1384,12 @@ impl SchedulerImpl {
                             id
                         );
                     }
+                    ExecutionTask::OnLowWasmMemory => {
+                        panic!(
+                            "Unexpected on low wasm memory task after a round in canister {:?}",
+                            id
+                        );
+                    }
                     ExecutionTask::PausedExecution(_) | ExecutionTask::PausedInstallCode(_) => {
                         assert_eq!(
                             self.deterministic_time_slicing,
@@ -2067,7 +2097,10 @@ fn observe_replicated_state_metrics(
             Some(&ExecutionTask::AbortedInstallCode { .. }) => {
                 num_aborted_install += 1;
             }
-            Some(&ExecutionTask::Heartbeat) | Some(&ExecutionTask::GlobalTimer) | None => {}
+            Some(&ExecutionTask::Heartbeat)
+            | Some(&ExecutionTask::GlobalTimer)
+            | Some(&ExecutionTask::OnLowWasmMemory)
+            | None => {}
         }
         consumed_cycles_total += canister
             .system_state
@@ -2382,7 +2415,8 @@ fn should_add_task(
         ExecutionTask::GlobalTimer => {
             global_timer_has_reached_deadline && canister.exports_global_timer_method()
         }
-        ExecutionTask::AbortedExecution { .. }
+        ExecutionTask::OnLowWasmMemory
+        | ExecutionTask::AbortedExecution { .. }
         | ExecutionTask::AbortedInstallCode { .. }
         | ExecutionTask::PausedExecution(..)
         | ExecutionTask::PausedInstallCode(..) => unreachable!("Unexpected ExecutionTask variant."),
@@ -2393,7 +2427,8 @@ fn get_other_task(task: ExecutionTask) -> ExecutionTask {
     match task {
         ExecutionTask::Heartbeat => ExecutionTask::GlobalTimer,
         ExecutionTask::GlobalTimer => ExecutionTask::Heartbeat,
-        ExecutionTask::AbortedExecution { .. }
+        ExecutionTask::OnLowWasmMemory
+        | ExecutionTask::AbortedExecution { .. }
         | ExecutionTask::AbortedInstallCode { .. }
         | ExecutionTask::PausedExecution(..)
         | ExecutionTask::PausedInstallCode(..) => unreachable!("Unexpected ExecutionTask variant."),
@@ -4,9 +4,10 @@ use ic_crypto_sha2::Sha256;
 use ic_error_types::{ErrorCode, UserError};
 use ic_ic00_types::CanisterInstallMode::{Install, Reinstall, Upgrade};
 use ic_ic00_types::{
-    self as ic00, CanisterChange, CanisterChangeDetails, CanisterChangeOrigin, CanisterIdRecord,
-    CanisterInfoRequest, CanisterInfoResponse, CreateCanisterArgs, InstallCodeArgs, Method,
-    Payload, UpdateSettingsArgs,
+    self as ic00, CanisterChange, CanisterChangeDetails, CanisterChangeOrigin, CanisterChangeType,
+    CanisterHistoryArgs, CanisterHistoryResponse, CanisterIdRecord, CanisterInfoRequest,
+    CanisterInfoResponse, CreateCanisterArgs, InstallCodeArgs, Method, Payload, QueryMethod,
+    UpdateSettingsArgs,
 };
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::canister_state::system_state::{
@@ -1051,3 +1052,282 @@ fn canister_info_retrieval() {
     assert_eq!(canister_info.module_hash(), None);
     assert_eq!(canister_info.controllers(), vec![user_id1, user_id2]);
 }
+
+fn create_canister_as(env: &StateMachine, sender: PrincipalId) -> CanisterId {
+    let wasm_result = env
+        .execute_ingress_as(
+            sender,
+            ic00::IC_00,
+            ic00::Method::ProvisionalCreateCanisterWithCycles,
+            ic00::ProvisionalCreateCanisterWithCyclesArgs {
+                amount: Some(candid::Nat::from(INITIAL_CYCLES_BALANCE.get())),
+                settings: Some(
+                    CanisterSettingsArgsBuilder::new()
+                        .with_controllers(vec![sender])
+                        .build(),
+                ),
+                specified_id: None,
+                sender_canister_version: None,
+            }
+            .encode(),
+        )
+        .expect("failed to create canister");
+    match wasm_result {
+        WasmResult::Reply(bytes) => CanisterIdRecord::decode(&bytes[..])
+            .expect("failed to decode canister ID record")
+            .get_canister_id(),
+        WasmResult::Reject(reason) => panic!("create_canister call rejected: {}", reason),
+    }
+}
+
+fn update_settings_as(
+    env: &StateMachine,
+    sender: PrincipalId,
+    canister_id: CanisterId,
+    settings: ic00::CanisterSettingsArgs,
+) {
+    env.execute_ingress_as(
+        sender,
+        ic00::IC_00,
+        Method::UpdateSettings,
+        UpdateSettingsArgs {
+            canister_id: canister_id.into(),
+            settings,
+            sender_canister_version: None,
+        }
+        .encode(),
+    )
+    .unwrap();
+}
+
+fn query_canister_history(
+    env: &StateMachine,
+    sender: PrincipalId,
+    args: CanisterHistoryArgs,
+) -> Result<CanisterHistoryResponse, UserError> {
+    match env.query_as(
+        sender,
+        ic00::IC_00,
+        QueryMethod::CanisterHistory.to_string(),
+        args.encode(),
+    )? {
+        WasmResult::Reply(bytes) => Ok(CanisterHistoryResponse::decode(&bytes[..])
+            .expect("failed to decode canister_history response")),
+        WasmResult::Reject(reason) => panic!("canister_history query rejected: {}", reason),
+    }
+}
+
+#[test]
+fn canister_history_tracks_settings_changes() {
+    let now = std::time::SystemTime::now();
+    let (env, _test_canister, _test_canister_sha256) = test_setup(SubnetType::Application, now);
+    let user_id = user_test_id(7).get();
+    let canister_id = create_canister_as(&env, user_id);
+
+    update_settings_as(
+        &env,
+        user_id,
+        canister_id,
+        CanisterSettingsArgsBuilder::new()
+            .with_compute_allocation(1)
+            .with_memory_allocation(10 << 20)
+            .with_freezing_threshold(1_000)
+            .with_reserved_cycles_limit(1_000_000_000_000)
+            .build(),
+    );
+
+    let timestamp_nanos = now.duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+    let origin = CanisterChangeOrigin::from_user(user_id);
+    let reference_change_entries = vec![
+        CanisterChange::new(
+            timestamp_nanos,
+            0,
+            origin.clone(),
+            CanisterChangeDetails::canister_creation(vec![user_id]),
+        ),
+        CanisterChange::new(
+            timestamp_nanos,
+            1,
+            origin.clone(),
+            CanisterChangeDetails::compute_allocation_change(1),
+        ),
+        CanisterChange::new(
+            timestamp_nanos,
+            1,
+            origin.clone(),
+            CanisterChangeDetails::memory_allocation_change(10 << 20),
+        ),
+        CanisterChange::new(
+            timestamp_nanos,
+            1,
+            origin.clone(),
+            CanisterChangeDetails::freezing_threshold_change(1_000),
+        ),
+        CanisterChange::new(
+            timestamp_nanos,
+            1,
+            origin,
+            CanisterChangeDetails::reserved_cycles_limit_change(1_000_000_000_000),
+        ),
+    ];
+
+    let history = get_canister_history(&env, canister_id);
+    assert_eq!(history.get_total_num_changes(), 5);
+    assert_eq!(
+        history
+            .get_changes(history.get_total_num_changes() as usize)
+            .map(|c| (**c).clone())
+            .collect::<Vec<CanisterChange>>(),
+        reference_change_entries
+    );
+}
+
+#[test]
+fn canister_history_query_filters_and_pages() {
+    let mut now = std::time::SystemTime::now();
+    let (env, _test_canister, _test_canister_sha256) = test_setup(SubnetType::Application, now);
+    let user_id1 = user_test_id(7).get();
+    let user_id2 = user_test_id(8).get();
+    let canister_id = create_canister_as(&env, user_id1);
+
+    now += Duration::from_secs(5);
+    env.set_time(now);
+    env.tick();
+    update_settings_as(
+        &env,
+        user_id1,
+        canister_id,
+        CanisterSettingsArgsBuilder::new()
+            .with_freezing_threshold(100)
+            .build(),
+    );
+
+    now += Duration::from_secs(5);
+    env.set_time(now);
+    env.tick();
+    let second_update_nanos = now.duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+    update_settings_as(
+        &env,
+        user_id1,
+        canister_id,
+        CanisterSettingsArgsBuilder::new()
+            .with_compute_allocation(1)
+            .with_freezing_threshold(200)
+            .build(),
+    );
+
+    now += Duration::from_secs(5);
+    env.set_time(now);
+    env.tick();
+    let third_update_nanos = now.duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+    update_settings_as(
+        &env,
+        user_id1,
+        canister_id,
+        CanisterSettingsArgsBuilder::new()
+            .with_controllers(vec![user_id1, user_id2])
+            .build(),
+    );
+
+    let details = |response: &CanisterHistoryResponse| {
+        response
+            .changes()
+            .iter()
+            .map(|change| change.details().clone())
+            .collect::<Vec<_>>()
+    };
+
+    // filter by change type
+    let response = query_canister_history(
+        &env,
+        user_id1,
+        CanisterHistoryArgs {
+            change_types: Some(vec![CanisterChangeType::FreezingThresholdChange]),
+            ..CanisterHistoryArgs::new(canister_id)
+        },
+    )
+    .unwrap();
+    assert_eq!(response.total_num_changes(), 5);
+    assert_eq!(
+        details(&response),
+        vec![
+            CanisterChangeDetails::freezing_threshold_change(100),
+            CanisterChangeDetails::freezing_threshold_change(200),
+        ]
+    );
+    assert_eq!(response.next_start_index(), None);
+
+    // filter by time range
+    let response = query_canister_history(
+        &env,
+        user_id2,
+        CanisterHistoryArgs {
+            start_timestamp_nanos: Some(second_update_nanos),
+            end_timestamp_nanos: Some(third_update_nanos),
+            ..CanisterHistoryArgs::new(canister_id)
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        details(&response),
+        vec![
+            CanisterChangeDetails::compute_allocation_change(1),
+            CanisterChangeDetails::freezing_threshold_change(200),
+        ]
+    );
+
+    // page through the full history
+    let mut pages = vec![];
+    let mut start_index = Some(0);
+    while let Some(index) = start_index {
+        let response = query_canister_history(
+            &env,
+            user_id1,
+            CanisterHistoryArgs {
+                start_index: Some(index),
+                max_changes: Some(2),
+                ..CanisterHistoryArgs::new(canister_id)
+            },
+        )
+        .unwrap();
+        pages.push(details(&response));
+        start_index = response.next_start_index();
+    }
+    assert_eq!(
+        pages,
+        vec![
+            vec![
+                CanisterChangeDetails::canister_creation(vec![user_id1]),
+                CanisterChangeDetails::freezing_threshold_change(100),
+            ],
+            vec![
+                CanisterChangeDetails::compute_allocation_change(1),
+                CanisterChangeDetails::freezing_threshold_change(200),
+            ],
+            vec![CanisterChangeDetails::controllers_change(vec![
+                user_id1, user_id2
+            ])],
+        ]
+    );
+}
+
+#[test]
+fn canister_history_query_rejects_non_controllers() {
+    let now = std::time::SystemTime::now();
+    let (env, _test_canister, _test_canister_sha256) = test_setup(SubnetType::Application, now);
+    let user_id1 = user_test_id(7).get();
+    let user_id2 = user_test_id(8).get();
+    let canister_id = create_canister_as(&env, user_id1);
+
+    let err =
+        query_canister_history(&env, user_id2, CanisterHistoryArgs::new(canister_id)).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterInvalidController);
+
+    let err = query_canister_history(
+        &env,
+        user_id1,
+        CanisterHistoryArgs::new(CanisterId::from_u64(666)),
+    )
+    .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterNotFound);
+}
@@ -0,0 +1,165 @@
+//! Runs canister messages in sandbox processes that enforce the syscall filter.
+//! A sandbox process that makes a system call that is not allowed is killed,
+//! which fails the message.
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+use ic_config::{
+    embedders::{Config as EmbeddersConfig, MemoryTrackerBackend, SandboxSyscallFilter},
+    execution_environment::Config as HypervisorConfig,
+    flag_status::FlagStatus,
+    subnet_config::{SchedulerConfig, SubnetConfig},
+};
+use ic_registry_subnet_type::SubnetType;
+use ic_state_machine_tests::{StateMachine, StateMachineBuilder, StateMachineConfig};
+use ic_types::{ingress::WasmResult, Cycles, NumInstructions};
+
+const INITIAL_CYCLES_BALANCE: Cycles = Cycles::new(100_000_000_000_000);
+
+const SLICE_INSTRUCTION_LIMIT: u64 = 1_000_000;
+
+const TEST_WAT: &str = r#"
+    (module
+        (import "ic0" "msg_reply" (func $msg_reply))
+        (import "ic0" "msg_reply_data_append"
+            (func $msg_reply_data_append (param i32 i32)))
+        (import "ic0" "stable64_grow" (func $stable64_grow (param i64) (result i64)))
+        (import "ic0" "stable64_write"
+            (func $stable64_write (param i64 i64 i64)))
+
+        (func (export "canister_update write")
+            (i32.store (i32.const 0) (i32.add (i32.load (i32.const 0)) (i32.const 1)))
+            (call $msg_reply)
+        )
+
+        ;; Grows the Wasm and the stable memory and writes to the new pages.
+        (func (export "canister_update grow")
+            (drop (memory.grow (i32.const 10)))
+            (i32.store (i32.const 655360) (i32.const 1))
+            (drop (call $stable64_grow (i64.const 1)))
+            (call $stable64_write (i64.const 0) (i64.const 0) (i64.const 4))
+            (call $msg_reply)
+        )
+
+        ;; Executes more instructions than fit in a single slice.
+        (func (export "canister_update work")
+            (local $i i32)
+            (loop $loop
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br_if $loop (i32.lt_u (local.get $i) (i32.const 10000000)))
+            )
+            (call $msg_reply)
+        )
+
+        (func (export "canister_query read")
+            (call $msg_reply_data_append (i32.const 0) (i32.const 4))
+            (call $msg_reply)
+        )
+
+        (memory 1)
+    )"#;
+
+/// `StateMachine` runs canisters without sandboxing if it cannot find the
+/// sandboxing binaries, which happens in local builds with `cargo`.
+fn should_skip_test_due_to_missing_sandbox() -> bool {
+    if !(std::env::var("SANDBOX_BINARY").is_ok() && std::env::var("LAUNCHER_BINARY").is_ok()) {
+        eprintln!(
+            "Skipping the test because it requires the canister sandboxing binaries.\n\
+             To fix this:\n\
+             - either run the test with `bazel test`\n\
+             - or define the SANDBOX_BINARY and LAUNCHER_BINARY environment variables \
+             with the paths to the corresponding binaries."
+        );
+        return true;
+    }
+    false
+}
+
+fn enforcing_env(memory_tracker_backend: MemoryTrackerBackend) -> StateMachine {
+    let subnet_config = SubnetConfig::new(SubnetType::Application);
+    let slice_instruction_limit = NumInstructions::from(SLICE_INSTRUCTION_LIMIT);
+    let subnet_config = SubnetConfig {
+        scheduler_config: SchedulerConfig {
+            max_instructions_per_round: slice_instruction_limit + slice_instruction_limit / 2,
+            max_instructions_per_message: slice_instruction_limit * 100,
+            max_instructions_per_message_without_dts: slice_instruction_limit,
+            max_instructions_per_slice: slice_instruction_limit,
+            ..subnet_config.scheduler_config
+        },
+        ..subnet_config
+    };
+    let hypervisor_config = HypervisorConfig {
+        embedders_config: EmbeddersConfig {
+            sandbox_syscall_filter: SandboxSyscallFilter::Enforce,
+            memory_tracker_backend,
+            ..EmbeddersConfig::default()
+        },
+        deterministic_time_slicing: FlagStatus::Enabled,
+        canister_sandboxing_flag: FlagStatus::Enabled,
+        ..Default::default()
+    };
+    StateMachineBuilder::new()
+        .with_config(Some(StateMachineConfig::new(
+            subnet_config,
+            hypervisor_config,
+        )))
+        .with_subnet_type(SubnetType::Application)
+        .with_checkpoints_enabled(true)
+        .build()
+}
+
+fn run_messages_with_enforced_filter(memory_tracker_backend: MemoryTrackerBackend) {
+    let env = enforcing_env(memory_tracker_backend);
+    let canister_id = env
+        .install_canister_with_cycles(
+            wat::parse_str(TEST_WAT).unwrap(),
+            vec![],
+            None,
+            INITIAL_CYCLES_BALANCE,
+        )
+        .unwrap();
+
+    assert_eq!(
+        env.execute_ingress(canister_id, "write", vec![]),
+        Ok(WasmResult::Reply(vec![]))
+    );
+    assert_eq!(
+        env.execute_ingress(canister_id, "grow", vec![]),
+        Ok(WasmResult::Reply(vec![]))
+    );
+    // Executed with deterministic time slicing over several rounds.
+    assert_eq!(
+        env.execute_ingress(canister_id, "work", vec![]),
+        Ok(WasmResult::Reply(vec![]))
+    );
+    assert_eq!(
+        env.query(canister_id, "read", vec![]),
+        Ok(WasmResult::Reply(1_u32.to_le_bytes().to_vec()))
+    );
+
+    // The sandbox process opens the memory of the canister from the checkpoint.
+    env.tick();
+    assert_eq!(
+        env.execute_ingress(canister_id, "write", vec![]),
+        Ok(WasmResult::Reply(vec![]))
+    );
+    assert_eq!(
+        env.query(canister_id, "read", vec![]),
+        Ok(WasmResult::Reply(2_u32.to_le_bytes().to_vec()))
+    );
+}
+
+#[test]
+fn canister_messages_run_with_enforced_syscall_filter() {
+    if should_skip_test_due_to_missing_sandbox() {
+        return;
+    }
+    run_messages_with_enforced_filter(MemoryTrackerBackend::Sigsegv);
+}
+
+#[test]
+fn canister_messages_run_with_enforced_syscall_filter_and_userfaultfd() {
+    if should_skip_test_due_to_missing_sandbox() {
+        return;
+    }
+    run_messages_with_enforced_filter(MemoryTrackerBackend::Userfaultfd);
+}
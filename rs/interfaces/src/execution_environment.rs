@@ -60,6 +60,30 @@ pub struct InstanceStats {
     pub copy_page_count: usize,
 }
 
+/// The parts of the canister state that an execution read in addition to its
+/// input. Used by the query cache to decide whether a cached result is still
+/// valid for a newer state.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateReads {
+    /// The Wasm memory pages accessed (read or written) by the execution in
+    /// increasing order. `None` if the accesses were not tracked.
+    pub wasm_memory_pages: Option<Vec<PageIndex>>,
+
+    /// The stable memory pages accessed (read or written) by the execution in
+    /// increasing order. `None` if the accesses were not tracked.
+    pub stable_memory_pages: Option<Vec<PageIndex>>,
+
+    /// Whether the execution read the time or the data certificate.
+    pub time: bool,
+
+    /// Whether the execution read the cycles balance.
+    pub cycles_balance: bool,
+
+    /// Whether the execution read the canister version or other parts of the
+    /// system state that only change together with it, e.g. the controllers.
+    pub canister_version: bool,
+}
+
 /// Errors that can be returned when fetching the available memory on a subnet.
 #[derive(Debug)]
 pub enum SubnetAvailableMemoryError {
@@ -114,6 +138,14 @@ pub enum SystemApiCallId {
     CanisterVersion,
     /// Tracker for `ic0.certified_data_set()`
     CertifiedDataSet,
+    /// Tracker for `ic0.cost_call()`
+    CostCall,
+    /// Tracker for `ic0.cost_create_canister()`
+    CostCreateCanister,
+    /// Tracker for `ic0.cost_http_request()`
+    CostHttpRequest,
+    /// Tracker for `ic0.cost_sign_with_ecdsa()`
+    CostSignWithEcdsa,
     /// Tracker for `ic0.cycles_burn128()`
     CyclesBurn128,
     /// Tracker for `ic0.data_certificate_copy()`
@@ -1056,6 +1088,50 @@ pub trait SystemApi {
         dst: u32,
         heap: &mut [u8],
     ) -> HypervisorResult<()>;
+
+    /// Copies to `dst` the amount of cycles charged for sending an
+    /// inter-canister call with a method name of `method_name_size` bytes and
+    /// a payload of `payload_size` bytes, as a 128-bit value.
+    ///
+    /// The cycles prepaid for the execution and transmission of the response
+    /// are not included, since they are refunded once the response arrives.
+    fn ic0_cost_call(
+        &self,
+        method_name_size: u64,
+        payload_size: u64,
+        dst: u32,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()>;
+
+    /// Copies to `dst` the fee for creating a canister on this subnet as a
+    /// 128-bit value.
+    fn ic0_cost_create_canister(&self, dst: u32, heap: &mut [u8]) -> HypervisorResult<()>;
+
+    /// Copies to `dst` the fee for an HTTPS outcall with a request of
+    /// `request_size` bytes and a response of at most `max_res_bytes` bytes
+    /// as a 128-bit value.
+    fn ic0_cost_http_request(
+        &self,
+        request_size: u64,
+        max_res_bytes: u64,
+        dst: u32,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()>;
+
+    /// Copies to `dst` the fee for a threshold ECDSA signature with the key
+    /// whose name is at `src..src+size` and whose curve is `ecdsa_curve` (0
+    /// for secp256k1) as a 128-bit value.
+    ///
+    /// Returns 0 on success, 1 if the curve is unknown and 2 if no subnet is
+    /// enabled to sign with the key. Nothing is copied in the error cases.
+    fn ic0_cost_sign_with_ecdsa(
+        &self,
+        src: u32,
+        size: u32,
+        ecdsa_curve: u32,
+        dst: u32,
+        heap: &mut [u8],
+    ) -> HypervisorResult<u32>;
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -1157,6 +1233,7 @@ pub struct WasmExecutionOutput {
     pub allocated_bytes: NumBytes,
     pub allocated_message_bytes: NumBytes,
     pub instance_stats: InstanceStats,
+    pub state_reads: StateReads,
 }
 
 impl fmt::Display for WasmExecutionOutput {
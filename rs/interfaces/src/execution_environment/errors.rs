@@ -148,6 +148,12 @@ pub enum HypervisorError {
         available: Cycles,
         threshold: Cycles,
     },
+    /// The canister attempted to grow its Wasm memory beyond the
+    /// `wasm_memory_limit` set in its settings.
+    WasmMemoryLimitExceeded {
+        bytes: NumBytes,
+        limit: NumBytes,
+    },
 }
 
 impl From<WasmInstrumentationError> for HypervisorError {
@@ -353,6 +359,14 @@ impl HypervisorError {
                      bytes,
                      threshold - available)
             ),
+            Self::WasmMemoryLimitExceeded { bytes, limit } => UserError::new(
+                E::CanisterWasmMemoryLimitExceeded,
+                format!(
+                    "Canister {} exceeded its Wasm memory limit: the Wasm memory would grow \
+                     to {} bytes, but the `wasm_memory_limit` setting is {} bytes.",
+                    canister_id, bytes, limit,
+                ),
+            ),
         }
     }
 
@@ -391,6 +405,7 @@ impl HypervisorError {
             HypervisorError::InsufficientCyclesInMessageMemoryGrow { .. } => {
                 "InsufficientCyclesInMessageMemoryGrow"
             }
+            HypervisorError::WasmMemoryLimitExceeded { .. } => "WasmMemoryLimitExceeded",
         }
     }
 }
@@ -122,6 +122,230 @@ fn criterion_fault_handler_sim_write(criterion: &mut Criterion) {
     });
 }
 
+/// Same as `criterion_fault_handler_sim_read` but for the new signal handler
+/// that is used on Linux.
+fn criterion_fault_handler_sim_read_new(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("fault_handler");
+
+    let ptr: *mut c_void = unsafe {
+        mmap(
+            ptr::null_mut(),
+            PAGE_SIZE,
+            ProtFlags::PROT_NONE,
+            MapFlags::MAP_ANON | MapFlags::MAP_PRIVATE,
+            0,
+            0,
+        )
+        .unwrap()
+    };
+
+    group.bench_function("fault handler sim read new", |bench| {
+        bench.iter_with_setup(
+            // Setup input data for measurement
+            || {
+                let page_map = PageMap::new_for_testing();
+                BenchData {
+                    ptr,
+                    tracker: SigsegvMemoryTracker::new(
+                        ptr,
+                        PAGE_SIZE,
+                        no_op_logger(),
+                        DirtyPageTracking::Track,
+                        page_map.clone(),
+                    )
+                    .unwrap(),
+                    page_map,
+                }
+            },
+            // Do the actual measurement
+            |data| {
+                sigsegv_fault_handler_new(
+                    black_box(&data.tracker),
+                    AccessKind::Read,
+                    black_box(data.ptr),
+                );
+                data
+            },
+        )
+    });
+}
+
+/// Same as `criterion_fault_handler_sim_write` but for the new signal handler
+/// that is used on Linux.
+fn criterion_fault_handler_sim_write_new(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("fault_handler");
+
+    let ptr: *mut c_void = unsafe {
+        mmap(
+            ptr::null_mut(),
+            PAGE_SIZE,
+            ProtFlags::PROT_NONE,
+            MapFlags::MAP_ANON | MapFlags::MAP_PRIVATE,
+            0,
+            0,
+        )
+        .unwrap()
+    };
+
+    group.bench_function("fault handler sim write new", |bench| {
+        bench.iter_with_setup(
+            // Setup input data for measurement
+            || {
+                let page_map = PageMap::new_for_testing();
+                let data = BenchData {
+                    ptr,
+                    tracker: SigsegvMemoryTracker::new(
+                        ptr,
+                        PAGE_SIZE,
+                        no_op_logger(),
+                        DirtyPageTracking::Track,
+                        page_map.clone(),
+                    )
+                    .unwrap(),
+                    page_map,
+                };
+
+                sigsegv_fault_handler_new(&data.tracker, AccessKind::Read, data.ptr);
+
+                data
+            },
+            // Do the actual measurement
+            |data| {
+                sigsegv_fault_handler_new(
+                    black_box(&data.tracker),
+                    AccessKind::Write,
+                    black_box(data.ptr),
+                );
+                data
+            },
+        )
+    });
+}
+
+#[cfg(target_os = "linux")]
+mod userfaultfd {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    struct UffdBenchData {
+        ptr: *mut c_void,
+        tracker: Arc<Mutex<SigsegvMemoryTracker>>,
+        _handler: UserfaultfdHandler,
+    }
+
+    fn setup(ptr: *mut c_void, num_pages: usize) -> UffdBenchData {
+        let (tracker, handler) = SigsegvMemoryTracker::new_with_userfaultfd(
+            ptr,
+            num_pages * PAGE_SIZE,
+            no_op_logger(),
+            DirtyPageTracking::Track,
+            PageMap::new_for_testing(),
+        )
+        .expect("userfaultfd is not available");
+        UffdBenchData {
+            ptr,
+            tracker,
+            _handler: handler,
+        }
+    }
+
+    fn reserve(num_pages: usize) -> *mut c_void {
+        unsafe {
+            mmap(
+                ptr::null_mut(),
+                num_pages * PAGE_SIZE,
+                ProtFlags::PROT_NONE,
+                MapFlags::MAP_ANON | MapFlags::MAP_PRIVATE,
+                0,
+                0,
+            )
+            .unwrap()
+        }
+    }
+
+    /// The equivalent of `criterion_fault_handler_sim_read` for the
+    /// `userfaultfd` backend: the page is populated write-protected.
+    pub fn criterion_fault_handler_sim_read(criterion: &mut Criterion) {
+        let mut group = criterion.benchmark_group("fault_handler");
+        let ptr = reserve(1);
+
+        group.bench_function("fault handler sim read userfaultfd", |bench| {
+            bench.iter_with_setup(
+                || setup(ptr, 1),
+                |data| {
+                    userfaultfd_fault_handler(
+                        black_box(&data.tracker.lock().unwrap()),
+                        AccessKind::Read,
+                        black_box(data.ptr),
+                    );
+                    data
+                },
+            )
+        });
+    }
+
+    /// The equivalent of `criterion_fault_handler_sim_write` for the
+    /// `userfaultfd` backend: the write protection of the page is removed.
+    pub fn criterion_fault_handler_sim_write(criterion: &mut Criterion) {
+        let mut group = criterion.benchmark_group("fault_handler");
+        let ptr = reserve(1);
+
+        group.bench_function("fault handler sim write userfaultfd", |bench| {
+            bench.iter_with_setup(
+                || {
+                    let data = setup(ptr, 1);
+                    userfaultfd_fault_handler(
+                        &data.tracker.lock().unwrap(),
+                        AccessKind::Read,
+                        data.ptr,
+                    );
+                    data
+                },
+                |data| {
+                    userfaultfd_fault_handler(
+                        black_box(&data.tracker.lock().unwrap()),
+                        AccessKind::Write,
+                        black_box(data.ptr),
+                    );
+                    data
+                },
+            )
+        });
+    }
+
+    /// Measures a full round trip of a fault: the access blocks until the
+    /// handler thread has populated the page.
+    pub fn criterion_fault_round_trip(criterion: &mut Criterion) {
+        let mut group = criterion.benchmark_group("fault_handler");
+        let ptr = reserve(1);
+
+        group.bench_function("fault round trip read userfaultfd", |bench| {
+            bench.iter_with_setup(
+                || setup(ptr, 1),
+                |data| {
+                    black_box(unsafe { (data.ptr as *const u8).read_volatile() });
+                    data
+                },
+            )
+        });
+
+        group.bench_function("fault round trip write userfaultfd", |bench| {
+            bench.iter_with_setup(
+                || {
+                    let data = setup(ptr, 1);
+                    black_box(unsafe { (data.ptr as *const u8).read_volatile() });
+                    data
+                },
+                |data| {
+                    unsafe { (data.ptr as *mut u8).write_volatile(1) };
+                    data
+                },
+            )
+        });
+    }
+}
+
 fn criterion_only_once() -> Criterion {
     // Maybe we need to disable warm-up?
     Criterion::default()
@@ -141,4 +365,23 @@ criterion_group! {
     targets = criterion_fault_handler_sim_write
 }
 
-criterion_main!(first_trap, second_trap);
+criterion_group! {
+    name = new_handler;
+    config = criterion_only_once();
+    targets = criterion_fault_handler_sim_read_new, criterion_fault_handler_sim_write_new
+}
+
+#[cfg(target_os = "linux")]
+criterion_group! {
+    name = userfaultfd_trap;
+    config = criterion_only_once();
+    targets = userfaultfd::criterion_fault_handler_sim_read,
+        userfaultfd::criterion_fault_handler_sim_write,
+        userfaultfd::criterion_fault_round_trip
+}
+
+#[cfg(target_os = "linux")]
+criterion_main!(first_trap, second_trap, new_handler, userfaultfd_trap);
+
+#[cfg(not(target_os = "linux"))]
+criterion_main!(first_trap, second_trap, new_handler);
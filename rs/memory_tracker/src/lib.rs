@@ -9,12 +9,20 @@ use nix::{
     errno::Errno,
     sys::mman::{mmap, mprotect, MapFlags, ProtFlags},
 };
+#[cfg(target_os = "linux")]
+use std::sync::{Arc, Mutex};
 use std::{
     cell::{Cell, RefCell},
     ops::Range,
     sync::atomic::{AtomicUsize, Ordering},
 };
 
+#[cfg(target_os = "linux")]
+mod userfaultfd;
+
+#[cfg(target_os = "linux")]
+pub use userfaultfd::{userfaultfd_fault_handler, UserfaultfdHandler};
+
 // The upper bound on the number of pages that are memory mapped from the
 // checkpoint file per signal handler call. Higher value gives higher
 // throughput in memory intensive workloads, but may regress performance
@@ -110,6 +118,16 @@ impl PageBitmap {
         self.pages.get(page.get() as usize).unwrap_or(false)
     }
 
+    /// Returns the indices of all accessed pages in increasing order.
+    pub fn marked_pages(&self) -> Vec<PageIndex> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter(|(_, marked)| *marked)
+            .map(|(i, _)| PageIndex::new(i as u64))
+            .collect()
+    }
+
     fn mark(&mut self, page: PageIndex) {
         self.pages.set(page.get() as usize, true);
         self.marked_count += 1;
@@ -224,6 +242,9 @@ pub struct SigsegvMemoryTracker {
     read_before_write_stats: ReadBeforeWriteStats,
     sigsegv_count: AtomicUsize,
     memory_instructions_stats: MemoryInstructionsStats,
+    /// Set if the memory is tracked with `userfaultfd` instead of `SIGSEGV`.
+    #[cfg(target_os = "linux")]
+    userfaultfd: Option<Arc<userfaultfd::Userfaultfd>>,
 }
 
 impl SigsegvMemoryTracker {
@@ -235,6 +256,93 @@ impl SigsegvMemoryTracker {
         dirty_page_tracking: DirtyPageTracking,
         page_map: PageMap,
     ) -> nix::Result<Self> {
+        let tracker = Self::new_untracked(addr, size, log, dirty_page_tracking, page_map);
+
+        // Map the memory and make the range inaccessible to track it with SIGSEGV.
+        if tracker.use_new_signal_handler {
+            let mut instructions = tracker.page_map.get_base_memory_instructions();
+
+            // Restrict to tracked range before applying
+            instructions.range = tracker.page_range();
+
+            apply_memory_instructions(&tracker, ProtFlags::PROT_NONE, instructions);
+        } else {
+            unsafe { mprotect(addr, size, ProtFlags::PROT_NONE)? }
+            tracker
+                .memory_instructions_stats
+                .mprotect_count
+                .fetch_add(1, Ordering::Relaxed);
+        }
+
+        Ok(tracker)
+    }
+
+    /// Creates a tracker that tracks the accesses with `userfaultfd` in
+    /// write-protect mode instead of handling `SIGSEGV`.
+    ///
+    /// The memory is replaced by an unpopulated read/write mapping whose pages
+    /// are populated from the `PageMap` on the first access. The faults are
+    /// resolved by the returned handler, which must be kept alive as long as
+    /// the memory is accessed. Growing the memory beyond `size` still raises
+    /// `SIGSEGV` and must be reported with [`Self::expand`].
+    ///
+    /// The memory is left untouched if `userfaultfd` is not available. If
+    /// setting it up fails after the memory was replaced, the memory is left
+    /// inaccessible, so that a tracker created with [`Self::new`] as fallback
+    /// populates every page from the `PageMap`.
+    #[cfg(target_os = "linux")]
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    pub fn new_with_userfaultfd(
+        addr: *mut libc::c_void,
+        size: usize,
+        log: ReplicaLogger,
+        dirty_page_tracking: DirtyPageTracking,
+        page_map: PageMap,
+    ) -> nix::Result<(Arc<Mutex<Self>>, UserfaultfdHandler)> {
+        let uffd = Arc::new(userfaultfd::Userfaultfd::new()?);
+        let write_protect = dirty_page_tracking == DirtyPageTracking::Track;
+        uffd.probe(write_protect)?;
+        let mut tracker =
+            Self::new_untracked(addr, size, log.clone(), dirty_page_tracking, page_map);
+        if size > 0 {
+            unsafe {
+                mmap(
+                    addr,
+                    size,
+                    ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+                    MapFlags::MAP_PRIVATE | MapFlags::MAP_ANONYMOUS | MapFlags::MAP_FIXED,
+                    -1,
+                    0,
+                )
+                .map_err(print_enomem_help)?
+            };
+            tracker
+                .memory_instructions_stats
+                .mmap_count
+                .fetch_add(1, Ordering::Relaxed);
+        }
+        if let Err(err) = uffd.register(addr, size, write_protect) {
+            make_inaccessible(addr, size)?;
+            return Err(err);
+        }
+        tracker.userfaultfd = Some(Arc::clone(&uffd));
+        let tracker = Arc::new(Mutex::new(tracker));
+        match UserfaultfdHandler::register(uffd, Arc::downgrade(&tracker), log) {
+            Ok(handler) => Ok((tracker, handler)),
+            Err(err) => {
+                make_inaccessible(addr, size)?;
+                Err(err)
+            }
+        }
+    }
+
+    fn new_untracked(
+        addr: *mut libc::c_void,
+        size: usize,
+        log: ReplicaLogger,
+        dirty_page_tracking: DirtyPageTracking,
+        page_map: PageMap,
+    ) -> Self {
         assert_eq!(ic_sys::sysconf_page_size(), PAGE_SIZE);
         let num_pages = size / PAGE_SIZE;
         debug!(
@@ -248,7 +356,7 @@ impl SigsegvMemoryTracker {
         let dirty_pages = RefCell::new(Vec::new());
         let speculatively_dirty_pages = RefCell::new(Vec::new());
         let use_new_signal_handler = new_signal_handler_available();
-        let tracker = SigsegvMemoryTracker {
+        SigsegvMemoryTracker {
             memory_area,
             accessed_bitmap,
             dirty_bitmap,
@@ -269,25 +377,9 @@ impl SigsegvMemoryTracker {
                 mprotect_count: AtomicUsize::new(0),
                 copy_page_count: AtomicUsize::new(0),
             },
-        };
-
-        // Map the memory and make the range inaccessible to track it with SIGSEGV.
-        if tracker.use_new_signal_handler {
-            let mut instructions = tracker.page_map.get_base_memory_instructions();
-
-            // Restrict to tracked range before applying
-            instructions.range = tracker.page_range();
-
-            apply_memory_instructions(&tracker, ProtFlags::PROT_NONE, instructions);
-        } else {
-            unsafe { mprotect(addr, size, ProtFlags::PROT_NONE)? }
-            tracker
-                .memory_instructions_stats
-                .mprotect_count
-                .fetch_add(1, Ordering::Relaxed);
+            #[cfg(target_os = "linux")]
+            userfaultfd: None,
         }
-
-        Ok(tracker)
     }
 
     pub fn handle_sigsegv(
@@ -295,6 +387,11 @@ impl SigsegvMemoryTracker {
         access_kind: Option<AccessKind>,
         fault_address: *mut libc::c_void,
     ) -> bool {
+        #[cfg(target_os = "linux")]
+        if self.userfaultfd.is_some() {
+            // Accesses within the area never raise a signal.
+            return false;
+        }
         self.sigsegv_count.fetch_add(1, Ordering::Relaxed);
         if self.use_new_signal_handler {
             sigsegv_fault_handler_new(self, access_kind.unwrap(), fault_address)
@@ -307,8 +404,45 @@ impl SigsegvMemoryTracker {
         &self.memory_area
     }
 
+    /// Handles a page fault reported by `userfaultfd`. Returns false if the
+    /// memory is not tracked with `userfaultfd` or the address is outside of
+    /// the tracked area.
+    #[cfg(target_os = "linux")]
+    pub fn handle_userfault(
+        &self,
+        access_kind: AccessKind,
+        fault_address: *mut libc::c_void,
+    ) -> bool {
+        self.sigsegv_count.fetch_add(1, Ordering::Relaxed);
+        userfaultfd_fault_handler(self, access_kind, fault_address)
+    }
+
     pub fn expand(&self, delta: usize) {
         let old_size = self.area().size.get();
+        #[cfg(target_os = "linux")]
+        if let Some(uffd) = &self.userfaultfd {
+            // The new pages are not accessible yet. Make them read/write and
+            // let `userfaultfd` populate them on the first access.
+            let new_pages = (self.memory_area.addr() + old_size) as *mut libc::c_void;
+            unsafe {
+                mprotect(
+                    new_pages,
+                    delta,
+                    ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+                )
+                .map_err(print_enomem_help)
+                .unwrap()
+            };
+            self.memory_instructions_stats
+                .mprotect_count
+                .fetch_add(1, Ordering::Relaxed);
+            uffd.register(
+                new_pages,
+                delta,
+                self.dirty_page_tracking == DirtyPageTracking::Track,
+            )
+            .unwrap_or_else(|err| panic!("Failed to register memory with userfaultfd: {}", err));
+        }
         self.area().size.set(old_size + delta);
         self.accessed_bitmap.borrow_mut().grow(delta);
         self.dirty_bitmap.borrow_mut().grow(delta);
@@ -392,7 +526,8 @@ impl SigsegvMemoryTracker {
             .load(Ordering::Relaxed)
     }
 
-    /// The number of calls to `handle_sigsegv`.
+    /// The number of calls to `handle_sigsegv` or, if the memory is tracked
+    /// with `userfaultfd`, the number of handled page faults.
     pub fn sigsegv_count(&self) -> usize {
         self.sigsegv_count.load(Ordering::Relaxed)
     }
@@ -826,6 +961,26 @@ fn range_from_count(page: PageIndex, count: usize) -> Range<PageIndex> {
     }
 }
 
+/// Replaces the given range by an inaccessible anonymous mapping, which also
+/// drops any `userfaultfd` registration of the range.
+#[cfg(target_os = "linux")]
+fn make_inaccessible(addr: *mut libc::c_void, size: usize) -> nix::Result<()> {
+    if size > 0 {
+        unsafe {
+            mmap(
+                addr,
+                size,
+                ProtFlags::PROT_NONE,
+                MapFlags::MAP_PRIVATE | MapFlags::MAP_ANONYMOUS | MapFlags::MAP_FIXED,
+                -1,
+                0,
+            )
+            .map_err(print_enomem_help)?
+        };
+    }
+    Ok(())
+}
+
 fn print_enomem_help(errno: Errno) -> Errno {
     if let Errno::ENOMEM = errno {
         eprintln!(
@@ -24,6 +24,26 @@ fn setup(
     page_delta: Vec<PageIndex>,
     dirty_page_tracking: DirtyPageTracking,
 ) -> (SigsegvMemoryTracker, PageMap, *mut c_void, Vec<u8>) {
+    let (page_map, memory, vec) = setup_memory(checkpoint_pages, memory_pages, page_delta);
+    let tracker = SigsegvMemoryTracker::new(
+        memory,
+        memory_pages * PAGE_SIZE,
+        no_op_logger(),
+        dirty_page_tracking,
+        page_map.clone(),
+    )
+    .unwrap();
+    (tracker, page_map, memory, vec)
+}
+
+/// Sets up a PageMap and an inaccessible region of memory to be tracked.
+/// Returns the PageMap, a pointer to the region and a regular vector with the
+/// same initial contents as the PageMap.
+fn setup_memory(
+    checkpoint_pages: usize,
+    memory_pages: usize,
+    page_delta: Vec<PageIndex>,
+) -> (PageMap, *mut c_void, Vec<u8>) {
     let mut vec = vec![0_u8; memory_pages * PAGE_SIZE];
     let tmpfile = tempfile::Builder::new().prefix("test").tempfile().unwrap();
     for page in 0..checkpoint_pages {
@@ -64,16 +84,7 @@ fn setup(
         )
         .unwrap()
     };
-
-    let tracker = SigsegvMemoryTracker::new(
-        memory,
-        memory_pages * PAGE_SIZE,
-        no_op_logger(),
-        dirty_page_tracking,
-        page_map.clone(),
-    )
-    .unwrap();
-    (tracker, page_map, memory, vec)
+    (page_map, memory, vec)
 }
 
 fn with_setup<F>(
@@ -1256,3 +1267,273 @@ mod random_ops {
         }
     }
 }
+
+#[cfg(target_os = "linux")]
+mod userfaultfd {
+    use super::*;
+
+    use crate::UserfaultfdHandler;
+
+    use nix::errno::Errno;
+    use proptest::prelude::*;
+    use std::{collections::BTreeSet, sync::Mutex};
+
+    const PAGE_COUNT: usize = 100;
+
+    /// Sets up a tracker backed by `userfaultfd` for the first `memory_pages`
+    /// pages of a region of `reserved_pages` pages. Returns `None` if
+    /// `userfaultfd` is not available on the machine.
+    #[allow(clippy::type_complexity)]
+    fn setup_userfaultfd(
+        checkpoint_pages: usize,
+        memory_pages: usize,
+        reserved_pages: usize,
+        page_delta: Vec<PageIndex>,
+        dirty_page_tracking: DirtyPageTracking,
+    ) -> Option<(
+        Arc<Mutex<SigsegvMemoryTracker>>,
+        UserfaultfdHandler,
+        *mut c_void,
+        Vec<u8>,
+    )> {
+        let (page_map, memory, vec) = setup_memory(checkpoint_pages, reserved_pages, page_delta);
+        match SigsegvMemoryTracker::new_with_userfaultfd(
+            memory,
+            memory_pages * PAGE_SIZE,
+            no_op_logger(),
+            dirty_page_tracking,
+            page_map,
+        ) {
+            Ok((tracker, handler)) => Some((tracker, handler, memory, vec)),
+            Err(Errno::EPERM) | Err(Errno::ENOSYS) | Err(Errno::EINVAL) => None,
+            Err(err) => panic!("Failed to set up userfaultfd: {}", err),
+        }
+    }
+
+    #[test]
+    fn sigsegv_fallback_restores_replaced_memory_from_page_map() {
+        let (page_map, memory, vec) =
+            setup_memory(50, PAGE_COUNT, (25..75).map(PageIndex::new).collect());
+        // Replace the memory like a failed `userfaultfd` setup does.
+        unsafe {
+            mmap(
+                memory,
+                PAGE_COUNT * PAGE_SIZE,
+                ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+                MapFlags::MAP_PRIVATE | MapFlags::MAP_ANONYMOUS | MapFlags::MAP_FIXED,
+                -1,
+                0,
+            )
+            .unwrap();
+            std::ptr::write_bytes(memory as *mut u8, 0xff, PAGE_COUNT * PAGE_SIZE);
+        }
+        crate::make_inaccessible(memory, PAGE_COUNT * PAGE_SIZE).unwrap();
+
+        let tracker = SigsegvMemoryTracker::new(
+            memory,
+            PAGE_COUNT * PAGE_SIZE,
+            no_op_logger(),
+            DirtyPageTracking::Track,
+            page_map,
+        )
+        .unwrap();
+        for page in 0..PAGE_COUNT {
+            if !tracker
+                .accessed_bitmap
+                .borrow()
+                .is_marked(PageIndex::new(page as u64))
+            {
+                sigsegv(&tracker, PageIndex::new(page as u64), AccessKind::Read);
+            }
+        }
+        let memory =
+            unsafe { std::slice::from_raw_parts(memory as *const u8, PAGE_COUNT * PAGE_SIZE) };
+        assert_eq!(memory, &vec[..]);
+    }
+
+    #[test]
+    fn userfaultfd_populates_pages_from_page_map() {
+        let Some((tracker, _handler, memory, vec)) = setup_userfaultfd(
+            50,
+            PAGE_COUNT,
+            PAGE_COUNT,
+            (25..75).map(PageIndex::new).collect(),
+            DirtyPageTracking::Track,
+        ) else {
+            return;
+        };
+        let memory =
+            unsafe { std::slice::from_raw_parts(memory as *const u8, PAGE_COUNT * PAGE_SIZE) };
+        assert_eq!(memory, &vec[..]);
+        let tracker = tracker.lock().unwrap();
+        assert_eq!(tracker.num_accessed_pages(), PAGE_COUNT);
+        assert!(tracker.take_dirty_pages().is_empty());
+        assert!(tracker.take_speculatively_dirty_pages().is_empty());
+    }
+
+    #[test]
+    fn userfaultfd_expand_populates_new_pages() {
+        let Some((tracker, _handler, memory, vec)) = setup_userfaultfd(
+            50,
+            PAGE_COUNT / 2,
+            PAGE_COUNT,
+            (25..75).map(PageIndex::new).collect(),
+            DirtyPageTracking::Track,
+        ) else {
+            return;
+        };
+        tracker.lock().unwrap().expand(PAGE_COUNT / 2 * PAGE_SIZE);
+        let memory =
+            unsafe { std::slice::from_raw_parts_mut(memory as *mut u8, PAGE_COUNT * PAGE_SIZE) };
+        let last_page = (PAGE_COUNT - 1) * PAGE_SIZE;
+        assert_eq!(memory[last_page..], vec[last_page..]);
+        memory[last_page] = memory[last_page].wrapping_add(1);
+        let tracker = tracker.lock().unwrap();
+        assert_eq!(
+            tracker.take_dirty_pages(),
+            vec![PageIndex::new(PAGE_COUNT as u64 - 1)]
+        );
+    }
+
+    #[test]
+    fn userfaultfd_memories_share_the_handler() {
+        let setup = || {
+            setup_userfaultfd(
+                50,
+                PAGE_COUNT,
+                PAGE_COUNT,
+                (25..75).map(PageIndex::new).collect(),
+                DirtyPageTracking::Track,
+            )
+        };
+        let Some((first_tracker, first_handler, first_memory, first_vec)) = setup() else {
+            return;
+        };
+        let (second_tracker, second_handler, second_memory, second_vec) = setup().unwrap();
+        let first_memory = unsafe {
+            std::slice::from_raw_parts(first_memory as *const u8, PAGE_COUNT * PAGE_SIZE)
+        };
+        assert_eq!(first_memory, &first_vec[..]);
+
+        // The faults of the remaining memory are still resolved after the
+        // other memory is unregistered.
+        drop(first_handler);
+        drop(first_tracker);
+        let second_memory = unsafe {
+            std::slice::from_raw_parts(second_memory as *const u8, PAGE_COUNT * PAGE_SIZE)
+        };
+        assert_eq!(second_memory, &second_vec[..]);
+        assert_eq!(
+            second_tracker.lock().unwrap().num_accessed_pages(),
+            PAGE_COUNT
+        );
+        drop(second_handler);
+    }
+
+    #[derive(Clone, Debug)]
+    enum Op {
+        Read { offset: usize, length: usize },
+        Write { offset: usize, contents: Vec<u8> },
+    }
+
+    fn arb_op(mem_length: usize) -> impl Strategy<Value = Op> {
+        let offset_length = (0..mem_length).prop_flat_map(move |offset| {
+            (
+                Just(offset),
+                (0..std::cmp::min(10 * PAGE_SIZE, mem_length - offset)),
+            )
+        });
+        prop_oneof![
+            offset_length
+                .clone()
+                .prop_map(|(offset, length)| Op::Read { offset, length }),
+            offset_length
+                .prop_flat_map(|(offset, length)| {
+                    (Just(offset), prop::collection::vec(any::<u8>(), length))
+                })
+                .prop_map(|(offset, contents)| Op::Write { offset, contents }),
+        ]
+    }
+
+    proptest! {
+        /// Check that the region tracked with `userfaultfd` behaves the same as
+        /// a regular slice and that every accessed/dirty page is marked as
+        /// accessed/dirty.
+        #[test]
+        fn userfaultfd_random_ops_tracking(ops in prop::collection::vec(arb_op(PAGE_COUNT * PAGE_SIZE), 30)) {
+            let Some((tracker, _handler, memory, mut vec_memory)) = setup_userfaultfd(
+                50,
+                PAGE_COUNT,
+                PAGE_COUNT,
+                (25..75).map(PageIndex::new).collect(),
+                DirtyPageTracking::Track,
+            ) else {
+                return Ok(());
+            };
+            let memory =
+                unsafe { std::slice::from_raw_parts_mut(memory as *mut u8, PAGE_COUNT * PAGE_SIZE) };
+            let copy = vec_memory.clone();
+            let mut accessed = BTreeSet::new();
+            for op in ops {
+                match op {
+                    Op::Read { offset, length } => {
+                        if length > 0 {
+                            accessed.extend(offset / PAGE_SIZE..=(offset + length - 1) / PAGE_SIZE);
+                        }
+                        assert_eq!(memory[offset..offset + length], vec_memory[offset..offset + length]);
+                    }
+                    Op::Write { offset, contents } => {
+                        memory[offset..offset + contents.len()].copy_from_slice(&contents);
+                        vec_memory[offset..offset + contents.len()].copy_from_slice(&contents);
+                    }
+                }
+            }
+            assert_eq!(memory, &vec_memory[..]);
+
+            let tracker = tracker.lock().unwrap();
+            let tracker_accessed = tracker.accessed_pages().borrow();
+            for page in accessed {
+                assert!(tracker_accessed.is_marked(PageIndex::new(page as u64)));
+            }
+            let tracker_dirty = tracker
+                .take_dirty_pages()
+                .into_iter()
+                .chain(tracker.take_speculatively_dirty_pages())
+                .collect::<BTreeSet<_>>();
+            for i in 0..PAGE_COUNT {
+                if copy[i * PAGE_SIZE..(i + 1) * PAGE_SIZE] != vec_memory[i * PAGE_SIZE..(i + 1) * PAGE_SIZE] {
+                    assert!(tracker_dirty.contains(&PageIndex::new(i as u64)));
+                }
+            }
+        }
+
+        /// Same as above but without dirty page tracking.
+        #[test]
+        fn userfaultfd_random_ops_ignoring(ops in prop::collection::vec(arb_op(PAGE_COUNT * PAGE_SIZE), 30)) {
+            let Some((tracker, _handler, memory, mut vec_memory)) = setup_userfaultfd(
+                50,
+                PAGE_COUNT,
+                PAGE_COUNT,
+                (25..75).map(PageIndex::new).collect(),
+                DirtyPageTracking::Ignore,
+            ) else {
+                return Ok(());
+            };
+            let memory =
+                unsafe { std::slice::from_raw_parts_mut(memory as *mut u8, PAGE_COUNT * PAGE_SIZE) };
+            for op in ops {
+                match op {
+                    Op::Read { offset, length } => {
+                        assert_eq!(memory[offset..offset + length], vec_memory[offset..offset + length]);
+                    }
+                    Op::Write { offset, contents } => {
+                        memory[offset..offset + contents.len()].copy_from_slice(&contents);
+                        vec_memory[offset..offset + contents.len()].copy_from_slice(&contents);
+                    }
+                }
+            }
+            assert_eq!(memory, &vec_memory[..]);
+            assert!(tracker.lock().unwrap().take_dirty_pages().is_empty());
+        }
+    }
+}
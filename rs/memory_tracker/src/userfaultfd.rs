@@ -0,0 +1,581 @@
+//! Memory tracking backend based on Linux `userfaultfd` in write-protect mode.
+//!
+//! Instead of mapping the memory `PROT_NONE` and handling `SIGSEGV`, the
+//! tracked region is mapped read/write but left unpopulated and registered
+//! with a `userfaultfd`. The first access to a page raises a "missing" fault
+//! that is resolved by a handler thread shared by all tracked memories, which
+//! copies the contents of the page from the `PageMap` into the memory with
+//! `UFFDIO_COPY`. When dirty
+//! pages are tracked, the pages are installed write-protected so that the first
+//! write raises a "write-protect" fault, which is resolved by removing the
+//! protection with `UFFDIO_WRITEPROTECT`.
+//!
+//! The faulting thread is suspended by the kernel until the fault is resolved,
+//! so no signals and no `mprotect` calls are needed.
+use crate::{
+    range_from_count, range_size_in_bytes, AccessKind, DirtyPageTracking, SigsegvMemoryTracker,
+    MAX_PAGES_TO_MAP,
+};
+use ic_logger::{fatal, ReplicaLogger};
+use ic_replicated_state::PageIndex;
+use ic_sys::PAGE_SIZE;
+use nix::{
+    errno::Errno,
+    sys::mman::{mmap, munmap, MapFlags, ProtFlags},
+};
+use std::{
+    collections::HashMap,
+    ops::Range,
+    os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    sync::{atomic::Ordering, Arc, Condvar, Mutex, Weak},
+};
+
+const UFFD_API: u64 = 0xAA;
+/// Only handle faults caused by user-space accesses, which doesn't require
+/// `CAP_SYS_PTRACE` when `vm.unprivileged_userfaultfd` is 0.
+const UFFD_USER_MODE_ONLY: libc::c_int = 1;
+const UFFDIO: u8 = 0xAA;
+
+const UFFD_EVENT_PAGEFAULT: u8 = 0x12;
+const UFFD_PAGEFAULT_FLAG_WRITE: u64 = 1 << 0;
+
+const UFFDIO_REGISTER_MODE_MISSING: u64 = 1 << 0;
+const UFFDIO_REGISTER_MODE_WP: u64 = 1 << 1;
+const UFFDIO_COPY_MODE_WP: u64 = 1 << 1;
+
+/// The kernel ABI of `userfaultfd`, see `linux/userfaultfd.h`. The fields
+/// that are filled in by the kernel are not read.
+#[allow(dead_code)]
+mod abi {
+    #[repr(C)]
+    pub(super) struct UffdioApi {
+        pub(super) api: u64,
+        pub(super) features: u64,
+        pub(super) ioctls: u64,
+    }
+
+    #[repr(C)]
+    pub(super) struct UffdioRange {
+        pub(super) start: u64,
+        pub(super) len: u64,
+    }
+
+    #[repr(C)]
+    pub(super) struct UffdioRegister {
+        pub(super) range: UffdioRange,
+        pub(super) mode: u64,
+        pub(super) ioctls: u64,
+    }
+
+    #[repr(C)]
+    pub(super) struct UffdioCopy {
+        pub(super) dst: u64,
+        pub(super) src: u64,
+        pub(super) len: u64,
+        pub(super) mode: u64,
+        pub(super) copy: i64,
+    }
+
+    #[repr(C)]
+    pub(super) struct UffdioWriteprotect {
+        pub(super) range: UffdioRange,
+        pub(super) mode: u64,
+    }
+
+    /// Mirrors `struct uffd_msg` restricted to the page fault event.
+    #[repr(C)]
+    pub(super) struct UffdMsg {
+        pub(super) event: u8,
+        pub(super) reserved1: u8,
+        pub(super) reserved2: u16,
+        pub(super) reserved3: u32,
+        pub(super) flags: u64,
+        pub(super) address: u64,
+        pub(super) ptid: u32,
+        pub(super) padding: u32,
+    }
+
+    nix::ioctl_readwrite!(uffdio_api, super::UFFDIO, 0x3F, UffdioApi);
+    nix::ioctl_readwrite!(uffdio_register, super::UFFDIO, 0x00, UffdioRegister);
+    nix::ioctl_read!(uffdio_wake, super::UFFDIO, 0x02, UffdioRange);
+    nix::ioctl_readwrite!(uffdio_copy, super::UFFDIO, 0x03, UffdioCopy);
+    nix::ioctl_readwrite!(uffdio_writeprotect, super::UFFDIO, 0x06, UffdioWriteprotect);
+}
+
+use abi::*;
+
+/// A page fault reported by the kernel.
+pub(crate) struct PageFault {
+    pub(crate) address: *mut libc::c_void,
+    pub(crate) access_kind: AccessKind,
+}
+
+/// An owned `userfaultfd` file descriptor.
+pub(crate) struct Userfaultfd {
+    fd: OwnedFd,
+}
+
+impl Userfaultfd {
+    pub(crate) fn new() -> nix::Result<Self> {
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_userfaultfd,
+                libc::O_CLOEXEC | libc::O_NONBLOCK | UFFD_USER_MODE_ONLY,
+            )
+        };
+        let fd = Errno::result(fd)? as RawFd;
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let mut api = UffdioApi {
+            api: UFFD_API,
+            features: 0,
+            ioctls: 0,
+        };
+        unsafe { uffdio_api(fd.as_raw_fd(), &mut api)? };
+        Ok(Self { fd })
+    }
+
+    /// Registers the given range for missing faults and, if
+    /// `write_protect` is set, also for write-protect faults.
+    pub(crate) fn register(
+        &self,
+        addr: *mut libc::c_void,
+        len: usize,
+        write_protect: bool,
+    ) -> nix::Result<()> {
+        if len == 0 {
+            return Ok(());
+        }
+        let mut mode = UFFDIO_REGISTER_MODE_MISSING;
+        if write_protect {
+            mode |= UFFDIO_REGISTER_MODE_WP;
+        }
+        let mut register = UffdioRegister {
+            range: UffdioRange {
+                start: addr as u64,
+                len: len as u64,
+            },
+            mode,
+            ioctls: 0,
+        };
+        unsafe { uffdio_register(self.fd.as_raw_fd(), &mut register)? };
+        Ok(())
+    }
+
+    /// Registers a scratch page the same way [`Self::register`] registers the
+    /// tracked memory, so that missing kernel support (e.g. for write-protect
+    /// faults) is detected before the tracked memory is replaced.
+    pub(crate) fn probe(&self, write_protect: bool) -> nix::Result<()> {
+        let page = unsafe {
+            mmap(
+                std::ptr::null_mut(),
+                PAGE_SIZE,
+                ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+                MapFlags::MAP_PRIVATE | MapFlags::MAP_ANONYMOUS,
+                -1,
+                0,
+            )?
+        };
+        let result = self.register(page, PAGE_SIZE, write_protect);
+        // Unmapping the page also unregisters it.
+        unsafe { munmap(page, PAGE_SIZE)? };
+        result
+    }
+
+    /// Atomically populates the unpopulated range at `dst` with the contents of
+    /// `src` and wakes up the threads waiting on the range. Returns `EEXIST` if
+    /// the range has already been populated.
+    pub(crate) fn copy(
+        &self,
+        dst: *mut libc::c_void,
+        src: &[u8],
+        write_protect: bool,
+    ) -> nix::Result<()> {
+        let mut copy = UffdioCopy {
+            dst: dst as u64,
+            src: src.as_ptr() as u64,
+            len: src.len() as u64,
+            mode: if write_protect {
+                UFFDIO_COPY_MODE_WP
+            } else {
+                0
+            },
+            copy: 0,
+        };
+        unsafe { uffdio_copy(self.fd.as_raw_fd(), &mut copy)? };
+        Ok(())
+    }
+
+    /// Removes the write protection of the given range and wakes up the
+    /// threads waiting on it.
+    pub(crate) fn unprotect(&self, addr: *mut libc::c_void, len: usize) -> nix::Result<()> {
+        let mut writeprotect = UffdioWriteprotect {
+            range: UffdioRange {
+                start: addr as u64,
+                len: len as u64,
+            },
+            mode: 0,
+        };
+        unsafe { uffdio_writeprotect(self.fd.as_raw_fd(), &mut writeprotect)? };
+        Ok(())
+    }
+
+    /// Wakes up the threads waiting on the given range without changing it.
+    pub(crate) fn wake(&self, addr: *mut libc::c_void, len: usize) -> nix::Result<()> {
+        let mut range = UffdioRange {
+            start: addr as u64,
+            len: len as u64,
+        };
+        unsafe { uffdio_wake(self.fd.as_raw_fd(), &mut range)? };
+        Ok(())
+    }
+
+    /// Reads the next page fault. Returns `None` if there are no pending
+    /// faults.
+    fn read_page_fault(&self) -> nix::Result<Option<PageFault>> {
+        loop {
+            let mut msg = std::mem::MaybeUninit::<UffdMsg>::zeroed();
+            let size = std::mem::size_of::<UffdMsg>();
+            let result =
+                unsafe { libc::read(self.fd.as_raw_fd(), msg.as_mut_ptr() as *mut _, size) };
+            match Errno::result(result) {
+                Ok(n) if n as usize == size => {}
+                Ok(_) => return Err(Errno::EIO),
+                Err(Errno::EAGAIN) => return Ok(None),
+                Err(Errno::EINTR) => continue,
+                Err(err) => return Err(err),
+            }
+            let msg = unsafe { msg.assume_init() };
+            // Other events are not enabled, so they are ignored.
+            if msg.event != UFFD_EVENT_PAGEFAULT {
+                continue;
+            }
+            let access_kind = if msg.flags & UFFD_PAGEFAULT_FLAG_WRITE != 0 {
+                AccessKind::Write
+            } else {
+                AccessKind::Read
+            };
+            return Ok(Some(PageFault {
+                address: msg.address as *mut libc::c_void,
+                access_kind,
+            }));
+        }
+    }
+}
+
+/// A memory registered with the shared handler thread.
+struct Registration {
+    uffd: Arc<Userfaultfd>,
+    tracker: Weak<Mutex<SigsegvMemoryTracker>>,
+    log: ReplicaLogger,
+}
+
+#[derive(Default)]
+struct HandlerState {
+    registrations: HashMap<u64, Registration>,
+    next_id: u64,
+    /// Incremented on every change of the registrations.
+    generation: u64,
+    /// The generation of the registrations the thread currently polls.
+    observed_generation: u64,
+}
+
+/// The single thread that resolves the faults of all memories tracked with
+/// `userfaultfd` in the process.
+struct SharedHandler {
+    state: Mutex<HandlerState>,
+    observed: Condvar,
+    /// An eventfd that makes the thread re-read the registrations.
+    wakeup: OwnedFd,
+}
+
+impl SharedHandler {
+    fn wake_up(&self) {
+        let value: u64 = 1;
+        unsafe {
+            libc::write(
+                self.wakeup.as_raw_fd(),
+                &value as *const u64 as *const libc::c_void,
+                std::mem::size_of::<u64>(),
+            )
+        };
+    }
+
+    /// Records a change of the registrations and waits until the thread polls
+    /// the new registrations.
+    fn update(&self, f: impl FnOnce(&mut HandlerState)) {
+        let mut state = self.state.lock().unwrap();
+        f(&mut state);
+        state.generation += 1;
+        let generation = state.generation;
+        self.wake_up();
+        let _state = self
+            .observed
+            .wait_while(state, |state| state.observed_generation < generation)
+            .unwrap();
+    }
+}
+
+static SHARED_HANDLER: Mutex<Option<Arc<SharedHandler>>> = Mutex::new(None);
+
+/// Returns the shared handler and starts its thread on the first call.
+fn shared_handler(log: &ReplicaLogger) -> nix::Result<Arc<SharedHandler>> {
+    let mut shared_handler = SHARED_HANDLER.lock().unwrap();
+    if let Some(handler) = shared_handler.as_ref() {
+        return Ok(Arc::clone(handler));
+    }
+    let wakeup = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+    let wakeup = unsafe { OwnedFd::from_raw_fd(Errno::result(wakeup)?) };
+    let handler = Arc::new(SharedHandler {
+        state: Mutex::new(HandlerState::default()),
+        observed: Condvar::new(),
+        wakeup,
+    });
+    let thread_handler = Arc::clone(&handler);
+    let log = log.clone();
+    std::thread::Builder::new()
+        .name("MemoryTrackerUffd".to_string())
+        .spawn(move || handle_page_faults(&thread_handler, &log))
+        .map_err(|_| Errno::EAGAIN)?;
+    *shared_handler = Some(Arc::clone(&handler));
+    Ok(handler)
+}
+
+/// The registration of a memory tracked with `userfaultfd` with the thread
+/// that resolves its faults. All memories of the process share one thread.
+/// The memory is unregistered when the handler is dropped, so the handler
+/// must outlive all accesses to the tracked memory.
+pub struct UserfaultfdHandler {
+    shared: Arc<SharedHandler>,
+    id: u64,
+}
+
+impl UserfaultfdHandler {
+    pub(crate) fn register(
+        uffd: Arc<Userfaultfd>,
+        tracker: Weak<Mutex<SigsegvMemoryTracker>>,
+        log: ReplicaLogger,
+    ) -> nix::Result<Self> {
+        let shared = shared_handler(&log)?;
+        let mut id = 0;
+        shared.update(|state| {
+            id = state.next_id;
+            state.next_id += 1;
+            state
+                .registrations
+                .insert(id, Registration { uffd, tracker, log });
+        });
+        Ok(Self { shared, id })
+    }
+}
+
+impl Drop for UserfaultfdHandler {
+    fn drop(&mut self) {
+        // Once the update returns, the thread no longer polls the memory.
+        self.shared.update(|state| {
+            state.registrations.remove(&self.id);
+        });
+    }
+}
+
+fn handle_page_faults(handler: &SharedHandler, log: &ReplicaLogger) {
+    loop {
+        let registrations: Vec<_> = {
+            let mut state = handler.state.lock().unwrap();
+            state.observed_generation = state.generation;
+            handler.observed.notify_all();
+            state
+                .registrations
+                .values()
+                .map(|registration| {
+                    (
+                        Arc::clone(&registration.uffd),
+                        registration.tracker.clone(),
+                        registration.log.clone(),
+                    )
+                })
+                .collect()
+        };
+        let mut fds: Vec<_> = std::iter::once(handler.wakeup.as_raw_fd())
+            .chain(registrations.iter().map(|(uffd, _, _)| uffd.fd.as_raw_fd()))
+            .map(|fd| libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            })
+            .collect();
+        let result = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+        match Errno::result(result) {
+            Ok(_) => {}
+            Err(Errno::EINTR) => continue,
+            Err(err) => fatal!(log, "Memory tracker: failed to poll userfaultfd: {}", err),
+        }
+        for ((uffd, tracker, log), fd) in registrations.iter().zip(&fds[1..]) {
+            if fd.revents != 0 {
+                // The faulting thread would wait forever if the fault is not
+                // resolved.
+                if let Err(err) = handle_pending_faults(uffd, tracker) {
+                    fatal!(
+                        log,
+                        "Memory tracker: failed to handle a userfaultfd page fault: {}",
+                        err
+                    );
+                }
+            }
+        }
+        if fds[0].revents != 0 {
+            let mut value: u64 = 0;
+            unsafe {
+                libc::read(
+                    handler.wakeup.as_raw_fd(),
+                    &mut value as *mut u64 as *mut libc::c_void,
+                    std::mem::size_of::<u64>(),
+                )
+            };
+        }
+    }
+}
+
+fn handle_pending_faults(
+    uffd: &Userfaultfd,
+    tracker: &Weak<Mutex<SigsegvMemoryTracker>>,
+) -> nix::Result<()> {
+    while let Some(fault) = uffd.read_page_fault()? {
+        let tracker = match tracker.upgrade() {
+            Some(tracker) => tracker,
+            None => return Ok(()),
+        };
+        let tracker = tracker.lock().unwrap();
+        if !tracker.handle_userfault(fault.access_kind, fault.address) {
+            return Err(Errno::EFAULT);
+        }
+    }
+    Ok(())
+}
+
+/// Resolves a page fault in the memory tracked with `userfaultfd`.
+///
+/// The handler follows the same prefetching strategy as
+/// [`crate::sigsegv_fault_handler_new`] and produces the same accessed and
+/// dirty page bitmaps. Missing pages are populated with `UFFDIO_COPY`. When
+/// dirty pages are tracked, pages populated on a read access are
+/// write-protected, so that a subsequent write raises another fault that
+/// removes the protection.
+pub fn userfaultfd_fault_handler(
+    tracker: &SigsegvMemoryTracker,
+    access_kind: AccessKind,
+    fault_address: *mut libc::c_void,
+) -> bool {
+    let uffd = match &tracker.userfaultfd {
+        Some(uffd) => uffd,
+        None => return false,
+    };
+    if !tracker.memory_area.is_within(fault_address) {
+        // This memory tracker is not responsible for handling this address.
+        return false;
+    };
+
+    let faulting_page = tracker.page_index_from(fault_address);
+    let mut accessed_bitmap = tracker.accessed_bitmap.borrow_mut();
+    let mut dirty_bitmap = tracker.dirty_bitmap.borrow_mut();
+
+    // The fault may have been resolved already while handling an earlier fault
+    // of another thread on the same page.
+    let resolved = match (access_kind, tracker.dirty_page_tracking) {
+        (AccessKind::Read, _) | (_, DirtyPageTracking::Ignore) => {
+            accessed_bitmap.is_marked(faulting_page)
+        }
+        (AccessKind::Write, DirtyPageTracking::Track) => dirty_bitmap.is_marked(faulting_page),
+    };
+    if resolved {
+        uffd.wake(tracker.page_start_addr_from(faulting_page), PAGE_SIZE)
+            .unwrap_or_else(|err| panic!("Failed to wake up the faulting thread: {}", err));
+        return true;
+    }
+
+    match (access_kind, tracker.dirty_page_tracking) {
+        (_, DirtyPageTracking::Ignore) => {
+            let prefetch_range = range_from_count(faulting_page, MAX_PAGES_TO_MAP);
+            let prefetch_range = accessed_bitmap.restrict_range_to_unmarked(prefetch_range);
+            let prefetch_range = accessed_bitmap.restrict_range_to_predicted(prefetch_range);
+            copy_unaccessed_pages(tracker, uffd, &prefetch_range, false);
+            accessed_bitmap.mark_range(&prefetch_range);
+        }
+        (AccessKind::Read, DirtyPageTracking::Track) => {
+            // Populate the pages write-protected in order to get a fault on
+            // subsequent write accesses to track dirty pages.
+            let prefetch_range = range_from_count(faulting_page, MAX_PAGES_TO_MAP);
+            let prefetch_range = accessed_bitmap.restrict_range_to_unmarked(prefetch_range);
+            let prefetch_range = accessed_bitmap.restrict_range_to_predicted(prefetch_range);
+            copy_unaccessed_pages(tracker, uffd, &prefetch_range, true);
+            accessed_bitmap.mark_range(&prefetch_range);
+        }
+        (AccessKind::Write, DirtyPageTracking::Track) => {
+            let prefetch_range = range_from_count(faulting_page, MAX_PAGES_TO_MAP);
+            // Ensure that we don't overwrite an already dirty page.
+            let prefetch_range = dirty_bitmap.restrict_range_to_unmarked(prefetch_range);
+            if accessed_bitmap.is_marked(faulting_page) {
+                tracker
+                    .read_before_write_stats
+                    .read_before_write_count
+                    .fetch_add(1, Ordering::Relaxed);
+                // The pages in the range are populated and write-protected, so
+                // it is enough to remove the protection.
+                let prefetch_range = accessed_bitmap.restrict_range_to_marked(prefetch_range);
+                let prefetch_range = dirty_bitmap.restrict_range_to_predicted(prefetch_range);
+                uffd.unprotect(
+                    tracker.page_start_addr_from(faulting_page),
+                    range_size_in_bytes(&prefetch_range),
+                )
+                .unwrap_or_else(|err| panic!("Failed to remove write protection: {}", err));
+                tracker
+                    .memory_instructions_stats
+                    .mprotect_count
+                    .fetch_add(1, Ordering::Relaxed);
+                dirty_bitmap.mark_range(&prefetch_range);
+                tracker.add_dirty_pages(faulting_page, prefetch_range);
+            } else {
+                tracker
+                    .read_before_write_stats
+                    .direct_write_count
+                    .fetch_add(1, Ordering::Relaxed);
+                // The first access to the page is a write access, so the pages
+                // can be populated writable right away.
+                let prefetch_range = accessed_bitmap.restrict_range_to_unmarked(prefetch_range);
+                let prefetch_range = dirty_bitmap.restrict_range_to_predicted(prefetch_range);
+                copy_unaccessed_pages(tracker, uffd, &prefetch_range, false);
+                accessed_bitmap.mark_range(&prefetch_range);
+                dirty_bitmap.mark_range(&prefetch_range);
+                tracker.add_dirty_pages(faulting_page, prefetch_range);
+            }
+        }
+    }
+    true
+}
+
+/// Populates the given range of unpopulated pages with their contents in the
+/// `PageMap` and wakes up the faulting thread.
+fn copy_unaccessed_pages(
+    tracker: &SigsegvMemoryTracker,
+    uffd: &Userfaultfd,
+    range: &Range<PageIndex>,
+    write_protect: bool,
+) {
+    let mut contents = Vec::with_capacity(range_size_in_bytes(range));
+    for page in range.start.get()..range.end.get() {
+        contents.extend_from_slice(tracker.page_map.get_page(PageIndex::new(page)));
+    }
+    let dst = tracker.page_start_addr_from(range.start);
+    match uffd.copy(dst, &contents, write_protect) {
+        Ok(()) => {}
+        // The pages have been populated concurrently, so the faulting thread
+        // only needs to be woken up.
+        Err(Errno::EEXIST) => uffd
+            .wake(dst, contents.len())
+            .unwrap_or_else(|err| panic!("Failed to wake up the faulting thread: {}", err)),
+        Err(err) => panic!("Failed to populate memory pages: {}", err),
+    }
+    tracker.memory_instructions_stats.copy_page_count.fetch_add(
+        (range.end.get() - range.start.get()) as usize,
+        Ordering::Relaxed,
+    );
+}
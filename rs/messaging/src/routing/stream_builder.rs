@@ -16,7 +16,7 @@ use ic_types::{
         MAX_INTER_CANISTER_PAYLOAD_IN_BYTES, MAX_REJECT_MESSAGE_LEN_BYTES,
     },
     xnet::QueueId,
-    CountBytes, SubnetId,
+    CanisterId, CountBytes, SubnetId,
 };
 #[cfg(test)]
 use mockall::automock;
@@ -38,6 +38,12 @@ struct StreamBuilderMetrics {
     pub routed_messages: IntCounterVec,
     /// Successfully routed XNet messages' total payload size.
     pub routed_payload_sizes: Histogram,
+    /// Messages left in output queues because the stream was at its limit or
+    /// the sender at its fair share, by destination subnet.
+    pub stream_backlog_messages: IntGaugeVec,
+    /// Messages left in output queues after routing, per canister with a
+    /// backlog.
+    pub canister_backlog_messages: Histogram,
     /// Critical error counter for detected infinite loops while routing.
     pub critical_error_infinite_loops: IntCounter,
     /// Critical error for payloads above the maximum supported size.
@@ -58,11 +64,21 @@ const TARGET_STREAM_SIZE_BYTES: usize = 10 * 1024 * 1024;
 /// `count_bytes()` is greater than or equal to `TARGET_STREAM_SIZE_BYTES`.
 const MAX_STREAM_MESSAGES: usize = 50_000;
 
+/// Fraction of the stream limits by which responses may exceed them.
+///
+/// Requests are only routed into a stream while it is below
+/// `MAX_STREAM_MESSAGES` and `TARGET_STREAM_SIZE_BYTES`; responses may use an
+/// additional `1 / RESPONSE_HEADROOM_DIVISOR` of either, so that a stream
+/// filled with requests does not hold back responses.
+const RESPONSE_HEADROOM_DIVISOR: usize = 4;
+
 const METRIC_STREAM_MESSAGES: &str = "mr_stream_messages";
 const METRIC_STREAM_BYTES: &str = "mr_stream_bytes";
 const METRIC_STREAM_BEGIN: &str = "mr_stream_begin";
 const METRIC_ROUTED_MESSAGES: &str = "mr_routed_message_count";
 const METRIC_ROUTED_PAYLOAD_SIZES: &str = "mr_routed_payload_size_bytes";
+const METRIC_STREAM_BACKLOG_MESSAGES: &str = "mr_stream_backlog_messages";
+const METRIC_CANISTER_BACKLOG_MESSAGES: &str = "mr_canister_backlog_messages";
 
 const LABEL_TYPE: &str = "type";
 const LABEL_STATUS: &str = "status";
@@ -107,6 +123,17 @@ impl StreamBuilderMetrics {
             // 10 B - 5 MB
             decimal_buckets(1, 6),
         );
+        let stream_backlog_messages = metrics_registry.int_gauge_vec(
+            METRIC_STREAM_BACKLOG_MESSAGES,
+            "Messages left in output queues because the stream was at its limit or the sender at its fair share, by destination subnet.",
+            &[LABEL_REMOTE],
+        );
+        let canister_backlog_messages = metrics_registry.histogram(
+            METRIC_CANISTER_BACKLOG_MESSAGES,
+            "Messages left in output queues after routing, per canister with a backlog.",
+            // 1 - 50K
+            decimal_buckets(0, 4),
+        );
         let critical_error_infinite_loops =
             metrics_registry.error_counter(CRITICAL_ERROR_INFINITE_LOOP);
         let critical_error_payload_too_large =
@@ -136,6 +163,8 @@ impl StreamBuilderMetrics {
             stream_begin,
             routed_messages,
             routed_payload_sizes,
+            stream_backlog_messages,
+            canister_backlog_messages,
             critical_error_infinite_loops,
             critical_error_payload_too_large,
             critical_error_response_destination_not_found,
@@ -143,6 +172,13 @@ impl StreamBuilderMetrics {
     }
 }
 
+/// Requests enqueued into a stream by a single sender.
+#[derive(Default)]
+struct SenderUsage {
+    messages: usize,
+    bytes: usize,
+}
+
 /// Interface for the StreamBuilder sub-component.  Invoked by the
 /// Coordinator.
 #[cfg_attr(test, automock)]
@@ -286,6 +322,59 @@ impl StreamBuilderImpl {
                 && stream_messages_len >= 2 * SYSTEM_SUBNET_STREAM_MSG_LIMIT
         }
 
+        /// Adds `1 / RESPONSE_HEADROOM_DIVISOR` to a stream limit.
+        fn with_response_headroom(limit: usize) -> usize {
+            limit.saturating_add(limit / RESPONSE_HEADROOM_DIVISOR)
+        }
+
+        /// Collects the requests already enqueued in `stream`, by sender.
+        /// Requests sent by the subnet itself are not subject to fairness and
+        /// are not collected.
+        fn requests_by_sender(
+            stream: Option<&Stream>,
+            own_subnet_canister: CanisterId,
+        ) -> BTreeMap<CanisterId, SenderUsage> {
+            let mut senders = BTreeMap::<CanisterId, SenderUsage>::new();
+            let messages = stream
+                .into_iter()
+                .flat_map(|stream| stream.messages().iter());
+            for (_, msg) in messages {
+                if let RequestOrResponse::Request(req) = msg {
+                    if req.sender != own_subnet_canister {
+                        let usage = senders.entry(req.sender).or_default();
+                        usage.messages += 1;
+                        usage.bytes += msg.count_bytes();
+                    }
+                }
+            }
+            senders
+        }
+
+        /// Tests whether `sender` has used up its fair share of a stream, i.e.
+        /// the message count or byte size limit divided by the number of
+        /// senders competing for the stream.
+        fn is_at_fair_share(
+            senders: &BTreeMap<CanisterId, SenderUsage>,
+            sender: &CanisterId,
+            max_stream_messages: usize,
+            target_stream_size_bytes: usize,
+        ) -> bool {
+            let competing_senders = senders.len().max(1);
+            senders.get(sender).map_or(false, |usage| {
+                usage.messages >= max_stream_messages / competing_senders
+                    || usage.bytes >= target_stream_size_bytes / competing_senders
+            })
+        }
+
+        /// Excludes the next queue from iteration and returns the number of
+        /// messages left in it.
+        fn exclude_queue(iterator: &mut dyn PeekableOutputIterator) -> usize {
+            let size_before = iterator.size_hint().0;
+            iterator.exclude_queue();
+            size_before - iterator.size_hint().0
+        }
+
+        let own_subnet_canister = CanisterId::from(self.subnet_id);
         let mut streams = state.take_streams();
         let routing_table = state.routing_table();
         let subnet_types: BTreeMap<_, _> = state
@@ -299,12 +388,24 @@ impl StreamBuilderImpl {
         let mut requests_to_reject = Vec::new();
         let mut oversized_requests = Vec::new();
 
+        // Requests in each stream, by sender. Populated from the stream's
+        // messages the first time a request is routed into the stream; every
+        // sender of a request then competes for a fair share of the stream.
+        let mut stream_senders: BTreeMap<SubnetId, BTreeMap<CanisterId, SenderUsage>> =
+            BTreeMap::new();
+        // Messages left in output queues, by destination subnet and by sender.
+        let mut stream_backlogs: BTreeMap<SubnetId, usize> = BTreeMap::new();
+        let mut canister_backlogs: BTreeMap<CanisterId, usize> = BTreeMap::new();
+
         let mut output_iter = state.output_into_iter();
         let mut last_output_size = usize::MAX;
 
         // Route all messages into the appropriate stream or generate reject Responses
         // when unable to (no route to canister). When a stream's byte size reaches or
         // exceeds `target_stream_size_bytes`, any matching queues are skipped.
+        // Responses may exceed the limits by a headroom; and a sender whose requests
+        // take up its fair share of a stream is skipped, so that other senders' requests
+        // make progress.
         while let Some((queue_id, msg)) = output_iter.peek() {
             // Cheap to clone, `RequestOrResponse` wraps `Arcs`.
             let msg = msg.clone();
@@ -327,17 +428,45 @@ impl StreamBuilderImpl {
             match routing_table.route(queue_id.dst_canister.get()) {
                 // Destination subnet found.
                 Some(dst_net_id) => {
-                    if is_at_limit(
+                    let is_request = matches!(msg, RequestOrResponse::Request(_));
+                    let (messages_limit, bytes_limit) = if is_request {
+                        (max_stream_messages, target_stream_size_bytes)
+                    } else {
+                        (
+                            with_response_headroom(max_stream_messages),
+                            with_response_headroom(target_stream_size_bytes),
+                        )
+                    };
+                    let stream_at_limit = is_at_limit(
                         streams.get(&dst_net_id),
-                        max_stream_messages,
-                        target_stream_size_bytes,
+                        messages_limit,
+                        bytes_limit,
                         self.subnet_id == dst_net_id,
                         *subnet_types
                             .get(&dst_net_id)
                             .unwrap_or(&SubnetType::Application),
-                    ) {
-                        // Stream full, skip all other messages to this destination.
-                        output_iter.exclude_queue();
+                    );
+                    let sender_at_fair_share = !stream_at_limit
+                        && is_request
+                        && queue_id.src_canister != own_subnet_canister
+                        && {
+                            let senders = stream_senders.entry(dst_net_id).or_insert_with(|| {
+                                requests_by_sender(streams.get(&dst_net_id), own_subnet_canister)
+                            });
+                            senders.entry(queue_id.src_canister).or_default();
+                            is_at_fair_share(
+                                senders,
+                                &queue_id.src_canister,
+                                max_stream_messages,
+                                target_stream_size_bytes,
+                            )
+                        };
+                    if stream_at_limit || sender_at_fair_share {
+                        // Stream full or sender at its fair share, skip all other messages
+                        // in this queue.
+                        let backlog = exclude_queue(&mut output_iter);
+                        *stream_backlogs.entry(dst_net_id).or_default() += backlog;
+                        *canister_backlogs.entry(queue_id.src_canister).or_default() += backlog;
                         continue;
                     }
 
@@ -408,6 +537,15 @@ impl StreamBuilderImpl {
                             // Route the message into the stream.
                             self.observe_message_status(&msg, LABEL_VALUE_STATUS_SUCCESS);
                             self.observe_payload_size(&msg);
+                            if let RequestOrResponse::Request(req) = &msg {
+                                if let Some(usage) = stream_senders
+                                    .get_mut(&dst_net_id)
+                                    .and_then(|senders| senders.get_mut(&req.sender))
+                                {
+                                    usage.messages += 1;
+                                    usage.bytes += msg.count_bytes();
+                                }
+                            }
                             streams.push(dst_net_id, msg);
                         }
                     };
@@ -491,6 +629,19 @@ impl StreamBuilderImpl {
                     .set(begin.get() as i64);
             });
 
+        // Export the messages left in output queues, per stream and per canister.
+        for subnet in streams.keys().chain(stream_backlogs.keys()) {
+            self.metrics
+                .stream_backlog_messages
+                .with_label_values(&[&subnet.to_string()])
+                .set(stream_backlogs.get(subnet).copied().unwrap_or_default() as i64);
+        }
+        for backlog in canister_backlogs.values() {
+            self.metrics
+                .canister_backlog_messages
+                .observe(*backlog as f64);
+        }
+
         {
             // Record the enqueuing time of any messages newly enqueued into `streams`.
             let mut time_in_stream_metrics = self.time_in_stream_metrics.lock().unwrap();
@@ -881,6 +881,188 @@ fn build_streams_with_oversized_payloads() {
     });
 }
 
+/// Tests that a sender whose requests already take up its fair share of a
+/// stream is skipped, so that another sender's requests are routed instead.
+#[test]
+fn build_streams_impl_limits_senders_to_fair_share() {
+    with_test_replica_logger(|log| {
+        let (stream_builder, mut provided_state, metrics_registry) = new_fixture(&log);
+        provided_state.metadata.network_topology.routing_table = Arc::new(RoutingTable::try_from(
+            btreemap! {
+                CanisterIdRange{ start: CanisterId::from(0), end: CanisterId::from(0xfff) } => REMOTE_SUBNET,
+            },
+        ).unwrap());
+
+        let chatty_canister = canister_test_id(3);
+        let other_canister = canister_test_id(4);
+        let receiver = canister_test_id(700);
+        let requests_from = |sender: CanisterId, callback_ids: std::ops::Range<u64>| {
+            callback_ids
+                .map(|i| {
+                    generate_message_for_test(
+                        sender,
+                        receiver,
+                        CallbackId::from(i),
+                        format!("req_{}", i),
+                        Cycles::new(1),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // The stream already holds 4 requests from `chatty_canister`.
+        let mut stream_messages = StreamIndexedQueue::default();
+        for req in requests_from(chatty_canister, 1..5) {
+            stream_messages.push(req.into());
+        }
+        provided_state.modify_streams(|streams| {
+            streams.insert(
+                REMOTE_SUBNET,
+                Stream::new(stream_messages.clone(), Default::default()),
+            );
+        });
+
+        // Both canisters have 4 more requests in their output queues.
+        let other_requests = requests_from(other_canister, 1..5);
+        let mut msgs = requests_from(chatty_canister, 5..9);
+        msgs.extend(other_requests.iter().cloned());
+        provided_state.put_canister_states(canister_states_with_outputs(msgs));
+
+        // Act: a limit of 8 messages gives each of the 2 senders a fair share of 4.
+        let result_state = stream_builder.build_streams_impl(provided_state, 8, usize::MAX);
+
+        // `chatty_canister` is at its fair share, only `other_canister`'s requests
+        // are routed.
+        for req in other_requests {
+            stream_messages.push(req.into());
+        }
+        assert_eq!(
+            &stream_messages,
+            result_state.get_stream(&REMOTE_SUBNET).unwrap().messages()
+        );
+        assert_eq!(
+            4,
+            result_state
+                .canister_state(&chatty_canister)
+                .unwrap()
+                .system_state
+                .queues()
+                .output_queues_message_count()
+        );
+        assert_eq!(
+            0,
+            result_state
+                .canister_state(&other_canister)
+                .unwrap()
+                .system_state
+                .queues()
+                .output_queues_message_count()
+        );
+
+        assert_eq!(
+            metric_vec(&[(&[(LABEL_REMOTE, &REMOTE_SUBNET.to_string())], 4)]),
+            fetch_int_gauge_vec(&metrics_registry, METRIC_STREAM_BACKLOG_MESSAGES)
+        );
+        let canister_backlogs =
+            fetch_histogram_stats(&metrics_registry, METRIC_CANISTER_BACKLOG_MESSAGES).unwrap();
+        assert_eq!(1, canister_backlogs.count);
+        assert_eq!(4.0, canister_backlogs.sum);
+    });
+}
+
+/// Tests that responses are routed into a stream that is at its limit for
+/// requests.
+#[test]
+fn build_streams_impl_routes_responses_beyond_request_limit() {
+    with_test_replica_logger(|log| {
+        let (stream_builder, mut provided_state, metrics_registry) = new_fixture(&log);
+        provided_state.metadata.network_topology.routing_table = Arc::new(RoutingTable::try_from(
+            btreemap! {
+                CanisterIdRange{ start: CanisterId::from(0), end: CanisterId::from(0xfff) } => REMOTE_SUBNET,
+            },
+        ).unwrap());
+
+        let local_canister = canister_test_id(3);
+
+        // The stream already holds 4 requests.
+        let mut stream_messages = StreamIndexedQueue::default();
+        for i in 1..5 {
+            stream_messages.push(
+                generate_message_for_test(
+                    canister_test_id(4),
+                    canister_test_id(700),
+                    CallbackId::from(i),
+                    format!("req_{}", i),
+                    Cycles::new(1),
+                )
+                .into(),
+            );
+        }
+        provided_state.modify_streams(|streams| {
+            streams.insert(
+                REMOTE_SUBNET,
+                Stream::new(stream_messages.clone(), Default::default()),
+            );
+        });
+
+        // `local_canister` has both a request and a response for remote canisters.
+        let request: RequestOrResponse = generate_message_for_test(
+            local_canister,
+            canister_test_id(701),
+            CallbackId::from(1),
+            "req".to_string(),
+            Cycles::new(1),
+        )
+        .into();
+        let response: RequestOrResponse = Response {
+            originator: canister_test_id(702),
+            respondent: local_canister,
+            originator_reply_callback: CallbackId::from(1),
+            refund: Cycles::new(0),
+            response_payload: Payload::Data(vec![]),
+        }
+        .into();
+        provided_state.put_canister_states(canister_states_with_outputs(vec![
+            request,
+            response.clone(),
+        ]));
+
+        // Act: the stream is at the limit of 4 messages for requests.
+        let result_state = stream_builder.build_streams_impl(provided_state, 4, usize::MAX);
+
+        // Only the response was routed.
+        stream_messages.push(response);
+        assert_eq!(
+            &stream_messages,
+            result_state.get_stream(&REMOTE_SUBNET).unwrap().messages()
+        );
+        assert_eq!(
+            1,
+            result_state
+                .canister_state(&local_canister)
+                .unwrap()
+                .system_state
+                .queues()
+                .output_queues_message_count()
+        );
+
+        assert_routed_messages_eq(
+            metric_vec(&[(
+                &[
+                    (LABEL_TYPE, LABEL_VALUE_TYPE_RESPONSE),
+                    (LABEL_STATUS, LABEL_VALUE_STATUS_SUCCESS),
+                ],
+                1,
+            )]),
+            &metrics_registry,
+        );
+        assert_eq!(
+            metric_vec(&[(&[(LABEL_REMOTE, &REMOTE_SUBNET.to_string())], 1)]),
+            fetch_int_gauge_vec(&metrics_registry, METRIC_STREAM_BACKLOG_MESSAGES)
+        );
+    });
+}
+
 /// Sets up the `StreamHandlerImpl`, `ReplicatedState` and `MetricsRegistry` to
 /// be used by a test.
 fn new_fixture(log: &ReplicaLogger) -> (StreamBuilderImpl, ReplicatedState, MetricsRegistry) {
@@ -1002,7 +1002,9 @@ fn get_effective_payload(mt: NnsFunction, payload: &[u8]) -> Cow<[u8]> {
         | NnsFunction::AddApiBoundaryNode
         | NnsFunction::RemoveApiBoundaryNodes
         | NnsFunction::UpdateApiBoundaryNodeDomain
-        | NnsFunction::UpdateApiBoundaryNodesVersion => Cow::Borrowed(payload),
+        | NnsFunction::UpdateApiBoundaryNodesVersion
+        | NnsFunction::SplitSubnet
+        | NnsFunction::UpdateSubnetSplittingStatus => Cow::Borrowed(payload),
     }
 }
 
@@ -3556,6 +3556,10 @@ pub enum NnsFunction {
     UpdateApiBoundaryNodeDomain = 45,
     /// A proposal to update the version of a set of API Boundary Nodes
     UpdateApiBoundaryNodesVersion = 46,
+    /// Split a subnet, moving a set of its canister ID ranges to a halted subnet.
+    SplitSubnet = 47,
+    /// Record the completion of a step of an ongoing subnet split.
+    UpdateSubnetSplittingStatus = 48,
 }
 impl NnsFunction {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -3623,6 +3627,10 @@ impl NnsFunction {
             NnsFunction::UpdateApiBoundaryNodesVersion => {
                 "NNS_FUNCTION_UPDATE_API_BOUNDARY_NODES_VERSION"
             }
+            NnsFunction::SplitSubnet => "NNS_FUNCTION_SPLIT_SUBNET",
+            NnsFunction::UpdateSubnetSplittingStatus => {
+                "NNS_FUNCTION_UPDATE_SUBNET_SPLITTING_STATUS"
+            }
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -3687,6 +3695,10 @@ impl NnsFunction {
             "NNS_FUNCTION_UPDATE_API_BOUNDARY_NODES_VERSION" => {
                 Some(Self::UpdateApiBoundaryNodesVersion)
             }
+            "NNS_FUNCTION_SPLIT_SUBNET" => Some(Self::SplitSubnet),
+            "NNS_FUNCTION_UPDATE_SUBNET_SPLITTING_STATUS" => {
+                Some(Self::UpdateSubnetSplittingStatus)
+            }
             _ => None,
         }
     }
@@ -654,6 +654,10 @@ impl NnsFunction {
             NnsFunction::UpdateApiBoundaryNodesVersion => {
                 (REGISTRY_CANISTER_ID, "update_api_boundary_nodes_version")
             }
+            NnsFunction::SplitSubnet => (REGISTRY_CANISTER_ID, "split_subnet"),
+            NnsFunction::UpdateSubnetSplittingStatus => {
+                (REGISTRY_CANISTER_ID, "update_subnet_splitting_status")
+            }
         };
         Ok((canister_id, method))
     }
@@ -770,6 +774,8 @@ impl Proposal {
                             NnsFunction::ChangeSubnetTypeAssignment => Topic::SubnetManagement,
                             NnsFunction::UpdateAllowedPrincipals => Topic::SnsAndCommunityFund,
                             NnsFunction::UpdateSnsWasmSnsSubnetIds => Topic::SubnetManagement,
+                            NnsFunction::SplitSubnet => Topic::SubnetManagement,
+                            NnsFunction::UpdateSubnetSplittingStatus => Topic::SubnetManagement,
                             // Retired NnsFunctions
                             NnsFunction::BlessReplicaVersion
                             | NnsFunction::RetireReplicaVersion => Topic::ReplicaVersionManagement,
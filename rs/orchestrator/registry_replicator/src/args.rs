@@ -48,6 +48,12 @@ pub struct RegistryReplicatorArgs {
     /// The delay between NNS polls in milliseconds
     #[clap(long, default_value = "5000")]
     pub poll_delay_duration_ms: u64,
+
+    /// If set, the NNS is watched for new registry versions in between polls
+    /// at this interval in milliseconds, so that new versions are fetched as
+    /// soon as they are available
+    #[clap(long)]
+    pub watch_interval_ms: Option<u64>,
 }
 
 impl RegistryReplicatorArgs {
@@ -69,6 +75,7 @@ impl RegistryReplicatorArgs {
         config.registration.nns_url = Some(self.nns_url.clone());
         config.registry_client.local_store = self.local_store_path.clone();
         config.nns_registry_replicator.poll_delay_duration_ms = self.poll_delay_duration_ms;
+        config.nns_registry_replicator.watch_interval_ms = self.watch_interval_ms;
 
         (config, _dir)
     }
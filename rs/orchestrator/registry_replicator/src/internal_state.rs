@@ -1,5 +1,5 @@
 use ic_interfaces_registry::{RegistryClient, ZERO_REGISTRY_VERSION};
-use ic_logger::{info, warn, ReplicaLogger};
+use ic_logger::{debug, info, warn, ReplicaLogger};
 use ic_protobuf::{
     registry::{
         node::v1::ConnectionEndpoint,
@@ -24,13 +24,39 @@ use ic_types::{
     crypto::threshold_sig::ThresholdSigPublicKey, NodeId, RegistryVersion, SubnetId, Time,
 };
 use std::{
-    collections::BTreeMap, convert::TryFrom, fmt::Debug, net::IpAddr, str::FromStr, sync::Arc,
-    time::Duration,
+    collections::BTreeMap,
+    convert::TryFrom,
+    fmt::Debug,
+    net::IpAddr,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 use url::Url;
 
 const MAX_CONSECUTIVE_FAILURES: i64 = 3;
 
+/// The outcome of [`InternalState::watch_for_new_version()`].
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum WatchOutcome {
+    /// The NNS reported a version newer than the latest stored one.
+    NewVersion(RegistryVersion),
+    /// No newer version was observed before the timeout elapsed.
+    Timeout,
+    /// The registry canister is not known yet, no version could be observed.
+    Unavailable,
+}
+
+impl WatchOutcome {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            WatchOutcome::NewVersion(_) => "new_version",
+            WatchOutcome::Timeout => "timeout",
+            WatchOutcome::Unavailable => "unavailable",
+        }
+    }
+}
+
 /// The `InternalState` encompasses a locally persisted registry changelog which
 /// is kept up to date by repeated calls to [`Self::poll()`]. If this node is
 /// part of a subnet that is starting up as the NNS after a switch-over, the
@@ -49,6 +75,7 @@ pub(crate) struct InternalState {
     registry_client: Arc<dyn RegistryClient>,
     local_store: Arc<dyn LocalStore>,
     latest_version: RegistryVersion,
+    latest_stored_version: RegistryVersion,
     last_certified_time: Time,
     nns_pub_key: Option<ThresholdSigPublicKey>,
     nns_urls: Vec<Url>,
@@ -82,6 +109,7 @@ impl InternalState {
             registry_client,
             local_store,
             latest_version: ZERO_REGISTRY_VERSION,
+            latest_stored_version: ZERO_REGISTRY_VERSION,
             last_certified_time,
             nns_pub_key: None,
             nns_urls: vec![],
@@ -184,11 +212,13 @@ impl InternalState {
                 self.last_certified_time = t;
             }
 
+            let stored_version = latest_version + RegistryVersion::from(entries as u64);
+            self.latest_stored_version = self.latest_stored_version.max(stored_version);
+
             if entries > 0 {
                 info!(
                     self.logger,
-                    "Stored registry versions up to: {}",
-                    latest_version + RegistryVersion::from(entries as u64)
+                    "Stored registry versions up to: {}", stored_version
                 );
             }
         }
@@ -196,6 +226,50 @@ impl InternalState {
         Ok(())
     }
 
+    /// Watches the NNS for a registry version newer than the latest version
+    /// stored by [`Self::poll()`] and returns as soon as one is observed, or
+    /// once `timeout` has elapsed.
+    ///
+    /// The registry canister does not offer a blocking call, so the long poll
+    /// is emulated by querying the (uncertified) latest version every
+    /// `watch_interval`. The new version is not applied; the certified changes
+    /// are to be fetched by a subsequent call to [`Self::poll()`]. Failed
+    /// queries are ignored, as they are reported by the next poll anyway.
+    pub(crate) async fn watch_for_new_version(
+        &self,
+        watch_interval: Duration,
+        timeout: Duration,
+    ) -> WatchOutcome {
+        let deadline = Instant::now() + timeout;
+        let registry_canister = match self.registry_canister.as_ref() {
+            Some(registry_canister) => Arc::clone(registry_canister),
+            None => {
+                tokio::time::sleep(timeout).await;
+                return WatchOutcome::Unavailable;
+            }
+        };
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return WatchOutcome::Timeout;
+            }
+            match tokio::time::timeout(remaining, registry_canister.get_latest_version()).await {
+                Ok(Ok(version)) if RegistryVersion::from(version) > self.latest_stored_version => {
+                    return WatchOutcome::NewVersion(RegistryVersion::from(version));
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => debug!(
+                    self.logger,
+                    "Querying the latest registry version failed: {:?}", e
+                ),
+                Err(_) => return WatchOutcome::Timeout,
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            tokio::time::sleep(watch_interval.min(remaining)).await;
+        }
+    }
+
     /// Iff at version `latest_version` the node id of this node appears on a
     /// subnet record that has the `start_as_nns` flag set, this function will
     /// adjust the registry such that the aforementioned subnet will become the
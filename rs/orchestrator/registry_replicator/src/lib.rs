@@ -2,7 +2,10 @@
 //!
 //! (1) It polls one of the NNS Nodes for registry updates on a regular basis,
 //! verifies the response using the public key configured in the registry and
-//! applies the received changelog to the Registry Local Store.
+//! applies the received changelog to the Registry Local Store. If a watch
+//! interval is configured, the NNS is additionally watched for new registry
+//! versions in between polls, so that they are replicated without waiting for
+//! the full poll delay.
 //!
 //! (2) In case of a "switch-over" or starting a new independent NNS subnet, the
 //! Registry Replicator modifies the Registry Local Store before rebooting:
@@ -28,7 +31,7 @@
 //! registry, the switch-over is *not* atomic. This is the reason why the
 //! switch-over is handled in this component.
 
-use crate::internal_state::InternalState;
+use crate::internal_state::{InternalState, WatchOutcome};
 use ic_config::{
     metrics::{Config as MetricsConfig, Exporter},
     Config,
@@ -67,6 +70,7 @@ pub struct RegistryReplicator {
     started: Arc<AtomicBool>,
     cancelled: Arc<AtomicBool>,
     poll_delay: Duration,
+    watch_interval: Option<Duration>,
     metrics: Arc<RegistryreplicatorMetrics>,
 }
 
@@ -87,6 +91,7 @@ impl RegistryReplicator {
             started: Arc::new(AtomicBool::new(false)),
             cancelled: Arc::new(AtomicBool::new(false)),
             poll_delay,
+            watch_interval: None,
             metrics,
         }
     }
@@ -104,6 +109,10 @@ impl RegistryReplicator {
 
         let poll_delay =
             std::time::Duration::from_millis(config.nns_registry_replicator.poll_delay_duration_ms);
+        let watch_interval = config
+            .nns_registry_replicator
+            .watch_interval_ms
+            .map(Duration::from_millis);
 
         // Initialize registry client and start polling/caching *local* store for
         // updates
@@ -119,6 +128,7 @@ impl RegistryReplicator {
             started: Arc::new(AtomicBool::new(false)),
             cancelled: Arc::new(AtomicBool::new(false)),
             poll_delay,
+            watch_interval,
             metrics,
         }
     }
@@ -341,6 +351,7 @@ impl RegistryReplicator {
         let registry_client = self.registry_client.clone();
         let cancelled = Arc::clone(&self.cancelled);
         let poll_delay = self.poll_delay;
+        let watch_interval = self.watch_interval;
         info!(logger, "Spawning background thread.");
         let handle = tokio::spawn(async move {
             while !cancelled.load(Ordering::Relaxed) {
@@ -350,18 +361,38 @@ impl RegistryReplicator {
                 // `poll_delay` when constructing the underlying
                 // `RegistryCanister` abstraction, we are guaranteed that
                 // `poll()` returns after a maximal duration of `poll_delay`.
-                if let Err(msg) = internal_state.poll().await {
+                let poll_succeeded = if let Err(msg) = internal_state.poll().await {
                     warn!(logger, "Polling the NNS registry failed: {}", msg);
                     metrics.poll_count.with_label_values(&["error"]).inc();
+                    false
                 } else {
                     debug!(logger, "Polling the NNS succeeded.");
                     metrics.poll_count.with_label_values(&["success"]).inc();
-                }
+                    true
+                };
                 timer.observe_duration();
                 metrics
                     .registry_version
                     .set(registry_client.get_latest_version().get() as i64);
-                tokio::time::sleep(poll_delay).await;
+                match watch_interval {
+                    // Instead of sleeping, wait for the NNS to report a new
+                    // version, so that it is fetched right away. After a failed
+                    // poll we back off for the full delay, as a new version
+                    // would be reported immediately.
+                    Some(watch_interval) if poll_succeeded => {
+                        let outcome = internal_state
+                            .watch_for_new_version(watch_interval, poll_delay)
+                            .await;
+                        if let WatchOutcome::NewVersion(version) = outcome {
+                            debug!(logger, "Observed new registry version {}.", version);
+                        }
+                        metrics
+                            .watch_count
+                            .with_label_values(&[outcome.as_str()])
+                            .inc();
+                    }
+                    _ => tokio::time::sleep(poll_delay).await,
+                }
             }
         });
         Ok(handle)
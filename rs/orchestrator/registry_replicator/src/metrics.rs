@@ -8,6 +8,7 @@ pub struct RegistryreplicatorMetrics {
     pub poll_duration: Histogram,
     pub poll_count: IntCounterVec,
     pub registry_version: IntGauge,
+    pub watch_count: IntCounterVec,
 }
 
 impl RegistryreplicatorMetrics {
@@ -29,6 +30,11 @@ impl RegistryreplicatorMetrics {
                 "replicator_registry_version",
                 "Latest registry version pulled",
             ),
+            watch_count: metrics_registry.int_counter_vec(
+                "replicator_watch_count",
+                "The number of times watching the NNS for a new registry version ended, by outcome.",
+                &["outcome"],
+            ),
         }
     }
 }
@@ -45,4 +45,5 @@ mod registration;
 mod registry_helper;
 mod signer;
 mod ssh_access_manager;
+mod subnet_splitting;
 mod upgrade;
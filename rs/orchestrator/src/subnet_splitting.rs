@@ -0,0 +1,347 @@
+//! Acts on the registry record of a subnet split this node's subnet takes
+//! part in.
+//!
+//! Once the registry records that the source subnet halted at a CUP (status
+//! `SourceHalted`), the split canister ranges are routed to the destination
+//! subnet. Until the node receives a CUP referencing a registry version with
+//! the new routes, i.e. the recovery CUP its subnet resumes from, the replica
+//! must not run. Meanwhile, the orchestrator derives the node's half of the
+//! state from the checkpoint at the CUP height with `state-tool split`. Nodes
+//! of the destination subnet can only do so once a copy of the source
+//! subnet's checkpoint at that height was placed in their state root.
+
+use crate::error::{OrchestratorError, OrchestratorResult};
+use crate::registry_helper::RegistryHelper;
+use ic_logger::{info, ReplicaLogger};
+use ic_protobuf::registry::routing_table::v1::SubnetSplittingStatus;
+use ic_registry_client_helpers::routing_table::RoutingTableRegistry;
+use ic_registry_routing_table::CanisterIdRange;
+use ic_types::{subnet_id_try_from_protobuf, Height, RegistryVersion, SubnetId};
+use std::convert::TryFrom;
+use std::path::Path;
+use std::process::Command;
+
+/// The part of a subnet split this node has to carry out before its subnet
+/// may resume.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct PendingSplit {
+    /// The subnet of this node.
+    subnet_id: SubnetId,
+    /// The height of the CUP the source subnet halted at.
+    cup_height: Height,
+    /// The canister ID ranges hosted by `subnet_id` after the split.
+    canister_id_ranges: Vec<CanisterIdRange>,
+}
+
+impl PendingSplit {
+    /// Returns the split `subnet_id` takes part in, if the source subnet
+    /// halted for it and the latest CUP, referencing `cup_registry_version`,
+    /// predates the rerouting of the split canister ranges.
+    pub(crate) fn get(
+        registry: &RegistryHelper,
+        subnet_id: SubnetId,
+        cup_registry_version: RegistryVersion,
+    ) -> OrchestratorResult<Option<Self>> {
+        let client = &registry.registry_client;
+        let version = registry.get_latest_version();
+        let record = match client
+            .get_subnet_splitting_record_involving(subnet_id, version)
+            .map_err(OrchestratorError::RegistryClientError)?
+        {
+            Some((_, record)) => record,
+            None => return Ok(None),
+        };
+        if !matches!(
+            record.status(),
+            SubnetSplittingStatus::SourceHalted | SubnetSplittingStatus::StatesSplit
+        ) {
+            return Ok(None);
+        }
+
+        let invalid_record = |err: String| {
+            OrchestratorError::UpgradeError(format!("Invalid subnet splitting record: {}", err))
+        };
+        let destination = record
+            .destination_subnet_id
+            .clone()
+            .ok_or_else(|| invalid_record("no destination subnet".to_string()))
+            .and_then(|id| {
+                subnet_id_try_from_protobuf(id).map_err(|err| invalid_record(format!("{:?}", err)))
+            })?;
+        let split_ranges = record
+            .canister_id_ranges
+            .iter()
+            .cloned()
+            .map(CanisterIdRange::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| invalid_record(format!("{:?}", err)))?;
+
+        // The split ranges are rerouted when the source subnet halts, so a CUP
+        // referencing a routing table that already routes them to the
+        // destination is a recovery CUP created after the split.
+        let resumed = client
+            .get_routing_table(cup_registry_version)
+            .map_err(OrchestratorError::RegistryClientError)?
+            .map_or(false, |routing_table| {
+                split_ranges
+                    .iter()
+                    .all(|range| routing_table.route(range.start.get()) == Some(destination))
+            });
+        if resumed {
+            return Ok(None);
+        }
+
+        let canister_id_ranges = client
+            .get_subnet_canister_ranges(version, subnet_id)
+            .map_err(OrchestratorError::RegistryClientError)?
+            .unwrap_or_default();
+        Ok(Some(Self {
+            subnet_id,
+            cup_height: Height::new(record.cup_height),
+            canister_id_ranges,
+        }))
+    }
+
+    /// Splits the state under `state_root` with the `state-tool` in
+    /// `ic_binary_dir`. Does nothing unless the latest checkpoint is at the
+    /// height the source subnet halted at: the split writes a checkpoint at
+    /// the next height, and without the source subnet's checkpoint there is
+    /// nothing to split yet.
+    pub(crate) fn split_state(
+        &self,
+        state_root: &Path,
+        ic_binary_dir: &Path,
+        logger: &ReplicaLogger,
+    ) -> OrchestratorResult<()> {
+        if latest_checkpoint_height(&state_root.join("checkpoints"))? != Some(self.cup_height) {
+            return Ok(());
+        }
+        if self.canister_id_ranges.is_empty() {
+            return Err(OrchestratorError::UpgradeError(format!(
+                "Subnet {} hosts no canister ranges after the split",
+                self.subnet_id
+            )));
+        }
+
+        info!(
+            logger,
+            "Splitting the state of subnet {} at height {}", self.subnet_id, self.cup_height
+        );
+        let output = Command::new(ic_binary_dir.join("state-tool"))
+            .arg("split")
+            .arg("--root")
+            .arg(state_root)
+            .arg("--subnet-id")
+            .arg(self.subnet_id.to_string())
+            .arg("--retain")
+            .args(
+                self.canister_id_ranges
+                    .iter()
+                    .map(|range| format!("{}:{}", range.start, range.end)),
+            )
+            .output()
+            .map_err(|err| OrchestratorError::IoError("Failed to run state-tool".into(), err))?;
+        if !output.status.success() {
+            return Err(OrchestratorError::UpgradeError(format!(
+                "Failed to split the state of subnet {}: {}",
+                self.subnet_id,
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the height of the latest checkpoint in `checkpoints_dir`, if any.
+fn latest_checkpoint_height(checkpoints_dir: &Path) -> OrchestratorResult<Option<Height>> {
+    let entries = match std::fs::read_dir(checkpoints_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(OrchestratorError::IoError(
+                format!("Failed to list {}", checkpoints_dir.display()),
+                err,
+            ))
+        }
+    };
+    Ok(entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            u64::from_str_radix(name.to_str()?, 16).ok()
+        })
+        .max()
+        .map(Height::new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_logger::replica_logger::no_op_logger;
+    use ic_protobuf::registry::routing_table::v1 as pb;
+    use ic_registry_client_fake::FakeRegistryClient;
+    use ic_registry_keys::{make_routing_table_record_key, make_subnet_splitting_record_key};
+    use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
+    use ic_registry_routing_table::RoutingTable;
+    use ic_test_utilities::types::ids::{node_test_id, subnet_test_id};
+    use ic_types::{subnet_id_into_protobuf, CanisterId};
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::Arc;
+
+    fn range(start: u64, end: u64) -> CanisterIdRange {
+        CanisterIdRange {
+            start: CanisterId::from_u64(start),
+            end: CanisterId::from_u64(end),
+        }
+    }
+
+    fn routing_table(entries: &[(CanisterIdRange, SubnetId)]) -> pb::RoutingTable {
+        let mut routing_table = RoutingTable::new();
+        for (range, subnet_id) in entries {
+            routing_table.insert(*range, *subnet_id).unwrap();
+        }
+        pb::RoutingTable::from(routing_table)
+    }
+
+    /// Returns a registry where the source subnet 1 hosted canisters 0 to 255
+    /// at version 1 and halted at height 100 to split off canisters 128 to 255
+    /// to subnet 2 at version 2, with the split in `status`.
+    fn registry(status: SubnetSplittingStatus) -> RegistryHelper {
+        let (source, destination) = (subnet_test_id(1), subnet_test_id(2));
+        let data_provider = Arc::new(ProtoRegistryDataProvider::new());
+        data_provider
+            .add(
+                &make_routing_table_record_key(),
+                RegistryVersion::from(1),
+                Some(routing_table(&[(range(0, 255), source)])),
+            )
+            .unwrap();
+        data_provider
+            .add(
+                &make_routing_table_record_key(),
+                RegistryVersion::from(2),
+                Some(routing_table(&[
+                    (range(0, 127), source),
+                    (range(128, 255), destination),
+                ])),
+            )
+            .unwrap();
+        let mut record = pb::SubnetSplittingRecord {
+            destination_subnet_id: Some(subnet_id_into_protobuf(destination)),
+            canister_id_ranges: vec![pb::CanisterIdRange::from(range(128, 255))],
+            cup_height: 100,
+            ..Default::default()
+        };
+        record.set_status(status);
+        data_provider
+            .add(
+                &make_subnet_splitting_record_key(source),
+                RegistryVersion::from(2),
+                Some(record),
+            )
+            .unwrap();
+
+        let registry_client = Arc::new(FakeRegistryClient::new(data_provider));
+        registry_client.update_to_latest_version();
+        RegistryHelper::new(node_test_id(1), registry_client, no_op_logger())
+    }
+
+    #[test]
+    fn both_subnets_hold_until_cup_references_rerouted_ranges() {
+        let registry = registry(SubnetSplittingStatus::SourceHalted);
+        let (source, destination) = (subnet_test_id(1), subnet_test_id(2));
+
+        assert_eq!(
+            PendingSplit::get(&registry, source, RegistryVersion::from(1)).unwrap(),
+            Some(PendingSplit {
+                subnet_id: source,
+                cup_height: Height::new(100),
+                canister_id_ranges: vec![range(0, 127)],
+            })
+        );
+        assert_eq!(
+            PendingSplit::get(&registry, destination, RegistryVersion::from(1)).unwrap(),
+            Some(PendingSplit {
+                subnet_id: destination,
+                cup_height: Height::new(100),
+                canister_id_ranges: vec![range(128, 255)],
+            })
+        );
+        assert_eq!(
+            PendingSplit::get(&registry, source, RegistryVersion::from(2)).unwrap(),
+            None
+        );
+        assert_eq!(
+            PendingSplit::get(&registry, destination, RegistryVersion::from(2)).unwrap(),
+            None
+        );
+        assert_eq!(
+            PendingSplit::get(&registry, subnet_test_id(3), RegistryVersion::from(1)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn no_hold_before_source_halted_or_after_completion() {
+        for status in [
+            SubnetSplittingStatus::Scheduled,
+            SubnetSplittingStatus::Completed,
+        ] {
+            assert_eq!(
+                PendingSplit::get(
+                    &registry(status),
+                    subnet_test_id(1),
+                    RegistryVersion::from(1)
+                )
+                .unwrap(),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn splits_only_the_checkpoint_at_the_cup_height() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (state_root, bin_dir) = (tmp.path().join("state"), tmp.path().join("bin"));
+        std::fs::create_dir_all(state_root.join("checkpoints").join(format!("{:016x}", 99)))
+            .unwrap();
+        std::fs::create_dir_all(state_root.join("checkpoints").join(format!("{:016x}", 100)))
+            .unwrap();
+        std::fs::create_dir_all(&bin_dir).unwrap();
+        let args_file = tmp.path().join("args");
+        let state_tool = bin_dir.join("state-tool");
+        std::fs::write(
+            &state_tool,
+            format!("#!/bin/sh\necho \"$@\" > {}\n", args_file.display()),
+        )
+        .unwrap();
+        std::fs::set_permissions(&state_tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let split = PendingSplit {
+            subnet_id: subnet_test_id(1),
+            cup_height: Height::new(100),
+            canister_id_ranges: vec![range(0, 127)],
+        };
+        split
+            .split_state(&state_root, &bin_dir, &no_op_logger())
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&args_file).unwrap(),
+            format!(
+                "split --root {} --subnet-id {} --retain {}:{}\n",
+                state_root.display(),
+                subnet_test_id(1),
+                CanisterId::from_u64(0),
+                CanisterId::from_u64(127)
+            )
+        );
+
+        // Once split, the latest checkpoint is past the CUP height.
+        std::fs::remove_file(&args_file).unwrap();
+        std::fs::create_dir_all(state_root.join("checkpoints").join(format!("{:016x}", 101)))
+            .unwrap();
+        split
+            .split_state(&state_root, &bin_dir, &no_op_logger())
+            .unwrap();
+        assert!(!args_file.exists());
+    }
+}
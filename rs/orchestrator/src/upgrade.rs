@@ -3,6 +3,7 @@ use crate::error::{OrchestratorError, OrchestratorResult};
 use crate::metrics::OrchestratorMetrics;
 use crate::process_manager::{Process, ProcessManager};
 use crate::registry_helper::RegistryHelper;
+use crate::subnet_splitting::PendingSplit;
 use async_trait::async_trait;
 use ic_crypto::get_tecdsa_master_public_key;
 use ic_http_utils::file_downloader::FileDownloader;
@@ -238,6 +239,14 @@ impl Upgrade {
         }
 
         // If we arrive here, we are on the newest replica version.
+        // If our subnet halted to be split, the replica must not run until the
+        // subnet resumes from its recovery CUP. Meanwhile, we split the state.
+        if let Some(split) = PendingSplit::get(&self.registry, subnet_id, cup_registry_version)? {
+            self.stop_replica()?;
+            split.split_state(&self.state_root()?, &self.ic_binary_dir, &self.logger)?;
+            return Ok(Some(subnet_id));
+        }
+
         // Now we check if a subnet recovery is in progress.
         // If it is, we restart to pass the unsigned CUP to consensus.
         self.stop_replica_if_new_recovery_cup(&latest_cup, old_cup_height);
@@ -353,6 +362,22 @@ impl Upgrade {
         })
     }
 
+    // Returns the state root configured in the replica config file.
+    fn state_root(&self) -> OrchestratorResult<PathBuf> {
+        use ic_config::{Config, ConfigSource};
+        let tmpdir = tempfile::Builder::new()
+            .prefix("ic_config")
+            .tempdir()
+            .map_err(|err| {
+                OrchestratorError::IoError("Couldn't create a temporary directory".into(), err)
+            })?;
+        let config = Config::load_with_tmpdir(
+            ConfigSource::File(self.replica_config_file.clone()),
+            tmpdir.path().to_path_buf(),
+        );
+        Ok(config.state_manager.state_root())
+    }
+
     // Stop the replica if the given CUP is unsigned and higher than the given height.
     // Without restart, consensus would reject the unsigned artifact.
     // If stopping the replica fails, restart the current process instead.
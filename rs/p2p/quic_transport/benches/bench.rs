@@ -20,7 +20,7 @@ use ic_p2p_test_utils::{
     create_registry_handle, temp_crypto_component_with_tls_keys, RegistryConsensusHandle,
 };
 use ic_peer_manager::SubnetTopology;
-use ic_quic_transport::{DummyUdpSocket, QuicTransport, Transport};
+use ic_quic_transport::{DummyUdpSocket, QuicTransport, StreamSchedulingConfig, Transport};
 use ic_types_test_utils::ids::{node_test_id, SUBNET_1};
 use tokio::{
     runtime::{Handle, Runtime},
@@ -69,6 +69,7 @@ fn spawn_transport(
         watch_rx,
         Either::<_, DummyUdpSocket>::Left(node_addr),
         Router::new().route("/", any(pong)),
+        StreamSchedulingConfig::default(),
     ));
     (transport, node_id, node_addr)
 }
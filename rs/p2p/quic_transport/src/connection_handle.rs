@@ -2,8 +2,11 @@
 //!
 //! Contains a wrapper, called `ConnectionHandle`, around quinn's Connection.
 //! The `ConnectionHandle` implements `rpc` and `push` methods for the given
-//! connection.
+//! connection. Outgoing requests are written through the stream scheduler of the
+//! connection, which is shared with the request handler.
 //!
+use std::sync::Arc;
+
 use bytes::Bytes;
 use http::{Request, Response};
 use ic_base_types::NodeId;
@@ -14,6 +17,8 @@ use crate::{
         QuicTransportMetrics, ERROR_TYPE_FINISH, ERROR_TYPE_OPEN, ERROR_TYPE_READ,
         ERROR_TYPE_WRITE, REQUEST_TYPE_PUSH, REQUEST_TYPE_RPC,
     },
+    scheduler::StreamScheduler,
+    stats::{ConnectionStats, OpenStreams},
     utils::{read_response, write_request},
     ConnId, SendError,
 };
@@ -23,6 +28,8 @@ pub(crate) struct ConnectionHandle {
     pub peer_id: NodeId,
     pub connection: Connection,
     pub metrics: QuicTransportMetrics,
+    pub scheduler: Arc<StreamScheduler>,
+    pub open_streams: Arc<OpenStreams>,
     conn_id: ConnId,
 }
 
@@ -31,12 +38,15 @@ impl ConnectionHandle {
         peer_id: NodeId,
         connection: Connection,
         metrics: QuicTransportMetrics,
+        scheduler: Arc<StreamScheduler>,
         conn_id: ConnId,
     ) -> Self {
         Self {
             peer_id,
             connection,
             metrics,
+            scheduler,
+            open_streams: Arc::new(OpenStreams::default()),
             conn_id,
         }
     }
@@ -45,6 +55,15 @@ impl ConnectionHandle {
         self.conn_id
     }
 
+    pub(crate) fn stats(&self) -> ConnectionStats {
+        ConnectionStats::new(
+            self.peer_id,
+            self.conn_id,
+            &self.connection,
+            &self.open_streams,
+        )
+    }
+
     pub(crate) async fn rpc(
         &self,
         mut request: Request<Bytes>,
@@ -66,6 +85,7 @@ impl ConnectionHandle {
         // Propagate PeerId from this connection to lower layers.
         request.extensions_mut().insert(self.peer_id);
 
+        let _open_stream = self.open_streams.outbound();
         let (mut send_stream, recv_stream) = self.connection.open_bi().await.map_err(|e| {
             self.metrics
                 .connection_handle_errors_total
@@ -75,7 +95,7 @@ impl ConnectionHandle {
             }
         })?;
 
-        write_request(&mut send_stream, request)
+        write_request(&mut send_stream, request, &self.scheduler)
             .await
             .map_err(|e| {
                 self.metrics
@@ -124,6 +144,7 @@ impl ConnectionHandle {
         // Propagate PeerId from this connection to lower layers.
         request.extensions_mut().insert(self.peer_id);
 
+        let _open_stream = self.open_streams.outbound();
         let mut send_stream = self.connection.open_uni().await.map_err(|e| {
             self.metrics
                 .connection_handle_errors_total
@@ -133,7 +154,7 @@ impl ConnectionHandle {
             }
         })?;
 
-        write_request(&mut send_stream, request)
+        write_request(&mut send_stream, request, &self.scheduler)
             .await
             .map_err(|e| {
                 self.metrics
@@ -68,6 +68,7 @@ use tokio_util::time::DelayQueue;
 use crate::{
     connection_handle::ConnectionHandle,
     metrics::{CONNECTION_RESULT_FAILED_LABEL, CONNECTION_RESULT_SUCCESS_LABEL},
+    scheduler::{StreamScheduler, StreamSchedulingConfig},
     utils::collect_metrics,
     ConnId,
 };
@@ -126,6 +127,7 @@ struct ConnectionManager {
     /// Endpoint config
     endpoint: Endpoint,
     transport_config: Arc<quinn::TransportConfig>,
+    scheduling_config: StreamSchedulingConfig,
     router: Router,
 }
 
@@ -201,6 +203,7 @@ pub(crate) fn start_connection_manager(
     watcher: tokio::sync::watch::Receiver<SubnetTopology>,
     socket: Either<SocketAddr, impl AsyncUdpSocket>,
     router: Router,
+    scheduling_config: StreamSchedulingConfig,
 ) {
     let topology = watcher.borrow().clone();
 
@@ -309,6 +312,7 @@ pub(crate) fn start_connection_manager(
         watcher,
         endpoint,
         transport_config,
+        scheduling_config,
         outbound_connecting: JoinMap::new(),
         inbound_connecting: JoinSet::new(),
         active_connections: JoinMap::new(),
@@ -575,8 +579,18 @@ impl ConnectionManager {
                 self.conn_id_counter.inc_assign();
                 let conn_id = self.conn_id_counter;
 
-                let connection_handle =
-                    ConnectionHandle::new(peer_id, connection, self.metrics.clone(), conn_id);
+                // Requests and responses on this connection share a single scheduler.
+                let scheduler = Arc::new(StreamScheduler::new(
+                    &self.scheduling_config,
+                    self.metrics.clone(),
+                ));
+                let connection_handle = ConnectionHandle::new(
+                    peer_id,
+                    connection,
+                    self.metrics.clone(),
+                    scheduler,
+                    conn_id,
+                );
                 let req_handler_connection_handle = connection_handle.clone();
 
                 // dropping the old connection will result in closing it
@@ -604,6 +618,8 @@ impl ConnectionManager {
                         req_handler_connection_handle.conn_id(),
                         req_handler_connection_handle.connection,
                         self.metrics.clone(),
+                        req_handler_connection_handle.scheduler,
+                        req_handler_connection_handle.open_streams,
                         self.router.clone(),
                     ),
                     &self.rt,
@@ -10,6 +10,10 @@
 //!  - Request Handler (request_handler.rs): Accepts streams on an active connection.
 //!    Spawned by the connection manager for each connection.
 //!  - Connection Handle (connection_handle.rs): Provides rpc and push interfaces to a peer.
+//!  - Stream Scheduler (scheduler.rs): Shares the send bandwidth of a connection between
+//!    consensus and state sync traffic according to configurable weights.
+//!  - Connection Stats (stats.rs): Per connection statistics exported as metrics and
+//!    served by the debug router.
 //!
 //! API:
 //!  - Constructor takes a topology watcher. The topology defines the
@@ -20,6 +24,8 @@
 //!     The connection handle is small wrapper around the actual quic connection
 //!     with an rpc/push interface. Passed in requests need to specify an URI to get
 //!     routed to the correct handler.
+//!  - `connection_stats`: Returns rtt, loss, congestion window and stream counts of
+//!     all active connections. `debug_router` serves the same information as json.
 //!
 //! GUARANTEES:
 //!  - If a peer is reachable, part of the topology and well-behaving transport will eventually
@@ -54,10 +60,15 @@ use thiserror::Error;
 use crate::connection_handle::ConnectionHandle;
 use crate::connection_manager::start_connection_manager;
 
+pub use crate::scheduler::StreamSchedulingConfig;
+pub use crate::stats::{ConnectionStats, CONNECTION_STATS_PATH};
+
 mod connection_handle;
 mod connection_manager;
 mod metrics;
 mod request_handler;
+mod scheduler;
+mod stats;
 mod utils;
 
 #[derive(Clone)]
@@ -87,6 +98,7 @@ impl QuicTransport {
         udp_socket: Either<SocketAddr, impl AsyncUdpSocket>,
         // Make sure this is respected https://docs.rs/axum/latest/axum/struct.Router.html#a-note-about-performance
         router: Router,
+        scheduling_config: StreamSchedulingConfig,
     ) -> QuicTransport {
         info!(log, "Starting Quic transport.");
 
@@ -104,11 +116,28 @@ impl QuicTransport {
             topology_watcher,
             udp_socket,
             router,
+            scheduling_config,
         );
 
         QuicTransport(peer_map)
     }
 
+    /// Returns the statistics of all active connections.
+    pub fn connection_stats(&self) -> Vec<ConnectionStats> {
+        self.0
+            .read()
+            .unwrap()
+            .values()
+            .map(|conn| conn.stats())
+            .collect()
+    }
+
+    /// Router serving the statistics of all active connections at `CONNECTION_STATS_PATH`.
+    /// Must only be served on an operator facing endpoint and not be exposed to peers.
+    pub fn debug_router(&self) -> Router {
+        stats::debug_router(self.clone())
+    }
+
     pub(crate) fn get_conn_handle(&self, peer_id: &NodeId) -> Result<ConnectionHandle, SendError> {
         let conn = self
             .0
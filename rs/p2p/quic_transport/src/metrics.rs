@@ -1,11 +1,11 @@
-use ic_base_types::NodeId;
 use ic_metrics::{
     buckets::decimal_buckets, tokio_metrics_collector::TokioTaskMetricsCollector, MetricsRegistry,
 };
 use prometheus::{GaugeVec, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec};
-use quinn::Connection;
 use tokio_metrics::TaskMonitor;
 
+use crate::stats::ConnectionStats;
+
 const CONNECTION_RESULT_LABEL: &str = "status";
 const PEER_ID_LABEL: &str = "peer";
 const REQUEST_TASK_MONITOR_NAME: &str = "quic_transport_request_handler";
@@ -13,6 +13,10 @@ const STREAM_TYPE_LABEL: &str = "stream";
 const HANDLER_LABEL: &str = "handler";
 const ERROR_TYPE_LABEL: &str = "error";
 const REQUEST_TYPE_LABEL: &str = "request";
+const DIRECTION_LABEL: &str = "direction";
+const TRAFFIC_CLASS_LABEL: &str = "class";
+const DIRECTION_INBOUND: &str = "inbound";
+const DIRECTION_OUTBOUND: &str = "outbound";
 pub(crate) const CONNECTION_RESULT_SUCCESS_LABEL: &str = "success";
 pub(crate) const CONNECTION_RESULT_FAILED_LABEL: &str = "failed";
 pub(crate) const ERROR_TYPE_ACCEPT: &str = "accept";
@@ -51,11 +55,18 @@ pub struct QuicTransportMetrics {
     pub connection_handle_bytes_sent_total: IntCounterVec,
     pub connection_handle_duration_seconds: HistogramVec,
     pub connection_handle_errors_total: IntCounterVec,
+    // Stream scheduler
+    pub stream_scheduler_bytes_total: IntCounterVec,
+    pub stream_scheduler_admission_delay_seconds: HistogramVec,
+    pub stream_scheduler_forced_admissions_total: IntCounterVec,
     // Quinn
     quinn_path_rtt_seconds: GaugeVec,
     quinn_path_congestion_window: IntGaugeVec,
+    quinn_path_congestion_events: IntGaugeVec,
     quinn_path_sent_packets: IntGaugeVec,
     quinn_path_lost_packets: IntGaugeVec,
+    quinn_path_lost_bytes: IntGaugeVec,
+    open_streams: IntGaugeVec,
 }
 
 impl QuicTransportMetrics {
@@ -156,6 +167,24 @@ impl QuicTransportMetrics {
                 &[REQUEST_TYPE_LABEL, ERROR_TYPE_LABEL],
             ),
 
+            // Stream scheduler
+            stream_scheduler_bytes_total: metrics_registry.int_counter_vec(
+                "quic_transport_stream_scheduler_bytes_total",
+                "Bytes written through the stream scheduler by traffic class.",
+                &[TRAFFIC_CLASS_LABEL],
+            ),
+            stream_scheduler_admission_delay_seconds: metrics_registry.histogram_vec(
+                "quic_transport_stream_scheduler_admission_delay_seconds",
+                "Time a chunk waited for admission by the stream scheduler by traffic class.",
+                decimal_buckets(-5, -1),
+                &[TRAFFIC_CLASS_LABEL],
+            ),
+            stream_scheduler_forced_admissions_total: metrics_registry.int_counter_vec(
+                "quic_transport_stream_scheduler_forced_admissions_total",
+                "Chunks admitted because they waited longer than the maximum admission delay.",
+                &[TRAFFIC_CLASS_LABEL],
+            ),
+
             // Quinn stats
             quinn_path_rtt_seconds: metrics_registry.gauge_vec(
                 "quic_transport_quinn_path_rtt_seconds",
@@ -167,6 +196,11 @@ impl QuicTransportMetrics {
                 "Congestion window of this connection.",
                 &[PEER_ID_LABEL],
             ),
+            quinn_path_congestion_events: metrics_registry.int_gauge_vec(
+                "quic_transport_quinn_path_congestion_events",
+                "Congestion events on this connection.",
+                &[PEER_ID_LABEL],
+            ),
             quinn_path_sent_packets: metrics_registry.int_gauge_vec(
                 "quic_transport_quinn_path_sent_packets",
                 "The amount of packets sent on this path.",
@@ -177,27 +211,53 @@ impl QuicTransportMetrics {
                 "The amount of packets lost on this path.",
                 &[PEER_ID_LABEL],
             ),
+            quinn_path_lost_bytes: metrics_registry.int_gauge_vec(
+                "quic_transport_quinn_path_lost_bytes",
+                "The amount of bytes lost on this path.",
+                &[PEER_ID_LABEL],
+            ),
+            open_streams: metrics_registry.int_gauge_vec(
+                "quic_transport_open_streams",
+                "Number of streams with an inflight request by direction.",
+                &[PEER_ID_LABEL, DIRECTION_LABEL],
+            ),
         }
     }
 
-    pub(crate) fn collect_quic_connection_stats(&self, conn: &Connection, peer_id: &NodeId) {
-        let path_stats = conn.stats().path;
-        let peer_id_label: [&str; 1] = [&peer_id.to_string()];
+    pub(crate) fn collect_quic_connection_stats(&self, stats: &ConnectionStats) {
+        let peer_id = stats.peer_id.to_string();
+        let peer_id_label: [&str; 1] = [&peer_id];
 
         self.quinn_path_rtt_seconds
             .with_label_values(&peer_id_label)
-            .set(path_stats.rtt.as_secs_f64());
+            .set(stats.rtt_seconds);
 
         self.quinn_path_congestion_window
             .with_label_values(&peer_id_label)
-            .set(path_stats.cwnd as i64);
+            .set(stats.congestion_window as i64);
+
+        self.quinn_path_congestion_events
+            .with_label_values(&peer_id_label)
+            .set(stats.congestion_events as i64);
 
         self.quinn_path_sent_packets
             .with_label_values(&peer_id_label)
-            .set(path_stats.sent_packets as i64);
+            .set(stats.sent_packets as i64);
 
         self.quinn_path_lost_packets
             .with_label_values(&peer_id_label)
-            .set(path_stats.lost_packets as i64);
+            .set(stats.lost_packets as i64);
+
+        self.quinn_path_lost_bytes
+            .with_label_values(&peer_id_label)
+            .set(stats.lost_bytes as i64);
+
+        self.open_streams
+            .with_label_values(&[&peer_id, DIRECTION_INBOUND])
+            .set(stats.open_inbound_streams as i64);
+
+        self.open_streams
+            .with_label_values(&[&peer_id, DIRECTION_OUTBOUND])
+            .set(stats.open_outbound_streams as i64);
     }
 }
@@ -6,11 +6,11 @@
 //!     - Adds metadata to the request based on the underlying connection.
 //!       E.g. adds the NodeId of the peer as an extension.
 //!     - Calls the router.
-//!     - Writes the response to the wire.
+//!     - Writes the response to the wire through the stream scheduler of the connection.
 //!
 //! Please note that the connection manager is responsible for closing connections.
 //!
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use axum::Router;
 use ic_base_types::NodeId;
@@ -23,6 +23,8 @@ use crate::{
         QuicTransportMetrics, ERROR_TYPE_ACCEPT, ERROR_TYPE_APP, ERROR_TYPE_FINISH,
         ERROR_TYPE_READ, ERROR_TYPE_WRITE, STREAM_TYPE_BIDI, STREAM_TYPE_UNI,
     },
+    scheduler::StreamScheduler,
+    stats::{ConnectionStats, OpenStreams},
     utils::{read_request, write_response},
     ConnId,
 };
@@ -35,6 +37,8 @@ pub(crate) async fn run_stream_acceptor(
     conn_id: ConnId,
     connection: Connection,
     metrics: QuicTransportMetrics,
+    scheduler: Arc<StreamScheduler>,
+    open_streams: Arc<OpenStreams>,
     router: Router,
 ) {
     let mut inflight_requests = tokio::task::JoinSet::new();
@@ -47,7 +51,12 @@ pub(crate) async fn run_stream_acceptor(
     loop {
         tokio::select! {
              _ = quic_metrics_scrape.tick() => {
-                metrics.collect_quic_connection_stats(&connection, &peer_id);
+                metrics.collect_quic_connection_stats(&ConnectionStats::new(
+                    peer_id,
+                    conn_id,
+                    &connection,
+                    &open_streams,
+                ));
             }
             uni = connection.accept_uni() => {
                 match uni {
@@ -59,6 +68,7 @@ pub(crate) async fn run_stream_acceptor(
                                     peer_id,
                                     conn_id,
                                     metrics.clone(),
+                                    open_streams.clone(),
                                     router.clone(),
                                     uni_rx,
                                 )
@@ -88,6 +98,8 @@ pub(crate) async fn run_stream_acceptor(
                                     peer_id,
                                     conn_id,
                                     metrics.clone(),
+                                    scheduler.clone(),
+                                    open_streams.clone(),
                                     router.clone(),
                                     bi_tx,
                                     bi_rx
@@ -129,10 +141,13 @@ async fn handle_bi_stream(
     peer_id: NodeId,
     conn_id: ConnId,
     metrics: QuicTransportMetrics,
+    scheduler: Arc<StreamScheduler>,
+    open_streams: Arc<OpenStreams>,
     router: Router,
     mut bi_tx: SendStream,
     bi_rx: RecvStream,
 ) {
+    let _open_stream = open_streams.inbound();
     let mut request = match read_request(bi_rx).await {
         Ok(request) => request,
         Err(e) => {
@@ -148,6 +163,8 @@ async fn handle_bi_stream(
     request.extensions_mut().insert::<NodeId>(peer_id);
     request.extensions_mut().insert::<ConnId>(conn_id);
 
+    // The response belongs to the same traffic class as the request.
+    let class = scheduler.classify(request.uri().path());
     let svc = router.oneshot(request);
     let stopped = bi_tx.stopped();
     let response = tokio::select! {
@@ -168,7 +185,7 @@ async fn handle_bi_stream(
     // We can ignore the errors because if both peers follow the protocol an errors will only occur
     // if the other peer has closed the connection. In this case `accept_bi` in the peer event
     // loop will close this connection.
-    if let Err(e) = write_response(&mut bi_tx, response).await {
+    if let Err(e) = write_response(&mut bi_tx, response, &scheduler, class).await {
         info!(log, "Failed to write response to stream: {}", e);
         metrics
             .request_handle_errors_total
@@ -189,9 +206,11 @@ async fn handle_uni_stream(
     peer_id: NodeId,
     conn_id: ConnId,
     metrics: QuicTransportMetrics,
+    open_streams: Arc<OpenStreams>,
     router: Router,
     uni_rx: RecvStream,
 ) {
+    let _open_stream = open_streams.inbound();
     let mut request = match read_request(uni_rx).await {
         Ok(request) => request,
         Err(e) => {
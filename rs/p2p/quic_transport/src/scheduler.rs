@@ -0,0 +1,388 @@
+//! Quic Transport stream scheduler.
+//!
+//! Consensus artifacts and state sync chunks share a single quic connection per peer.
+//! A state sync can easily saturate the send buffer of a connection, which delays the
+//! delivery of consensus artifacts. To prevent this, outgoing requests and responses are
+//! split into traffic classes based on their URI and the scheduler shares the send
+//! bandwidth of a connection between the classes proportionally to their configured weights.
+//!
+//! Scheduling happens on two layers:
+//!     - Streams carrying consensus traffic get a higher quinn stream priority. Data that is
+//!       already buffered by quinn is therefore put on the wire first.
+//!     - Data is handed to quinn in chunks. Before a chunk is written the stream must be
+//!       admitted by the scheduler. Each class keeps a virtual time that is advanced by
+//!       the chunk size divided by the weight of the class. A class is only admitted if its
+//!       virtual time is not ahead of any other class that is waiting for admission.
+//!       This bounds the amount of state sync data buffered in front of consensus data.
+//!
+//! A stream that is blocked by the flow control of the peer is not waiting for admission
+//! and therefore does not hold back the other classes. As a last resort a chunk that was
+//! not admitted within `MAX_ADMISSION_DELAY` is admitted anyway.
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use quinn::SendStream;
+use tokio::sync::Notify;
+
+use crate::metrics::QuicTransportMetrics;
+
+/// Size of the chunks in which data is handed to quinn.
+const WRITE_CHUNK_SIZE: usize = 64 * 1024;
+/// Upper bound on how long a chunk waits for admission.
+const MAX_ADMISSION_DELAY: Duration = Duration::from_millis(20);
+/// Scale of the virtual time to keep precision when dividing by the weight.
+const VIRTUAL_TIME_SCALE: u128 = 1 << 16;
+
+/// Configures how the send bandwidth of a connection is shared between
+/// consensus and state sync traffic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamSchedulingConfig {
+    /// Share of the bandwidth given to consensus traffic while both classes have data to send.
+    pub consensus_weight: u32,
+    /// Share of the bandwidth given to state sync traffic while both classes have data to send.
+    pub state_sync_weight: u32,
+    /// Requests with an URI path starting with this prefix are state sync traffic.
+    /// All other requests are consensus traffic.
+    pub state_sync_path_prefix: String,
+}
+
+impl Default for StreamSchedulingConfig {
+    fn default() -> Self {
+        Self {
+            consensus_weight: 4,
+            state_sync_weight: 1,
+            state_sync_path_prefix: "/state-sync/".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TrafficClass {
+    Consensus,
+    StateSync,
+}
+
+impl TrafficClass {
+    const ALL: [TrafficClass; 2] = [TrafficClass::Consensus, TrafficClass::StateSync];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            TrafficClass::Consensus => "consensus",
+            TrafficClass::StateSync => "state_sync",
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            TrafficClass::Consensus => 0,
+            TrafficClass::StateSync => 1,
+        }
+    }
+
+    /// Quinn sends data of streams with a higher priority first.
+    fn stream_priority(&self) -> i32 {
+        match self {
+            TrafficClass::Consensus => 1,
+            TrafficClass::StateSync => 0,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ClassState {
+    /// Number of streams of this class that are currently writing.
+    writing_streams: usize,
+    /// Number of streams of this class that have a chunk ready and wait for admission.
+    waiting_streams: usize,
+    virtual_time: u128,
+}
+
+/// Admission state of the scheduler. Kept free of any async code so that the
+/// scheduling decisions can be tested in isolation.
+#[derive(Debug)]
+struct SchedulerState {
+    weights: [u128; 2],
+    classes: [ClassState; 2],
+}
+
+impl SchedulerState {
+    fn new(config: &StreamSchedulingConfig) -> Self {
+        Self {
+            weights: [
+                config.consensus_weight.max(1) as u128,
+                config.state_sync_weight.max(1) as u128,
+            ],
+            classes: Default::default(),
+        }
+    }
+
+    /// Smallest virtual time of the other classes that match `filter`.
+    fn min_other_virtual_time(
+        &self,
+        class: TrafficClass,
+        filter: impl Fn(&ClassState) -> bool,
+    ) -> Option<u128> {
+        TrafficClass::ALL
+            .iter()
+            .filter(|other| **other != class)
+            .map(|other| &self.classes[other.index()])
+            .filter(|state| filter(state))
+            .map(|state| state.virtual_time)
+            .min()
+    }
+
+    fn start_writing(&mut self, class: TrafficClass) {
+        let min_other = self.min_other_virtual_time(class, |state| state.writing_streams > 0);
+        let state = &mut self.classes[class.index()];
+        // A class that was idle must not accumulate credit while it had nothing to send.
+        if state.writing_streams == 0 {
+            if let Some(min_other) = min_other {
+                state.virtual_time = state.virtual_time.max(min_other);
+            }
+        }
+        state.writing_streams += 1;
+    }
+
+    fn finish_writing(&mut self, class: TrafficClass) {
+        self.classes[class.index()].writing_streams -= 1;
+    }
+
+    fn start_waiting(&mut self, class: TrafficClass) {
+        self.classes[class.index()].waiting_streams += 1;
+    }
+
+    fn finish_waiting(&mut self, class: TrafficClass) {
+        self.classes[class.index()].waiting_streams -= 1;
+    }
+
+    /// Only classes that wait for admission are taken into account. A stream that is
+    /// writing but blocked by the flow control of the peer makes no progress and must
+    /// not delay the other classes.
+    fn try_admit(&mut self, class: TrafficClass, len: usize) -> bool {
+        let virtual_time = self.classes[class.index()].virtual_time;
+        let min_other = self.min_other_virtual_time(class, |state| state.waiting_streams > 0);
+        if matches!(min_other, Some(min_other) if virtual_time > min_other) {
+            return false;
+        }
+        self.admit(class, len);
+        true
+    }
+
+    fn admit(&mut self, class: TrafficClass, len: usize) {
+        self.classes[class.index()].virtual_time +=
+            len as u128 * VIRTUAL_TIME_SCALE / self.weights[class.index()];
+    }
+}
+
+/// Schedules the outgoing data of all streams of a single connection.
+#[derive(Debug)]
+pub(crate) struct StreamScheduler {
+    state_sync_path_prefix: String,
+    state: Mutex<SchedulerState>,
+    admitted: Notify,
+    metrics: QuicTransportMetrics,
+}
+
+impl StreamScheduler {
+    pub(crate) fn new(config: &StreamSchedulingConfig, metrics: QuicTransportMetrics) -> Self {
+        Self {
+            state_sync_path_prefix: config.state_sync_path_prefix.clone(),
+            state: Mutex::new(SchedulerState::new(config)),
+            admitted: Notify::new(),
+            metrics,
+        }
+    }
+
+    pub(crate) fn classify(&self, path: &str) -> TrafficClass {
+        if path.starts_with(&self.state_sync_path_prefix) {
+            TrafficClass::StateSync
+        } else {
+            TrafficClass::Consensus
+        }
+    }
+
+    /// Writes `data` to `send_stream`, interleaved with the other streams of the
+    /// connection according to the weight of `class`.
+    pub(crate) async fn write_all(
+        &self,
+        send_stream: &mut SendStream,
+        class: TrafficClass,
+        data: &[u8],
+    ) -> Result<(), quinn::WriteError> {
+        // Setting the priority only fails if the stream is already closed,
+        // in which case the write below fails as well.
+        let _ = send_stream.set_priority(class.stream_priority());
+
+        self.state.lock().unwrap().start_writing(class);
+        let _writing = WritingGuard {
+            scheduler: self,
+            class,
+        };
+        for chunk in data.chunks(WRITE_CHUNK_SIZE) {
+            self.wait_for_admission(class, chunk.len()).await;
+            send_stream.write_all(chunk).await?;
+            self.metrics
+                .stream_scheduler_bytes_total
+                .with_label_values(&[class.as_str()])
+                .inc_by(chunk.len() as u64);
+        }
+        Ok(())
+    }
+
+    async fn wait_for_admission(&self, class: TrafficClass, len: usize) {
+        let _timer = self
+            .metrics
+            .stream_scheduler_admission_delay_seconds
+            .with_label_values(&[class.as_str()])
+            .start_timer();
+        self.state.lock().unwrap().start_waiting(class);
+        let _waiting = WaitingGuard {
+            scheduler: self,
+            class,
+        };
+        let deadline = Instant::now() + MAX_ADMISSION_DELAY;
+        loop {
+            // The notification is created while holding the lock, hence no admission
+            // can happen in between checking the state and waiting for the notification.
+            let admitted = {
+                let mut state = self.state.lock().unwrap();
+                if state.try_admit(class, len) {
+                    break;
+                }
+                self.admitted.notified()
+            };
+            if tokio::time::timeout_at(deadline.into(), admitted)
+                .await
+                .is_err()
+            {
+                self.state.lock().unwrap().admit(class, len);
+                self.metrics
+                    .stream_scheduler_forced_admissions_total
+                    .with_label_values(&[class.as_str()])
+                    .inc();
+                break;
+            }
+        }
+    }
+}
+
+/// Marks a class as no longer writing when a write completes or is cancelled.
+struct WritingGuard<'a> {
+    scheduler: &'a StreamScheduler,
+    class: TrafficClass,
+}
+
+impl Drop for WritingGuard<'_> {
+    fn drop(&mut self) {
+        self.scheduler
+            .state
+            .lock()
+            .unwrap()
+            .finish_writing(self.class);
+        self.scheduler.admitted.notify_waiters();
+    }
+}
+
+/// Marks a stream as no longer waiting for admission when it is admitted or the
+/// write is cancelled. Wakes up the other waiting streams since either the virtual
+/// time of the class advanced or the class no longer competes for admission.
+struct WaitingGuard<'a> {
+    scheduler: &'a StreamScheduler,
+    class: TrafficClass,
+}
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.scheduler
+            .state
+            .lock()
+            .unwrap()
+            .finish_waiting(self.class);
+        self.scheduler.admitted.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Repeatedly offers a chunk of both classes and returns the number of
+    /// admitted chunks per class.
+    fn admitted_chunks(state: &mut SchedulerState, offers: usize) -> [usize; 2] {
+        let mut admitted = [0; 2];
+        for _ in 0..offers {
+            for class in [TrafficClass::StateSync, TrafficClass::Consensus] {
+                if state.try_admit(class, WRITE_CHUNK_SIZE) {
+                    admitted[class.index()] += 1;
+                }
+            }
+        }
+        admitted
+    }
+
+    /// Marks a stream of `class` as writing and waiting for admission.
+    fn start_stream(state: &mut SchedulerState, class: TrafficClass) {
+        state.start_writing(class);
+        state.start_waiting(class);
+    }
+
+    #[test]
+    fn bandwidth_is_shared_according_to_weights() {
+        let mut state = SchedulerState::new(&StreamSchedulingConfig::default());
+        start_stream(&mut state, TrafficClass::Consensus);
+        start_stream(&mut state, TrafficClass::StateSync);
+
+        let [consensus, state_sync] = admitted_chunks(&mut state, 400);
+        assert_eq!(consensus, 400);
+        assert_eq!(state_sync, 100);
+    }
+
+    #[test]
+    fn single_class_is_never_throttled() {
+        let mut state = SchedulerState::new(&StreamSchedulingConfig::default());
+        start_stream(&mut state, TrafficClass::StateSync);
+        for _ in 0..100 {
+            assert!(state.try_admit(TrafficClass::StateSync, WRITE_CHUNK_SIZE));
+        }
+    }
+
+    #[test]
+    fn idle_class_does_not_accumulate_credit() {
+        let mut state = SchedulerState::new(&StreamSchedulingConfig {
+            consensus_weight: 1,
+            state_sync_weight: 1,
+            ..Default::default()
+        });
+        start_stream(&mut state, TrafficClass::StateSync);
+        for _ in 0..100 {
+            assert!(state.try_admit(TrafficClass::StateSync, WRITE_CHUNK_SIZE));
+        }
+
+        // Consensus starts writing after state sync has sent a lot of data. It
+        // must not be able to send a burst of the same size on its own.
+        start_stream(&mut state, TrafficClass::Consensus);
+        let [consensus, state_sync] = admitted_chunks(&mut state, 10);
+        assert_eq!(consensus, 10);
+        assert_eq!(state_sync, 10);
+
+        state.finish_waiting(TrafficClass::StateSync);
+        state.finish_writing(TrafficClass::StateSync);
+        assert!(state.try_admit(TrafficClass::Consensus, WRITE_CHUNK_SIZE));
+    }
+
+    #[test]
+    fn stream_blocked_by_flow_control_does_not_throttle_other_class() {
+        let mut state = SchedulerState::new(&StreamSchedulingConfig::default());
+        start_stream(&mut state, TrafficClass::Consensus);
+        start_stream(&mut state, TrafficClass::StateSync);
+        assert!(state.try_admit(TrafficClass::Consensus, WRITE_CHUNK_SIZE));
+
+        // The consensus stream hands its chunk to quinn and is blocked by the flow
+        // control of the peer. It is still writing but no longer waiting for admission.
+        state.finish_waiting(TrafficClass::Consensus);
+        let [_, state_sync] = admitted_chunks(&mut state, 100);
+        assert_eq!(state_sync, 100);
+    }
+}
@@ -0,0 +1,141 @@
+//! Quic Transport connection statistics.
+//!
+//! Combines the statistics quinn keeps for a connection with the number of streams
+//! that transport currently has open on it. The statistics are exported as metrics
+//! by the request handler and can be inspected through `QuicTransport::connection_stats`
+//! and the debug router.
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use axum::{extract::State, routing::get, Json, Router};
+use ic_base_types::NodeId;
+use quinn::Connection;
+use serde::Serialize;
+
+use crate::{ConnId, QuicTransport};
+
+/// Path under which the debug router serves the connection statistics.
+pub const CONNECTION_STATS_PATH: &str = "/_/quic/connections";
+
+/// Snapshot of the state of a connection to a peer.
+#[derive(Clone, Debug, Serialize)]
+pub struct ConnectionStats {
+    pub peer_id: NodeId,
+    pub conn_id: u64,
+    pub remote_address: SocketAddr,
+    /// Current best estimate of the round trip time.
+    pub rtt_seconds: f64,
+    /// Current congestion window in bytes.
+    pub congestion_window: u64,
+    /// Number of times the congestion window was reduced.
+    pub congestion_events: u64,
+    pub sent_packets: u64,
+    pub lost_packets: u64,
+    pub lost_bytes: u64,
+    pub udp_sent_bytes: u64,
+    pub udp_received_bytes: u64,
+    /// Streams opened by this node for requests that are still in flight.
+    pub open_outbound_streams: usize,
+    /// Streams opened by the peer whose request is still being handled.
+    pub open_inbound_streams: usize,
+}
+
+impl ConnectionStats {
+    pub(crate) fn new(
+        peer_id: NodeId,
+        conn_id: ConnId,
+        connection: &Connection,
+        open_streams: &OpenStreams,
+    ) -> Self {
+        let stats = connection.stats();
+        Self {
+            peer_id,
+            conn_id: conn_id.get(),
+            remote_address: connection.remote_address(),
+            rtt_seconds: stats.path.rtt.as_secs_f64(),
+            congestion_window: stats.path.cwnd,
+            congestion_events: stats.path.congestion_events,
+            sent_packets: stats.path.sent_packets,
+            lost_packets: stats.path.lost_packets,
+            lost_bytes: stats.path.lost_bytes,
+            udp_sent_bytes: stats.udp_tx.bytes,
+            udp_received_bytes: stats.udp_rx.bytes,
+            open_outbound_streams: open_streams.outbound.load(Ordering::Relaxed),
+            open_inbound_streams: open_streams.inbound.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Number of streams transport has open on a connection.
+#[derive(Debug, Default)]
+pub(crate) struct OpenStreams {
+    outbound: AtomicUsize,
+    inbound: AtomicUsize,
+}
+
+impl OpenStreams {
+    /// Counts an outbound stream as open until the returned guard is dropped.
+    pub(crate) fn outbound(self: &Arc<Self>) -> OpenStreamGuard {
+        OpenStreamGuard::new(self.clone(), StreamDirection::Outbound)
+    }
+
+    /// Counts an inbound stream as open until the returned guard is dropped.
+    pub(crate) fn inbound(self: &Arc<Self>) -> OpenStreamGuard {
+        OpenStreamGuard::new(self.clone(), StreamDirection::Inbound)
+    }
+
+    fn counter(&self, direction: StreamDirection) -> &AtomicUsize {
+        match direction {
+            StreamDirection::Outbound => &self.outbound,
+            StreamDirection::Inbound => &self.inbound,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum StreamDirection {
+    Outbound,
+    Inbound,
+}
+
+pub(crate) struct OpenStreamGuard {
+    streams: Arc<OpenStreams>,
+    direction: StreamDirection,
+}
+
+impl OpenStreamGuard {
+    fn new(streams: Arc<OpenStreams>, direction: StreamDirection) -> Self {
+        streams.counter(direction).fetch_add(1, Ordering::Relaxed);
+        Self { streams, direction }
+    }
+}
+
+impl Drop for OpenStreamGuard {
+    fn drop(&mut self) {
+        self.streams
+            .counter(self.direction)
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Builds a router that serves the statistics of all active connections as json.
+///
+/// The router is meant to be served on an operator facing debug endpoint and must
+/// not be merged into the router passed to `QuicTransport::start`, which is
+/// reachable by peers.
+pub(crate) fn debug_router(transport: QuicTransport) -> Router {
+    Router::new()
+        .route(CONNECTION_STATS_PATH, get(connection_stats_handler))
+        .with_state(transport)
+}
+
+async fn connection_stats_handler(
+    State(transport): State<QuicTransport>,
+) -> Json<Vec<ConnectionStats>> {
+    Json(transport.connection_stats())
+}
@@ -23,7 +23,10 @@ use http::{Request, Response, StatusCode, Uri};
 use quinn::{RecvStream, SendStream};
 use serde::{Deserialize, Serialize};
 
-use crate::metrics::QuicTransportMetrics;
+use crate::{
+    metrics::QuicTransportMetrics,
+    scheduler::{StreamScheduler, TrafficClass},
+};
 
 #[derive(Debug)]
 pub(crate) enum RecvError {
@@ -98,7 +101,9 @@ pub(crate) async fn read_response(mut recv_stream: RecvStream) -> Result<Respons
 pub(crate) async fn write_request(
     send_stream: &mut SendStream,
     request: Request<Bytes>,
+    scheduler: &StreamScheduler,
 ) -> Result<(), String> {
+    let class = scheduler.classify(request.uri().path());
     let (parts, body) = request.into_parts();
 
     let msg = WireRequest {
@@ -109,8 +114,8 @@ pub(crate) async fn write_request(
     let res = bincode_config()
         .serialize(&msg)
         .map_err(|err| err.to_string())?;
-    send_stream
-        .write_all(&res)
+    scheduler
+        .write_all(send_stream, class, &res)
         .await
         .map_err(|err| err.to_string())
 }
@@ -118,6 +123,8 @@ pub(crate) async fn write_request(
 pub(crate) async fn write_response(
     send_stream: &mut SendStream,
     response: Response<BoxBody>,
+    scheduler: &StreamScheduler,
+    class: TrafficClass,
 ) -> Result<(), RecvError> {
     let (parts, body) = response.into_parts();
     // Check for axum error in body
@@ -137,8 +144,8 @@ pub(crate) async fn write_response(
         .map_err(|err| RecvError::SendResponseFailed {
             reason: err.to_string(),
         })?;
-    send_stream
-        .write_all(&res)
+    scheduler
+        .write_all(send_stream, class, &res)
         .await
         .map_err(|err| RecvError::SendResponseFailed {
             reason: err.to_string(),
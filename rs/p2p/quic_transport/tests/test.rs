@@ -17,7 +17,7 @@ use ic_p2p_test_utils::{
     },
     ConnectivityChecker,
 };
-use ic_quic_transport::{DummyUdpSocket, QuicTransport, Transport};
+use ic_quic_transport::{DummyUdpSocket, QuicTransport, StreamSchedulingConfig, Transport};
 use ic_test_utilities_logger::with_test_replica_logger;
 use ic_types_test_utils::ids::{NODE_1, NODE_2, NODE_3, NODE_4, NODE_5, SUBNET_1};
 use tokio::sync::Notify;
@@ -126,6 +126,7 @@ fn test_real_socket() {
             topology_watcher.clone(),
             Either::Left::<_, DummyUdpSocket>(socket_1),
             ConnectivityChecker::router(),
+            StreamSchedulingConfig::default(),
         ));
 
         let transport_2 = Arc::new(QuicTransport::start(
@@ -139,6 +140,7 @@ fn test_real_socket() {
             topology_watcher,
             Either::Left::<_, DummyUdpSocket>(socket_2),
             ConnectivityChecker::router(),
+            StreamSchedulingConfig::default(),
         ));
 
         registry_handler.add_node(
@@ -166,6 +168,17 @@ fn test_real_socket() {
                     break;
                 }
             }
+
+            // Both sides report the connection to the other peer.
+            let stats_1 = transport_1.connection_stats();
+            assert_eq!(stats_1.len(), 1);
+            assert_eq!(stats_1[0].peer_id, NODE_2);
+            assert_eq!(stats_1[0].remote_address, socket_2);
+            assert!(stats_1[0].sent_packets > 0);
+            assert_eq!(stats_1[0].open_outbound_streams, 0);
+            let stats_2 = transport_2.connection_stats();
+            assert_eq!(stats_2.len(), 1);
+            assert_eq!(stats_2[0].peer_id, NODE_1);
         });
     })
 }
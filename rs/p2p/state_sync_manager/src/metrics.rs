@@ -10,6 +10,7 @@ use crate::ongoing::DownloadChunkError;
 const CHUNK_DOWNLOAD_STATUS_LABEL: &str = "status";
 const CHUNK_DOWNLOAD_STATUS_MORE_NEEDED: &str = "more_needed";
 const CHUNK_DOWNLOAD_STATUS_SUCCESS: &str = "success";
+const CHUNK_COMPRESSION_LABEL: &str = "compression";
 
 #[derive(Debug, Clone)]
 pub(crate) struct StateSyncManagerMetrics {
@@ -46,6 +47,8 @@ impl StateSyncManagerMetrics {
 #[derive(Debug, Clone)]
 pub struct StateSyncManagerHandlerMetrics {
     pub compression_ratio: Histogram,
+    pub chunks_served_total: IntCounterVec,
+    pub compression_bytes_saved_total: IntCounter,
 }
 
 impl StateSyncManagerHandlerMetrics {
@@ -56,6 +59,15 @@ impl StateSyncManagerHandlerMetrics {
                 "State sync manager chunk compression ratio.",
                 vec![1.0, 1.25, 1.5, 2.0, 3.0, 5.0, 10.0],
             ),
+            chunks_served_total: metrics_registry.int_counter_vec(
+                "state_sync_manager_chunks_served_total",
+                "Number of chunks served to peers, by compression used.",
+                &[CHUNK_COMPRESSION_LABEL],
+            ),
+            compression_bytes_saved_total: metrics_registry.int_counter(
+                "state_sync_manager_chunk_compression_bytes_saved_total",
+                "Number of bytes saved by compressing served chunks.",
+            ),
         }
     }
 }
@@ -65,6 +77,7 @@ pub(crate) struct OngoingStateSyncMetrics {
     pub allowed_parallel_downloads: IntGauge,
     pub chunk_size_compressed_total: IntCounter,
     pub chunk_size_decompressed_total: IntCounter,
+    pub chunk_bytes_saved_total: IntCounter,
     pub chunks_to_download_calls_total: IntCounter,
     pub chunks_to_download_total: IntCounter,
     pub peers_serving_state: IntGauge,
@@ -92,6 +105,10 @@ impl OngoingStateSyncMetrics {
                 "state_sync_manager_chunk_size_decompressed_total",
                 "Sum of all chunks received after decompresssion.",
             ),
+            chunk_bytes_saved_total: metrics_registry.int_counter(
+                "state_sync_manager_chunk_bytes_saved_total",
+                "Number of bytes not transferred thanks to chunk compression.",
+            ),
             chunks_to_download_calls_total: metrics_registry.int_counter(
                 "state_sync_manager_chunks_to_download_calls_total",
                 "Number of times manager asked state sync for list of chunks to download.",
@@ -22,6 +22,15 @@ pub const STATE_SYNC_CHUNK_PATH: &str = "/state-sync/chunk";
 /// State sync uses 1Mb chunks. To be safe we use 8Mib here same as transport.
 const MAX_CHUNK_SIZE: usize = 8 * 1024 * 1024;
 
+/// Magic number at the start of every zstd frame. Responses from peers that do not
+/// support per-chunk compression are entirely zstd-compressed and start with it, while
+/// an encoded `StateSyncChunkResponse` never does (it starts with a field key of field 1 or 2).
+const ZSTD_MAGIC_NUMBER: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compressions this node can decode on a per-chunk basis.
+const ACCEPTED_COMPRESSIONS: [pb::ChunkCompression; 2] =
+    [pb::ChunkCompression::None, pb::ChunkCompression::Zstd];
+
 pub(crate) struct StateSyncChunkHandler {
     _log: ReplicaLogger,
     state_sync: Arc<dyn StateSyncClient>,
@@ -47,27 +56,28 @@ pub(crate) async fn state_sync_chunk_handler(
     payload: Bytes,
 ) -> Result<Bytes, StatusCode> {
     // Parse payload
-    let pb::StateSyncChunkRequest { id, chunk_id } =
-        pb::StateSyncChunkRequest::decode(payload).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let pb::StateSyncChunkRequest {
+        id,
+        chunk_id,
+        accepted_compressions,
+    } = pb::StateSyncChunkRequest::decode(payload).map_err(|_| StatusCode::BAD_REQUEST)?;
     let artifact_id: StateSyncArtifactId = id.map(From::from).ok_or(StatusCode::BAD_REQUEST)?;
     let chunk_id = ChunkId::from(chunk_id);
 
     let jh =
         tokio::task::spawn_blocking(
             move || match state.state_sync.chunk(&artifact_id, chunk_id) {
+                Some(data) if accepted_compressions.is_empty() => {
+                    Ok(encode_legacy_chunk_response(data.into(), &state.metrics))
+                }
                 Some(data) => {
-                    let pb_chunk = pb::StateSyncChunkResponse { data: data.into() };
-                    let mut raw = BytesMut::with_capacity(pb_chunk.encoded_len());
-                    pb_chunk.encode(&mut raw).expect("Allocated enough memory");
-                    let raw = raw.freeze();
-
-                    let compressed = zstd::bulk::compress(&raw, zstd::DEFAULT_COMPRESSION_LEVEL)
-                        .expect("Compression failed");
-                    state
-                        .metrics
-                        .compression_ratio
-                        .observe(raw.len() as f64 / compressed.len() as f64);
-                    Ok(compressed)
+                    let accepts_zstd =
+                        accepted_compressions.contains(&(pb::ChunkCompression::Zstd as i32));
+                    Ok(encode_chunk_response(
+                        data.into(),
+                        accepts_zstd,
+                        &state.metrics,
+                    ))
                 }
                 None => Err(StatusCode::NO_CONTENT),
             },
@@ -77,6 +87,75 @@ pub(crate) async fn state_sync_chunk_handler(
     Ok(data.into())
 }
 
+/// Encodes the chunk for peers that do not negotiate per-chunk compression by
+/// zstd-compressing the whole encoded response.
+fn encode_legacy_chunk_response(
+    data: Vec<u8>,
+    metrics: &StateSyncManagerHandlerMetrics,
+) -> Vec<u8> {
+    let pb_chunk = pb::StateSyncChunkResponse {
+        data,
+        compression: pb::ChunkCompression::Unspecified as i32,
+    };
+    let mut raw = BytesMut::with_capacity(pb_chunk.encoded_len());
+    pb_chunk.encode(&mut raw).expect("Allocated enough memory");
+
+    let compressed =
+        zstd::bulk::compress(&raw, zstd::DEFAULT_COMPRESSION_LEVEL).expect("Compression failed");
+    metrics
+        .compression_ratio
+        .observe(raw.len() as f64 / compressed.len() as f64);
+    metrics
+        .chunks_served_total
+        .with_label_values(&[pb::ChunkCompression::Unspecified.as_str_name()])
+        .inc();
+    metrics
+        .compression_bytes_saved_total
+        .inc_by(raw.len().saturating_sub(compressed.len()) as u64);
+    compressed
+}
+
+/// Encodes the chunk, compressing its data only if the requester accepts zstd and
+/// compression actually makes it smaller. Already compressed or random data (e.g. Wasm
+/// modules) is sent as is, which saves CPU time on both sides.
+fn encode_chunk_response(
+    data: Vec<u8>,
+    accepts_zstd: bool,
+    metrics: &StateSyncManagerHandlerMetrics,
+) -> Vec<u8> {
+    let raw_len = data.len();
+    let compressed = if accepts_zstd {
+        zstd::bulk::compress(&data, zstd::DEFAULT_COMPRESSION_LEVEL).ok()
+    } else {
+        None
+    };
+    let (data, compression) = match compressed {
+        Some(compressed) if compressed.len() < raw_len => (compressed, pb::ChunkCompression::Zstd),
+        _ => (data, pb::ChunkCompression::None),
+    };
+
+    if raw_len > 0 {
+        metrics
+            .compression_ratio
+            .observe(raw_len as f64 / data.len() as f64);
+    }
+    metrics
+        .chunks_served_total
+        .with_label_values(&[compression.as_str_name()])
+        .inc();
+    metrics
+        .compression_bytes_saved_total
+        .inc_by((raw_len - data.len()) as u64);
+
+    let pb_chunk = pb::StateSyncChunkResponse {
+        data,
+        compression: compression as i32,
+    };
+    let mut raw = Vec::with_capacity(pb_chunk.encoded_len());
+    pb_chunk.encode(&mut raw).expect("Allocated enough memory");
+    raw
+}
+
 pub(crate) fn build_chunk_handler_request(
     artifact_id: StateSyncArtifactId,
     chunk_id: ChunkId,
@@ -84,6 +163,7 @@ pub(crate) fn build_chunk_handler_request(
     let pb = pb::StateSyncChunkRequest {
         id: Some(artifact_id.into()),
         chunk_id: chunk_id.get(),
+        accepted_compressions: ACCEPTED_COMPRESSIONS.iter().map(|c| *c as i32).collect(),
     };
 
     let mut raw = BytesMut::with_capacity(pb.encoded_len());
@@ -105,32 +185,47 @@ pub(crate) fn parse_chunk_handler_response(
 
     match parts.status {
         StatusCode::OK => {
+            let to_request_error = |err: String| DownloadChunkError::RequestError { chunk_id, err };
             metrics
                 .chunk_size_compressed_total
                 .inc_by(body.len() as u64);
-            let decompressed = zstd::bulk::decompress(&body, MAX_CHUNK_SIZE).map_err(|e| {
-                DownloadChunkError::RequestError {
-                    chunk_id,
-                    err: e.to_string(),
+
+            let data = if body.starts_with(&ZSTD_MAGIC_NUMBER) {
+                // The peer does not support per-chunk compression.
+                let decompressed = zstd::bulk::decompress(&body, MAX_CHUNK_SIZE)
+                    .map_err(|e| to_request_error(e.to_string()))?;
+                pb::StateSyncChunkResponse::decode(Bytes::from(decompressed))
+                    .map_err(|e| to_request_error(e.to_string()))?
+                    .data
+            } else {
+                let pb = pb::StateSyncChunkResponse::decode(body.clone())
+                    .map_err(|e| to_request_error(e.to_string()))?;
+                match pb::ChunkCompression::try_from(pb.compression) {
+                    Ok(pb::ChunkCompression::Zstd) => {
+                        zstd::bulk::decompress(&pb.data, MAX_CHUNK_SIZE)
+                            .map_err(|e| to_request_error(e.to_string()))?
+                    }
+                    Ok(pb::ChunkCompression::None) => pb.data,
+                    Ok(pb::ChunkCompression::Unspecified) | Err(_) => {
+                        return Err(to_request_error(format!(
+                            "Unsupported chunk compression {}",
+                            pb.compression
+                        )))
+                    }
                 }
-            })?;
+            };
 
             metrics
                 .chunk_size_decompressed_total
-                .inc_by(decompressed.len() as u64);
-
-            let pb =
-                pb::StateSyncChunkResponse::decode(Bytes::from(decompressed)).map_err(|e| {
-                    DownloadChunkError::RequestError {
-                        chunk_id,
-                        err: e.to_string(),
-                    }
-                })?;
+                .inc_by(data.len() as u64);
+            metrics
+                .chunk_bytes_saved_total
+                .inc_by((data.len() as u64).saturating_sub(body.len() as u64));
 
             let chunk = ArtifactChunk {
                 chunk_id,
                 artifact_chunk_data:
-                    ic_types::chunkable::ArtifactChunkData::SemiStructuredChunkData(pb.data),
+                    ic_types::chunkable::ArtifactChunkData::SemiStructuredChunkData(data),
             };
             Ok(chunk)
         }
@@ -143,3 +238,96 @@ pub(crate) fn parse_chunk_handler_response(
         }),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_metrics::MetricsRegistry;
+    use rand::{Rng, SeedableRng};
+
+    fn ok_response(body: Vec<u8>) -> Response<Bytes> {
+        Response::builder()
+            .status(StatusCode::OK)
+            .body(Bytes::from(body))
+            .unwrap()
+    }
+
+    fn parse(body: Vec<u8>, metrics: OngoingStateSyncMetrics) -> Vec<u8> {
+        let chunk = parse_chunk_handler_response(ok_response(body), ChunkId::from(1), metrics)
+            .expect("failed to parse chunk response");
+        let ic_types::chunkable::ArtifactChunkData::SemiStructuredChunkData(data) =
+            chunk.artifact_chunk_data;
+        data
+    }
+
+    /// Compressible chunks are zstd-compressed and the saved bytes are recorded on both sides.
+    #[test]
+    fn compressible_chunk_is_compressed() {
+        let handler_metrics = StateSyncManagerHandlerMetrics::new(&MetricsRegistry::default());
+        let ongoing_metrics = OngoingStateSyncMetrics::new(&MetricsRegistry::default());
+        let data = vec![0; 1024 * 1024];
+
+        let body = encode_chunk_response(data.clone(), true, &handler_metrics);
+        assert!(body.len() < data.len());
+        assert_eq!(
+            handler_metrics
+                .chunks_served_total
+                .with_label_values(&[pb::ChunkCompression::Zstd.as_str_name()])
+                .get(),
+            1
+        );
+        assert_eq!(
+            handler_metrics.compression_bytes_saved_total.get() as usize,
+            data.len()
+                - pb::StateSyncChunkResponse::decode(&body[..])
+                    .unwrap()
+                    .data
+                    .len()
+        );
+
+        assert_eq!(parse(body.clone(), ongoing_metrics.clone()), data);
+        assert_eq!(
+            ongoing_metrics.chunk_bytes_saved_total.get() as usize,
+            data.len() - body.len()
+        );
+    }
+
+    /// Incompressible chunks and chunks for peers that do not accept zstd are sent as is.
+    #[test]
+    fn incompressible_chunk_is_sent_uncompressed() {
+        let handler_metrics = StateSyncManagerHandlerMetrics::new(&MetricsRegistry::default());
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let random_data: Vec<u8> = (0..1024 * 1024).map(|_| rng.gen()).collect();
+
+        for (data, accepts_zstd) in [(random_data, true), (vec![0; 1024], false)] {
+            let body = encode_chunk_response(data.clone(), accepts_zstd, &handler_metrics);
+            let pb_chunk = pb::StateSyncChunkResponse::decode(&body[..]).unwrap();
+            assert_eq!(pb_chunk.compression, pb::ChunkCompression::None as i32);
+            assert_eq!(
+                parse(
+                    body,
+                    OngoingStateSyncMetrics::new(&MetricsRegistry::default())
+                ),
+                data
+            );
+        }
+        assert_eq!(handler_metrics.compression_bytes_saved_total.get(), 0);
+    }
+
+    /// Responses of peers that compress the whole response are still understood.
+    #[test]
+    fn legacy_response_is_parsed() {
+        let handler_metrics = StateSyncManagerHandlerMetrics::new(&MetricsRegistry::default());
+        let data = vec![7; 4096];
+
+        let body = encode_legacy_chunk_response(data.clone(), &handler_metrics);
+        assert!(body.starts_with(&ZSTD_MAGIC_NUMBER));
+        assert_eq!(
+            parse(
+                body,
+                OngoingStateSyncMetrics::new(&MetricsRegistry::default())
+            ),
+            data
+        );
+    }
+}
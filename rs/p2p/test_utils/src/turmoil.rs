@@ -26,7 +26,7 @@ use ic_interfaces::{
 use ic_logger::ReplicaLogger;
 use ic_metrics::MetricsRegistry;
 use ic_peer_manager::SubnetTopology;
-use ic_quic_transport::{QuicTransport, Transport};
+use ic_quic_transport::{QuicTransport, StreamSchedulingConfig, Transport};
 use ic_types::{artifact::UnvalidatedArtifactMutation, NodeId, RegistryVersion};
 use ic_types_test_utils::ids::SUBNET_1;
 use quinn::{
@@ -359,6 +359,7 @@ pub fn add_transport_to_sim<F>(
                 topology_watcher_clone.clone(),
                 Either::Right(custom_udp),
                 router.unwrap_or_default(),
+                StreamSchedulingConfig::default(),
             ));
 
             consensus_builder.run(transport.clone(), topology_watcher_clone.clone());
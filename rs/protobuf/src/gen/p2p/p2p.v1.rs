@@ -15,6 +15,10 @@ pub struct StateSyncChunkRequest {
     pub id: ::core::option::Option<StateSyncId>,
     #[prost(uint32, tag = "2")]
     pub chunk_id: u32,
+    /// Compressions the requester can decode on a per-chunk basis. If empty, the
+    /// responder zstd-compresses the whole encoded response (legacy behaviour).
+    #[prost(enumeration = "ChunkCompression", repeated, tag = "3")]
+    pub accepted_compressions: ::prost::alloc::vec::Vec<i32>,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -22,6 +26,9 @@ pub struct StateSyncChunkRequest {
 pub struct StateSyncChunkResponse {
     #[prost(bytes = "vec", tag = "1")]
     pub data: ::prost::alloc::vec::Vec<u8>,
+    /// How `data` is compressed. Only set if the request listed accepted compressions.
+    #[prost(enumeration = "ChunkCompression", tag = "2")]
+    pub compression: i32,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -55,3 +62,44 @@ pub struct Advert {
     #[prost(bytes = "vec", tag = "2")]
     pub attribute: ::prost::alloc::vec::Vec<u8>,
 }
+#[derive(
+    serde::Serialize,
+    serde::Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    ::prost::Enumeration,
+)]
+#[repr(i32)]
+pub enum ChunkCompression {
+    Unspecified = 0,
+    None = 1,
+    Zstd = 2,
+}
+impl ChunkCompression {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ChunkCompression::Unspecified => "CHUNK_COMPRESSION_UNSPECIFIED",
+            ChunkCompression::None => "CHUNK_COMPRESSION_NONE",
+            ChunkCompression::Zstd => "CHUNK_COMPRESSION_ZSTD",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "CHUNK_COMPRESSION_UNSPECIFIED" => Some(Self::Unspecified),
+            "CHUNK_COMPRESSION_NONE" => Some(Self::None),
+            "CHUNK_COMPRESSION_ZSTD" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
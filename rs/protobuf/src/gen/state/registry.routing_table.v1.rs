@@ -59,3 +59,61 @@ pub mod canister_migrations {
         pub subnet_ids: ::prost::alloc::vec::Vec<super::super::super::super::types::v1::SubnetId>,
     }
 }
+/// An in-progress or completed subnet split, keyed by the source subnet.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubnetSplittingRecord {
+    /// The subnet that the canisters in `canister_id_ranges` are split off to.
+    #[prost(message, optional, tag = "1")]
+    pub destination_subnet_id: ::core::option::Option<super::super::super::types::v1::SubnetId>,
+    /// The canister ID ranges moving from the source to the destination subnet.
+    #[prost(message, repeated, tag = "2")]
+    pub canister_id_ranges: ::prost::alloc::vec::Vec<CanisterIdRange>,
+    #[prost(enumeration = "SubnetSplittingStatus", tag = "3")]
+    pub status: i32,
+    /// The height of the CUP at which the source subnet halted. Only set from
+    /// `SUBNET_SPLITTING_STATUS_SOURCE_HALTED` on.
+    #[prost(uint64, tag = "4")]
+    pub cup_height: u64,
+}
+/// The steps of a subnet split orchestrated by the registry, in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum SubnetSplittingStatus {
+    Unspecified = 0,
+    /// The split was adopted. The source subnet halts at its next CUP height.
+    Scheduled = 1,
+    /// The source subnet halted at `cup_height` and the canister ranges were
+    /// rerouted to the destination subnet.
+    SourceHalted = 2,
+    /// Both halves derived their states from the state at `cup_height`.
+    StatesSplit = 3,
+    /// Both subnets resumed from their new CUPs.
+    Completed = 4,
+}
+impl SubnetSplittingStatus {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            SubnetSplittingStatus::Unspecified => "SUBNET_SPLITTING_STATUS_UNSPECIFIED",
+            SubnetSplittingStatus::Scheduled => "SUBNET_SPLITTING_STATUS_SCHEDULED",
+            SubnetSplittingStatus::SourceHalted => "SUBNET_SPLITTING_STATUS_SOURCE_HALTED",
+            SubnetSplittingStatus::StatesSplit => "SUBNET_SPLITTING_STATUS_STATES_SPLIT",
+            SubnetSplittingStatus::Completed => "SUBNET_SPLITTING_STATUS_COMPLETED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "SUBNET_SPLITTING_STATUS_UNSPECIFIED" => Some(Self::Unspecified),
+            "SUBNET_SPLITTING_STATUS_SCHEDULED" => Some(Self::Scheduled),
+            "SUBNET_SPLITTING_STATUS_SOURCE_HALTED" => Some(Self::SourceHalted),
+            "SUBNET_SPLITTING_STATUS_STATES_SPLIT" => Some(Self::StatesSplit),
+            "SUBNET_SPLITTING_STATUS_COMPLETED" => Some(Self::Completed),
+            _ => None,
+        }
+    }
+}
@@ -158,6 +158,7 @@ pub mod wasm_method {
         CanisterHeartbeat = 6,
         Empty = 7,
         CanisterGlobalTimer = 8,
+        CanisterOnLowWasmMemory = 9,
     }
     impl SystemMethod {
         /// String value of the enum field names used in the ProtoBuf definition.
@@ -175,6 +176,9 @@ pub mod wasm_method {
                 SystemMethod::CanisterHeartbeat => "SYSTEM_METHOD_CANISTER_HEARTBEAT",
                 SystemMethod::Empty => "SYSTEM_METHOD_EMPTY",
                 SystemMethod::CanisterGlobalTimer => "SYSTEM_METHOD_CANISTER_GLOBAL_TIMER",
+                SystemMethod::CanisterOnLowWasmMemory => {
+                    "SYSTEM_METHOD_CANISTER_ON_LOW_WASM_MEMORY"
+                }
             }
         }
         /// Creates an enum from field names used in the ProtoBuf definition.
@@ -189,6 +193,7 @@ pub mod wasm_method {
                 "SYSTEM_METHOD_CANISTER_HEARTBEAT" => Some(Self::CanisterHeartbeat),
                 "SYSTEM_METHOD_EMPTY" => Some(Self::Empty),
                 "SYSTEM_METHOD_CANISTER_GLOBAL_TIMER" => Some(Self::CanisterGlobalTimer),
+                "SYSTEM_METHOD_CANISTER_ON_LOW_WASM_MEMORY" => Some(Self::CanisterOnLowWasmMemory),
                 _ => None,
             }
         }
@@ -363,6 +368,7 @@ pub mod execution_task {
         Unspecified = 0,
         Heartbeat = 1,
         Timer = 2,
+        OnLowWasmMemory = 3,
     }
     impl CanisterTask {
         /// String value of the enum field names used in the ProtoBuf definition.
@@ -374,6 +380,7 @@ pub mod execution_task {
                 CanisterTask::Unspecified => "CANISTER_TASK_UNSPECIFIED",
                 CanisterTask::Heartbeat => "CANISTER_TASK_HEARTBEAT",
                 CanisterTask::Timer => "CANISTER_TASK_TIMER",
+                CanisterTask::OnLowWasmMemory => "CANISTER_TASK_ON_LOW_WASM_MEMORY",
             }
         }
         /// Creates an enum from field names used in the ProtoBuf definition.
@@ -382,6 +389,7 @@ pub mod execution_task {
                 "CANISTER_TASK_UNSPECIFIED" => Some(Self::Unspecified),
                 "CANISTER_TASK_HEARTBEAT" => Some(Self::Heartbeat),
                 "CANISTER_TASK_TIMER" => Some(Self::Timer),
+                "CANISTER_TASK_ON_LOW_WASM_MEMORY" => Some(Self::OnLowWasmMemory),
                 _ => None,
             }
         }
@@ -445,6 +453,30 @@ pub struct CanisterControllersChange {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterComputeAllocationChange {
+    #[prost(uint64, tag = "1")]
+    pub compute_allocation: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterMemoryAllocationChange {
+    #[prost(uint64, tag = "1")]
+    pub memory_allocation: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterFreezingThresholdChange {
+    #[prost(uint64, tag = "1")]
+    pub freezing_threshold: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CanisterReservedCyclesLimitChange {
+    #[prost(message, optional, tag = "1")]
+    pub reserved_cycles_limit: ::core::option::Option<Unsigned128>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CanisterChange {
     #[prost(uint64, tag = "1")]
     pub timestamp_nanos: u64,
@@ -452,7 +484,10 @@ pub struct CanisterChange {
     pub canister_version: u64,
     #[prost(oneof = "canister_change::ChangeOrigin", tags = "3, 4")]
     pub change_origin: ::core::option::Option<canister_change::ChangeOrigin>,
-    #[prost(oneof = "canister_change::ChangeDetails", tags = "5, 6, 7, 8")]
+    #[prost(
+        oneof = "canister_change::ChangeDetails",
+        tags = "5, 6, 7, 8, 9, 10, 11, 12"
+    )]
     pub change_details: ::core::option::Option<canister_change::ChangeDetails>,
 }
 /// Nested message and enum types in `CanisterChange`.
@@ -476,6 +511,14 @@ pub mod canister_change {
         CanisterCodeDeployment(super::CanisterCodeDeployment),
         #[prost(message, tag = "8")]
         CanisterControllersChange(super::CanisterControllersChange),
+        #[prost(message, tag = "9")]
+        CanisterComputeAllocationChange(super::CanisterComputeAllocationChange),
+        #[prost(message, tag = "10")]
+        CanisterMemoryAllocationChange(super::CanisterMemoryAllocationChange),
+        #[prost(message, tag = "11")]
+        CanisterFreezingThresholdChange(super::CanisterFreezingThresholdChange),
+        #[prost(message, tag = "12")]
+        CanisterReservedCyclesLimitChange(super::CanisterReservedCyclesLimitChange),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -601,6 +644,17 @@ pub struct CanisterStateBits {
     /// Statistics on query execution for entire lifetime of canister.
     #[prost(message, optional, tag = "41")]
     pub total_query_stats: ::core::option::Option<TotalQueryStats>,
+    /// The user-specified upper limit on the size of the Wasm memory.
+    #[prost(uint64, optional, tag = "42")]
+    pub wasm_memory_limit: ::core::option::Option<u64>,
+    /// The user-specified threshold of free Wasm memory below which the
+    /// `canister_on_low_wasm_memory` hook is run.
+    #[prost(uint64, optional, tag = "43")]
+    pub wasm_memory_threshold: ::core::option::Option<u64>,
+    /// Whether the `canister_on_low_wasm_memory` hook is pending or has already
+    /// run for the current crossing of `wasm_memory_threshold`.
+    #[prost(enumeration = "OnLowWasmMemoryHookStatus", tag = "44")]
+    pub on_low_wasm_memory_hook_status: i32,
     #[prost(oneof = "canister_state_bits::CanisterStatus", tags = "11, 12, 13")]
     pub canister_status: ::core::option::Option<canister_state_bits::CanisterStatus>,
 }
@@ -680,6 +734,42 @@ impl NextScheduledMethod {
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
+pub enum OnLowWasmMemoryHookStatus {
+    Unspecified = 0,
+    ConditionNotSatisfied = 1,
+    Ready = 2,
+    Executed = 3,
+}
+impl OnLowWasmMemoryHookStatus {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            OnLowWasmMemoryHookStatus::Unspecified => "ON_LOW_WASM_MEMORY_HOOK_STATUS_UNSPECIFIED",
+            OnLowWasmMemoryHookStatus::ConditionNotSatisfied => {
+                "ON_LOW_WASM_MEMORY_HOOK_STATUS_CONDITION_NOT_SATISFIED"
+            }
+            OnLowWasmMemoryHookStatus::Ready => "ON_LOW_WASM_MEMORY_HOOK_STATUS_READY",
+            OnLowWasmMemoryHookStatus::Executed => "ON_LOW_WASM_MEMORY_HOOK_STATUS_EXECUTED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "ON_LOW_WASM_MEMORY_HOOK_STATUS_UNSPECIFIED" => Some(Self::Unspecified),
+            "ON_LOW_WASM_MEMORY_HOOK_STATUS_CONDITION_NOT_SATISFIED" => {
+                Some(Self::ConditionNotSatisfied)
+            }
+            "ON_LOW_WASM_MEMORY_HOOK_STATUS_READY" => Some(Self::Ready),
+            "ON_LOW_WASM_MEMORY_HOOK_STATUS_EXECUTED" => Some(Self::Executed),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
 pub enum CyclesUseCase {
     Unspecified = 0,
     Memory = 1,
@@ -38,3 +38,40 @@ pub struct MetaManifest {
     #[prost(bytes = "vec", repeated, tag = "2")]
     pub sub_manifest_hashes: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
 }
+/// Progress of a state sync in the loading phase, persisted next to its
+/// scratchpad so that the state sync can be resumed after a restart.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StateSyncProgress {
+    #[prost(uint64, tag = "1")]
+    pub height: u64,
+    #[prost(message, optional, tag = "2")]
+    pub manifest: ::core::option::Option<Manifest>,
+    /// Indices into the manifest's chunk table of the chunks that were missing
+    /// from the scratchpad when the loading phase started.
+    #[prost(uint32, repeated, tag = "3")]
+    pub missing_chunks: ::prost::alloc::vec::Vec<u32>,
+}
+/// Chunks of a checkpoint that might differ from the checkpoint at
+/// `base_height`, persisted so that the manifest of the checkpoint can be
+/// computed incrementally after a restart.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DirtyChunks {
+    #[prost(uint64, tag = "1")]
+    pub base_height: u64,
+    /// Files without an entry have to be rehashed completely.
+    #[prost(message, repeated, tag = "2")]
+    pub files: ::prost::alloc::vec::Vec<FileDirtyChunks>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FileDirtyChunks {
+    #[prost(string, tag = "1")]
+    pub relative_path: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub num_chunks: u64,
+    /// Bitmap of the dirty chunks of the file, as produced by `BitVec::to_bytes`.
+    #[prost(bytes = "vec", tag = "3")]
+    pub bitmap: ::prost::alloc::vec::Vec<u8>,
+}
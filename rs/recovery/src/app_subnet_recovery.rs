@@ -1,7 +1,7 @@
 use crate::{
     cli::{
         consent_given, print_height_info, read_optional, read_optional_node_ids,
-        read_optional_subnet_id, read_optional_version, wait_for_confirmation,
+        read_optional_subnet_id, read_optional_version,
     },
     error::RecoveryError,
     recovery_iterator::RecoveryIterator,
@@ -24,6 +24,8 @@ use url::Url;
 pub enum StepType {
     /// Before we can start the recovery process, we need to prevent the subnet from attempting to finalize new blocks. This step issues a simple ic-admin command creating a proposal halting the consensus of the subnet we try to recover. It is recommended to add an SSH key which will be deployed to all nodes for a read-only access. This access will be needed later to download the subnet state from the most up to date node.
     Halt,
+    /// The following steps require that the subnet doesn't make progress anymore. This step waits until the halt proposal is adopted and the highest finalization height of the subnet's nodes stopped increasing.
+    WaitForHalt,
     /// In order to determine whether we had a possible state divergence during the subnet failure, we need to pull all certification pools from all nodes.
     DownloadCertifications,
     /// In this step we will merge all found certifications and determine whether it is safe to continue without a manual intervention. In most cases, when a subnet happened due to a replica bug and not due to malicious actors, this step should not reveal any problems.
@@ -168,13 +170,6 @@ impl RecoveryIterator<StepType, StepTypeIter> for AppSubnetRecovery {
                 }
             }
 
-            StepType::DownloadCertifications => {
-                info!(&self.logger, "Ensure subnet is halted.");
-                // This can hardly be automated as currently the notion of "subnet is halted" is
-                // unclear, especially in the presence of failures.
-                wait_for_confirmation(&self.logger);
-            }
-
             StepType::DownloadState => {
                 // We could pick a node with highest finalization height automatically,
                 // but we might have a preference between nodes of the same finalization height.
@@ -242,6 +237,10 @@ impl RecoveryIterator<StepType, StepTypeIter> for AppSubnetRecovery {
                 )))
             }
 
+            StepType::WaitForHalt => Ok(Box::new(
+                self.recovery.get_wait_for_halt_step(self.params.subnet_id),
+            )),
+
             StepType::DownloadCertifications => {
                 if self.params.pub_key.is_some() {
                     Ok(Box::new(
@@ -66,6 +66,7 @@ mod tests {
             key_file: Some(PathBuf::from("/dir1/key_file")),
             test_mode: true,
             skip_prompts: true,
+            unattended: false,
         };
         let args2 = RecoveryArgs {
             dir: PathBuf::from("/dir2/"),
@@ -74,6 +75,7 @@ mod tests {
             key_file: None,
             test_mode: false,
             skip_prompts: true,
+            unattended: false,
         };
 
         let expected = RecoveryArgs {
@@ -83,6 +85,7 @@ mod tests {
             key_file: args1.key_file.clone(),
             test_mode: args2.test_mode,
             skip_prompts: true,
+            unattended: false,
         };
 
         assert_eq!(expected, merge(&logger, "test", &args1, &args2).unwrap());
@@ -2,9 +2,12 @@
 use crate::{
     app_subnet_recovery::{AppSubnetRecovery, AppSubnetRecoveryArgs},
     args_merger::merge,
+    cmd::SubCommand,
+    error::RecoveryResult,
     get_node_heights_from_metrics,
     nns_recovery_failover_nodes::{NNSRecoveryFailoverNodes, NNSRecoveryFailoverNodesArgs},
     nns_recovery_same_nodes::{NNSRecoverySameNodes, NNSRecoverySameNodesArgs},
+    plan::{self, RecoveryPlan},
     recovery_iterator::RecoveryIterator,
     recovery_state::{HasRecoveryState, RecoveryState},
     registry_helper::RegistryHelper,
@@ -21,6 +24,7 @@ use std::{
     convert::TryFrom,
     fmt::Display,
     io::{stdin, stdout, Write},
+    path::Path,
     str::FromStr,
 };
 use strum::EnumMessage;
@@ -130,6 +134,105 @@ pub fn nns_recovery_failover_nodes(
     execute_steps(&logger, args.skip_prompts, nns_recovery);
 }
 
+/// Writes the plan of the recovery described by the given arguments to `path`,
+/// without executing any of its steps.
+///
+/// The plan doesn't contain the [NeuronArgs]. The steps using them are planned
+/// with [NeuronArgs::placeholders], to be filled in when the plan is executed.
+pub fn emit_plan(
+    logger: Logger,
+    args: RecoveryArgs,
+    subcommand_args: SubCommand,
+    path: &Path,
+) -> RecoveryResult<()> {
+    print_step(&logger, "Recovery Plan");
+    let args = RecoveryArgs {
+        skip_prompts: true,
+        unattended: true,
+        ..args
+    };
+    let neuron_args = needs_neuron_args(&args, &subcommand_args).then(NeuronArgs::placeholders);
+    let steps = match subcommand_args.clone() {
+        SubCommand::AppSubnetRecovery(subnet_recovery_args) => {
+            plan::plan_steps(&AppSubnetRecovery::new(
+                logger.clone(),
+                args.clone(),
+                neuron_args,
+                subnet_recovery_args,
+            ))
+        }
+        SubCommand::NNSRecoverySameNodes(nns_recovery_args) => plan::plan_steps(
+            &NNSRecoverySameNodes::new(logger.clone(), args.clone(), nns_recovery_args),
+        ),
+        SubCommand::NNSRecoveryFailoverNodes(nns_recovery_args) => {
+            plan::plan_steps(&NNSRecoveryFailoverNodes::new(
+                logger.clone(),
+                args.clone(),
+                neuron_args,
+                nns_recovery_args,
+            ))
+        }
+    };
+
+    let plan = RecoveryPlan {
+        recovery_args: args,
+        subcommand_args,
+        steps,
+    };
+    plan.write(path)?;
+    info!(logger, "Recovery plan written to {:?}", path);
+    Ok(())
+}
+
+/// Executes the given recovery plan without any user interaction, except for
+/// entering the [NeuronArgs] if they aren't set in the environment.
+pub fn execute_plan(logger: Logger, plan: RecoveryPlan) -> RecoveryResult<()> {
+    print_step(&logger, "Recovery Plan Execution");
+    let args = RecoveryArgs {
+        skip_prompts: true,
+        unattended: true,
+        ..plan.recovery_args.clone()
+    };
+    let neuron_args = needs_neuron_args(&args, &plan.subcommand_args)
+        .then(|| NeuronArgs::from_env().unwrap_or_else(|| read_neuron_args(&logger)));
+    match plan.subcommand_args.clone() {
+        SubCommand::AppSubnetRecovery(subnet_recovery_args) => plan::execute_plan(
+            &logger,
+            &plan,
+            neuron_args.as_ref(),
+            &AppSubnetRecovery::new(
+                logger.clone(),
+                args,
+                neuron_args.clone(),
+                subnet_recovery_args,
+            ),
+        ),
+        SubCommand::NNSRecoverySameNodes(nns_recovery_args) => plan::execute_plan(
+            &logger,
+            &plan,
+            None,
+            &NNSRecoverySameNodes::new(logger.clone(), args, nns_recovery_args),
+        ),
+        SubCommand::NNSRecoveryFailoverNodes(nns_recovery_args) => plan::execute_plan(
+            &logger,
+            &plan,
+            neuron_args.as_ref(),
+            &NNSRecoveryFailoverNodes::new(
+                logger.clone(),
+                args,
+                neuron_args.clone(),
+                nns_recovery_args,
+            ),
+        ),
+    }
+}
+
+/// Returns whether the steps of the given recovery submit proposals with a
+/// neuron.
+fn needs_neuron_args(args: &RecoveryArgs, subcommand_args: &SubCommand) -> bool {
+    !args.test_mode && !matches!(subcommand_args, SubCommand::NNSRecoverySameNodes(_))
+}
+
 pub fn execute_steps<
     StepType: Copy + Debug + PartialEq + EnumMessage,
     SubcommandArgsType: Serialize + DeserializeOwned,
@@ -54,6 +54,17 @@ pub struct RecoveryToolArgs {
     #[clap(long)]
     pub skip_prompts: bool,
 
+    /// Write the plan of the recovery to the given file, without executing any
+    /// of its steps
+    #[clap(long, parse(from_os_str))]
+    pub emit_plan: Option<PathBuf>,
+
+    /// Execute the recovery plan in the given file unattended, resuming after
+    /// the steps completed by a previous execution. The neuron arguments are
+    /// read from DFX_HSM_PIN, HSM_SLOT, NEURON_ID and HSM_KEY_ID, or prompted for
+    #[clap(long, parse(from_os_str))]
+    pub plan: Option<PathBuf>,
+
     #[clap(subcommand)]
     pub subcmd: Option<SubCommand>,
 }
@@ -47,6 +47,7 @@ pub mod error;
 pub mod file_sync_helper;
 pub mod nns_recovery_failover_nodes;
 pub mod nns_recovery_same_nodes;
+pub mod plan;
 pub mod recovery_iterator;
 pub mod recovery_state;
 pub mod registry_helper;
@@ -72,6 +73,9 @@ pub const IC_STATE_EXCLUDES: &[&str] = &[
     // it is a new directory used for storing the files backing up the
     // page deltas. We do not need to copy page deltas when nodes are re-assigned.
     "page_deltas",
+    // The compiled Wasm modules are specific to the replica version and
+    // configuration of the node and are recompiled when missing.
+    "compilation_cache",
     IC_REGISTRY_LOCAL_STORE,
 ];
 pub const IC_STATE: &str = "ic_state";
@@ -89,6 +93,53 @@ pub struct NeuronArgs {
     key_id: String,
 }
 
+/// The environment variables holding the [NeuronArgs] of a plan execution, which
+/// also serve as the placeholders of the [NeuronArgs] in the plan.
+const DFX_HSM_PIN_VAR: &str = "DFX_HSM_PIN";
+const HSM_SLOT_VAR: &str = "HSM_SLOT";
+const NEURON_ID_VAR: &str = "NEURON_ID";
+const HSM_KEY_ID_VAR: &str = "HSM_KEY_ID";
+
+impl NeuronArgs {
+    /// Returns [NeuronArgs] consisting of placeholders, which stand for the actual
+    /// arguments in the steps of a recovery plan.
+    pub fn placeholders() -> Self {
+        Self {
+            dfx_hsm_pin: format!("${{{}}}", DFX_HSM_PIN_VAR),
+            slot: format!("${{{}}}", HSM_SLOT_VAR),
+            neuron_id: format!("${{{}}}", NEURON_ID_VAR),
+            key_id: format!("${{{}}}", HSM_KEY_ID_VAR),
+        }
+    }
+
+    /// Reads the [NeuronArgs] from the environment, if all of them are set.
+    pub fn from_env() -> Option<Self> {
+        Some(Self {
+            dfx_hsm_pin: std::env::var(DFX_HSM_PIN_VAR).ok()?,
+            slot: std::env::var(HSM_SLOT_VAR).ok()?,
+            neuron_id: std::env::var(NEURON_ID_VAR).ok()?,
+            key_id: std::env::var(HSM_KEY_ID_VAR).ok()?,
+        })
+    }
+
+    /// Replaces the [NeuronArgs::placeholders] in `text` with these arguments.
+    pub fn fill_placeholders(&self, text: &str) -> String {
+        let placeholders = Self::placeholders();
+        text.replace(&placeholders.dfx_hsm_pin, &self.dfx_hsm_pin)
+            .replace(&placeholders.slot, &self.slot)
+            .replace(&placeholders.neuron_id, &self.neuron_id)
+            .replace(&placeholders.key_id, &self.key_id)
+    }
+
+    /// Replaces the DFX HSM PIN in `text` with its placeholder.
+    pub fn redact_pin(&self, text: &str) -> String {
+        if self.dfx_hsm_pin.is_empty() {
+            return text.to_string();
+        }
+        text.replace(&self.dfx_hsm_pin, &Self::placeholders().dfx_hsm_pin)
+    }
+}
+
 #[derive(Debug)]
 pub struct NodeMetrics {
     _ip: IpAddr,
@@ -104,6 +155,10 @@ pub struct RecoveryArgs {
     pub key_file: Option<PathBuf>,
     pub test_mode: bool,
     pub skip_prompts: bool,
+    /// Set while a recovery plan is emitted or executed, where no one is there to
+    /// confirm the ssh access to the nodes.
+    #[serde(default)]
+    pub unattended: bool,
 }
 
 /// The recovery struct comprises working directories for the recovery of a
@@ -140,7 +195,7 @@ impl Recovery {
         registry_nns_url: Url,
         registry_polling_strategy: RegistryPollingStrategy,
     ) -> RecoveryResult<Self> {
-        let ssh_confirmation = !args.test_mode;
+        let ssh_confirmation = !args.test_mode && !args.unattended;
         let recovery_dir = args.dir.join(RECOVERY_DIRECTORY_NAME);
         let binary_dir = recovery_dir.join("binaries");
         let data_dir = recovery_dir.join("original_data");
@@ -731,6 +786,64 @@ impl Recovery {
         Ok(())
     }
 
+    /// Return a [WaitForHaltStep] to wait until the given subnet is halted.
+    pub fn get_wait_for_halt_step(&self, subnet_id: SubnetId) -> impl Step {
+        WaitForHaltStep {
+            logger: self.logger.clone(),
+            subnet_id,
+            registry_helper: self.registry_helper.clone(),
+        }
+    }
+
+    // Wait until the subnet record of the given subnet is halted in the latest registry version
+    // and the highest finalization height reported by its nodes stopped increasing.
+    pub fn wait_for_halt(
+        logger: &Logger,
+        registry_helper: &RegistryHelper,
+        subnet_id: SubnetId,
+    ) -> RecoveryResult<()> {
+        let mut halted_in_registry = false;
+        let mut last_height = None;
+        for i in 0..40 {
+            if !halted_in_registry {
+                registry_helper.poll()?;
+                let (registry_version, subnet_record) =
+                    registry_helper.get_subnet_record(subnet_id)?;
+                halted_in_registry = subnet_record.map_or(false, |record| record.is_halted);
+                info!(
+                    logger,
+                    "Try: {}. Subnet halted in registry version {}: {}",
+                    i,
+                    registry_version,
+                    halted_in_registry
+                );
+            }
+
+            if halted_in_registry {
+                let height = get_node_heights_from_metrics(logger, registry_helper, subnet_id)?
+                    .iter()
+                    .map(|metrics| metrics.finalization_height)
+                    .max();
+                info!(
+                    logger,
+                    "Try: {}. Highest finalization height: {:?}", i, height
+                );
+                if height.is_some() && height == last_height {
+                    info!(logger, "Subnet is halted!");
+                    return Ok(());
+                }
+                last_height = height;
+            }
+
+            thread::sleep(time::Duration::from_secs(15));
+        }
+
+        Err(RecoveryError::invalid_output_error(format!(
+            "Subnet {} did not halt",
+            subnet_id
+        )))
+    }
+
     /// Return a [CleanupStep] to remove the recovery directory and all of its contents
     pub fn get_cleanup_step(&self) -> impl Step {
         CleanupStep {
@@ -6,8 +6,10 @@ use ic_canister_sandbox_backend_lib::{
 };
 use ic_canister_sandbox_launcher::sandbox_launcher_main;
 use ic_recovery::cmd::{RecoveryToolArgs, SubCommand};
+use ic_recovery::plan::RecoveryPlan;
 use ic_recovery::RecoveryArgs;
 use ic_recovery::{cli, util};
+use slog::error;
 
 fn main() {
     if std::env::args().any(|arg| arg == RUN_AS_CANISTER_SANDBOX_FLAG) {
@@ -21,6 +23,15 @@ fn main() {
     let logger = util::make_logger();
     let args = RecoveryToolArgs::parse();
 
+    if let Some(plan_file) = args.plan {
+        let plan = RecoveryPlan::read(&plan_file).expect("Failed to read the recovery plan");
+        if let Err(e) = cli::execute_plan(logger.clone(), plan) {
+            error!(logger, "Failed to execute the recovery plan: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let recovery_args = RecoveryArgs {
         dir: args.dir,
         nns_url: args.nns_url,
@@ -28,8 +39,20 @@ fn main() {
         key_file: args.key_file,
         test_mode: args.test,
         skip_prompts: args.skip_prompts,
+        unattended: false,
     };
 
+    if let Some(plan_file) = args.emit_plan {
+        cli::emit_plan(
+            logger.clone(),
+            recovery_args,
+            args.subcmd.expect("subcommand not provided"),
+            &plan_file,
+        )
+        .expect("Failed to emit the recovery plan");
+        return;
+    }
+
     let recovery_state = cli::read_and_maybe_update_state(&logger, recovery_args, args.subcmd);
 
     match recovery_state.subcommand_args {
@@ -0,0 +1,640 @@
+//! Declarative recovery plans.
+//!
+//! A [RecoveryPlan] holds the arguments of a recovery procedure together with
+//! the list of steps it consists of and, for each step, the description of
+//! what it will do. Plans are emitted without executing any step (see
+//! [plan_steps]), so that a procedure can be reviewed and rehearsed, and are
+//! then executed unattended (see [execute_plan]).
+//!
+//! Plans don't contain the [NeuronArgs]: the steps submitting proposals are
+//! planned with [NeuronArgs::placeholders], which are filled in with the
+//! arguments given to the execution.
+//!
+//! While a plan is executed, every completed step is recorded in a checkpoint
+//! file, so that an interrupted execution resumes from the first step that did
+//! not complete. Every step event is also appended to a log file, as one JSON
+//! object per line.
+use crate::{
+    cmd::SubCommand,
+    error::{RecoveryError, RecoveryResult},
+    file_sync_helper::{path_exists, read_file, write_file},
+    recovery_iterator::RecoveryIterator,
+    NeuronArgs, RecoveryArgs,
+};
+use serde::{Deserialize, Serialize};
+use slog::{info, warn, Logger};
+use std::{
+    fmt::Debug,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use strum::{EnumMessage, IntoEnumIterator};
+
+const PLAN_CHECKPOINT_FILE_NAME: &str = "recovery_plan_checkpoint.json";
+const PLAN_LOG_FILE_NAME: &str = "recovery_plan_log.jsonl";
+
+/// A recovery procedure, as a list of steps and the arguments they are
+/// generated from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecoveryPlan {
+    pub recovery_args: RecoveryArgs,
+    pub subcommand_args: SubCommand,
+    pub steps: Vec<PlannedStep>,
+}
+
+/// A single step of a [RecoveryPlan].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlannedStep {
+    /// The name of the step, e.g. `Halt`.
+    pub step: String,
+    /// The description of the step, or [None] if it depends on the outcome of
+    /// previous steps (e.g. a CUP proposal including the hash of the replayed
+    /// state).
+    pub description: Option<String>,
+}
+
+impl RecoveryPlan {
+    /// Reads a plan from the given JSON file.
+    pub fn read(path: &Path) -> RecoveryResult<Self> {
+        read_file(path).and_then(|content| {
+            serde_json::from_str(&content).map_err(RecoveryError::parsing_error)
+        })
+    }
+
+    /// Writes the plan to the given JSON file.
+    pub fn write(&self, path: &Path) -> RecoveryResult<()> {
+        serde_json::to_string_pretty(self)
+            .map_err(RecoveryError::serialization_error)
+            .and_then(|json| write_file(path, json))
+    }
+}
+
+/// The steps of a plan that were executed successfully.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlanCheckpoint {
+    pub completed_steps: Vec<String>,
+}
+
+impl PlanCheckpoint {
+    /// Reads the checkpoint from the working directory `dir`, returning an
+    /// empty checkpoint if there is none.
+    pub fn read(dir: &Path) -> RecoveryResult<Self> {
+        let path = Self::get_file_name(dir);
+        if !path_exists(&path)? {
+            return Ok(Self::default());
+        }
+        read_file(&path).and_then(|content| {
+            serde_json::from_str(&content).map_err(RecoveryError::parsing_error)
+        })
+    }
+
+    /// Writes the checkpoint to the working directory `dir`.
+    pub fn save(&self, dir: &Path) -> RecoveryResult<()> {
+        serde_json::to_string(self)
+            .map_err(RecoveryError::serialization_error)
+            .and_then(|json| write_file(&Self::get_file_name(dir), json))
+    }
+
+    fn get_file_name(dir: &Path) -> PathBuf {
+        dir.join(PLAN_CHECKPOINT_FILE_NAME)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum StepEvent {
+    /// The step was completed by a previous execution of the plan.
+    AlreadyCompleted,
+    /// The step does not apply to the given arguments, which fails the
+    /// execution of the plan.
+    Skipped,
+    Started,
+    Succeeded,
+    Failed,
+}
+
+/// An entry of the structured plan log.
+#[derive(Serialize)]
+struct PlanLogEntry<'a> {
+    timestamp_secs: u64,
+    step: &'a str,
+    event: StepEvent,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Appends [PlanLogEntry]s to the log file in the working directory.
+struct PlanLog {
+    path: PathBuf,
+}
+
+impl PlanLog {
+    fn new(dir: &Path) -> Self {
+        Self {
+            path: dir.join(PLAN_LOG_FILE_NAME),
+        }
+    }
+
+    fn log(
+        &self,
+        step: &str,
+        event: StepEvent,
+        description: Option<&str>,
+        error: Option<String>,
+    ) -> RecoveryResult<()> {
+        let entry = PlanLogEntry {
+            timestamp_secs: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            step,
+            event,
+            description,
+            error,
+        };
+        let mut line = serde_json::to_string(&entry).map_err(RecoveryError::serialization_error)?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| RecoveryError::file_error(&self.path, e))
+    }
+}
+
+/// Lists the steps `steps` would execute, without executing any of them.
+///
+/// Steps that are skipped for the given arguments are not listed. Steps that
+/// can't be generated yet (because they depend on the outcome of previous
+/// steps) are listed without a description.
+pub fn plan_steps<StepType, I, Steps>(steps: &Steps) -> Vec<PlannedStep>
+where
+    StepType: Copy + Debug + PartialEq + EnumMessage + IntoEnumIterator,
+    I: Iterator<Item = StepType>,
+    Steps: RecoveryIterator<StepType, I>,
+{
+    let skipped_steps = steps.get_skipped_steps();
+    StepType::iter()
+        .filter(|step_type| !skipped_steps.contains(step_type))
+        .filter_map(|step_type| {
+            let description = match steps.get_step_impl(step_type) {
+                Ok(step) => Some(step.descr()),
+                Err(RecoveryError::StepSkipped) => return None,
+                Err(_) => None,
+            };
+            Some(PlannedStep {
+                step: format!("{:?}", step_type),
+                description,
+            })
+        })
+        .collect()
+}
+
+/// Executes the steps of `plan` in order, without any user interaction.
+///
+/// Steps recorded as completed in the checkpoint are not executed again. A
+/// step whose description differs from the one in the plan, once the
+/// placeholders are filled in with `neuron_args`, is not executed and fails the
+/// execution, as it is not the step that was reviewed. The same holds for a
+/// planned step that is skipped for the given arguments. The execution also stops
+/// at the first step that fails. The DFX HSM PIN is not logged.
+pub fn execute_plan<StepType, I, Steps>(
+    logger: &Logger,
+    plan: &RecoveryPlan,
+    neuron_args: Option<&NeuronArgs>,
+    steps: &Steps,
+) -> RecoveryResult<()>
+where
+    StepType: Copy + Debug + PartialEq + EnumMessage + FromStr,
+    I: Iterator<Item = StepType>,
+    Steps: RecoveryIterator<StepType, I>,
+{
+    let dir = &plan.recovery_args.dir;
+    let log = PlanLog::new(dir);
+    let mut checkpoint = PlanCheckpoint::read(dir)?;
+
+    for planned_step in &plan.steps {
+        let name = planned_step.step.as_str();
+        let step_type = StepType::from_str(name)
+            .map_err(|_| RecoveryError::UnexpectedError(format!("Unknown step: {}", name)))?;
+
+        if checkpoint.completed_steps.iter().any(|step| step == name) {
+            info!(logger, "Skipping already executed step {}", name);
+            log.log(name, StepEvent::AlreadyCompleted, None, None)?;
+            continue;
+        }
+
+        super::cli::print_step(logger, name);
+        let step = match steps.get_step_impl(step_type) {
+            Ok(step) => step,
+            Err(RecoveryError::StepSkipped) => {
+                let e = RecoveryError::validation_failed(
+                    format!("Step {} deviates from the plan", name),
+                    "the step is skipped for the given arguments",
+                );
+                log.log(name, StepEvent::Skipped, None, Some(e.to_string()))?;
+                return Err(e);
+            }
+            Err(e) => {
+                log.log(name, StepEvent::Failed, None, Some(e.to_string()))?;
+                return Err(e);
+            }
+        };
+
+        let redact = |text: &str| match neuron_args {
+            Some(neuron_args) => neuron_args.redact_pin(text),
+            None => text.to_string(),
+        };
+        let fill_placeholders = |text: &str| match neuron_args {
+            Some(neuron_args) => neuron_args.fill_placeholders(text),
+            None => text.to_string(),
+        };
+        let description = redact(&step.descr());
+        if let Some(planned_description) = &planned_step.description {
+            if fill_placeholders(planned_description) != step.descr() {
+                let e = RecoveryError::validation_failed(
+                    format!("Step {} deviates from the plan", name),
+                    format!("expected {:?}, got {:?}", planned_description, description),
+                );
+                log.log(
+                    name,
+                    StepEvent::Failed,
+                    Some(&description),
+                    Some(e.to_string()),
+                )?;
+                return Err(e);
+            }
+        }
+
+        info!(logger, "{}", description);
+        log.log(name, StepEvent::Started, Some(&description), None)?;
+        if let Err(e) = step.exec() {
+            warn!(logger, "Error: {}", e);
+            log.log(
+                name,
+                StepEvent::Failed,
+                Some(&description),
+                Some(e.to_string()),
+            )?;
+            return Err(e);
+        }
+        log.log(name, StepEvent::Succeeded, Some(&description), None)?;
+
+        checkpoint.completed_steps.push(name.to_string());
+        checkpoint.save(dir)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        app_subnet_recovery::AppSubnetRecoveryArgs, steps::Step, util::subnet_id_from_str,
+    };
+    use std::iter::Peekable;
+    use strum_macros::{EnumIter, EnumString};
+    use tempfile::tempdir;
+    use url::Url;
+
+    #[derive(Debug, Copy, Clone, EnumIter, EnumMessage, EnumString, PartialEq)]
+    enum FakeStep {
+        P0,
+        P1,
+        P2,
+        P3,
+    }
+
+    struct FakeStepImpl {
+        step_type: FakeStep,
+        fail: bool,
+        neuron_args: Option<NeuronArgs>,
+    }
+
+    impl Step for FakeStepImpl {
+        fn descr(&self) -> String {
+            match &self.neuron_args {
+                Some(neuron_args) => format!(
+                    "Fake step {:?} --pin {}",
+                    self.step_type, neuron_args.dfx_hsm_pin
+                ),
+                None => format!("Fake step {:?}", self.step_type),
+            }
+        }
+
+        fn exec(&self) -> RecoveryResult<()> {
+            if self.fail {
+                Err(RecoveryError::UnexpectedError("step failed".to_string()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Fake RecoveryIterator over `FakeStep`s, skipping `P1` and failing to
+    /// execute `failing_step`.
+    struct FakeRecovery {
+        step_iterator: Peekable<FakeStepIter>,
+        logger: Logger,
+        failing_step: Option<FakeStep>,
+        neuron_args: Option<NeuronArgs>,
+    }
+
+    impl FakeRecovery {
+        fn new(failing_step: Option<FakeStep>) -> Self {
+            Self {
+                step_iterator: FakeStep::iter().peekable(),
+                logger: crate::util::make_logger(),
+                failing_step,
+                neuron_args: None,
+            }
+        }
+
+        fn with_neuron_args(neuron_args: NeuronArgs) -> Self {
+            Self {
+                neuron_args: Some(neuron_args),
+                ..Self::new(None)
+            }
+        }
+    }
+
+    impl RecoveryIterator<FakeStep, FakeStepIter> for FakeRecovery {
+        fn get_step_iterator(&mut self) -> &mut Peekable<FakeStepIter> {
+            &mut self.step_iterator
+        }
+
+        fn store_next_step(&mut self, _step_type: Option<FakeStep>) {}
+
+        fn get_logger(&self) -> &Logger {
+            &self.logger
+        }
+
+        fn interactive(&self) -> bool {
+            false
+        }
+
+        fn read_step_params(&mut self, _step_type: FakeStep) {}
+
+        fn get_step_impl(&self, step_type: FakeStep) -> RecoveryResult<Box<dyn Step>> {
+            if step_type == FakeStep::P1 {
+                return Err(RecoveryError::StepSkipped);
+            }
+            Ok(Box::new(FakeStepImpl {
+                step_type,
+                fail: self.failing_step == Some(step_type),
+                neuron_args: self.neuron_args.clone(),
+            }))
+        }
+    }
+
+    fn fake_plan(dir: &Path, steps: Vec<PlannedStep>) -> RecoveryPlan {
+        RecoveryPlan {
+            recovery_args: RecoveryArgs {
+                dir: dir.to_path_buf(),
+                nns_url: Url::parse("https://fake_nns_url.com/").unwrap(),
+                replica_version: None,
+                key_file: None,
+                test_mode: true,
+                skip_prompts: true,
+                unattended: false,
+            },
+            subcommand_args: SubCommand::AppSubnetRecovery(AppSubnetRecoveryArgs {
+                subnet_id: subnet_id_from_str(
+                    "gpvux-2ejnk-3hgmh-cegwf-iekfc-b7rzs-hrvep-5euo2-3ywz3-k3hcb-cqe",
+                )
+                .unwrap(),
+                upgrade_version: None,
+                replacement_nodes: None,
+                pub_key: None,
+                download_node: None,
+                keep_downloaded_state: None,
+                upload_node: None,
+                ecdsa_subnet_id: None,
+                next_step: None,
+                upgrade_image_url: None,
+                upgrade_image_hash: None,
+            }),
+            steps,
+        }
+    }
+
+    fn planned_step(step: &str) -> PlannedStep {
+        PlannedStep {
+            step: step.to_string(),
+            description: Some(format!("Fake step {}", step)),
+        }
+    }
+
+    /// Returns the `(step, event)` pairs of the plan log in `dir`.
+    fn read_log(dir: &Path) -> Vec<(String, String)> {
+        read_file(&dir.join(PLAN_LOG_FILE_NAME))
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+                (
+                    entry["step"].as_str().unwrap().to_string(),
+                    entry["event"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn log_entries(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries
+            .iter()
+            .map(|(step, event)| (step.to_string(), event.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn plan_lists_steps_that_are_not_skipped() {
+        let planned_steps = plan_steps(&FakeRecovery::new(None));
+
+        assert_eq!(
+            planned_steps,
+            vec![planned_step("P0"), planned_step("P2"), planned_step("P3")]
+        );
+    }
+
+    #[test]
+    fn plan_round_trips_through_file() {
+        let tmp = tempdir().unwrap();
+        let plan = fake_plan(tmp.path(), plan_steps(&FakeRecovery::new(None)));
+        let path = tmp.path().join("plan.json");
+
+        plan.write(&path).unwrap();
+
+        assert_eq!(RecoveryPlan::read(&path).unwrap(), plan);
+    }
+
+    #[test]
+    fn execute_plan_executes_listed_steps_and_records_checkpoint() {
+        let tmp = tempdir().unwrap();
+        let plan = fake_plan(tmp.path(), vec![planned_step("P0"), planned_step("P3")]);
+
+        execute_plan(
+            &crate::util::make_logger(),
+            &plan,
+            None,
+            &FakeRecovery::new(None),
+        )
+        .unwrap();
+
+        assert_eq!(
+            PlanCheckpoint::read(tmp.path()).unwrap().completed_steps,
+            vec!["P0".to_string(), "P3".to_string()]
+        );
+        assert_eq!(
+            read_log(tmp.path()),
+            log_entries(&[
+                ("P0", "started"),
+                ("P0", "succeeded"),
+                ("P3", "started"),
+                ("P3", "succeeded"),
+            ])
+        );
+    }
+
+    #[test]
+    fn execute_plan_resumes_after_failed_step() {
+        let tmp = tempdir().unwrap();
+        let plan = fake_plan(tmp.path(), plan_steps(&FakeRecovery::new(None)));
+        let logger = crate::util::make_logger();
+
+        assert!(
+            execute_plan(&logger, &plan, None, &FakeRecovery::new(Some(FakeStep::P2))).is_err()
+        );
+        assert_eq!(
+            PlanCheckpoint::read(tmp.path()).unwrap().completed_steps,
+            vec!["P0".to_string()]
+        );
+
+        execute_plan(&logger, &plan, None, &FakeRecovery::new(None)).unwrap();
+
+        assert_eq!(
+            PlanCheckpoint::read(tmp.path()).unwrap().completed_steps,
+            vec!["P0".to_string(), "P2".to_string(), "P3".to_string()]
+        );
+        assert_eq!(
+            read_log(tmp.path()),
+            log_entries(&[
+                ("P0", "started"),
+                ("P0", "succeeded"),
+                ("P2", "started"),
+                ("P2", "failed"),
+                ("P0", "already_completed"),
+                ("P2", "started"),
+                ("P2", "succeeded"),
+                ("P3", "started"),
+                ("P3", "succeeded"),
+            ])
+        );
+    }
+
+    #[test]
+    fn execute_plan_rejects_planned_steps_that_are_skipped() {
+        let tmp = tempdir().unwrap();
+        let plan = fake_plan(tmp.path(), vec![planned_step("P1"), planned_step("P2")]);
+
+        assert!(matches!(
+            execute_plan(
+                &crate::util::make_logger(),
+                &plan,
+                None,
+                &FakeRecovery::new(None)
+            ),
+            Err(RecoveryError::ValidationFailed(_))
+        ));
+        assert_eq!(read_log(tmp.path()), log_entries(&[("P1", "skipped")]));
+        assert!(PlanCheckpoint::read(tmp.path())
+            .unwrap()
+            .completed_steps
+            .is_empty());
+    }
+
+    #[test]
+    fn execute_plan_rejects_steps_deviating_from_the_plan() {
+        let tmp = tempdir().unwrap();
+        let plan = fake_plan(
+            tmp.path(),
+            vec![PlannedStep {
+                step: "P0".to_string(),
+                description: Some("A different description".to_string()),
+            }],
+        );
+
+        assert!(matches!(
+            execute_plan(
+                &crate::util::make_logger(),
+                &plan,
+                None,
+                &FakeRecovery::new(None)
+            ),
+            Err(RecoveryError::ValidationFailed(_))
+        ));
+        assert_eq!(read_log(tmp.path()), log_entries(&[("P0", "failed")]));
+        assert!(PlanCheckpoint::read(tmp.path())
+            .unwrap()
+            .completed_steps
+            .is_empty());
+    }
+
+    #[test]
+    fn plan_and_log_dont_contain_the_pin() {
+        let tmp = tempdir().unwrap();
+        let neuron_args = NeuronArgs {
+            dfx_hsm_pin: "fake_dfx_hsm_pin".to_string(),
+            slot: "0".to_string(),
+            neuron_id: "49".to_string(),
+            key_id: "01".to_string(),
+        };
+        let plan = fake_plan(
+            tmp.path(),
+            plan_steps(&FakeRecovery::with_neuron_args(NeuronArgs::placeholders())),
+        );
+        let path = tmp.path().join("plan.json");
+        plan.write(&path).unwrap();
+
+        execute_plan(
+            &crate::util::make_logger(),
+            &plan,
+            Some(&neuron_args),
+            &FakeRecovery::with_neuron_args(neuron_args.clone()),
+        )
+        .unwrap();
+
+        assert_eq!(
+            PlanCheckpoint::read(tmp.path()).unwrap().completed_steps,
+            vec!["P0".to_string(), "P2".to_string(), "P3".to_string()]
+        );
+        assert!(!read_file(&path).unwrap().contains("fake_dfx_hsm_pin"));
+        assert!(!read_file(&tmp.path().join(PLAN_LOG_FILE_NAME))
+            .unwrap()
+            .contains("fake_dfx_hsm_pin"));
+    }
+
+    #[test]
+    fn execute_plan_rejects_unknown_steps() {
+        let tmp = tempdir().unwrap();
+        let plan = fake_plan(tmp.path(), vec![planned_step("P7")]);
+
+        assert!(matches!(
+            execute_plan(
+                &crate::util::make_logger(),
+                &plan,
+                None,
+                &FakeRecovery::new(None)
+            ),
+            Err(RecoveryError::UnexpectedError(_))
+        ));
+    }
+}
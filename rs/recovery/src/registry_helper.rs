@@ -140,16 +140,7 @@ impl RegistryHelper {
     /// gets the latest registry version.
     pub fn latest_registry_version(&self) -> RecoveryResult<RegistryVersion> {
         match self.polling_strategy {
-            RegistryPollingStrategy::WithEveryRead => {
-                block_on(self.registry_replicator.poll(vec![self.nns_url.clone()])).map_err(
-                    |err| {
-                        RecoveryError::RegistryError(format!(
-                            "Failed to poll the newest registry: {}",
-                            err
-                        ))
-                    },
-                )?;
-            }
+            RegistryPollingStrategy::WithEveryRead => self.poll()?,
             RegistryPollingStrategy::OnlyOnInit => {}
         }
 
@@ -160,6 +151,14 @@ impl RegistryHelper {
         Ok(self.registry_client.get_latest_version())
     }
 
+    /// Polls the [RegistryReplicator] for the most recent version of the registry, regardless of
+    /// the [RegistryPollingStrategy].
+    pub fn poll(&self) -> RecoveryResult<()> {
+        block_on(self.registry_replicator.poll(vec![self.nns_url.clone()])).map_err(|err| {
+            RecoveryError::RegistryError(format!("Failed to poll the newest registry: {}", err))
+        })
+    }
+
     /// Polls the [RegistryReplicator] for the most recent version of the registry and then
     /// extracts the appropriate entries based on the provided closure.
     fn get<T>(
@@ -656,6 +656,25 @@ impl Step for WaitForCUPStep {
     }
 }
 
+pub struct WaitForHaltStep {
+    pub logger: Logger,
+    pub subnet_id: SubnetId,
+    pub registry_helper: RegistryHelper,
+}
+
+impl Step for WaitForHaltStep {
+    fn descr(&self) -> String {
+        format!(
+            "Waiting until subnet {} is halted in the registry and its nodes stopped finalizing blocks.",
+            self.subnet_id
+        )
+    }
+
+    fn exec(&self) -> RecoveryResult<()> {
+        Recovery::wait_for_halt(&self.logger, &self.registry_helper, self.subnet_id)
+    }
+}
+
 pub struct CleanupStep {
     pub recovery_dir: PathBuf,
 }
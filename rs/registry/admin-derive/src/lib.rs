@@ -27,12 +27,16 @@ fn impl_proposal_metadata(ast: &syn::DeriveInput) -> TokenStream {
             }
 
             fn proposer_and_sender(&self, sender: Sender) -> (NeuronId, Sender) {
-                let use_test_neuron = self.test_neuron_proposer || (self.dry_run && matches!(sender, Sender::Anonymous));
+                let use_test_neuron = self.test_neuron_proposer || (self.is_dry_run() && matches!(sender, Sender::Anonymous));
                 get_proposer_and_sender(self.proposer.clone(), sender, use_test_neuron)
             }
 
             fn is_dry_run(&self) -> bool {
-                self.dry_run
+                self.dry_run || self.simulate
+            }
+
+            fn is_simulation(&self) -> bool {
+                self.simulate
             }
 
             fn is_json(&self) -> bool {
@@ -91,6 +95,13 @@ pub fn derive_common_proposal_fields(_: TokenStream, item: TokenStream) -> Token
                             #[clap(long)]
                             pub dry_run: bool,
 
+                            /// If set, the proposal will not be submitted. Instead, its registry
+                            /// mutations are applied to a copy of the latest registry version, the
+                            /// registry invariants are checked and the before/after values of all
+                            /// affected keys are printed. Implies --dry-run.
+                            #[clap(long)]
+                            pub simulate: bool,
+
                             /// If set, JSON output will be printed for --dry-run
                             #[clap(long)]
                             pub json: bool,
@@ -0,0 +1,470 @@
+//! Simulates the effect of a proposal on the registry without submitting it
+//! (`--simulate`).
+//!
+//! The latest version of the registry is fetched from the NNS, the mutation
+//! code of the registry canister is applied to a local copy of it, and the
+//! invariants of the registry canister are checked against the result. The
+//! keys affected by the proposal are then printed as a before/after diff.
+use candid::{CandidType, Decode, Encode};
+use ic_nns_governance::pb::v1::NnsFunction;
+use ic_protobuf::registry::{
+    api_boundary_node::v1::ApiBoundaryNodeRecord,
+    dc::v1::{AddOrRemoveDataCentersProposalPayload, DataCenterRecord},
+    firewall::v1::{FirewallConfig, FirewallRuleSet},
+    hostos_version::v1::HostosVersionRecord,
+    node::v1::NodeRecord,
+    node_operator::v1::{NodeOperatorRecord, RemoveNodeOperatorsPayload},
+    node_rewards::v2::{NodeRewardsTable, UpdateNodeRewardsTableProposalPayload},
+    provisional_whitelist::v1::ProvisionalWhitelist,
+    replica_version::v1::{BlessedReplicaVersions, ReplicaVersionRecord},
+    routing_table::v1::{CanisterMigrations, RoutingTable, SubnetSplittingRecord},
+    subnet::v1::{SubnetListRecord, SubnetRecord},
+    unassigned_nodes_config::v1::UnassignedNodesConfigRecord,
+};
+use ic_registry_keys::{
+    make_blessed_replica_versions_key, make_canister_migrations_record_key,
+    make_firewall_config_record_key, make_provisional_whitelist_record_key,
+    make_routing_table_record_key, make_subnet_list_record_key,
+    make_unassigned_nodes_config_record_key, API_BOUNDARY_NODE_RECORD_KEY_PREFIX,
+    DATA_CENTER_KEY_PREFIX, FIREWALL_RULES_RECORD_KEY_PREFIX, HOSTOS_VERSION_KEY_PREFIX,
+    NODE_OPERATOR_RECORD_KEY_PREFIX, NODE_RECORD_KEY_PREFIX, NODE_REWARDS_TABLE_KEY,
+    REPLICA_VERSION_KEY_PREFIX, SUBNET_RECORD_KEY_PREFIX, SUBNET_SPLITTING_RECORD_KEY_PREFIX,
+};
+use ic_registry_nns_data_provider::registry::RegistryCanister;
+use prost::Message;
+use registry_canister::{
+    check_invariants,
+    mutations::{
+        complete_canister_migration::CompleteCanisterMigrationPayload,
+        do_add_api_boundary_node::AddApiBoundaryNodePayload,
+        do_add_node_operator::AddNodeOperatorPayload,
+        do_add_nodes_to_subnet::AddNodesToSubnetPayload,
+        do_change_subnet_membership::ChangeSubnetMembershipPayload,
+        do_remove_api_boundary_nodes::RemoveApiBoundaryNodesPayload,
+        do_remove_nodes_from_subnet::RemoveNodesFromSubnetPayload,
+        do_set_firewall_config::SetFirewallConfigPayload,
+        do_update_api_boundary_node_domain::UpdateApiBoundaryNodeDomainPayload,
+        do_update_api_boundary_nodes_version::UpdateApiBoundaryNodesVersionPayload,
+        do_update_elected_hostos_versions::UpdateElectedHostosVersionsPayload,
+        do_update_elected_replica_versions::UpdateElectedReplicaVersionsPayload,
+        do_update_node_operator_config::UpdateNodeOperatorConfigPayload,
+        do_update_nodes_hostos_version::UpdateNodesHostosVersionPayload,
+        do_update_subnet::UpdateSubnetPayload,
+        do_update_subnet_replica::UpdateSubnetReplicaVersionPayload,
+        do_update_unassigned_nodes_config::UpdateUnassignedNodesConfigPayload,
+        firewall::{
+            AddFirewallRulesPayload, RemoveFirewallRulesPayload, UpdateFirewallRulesPayload,
+        },
+        node_management::do_remove_nodes::RemoveNodesPayload,
+        prepare_canister_migration::PrepareCanisterMigrationPayload,
+        reroute_canister_ranges::RerouteCanisterRangesPayload,
+        split_subnet::{SplitSubnetPayload, UpdateSubnetSplittingStatusPayload},
+    },
+    registry::Registry,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Write},
+    os::unix::io::RawFd,
+    panic::{self, AssertUnwindSafe},
+};
+
+/// The latest value of every key in the registry.
+type RegistrySnapshot = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// A mutation of the registry that can be simulated locally.
+type RegistryMutationFn = Box<dyn FnOnce(&mut Registry) -> Result<(), String>>;
+
+/// The before/after values of a single registry key affected by a proposal.
+#[derive(Serialize)]
+struct KeyDiff {
+    key: String,
+    before: Option<Value>,
+    after: Option<Value>,
+}
+
+/// The outcome of simulating a proposal against the latest registry version.
+#[derive(Serialize)]
+struct DryRunResult {
+    registry_version: u64,
+    invariants_hold: bool,
+    error: Option<String>,
+    diff: Vec<KeyDiff>,
+}
+
+/// Applies the registry mutations of the proposal with the given payload to
+/// a copy of the latest registry version and prints the resulting diff.
+///
+/// Proposals that do not (or not only) modify the registry through its
+/// mutation code are not simulated.
+pub(crate) async fn simulate_registry_effect<C: CandidType>(
+    nns_function: NnsFunction,
+    payload: &C,
+    registry_canister: &RegistryCanister,
+) -> Result<(), String> {
+    let payload =
+        Encode!(payload).map_err(|e| format!("Couldn't encode the proposal payload: {}", e))?;
+    let mutation = match registry_mutation_for(nns_function, payload) {
+        Some(mutation) => mutation,
+        None => {
+            report_not_simulated(&format!("{:?}", nns_function));
+            return Ok(());
+        }
+    };
+
+    let (before, registry_version) = fetch_latest_snapshot(registry_canister).await?;
+
+    let (error, after) = {
+        // The registry canister code logs to stdout, which has to stay
+        // reserved for the result.
+        let _redirect = StdoutToStderr::new()
+            .map_err(|e| format!("Couldn't redirect stdout to stderr: {}", e))?;
+        let mut registry = Registry::from_snapshot(before.clone(), registry_version);
+        // The registry canister signals rejected mutations by panicking,
+        // which is why the panic has to be caught here.
+        let error = match panic::catch_unwind(AssertUnwindSafe(|| mutation(&mut registry))) {
+            Ok(result) => result.err(),
+            Err(panic) => Some(panic_message(panic)),
+        };
+        let after = registry.take_latest_snapshot();
+        let error = error.or_else(|| check_invariants(&after).err().map(|e| e.msg));
+        (error, after)
+    };
+
+    let result = DryRunResult {
+        registry_version,
+        invariants_hold: error.is_none(),
+        error,
+        diff: diff_snapshots(&before, &after),
+    };
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&result)
+            .map_err(|e| format!("Couldn't serialize the result: {}", e))?
+    );
+    Ok(())
+}
+
+/// Tells the user that proposals of the given type can't be simulated.
+pub(crate) fn report_not_simulated(proposal_type: &str) {
+    eprintln!(
+        "Simulation: {} proposals can't be simulated against the registry.",
+        proposal_type
+    );
+}
+
+/// Redirects stdout to stderr until dropped.
+struct StdoutToStderr {
+    stdout: RawFd,
+}
+
+impl StdoutToStderr {
+    fn new() -> io::Result<Self> {
+        io::stdout().flush()?;
+        // SAFETY: Only duplicates and replaces the standard file descriptors.
+        let stdout = unsafe { libc::dup(libc::STDOUT_FILENO) };
+        if stdout < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+            let err = io::Error::last_os_error();
+            unsafe { libc::close(stdout) };
+            return Err(err);
+        }
+        Ok(Self { stdout })
+    }
+}
+
+impl Drop for StdoutToStderr {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        // SAFETY: `self.stdout` is the duplicate of stdout created in `new`.
+        unsafe {
+            libc::dup2(self.stdout, libc::STDOUT_FILENO);
+            libc::close(self.stdout);
+        }
+    }
+}
+
+/// Returns the registry canister mutation that is executed when a proposal of
+/// type `nns_function` with the candid-encoded `payload` is adopted, if any.
+fn registry_mutation_for(
+    nns_function: NnsFunction,
+    payload: Vec<u8>,
+) -> Option<RegistryMutationFn> {
+    fn decode<T: CandidType + DeserializeOwned>(payload: &[u8]) -> T {
+        Decode!(payload, T).expect("Couldn't decode the proposal payload.")
+    }
+
+    macro_rules! mutation {
+        ($payload_type:ty, $method:ident) => {{
+            let payload: $payload_type = decode(&payload);
+            Box::new(move |registry: &mut Registry| {
+                registry.$method(payload);
+                Ok(())
+            })
+        }};
+    }
+
+    let mutation: RegistryMutationFn = match nns_function {
+        NnsFunction::AddNodeToSubnet => {
+            mutation!(AddNodesToSubnetPayload, do_add_nodes_to_subnet)
+        }
+        NnsFunction::RemoveNodesFromSubnet => {
+            mutation!(RemoveNodesFromSubnetPayload, do_remove_nodes_from_subnet)
+        }
+        NnsFunction::ChangeSubnetMembership => {
+            mutation!(ChangeSubnetMembershipPayload, do_change_subnet_membership)
+        }
+        NnsFunction::UpdateConfigOfSubnet => mutation!(UpdateSubnetPayload, do_update_subnet),
+        NnsFunction::UpdateSubnetReplicaVersion => mutation!(
+            UpdateSubnetReplicaVersionPayload,
+            do_update_subnet_replica_version
+        ),
+        NnsFunction::UpdateElectedReplicaVersions => mutation!(
+            UpdateElectedReplicaVersionsPayload,
+            do_update_elected_replica_versions
+        ),
+        NnsFunction::UpdateElectedHostosVersions => mutation!(
+            UpdateElectedHostosVersionsPayload,
+            do_update_elected_hostos_versions
+        ),
+        NnsFunction::UpdateNodesHostosVersion => {
+            mutation!(
+                UpdateNodesHostosVersionPayload,
+                do_update_nodes_hostos_version
+            )
+        }
+        NnsFunction::UpdateUnassignedNodesConfig => mutation!(
+            UpdateUnassignedNodesConfigPayload,
+            do_update_unassigned_nodes_config
+        ),
+        NnsFunction::AssignNoid => mutation!(AddNodeOperatorPayload, do_add_node_operator),
+        NnsFunction::UpdateNodeOperatorConfig => {
+            mutation!(
+                UpdateNodeOperatorConfigPayload,
+                do_update_node_operator_config
+            )
+        }
+        NnsFunction::RemoveNodeOperators => {
+            mutation!(RemoveNodeOperatorsPayload, do_remove_node_operators)
+        }
+        NnsFunction::RemoveNodes => mutation!(RemoveNodesPayload, do_remove_nodes),
+        NnsFunction::SetFirewallConfig => {
+            mutation!(SetFirewallConfigPayload, do_set_firewall_config)
+        }
+        NnsFunction::AddFirewallRules => mutation!(AddFirewallRulesPayload, do_add_firewall_rules),
+        NnsFunction::RemoveFirewallRules => {
+            mutation!(RemoveFirewallRulesPayload, do_remove_firewall_rules)
+        }
+        NnsFunction::UpdateFirewallRules => {
+            mutation!(UpdateFirewallRulesPayload, do_update_firewall_rules)
+        }
+        NnsFunction::AddOrRemoveDataCenters => mutation!(
+            AddOrRemoveDataCentersProposalPayload,
+            do_add_or_remove_data_centers
+        ),
+        NnsFunction::UpdateNodeRewardsTable => mutation!(
+            UpdateNodeRewardsTableProposalPayload,
+            do_update_node_rewards_table
+        ),
+        NnsFunction::ClearProvisionalWhitelist => Box::new(|registry: &mut Registry| {
+            registry.do_clear_provisional_whitelist();
+            Ok(())
+        }),
+        NnsFunction::AddApiBoundaryNode => {
+            mutation!(AddApiBoundaryNodePayload, do_add_api_boundary_node)
+        }
+        NnsFunction::RemoveApiBoundaryNodes => {
+            mutation!(RemoveApiBoundaryNodesPayload, do_remove_api_boundary_nodes)
+        }
+        NnsFunction::UpdateApiBoundaryNodeDomain => mutation!(
+            UpdateApiBoundaryNodeDomainPayload,
+            do_update_api_boundary_node_domain
+        ),
+        NnsFunction::UpdateApiBoundaryNodesVersion => mutation!(
+            UpdateApiBoundaryNodesVersionPayload,
+            do_update_api_boundary_nodes_version
+        ),
+        NnsFunction::RerouteCanisterRanges => {
+            let payload: RerouteCanisterRangesPayload = decode(&payload);
+            Box::new(move |registry: &mut Registry| registry.reroute_canister_ranges(payload))
+        }
+        NnsFunction::PrepareCanisterMigration => {
+            let payload: PrepareCanisterMigrationPayload = decode(&payload);
+            Box::new(move |registry: &mut Registry| registry.prepare_canister_migration(payload))
+        }
+        NnsFunction::CompleteCanisterMigration => {
+            let payload: CompleteCanisterMigrationPayload = decode(&payload);
+            Box::new(move |registry: &mut Registry| registry.complete_canister_migration(payload))
+        }
+        NnsFunction::SplitSubnet => {
+            let payload: SplitSubnetPayload = decode(&payload);
+            Box::new(move |registry: &mut Registry| registry.split_subnet(payload))
+        }
+        NnsFunction::UpdateSubnetSplittingStatus => {
+            let payload: UpdateSubnetSplittingStatusPayload = decode(&payload);
+            Box::new(move |registry: &mut Registry| {
+                registry.update_subnet_splitting_status(payload)
+            })
+        }
+        // Creating, recovering and deleting subnets involves calls to the
+        // management canister, which can't be simulated locally.
+        _ => return None,
+    };
+    Some(mutation)
+}
+
+/// Fetches all records of the latest registry version.
+async fn fetch_latest_snapshot(
+    registry_canister: &RegistryCanister,
+) -> Result<(RegistrySnapshot, u64), String> {
+    let latest_version = registry_canister
+        .get_latest_version()
+        .await
+        .map_err(|e| format!("Couldn't fetch the latest registry version: {:?}", e))?;
+
+    // get_changes_since caps the size of its responses, hence the loop.
+    let mut snapshot = RegistrySnapshot::new();
+    let mut current_version = 0;
+    while current_version < latest_version {
+        let (records, _) = registry_canister
+            .get_changes_since_as_transport_records(current_version)
+            .await
+            .map_err(|e| format!("Couldn't fetch registry records: {:?}", e))?;
+        let Some(last) = records.last() else {
+            break;
+        };
+        current_version = last.version.get();
+        for record in records {
+            match record.value {
+                Some(value) => snapshot.insert(record.key.into_bytes(), value),
+                None => snapshot.remove(record.key.as_bytes()),
+            };
+        }
+    }
+    Ok((snapshot, latest_version))
+}
+
+/// Returns the keys whose values differ between `before` and `after`.
+fn diff_snapshots(before: &RegistrySnapshot, after: &RegistrySnapshot) -> Vec<KeyDiff> {
+    let keys: BTreeSet<_> = before.keys().chain(after.keys()).collect();
+    keys.into_iter()
+        .filter(|key| before.get(*key) != after.get(*key))
+        .map(|key| {
+            let key_str = String::from_utf8_lossy(key).to_string();
+            KeyDiff {
+                before: before.get(key).map(|v| registry_value_to_json(&key_str, v)),
+                after: after.get(key).map(|v| registry_value_to_json(&key_str, v)),
+                key: key_str,
+            }
+        })
+        .collect()
+}
+
+/// Decodes the protobuf-encoded value of the given registry key into JSON.
+/// Values of unknown keys are printed as hex.
+fn registry_value_to_json(key: &str, value: &[u8]) -> Value {
+    fn decode<T: Message + Default + Serialize>(value: &[u8]) -> Value {
+        match T::decode(value) {
+            Ok(record) => serde_json::to_value(record).expect("Couldn't serialize the record."),
+            Err(_) => Value::String(hex::encode(value)),
+        }
+    }
+
+    if key.starts_with(SUBNET_RECORD_KEY_PREFIX) {
+        decode::<SubnetRecord>(value)
+    } else if key.starts_with(SUBNET_SPLITTING_RECORD_KEY_PREFIX) {
+        decode::<SubnetSplittingRecord>(value)
+    } else if key.starts_with(NODE_RECORD_KEY_PREFIX) {
+        decode::<NodeRecord>(value)
+    } else if key.starts_with(NODE_OPERATOR_RECORD_KEY_PREFIX) {
+        decode::<NodeOperatorRecord>(value)
+    } else if key.starts_with(REPLICA_VERSION_KEY_PREFIX) {
+        decode::<ReplicaVersionRecord>(value)
+    } else if key.starts_with(HOSTOS_VERSION_KEY_PREFIX) {
+        decode::<HostosVersionRecord>(value)
+    } else if key.starts_with(DATA_CENTER_KEY_PREFIX) {
+        decode::<DataCenterRecord>(value)
+    } else if key.starts_with(API_BOUNDARY_NODE_RECORD_KEY_PREFIX) {
+        decode::<ApiBoundaryNodeRecord>(value)
+    } else if key.starts_with(FIREWALL_RULES_RECORD_KEY_PREFIX) {
+        decode::<FirewallRuleSet>(value)
+    } else if key == make_subnet_list_record_key() {
+        decode::<SubnetListRecord>(value)
+    } else if key == make_blessed_replica_versions_key() {
+        decode::<BlessedReplicaVersions>(value)
+    } else if key == make_routing_table_record_key() {
+        decode::<RoutingTable>(value)
+    } else if key == make_canister_migrations_record_key() {
+        decode::<CanisterMigrations>(value)
+    } else if key == make_firewall_config_record_key() {
+        decode::<FirewallConfig>(value)
+    } else if key == make_unassigned_nodes_config_record_key() {
+        decode::<UnassignedNodesConfigRecord>(value)
+    } else if key == make_provisional_whitelist_record_key() {
+        decode::<ProvisionalWhitelist>(value)
+    } else if key == NODE_REWARDS_TABLE_KEY {
+        decode::<NodeRewardsTable>(value)
+    } else {
+        Value::String(hex::encode(value))
+    }
+}
+
+fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    panic
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_contains_only_changed_keys() {
+        let before: RegistrySnapshot = [
+            (b"unchanged".to_vec(), vec![1]),
+            (b"changed".to_vec(), vec![2]),
+            (b"deleted".to_vec(), vec![3]),
+        ]
+        .into_iter()
+        .collect();
+        let after: RegistrySnapshot = [
+            (b"unchanged".to_vec(), vec![1]),
+            (b"changed".to_vec(), vec![4]),
+            (b"added".to_vec(), vec![5]),
+        ]
+        .into_iter()
+        .collect();
+
+        let diff = diff_snapshots(&before, &after);
+
+        let summary: Vec<_> = diff
+            .iter()
+            .map(|d| (d.key.as_str(), d.before.clone(), d.after.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("added", None, Some(Value::String("05".into()))),
+                (
+                    "changed",
+                    Some(Value::String("02".into())),
+                    Some(Value::String("04".into()))
+                ),
+                ("deleted", Some(Value::String("03".into())), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn non_registry_proposals_are_not_simulated() {
+        assert!(registry_mutation_for(NnsFunction::NnsCanisterUpgrade, vec![]).is_none());
+        assert!(registry_mutation_for(NnsFunction::CreateSubnet, vec![]).is_none());
+    }
+}
@@ -2,7 +2,7 @@
 use anyhow::anyhow;
 use async_trait::async_trait;
 use candid::{CandidType, Decode, Encode, Principal};
-use clap::{Args, Parser};
+use clap::{ArgEnum, Args, Parser};
 use cycles_minting_canister::{
     ChangeSubnetTypeAssignmentArgs, SetAuthorizedSubnetworkListArgs, SubnetListWithType,
     UpdateSubnetTypeArgs,
@@ -150,6 +150,9 @@ use registry_canister::mutations::{
     node_management::do_remove_nodes::RemoveNodesPayload,
     prepare_canister_migration::PrepareCanisterMigrationPayload,
     reroute_canister_ranges::RerouteCanisterRangesPayload,
+    split_subnet::{
+        SplitSubnetPayload, SubnetSplittingStatusUpdate, UpdateSubnetSplittingStatusPayload,
+    },
 };
 use serde::{Deserialize, Serialize};
 use std::net::Ipv6Addr;
@@ -175,6 +178,7 @@ extern crate ic_admin_derive;
 
 extern crate chrono;
 
+mod dry_run;
 mod types;
 
 #[cfg(test)]
@@ -427,6 +431,10 @@ enum SubCommand {
     ProposeToCompleteCanisterMigration(ProposeToCompleteCanisterMigrationCmd),
     /// Get the latest canister migrations.
     GetCanisterMigrations,
+    /// Propose to split a subnet, moving canister ID ranges to a halted subnet.
+    ProposeToSplitSubnet(ProposeToSplitSubnetCmd),
+    /// Propose to record the completion of a step of a subnet split.
+    ProposeToUpdateSubnetSplittingStatus(ProposeToUpdateSubnetSplittingStatusCmd),
     /// Submits a proposal to add an SNS wasm (e.g. Governance, Ledger, etc) to the SNS-WASM NNS
     /// canister.
     ProposeToAddWasmToSnsWasm(ProposeToAddWasmToSnsWasmCmd),
@@ -546,6 +554,7 @@ pub trait ProposalMetadata {
     fn url(&self) -> String;
     fn proposer_and_sender(&self, sender: Sender) -> (NeuronId, Sender);
     fn is_dry_run(&self) -> bool;
+    fn is_simulation(&self) -> bool;
     fn is_json(&self) -> bool;
 }
 
@@ -3554,6 +3563,103 @@ impl ProposalPayload<CompleteCanisterMigrationPayload> for ProposeToCompleteCani
     }
 }
 
+/// Sub-command to submit a proposal to split a subnet.
+#[derive_common_proposal_fields]
+#[derive(ProposalMetadata, Parser)]
+struct ProposeToSplitSubnetCmd {
+    /// The subnet being split.
+    #[clap(long, required = true)]
+    source_subnet: PrincipalId,
+    /// The halted subnet taking over the specified canister ranges.
+    #[clap(long, required = true)]
+    destination_subnet: PrincipalId,
+    /// The list of canister ID ranges moving to the destination subnet.
+    #[clap(long, multiple_values(true), required = true)]
+    canister_id_ranges: Vec<CanisterIdRange>,
+}
+
+impl ProposalTitle for ProposeToSplitSubnetCmd {
+    fn title(&self) -> String {
+        match &self.proposal_title {
+            Some(title) => title.clone(),
+            None => format!(
+                "Split {} canister ranges off subnet {} into subnet {}",
+                self.canister_id_ranges.len(),
+                self.source_subnet,
+                self.destination_subnet
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl ProposalPayload<SplitSubnetPayload> for ProposeToSplitSubnetCmd {
+    async fn payload(&self, _: Url) -> SplitSubnetPayload {
+        SplitSubnetPayload {
+            source_subnet: SubnetId::from(self.source_subnet),
+            destination_subnet: SubnetId::from(self.destination_subnet),
+            canister_id_ranges: self.canister_id_ranges.clone(),
+        }
+    }
+}
+
+/// A completed step of a subnet split, see [SubnetSplittingStatusUpdate].
+#[derive(ArgEnum, Clone, Copy, Debug)]
+enum SubnetSplittingStep {
+    SourceHalted,
+    StatesSplit,
+    Completed,
+}
+
+/// Sub-command to submit a proposal to record the completion of a step of a
+/// subnet split.
+#[derive_common_proposal_fields]
+#[derive(ProposalMetadata, Parser)]
+struct ProposeToUpdateSubnetSplittingStatusCmd {
+    /// The subnet being split.
+    #[clap(long, required = true)]
+    source_subnet: PrincipalId,
+    /// The step of the split that was completed.
+    #[clap(long, arg_enum, required = true)]
+    status: SubnetSplittingStep,
+    /// The height of the CUP the source subnet halted at.
+    #[clap(long, required_if_eq("status", "source-halted"))]
+    cup_height: Option<u64>,
+}
+
+impl ProposalTitle for ProposeToUpdateSubnetSplittingStatusCmd {
+    fn title(&self) -> String {
+        match &self.proposal_title {
+            Some(title) => title.clone(),
+            None => format!(
+                "Record step {:?} of the split of subnet {}",
+                self.status, self.source_subnet
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl ProposalPayload<UpdateSubnetSplittingStatusPayload>
+    for ProposeToUpdateSubnetSplittingStatusCmd
+{
+    async fn payload(&self, _: Url) -> UpdateSubnetSplittingStatusPayload {
+        let status = match self.status {
+            SubnetSplittingStep::SourceHalted => SubnetSplittingStatusUpdate::SourceHalted {
+                cup_height: self
+                    .cup_height
+                    .expect("--cup-height is required with --status source-halted"),
+            },
+            SubnetSplittingStep::StatesSplit => SubnetSplittingStatusUpdate::StatesSplit,
+            SubnetSplittingStep::Completed => SubnetSplittingStatusUpdate::Completed,
+        };
+        UpdateSubnetSplittingStatusPayload {
+            source_subnet: SubnetId::from(self.source_subnet),
+            status,
+        }
+    }
+}
+
 /// Sub-command to submit a proposal to set the bitcoin configuration.
 #[derive_common_proposal_fields]
 #[derive(ProposalMetadata, Parser)]
@@ -4043,6 +4149,9 @@ async fn propose_to_create_service_nervous_system(
     };
     print_proposal(&proposal, &cmd);
 
+    if cmd.is_simulation() {
+        dry_run::report_not_simulated("CreateServiceNervousSystem");
+    }
     if is_dry_run {
         return;
     }
@@ -4525,6 +4634,8 @@ async fn main() {
             SubCommand::ProposeToStopCanister(_) => (),
             SubCommand::ProposeToStartCanister(_) => (),
             SubCommand::ProposeToRerouteCanisterRanges(_) => (),
+            SubCommand::ProposeToSplitSubnet(_) => (),
+            SubCommand::ProposeToUpdateSubnetSplittingStatus(_) => (),
             SubCommand::ProposeXdrIcpConversionRate(_) => (),
             SubCommand::ProposeToUpdateSnsSubnetIdsInSnsWasm(_) => (),
             SubCommand::ProposeToUpdateSnsDeployWhitelist(_) => (),
@@ -5531,6 +5642,36 @@ async fn main() {
             )
             .await;
         }
+        SubCommand::ProposeToSplitSubnet(cmd) => {
+            let (proposer, sender) = cmd.proposer_and_sender(sender);
+            propose_external_proposal_from_command(
+                cmd,
+                NnsFunction::SplitSubnet,
+                make_canister_client(
+                    reachable_nns_urls,
+                    opts.verify_nns_responses,
+                    opts.nns_public_key_pem_file,
+                    sender,
+                ),
+                proposer,
+            )
+            .await;
+        }
+        SubCommand::ProposeToUpdateSubnetSplittingStatus(cmd) => {
+            let (proposer, sender) = cmd.proposer_and_sender(sender);
+            propose_external_proposal_from_command(
+                cmd,
+                NnsFunction::UpdateSubnetSplittingStatus,
+                make_canister_client(
+                    reachable_nns_urls,
+                    opts.verify_nns_responses,
+                    opts.nns_public_key_pem_file,
+                    sender,
+                ),
+                proposer,
+            )
+            .await;
+        }
         SubCommand::GetCanisterMigrations => {
             print_and_get_last_value::<CanisterMigrations>(
                 make_canister_migrations_record_key().as_bytes().to_vec(),
@@ -5613,6 +5754,9 @@ async fn main() {
             if cmd.is_dry_run() {
                 let payload = cmd.payload(agent.url.clone()).await;
                 print_insert_sns_wasm_upgrade_path_entries_payload(payload);
+                if cmd.is_simulation() {
+                    dry_run::report_not_simulated("InsertSnsWasmUpgradePathEntries");
+                }
                 return;
             }
 
@@ -5985,6 +6129,7 @@ async fn propose_external_proposal_from_command<
     proposer: NeuronId,
 ) {
     let payload = cmd.payload(agent.url.clone()).await;
+    let registry_canister = RegistryCanister::new(vec![agent.url.clone()]);
     let canister_client = GovernanceCanisterClient(NnsCanisterClient::new(
         agent,
         GOVERNANCE_CANISTER_ID,
@@ -5993,6 +6138,14 @@ async fn propose_external_proposal_from_command<
 
     print_proposal(&payload, &cmd);
 
+    if cmd.is_simulation() {
+        if let Err(e) =
+            dry_run::simulate_registry_effect(nns_function, &payload, &registry_canister).await
+        {
+            eprintln!("Simulation of {} failed: {}", cmd.title(), e);
+            std::process::exit(1);
+        }
+    }
     if cmd.is_dry_run() {
         return;
     }
@@ -6542,6 +6695,9 @@ async fn propose_to_open_sns_token_swap(
         let payload = OpenSnsTokenSwap::from(&cmd);
         print_proposal(&payload, &cmd);
 
+        if cmd.is_simulation() {
+            dry_run::report_not_simulated("OpenSnsTokenSwap");
+        }
         if cmd.is_dry_run() {
             return Ok(None);
         }
@@ -6619,6 +6775,9 @@ async fn propose_to_add_or_remove_node_provider(
     let payload = AddOrRemoveNodeProvider { change };
     print_proposal(&payload, &cmd);
 
+    if cmd.is_simulation() {
+        dry_run::report_not_simulated("AddOrRemoveNodeProvider");
+    }
     if cmd.is_dry_run() {
         return;
     }
@@ -64,6 +64,7 @@ use registry_canister::{
         },
         prepare_canister_migration::PrepareCanisterMigrationPayload,
         reroute_canister_ranges::RerouteCanisterRangesPayload,
+        split_subnet::{SplitSubnetPayload, UpdateSubnetSplittingStatusPayload},
     },
     pb::v1::{
         GetSubnetForCanisterRequest, GetSubnetForCanisterResponse, NodeProvidersMonthlyXdrRewards,
@@ -826,6 +827,44 @@ fn complete_canister_migration_(payload: CompleteCanisterMigrationPayload) -> Re
     Ok(())
 }
 
+#[export_name = "canister_update split_subnet"]
+fn split_subnet() {
+    check_caller_is_governance_and_log("split_subnet");
+    over_may_reject(candid_one, |payload: SplitSubnetPayload| {
+        split_subnet_(payload)
+    });
+}
+
+#[candid_method(update, rename = "split_subnet")]
+fn split_subnet_(payload: SplitSubnetPayload) -> Result<(), String> {
+    if let Err(msg) = registry_mut().split_subnet(payload) {
+        println!("{} Reject: {}", LOG_PREFIX, msg);
+        return Err(msg);
+    }
+    recertify_registry();
+    Ok(())
+}
+
+#[export_name = "canister_update update_subnet_splitting_status"]
+fn update_subnet_splitting_status() {
+    check_caller_is_governance_and_log("update_subnet_splitting_status");
+    over_may_reject(candid_one, |payload: UpdateSubnetSplittingStatusPayload| {
+        update_subnet_splitting_status_(payload)
+    });
+}
+
+#[candid_method(update, rename = "update_subnet_splitting_status")]
+fn update_subnet_splitting_status_(
+    payload: UpdateSubnetSplittingStatusPayload,
+) -> Result<(), String> {
+    if let Err(msg) = registry_mut().update_subnet_splitting_status(payload) {
+        println!("{} Reject: {}", LOG_PREFIX, msg);
+        return Err(msg);
+    }
+    recertify_registry();
+    Ok(())
+}
+
 #[export_name = "canister_query get_node_providers_monthly_xdr_rewards"]
 fn get_node_providers_monthly_xdr_rewards() {
     check_caller_is_governance_and_log("get_node_providers_monthly_xdr_rewards");
@@ -3,7 +3,7 @@ use crate::{
     invariants::{
         api_boundary_node::check_api_boundary_node_invariants,
         assignment::check_node_assignment_invariants,
-        common::RegistrySnapshot,
+        common::{InvariantCheckError, RegistrySnapshot},
         crypto::check_node_crypto_keys_invariants,
         endpoint::check_endpoint_invariants,
         firewall::check_firewall_invariants,
@@ -63,49 +63,7 @@ impl Registry {
 
         let snapshot = self.take_latest_snapshot_with_mutations(mutations);
 
-        // Node invariants
-        // TODO(NNS1-202): re-enable this check when cd hourly test issues are sorted
-        // out.
-        // Note that for now, once a node record has been added, it MUST not be
-        // modified, as P2P and Transport rely on this data to stay the same
-
-        // Node Operator invariants
-        let mut result = check_node_operator_invariants(&snapshot, false);
-
-        // Crypto invariants
-        result = result.and(check_node_crypto_keys_invariants(&snapshot));
-
-        // Node assignment invariants
-        result = result.and(check_node_assignment_invariants(&snapshot));
-
-        // Routing Table invariants
-        result = result.and(check_routing_table_invariants(&snapshot));
-
-        // Canister migrations invariants
-        result = result.and(check_canister_migrations_invariants(&snapshot));
-
-        // Subnet invariants
-        result = result.and(check_subnet_invariants(&snapshot));
-
-        // Replica version invariants
-        result = result.and(check_replica_version_invariants(&snapshot));
-
-        // API Boundary Node invariant
-        result = result.and(check_api_boundary_node_invariants(&snapshot));
-
-        // HostOS version invariants
-        result = result.and(check_hostos_version_invariants(&snapshot));
-
-        // Endpoint invariants
-        result = result.and(check_endpoint_invariants(&snapshot, false));
-
-        // Firewall invariants
-        result = result.and(check_firewall_invariants(&snapshot));
-
-        // Unassigned node invariants
-        result = result.and(check_unassigned_nodes_config_invariants(&snapshot));
-
-        if let Err(e) = result {
+        if let Err(e) = check_invariants(&snapshot) {
             panic!(
                 "{} invariant check failed with message: {}",
                 LOG_PREFIX, e.msg
@@ -132,7 +90,8 @@ impl Registry {
         snapshot
     }
 
-    fn take_latest_snapshot(&self) -> RegistrySnapshot {
+    /// Returns the latest value of every (non-deleted) key in the registry.
+    pub fn take_latest_snapshot(&self) -> RegistrySnapshot {
         let mut snapshot = RegistrySnapshot::new();
 
         for (key, values) in self.store.iter() {
@@ -145,6 +104,58 @@ impl Registry {
     }
 }
 
+/// Runs all global state invariant checks against the given snapshot of the
+/// registry.
+///
+/// This is what the registry canister runs before committing any mutation;
+/// it is exposed so that off-chain tools (e.g. `ic-regedit`) can validate a
+/// registry state before writing it.
+pub fn check_invariants(snapshot: &RegistrySnapshot) -> Result<(), InvariantCheckError> {
+    // Node invariants
+    // TODO(NNS1-202): re-enable this check when cd hourly test issues are sorted
+    // out.
+    // Note that for now, once a node record has been added, it MUST not be
+    // modified, as P2P and Transport rely on this data to stay the same
+
+    // Node Operator invariants
+    let mut result = check_node_operator_invariants(snapshot, false);
+
+    // Crypto invariants
+    result = result.and(check_node_crypto_keys_invariants(snapshot));
+
+    // Node assignment invariants
+    result = result.and(check_node_assignment_invariants(snapshot));
+
+    // Routing Table invariants
+    result = result.and(check_routing_table_invariants(snapshot));
+
+    // Canister migrations invariants
+    result = result.and(check_canister_migrations_invariants(snapshot));
+
+    // Subnet invariants
+    result = result.and(check_subnet_invariants(snapshot));
+
+    // Replica version invariants
+    result = result.and(check_replica_version_invariants(snapshot));
+
+    // API Boundary Node invariant
+    result = result.and(check_api_boundary_node_invariants(snapshot));
+
+    // HostOS version invariants
+    result = result.and(check_hostos_version_invariants(snapshot));
+
+    // Endpoint invariants
+    result = result.and(check_endpoint_invariants(snapshot, false));
+
+    // Firewall invariants
+    result = result.and(check_firewall_invariants(snapshot));
+
+    // Unassigned node invariants
+    result = result.and(check_unassigned_nodes_config_invariants(snapshot));
+
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use crate::registry::EncodedVersion;
@@ -21,10 +21,10 @@ use ic_registry_keys::{
 /// A representation of the data held by the registry.
 /// It is kept in-memory only, for global consistency checks before mutations
 /// are finalized.
-pub(crate) type RegistrySnapshot = BTreeMap<Vec<u8>, Vec<u8>>;
+pub type RegistrySnapshot = BTreeMap<Vec<u8>, Vec<u8>>;
 
 #[derive(Debug)]
-pub(crate) struct InvariantCheckError {
+pub struct InvariantCheckError {
     pub msg: String,
     pub source: Option<Box<dyn error::Error + 'static>>,
 }
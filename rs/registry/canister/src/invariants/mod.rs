@@ -11,3 +11,6 @@ mod replica_version;
 mod routing_table;
 mod subnet;
 mod unassigned_nodes_config;
+
+pub use checks::check_invariants;
+pub(crate) use common::RegistrySnapshot;
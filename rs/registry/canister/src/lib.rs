@@ -9,3 +9,5 @@ pub mod pb;
 pub mod proto_on_wire;
 pub mod registry;
 pub mod registry_lifecycle;
+
+pub use invariants::check_invariants;
@@ -34,4 +34,5 @@ pub mod node_management;
 pub mod prepare_canister_migration;
 pub mod reroute_canister_ranges;
 mod routing_table;
+pub mod split_subnet;
 mod subnet;
@@ -0,0 +1,545 @@
+//! Registry records of subnet splits.
+//!
+//! A split moves a set of canister ID ranges from a source subnet to an
+//! existing (halted) destination subnet. The registry records the progress of
+//! the split and applies the routing changes of each step. The orchestrators
+//! of both subnets act on the record; the status updates and recovery CUPs are
+//! proposed by the operators (see the `ic-admin` subnet splitting commands):
+//!
+//!  1. `split_subnet` records the `canister_migrations` entries, instructs the
+//!     source subnet to halt at its next CUP height and creates the
+//!     `SubnetSplittingRecord` with status `Scheduled`.
+//!  2. Once the source subnet halted, `update_subnet_splitting_status` moves the
+//!     record to `SourceHalted` (recording the CUP height) and reroutes the
+//!     canister ranges to the destination subnet.
+//!  3. Seeing `SourceHalted`, the orchestrator of every node of both subnets
+//!     stops the replica and splits the node's state at the CUP height with
+//!     `state-tool split` (see `ic_state_manager::split`). The destination
+//!     nodes need a copy of the source subnet's checkpoint at that height to
+//!     do so. The replicas stay stopped until their subnet's recovery CUP.
+//!  4. The record is moved to `StatesSplit` and new CUPs for both subnets are
+//!     proposed via `recover_subnet`, on top of the rerouted ranges.
+//!  5. Once both subnets resumed, the record is moved to `Completed`, which
+//!     removes the `canister_migrations` entries and unhalts both subnets.
+use crate::{
+    mutations::common::{decode_registry_value, encode_or_panic},
+    registry::Registry,
+};
+use candid::CandidType;
+use ic_base_types::{subnet_id_into_protobuf, subnet_id_try_from_protobuf, SubnetId};
+use ic_protobuf::registry::routing_table::v1::{
+    self as pb, SubnetSplittingRecord, SubnetSplittingStatus,
+};
+use ic_registry_keys::{make_subnet_record_key, make_subnet_splitting_record_key};
+use ic_registry_routing_table::{are_disjoint, is_subset_of, CanisterIdRange, CanisterIdRanges};
+use ic_registry_transport::{pb::v1::RegistryMutation, update, upsert};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+impl Registry {
+    /// Schedules the split of `payload.source_subnet`, moving the given canister
+    /// ID ranges to `payload.destination_subnet`.
+    pub fn split_subnet(&mut self, payload: SplitSubnetPayload) -> Result<(), String> {
+        let mutations = self.split_subnet_mutations(payload)?;
+        self.maybe_apply_mutation_internal(mutations);
+        Ok(())
+    }
+
+    /// Makes the registry mutations that schedule the split described by
+    /// `payload`, without checking the global invariants or applying them.
+    pub fn split_subnet_mutations(
+        &self,
+        payload: SplitSubnetPayload,
+    ) -> Result<Vec<RegistryMutation>, String> {
+        let ranges = CanisterIdRanges::try_from(payload.canister_id_ranges.clone())
+            .map_err(|e| format!("canister ID ranges are not well formed: {:?}", e))?;
+
+        let source = payload.source_subnet;
+        let destination = payload.destination_subnet;
+        if source == destination {
+            return Err(format!("cannot split subnet {} into itself", source));
+        }
+
+        let version = self.latest_version();
+
+        self.get(&make_subnet_record_key(source).into_bytes(), version)
+            .ok_or_else(|| format!("source {} is not a known subnet", source))?;
+        self.get(&make_subnet_record_key(destination).into_bytes(), version)
+            .ok_or_else(|| format!("destination {} is not a known subnet", destination))?;
+        if let Some(record) = self.get_subnet_splitting_record(source, version) {
+            if record.status() != SubnetSplittingStatus::Completed {
+                return Err(format!("subnet {} is already being split", source));
+            }
+        }
+
+        // The destination subnet must not run before it has a state derived from
+        // the source subnet's state.
+        if !self.get_subnet_or_panic(destination).is_halted {
+            return Err(format!("destination {} must be halted", destination));
+        }
+
+        let routing_table = self.get_routing_table_or_panic(version);
+        if !routing_table.ranges(destination).is_empty() {
+            return Err(format!(
+                "destination {} already hosts canister ranges",
+                destination
+            ));
+        }
+        if !is_subset_of(ranges.iter(), routing_table.ranges(source).iter()) {
+            return Err(format!(
+                "not all canisters to be split off are hosted by the provided source subnet {}",
+                source
+            ));
+        }
+        if let Some(canister_migrations) = self.get_canister_migrations(version) {
+            if !are_disjoint(canister_migrations.ranges(), ranges.iter()) {
+                return Err(format!(
+                    "some of the canister in the given ranges {:?} are already being migrated",
+                    ranges
+                ));
+            }
+        }
+
+        let mut source_record = self.get_subnet_or_panic(source);
+        source_record.halt_at_cup_height = true;
+
+        let splitting_record = SubnetSplittingRecord {
+            destination_subnet_id: Some(subnet_id_into_protobuf(destination)),
+            canister_id_ranges: ranges
+                .iter()
+                .cloned()
+                .map(pb::CanisterIdRange::from)
+                .collect(),
+            status: SubnetSplittingStatus::Scheduled as i32,
+            cup_height: 0,
+        };
+
+        Ok(vec![
+            self.migrate_canister_ranges_mutation(version, ranges, source, destination),
+            update(
+                make_subnet_record_key(source),
+                encode_or_panic(&source_record),
+            ),
+            upsert(
+                make_subnet_splitting_record_key(source),
+                encode_or_panic(&splitting_record),
+            ),
+        ])
+    }
+
+    /// Advances the split of `payload.source_subnet` by one step.
+    pub fn update_subnet_splitting_status(
+        &mut self,
+        payload: UpdateSubnetSplittingStatusPayload,
+    ) -> Result<(), String> {
+        let mutations = self.update_subnet_splitting_status_mutations(payload)?;
+        self.maybe_apply_mutation_internal(mutations);
+        Ok(())
+    }
+
+    /// Makes the registry mutations that advance the split of
+    /// `payload.source_subnet` by one step, without checking the global
+    /// invariants or applying them.
+    pub fn update_subnet_splitting_status_mutations(
+        &self,
+        payload: UpdateSubnetSplittingStatusPayload,
+    ) -> Result<Vec<RegistryMutation>, String> {
+        let source = payload.source_subnet;
+        let version = self.latest_version();
+
+        let mut record = self
+            .get_subnet_splitting_record(source, version)
+            .ok_or_else(|| format!("subnet {} is not being split", source))?;
+        let destination = record
+            .destination_subnet_id
+            .clone()
+            .ok_or_else(|| format!("the split of subnet {} has no destination", source))
+            .and_then(|id| {
+                subnet_id_try_from_protobuf(id)
+                    .map_err(|e| format!("invalid destination subnet: {}", e))
+            })?;
+        let ranges = CanisterIdRanges::try_from(
+            record
+                .canister_id_ranges
+                .iter()
+                .cloned()
+                .map(CanisterIdRange::try_from)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("invalid canister ID range: {}", e))?,
+        )
+        .map_err(|e| format!("canister ID ranges are not well formed: {:?}", e))?;
+
+        let current = record.status();
+        let (expected, next) = match payload.status {
+            SubnetSplittingStatusUpdate::SourceHalted { .. } => (
+                SubnetSplittingStatus::Scheduled,
+                SubnetSplittingStatus::SourceHalted,
+            ),
+            SubnetSplittingStatusUpdate::StatesSplit => (
+                SubnetSplittingStatus::SourceHalted,
+                SubnetSplittingStatus::StatesSplit,
+            ),
+            SubnetSplittingStatusUpdate::Completed => (
+                SubnetSplittingStatus::StatesSplit,
+                SubnetSplittingStatus::Completed,
+            ),
+        };
+        if current != expected {
+            return Err(format!(
+                "cannot move the split of subnet {} from {:?} to {:?}",
+                source, current, next
+            ));
+        }
+
+        if let SubnetSplittingStatusUpdate::SourceHalted { cup_height: 0 } = payload.status {
+            return Err(format!(
+                "the split of subnet {} needs the height of the CUP the source halted at",
+                source
+            ));
+        }
+
+        let mut mutations: Vec<RegistryMutation> = vec![];
+        match payload.status {
+            SubnetSplittingStatusUpdate::SourceHalted { cup_height } => {
+                record.cup_height = cup_height;
+                mutations.push(self.reroute_canister_ranges_mutation(version, ranges, destination));
+            }
+            SubnetSplittingStatusUpdate::StatesSplit => {}
+            SubnetSplittingStatusUpdate::Completed => {
+                mutations.push(self.remove_canister_migrations_mutation(
+                    version,
+                    ranges,
+                    vec![source, destination],
+                ));
+                for subnet_id in [source, destination] {
+                    let mut subnet_record = self.get_subnet_or_panic(subnet_id);
+                    subnet_record.halt_at_cup_height = false;
+                    subnet_record.is_halted = false;
+                    mutations.push(update(
+                        make_subnet_record_key(subnet_id),
+                        encode_or_panic(&subnet_record),
+                    ));
+                }
+            }
+        }
+        record.status = next as i32;
+        mutations.push(update(
+            make_subnet_splitting_record_key(source),
+            encode_or_panic(&record),
+        ));
+
+        Ok(mutations)
+    }
+
+    /// Retrieves the record tracking the split of `source`, if any.
+    pub fn get_subnet_splitting_record(
+        &self,
+        source: SubnetId,
+        version: u64,
+    ) -> Option<SubnetSplittingRecord> {
+        self.get(make_subnet_splitting_record_key(source).as_bytes(), version)
+            .map(|registry_value| decode_registry_value(registry_value.value.clone()))
+    }
+}
+
+/// The argument for the `split_subnet` update call.
+#[derive(Debug, CandidType, Serialize, Deserialize)]
+pub struct SplitSubnetPayload {
+    /// The subnet being split.
+    pub source_subnet: SubnetId,
+    /// The (halted) subnet taking over the canisters in `canister_id_ranges`.
+    pub destination_subnet: SubnetId,
+    /// The canister ID ranges moving to the destination subnet.
+    pub canister_id_ranges: Vec<CanisterIdRange>,
+}
+
+/// The argument for the `update_subnet_splitting_status` update call.
+#[derive(Debug, CandidType, Serialize, Deserialize)]
+pub struct UpdateSubnetSplittingStatusPayload {
+    /// The subnet being split.
+    pub source_subnet: SubnetId,
+    /// The step of the split that was completed.
+    pub status: SubnetSplittingStatusUpdate,
+}
+
+/// A completed step of a subnet split.
+#[derive(Clone, Copy, Debug, CandidType, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SubnetSplittingStatusUpdate {
+    /// The source subnet halted at the CUP at `cup_height`.
+    SourceHalted { cup_height: u64 },
+    /// Both halves derived their states from the state at the CUP height.
+    StatesSplit,
+    /// Both subnets resumed from their new CUPs.
+    Completed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_helpers::{
+        add_fake_subnet, get_invariant_compliant_subnet_record, invariant_compliant_registry,
+        prepare_registry_with_nodes,
+    };
+    use ic_base_types::{CanisterId, PrincipalId};
+    use ic_registry_keys::make_routing_table_record_key;
+    use ic_registry_routing_table::RoutingTable;
+    use ic_test_utilities::types::ids::subnet_test_id;
+
+    fn range(start: u64, end: u64) -> CanisterIdRange {
+        CanisterIdRange {
+            start: CanisterId::from(start),
+            end: CanisterId::from(end),
+        }
+    }
+
+    /// Returns a registry with a source subnet hosting canisters `0..=511` and
+    /// a halted destination subnet hosting no canisters.
+    fn registry_with_source_and_halted_destination() -> (Registry, SubnetId, SubnetId) {
+        let mut registry = invariant_compliant_registry(0);
+        let (mutate_request, mut node_ids) = prepare_registry_with_nodes(1, 1);
+        registry.maybe_apply_mutation_internal(mutate_request.mutations);
+
+        let mut subnet_list_record = registry.get_subnet_list_record();
+        let source = SubnetId::from(
+            PrincipalId::try_from(subnet_list_record.subnets.get(0).unwrap()).unwrap(),
+        );
+        let destination = subnet_test_id(1000);
+        let mut destination_record =
+            get_invariant_compliant_subnet_record(vec![node_ids.pop().unwrap()]);
+        destination_record.is_halted = true;
+        registry.maybe_apply_mutation_internal(add_fake_subnet(
+            destination,
+            &mut subnet_list_record,
+            destination_record,
+        ));
+
+        let mut routing_table = RoutingTable::new();
+        routing_table.insert(range(0, 511), source).unwrap();
+        registry.maybe_apply_mutation_internal(vec![upsert(
+            make_routing_table_record_key(),
+            encode_or_panic(&pb::RoutingTable::from(routing_table)),
+        )]);
+
+        (registry, source, destination)
+    }
+
+    fn split_payload(source: SubnetId, destination: SubnetId) -> SplitSubnetPayload {
+        SplitSubnetPayload {
+            source_subnet: source,
+            destination_subnet: destination,
+            canister_id_ranges: vec![range(256, 511)],
+        }
+    }
+
+    fn update_status(
+        registry: &mut Registry,
+        source: SubnetId,
+        status: SubnetSplittingStatusUpdate,
+    ) -> Result<(), String> {
+        registry.update_subnet_splitting_status(UpdateSubnetSplittingStatusPayload {
+            source_subnet: source,
+            status,
+        })
+    }
+
+    fn splitting_status(registry: &Registry, source: SubnetId) -> SubnetSplittingStatus {
+        registry
+            .get_subnet_splitting_record(source, registry.latest_version())
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn split_subnet_goes_through_all_steps() {
+        let (mut registry, source, destination) = registry_with_source_and_halted_destination();
+
+        registry
+            .split_subnet(split_payload(source, destination))
+            .unwrap();
+        let version = registry.latest_version();
+        assert_eq!(
+            splitting_status(&registry, source),
+            SubnetSplittingStatus::Scheduled
+        );
+        assert!(registry.get_subnet_or_panic(source).halt_at_cup_height);
+        assert_eq!(
+            registry
+                .get_canister_migrations(version)
+                .unwrap()
+                .lookup(CanisterId::from(300)),
+            Some(vec![source, destination])
+        );
+        // Canisters are only rerouted once the source subnet halted.
+        assert_eq!(
+            registry
+                .get_routing_table_or_panic(version)
+                .route(CanisterId::from(300).get()),
+            Some(source)
+        );
+
+        update_status(
+            &mut registry,
+            source,
+            SubnetSplittingStatusUpdate::SourceHalted { cup_height: 500 },
+        )
+        .unwrap();
+        let version = registry.latest_version();
+        let record = registry
+            .get_subnet_splitting_record(source, version)
+            .unwrap();
+        assert_eq!(record.status(), SubnetSplittingStatus::SourceHalted);
+        assert_eq!(record.cup_height, 500);
+        let routing_table = registry.get_routing_table_or_panic(version);
+        assert_eq!(
+            routing_table.route(CanisterId::from(300).get()),
+            Some(destination)
+        );
+        assert_eq!(
+            routing_table.route(CanisterId::from(200).get()),
+            Some(source)
+        );
+
+        update_status(
+            &mut registry,
+            source,
+            SubnetSplittingStatusUpdate::StatesSplit,
+        )
+        .unwrap();
+        assert_eq!(
+            splitting_status(&registry, source),
+            SubnetSplittingStatus::StatesSplit
+        );
+
+        update_status(
+            &mut registry,
+            source,
+            SubnetSplittingStatusUpdate::Completed,
+        )
+        .unwrap();
+        let version = registry.latest_version();
+        assert_eq!(
+            splitting_status(&registry, source),
+            SubnetSplittingStatus::Completed
+        );
+        assert_eq!(
+            registry
+                .get_canister_migrations(version)
+                .unwrap()
+                .lookup(CanisterId::from(300)),
+            None
+        );
+        for subnet_id in [source, destination] {
+            let subnet_record = registry.get_subnet_or_panic(subnet_id);
+            assert!(!subnet_record.is_halted);
+            assert!(!subnet_record.halt_at_cup_height);
+        }
+    }
+
+    #[test]
+    fn split_subnet_requires_halted_destination() {
+        let (mut registry, source, destination) = registry_with_source_and_halted_destination();
+        let mut destination_record = registry.get_subnet_or_panic(destination);
+        destination_record.is_halted = false;
+        registry.maybe_apply_mutation_internal(vec![update(
+            make_subnet_record_key(destination),
+            encode_or_panic(&destination_record),
+        )]);
+
+        let err = registry
+            .split_subnet(split_payload(source, destination))
+            .unwrap_err();
+        assert!(err.contains("must be halted"), "{}", err);
+    }
+
+    #[test]
+    fn split_subnet_rejects_ranges_not_hosted_by_source() {
+        let (mut registry, source, destination) = registry_with_source_and_halted_destination();
+
+        let err = registry
+            .split_subnet(SplitSubnetPayload {
+                canister_id_ranges: vec![range(500, 600)],
+                ..split_payload(source, destination)
+            })
+            .unwrap_err();
+        assert!(err.contains("not all canisters"), "{}", err);
+        assert_eq!(
+            registry.get_subnet_splitting_record(source, registry.latest_version()),
+            None
+        );
+    }
+
+    #[test]
+    fn split_subnet_rejects_concurrent_split() {
+        let (mut registry, source, destination) = registry_with_source_and_halted_destination();
+        registry
+            .split_subnet(split_payload(source, destination))
+            .unwrap();
+
+        let err = registry
+            .split_subnet(split_payload(source, destination))
+            .unwrap_err();
+        assert!(err.contains("already being split"), "{}", err);
+    }
+
+    #[test]
+    fn update_subnet_splitting_status_rejects_skipped_steps() {
+        let (mut registry, source, destination) = registry_with_source_and_halted_destination();
+
+        let err = update_status(
+            &mut registry,
+            source,
+            SubnetSplittingStatusUpdate::StatesSplit,
+        )
+        .unwrap_err();
+        assert!(err.contains("is not being split"), "{}", err);
+
+        registry
+            .split_subnet(split_payload(source, destination))
+            .unwrap();
+        let version_before = registry.latest_version();
+
+        let err = update_status(
+            &mut registry,
+            source,
+            SubnetSplittingStatusUpdate::StatesSplit,
+        )
+        .unwrap_err();
+        assert!(err.contains("from Scheduled to StatesSplit"), "{}", err);
+        let err = update_status(
+            &mut registry,
+            source,
+            SubnetSplittingStatusUpdate::Completed,
+        )
+        .unwrap_err();
+        assert!(err.contains("from Scheduled to Completed"), "{}", err);
+
+        assert_eq!(registry.latest_version(), version_before);
+        assert_eq!(
+            splitting_status(&registry, source),
+            SubnetSplittingStatus::Scheduled
+        );
+    }
+
+    #[test]
+    fn update_subnet_splitting_status_rejects_zero_cup_height() {
+        let (mut registry, source, destination) = registry_with_source_and_halted_destination();
+        registry
+            .split_subnet(split_payload(source, destination))
+            .unwrap();
+        let version_before = registry.latest_version();
+
+        let err = update_status(
+            &mut registry,
+            source,
+            SubnetSplittingStatusUpdate::SourceHalted { cup_height: 0 },
+        )
+        .unwrap_err();
+        assert!(err.contains("needs the height of the CUP"), "{}", err);
+
+        assert_eq!(registry.latest_version(), version_before);
+        assert_eq!(
+            splitting_status(&registry, source),
+            SubnetSplittingStatus::Scheduled
+        );
+    }
+}
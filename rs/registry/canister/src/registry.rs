@@ -1,5 +1,6 @@
 use crate::{
     common::LOG_PREFIX,
+    invariants::RegistrySnapshot,
     pb::v1::{
         registry_stable_storage::Version as ReprVersion, ChangelogEntry, RegistryStableStorage,
     },
@@ -101,6 +102,28 @@ impl Registry {
         Self::default()
     }
 
+    /// Creates a registry whose latest version is `version` and that contains
+    /// exactly the records of `snapshot`. No invariants are checked.
+    ///
+    /// This allows off-chain tools to simulate mutations against a copy of the
+    /// registry (e.g. `ic-admin --dry-run`).
+    pub fn from_snapshot(snapshot: RegistrySnapshot, version: Version) -> Self {
+        let mut registry = Self::new();
+        let mutations = snapshot
+            .into_iter()
+            .map(|(key, value)| RegistryMutation {
+                mutation_type: Type::Upsert as i32,
+                key,
+                value,
+            })
+            .collect::<Vec<_>>();
+        if !mutations.is_empty() {
+            registry.version = version;
+            registry.apply_mutations_as_version(mutations, version);
+        }
+        registry
+    }
+
     /// Returns the deltas applied since `version`, exclusive; optionally
     /// limited to the subsequent `max_versions` (i.e. changes applied in
     /// versions `(version, version + max_versions]`).
@@ -1,7 +1,12 @@
 //! Implementation of the registry client. Calls to the API always return
 //! immediately. The provided data provider is polled periodically in the
 //! background when start_polling() is called.
-use crossbeam_channel::{RecvTimeoutError, Sender, TrySendError};
+//!
+//! Components that need to react to registry changes can [subscribe] to a set
+//! of key prefixes instead of polling the client themselves.
+//!
+//! [subscribe]: RegistryClientImpl::subscribe
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, TrySendError};
 pub use ic_interfaces_registry::{
     empty_zero_registry_record, RegistryClient, RegistryClientVersionedResult,
     RegistryDataProvider, RegistryTransportRecord, POLLING_PERIOD, ZERO_REGISTRY_VERSION,
@@ -14,8 +19,8 @@ pub use ic_types::{
     RegistryVersion, Time,
 };
 use ic_utils::thread::JoinOnDrop;
-use std::sync::{Arc, RwLock, RwLockReadGuard};
-use std::{collections::BTreeMap, thread::JoinHandle};
+use std::sync::{Arc, RwLock, RwLockReadGuard, Weak};
+use std::{collections::BTreeMap, ops::Deref, thread::JoinHandle};
 
 use crate::metrics::Metrics;
 
@@ -25,6 +30,88 @@ pub struct RegistryClientImpl {
     data_provider: Arc<dyn RegistryDataProvider>,
     metrics: Arc<Metrics>,
     poll_thread: Arc<RwLock<Option<PollThread>>>,
+    subscribers: Arc<RwLock<Vec<Subscriber>>>,
+}
+
+/// A batch of registry changes delivered to a subscriber, see
+/// [`RegistryClientImpl::subscribe`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegistryUpdate {
+    /// The registry version the client advanced to.
+    pub version: RegistryVersion,
+    /// The records that changed since the previously delivered update and
+    /// whose key matches one of the subscribed prefixes, sorted by key and
+    /// version. A record with `value == None` denotes a deletion.
+    pub records: Vec<RegistryTransportRecord>,
+}
+
+impl RegistryUpdate {
+    /// Merges `newer` into this update, keeping only the latest record of
+    /// each key.
+    fn merge(self, newer: RegistryUpdate) -> RegistryUpdate {
+        let records: BTreeMap<_, _> = self
+            .records
+            .into_iter()
+            .chain(newer.records)
+            .map(|r| (r.key.clone(), r))
+            .collect();
+        RegistryUpdate {
+            version: newer.version,
+            records: records.into_values().collect(),
+        }
+    }
+}
+
+/// The receiving end of a subscription, see [`RegistryClientImpl::subscribe`].
+/// Dereferences to the channel on which the updates are delivered.
+///
+/// The subscription is removed once this is dropped.
+pub struct RegistrySubscription {
+    receiver: Receiver<RegistryUpdate>,
+    _alive: Arc<()>,
+}
+
+impl Deref for RegistrySubscription {
+    type Target = Receiver<RegistryUpdate>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.receiver
+    }
+}
+
+struct Subscriber {
+    key_prefixes: Vec<String>,
+    sender: Sender<RegistryUpdate>,
+    /// Used to take back an update that was not received yet.
+    receiver: Receiver<RegistryUpdate>,
+    alive: Weak<()>,
+}
+
+impl Subscriber {
+    fn matches(&self, key: &str) -> bool {
+        self.key_prefixes
+            .iter()
+            .any(|prefix| key.starts_with(prefix.as_str()))
+    }
+
+    fn is_subscribed(&self) -> bool {
+        self.alive.strong_count() > 0
+    }
+
+    /// Sends `update`, merging it with the previous update if that one was
+    /// not received yet, so that at most one update is queued.
+    fn send(&self, update: RegistryUpdate) {
+        if let Err(TrySendError::Full(update)) = self.sender.try_send(update) {
+            // Updates are only sent while holding the write lock on the
+            // subscribers, so the channel has room after taking back the
+            // pending update (or after the subscriber received it).
+            let update = match self.receiver.try_recv() {
+                Ok(pending) => pending.merge(update),
+                Err(_) => update,
+            };
+            let _ = self.sender.try_send(update);
+        }
+    }
 }
 
 /// RegistryClientImpl polls the data provider and caches the received results.
@@ -44,9 +131,72 @@ impl RegistryClientImpl {
             data_provider,
             metrics,
             poll_thread: Arc::new(RwLock::new(None)),
+            subscribers: Arc::new(RwLock::new(vec![])),
         }
     }
 
+    /// Registers for changes to records whose key starts with any of the
+    /// given `key_prefixes`.
+    ///
+    /// Whenever the client advances to a new registry version, either through
+    /// the background polling thread or through an explicit call to
+    /// `poll_once()`, an update containing the new version and all changed
+    /// records matching the prefixes is sent on the returned channel. No
+    /// update is sent if none of the changed records match. Changes made
+    /// before the subscription was registered are not delivered; subscribers
+    /// should read the current values through the [`RegistryClient`] API at
+    /// `get_latest_version()` after subscribing.
+    ///
+    /// At most one update is queued per subscriber: if the subscriber has not
+    /// received the previous update yet, the new update is merged into it,
+    /// keeping only the latest record of each key.
+    ///
+    /// The subscription is removed once the returned subscription is dropped.
+    pub fn subscribe(&self, key_prefixes: Vec<String>) -> RegistrySubscription {
+        let (sender, receiver) = crossbeam_channel::bounded(1);
+        let alive = Arc::new(());
+        let mut subscribers = self.subscribers.write().unwrap();
+        subscribers.push(Subscriber {
+            key_prefixes,
+            sender,
+            receiver: receiver.clone(),
+            alive: Arc::downgrade(&alive),
+        });
+        self.metrics.subscriptions.set(subscribers.len() as i64);
+        RegistrySubscription {
+            receiver,
+            _alive: alive,
+        }
+    }
+
+    /// Sends the matching subset of `records` to every subscriber and drops
+    /// the subscribers whose receiver is gone.
+    fn notify_subscribers(&self, records: &[RegistryTransportRecord], version: RegistryVersion) {
+        let mut subscribers = self.subscribers.write().unwrap();
+        if subscribers.is_empty() {
+            return;
+        }
+        subscribers.retain(|subscriber| {
+            if !subscriber.is_subscribed() {
+                return false;
+            }
+            let mut matching: Vec<_> = records
+                .iter()
+                .filter(|r| subscriber.matches(&r.key))
+                .cloned()
+                .collect();
+            if !matching.is_empty() {
+                matching.sort_by(|a, b| (&a.key, a.version).cmp(&(&b.key, b.version)));
+                subscriber.send(RegistryUpdate {
+                    version,
+                    records: matching,
+                });
+            }
+            true
+        });
+        self.metrics.subscriptions.set(subscribers.len() as i64);
+    }
+
     /// Calls `poll_once()` synchronously, if it succeeds a background task is
     /// spawned that continuously polls for updates.
     /// The background task is stopped when the object is dropped.
@@ -107,6 +257,9 @@ impl RegistryClientImpl {
         // Check version again under write lock, to prevent race conditions.
         if version > cache_state.latest_version {
             self.metrics.registry_version.set(version.get() as i64);
+            // Subscribers are notified while holding the write lock, so that
+            // concurrent polls deliver updates in version order.
+            self.notify_subscribers(&records, version);
             cache_state.update(records, version);
         }
         Ok(())
@@ -533,6 +686,110 @@ mod tests {
         assert!(data_provider.poll_counter.load(Ordering::Relaxed) > 0);
     }
 
+    #[test]
+    fn subscribers_receive_matching_updates_with_version() {
+        let data_provider = Arc::new(ProtoRegistryDataProvider::new());
+        let registry = RegistryClientImpl::new(data_provider.clone(), None);
+        let set = |key: &str, ver: u64| data_provider.add(key, v(ver), Some(value(ver))).unwrap();
+        let rem = |key: &str, ver: u64| data_provider.add::<TestProto>(key, v(ver), None).unwrap();
+
+        let receiver = registry.subscribe(vec!["FA_".to_string(), "B".to_string()]);
+
+        set("A", 1);
+        set("FA_1", 1);
+        set("FB_1", 2);
+        registry.poll_once().unwrap();
+
+        let update = receiver.try_recv().unwrap();
+        assert_eq!(update.version, v(2));
+        assert_eq!(
+            update
+                .records
+                .iter()
+                .map(|r| &r.key[..])
+                .collect::<Vec<_>>(),
+            vec!["FA_1"]
+        );
+        assert!(receiver.try_recv().is_err());
+
+        // No update is delivered if none of the changed keys match.
+        set("C", 3);
+        registry.poll_once().unwrap();
+        assert!(receiver.try_recv().is_err());
+
+        set("B", 4);
+        rem("FA_1", 4);
+        registry.poll_once().unwrap();
+
+        let update = receiver.try_recv().unwrap();
+        assert_eq!(update.version, v(4));
+        assert_eq!(
+            update
+                .records
+                .iter()
+                .map(|r| (&r.key[..], r.version, r.value.is_some()))
+                .collect::<Vec<_>>(),
+            vec![("B", v(4), true), ("FA_1", v(4), false)]
+        );
+    }
+
+    #[test]
+    fn updates_not_received_yet_are_merged() {
+        let data_provider = Arc::new(ProtoRegistryDataProvider::new());
+        let registry = RegistryClientImpl::new(data_provider.clone(), None);
+        let set = |key: &str, ver: u64| data_provider.add(key, v(ver), Some(value(ver))).unwrap();
+        let rem = |key: &str, ver: u64| data_provider.add::<TestProto>(key, v(ver), None).unwrap();
+
+        let receiver = registry.subscribe(vec!["A".to_string(), "B".to_string()]);
+
+        set("A", 1);
+        set("B", 1);
+        registry.poll_once().unwrap();
+        set("A", 2);
+        rem("B", 3);
+        registry.poll_once().unwrap();
+        set("C", 4);
+        registry.poll_once().unwrap();
+
+        let update = receiver.try_recv().unwrap();
+        assert_eq!(update.version, v(3));
+        assert_eq!(
+            update
+                .records
+                .iter()
+                .map(|r| (&r.key[..], r.version, r.value.is_some()))
+                .collect::<Vec<_>>(),
+            vec![("A", v(2), true), ("B", v(3), false)]
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn dropped_subscribers_are_removed() {
+        use ic_test_utilities_metrics::fetch_int_gauge;
+
+        let data_provider = Arc::new(ProtoRegistryDataProvider::new());
+        let metrics_registry = MetricsRegistry::new();
+        let registry = RegistryClientImpl::new(data_provider.clone(), Some(&metrics_registry));
+
+        let receiver = registry.subscribe(vec!["A".to_string()]);
+        let _other = registry.subscribe(vec!["B".to_string()]);
+        assert_eq!(
+            fetch_int_gauge(&metrics_registry, "ic_registry_client_subscriptions"),
+            Some(2)
+        );
+
+        drop(receiver);
+        data_provider.add("A", v(1), Some(value(1))).unwrap();
+        registry.poll_once().unwrap();
+
+        assert_eq!(registry.subscribers.read().unwrap().len(), 1);
+        assert_eq!(
+            fetch_int_gauge(&metrics_registry, "ic_registry_client_subscriptions"),
+            Some(1)
+        );
+    }
+
     #[test]
     fn polling_for_latest_version_fails_for_insufficient_retries() {
         let data_provider = Arc::new(ProtoRegistryDataProvider::new());
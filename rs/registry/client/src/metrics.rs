@@ -8,6 +8,8 @@ pub(crate) struct Metrics {
     /// Most recent registry version fetched by the client
     pub(crate) registry_version: IntGauge,
     pub(crate) api_call_duration: HistogramVec,
+    /// Number of active subscriptions to registry updates
+    pub(crate) subscriptions: IntGauge,
 }
 
 impl Metrics {
@@ -25,6 +27,11 @@ impl Metrics {
                 "ic_registry_client_registry_version",
                 "Most recent registry version fetched by the client",
             ),
+
+            subscriptions: r.int_gauge(
+                "ic_registry_client_subscriptions",
+                "Number of active subscriptions to registry updates",
+            ),
         }
     }
 }
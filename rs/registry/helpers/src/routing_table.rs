@@ -1,10 +1,16 @@
 use crate::deserialize_registry_value;
+use ic_base_types::subnet_id_into_protobuf;
 use ic_interfaces_registry::{RegistryClient, RegistryClientResult};
 use ic_protobuf::registry::routing_table::v1 as pb;
-use ic_registry_keys::{make_canister_migrations_record_key, make_routing_table_record_key};
+use ic_registry_keys::{
+    make_canister_migrations_record_key, make_routing_table_record_key,
+    make_subnet_splitting_record_key, SUBNET_SPLITTING_RECORD_KEY_PREFIX,
+};
 use ic_registry_routing_table::{CanisterIdRange, CanisterMigrations, RoutingTable};
-use ic_types::{registry::RegistryClientError::DecodeError, RegistryVersion, SubnetId};
-use std::convert::TryFrom;
+use ic_types::{
+    registry::RegistryClientError::DecodeError, PrincipalId, RegistryVersion, SubnetId,
+};
+use std::{convert::TryFrom, str::FromStr};
 
 /// A trait that allows access to `RoutingTable`.  The expectation for the
 /// foreseeable future is that the `RoutingTable` will remain small enough so
@@ -20,6 +26,21 @@ pub trait RoutingTableRegistry {
         &self,
         version: RegistryVersion,
     ) -> RegistryClientResult<CanisterMigrations>;
+
+    /// Returns the record tracking the split of `source_subnet_id`, if any.
+    fn get_subnet_splitting_record(
+        &self,
+        source_subnet_id: SubnetId,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<pb::SubnetSplittingRecord>;
+
+    /// Returns the source subnet and the record of the split `subnet_id` takes
+    /// part in, as the source or as the destination, if any.
+    fn get_subnet_splitting_record_involving(
+        &self,
+        subnet_id: SubnetId,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<(SubnetId, pb::SubnetSplittingRecord)>;
 }
 
 impl<T: RegistryClient + ?Sized> RoutingTableRegistry for T {
@@ -70,4 +91,40 @@ impl<T: RegistryClient + ?Sized> RoutingTableRegistry for T {
             },
         )?
     }
+
+    fn get_subnet_splitting_record(
+        &self,
+        source_subnet_id: SubnetId,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<pb::SubnetSplittingRecord> {
+        let bytes = self.get_value(&make_subnet_splitting_record_key(source_subnet_id), version);
+        deserialize_registry_value::<pb::SubnetSplittingRecord>(bytes)
+    }
+
+    fn get_subnet_splitting_record_involving(
+        &self,
+        subnet_id: SubnetId,
+        version: RegistryVersion,
+    ) -> RegistryClientResult<(SubnetId, pb::SubnetSplittingRecord)> {
+        if let Some(record) = self.get_subnet_splitting_record(subnet_id, version)? {
+            return Ok(Some((subnet_id, record)));
+        }
+
+        let destination = Some(subnet_id_into_protobuf(subnet_id));
+        for key in self.get_key_family(SUBNET_SPLITTING_RECORD_KEY_PREFIX, version)? {
+            let source = key
+                .strip_prefix(SUBNET_SPLITTING_RECORD_KEY_PREFIX)
+                .and_then(|id| PrincipalId::from_str(id).ok())
+                .map(SubnetId::from)
+                .ok_or_else(|| DecodeError {
+                    error: format!("invalid subnet splitting record key {}", key),
+                })?;
+            if let Some(record) = self.get_subnet_splitting_record(source, version)? {
+                if record.destination_subnet_id == destination {
+                    return Ok(Some((source, record)));
+                }
+            }
+        }
+        Ok(None)
+    }
 }
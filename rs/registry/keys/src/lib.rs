@@ -28,6 +28,7 @@ pub const NODE_OPERATOR_RECORD_KEY_PREFIX: &str = "node_operator_record_";
 pub const REPLICA_VERSION_KEY_PREFIX: &str = "replica_version_";
 pub const HOSTOS_VERSION_KEY_PREFIX: &str = "hostos_version_";
 pub const SUBNET_RECORD_KEY_PREFIX: &str = "subnet_record_";
+pub const SUBNET_SPLITTING_RECORD_KEY_PREFIX: &str = "subnet_splitting_";
 pub const CRYPTO_RECORD_KEY_PREFIX: &str = "crypto_record_";
 pub const CRYPTO_TLS_CERT_KEY_PREFIX: &str = "crypto_tls_cert_";
 pub const CRYPTO_THRESHOLD_SIGNING_KEY_PREFIX: &str = "crypto_threshold_signing_public_key_";
@@ -104,7 +105,7 @@ pub fn make_firewall_config_record_key() -> String {
     "firewall_config".to_string()
 }
 
-const FIREWALL_RULES_RECORD_KEY_PREFIX: &str = "firewall_rules_";
+pub const FIREWALL_RULES_RECORD_KEY_PREFIX: &str = "firewall_rules_";
 const FIREWALL_RULES_SCOPE_GLOBAL: &str = "global";
 const FIREWALL_RULES_SCOPE_REPLICA_NODES: &str = "replica_nodes";
 const FIREWALL_RULES_SCOPE_SUBNET_PREFIX: &str = "subnet";
@@ -302,6 +303,11 @@ pub fn make_subnet_record_key(subnet_id: SubnetId) -> String {
     format!("{}{}", SUBNET_RECORD_KEY_PREFIX, subnet_id)
 }
 
+/// Makes a key for the SubnetSplittingRecord of a subnet being split.
+pub fn make_subnet_splitting_record_key(source_subnet_id: SubnetId) -> String {
+    format!("{}{}", SUBNET_SPLITTING_RECORD_KEY_PREFIX, source_subnet_id)
+}
+
 /// Makes a key for a crypto key registry entry for a node.
 pub fn make_crypto_node_key(node_id: NodeId, key_purpose: KeyPurpose) -> String {
     format!(
@@ -83,6 +83,17 @@ pub enum CommandArg {
         #[clap(long)]
         amend: bool,
 
+        /// Comma-separated list of key prefixes. If provided, only keys
+        /// matching one of the prefixes are updated or deleted, i.e. the
+        /// snapshot may have been produced with the same --keys filter.
+        #[clap(short, long)]
+        keys: Option<String>,
+
+        /// Check the global state invariants of the registry canister against
+        /// the updated registry before anything is written.
+        #[clap(long)]
+        check_invariants: bool,
+
         /// Path to the local store (may not be specified together with --url).
         #[clap(parse(from_os_str))]
         local_store_path: PathBuf,
@@ -191,14 +202,19 @@ impl CliArgs {
                 local_store_path,
                 snapshot_file,
                 amend,
+                keys,
+                check_invariants,
             } => {
                 let local_store_path = Self::is_dir(local_store_path)?;
                 let snapshot = Self::read_json_value(snapshot_file)?;
+                let projection = Self::keys_to_projection(keys);
 
                 Command::ApplyUpdate {
                     local_store_path,
                     snapshot,
                     amend,
+                    projection,
+                    check_invariants,
                 }
             }
             CommandArg::CanisterSnapshot {
@@ -327,6 +343,8 @@ pub enum Command {
         local_store_path: PathBuf,
         snapshot: Value,
         amend: bool,
+        projection: Projection,
+        check_invariants: bool,
     },
 }
 
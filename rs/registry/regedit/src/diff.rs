@@ -69,6 +69,27 @@ pub fn make_diff(base_snapshot: Snapshot, new_snapshot: Snapshot) -> Result<Diff
     Ok(Diff(json::assert_to_value(&res)))
 }
 
+/// Returns the snapshot that results from applying `diff` to `base_snapshot`.
+pub fn apply_diff(base_snapshot: &Snapshot, diff: &Diff) -> Result<Snapshot> {
+    let mut res = base_snapshot
+        .0
+        .as_object()
+        .ok_or_else(|| DiffErr::InvalidJsonValue("Base Snapshot is not an Object".into()))?
+        .clone();
+    let diff = diff
+        .0
+        .as_object()
+        .ok_or_else(|| DiffErr::InvalidJsonValue("Diff is not an Object.".into()))?;
+    for (k, v) in diff.iter() {
+        if is_deleted_marker(v) {
+            res.remove(k);
+        } else {
+            res.insert(k.clone(), v.clone());
+        }
+    }
+    Ok(Snapshot(Value::Object(res)))
+}
+
 pub fn diff_to_changelog_entry(diff: Diff) -> Result<(RegistryVersion, ChangelogEntry)> {
     let v = RegistryVersion::from(snapshot_to_version(&diff.0)?);
 
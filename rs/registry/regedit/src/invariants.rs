@@ -0,0 +1,60 @@
+use crate::{protobuf, snapshot::SPECIAL_FIELD_PREFIX};
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    panic::{self, UnwindSafe},
+};
+use thiserror::Error;
+
+/// Checks the global state invariants of the registry canister against the
+/// given (expanded) snapshot of the complete registry.
+///
+/// Decoding the snapshot and some of the invariant checks panic instead of
+/// returning an error. Such panics are caught and reported as an
+/// [InvariantErr] as well.
+pub fn check_invariants(snapshot: &Value) -> Result<()> {
+    let registry_snapshot = catch_panic(|| to_registry_snapshot(snapshot))
+        .map_err(|msg| anyhow!(InvariantErr::InvalidJsonValue(msg)))??;
+    catch_panic(|| registry_canister::check_invariants(&registry_snapshot))
+        .map_err(|msg| anyhow!(InvariantErr::Violated(msg)))?
+        .map_err(|e| anyhow!(InvariantErr::Violated(e.msg)))
+}
+
+/// Converts a snapshot into the raw key/value representation used by the
+/// registry canister.
+fn to_registry_snapshot(snapshot: &Value) -> Result<BTreeMap<Vec<u8>, Vec<u8>>> {
+    let obj = snapshot
+        .as_object()
+        .ok_or_else(|| anyhow!(InvariantErr::InvalidJsonValue("Expected object.".into())))?;
+    Ok(obj
+        .iter()
+        .filter(|(k, _)| !k.starts_with(SPECIAL_FIELD_PREFIX))
+        .map(|(k, v)| {
+            (
+                k.as_bytes().to_vec(),
+                protobuf::value_to_raw_data(k, v.clone()),
+            )
+        })
+        .collect())
+}
+
+/// Runs `f`, returning the message of the panic if it panics.
+fn catch_panic<T>(f: impl FnOnce() -> T + UnwindSafe) -> Result<T, String> {
+    panic::catch_unwind(f).map_err(|panic| {
+        panic
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_else(|| "unknown panic".to_string())
+    })
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum InvariantErr {
+    #[error("Registry invariants violated: {0}")]
+    Violated(String),
+
+    #[error("Invalid object structure: {0}")]
+    InvalidJsonValue(String),
+}
@@ -1,5 +1,6 @@
 pub mod args;
 mod diff;
+mod invariants;
 mod json;
 mod normalization;
 mod projection;
@@ -112,6 +113,8 @@ pub fn execute_command(cmd: Command) -> Result<Value> {
             local_store_path,
             snapshot,
             amend,
+            projection,
+            check_invariants,
         } => {
             let base_snapshot = registry_spec_to_snapshot(RegistrySpec {
                 source: SourceSpec::LocalStore(local_store_path.clone()),
@@ -120,7 +123,17 @@ pub fn execute_command(cmd: Command) -> Result<Value> {
 
             let (_, inv_map) = normalization::normalize(base_snapshot.0.clone());
             let expanded_snapshot = normalization::expand(&inv_map, NormalizedSnapshot(snapshot));
-            let diff = diff::make_diff(base_snapshot, expanded_snapshot)?;
+            // Keys outside of the projection are neither changed nor deleted.
+            let diff = diff::make_diff(
+                Snapshot(projection::project(
+                    base_snapshot.0.clone(),
+                    projection.clone(),
+                )),
+                Snapshot(projection::project(expanded_snapshot.0, projection)),
+            )?;
+            if check_invariants {
+                invariants::check_invariants(&diff::apply_diff(&base_snapshot, &diff)?.0)?;
+            }
             let (v, changelog_entry) = diff::diff_to_changelog_entry(diff.clone())?;
 
             let local_store = LocalStoreImpl::new(&local_store_path);
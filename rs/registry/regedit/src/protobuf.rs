@@ -10,7 +10,7 @@ use ic_protobuf::{
         node_operator::v1::NodeOperatorRecord,
         provisional_whitelist::v1::ProvisionalWhitelist,
         replica_version::v1::{BlessedReplicaVersions, ReplicaVersionRecord},
-        routing_table::v1::{CanisterMigrations, RoutingTable},
+        routing_table::v1::{CanisterMigrations, RoutingTable, SubnetSplittingRecord},
         subnet::v1::{CatchUpPackageContents, SubnetListRecord, SubnetRecord},
     },
     types::v1::SubnetId as SubnetIdProto,
@@ -23,6 +23,7 @@ use ic_registry_keys::{
     make_subnet_list_record_key, CRYPTO_RECORD_KEY_PREFIX, CRYPTO_THRESHOLD_SIGNING_KEY_PREFIX,
     CRYPTO_TLS_CERT_KEY_PREFIX, NODE_OPERATOR_RECORD_KEY_PREFIX, NODE_RECORD_KEY_PREFIX,
     REPLICA_VERSION_KEY_PREFIX, ROOT_SUBNET_ID_KEY, SUBNET_RECORD_KEY_PREFIX,
+    SUBNET_SPLITTING_RECORD_KEY_PREFIX,
 };
 pub(crate) trait Transformable {
     fn pb_to_value(data: &[u8]) -> Value;
@@ -80,6 +81,8 @@ fn get_transformer(key: &str) -> Transformers {
         ReplicaVersionRecord::transformers()
     } else if key.starts_with(SUBNET_RECORD_KEY_PREFIX) {
         SubnetRecord::transformers()
+    } else if key.starts_with(SUBNET_SPLITTING_RECORD_KEY_PREFIX) {
+        SubnetSplittingRecord::transformers()
     } else if key.starts_with(CRYPTO_RECORD_KEY_PREFIX) {
         PublicKey::transformers()
     } else if key.starts_with(CRYPTO_TLS_CERT_KEY_PREFIX) {
@@ -11,9 +11,11 @@ use ic_prep_lib::{
     prep_state_directory::IcPrepStateDir,
     subnet_configuration::{SubnetConfig, SubnetRunningState},
 };
+use ic_registry_keys::SUBNET_RECORD_KEY_PREFIX;
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_registry_subnet_type::SubnetType;
 use ic_types::ReplicaVersion;
+use serde_json::{Map, Value};
 use std::{
     collections::{BTreeMap, HashSet},
     net::SocketAddr,
@@ -87,6 +89,8 @@ fn adding_deleting_values_shows_up_in_diff() {
         local_store_path: ic_prep_dir.registry_local_store_path(),
         snapshot: snapshot.clone(),
         amend: false,
+        projection: universal_projection(),
+        check_invariants: false,
     })
     .unwrap();
 
@@ -111,6 +115,140 @@ fn adding_deleting_values_shows_up_in_diff() {
     assert_eq!(expected_snapshot.0, final_snapshot);
 }
 
+#[test]
+fn apply_update_checking_invariants_writes_new_version_if_invariants_hold() {
+    let (_guard, ic_prep_dir) = run_ic_prep_with_valid_node_addresses();
+    let registry_spec = local_store_latest_snapshot(ic_prep_dir.registry_local_store_path());
+    let projection = universal_projection();
+    let cmd = Command::Snapshot {
+        registry_spec: registry_spec.clone(),
+        projection: projection.clone(),
+    };
+    let mut snapshot = execute_command(cmd).unwrap();
+
+    let subnet_record = subnet_record_mut(&mut snapshot);
+    subnet_record.insert("max_ingress_messages_per_block".into(), 42.into());
+
+    let diff = execute_command(Command::ApplyUpdate {
+        local_store_path: ic_prep_dir.registry_local_store_path(),
+        snapshot: snapshot.clone(),
+        amend: false,
+        projection: universal_projection(),
+        check_invariants: true,
+    })
+    .unwrap();
+    let diff_keys: Vec<_> = diff.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(filter_special_keys(diff_keys).len(), 1);
+
+    let (mut expected_snapshot, _) = normalization::normalize(snapshot);
+    expected_snapshot
+        .0
+        .as_object_mut()
+        .unwrap()
+        .insert("__version".into(), serde_json::to_value(2).unwrap());
+
+    let cmd = Command::Snapshot {
+        registry_spec,
+        projection,
+    };
+    let final_snapshot = execute_command(cmd).unwrap();
+
+    assert_eq!(expected_snapshot.0, final_snapshot);
+}
+
+#[test]
+fn apply_update_checking_invariants_rejects_invariant_violations() {
+    let (_guard, ic_prep_dir) = run_ic_prep_with_valid_node_addresses();
+    let registry_spec = local_store_latest_snapshot(ic_prep_dir.registry_local_store_path());
+    let projection = universal_projection();
+    let cmd = Command::Snapshot {
+        registry_spec: registry_spec.clone(),
+        projection: projection.clone(),
+    };
+    let original_snapshot = execute_command(cmd).unwrap();
+    let mut snapshot = original_snapshot.clone();
+
+    // Turning the only system subnet into an application subnet leaves the
+    // registry without a system subnet.
+    let subnet_record = subnet_record_mut(&mut snapshot);
+    subnet_record.insert(
+        "subnet_type".into(),
+        i32::from(SubnetType::Application).into(),
+    );
+
+    let err = execute_command(Command::ApplyUpdate {
+        local_store_path: ic_prep_dir.registry_local_store_path(),
+        snapshot,
+        amend: false,
+        projection: universal_projection(),
+        check_invariants: true,
+    })
+    .unwrap_err();
+    assert!(err.to_string().contains("no system subnet"), "{}", err);
+
+    let cmd = Command::Snapshot {
+        registry_spec,
+        projection,
+    };
+    let final_snapshot = execute_command(cmd).unwrap();
+
+    assert_eq!(original_snapshot, final_snapshot);
+}
+
+#[test]
+fn apply_update_with_projection_keeps_keys_outside_of_projection() {
+    let (_guard, ic_prep_dir) = run_ic_prep_with_valid_node_addresses();
+    let registry_spec = local_store_latest_snapshot(ic_prep_dir.registry_local_store_path());
+    let original_snapshot = execute_command(Command::Snapshot {
+        registry_spec: registry_spec.clone(),
+        projection: universal_projection(),
+    })
+    .unwrap();
+    let projection = vec![SUBNET_RECORD_KEY_PREFIX.to_string()];
+    let mut snapshot = execute_command(Command::Snapshot {
+        registry_spec: registry_spec.clone(),
+        projection: projection.clone(),
+    })
+    .unwrap();
+
+    let subnet_record = subnet_record_mut(&mut snapshot);
+    subnet_record.insert("max_ingress_messages_per_block".into(), 42.into());
+
+    let diff = execute_command(Command::ApplyUpdate {
+        local_store_path: ic_prep_dir.registry_local_store_path(),
+        snapshot: snapshot.clone(),
+        amend: false,
+        projection,
+        check_invariants: true,
+    })
+    .unwrap();
+    let diff_keys: Vec<_> = diff.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(filter_special_keys(diff_keys).len(), 1);
+
+    let mut final_snapshot = execute_command(Command::Snapshot {
+        registry_spec,
+        projection: universal_projection(),
+    })
+    .unwrap();
+    assert_eq!(
+        subnet_record_mut(&mut final_snapshot).get("max_ingress_messages_per_block"),
+        Some(&Value::from(42))
+    );
+    let original_keys: Vec<_> = original_snapshot.as_object().unwrap().keys().collect();
+    let final_keys: Vec<_> = final_snapshot.as_object().unwrap().keys().collect();
+    assert_eq!(original_keys, final_keys);
+}
+
+fn subnet_record_mut(snapshot: &mut Value) -> &mut Map<String, Value> {
+    snapshot
+        .as_object_mut()
+        .unwrap()
+        .iter_mut()
+        .find(|(k, _)| k.starts_with(SUBNET_RECORD_KEY_PREFIX))
+        .and_then(|(_, v)| v.as_object_mut())
+        .unwrap()
+}
+
 pub fn local_store_latest_snapshot(path: PathBuf) -> RegistrySpec {
     let source = SourceSpec::LocalStore(path);
     let version = VersionSpec::RelativeToLatest(0);
@@ -126,12 +264,31 @@ pub fn filter_special_keys(keys: Vec<String>) -> Vec<String> {
 }
 
 pub fn run_ic_prep() -> (TempDir, IcPrepStateDir) {
+    run_ic_prep_with_node_addresses(
+        SocketAddr::from_str("0.0.0.0:0").unwrap(),
+        SocketAddr::from_str("0.0.0.0:8080").unwrap(),
+    )
+}
+
+/// Like `run_ic_prep`, but with routable node addresses, so that the registry
+/// satisfies the node invariants checked by `apply-update --check-invariants`.
+pub fn run_ic_prep_with_valid_node_addresses() -> (TempDir, IcPrepStateDir) {
+    run_ic_prep_with_node_addresses(
+        SocketAddr::from_str("1.2.3.4:2497").unwrap(),
+        SocketAddr::from_str("1.2.3.4:8080").unwrap(),
+    )
+}
+
+fn run_ic_prep_with_node_addresses(
+    xnet_api: SocketAddr,
+    public_api: SocketAddr,
+) -> (TempDir, IcPrepStateDir) {
     let mut subnet_nodes: BTreeMap<NodeIndex, NodeConfiguration> = BTreeMap::new();
     subnet_nodes.insert(
         NODE_INDEX,
         NodeConfiguration {
-            xnet_api: SocketAddr::from_str("0.0.0.0:0").unwrap(),
-            public_api: SocketAddr::from_str("0.0.0.0:8080").unwrap(),
+            xnet_api,
+            public_api,
             node_operator_principal_id: None,
             secret_key_store: None,
             chip_id: None,
@@ -1,4 +1,9 @@
-use ic_artifact_pool::consensus_pool::ConsensusPoolImpl;
+use ic_artifact_pool::{
+    backup::archive::{
+        backup_file_exists, invalidate_backup_file, list_backup_files, read_backup_file,
+    },
+    consensus_pool::ConsensusPoolImpl,
+};
 use ic_config::artifact_pool::BACKUP_GROUP_SIZE;
 use ic_consensus::consensus::dkg_key_manager::DkgKeyManager;
 use ic_consensus_utils::pool_reader::PoolReader;
@@ -23,8 +28,6 @@ use prost::Message;
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
     convert::TryFrom,
-    fs,
-    io::Read,
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -41,26 +44,18 @@ pub(super) struct HeightArtifacts {
     notarizations: Vec<String>,
 }
 
-// Reads the file at `path` and the returns the content as bytes.
+// Reads the file at `path`, which may be packed into an archive, and the returns the content as
+// bytes.
 fn read_file(path: &Path) -> Vec<u8> {
-    let mut buffer = Vec::new();
-    let mut file = fs::File::open(path)
-        .unwrap_or_else(|err| panic!("Couldn't open file {:?}: {:?}", path, err));
-    file.read_to_end(&mut buffer)
-        .unwrap_or_else(|err| panic!("Couldn't read file {:?}: {:?}", path, err));
-    buffer
+    read_backup_file(path).unwrap_or_else(|err| panic!("Couldn't read file {:?}: {:?}", path, err))
 }
 
-// Renames the file at `path` and by adding a prefix 'invalid_'.
+// Renames the file at `path` by adding a prefix 'invalid_'. Archived files are copied out of the
+// archive under the new name instead.
 pub(crate) fn rename_file(original_file: &Path) {
-    let file_name = original_file
-        .file_name()
-        .expect("File name is missing")
-        .to_str()
-        .expect("File name is not a proper string");
-    let renamed_file = original_file.with_file_name(format!("invalid_{}", file_name));
-    println!("Renaming {:?} to {:?}", original_file, renamed_file);
-    fs::rename(original_file, renamed_file).expect("Error renaming a file");
+    let renamed_file = invalidate_backup_file(original_file)
+        .unwrap_or_else(|err| panic!("Error renaming {:?}: {:?}", original_file, err));
+    println!("Renamed {:?} to {:?}", original_file, renamed_file);
 }
 
 /// All possible exits from the deserialization loop of the artifacts. All
@@ -142,40 +137,14 @@ pub(crate) fn cup_file_name(backup_dir: &Path, height: Height) -> PathBuf {
 }
 
 /// Read all files from the backup folder starting from the `start_height` and
-/// convert them into batches.
+/// convert them into batches. Files packed into archives are included.
 pub(super) fn heights_to_artifacts_metadata(
     backup_dir: &Path,
     start_height: Height,
 ) -> Result<BTreeMap<Height, HeightArtifacts>, std::io::Error> {
-    let mut results = Vec::new();
-    for group_dir in fs::read_dir(backup_dir)? {
-        for height_dir in fs::read_dir(group_dir?.path())? {
-            let path = height_dir?.path();
-            let height = Height::from(
-                path.file_name()
-                    .unwrap_or_default()
-                    .to_str()
-                    .unwrap_or_default()
-                    .parse::<u64>()
-                    .expect("Couldn't parse the height directory name"),
-            );
-
-            // Skip all height folders below the start height,
-            if height < start_height {
-                continue;
-            }
-            let mut files = Vec::new();
-            for file in fs::read_dir(&path)? {
-                let file_path = file?.path();
-                files.push(
-                    file_path
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_str()
-                        .unwrap_or_default()
-                        .to_string(),
-                );
-            }
+    Ok(list_backup_files(backup_dir, start_height)?
+        .into_iter()
+        .map(|(height, (path, files))| {
             let get_files = |s| {
                 files
                     .iter()
@@ -183,7 +152,7 @@ pub(super) fn heights_to_artifacts_metadata(
                     .cloned()
                     .collect::<Vec<_>>()
             };
-            results.push((
+            (
                 height,
                 HeightArtifacts {
                     path,
@@ -192,10 +161,9 @@ pub(super) fn heights_to_artifacts_metadata(
                     finalizations: get_files("finalization"),
                     notarizations: get_files("notarization"),
                 },
-            ));
-        }
-    }
-    Ok(results.into_iter().collect())
+            )
+        })
+        .collect())
 }
 
 fn read_artifact_if_correct_height<T, PBT>(
@@ -360,7 +328,7 @@ pub(crate) fn deserialize_consensus_artifacts(
 
         // Insert the random beacon and the random tape.
         let rb_path = path.join("random_beacon.bin");
-        if !rb_path.exists() {
+        if !backup_file_exists(&rb_path) {
             println!(
                 "Stopping deserialization at height {:?} as this height contains no random beacon.",
                 height,
@@ -375,7 +343,7 @@ pub(crate) fn deserialize_consensus_artifacts(
         artifacts.push(rb.into_message());
 
         let rt_path = path.join("random_tape.bin");
-        if !rt_path.exists() {
+        if !backup_file_exists(&rt_path) {
             println!(
                 "Stopping deserialization at height {:?} as this height contains no random tape.",
                 height,
@@ -432,7 +400,7 @@ pub(crate) fn deserialize_consensus_artifacts(
                 Some(name) => {
                     let artifact_path = path.join(name);
                     assert!(
-                        artifact_path.exists(),
+                        backup_file_exists(&artifact_path),
                         "Path to invalid artifact doesn't exist."
                     );
                     println!("Invalid artifact detected: {:?}", &artifact_path);
@@ -46,10 +46,10 @@ use ic_interfaces_adapter_client::NonBlockingChannel;
 use ic_interfaces_registry::{LocalStoreCertifiedTimeReader, RegistryClient};
 use ic_interfaces_state_manager::{StateManager, StateReader};
 use ic_interfaces_transport::Transport;
-use ic_logger::{info, replica_logger::ReplicaLogger};
+use ic_logger::{info, replica_logger::ReplicaLogger, warn};
 use ic_metrics::MetricsRegistry;
 use ic_p2p::{start_p2p, MAX_ADVERT_BUFFER};
-use ic_quic_transport::DummyUdpSocket;
+use ic_quic_transport::{DummyUdpSocket, StreamSchedulingConfig};
 use ic_registry_client_helpers::subnet::SubnetRegistry;
 use ic_replicated_state::ReplicatedState;
 use ic_transport::transport::create_transport;
@@ -69,7 +69,7 @@ use ic_types::{
 };
 use std::{
     collections::HashMap,
-    net::{IpAddr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
     str::FromStr,
     sync::{Arc, Mutex, RwLock},
 };
@@ -377,7 +377,7 @@ pub fn setup_consensus_and_p2p(
     )
         .into();
     let quic_transport = Arc::new(ic_quic_transport::QuicTransport::start(
-        log,
+        log.clone(),
         metrics_registry,
         rt_handle,
         tls_config,
@@ -387,8 +387,35 @@ pub fn setup_consensus_and_p2p(
         topology_watcher.clone(),
         Either::<_, DummyUdpSocket>::Left(transport_addr),
         p2p_router.unwrap_or_default(),
+        StreamSchedulingConfig {
+            consensus_weight: transport_config.quic_consensus_weight,
+            state_sync_weight: transport_config.quic_state_sync_weight,
+            ..Default::default()
+        },
     ));
 
+    if let Some(port) = transport_config.quic_debug_port {
+        // Only bound to localhost since the statistics must not be exposed to peers.
+        let debug_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let debug_router = quic_transport.debug_router();
+        let log = log.clone();
+        rt_handle.spawn(async move {
+            match axum::Server::try_bind(&debug_addr) {
+                Ok(server) => {
+                    if let Err(err) = server.serve(debug_router.into_make_service()).await {
+                        warn!(log, "Quic debug endpoint on {} failed: {}", debug_addr, err);
+                    }
+                }
+                Err(err) => {
+                    warn!(
+                        log,
+                        "Failed to bind quic debug endpoint to {}: {}", debug_addr, err
+                    )
+                }
+            }
+        });
+    }
+
     let _state_sync_manager = ic_state_sync_manager::start_state_sync_manager(
         log.clone(),
         metrics_registry,
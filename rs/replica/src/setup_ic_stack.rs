@@ -30,8 +30,9 @@ use ic_types::{
     consensus::{CatchUpPackage, HasHeight},
     NodeId, SubnetId,
 };
-use ic_xnet_endpoint::{XNetEndpoint, XNetEndpointConfig};
+use ic_xnet_endpoint::{XNetEndpoint, XNetEndpointConfig, XNetPublicEndpointConfig};
 use ic_xnet_payload_builder::XNetPayloadBuilderImpl;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::{Arc, RwLock};
 
 /// Create the consensus pool directory (if none exists)
@@ -173,13 +174,20 @@ pub fn construct_ic_stack(
         subnet_config.cycles_account_manager_config,
     ));
 
+    let mut hypervisor_config = config.hypervisor.clone();
+    hypervisor_config.compilation_cache_dir = Some(
+        config
+            .state_manager
+            .state_root()
+            .join(config.state_manager.compilation_cache_dirname()),
+    );
     let execution_services = ExecutionServices::setup_execution(
         log.clone(),
         metrics_registry,
         subnet_id,
         subnet_type,
         subnet_config.scheduler_config,
-        config.hypervisor.clone(),
+        hypervisor_config,
         cycles_account_manager.clone(),
         state_manager.clone(),
         state_manager.get_fd_factory(),
@@ -215,7 +223,23 @@ pub fn construct_ic_stack(
         )
     };
     let message_router = Arc::new(message_router);
-    let xnet_config = XNetEndpointConfig::from(Arc::clone(&registry) as Arc<_>, node_id, log);
+    let mut xnet_config = XNetEndpointConfig::from(Arc::clone(&registry) as Arc<_>, node_id, log);
+    if let Some(xnet_public_port) = config.message_routing.xnet_public_port {
+        let ip_addr: IpAddr = config
+            .message_routing
+            .xnet_ip_addr
+            .parse()
+            .unwrap_or_else(|err| {
+                panic!(
+                    "Invalid XNet IP address {}: {}",
+                    config.message_routing.xnet_ip_addr, err
+                )
+            });
+        xnet_config = xnet_config.with_public_endpoint(XNetPublicEndpointConfig {
+            address: SocketAddr::new(ip_addr, xnet_public_port),
+            max_requests_per_second: config.message_routing.xnet_public_max_requests_per_second,
+        });
+    }
     let xnet_endpoint = XNetEndpoint::new(
         rt_handle_xnet.clone(),
         Arc::clone(&certified_stream_store),
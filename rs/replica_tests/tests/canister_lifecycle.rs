@@ -712,6 +712,8 @@ fn can_get_canister_information() {
                 None,
                 2592000,
                 Some(5_000_000_000_000u128),
+                None,
+                0,
                 0u128,
                 0u128,
                 0u128,
@@ -769,6 +771,8 @@ fn can_get_canister_information() {
                     None,
                     259200,
                     None,
+                    None,
+                    0,
                     0u128,
                     0u128,
                     0u128,
@@ -210,6 +210,7 @@ impl CanisterState {
             (None, true) => NextExecution::StartNew,
             (Some(ExecutionTask::Heartbeat), _) => NextExecution::StartNew,
             (Some(ExecutionTask::GlobalTimer), _) => NextExecution::StartNew,
+            (Some(ExecutionTask::OnLowWasmMemory), _) => NextExecution::StartNew,
             (Some(ExecutionTask::AbortedExecution { .. }), _)
             | (Some(ExecutionTask::PausedExecution(..)), _) => NextExecution::ContinueLong,
             (Some(ExecutionTask::AbortedInstallCode { .. }), _)
@@ -229,6 +230,7 @@ impl CanisterState {
             None
             | Some(ExecutionTask::Heartbeat)
             | Some(ExecutionTask::GlobalTimer)
+            | Some(ExecutionTask::OnLowWasmMemory)
             | Some(ExecutionTask::PausedExecution(..))
             | Some(ExecutionTask::PausedInstallCode(..))
             | Some(ExecutionTask::AbortedInstallCode { .. }) => false,
@@ -242,6 +244,7 @@ impl CanisterState {
             None
             | Some(ExecutionTask::Heartbeat)
             | Some(ExecutionTask::GlobalTimer)
+            | Some(ExecutionTask::OnLowWasmMemory)
             | Some(ExecutionTask::PausedInstallCode(..))
             | Some(ExecutionTask::AbortedExecution { .. })
             | Some(ExecutionTask::AbortedInstallCode { .. }) => false,
@@ -255,6 +258,7 @@ impl CanisterState {
             None
             | Some(ExecutionTask::Heartbeat)
             | Some(ExecutionTask::GlobalTimer)
+            | Some(ExecutionTask::OnLowWasmMemory)
             | Some(ExecutionTask::PausedExecution(..))
             | Some(ExecutionTask::AbortedExecution { .. })
             | Some(ExecutionTask::AbortedInstallCode { .. }) => false,
@@ -268,6 +272,7 @@ impl CanisterState {
             None
             | Some(ExecutionTask::Heartbeat)
             | Some(ExecutionTask::GlobalTimer)
+            | Some(ExecutionTask::OnLowWasmMemory)
             | Some(ExecutionTask::PausedExecution(..))
             | Some(ExecutionTask::PausedInstallCode(..))
             | Some(ExecutionTask::AbortedExecution { .. }) => false,
@@ -471,6 +476,39 @@ impl CanisterState {
         self.exports_method(&WasmMethod::System(SystemMethod::CanisterGlobalTimer))
     }
 
+    /// Returns true if the canister exports the `canister_on_low_wasm_memory`
+    /// system method.
+    pub fn exports_on_low_wasm_memory_method(&self) -> bool {
+        self.exports_method(&WasmMethod::System(SystemMethod::CanisterOnLowWasmMemory))
+    }
+
+    /// Returns true if the free Wasm memory of the canister, i.e. the
+    /// difference between its `wasm_memory_limit` and the current size of its
+    /// Wasm memory, is below its `wasm_memory_threshold`.
+    ///
+    /// Always returns false if the canister has no Wasm memory limit, no
+    /// threshold or no execution state.
+    pub fn is_low_on_wasm_memory(&self) -> bool {
+        let threshold = self.system_state.wasm_memory_threshold;
+        match (&self.execution_state, self.system_state.wasm_memory_limit) {
+            (Some(execution_state), Some(limit)) if threshold.get() > 0 => {
+                let used = num_bytes_try_from(execution_state.wasm_memory.size)
+                    .unwrap_or_else(|_| NumBytes::new(u64::MAX));
+                limit.get().saturating_sub(used.get()) < threshold.get()
+            }
+            _ => false,
+        }
+    }
+
+    /// Updates the status of the `canister_on_low_wasm_memory` hook based on
+    /// the current free Wasm memory of the canister.
+    pub fn update_on_low_wasm_memory_hook_status(&mut self) {
+        let is_low_on_wasm_memory = self.is_low_on_wasm_memory();
+        self.system_state
+            .on_low_wasm_memory_hook_status
+            .update(is_low_on_wasm_memory);
+    }
+
     /// Returns true if the canister exports the given Wasm method.
     pub fn exports_method(&self, method: &WasmMethod) -> bool {
         match &self.execution_state {
@@ -537,6 +575,7 @@ impl CanisterState {
             ExecutionTask::AbortedInstallCode { .. } => false,
             ExecutionTask::Heartbeat
             | ExecutionTask::GlobalTimer
+            | ExecutionTask::OnLowWasmMemory
             | ExecutionTask::PausedExecution(_)
             | ExecutionTask::PausedInstallCode(_)
             | ExecutionTask::AbortedExecution { .. } => true,
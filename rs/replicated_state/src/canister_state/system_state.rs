@@ -243,6 +243,19 @@ impl CanisterHistory {
         self.changes.range((num_all_changes - num_changes)..)
     }
 
+    /// Returns an iterator over all retained canister changes in chronological order,
+    /// each paired with its index in the full history since canister creation.
+    /// Indices of older changes that have been dropped are not reused.
+    pub fn get_indexed_changes(&self) -> impl Iterator<Item = (u64, &Arc<CanisterChange>)> {
+        let first_index = self
+            .total_num_changes
+            .saturating_sub(self.changes.len() as u64);
+        self.changes
+            .iter()
+            .enumerate()
+            .map(move |(i, change)| (first_index + i as u64, change))
+    }
+
     pub fn get_total_num_changes(&self) -> u64 {
         self.total_num_changes
     }
@@ -331,6 +344,76 @@ pub struct SystemState {
 
     /// Store of Wasm chunks to support installation of large Wasm modules.
     pub wasm_chunk_store: WasmChunkStore,
+
+    /// The user-specified upper limit on the size of the Wasm memory.
+    ///
+    /// If set, an attempt to grow the Wasm memory beyond this limit fails
+    /// with `CanisterWasmMemoryLimitExceeded`.
+    pub wasm_memory_limit: Option<NumBytes>,
+
+    /// The user-specified threshold of free Wasm memory below which the
+    /// `canister_on_low_wasm_memory` hook is run. Zero disables the hook.
+    ///
+    /// Free Wasm memory is measured against `wasm_memory_limit`, so the hook
+    /// is never run if the canister has no Wasm memory limit.
+    pub wasm_memory_threshold: NumBytes,
+
+    /// Tracks whether the `canister_on_low_wasm_memory` hook needs to run.
+    pub on_low_wasm_memory_hook_status: OnLowWasmMemoryHookStatus,
+}
+
+/// The state of the `canister_on_low_wasm_memory` hook of a canister.
+///
+/// The hook runs at most once each time the free Wasm memory of the canister
+/// drops below `wasm_memory_threshold`:
+/// `ConditionNotSatisfied` -> `Ready` when the free memory drops below the
+/// threshold, `Ready` -> `Executed` when the scheduler runs the hook and back
+/// to `ConditionNotSatisfied` once the free memory is above the threshold.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OnLowWasmMemoryHookStatus {
+    #[default]
+    ConditionNotSatisfied,
+    Ready,
+    Executed,
+}
+
+impl OnLowWasmMemoryHookStatus {
+    /// Updates the status given whether the free Wasm memory of the canister
+    /// is currently below its threshold.
+    pub fn update(&mut self, is_below_threshold: bool) {
+        *self = match (*self, is_below_threshold) {
+            (_, false) => Self::ConditionNotSatisfied,
+            (Self::ConditionNotSatisfied, true) => Self::Ready,
+            (status @ (Self::Ready | Self::Executed), true) => status,
+        };
+    }
+
+    /// Returns true if the hook should be scheduled for execution.
+    pub fn is_ready(&self) -> bool {
+        *self == Self::Ready
+    }
+}
+
+impl From<&OnLowWasmMemoryHookStatus> for pb::OnLowWasmMemoryHookStatus {
+    fn from(item: &OnLowWasmMemoryHookStatus) -> Self {
+        match item {
+            OnLowWasmMemoryHookStatus::ConditionNotSatisfied => Self::ConditionNotSatisfied,
+            OnLowWasmMemoryHookStatus::Ready => Self::Ready,
+            OnLowWasmMemoryHookStatus::Executed => Self::Executed,
+        }
+    }
+}
+
+impl From<pb::OnLowWasmMemoryHookStatus> for OnLowWasmMemoryHookStatus {
+    fn from(item: pb::OnLowWasmMemoryHookStatus) -> Self {
+        match item {
+            // Checkpoints written before the hook existed have no status.
+            pb::OnLowWasmMemoryHookStatus::Unspecified
+            | pb::OnLowWasmMemoryHookStatus::ConditionNotSatisfied => Self::ConditionNotSatisfied,
+            pb::OnLowWasmMemoryHookStatus::Ready => Self::Ready,
+            pb::OnLowWasmMemoryHookStatus::Executed => Self::Executed,
+        }
+    }
 }
 
 /// A wrapper around the different canister statuses.
@@ -430,6 +513,10 @@ pub enum ExecutionTask {
     /// The task exists only within an execution round, it never gets serialized.
     GlobalTimer,
 
+    /// Canister low Wasm memory hook task.
+    /// The task exists only within an execution round, it never gets serialized.
+    OnLowWasmMemory,
+
     // A paused execution task exists only within an epoch (between
     // checkpoints). It is never serialized, and it turns into `AbortedExecution`
     // before the checkpoint or when there are too many long-running executions.
@@ -471,6 +558,7 @@ impl From<&ExecutionTask> for pb::ExecutionTask {
         match item {
             ExecutionTask::Heartbeat
             | ExecutionTask::GlobalTimer
+            | ExecutionTask::OnLowWasmMemory
             | ExecutionTask::PausedExecution(_)
             | ExecutionTask::PausedInstallCode(_) => {
                 panic!("Attempt to serialize ephemeral task: {:?}.", item);
@@ -498,6 +586,9 @@ impl From<&ExecutionTask> for pb::ExecutionTask {
                     CanisterMessageOrTask::Task(CanisterTask::GlobalTimer) => {
                         PbInput::Task(PbCanisterTask::Timer as i32)
                     }
+                    CanisterMessageOrTask::Task(CanisterTask::OnLowWasmMemory) => {
+                        PbInput::Task(PbCanisterTask::OnLowWasmMemory as i32)
+                    }
                 };
                 Self {
                     task: Some(pb::execution_task::Task::AbortedExecution(
@@ -573,6 +664,7 @@ impl TryFrom<pb::ExecutionTask> for ExecutionTask {
                             }
                             PbCanisterTask::Heartbeat => CanisterTask::Heartbeat,
                             PbCanisterTask::Timer => CanisterTask::GlobalTimer,
+                            PbCanisterTask::OnLowWasmMemory => CanisterTask::OnLowWasmMemory,
                         };
                         CanisterMessageOrTask::Task(task)
                     }
@@ -702,6 +794,9 @@ impl SystemState {
             canister_version: 0,
             canister_history: CanisterHistory::default(),
             wasm_chunk_store,
+            wasm_memory_limit: None,
+            wasm_memory_threshold: NumBytes::new(0),
+            on_low_wasm_memory_hook_status: OnLowWasmMemoryHookStatus::default(),
         }
     }
 
@@ -745,6 +840,9 @@ impl SystemState {
         canister_history: CanisterHistory,
         wasm_chunk_store_data: PageMap,
         wasm_chunk_store_metadata: WasmChunkStoreMetadata,
+        wasm_memory_limit: Option<NumBytes>,
+        wasm_memory_threshold: NumBytes,
+        on_low_wasm_memory_hook_status: OnLowWasmMemoryHookStatus,
     ) -> Self {
         Self {
             controllers,
@@ -767,6 +865,9 @@ impl SystemState {
                 wasm_chunk_store_data,
                 wasm_chunk_store_metadata,
             ),
+            wasm_memory_limit,
+            wasm_memory_threshold,
+            on_low_wasm_memory_hook_status,
         }
     }
 
@@ -44,7 +44,8 @@ pub use canister_state::{
     num_bytes_try_from,
     system_state::{
         memory_required_to_push_request, CallContext, CallContextAction, CallContextManager,
-        CallOrigin, CanisterMetrics, CanisterStatus, ExecutionTask, SystemState,
+        CallOrigin, CanisterMetrics, CanisterStatus, ExecutionTask, OnLowWasmMemoryHookStatus,
+        SystemState,
     },
     CanisterQueues, CanisterState, EmbedderCache, ExecutionState, ExportedFunctions, Global,
     NumWasmPages, SchedulerState,
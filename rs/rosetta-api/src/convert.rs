@@ -11,10 +11,11 @@ use crate::request::transaction_operation_results::TransactionOperationResults;
 use crate::request::transaction_results::TransactionResults;
 use crate::request::Request;
 use crate::request_types::{
-    ChangeAutoStakeMaturityMetadata, DisburseMetadata, FollowMetadata, KeyMetadata,
-    MergeMaturityMetadata, NeuronIdentifierMetadata, NeuronInfoMetadata, PublicKeyOrPrincipal,
-    RegisterVoteMetadata, RequestResultMetadata, SetDissolveTimestampMetadata, SpawnMetadata,
-    StakeMaturityMetadata, Status, STATUS_COMPLETED,
+    ApproveMetadata, ChangeAutoStakeMaturityMetadata, DisburseMetadata, FollowMetadata,
+    KeyMetadata, MergeMaturityMetadata, NeuronIdentifierMetadata, NeuronInfoMetadata,
+    PublicKeyOrPrincipal, RegisterVoteMetadata, RequestResultMetadata,
+    SetDissolveTimestampMetadata, SpawnMetadata, StakeMaturityMetadata, Status,
+    TransferFromMetadata, STATUS_COMPLETED,
 };
 use crate::transaction_id::TransactionIdentifier;
 use crate::{convert, errors};
@@ -125,10 +126,17 @@ pub fn operations_to_requests(
                 state.transaction(account, amount)?;
             }
             OperationType::Approve => {
-                return Err(ApiError::InvalidRequest(
-                    false,
-                    "OperationType Approve is not supported for Requests".into(),
-                ))
+                let metadata: ApproveMetadata = o.metadata.clone().try_into()?;
+                state.approve(account, metadata)?;
+            }
+            OperationType::TransferFrom => {
+                let amount = o
+                    .amount
+                    .as_ref()
+                    .ok_or_else(|| op_error(o, "Amount must be populated".into()))?;
+                let amount = from_amount(amount, token_name).map_err(|e| op_error(o, e))?;
+                let metadata: TransferFromMetadata = o.metadata.clone().try_into()?;
+                state.transfer_from(account, amount, metadata)?;
             }
             OperationType::Fee => {
                 let amount = o
@@ -453,6 +461,21 @@ pub fn from_transaction_operation_results(
                 op_idx += 3;
                 fee
             }
+            (Request::TransferFrom(_), [withdraw, deposit, fee, ..])
+                if withdraw._type.parse::<OperationType>()? == OperationType::TransferFrom
+                    && deposit._type.parse::<OperationType>()? == OperationType::Transaction
+                    && fee._type.parse::<OperationType>()? == OperationType::Fee =>
+            {
+                op_idx += 3;
+                fee
+            }
+            (Request::Approve(_), [approve, fee, ..])
+                if approve._type.parse::<OperationType>()? == OperationType::Approve
+                    && fee._type.parse::<OperationType>()? == OperationType::Fee =>
+            {
+                op_idx += 2;
+                fee
+            }
             (_, [o, ..]) => {
                 op_idx += 1;
                 o
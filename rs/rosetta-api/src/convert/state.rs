@@ -2,21 +2,25 @@ use crate::errors::ApiError;
 use crate::models::seconds::Seconds;
 use crate::request::Request;
 use crate::request_types::{
-    AddHotKey, ChangeAutoStakeMaturity, Disburse, Follow, MergeMaturity, NeuronInfo,
-    PublicKeyOrPrincipal, RegisterVote, RemoveHotKey, SetDissolveTimestamp, Spawn, Stake,
-    StakeMaturity, StartDissolve, StopDissolve,
+    AddHotKey, Approve, ApproveMetadata, ChangeAutoStakeMaturity, Disburse, Follow, MergeMaturity,
+    NeuronInfo, PublicKeyOrPrincipal, RegisterVote, RemoveHotKey, SetDissolveTimestamp, Spawn,
+    Stake, StakeMaturity, StartDissolve, StopDissolve, TransferFrom, TransferFromMetadata,
 };
 use ic_types::PrincipalId;
 use icp_ledger::{Operation, Tokens, DEFAULT_TRANSFER_FEE};
 
-/// Helper for `from_operations` that creates `Transfer`s from related
-/// debit/credit/fee operations.
+/// Helper for `from_operations` that creates `Transfer`s, `Approve`s and
+/// `TransferFrom`s from related debit/credit/fee operations.
 pub struct State {
     preprocessing: bool,
     pub(crate) actions: Vec<Request>,
     credit: Option<AccountTokens>,
     debit: Option<AccountTokens>,
     fee: Option<AccountTokens>,
+    /// Set if the debit was a TRANSFER_FROM operation.
+    transfer_from: Option<TransferFromMetadata>,
+    /// An APPROVE operation waiting for its fee.
+    approve: Option<(icp_ledger::AccountIdentifier, ApproveMetadata)>,
 }
 
 impl State {
@@ -33,6 +37,8 @@ impl State {
             credit,
             debit,
             fee,
+            transfer_from: None,
+            approve: None,
         }
     }
 
@@ -45,6 +51,10 @@ impl State {
             Err(err)
         };
 
+        if self.approve.is_some() {
+            return self.flush_approve();
+        }
+
         if self.credit.is_none() && self.debit.is_none() && self.fee.is_none() {
             return Ok(());
         }
@@ -87,6 +97,32 @@ impl State {
             return trans_err("Debit_amount should be equal -credit_amount".to_string());
         }
 
+        if let Some(metadata) = self.transfer_from.take() {
+            let (from_account, to_account) = match (metadata.from_account, metadata.to_account) {
+                (Some(from_account), Some(to_account)) => (from_account, to_account),
+                _ => {
+                    return trans_err(
+                        "TRANSFER_FROM metadata must contain `from_account` and `to_account`"
+                            .to_string(),
+                    )
+                }
+            };
+            if icp_ledger::AccountIdentifier::from(from_account) != from {
+                return trans_err(format!("`from_account` does not match account {}", from));
+            }
+            if icp_ledger::AccountIdentifier::from(to_account) != to {
+                return trans_err(format!("`to_account` does not match account {}", to));
+            }
+            self.actions.push(Request::TransferFrom(TransferFrom {
+                spender: metadata.spender,
+                from: from_account,
+                to: to_account,
+                amount: cr_amount,
+                fee: fee_amount,
+            }));
+            return Ok(());
+        }
+
         self.actions.push(Request::Transfer(Operation::Transfer {
             from,
             to,
@@ -98,11 +134,63 @@ impl State {
         Ok(())
     }
 
+    /// Create an `Approve` from the approve/fee operations seen previously.
+    fn flush_approve(&mut self) -> Result<(), ApiError> {
+        let trans_err = |msg: String| {
+            let msg = format!("Bad transaction: {}", msg);
+            Err(ApiError::InvalidTransaction(false, msg.into()))
+        };
+
+        let (account, metadata) = self.approve.take().unwrap();
+        if self.credit.is_some() || self.debit.is_some() {
+            return trans_err("APPROVE cannot be combined with TRANSACTION operations".to_string());
+        }
+        // If you're preprocessing just continue with the default fee
+        let fee = match self.fee.take() {
+            Some(fee) => fee,
+            None if self.preprocessing => AccountTokens {
+                account,
+                tokens: DEFAULT_TRANSFER_FEE,
+            },
+            None => return trans_err("APPROVE must be followed by a FEE operation".to_string()),
+        };
+        if fee.account != account {
+            return trans_err(format!("Fee should be taken from {}", account));
+        }
+        if metadata.from != account {
+            return trans_err(format!("Approve metadata `from` should be {}", account));
+        }
+        let spender = match metadata.spender_account {
+            Some(spender) if icp_ledger::AccountIdentifier::from(spender) == metadata.spender => {
+                spender
+            }
+            Some(_) => {
+                return trans_err("`spender_account` does not match `spender`".to_string());
+            }
+            None => {
+                return trans_err("APPROVE metadata must contain `spender_account`".to_string());
+            }
+        };
+
+        self.actions.push(Request::Approve(Approve {
+            account,
+            spender,
+            allowance: metadata.allowance,
+            expected_allowance: metadata.expected_allowance,
+            expires_at: metadata.expires_at,
+            fee: fee.tokens,
+        }));
+        Ok(())
+    }
+
     pub fn transaction(
         &mut self,
         account: icp_ledger::AccountIdentifier,
         amount: i128,
     ) -> Result<(), ApiError> {
+        if self.approve.is_some() {
+            self.flush()?;
+        }
         if amount > 0 || self.debit.is_some() && amount == 0 {
             if self.credit.is_some() {
                 self.flush()?;
@@ -123,6 +211,37 @@ impl State {
         Ok(())
     }
 
+    pub fn transfer_from(
+        &mut self,
+        account: icp_ledger::AccountIdentifier,
+        amount: i128,
+        metadata: TransferFromMetadata,
+    ) -> Result<(), ApiError> {
+        if amount > 0 {
+            let msg = format!("TRANSFER_FROM must debit {}", account);
+            return Err(ApiError::InvalidTransaction(false, msg.into()));
+        }
+        if self.approve.is_some() || self.debit.is_some() {
+            self.flush()?;
+        }
+        self.debit = Some(AccountTokens {
+            account,
+            tokens: Tokens::from_e8s((-amount) as u64),
+        });
+        self.transfer_from = Some(metadata);
+        Ok(())
+    }
+
+    pub fn approve(
+        &mut self,
+        account: icp_ledger::AccountIdentifier,
+        metadata: ApproveMetadata,
+    ) -> Result<(), ApiError> {
+        self.flush()?;
+        self.approve = Some((account, metadata));
+        Ok(())
+    }
+
     pub fn fee(
         &mut self,
         account: icp_ledger::AccountIdentifier,
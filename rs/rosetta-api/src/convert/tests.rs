@@ -2,10 +2,11 @@ use super::*;
 use crate::models::amount::signed_amount;
 use crate::models::operation::OperationType;
 use crate::models::OperationIdentifier;
-use crate::request_types::Stake;
+use crate::request_types::{Approve, Stake, TransferFrom};
 use crate::DEFAULT_TOKEN_SYMBOL;
 use icp_ledger::AccountIdentifier;
 use icp_ledger::Operation as LedgerOperation;
+use icrc_ledger_types::icrc1::account::Account;
 
 struct OperationBuilder(Operation);
 impl OperationBuilder {
@@ -180,6 +181,79 @@ fn test_can_handle_multiple_transfers() {
     );
 }
 
+fn test_icrc1_account(n: u64) -> Account {
+    Account {
+        owner: PrincipalId::new_user_test_id(n).0,
+        subaccount: None,
+    }
+}
+
+#[test]
+fn test_approve_round_trip() {
+    let approve = Request::Approve(Approve {
+        account: AccountIdentifier::from(test_icrc1_account(1)),
+        spender: test_icrc1_account(2),
+        allowance: Tokens::from_e8s(1_000),
+        expected_allowance: Some(Tokens::from_e8s(500)),
+        expires_at: Some(1_000_000),
+        fee: Tokens::from_e8s(10),
+    });
+    let ops = Request::requests_to_operations(&[approve.clone()], DEFAULT_TOKEN_SYMBOL).unwrap();
+    assert_eq!(ops.len(), 2);
+    assert_eq!(ops[0]._type, OperationType::Approve.to_string());
+    assert_eq!(ops[1]._type, OperationType::Fee.to_string());
+    assert_eq!(
+        operations_to_requests(&ops, false, DEFAULT_TOKEN_SYMBOL),
+        Ok(vec![approve])
+    );
+}
+
+#[test]
+fn test_transfer_from_round_trip() {
+    let transfer_from = Request::TransferFrom(TransferFrom {
+        spender: AccountIdentifier::from(test_icrc1_account(3)),
+        from: test_icrc1_account(1),
+        to: test_icrc1_account(2),
+        amount: Tokens::from_e8s(100),
+        fee: Tokens::from_e8s(10),
+    });
+    let ops =
+        Request::requests_to_operations(&[transfer_from.clone()], DEFAULT_TOKEN_SYMBOL).unwrap();
+    assert_eq!(ops.len(), 3);
+    assert_eq!(ops[0]._type, OperationType::TransferFrom.to_string());
+    assert_eq!(
+        operations_to_requests(&ops, false, DEFAULT_TOKEN_SYMBOL),
+        Ok(vec![transfer_from])
+    );
+}
+
+#[test]
+fn test_ledger_transfer_from_uses_transfer_from_operation() {
+    let ops = Request::requests_to_operations(
+        &[Request::Transfer(LedgerOperation::Transfer {
+            from: test_account(1),
+            to: test_account(2),
+            spender: Some(test_account(3)),
+            amount: Tokens::from_e8s(100),
+            fee: Tokens::from_e8s(10),
+        })],
+        DEFAULT_TOKEN_SYMBOL,
+    )
+    .unwrap();
+    let types: Vec<_> = ops.iter().map(|o| o._type.clone()).collect();
+    assert_eq!(
+        types,
+        vec![
+            OperationType::TransferFrom.to_string(),
+            OperationType::Transaction.to_string(),
+            OperationType::Fee.to_string(),
+        ]
+    );
+    // Blocks do not record the ICRC-1 accounts, so they cannot be turned
+    // back into a request.
+    assert!(operations_to_requests(&ops, false, DEFAULT_TOKEN_SYMBOL).is_err());
+}
+
 #[test]
 fn account_identifier_decode_test() {
     // a good address
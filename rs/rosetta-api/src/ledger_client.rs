@@ -1,4 +1,5 @@
 mod handle_add_hotkey;
+mod handle_approve;
 mod handle_change_auto_stake_maturity;
 mod handle_disburse;
 mod handle_follow;
@@ -13,6 +14,7 @@ mod handle_stake;
 mod handle_stake_maturity;
 mod handle_start_dissolve;
 mod handle_stop_dissolve;
+mod handle_transfer_from;
 mod neuron_response;
 pub mod pending_proposals_response;
 pub mod proposal_info_response;
@@ -50,7 +52,7 @@ use crate::convert;
 use crate::errors::{ApiError, Details, ICError};
 use crate::ledger_client::neuron_response::NeuronResponse;
 use crate::ledger_client::{
-    handle_add_hotkey::handle_add_hotkey,
+    handle_add_hotkey::handle_add_hotkey, handle_approve::handle_approve,
     handle_change_auto_stake_maturity::handle_change_auto_stake_maturity,
     handle_disburse::handle_disburse, handle_follow::handle_follow,
     handle_merge_maturity::handle_merge_maturity, handle_neuron_info::handle_neuron_info,
@@ -58,7 +60,7 @@ use crate::ledger_client::{
     handle_send::handle_send, handle_set_dissolve_timestamp::handle_set_dissolve_timestamp,
     handle_spawn::handle_spawn, handle_stake::handle_stake,
     handle_stake_maturity::handle_stake_maturity, handle_start_dissolve::handle_start_dissolve,
-    handle_stop_dissolve::handle_stop_dissolve,
+    handle_stop_dissolve::handle_stop_dissolve, handle_transfer_from::handle_transfer_from,
 };
 use crate::models::{EnvelopePair, SignedTransaction};
 use crate::request::request_result::RequestResult;
@@ -768,6 +770,8 @@ impl LedgerClient {
             RequestType::NeuronInfo { .. } => handle_neuron_info(bytes),
             RequestType::RemoveHotKey { .. } => handle_remove_hotkey(bytes),
             RequestType::Send => handle_send(bytes),
+            RequestType::Approve => handle_approve(bytes),
+            RequestType::TransferFrom => handle_transfer_from(bytes),
             RequestType::SetDissolveTimestamp { .. } => handle_set_dissolve_timestamp(bytes),
             RequestType::ChangeAutoStakeMaturity { .. } => handle_change_auto_stake_maturity(bytes),
             RequestType::Spawn { .. } => handle_spawn(bytes),
@@ -0,0 +1,23 @@
+use crate::errors::ApiError;
+use crate::ledger_client::OperationOutput;
+use candid::Nat;
+use icrc_ledger_types::icrc2::transfer_from::TransferFromError;
+
+pub fn handle_transfer_from(
+    bytes: Vec<u8>,
+) -> Result<Result<Option<OperationOutput>, ApiError>, String> {
+    let response: Result<Nat, TransferFromError> = candid::decode_one(bytes.as_ref())
+        .map_err(|err| format!("Could not decode TRANSFER_FROM response: {}", err))?;
+    match response {
+        Ok(block_index) => {
+            let block_index = u64::try_from(block_index.0).map_err(|err| {
+                format!("Block index of TRANSFER_FROM does not fit in u64: {}", err)
+            })?;
+            Ok(Ok(Some(OperationOutput::BlockIndex(block_index))))
+        }
+        Err(err) => Ok(Err(ApiError::TransactionRejected(
+            false,
+            format!("Could not transfer from: {:?}", err).into(),
+        ))),
+    }
+}
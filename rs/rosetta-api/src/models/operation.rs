@@ -9,6 +9,7 @@ pub enum OperationType {
     Mint,
     Burn,
     Approve,
+    TransferFrom,
     Fee,
     Stake,
     StartDissolving,
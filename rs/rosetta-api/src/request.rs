@@ -7,6 +7,8 @@ use dfn_candid::CandidOne;
 use ic_nns_governance::pb::v1::manage_neuron::{self, configure, Command, Configure};
 use ic_types::PrincipalId;
 use icp_ledger::Tokens;
+use icrc_ledger_types::icrc2::approve::ApproveArgs;
+use icrc_ledger_types::icrc2::transfer_from::TransferFromArgs;
 use on_wire::FromWire;
 use std::convert::{TryFrom, TryInto};
 
@@ -30,6 +32,10 @@ pub enum Request {
     #[serde(rename = "TRANSACTION")]
     #[serde(with = "serde_transfer")]
     Transfer(icp_ledger::Operation),
+    #[serde(rename = "APPROVE")]
+    Approve(Approve),
+    #[serde(rename = "TRANSFER_FROM")]
+    TransferFrom(TransferFrom),
     #[serde(rename = "STAKE")]
     Stake(Stake),
     #[serde(rename = "SET_DISSOLVE_TIMESTAMP")]
@@ -101,20 +107,24 @@ impl Request {
             Request::Transfer(icp_ledger::Operation::Transfer { spender, .. }) => {
                 if spender.is_some() {
                     return Err(ApiError::invalid_request(
-                        "TransferFrom operations are not supported through Rosetta",
+                        "TransferFrom operations must be submitted as TRANSFER_FROM operations",
                     ));
                 }
                 Ok(RequestType::Send)
             }
+            Request::Approve(_) => Ok(RequestType::Approve),
+            Request::TransferFrom(_) => Ok(RequestType::TransferFrom),
             Request::Transfer(icp_ledger::Operation::Burn { .. }) => Err(
                 ApiError::invalid_request("Burn operations are not supported through Rosetta"),
             ),
             Request::Transfer(icp_ledger::Operation::Mint { .. }) => Err(
                 ApiError::invalid_request("Mint operations are not supported through Rosetta"),
             ),
-            Request::Transfer(icp_ledger::Operation::Approve { .. }) => Err(
-                ApiError::invalid_request("Approve operations are not supported through Rosetta"),
-            ),
+            Request::Transfer(icp_ledger::Operation::Approve { .. }) => {
+                Err(ApiError::invalid_request(
+                    "Approve operations must be submitted as APPROVE operations",
+                ))
+            }
             Request::Spawn(Spawn { neuron_index, .. }) => Ok(RequestType::Spawn {
                 neuron_index: *neuron_index,
             }),
@@ -164,6 +174,8 @@ impl Request {
         for request in requests {
             match request {
                 Request::Transfer(o) => builder.transfer(o, token_name)?,
+                Request::Approve(o) => builder.approve(o, token_name),
+                Request::TransferFrom(o) => builder.transfer_from(o, token_name),
                 Request::Stake(o) => builder.stake(o),
                 Request::SetDissolveTimestamp(o) => builder.set_dissolve_timestamp(o),
                 Request::ChangeAutoStakeMaturity(o) => builder.change_auto_stake_maturity(o),
@@ -184,7 +196,10 @@ impl Request {
     }
 
     pub fn is_transfer(&self) -> bool {
-        matches!(self, Request::Transfer(_))
+        matches!(
+            self,
+            Request::Transfer(_) | Request::Approve(_) | Request::TransferFrom(_)
+        )
     }
 
     pub fn is_neuron_management(&self) -> bool {
@@ -253,6 +268,23 @@ impl TryFrom<&models::Request> for Request {
                     fee,
                 }))
             }
+            RequestType::Approve => {
+                let args: ApproveArgs = candid::decode_one(payload.update_content().arg.0.as_ref())
+                    .map_err(|e| {
+                        ApiError::invalid_request(format!("Could not parse approve args: {}", e))
+                    })?;
+                Approve::try_from_args(pid, args).map(Request::Approve)
+            }
+            RequestType::TransferFrom => {
+                let args: TransferFromArgs =
+                    candid::decode_one(payload.update_content().arg.0.as_ref()).map_err(|e| {
+                        ApiError::invalid_request(format!(
+                            "Could not parse transfer_from args: {}",
+                            e
+                        ))
+                    })?;
+                TransferFrom::try_from_args(pid, args).map(Request::TransferFrom)
+            }
             RequestType::Stake { neuron_index } => Ok(Request::Stake(Stake {
                 account,
                 neuron_index: *neuron_index,
@@ -3,9 +3,9 @@ use crate::errors::ApiError;
 use crate::models::{ConstructionParseRequest, ConstructionParseResponse, ParsedTransaction};
 use crate::request_handler::{verify_network_id, RosettaRequestHandler};
 use crate::request_types::{
-    AddHotKey, ChangeAutoStakeMaturity, Disburse, Follow, MergeMaturity, NeuronInfo,
+    AddHotKey, Approve, ChangeAutoStakeMaturity, Disburse, Follow, MergeMaturity, NeuronInfo,
     PublicKeyOrPrincipal, RegisterVote, RemoveHotKey, RequestType, SetDissolveTimestamp, Spawn,
-    Stake, StakeMaturity, StartDissolve, StopDissolve,
+    Stake, StakeMaturity, StartDissolve, StopDissolve, TransferFrom,
 };
 use rosetta_core::objects::ObjectMap;
 
@@ -19,6 +19,8 @@ use crate::request::Request;
 use ic_types::messages::{Blob, HttpCallContent, HttpCanisterUpdate};
 use ic_types::PrincipalId;
 use icp_ledger::{AccountIdentifier, Operation, SendArgs};
+use icrc_ledger_types::icrc2::approve::ApproveArgs;
+use icrc_ledger_types::icrc2::transfer_from::TransferFromArgs;
 use std::convert::TryFrom;
 
 impl RosettaRequestHandler {
@@ -47,15 +49,19 @@ impl RosettaRequestHandler {
         let mut metadata = serde_json::Map::new();
 
         for (request_type, HttpCanisterUpdate { arg, sender, .. }) in updates {
-            let from = PrincipalId::try_from(sender.0)
-                .map_err(|e| ApiError::internal_error(e.to_string()))?
-                .into();
+            let sender_pid = PrincipalId::try_from(sender.0)
+                .map_err(|e| ApiError::internal_error(e.to_string()))?;
+            let from = sender_pid.into();
             if msg.signed {
                 from_ai.push(from);
             }
 
             match request_type {
                 RequestType::Send => send(&mut requests, &mut metadata, arg, from)?,
+                RequestType::Approve => approve(&mut requests, &mut metadata, arg, sender_pid)?,
+                RequestType::TransferFrom => {
+                    transfer_from(&mut requests, &mut metadata, arg, sender_pid)?
+                }
                 RequestType::Stake { neuron_index } => {
                     stake(&mut requests, arg, from, neuron_index)?
                 }
@@ -148,6 +154,48 @@ fn send(
     Ok(())
 }
 
+/// Handle APPROVE.
+fn approve(
+    requests: &mut Vec<Request>,
+    metadata: &mut ObjectMap,
+    arg: Blob,
+    caller: PrincipalId,
+) -> Result<(), ApiError> {
+    let args: ApproveArgs = candid::decode_one(arg.0.as_ref()).map_err(|e| {
+        ApiError::internal_error(format!("Could not decode Approve argument: {:?}", e))
+    })?;
+    if let Some(created_at_time) = args.created_at_time {
+        metadata.insert(
+            "created_at_time".into(),
+            serde_json::to_value(created_at_time).unwrap(),
+        );
+    }
+    requests.push(Request::Approve(Approve::try_from_args(caller, args)?));
+    Ok(())
+}
+
+/// Handle TRANSFER_FROM.
+fn transfer_from(
+    requests: &mut Vec<Request>,
+    metadata: &mut ObjectMap,
+    arg: Blob,
+    caller: PrincipalId,
+) -> Result<(), ApiError> {
+    let args: TransferFromArgs = candid::decode_one(arg.0.as_ref()).map_err(|e| {
+        ApiError::internal_error(format!("Could not decode TransferFrom argument: {:?}", e))
+    })?;
+    if let Some(created_at_time) = args.created_at_time {
+        metadata.insert(
+            "created_at_time".into(),
+            serde_json::to_value(created_at_time).unwrap(),
+        );
+    }
+    requests.push(Request::TransferFrom(TransferFrom::try_from_args(
+        caller, args,
+    )?));
+    Ok(())
+}
+
 /// Handle STAKE.
 fn stake(
     requests: &mut Vec<Request>,
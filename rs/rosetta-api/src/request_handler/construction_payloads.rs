@@ -24,9 +24,9 @@ use crate::models::{
 use crate::request::Request;
 use crate::request_handler::{make_sig_data, verify_network_id, RosettaRequestHandler};
 use crate::request_types::{
-    AddHotKey, ChangeAutoStakeMaturity, Disburse, Follow, MergeMaturity, NeuronInfo,
+    AddHotKey, Approve, ChangeAutoStakeMaturity, Disburse, Follow, MergeMaturity, NeuronInfo,
     PublicKeyOrPrincipal, RegisterVote, RemoveHotKey, RequestType, SetDissolveTimestamp, Spawn,
-    Stake, StakeMaturity, StartDissolve, StopDissolve,
+    Stake, StakeMaturity, StartDissolve, StopDissolve, TransferFrom,
 };
 use crate::{convert, models};
 
@@ -111,6 +111,24 @@ impl RosettaRequestHandler {
                     &pks_map,
                     &ingress_expiries,
                 )?,
+                Request::Approve(req) => handle_approve(
+                    req,
+                    created_at_time,
+                    &self.ledger,
+                    &mut payloads,
+                    &mut updates,
+                    &pks_map,
+                    &ingress_expiries,
+                )?,
+                Request::TransferFrom(req) => handle_transfer_from(
+                    req,
+                    created_at_time,
+                    &self.ledger,
+                    &mut payloads,
+                    &mut updates,
+                    &pks_map,
+                    &ingress_expiries,
+                )?,
                 Request::NeuronInfo(req) => handle_neuron_info(
                     req,
                     &mut payloads,
@@ -241,7 +259,7 @@ fn handle_transfer(
             "Mint operations are not supported through Rosetta.",
         )),
         Operation::Approve { .. } => Err(ApiError::invalid_request(
-            "Approve operations are not supported through Rosetta.",
+            "Approve operations must be submitted as APPROVE operations.",
         )),
         Operation::Transfer {
             from,
@@ -252,7 +270,7 @@ fn handle_transfer(
         } => {
             if spender.is_some() {
                 return Err(ApiError::invalid_request(
-                    "TransferFrom operations are not supported through Rosetta.",
+                    "TransferFrom operations must be submitted as TRANSFER_FROM operations.",
                 ));
             }
             handle_transfer_operation(
@@ -324,6 +342,89 @@ fn handle_transfer_operation(
     Ok(())
 }
 
+/// Handle APPROVE.
+fn handle_approve(
+    req: Approve,
+    created_at_time: ic_ledger_core::timestamp::TimeStamp,
+    ledger: &Arc<dyn LedgerAccess + Send + Sync>,
+    payloads: &mut Vec<SigningPayload>,
+    updates: &mut Vec<(RequestType, HttpCanisterUpdate)>,
+    pks_map: &HashMap<icp_ledger::AccountIdentifier, &PublicKey>,
+    ingress_expiries: &[u64],
+) -> Result<(), ApiError> {
+    let pk = pks_map.get(&req.account).ok_or_else(|| {
+        ApiError::internal_error(format!(
+            "Cannot find public key for account identifier {}",
+            req.account,
+        ))
+    })?;
+
+    let args = req.to_args(Some(created_at_time.as_nanos_since_unix_epoch()));
+    let update = HttpCanisterUpdate {
+        canister_id: Blob(ledger.ledger_canister_id().get().to_vec()),
+        method_name: "icrc2_approve".to_string(),
+        arg: Blob(candid::encode_one(args).map_err(|e| {
+            ApiError::internal_error(format!("Serialization of approve args failed: {:?}", e))
+        })?),
+        nonce: None,
+        sender: Blob(convert::principal_id_from_public_key(pk)?.into_vec()),
+        ingress_expiry: 0,
+    };
+
+    add_payloads(
+        payloads,
+        ingress_expiries,
+        &convert::to_model_account_identifier(&req.account),
+        &update,
+    );
+    updates.push((RequestType::Approve, update));
+    Ok(())
+}
+
+/// Handle TRANSFER_FROM.
+fn handle_transfer_from(
+    req: TransferFrom,
+    created_at_time: ic_ledger_core::timestamp::TimeStamp,
+    ledger: &Arc<dyn LedgerAccess + Send + Sync>,
+    payloads: &mut Vec<SigningPayload>,
+    updates: &mut Vec<(RequestType, HttpCanisterUpdate)>,
+    pks_map: &HashMap<icp_ledger::AccountIdentifier, &PublicKey>,
+    ingress_expiries: &[u64],
+) -> Result<(), ApiError> {
+    // The spender signs the call, so its account must be the default account
+    // of one of the provided public keys.
+    let pk = pks_map.get(&req.spender).ok_or_else(|| {
+        ApiError::internal_error(format!(
+            "Cannot find public key for spender account identifier {}",
+            req.spender,
+        ))
+    })?;
+
+    let args = req.to_args(Some(created_at_time.as_nanos_since_unix_epoch()));
+    let update = HttpCanisterUpdate {
+        canister_id: Blob(ledger.ledger_canister_id().get().to_vec()),
+        method_name: "icrc2_transfer_from".to_string(),
+        arg: Blob(candid::encode_one(args).map_err(|e| {
+            ApiError::internal_error(format!(
+                "Serialization of transfer_from args failed: {:?}",
+                e
+            ))
+        })?),
+        nonce: None,
+        sender: Blob(convert::principal_id_from_public_key(pk)?.into_vec()),
+        ingress_expiry: 0,
+    };
+
+    add_payloads(
+        payloads,
+        ingress_expiries,
+        &convert::to_model_account_identifier(&req.spender),
+        &update,
+    );
+    updates.push((RequestType::TransferFrom, update));
+    Ok(())
+}
+
 /// Handle NEURON_INFO.
 fn handle_neuron_info(
     req: NeuronInfo,
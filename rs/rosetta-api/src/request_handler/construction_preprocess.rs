@@ -7,8 +7,9 @@ use crate::models::{
 use crate::request::Request;
 use crate::request_handler::{verify_network_id, RosettaRequestHandler};
 use crate::request_types::{
-    AddHotKey, ChangeAutoStakeMaturity, Disburse, Follow, MergeMaturity, NeuronInfo, RegisterVote,
-    RemoveHotKey, SetDissolveTimestamp, Spawn, Stake, StakeMaturity, StartDissolve, StopDissolve,
+    AddHotKey, Approve, ChangeAutoStakeMaturity, Disburse, Follow, MergeMaturity, NeuronInfo,
+    RegisterVote, RemoveHotKey, SetDissolveTimestamp, Spawn, Stake, StakeMaturity, StartDissolve,
+    StopDissolve, TransferFrom,
 };
 use icp_ledger::Operation;
 use std::collections::HashSet;
@@ -65,7 +66,9 @@ fn required_public_key(request: Request) -> Result<icp_ledger::AccountIdentifier
         Request::Transfer(Operation::Approve { .. }) => Err(ApiError::invalid_request(
             "Approve operations are not supported through rosetta",
         )),
-        Request::Stake(Stake { account, .. })
+        Request::TransferFrom(TransferFrom { spender, .. }) => Ok(spender),
+        Request::Approve(Approve { account, .. })
+        | Request::Stake(Stake { account, .. })
         | Request::SetDissolveTimestamp(SetDissolveTimestamp { account, .. })
         | Request::ChangeAutoStakeMaturity(ChangeAutoStakeMaturity { account, .. })
         | Request::StartDissolve(StartDissolve { account, .. })
@@ -8,8 +8,12 @@ use crate::{
     models::{self, Operation},
     transaction_id::TransactionIdentifier,
 };
+use candid::Nat;
 use ic_types::PrincipalId;
-use icp_ledger::{AccountIdentifier, BlockIndex, Operation as LedgerOperation, Tokens};
+use icp_ledger::{AccountIdentifier, BlockIndex, Operation as LedgerOperation, Subaccount, Tokens};
+use icrc_ledger_types::icrc1::account::Account;
+use icrc_ledger_types::icrc2::approve::ApproveArgs;
+use icrc_ledger_types::icrc2::transfer_from::TransferFromArgs;
 use rosetta_core::objects::ObjectMap;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -20,6 +24,8 @@ use std::convert::TryFrom;
 pub const STATUS_COMPLETED: &str = "COMPLETED";
 
 pub const TRANSACTION: &str = "TRANSACTION";
+pub const APPROVE: &str = "APPROVE";
+pub const TRANSFER_FROM: &str = "TRANSFER_FROM";
 pub const MINT: &str = "MINT";
 pub const BURN: &str = "BURN";
 pub const FEE: &str = "FEE";
@@ -48,6 +54,10 @@ pub enum RequestType {
     #[serde(rename = "TRANSACTION")]
     #[serde(alias = "Send")]
     Send,
+    #[serde(rename = "APPROVE")]
+    Approve,
+    #[serde(rename = "TRANSFER_FROM")]
+    TransferFrom,
     #[serde(rename = "STAKE")]
     #[serde(alias = "Stake")]
     Stake { neuron_index: u64 },
@@ -102,6 +112,8 @@ impl RequestType {
     pub fn into_str(self) -> &'static str {
         match self {
             RequestType::Send { .. } => TRANSACTION,
+            RequestType::Approve => APPROVE,
+            RequestType::TransferFrom => TRANSFER_FROM,
             RequestType::Stake { .. } => STAKE,
             RequestType::SetDissolveTimestamp { .. } => SET_DISSOLVE_TIMESTAMP,
             RequestType::ChangeAutoStakeMaturity { .. } => CHANGE_AUTO_STAKE_MATURITY,
@@ -120,7 +132,10 @@ impl RequestType {
     }
 
     pub const fn is_transfer(&self) -> bool {
-        matches!(self, RequestType::Send)
+        matches!(
+            self,
+            RequestType::Send | RequestType::Approve | RequestType::TransferFrom
+        )
     }
 
     pub const fn is_neuron_management(&self) -> bool {
@@ -269,6 +284,97 @@ pub struct StartDissolve {
     pub neuron_index: u64,
 }
 
+/// An ICRC-2 approval issued by the default account of the signer.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Approve {
+    pub account: icp_ledger::AccountIdentifier,
+    pub spender: Account,
+    pub allowance: Tokens,
+    pub expected_allowance: Option<Tokens>,
+    pub expires_at: Option<u64>,
+    pub fee: Tokens,
+}
+
+/// An ICRC-2 transfer initiated by the default account of the signer using an
+/// allowance previously granted by the owner of `from`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TransferFrom {
+    pub spender: icp_ledger::AccountIdentifier,
+    pub from: Account,
+    pub to: Account,
+    pub amount: Tokens,
+    pub fee: Tokens,
+}
+
+impl Approve {
+    /// Converts the arguments of an `icrc2_approve` call made by `caller`.
+    pub fn try_from_args(caller: PrincipalId, args: ApproveArgs) -> Result<Self, ApiError> {
+        let fee = args
+            .fee
+            .ok_or_else(|| ApiError::invalid_request("Approve requests must specify a fee"))?;
+        Ok(Self {
+            account: AccountIdentifier::new(caller, args.from_subaccount.map(Subaccount)),
+            spender: args.spender,
+            allowance: tokens_from_nat(args.amount)?,
+            expected_allowance: args.expected_allowance.map(tokens_from_nat).transpose()?,
+            expires_at: args.expires_at,
+            fee: tokens_from_nat(fee)?,
+        })
+    }
+
+    /// The arguments of the `icrc2_approve` call that performs this request.
+    pub fn to_args(&self, created_at_time: Option<u64>) -> ApproveArgs {
+        ApproveArgs {
+            from_subaccount: None,
+            spender: self.spender,
+            amount: Nat::from(self.allowance.get_e8s()),
+            expected_allowance: self
+                .expected_allowance
+                .map(|tokens| Nat::from(tokens.get_e8s())),
+            expires_at: self.expires_at,
+            fee: Some(Nat::from(self.fee.get_e8s())),
+            memo: None,
+            created_at_time,
+        }
+    }
+}
+
+impl TransferFrom {
+    /// Converts the arguments of an `icrc2_transfer_from` call made by `caller`.
+    pub fn try_from_args(caller: PrincipalId, args: TransferFromArgs) -> Result<Self, ApiError> {
+        let fee = args
+            .fee
+            .ok_or_else(|| ApiError::invalid_request("TransferFrom requests must specify a fee"))?;
+        Ok(Self {
+            spender: AccountIdentifier::new(caller, args.spender_subaccount.map(Subaccount)),
+            from: args.from,
+            to: args.to,
+            amount: tokens_from_nat(args.amount)?,
+            fee: tokens_from_nat(fee)?,
+        })
+    }
+
+    /// The arguments of the `icrc2_transfer_from` call that performs this
+    /// request.
+    pub fn to_args(&self, created_at_time: Option<u64>) -> TransferFromArgs {
+        TransferFromArgs {
+            spender_subaccount: None,
+            from: self.from,
+            to: self.to,
+            amount: Nat::from(self.amount.get_e8s()),
+            fee: Some(Nat::from(self.fee.get_e8s())),
+            memo: None,
+            created_at_time,
+        }
+    }
+}
+
+fn tokens_from_nat(n: Nat) -> Result<Tokens, ApiError> {
+    u64::try_from(n.0)
+        .map(Tokens::from_e8s)
+        .map_err(|e| ApiError::invalid_request(format!("Amount does not fit in u64: {}", e)))
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct StopDissolve {
     pub account: icp_ledger::AccountIdentifier,
@@ -839,6 +945,11 @@ pub struct ApproveMetadata {
     pub allowance: Tokens,
     pub expected_allowance: Option<Tokens>,
     pub expires_at: Option<u64>,
+    /// The ICRC-1 account of the spender. Blocks only record the account
+    /// identifier, but the ledger requires the full account to construct an
+    /// approval.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spender_account: Option<Account>,
 }
 
 impl TryFrom<Option<ObjectMap>> for ApproveMetadata {
@@ -862,6 +973,38 @@ impl From<ApproveMetadata> for ObjectMap {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TransferFromMetadata {
+    pub spender: AccountIdentifier,
+    /// The ICRC-1 accounts of the owner and the recipient. Like for
+    /// `ApproveMetadata`, these are only needed to construct a transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_account: Option<Account>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_account: Option<Account>,
+}
+
+impl TryFrom<Option<ObjectMap>> for TransferFromMetadata {
+    type Error = ApiError;
+    fn try_from(o: Option<ObjectMap>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::Value::Object(o.unwrap_or_default())).map_err(|e| {
+            ApiError::internal_error(format!(
+                "Could not parse a TRANSFER_FROM operation metadata from metadata JSON object: {}",
+                e
+            ))
+        })
+    }
+}
+
+impl From<TransferFromMetadata> for ObjectMap {
+    fn from(m: TransferFromMetadata) -> Self {
+        match serde_json::to_value(m) {
+            Ok(Value::Object(o)) => o,
+            _ => unreachable!(),
+        }
+    }
+}
+
 /// Transaction is a bit of a misnomer, since operations can succeed or fail
 /// independently from a Transaction.
 #[derive(Default)]
@@ -947,6 +1090,7 @@ impl TransactionBuilder {
                             spender: *spender,
                             expected_allowance: *expected_allowance,
                             expires_at: expires_at.map(|ts| ts.as_nanos_since_unix_epoch()),
+                            spender_account: None,
                         }
                         .into(),
                     ),
@@ -963,14 +1107,30 @@ impl TransactionBuilder {
                 to,
                 amount,
                 fee,
-                ..
+                spender,
             } => {
                 let amount = i128::from(amount.get_e8s());
+                // Transfers initiated by a spender debit the owner through a
+                // TRANSFER_FROM operation that records who used the allowance.
+                let (debit_type, debit_metadata) = match spender {
+                    Some(spender) => (
+                        OperationType::TransferFrom,
+                        Some(
+                            TransferFromMetadata {
+                                spender: *spender,
+                                from_account: None,
+                                to_account: None,
+                            }
+                            .into(),
+                        ),
+                    ),
+                    None => (OperationType::Transaction, None),
+                };
                 push_op(
-                    OperationType::Transaction,
+                    debit_type,
                     Some(to_model_account_identifier(from)),
                     Some(signed_amount(-amount, token_name)),
-                    None,
+                    debit_metadata,
                 );
                 push_op(
                     OperationType::Transaction,
@@ -989,6 +1149,103 @@ impl TransactionBuilder {
         Ok(())
     }
 
+    /// Add a `Request::Approve` to the Transaction.
+    pub fn approve(&mut self, approve: &Approve, token_name: &str) {
+        let Approve {
+            account,
+            spender,
+            allowance,
+            expected_allowance,
+            expires_at,
+            fee,
+        } = approve;
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: OperationType::Approve.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: None,
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                ApproveMetadata {
+                    from: *account,
+                    spender: AccountIdentifier::from(*spender),
+                    allowance: *allowance,
+                    expected_allowance: *expected_allowance,
+                    expires_at: *expires_at,
+                    spender_account: Some(*spender),
+                }
+                .into(),
+            ),
+        });
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: OperationType::Fee.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(account)),
+            amount: Some(signed_amount(-i128::from(fee.get_e8s()), token_name)),
+            related_operations: None,
+            coin_change: None,
+            metadata: None,
+        });
+    }
+
+    /// Add a `Request::TransferFrom` to the Transaction.
+    pub fn transfer_from(&mut self, transfer_from: &TransferFrom, token_name: &str) {
+        let TransferFrom {
+            spender,
+            from,
+            to,
+            amount,
+            fee,
+        } = transfer_from;
+        let from_identifier = AccountIdentifier::from(*from);
+        let amount = i128::from(amount.get_e8s());
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: OperationType::TransferFrom.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(&from_identifier)),
+            amount: Some(signed_amount(-amount, token_name)),
+            related_operations: None,
+            coin_change: None,
+            metadata: Some(
+                TransferFromMetadata {
+                    spender: *spender,
+                    from_account: Some(*from),
+                    to_account: Some(*to),
+                }
+                .into(),
+            ),
+        });
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: OperationType::Transaction.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(&AccountIdentifier::from(*to))),
+            amount: Some(signed_amount(amount, token_name)),
+            related_operations: None,
+            coin_change: None,
+            metadata: None,
+        });
+        let operation_identifier = self.allocate_op_id();
+        self.ops.push(Operation {
+            operation_identifier,
+            _type: OperationType::Fee.to_string(),
+            status: None,
+            account: Some(to_model_account_identifier(&from_identifier)),
+            amount: Some(signed_amount(-i128::from(fee.get_e8s()), token_name)),
+            related_operations: None,
+            coin_change: None,
+            metadata: None,
+        });
+    }
+
     pub fn stake(&mut self, stake: &Stake) {
         let Stake {
             account,
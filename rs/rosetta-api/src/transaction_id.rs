@@ -1,13 +1,19 @@
 use std::{convert::TryFrom, str::FromStr};
 
-use crate::{convert, errors::ApiError, request_types::RequestType};
+use crate::{
+    convert,
+    errors::ApiError,
+    request_types::{Approve, RequestType, TransferFrom},
+};
 use ic_ledger_canister_core::ledger::LedgerTransaction;
 use ic_ledger_hash_of::HashOf;
 use ic_types::{
     messages::{HttpCallContent, HttpRequestEnvelope},
     PrincipalId,
 };
-use icp_ledger::{SendArgs, Transaction};
+use icp_ledger::{AccountIdentifier, Memo, Operation, SendArgs, TimeStamp, Transaction};
+use icrc_ledger_types::icrc2::approve::ApproveArgs;
+use icrc_ledger_types::icrc2::transfer_from::TransferFromArgs;
 use serde::{Deserialize, Serialize};
 
 pub const NEURON_MANAGEMENT_PSEUDO_HASH: &str =
@@ -45,13 +51,81 @@ impl TransactionIdentifier {
                     "A transaction ID cannot be generated from a constructed transaction without an explicit 'created_at_time'"
             ))?;
 
-                let from = icp_ledger::AccountIdentifier::new(from, from_subaccount);
+                let from = AccountIdentifier::new(from, from_subaccount);
 
                 let hash =
                     Transaction::new(from, to, None, amount, fee, memo, created_at_time).hash();
 
                 Ok(TransactionIdentifier::from(&hash))
             }
+            RequestType::Approve => {
+                let HttpCallContent::Call { update } = &signed_transaction.content;
+                let caller = PrincipalId::try_from(update.sender.clone().0)
+                    .map_err(|e| ApiError::internal_error(e.to_string()))?;
+                let args: ApproveArgs = candid::decode_one(update.arg.0.as_ref()).map_err(|e| {
+                    ApiError::internal_error(format!("Could not decode approve args: {}", e))
+                })?;
+                let created_at_time = args.created_at_time.ok_or_else(|| ApiError::internal_error(
+                    "A transaction ID cannot be generated from a constructed transaction without an explicit 'created_at_time'"
+                ))?;
+                let Approve {
+                    account,
+                    spender,
+                    allowance,
+                    expected_allowance,
+                    expires_at,
+                    fee,
+                } = Approve::try_from_args(caller, args)?;
+                let transaction = Transaction {
+                    operation: Operation::Approve {
+                        from: account,
+                        spender: AccountIdentifier::from(spender),
+                        allowance,
+                        expected_allowance,
+                        expires_at: expires_at.map(TimeStamp::from_nanos_since_unix_epoch),
+                        fee,
+                    },
+                    memo: Memo(0),
+                    created_at_time: Some(TimeStamp::from_nanos_since_unix_epoch(created_at_time)),
+                    icrc1_memo: None,
+                };
+                Ok(TransactionIdentifier::from(&transaction))
+            }
+            RequestType::TransferFrom => {
+                let HttpCallContent::Call { update } = &signed_transaction.content;
+                let caller = PrincipalId::try_from(update.sender.clone().0)
+                    .map_err(|e| ApiError::internal_error(e.to_string()))?;
+                let args: TransferFromArgs =
+                    candid::decode_one(update.arg.0.as_ref()).map_err(|e| {
+                        ApiError::internal_error(format!(
+                            "Could not decode transfer_from args: {}",
+                            e
+                        ))
+                    })?;
+                let created_at_time = args.created_at_time.ok_or_else(|| ApiError::internal_error(
+                    "A transaction ID cannot be generated from a constructed transaction without an explicit 'created_at_time'"
+                ))?;
+                let TransferFrom {
+                    spender,
+                    from,
+                    to,
+                    amount,
+                    fee,
+                } = TransferFrom::try_from_args(caller, args)?;
+                let transaction = Transaction {
+                    operation: Operation::Transfer {
+                        from: AccountIdentifier::from(from),
+                        to: AccountIdentifier::from(to),
+                        spender: Some(spender),
+                        amount,
+                        fee,
+                    },
+                    memo: Memo(0),
+                    created_at_time: Some(TimeStamp::from_nanos_since_unix_epoch(created_at_time)),
+                    icrc1_memo: None,
+                };
+                Ok(TransactionIdentifier::from(&transaction))
+            }
             RequestType::Stake { .. }
             | RequestType::StartDissolve { .. }
             | RequestType::StopDissolve { .. }
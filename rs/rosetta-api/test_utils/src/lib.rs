@@ -17,8 +17,9 @@ use ic_rosetta_api::request::transaction_operation_results::TransactionOperation
 use ic_rosetta_api::request::transaction_results::TransactionResults;
 use ic_rosetta_api::request::Request;
 use ic_rosetta_api::request_types::{
-    AddHotKey, ChangeAutoStakeMaturity, Disburse, Follow, MergeMaturity, NeuronInfo, RegisterVote,
-    RemoveHotKey, SetDissolveTimestamp, Spawn, Stake, StakeMaturity, StartDissolve, StopDissolve,
+    AddHotKey, Approve, ChangeAutoStakeMaturity, Disburse, Follow, MergeMaturity, NeuronInfo,
+    RegisterVote, RemoveHotKey, SetDissolveTimestamp, Spawn, Stake, StakeMaturity, StartDissolve,
+    StopDissolve, TransferFrom,
 };
 use ic_rosetta_api::transaction_id::TransactionIdentifier;
 use ic_rosetta_api::{convert, errors, errors::ApiError, DEFAULT_TOKEN_SYMBOL};
@@ -108,6 +109,16 @@ where
                 // just a sanity check
                 assert!(fee_found, "There should be a fee op in operations");
             }
+            Request::Approve(Approve { account, fee, .. }) => {
+                trans_fee_amount = Some(tokens_to_amount(fee, token_name).unwrap());
+                all_sender_account_ids.push(to_model_account_identifier(&account));
+                assert!(fee_found, "There should be a fee op in operations");
+            }
+            Request::TransferFrom(TransferFrom { spender, fee, .. }) => {
+                trans_fee_amount = Some(tokens_to_amount(fee, token_name).unwrap());
+                all_sender_account_ids.push(to_model_account_identifier(&spender));
+                assert!(fee_found, "There should be a fee op in operations");
+            }
             Request::Stake(Stake { account, .. })
             | Request::StartDissolve(StartDissolve { account, .. })
             | Request::StopDissolve(StopDissolve { account, .. })
@@ -9,13 +9,17 @@ use ic_protobuf::{
     proxy::{try_from_option_field, ProxyDecodeError},
     state::{
         canister_state_bits::v1 as pb_canister_state_bits, ingress::v1 as pb_ingress,
-        queues::v1 as pb_queues, stats::v1 as pb_stats, system_metadata::v1 as pb_metadata,
+        queues::v1 as pb_queues, stats::v1 as pb_stats, sync::v1 as pb_sync,
+        system_metadata::v1 as pb_metadata,
     },
 };
 use ic_replicated_state::{
     canister_state::{
         execution_state::{NextScheduledMethod, WasmMetadata},
-        system_state::{wasm_chunk_store::WasmChunkStoreMetadata, CanisterHistory, CyclesUseCase},
+        system_state::{
+            wasm_chunk_store::WasmChunkStoreMetadata, CanisterHistory, CyclesUseCase,
+            OnLowWasmMemoryHookStatus,
+        },
     },
     CallContextManager, CanisterStatus, ExecutionTask, ExportedFunctions, Global, NumWasmPages,
 };
@@ -161,6 +165,9 @@ pub struct CanisterStateBits {
     pub canister_history: CanisterHistory,
     pub wasm_chunk_store_metadata: WasmChunkStoreMetadata,
     pub total_query_stats: TotalQueryStats,
+    pub wasm_memory_limit: Option<NumBytes>,
+    pub wasm_memory_threshold: NumBytes,
+    pub on_low_wasm_memory_hook_status: OnLowWasmMemoryHookStatus,
 }
 
 #[derive(Clone)]
@@ -240,6 +247,14 @@ struct CheckpointRefData {
 /// ├── diverged_state_markers
 /// │   └──<hex(round)>
 /// │
+/// ├── dirty_chunks
+/// │   └──<hex(round)>.pbuf
+/// │
+/// ├── state_sync
+/// │   ├── state_sync_scratchpad_<hex(round)>
+/// │   ├── state_sync_progress_<hex(round)>.pbuf
+/// │   └── state_sync_fetched_chunks_<hex(round)>
+/// │
 /// ├── tmp
 /// └── fs_tmp
 /// ```
@@ -274,15 +289,25 @@ struct CheckpointRefData {
 /// ## Promoting a State Sync artifact to a checkpoint
 ///
 ///   1. Create state files directly in
-///      "<state_root>/fs_tmp/state_sync_scratchpad_<height>".
+///      "<state_root>/state_sync/state_sync_scratchpad_<height>".
+///      The progress of the state sync is recorded next to the scratchpad
+///      so that the state sync can be resumed after a restart.
 ///
 ///   2. When all the writes are complete, call sync_and_mark_files_readonly()
-///      on "<state_root>/fs_tmp/state_sync_scratchpad_<height>".  This function
+///      on "<state_root>/state_sync/state_sync_scratchpad_<height>".  This function
 ///      syncs all the files and directories under the scratchpad directory,
 ///      including the scratchpad directory itself.
 ///
-///   3. Rename "<state_root>/fs_tmp/state_sync_scratchpad_<height>" to
+///   3. Rename "<state_root>/state_sync/state_sync_scratchpad_<height>" to
 ///      "<state_root>/checkpoints/<height>", sync "<state_root>/checkpoints".
+///
+/// ## Dirty chunks
+///
+/// "<state_root>/dirty_chunks/<height>.pbuf" records which chunks of the
+/// checkpoint at `height` might differ from an older checkpoint, so that its
+/// manifest can be computed incrementally even after a restart. The file is
+/// kept outside of the checkpoint as it must not be part of the manifest, and
+/// it is removed whenever the checkpoint at `height` is created or removed.
 
 #[derive(Clone)]
 pub struct StateLayout {
@@ -435,7 +460,9 @@ impl StateLayout {
         WriteOnly::check_dir(&self.checkpoints())?;
         WriteOnly::check_dir(&self.diverged_checkpoints())?;
         WriteOnly::check_dir(&self.diverged_state_markers())?;
+        WriteOnly::check_dir(&self.dirty_chunks_dir())?;
         WriteOnly::check_dir(&self.fs_tmp())?;
+        WriteOnly::check_dir(&self.state_sync_dir())?;
         WriteOnly::check_dir(&self.tip_path())?;
         WriteOnly::check_dir(&self.tmp())?;
         for path in [
@@ -450,6 +477,27 @@ impl StateLayout {
                 io_err: err,
             })?
         }
+        self.cleanup_dirty_chunks()
+    }
+
+    /// Removes the dirty chunks of checkpoints that do not exist anymore.
+    fn cleanup_dirty_chunks(&self) -> Result<(), LayoutError> {
+        let checkpoint_heights: BTreeSet<Height> = self.checkpoint_heights()?.into_iter().collect();
+        let names =
+            dir_file_names(&self.dirty_chunks_dir()).map_err(|err| LayoutError::IoError {
+                path: self.dirty_chunks_dir(),
+                message: "Failed to list dirty chunks".to_string(),
+                io_err: err,
+            })?;
+        for name in names {
+            let height = name
+                .strip_suffix(".pbuf")
+                .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+                .map(Height::new);
+            if height.map_or(true, |h| !checkpoint_heights.contains(&h)) {
+                remove_existing_file(&self.dirty_chunks_dir().join(name))?;
+            }
+        }
         Ok(())
     }
 
@@ -550,13 +598,48 @@ impl StateLayout {
         self.root.join("states_metadata.pbuf")
     }
 
+    fn dirty_chunks_dir(&self) -> PathBuf {
+        self.root.join("dirty_chunks")
+    }
+
+    /// Returns the file recording which chunks of the checkpoint at `height`
+    /// might differ from an older checkpoint.
+    pub fn dirty_chunks(
+        &self,
+        height: Height,
+    ) -> ProtoFileWith<pb_sync::DirtyChunks, RwPolicy<Self>> {
+        self.dirty_chunks_dir()
+            .join(format!("{}.pbuf", Self::checkpoint_name(height)))
+            .into()
+    }
+
+    /// Returns the path to the directory holding the scratchpad and the
+    /// progress of ongoing state syncs. Unlike `tmp`, this directory is not
+    /// cleaned during restart so that an interrupted state sync can be resumed.
+    pub fn state_sync_dir(&self) -> PathBuf {
+        self.root.join("state_sync")
+    }
+
     /// Returns scratchpad used during statesync
     pub fn state_sync_scratchpad(&self, height: Height) -> Result<PathBuf, LayoutError> {
         Ok(self
-            .tmp()
+            .state_sync_dir()
             .join(format!("state_sync_scratchpad_{:016x}", height.get())))
     }
 
+    /// Returns the path to the manifest and the initially missing chunks of
+    /// the statesync at `height`
+    pub fn state_sync_progress(&self, height: Height) -> PathBuf {
+        self.state_sync_dir()
+            .join(format!("state_sync_progress_{:016x}.pbuf", height.get()))
+    }
+
+    /// Returns the path to the log of chunks fetched by the statesync at `height`
+    pub fn state_sync_fetched_chunks(&self, height: Height) -> PathBuf {
+        self.state_sync_dir()
+            .join(format!("state_sync_fetched_chunks_{:016x}", height.get()))
+    }
+
     /// Returns the path to cache an unfinished statesync at `height`
     pub fn state_sync_cache(&self, height: Height) -> Result<PathBuf, LayoutError> {
         let tmp = self.tmp();
@@ -595,6 +678,9 @@ impl StateLayout {
                 io_err: err,
             },
         )?;
+        // Dirty chunks left behind by an older checkpoint at the same height
+        // don't describe the new one.
+        self.dirty_chunks(height).try_remove_file()?;
         std::fs::rename(scratchpad, cp_path).map_err(|err| {
             if is_already_exists_err(&err) {
                 LayoutError::AlreadyExists(height)
@@ -615,6 +701,7 @@ impl StateLayout {
     }
 
     pub fn clone_checkpoint(&self, from: Height, to: Height) -> Result<(), LayoutError> {
+        self.dirty_chunks(to).try_remove_file()?;
         let src = self.checkpoints().join(Self::checkpoint_name(from));
         let dst = self.checkpoints().join(Self::checkpoint_name(to));
         self.copy_and_sync_checkpoint(&Self::checkpoint_name(to), &src, &dst, None)
@@ -793,6 +880,7 @@ impl StateLayout {
         let cp_path = self.checkpoints().join(&cp_name);
         let tmp_path = self.fs_tmp().join(&cp_name);
 
+        self.dirty_chunks(height).try_remove_file()?;
         self.atomically_remove_via_path(&cp_path, &tmp_path, drop_after_rename)
             .map_err(|err| LayoutError::IoError {
                 path: cp_path,
@@ -878,6 +966,7 @@ impl StateLayout {
 
         let dst_path = self.diverged_checkpoints().join(&cp_name);
 
+        self.dirty_chunks(height).try_remove_file()?;
         match std::fs::rename(&cp_path, dst_path) {
             Ok(()) => {
                 for path in [&self.checkpoints(), &self.diverged_checkpoints()] {
@@ -1011,6 +1100,7 @@ impl StateLayout {
             return self.force_remove_checkpoint(height);
         }
 
+        self.dirty_chunks(height).try_remove_file()?;
         std::fs::rename(&cp_path, &dst).map_err(|err| LayoutError::IoError {
             path: cp_path,
             message: format!("failed to archive checkpoint {}", height),
@@ -1766,6 +1856,13 @@ impl From<CanisterStateBits> for pb_canister_state_bits::CanisterStateBits {
             canister_history: Some((&item.canister_history).into()),
             wasm_chunk_store_metadata: Some((&item.wasm_chunk_store_metadata).into()),
             total_query_stats: Some((&item.total_query_stats).into()),
+            wasm_memory_limit: item.wasm_memory_limit.map(|v| v.get()),
+            wasm_memory_threshold: Some(item.wasm_memory_threshold.get()),
+            on_low_wasm_memory_hook_status:
+                pb_canister_state_bits::OnLowWasmMemoryHookStatus::from(
+                    &item.on_low_wasm_memory_hook_status,
+                )
+                .into(),
         }
     }
 }
@@ -1887,6 +1984,14 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
                 "CanisterStateBits::total_query_stats",
             )
             .unwrap_or_default(),
+            wasm_memory_limit: value.wasm_memory_limit.map(NumBytes::from),
+            wasm_memory_threshold: NumBytes::from(value.wasm_memory_threshold.unwrap_or(0)),
+            on_low_wasm_memory_hook_status:
+                pb_canister_state_bits::OnLowWasmMemoryHookStatus::try_from(
+                    value.on_low_wasm_memory_hook_status,
+                )
+                .unwrap_or_default()
+                .into(),
         })
     }
 }
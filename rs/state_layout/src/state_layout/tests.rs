@@ -3,7 +3,9 @@ use super::*;
 use ic_ic00_types::{
     CanisterChange, CanisterChangeDetails, CanisterChangeOrigin, CanisterInstallMode, IC_00,
 };
-use ic_replicated_state::canister_state::system_state::CanisterHistory;
+use ic_replicated_state::canister_state::system_state::{
+    CanisterHistory, OnLowWasmMemoryHookStatus,
+};
 use ic_replicated_state::metadata_state::subnet_call_context_manager::InstallCodeCallId;
 use ic_test_utilities::types::ids::user_test_id;
 use ic_test_utilities::{
@@ -53,6 +55,9 @@ fn default_canister_state_bits() -> CanisterStateBits {
         canister_history: CanisterHistory::default(),
         wasm_chunk_store_metadata: WasmChunkStoreMetadata::default(),
         total_query_stats: TotalQueryStats::default(),
+        wasm_memory_limit: None,
+        wasm_memory_threshold: NumBytes::from(0),
+        on_low_wasm_memory_hook_status: OnLowWasmMemoryHookStatus::default(),
     }
 }
 
@@ -113,6 +118,48 @@ fn test_encode_decode_non_empty_controllers() {
     assert_eq!(canister_state_bits.controllers, expected_controllers);
 }
 
+#[test]
+fn test_encode_decode_wasm_memory_limit() {
+    for wasm_memory_limit in [None, Some(NumBytes::from(0)), Some(NumBytes::from(1 << 30))] {
+        let canister_state_bits = CanisterStateBits {
+            wasm_memory_limit,
+            ..default_canister_state_bits()
+        };
+
+        let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+        let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+
+        assert_eq!(canister_state_bits.wasm_memory_limit, wasm_memory_limit);
+    }
+}
+
+#[test]
+fn test_encode_decode_on_low_wasm_memory_hook() {
+    for on_low_wasm_memory_hook_status in [
+        OnLowWasmMemoryHookStatus::ConditionNotSatisfied,
+        OnLowWasmMemoryHookStatus::Ready,
+        OnLowWasmMemoryHookStatus::Executed,
+    ] {
+        let canister_state_bits = CanisterStateBits {
+            wasm_memory_threshold: NumBytes::from(1 << 20),
+            on_low_wasm_memory_hook_status,
+            ..default_canister_state_bits()
+        };
+
+        let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+        let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+
+        assert_eq!(
+            canister_state_bits.wasm_memory_threshold,
+            NumBytes::from(1 << 20)
+        );
+        assert_eq!(
+            canister_state_bits.on_low_wasm_memory_hook_status,
+            on_low_wasm_memory_hook_status
+        );
+    }
+}
+
 #[test]
 fn test_encode_decode_empty_history() {
     let canister_history = CanisterHistory::default();
@@ -180,6 +227,30 @@ fn test_encode_decode_non_empty_history() {
         CanisterChangeOrigin::from_canister(canister_test_id(123).get(), None),
         CanisterChangeDetails::controllers_change(vec![]),
     ));
+    canister_history.add_canister_change(CanisterChange::new(
+        555,
+        7,
+        CanisterChangeOrigin::from_user(user_test_id(42).get()),
+        CanisterChangeDetails::compute_allocation_change(50),
+    ));
+    canister_history.add_canister_change(CanisterChange::new(
+        555,
+        7,
+        CanisterChangeOrigin::from_user(user_test_id(42).get()),
+        CanisterChangeDetails::memory_allocation_change(1 << 30),
+    ));
+    canister_history.add_canister_change(CanisterChange::new(
+        555,
+        7,
+        CanisterChangeOrigin::from_user(user_test_id(42).get()),
+        CanisterChangeDetails::freezing_threshold_change(2_592_000),
+    ));
+    canister_history.add_canister_change(CanisterChange::new(
+        555,
+        7,
+        CanisterChangeOrigin::from_user(user_test_id(42).get()),
+        CanisterChangeDetails::reserved_cycles_limit_change(u128::MAX),
+    ));
 
     // A canister state with non-empty history.
     let canister_state_bits = CanisterStateBits {
@@ -299,6 +370,63 @@ fn test_removal_when_last_dropped() {
     });
 }
 
+#[test]
+fn test_dirty_chunks_are_removed_with_their_checkpoint() {
+    with_test_replica_logger(|log| {
+        let tempdir = tmpdir("state_layout");
+        let root_path = tempdir.path().to_path_buf();
+        let metrics_registry = ic_metrics::MetricsRegistry::new();
+        let state_layout =
+            StateLayout::try_new(log.clone(), root_path.clone(), &metrics_registry).unwrap();
+        let scratchpad_dir = tmpdir("scratchpad");
+        for h in [1, 2] {
+            state_layout
+                .scratchpad_to_checkpoint(
+                    CheckpointLayout::<RwPolicy<()>>::new_untracked(
+                        scratchpad_dir.path().to_path_buf().join(h.to_string()),
+                        Height::new(h),
+                    )
+                    .unwrap(),
+                    Height::new(h),
+                    None,
+                )
+                .unwrap();
+        }
+
+        let dirty_chunks = pb_sync::DirtyChunks {
+            base_height: 1,
+            files: vec![],
+        };
+        for h in [1, 2, 7] {
+            state_layout
+                .dirty_chunks(Height::new(h))
+                .serialize(dirty_chunks.clone())
+                .unwrap();
+        }
+
+        state_layout.remove_checkpoint_when_unused(Height::new(1));
+        assert!(!state_layout
+            .dirty_chunks(Height::new(1))
+            .raw_path()
+            .exists());
+
+        // There is no checkpoint @7, so its dirty chunks are removed on restart.
+        let state_layout =
+            StateLayout::try_new(log, root_path, &ic_metrics::MetricsRegistry::new()).unwrap();
+        assert!(!state_layout
+            .dirty_chunks(Height::new(7))
+            .raw_path()
+            .exists());
+        assert_eq!(
+            state_layout
+                .dirty_chunks(Height::new(2))
+                .deserialize()
+                .unwrap(),
+            dirty_chunks
+        );
+    });
+}
+
 #[test]
 #[should_panic]
 #[cfg(debug_assertions)]
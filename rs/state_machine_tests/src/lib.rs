@@ -34,7 +34,7 @@ use ic_interfaces::{
     validation::ValidationResult,
 };
 use ic_interfaces_certified_stream_store::{CertifiedStreamStore, EncodeStreamError};
-use ic_interfaces_registry::RegistryClient;
+use ic_interfaces_registry::{RegistryClient, RegistryDataProvider, ZERO_REGISTRY_VERSION};
 use ic_interfaces_state_manager::{
     CertificationScope, Labeled, StateHashError, StateManager, StateReader,
 };
@@ -65,6 +65,7 @@ use ic_registry_routing_table::{
 };
 use ic_registry_subnet_features::{EcdsaConfig, SubnetFeatures, DEFAULT_ECDSA_MAX_QUEUE_SIZE};
 use ic_registry_subnet_type::SubnetType;
+use ic_registry_transport::pb::v1::RegistryMutation;
 use ic_replicated_state::canister_state::system_state::CyclesUseCase;
 use ic_replicated_state::metadata_state::subnet_call_context_manager::SignWithEcdsaContext;
 use ic_replicated_state::page_map::Buffer;
@@ -124,6 +125,7 @@ use ic_xnet_payload_builder::{
 pub use ic_error_types::RejectCode;
 use maplit::btreemap;
 use rand::{rngs::StdRng, SeedableRng};
+use registry_canister::registry::Registry;
 use serde::Serialize;
 pub use slog::Level;
 use std::collections::hash_map::DefaultHasher;
@@ -211,7 +213,22 @@ fn make_nodes_registry(
     features: SubnetFeatures,
     registry_version: RegistryVersion,
     registry_data_provider: Arc<ProtoRegistryDataProvider>,
+    keep_existing_subnet_records: bool,
 ) -> Arc<FakeRegistryClient> {
+    // A subnet that resumes after a split keeps its records in the shared
+    // registry.
+    if keep_existing_subnet_records {
+        let registry_client = Arc::new(FakeRegistryClient::new(
+            Arc::clone(&registry_data_provider) as _,
+        ));
+        registry_client.update_to_latest_version();
+        if let Ok(Some(_)) =
+            registry_client.get_subnet_record(subnet_id, registry_client.get_latest_version())
+        {
+            return registry_client;
+        }
+    }
+
     // ECDSA subnet_id must be different from nns_subnet_id, otherwise
     // `sign_with_ecdsa` won't be charged.
     let subnet_id_proto = SubnetIdProto {
@@ -327,6 +344,45 @@ fn make_nodes_registry(
     registry_client
 }
 
+/// Applies the mutations made by `f` from the registry canister's view of the
+/// records in `registry_data_provider`, as a new registry version. The global
+/// invariants of the registry are not checked.
+fn mutate_registry(
+    registry_data_provider: &ProtoRegistryDataProvider,
+    f: impl FnOnce(&Registry) -> Result<Vec<RegistryMutation>, String>,
+) -> Result<(), String> {
+    let version = registry_data_provider.latest_version();
+    let mut records = registry_data_provider
+        .get_updates_since(ZERO_REGISTRY_VERSION)
+        .map_err(|e| format!("failed to read the registry: {:?}", e))?;
+    records.sort_by_key(|record| record.version);
+    let mut snapshot = BTreeMap::new();
+    for record in records {
+        match record.value {
+            Some(value) => snapshot.insert(record.key.into_bytes(), value),
+            None => snapshot.remove(record.key.as_bytes()),
+        };
+    }
+
+    let mutations = f(&Registry::from_snapshot(snapshot, version.get()))?;
+    registry_data_provider.apply_mutations_as_version(mutations, version.increment());
+    Ok(())
+}
+
+/// Copies the directory `src` into `dst`, recursively.
+fn copy_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dst.join(entry.file_name()))?;
+        } else {
+            std::fs::copy(entry.path(), dst.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
 /// Convert an object into CBOR binary.
 fn into_cbor<R: Serialize>(r: &R) -> Vec<u8> {
     let mut ser = serde_cbor::Serializer::new(Vec::new());
@@ -565,6 +621,7 @@ pub struct StateMachineBuilder {
     features: SubnetFeatures,
     runtime: Option<Arc<Runtime>>,
     registry_data_provider: Arc<ProtoRegistryDataProvider>,
+    keep_existing_subnet_records: bool,
 }
 
 impl StateMachineBuilder {
@@ -595,6 +652,7 @@ impl StateMachineBuilder {
             },
             runtime: None,
             registry_data_provider: Arc::new(ProtoRegistryDataProvider::new()),
+            keep_existing_subnet_records: false,
         }
     }
 
@@ -731,6 +789,15 @@ impl StateMachineBuilder {
         }
     }
 
+    /// Reuses the records of the subnet if they are already in the registry
+    /// instead of adding them, as needed to resume a subnet after a split.
+    fn with_existing_subnet_records(self) -> Self {
+        Self {
+            keep_existing_subnet_records: true,
+            ..self
+        }
+    }
+
     pub fn build(self) -> StateMachine {
         let mut routing_table = self.routing_table;
         if routing_table.is_empty() {
@@ -767,6 +834,7 @@ impl StateMachineBuilder {
             }),
             registry_version,
             self.registry_data_provider,
+            self.keep_existing_subnet_records,
         )
     }
 
@@ -960,6 +1028,7 @@ impl StateMachine {
         runtime: Arc<Runtime>,
         registry_version: RegistryVersion,
         registry_data_provider: Arc<ProtoRegistryDataProvider>,
+        keep_existing_subnet_records: bool,
     ) -> Self {
         let replica_logger = replica_logger();
 
@@ -979,6 +1048,7 @@ impl StateMachine {
             features,
             registry_version,
             registry_data_provider.clone(),
+            keep_existing_subnet_records,
         );
 
         let sm_config = ic_config::state_manager::Config::new(state_dir.path().to_path_buf());
@@ -2294,6 +2364,162 @@ impl StateMachine {
         assert_eq!(next_version, self.registry_client.get_latest_version());
     }
 
+    /// Adds the records of a halted subnet `subnet_id`, configured as by
+    /// [StateMachineBuilder::new], to the registry unless it already has a
+    /// subnet record.
+    fn add_halted_subnet(&self, subnet_id: SubnetId) {
+        self.registry_client.update_to_latest_version();
+        let version = self.registry_client.get_latest_version();
+        if let Ok(Some(_)) = self.registry_client.get_subnet_record(subnet_id, version) {
+            return;
+        }
+
+        let defaults = StateMachineBuilder::new();
+        let registry_client = make_nodes_registry(
+            defaults.nns_subnet_id,
+            subnet_id,
+            defaults.subnet_type,
+            defaults.subnet_size,
+            &[],
+            defaults.features,
+            version.increment(),
+            self.registry_data_provider.clone(),
+            false,
+        );
+        let mut record = registry_client
+            .get_subnet_record(subnet_id, version.increment())
+            .expect("malformed subnet record")
+            .expect("missing subnet record");
+        record.is_halted = true;
+        add_single_subnet_record(
+            &self.registry_data_provider,
+            version.increment().increment().get(),
+            subnet_id,
+            record,
+        );
+        self.registry_client.update_to_latest_version();
+    }
+
+    /// Splits off the canisters in `canister_range` into the subnet
+    /// `destination`, driving the split through the registry canister's
+    /// `split_subnet` and `update_subnet_splitting_status` mutations:
+    ///
+    ///  1. `destination` is added to the registry as a halted subnet, unless
+    ///     it already has a subnet record, and the split is scheduled.
+    ///  2. This subnet halts at a checkpoint (standing in for the CUP height),
+    ///     which reroutes the canisters to `destination`.
+    ///  3. Both halves derive their states from this checkpoint.
+    ///  4. Both subnets resume from their split states and the split is
+    ///     completed.
+    ///
+    /// The mutations are applied to this state machine's registry without
+    /// checking the registry's global invariants. `destination` must be in the
+    /// registry's subnet list (see [StateMachineBuilder::with_subnet_list]).
+    ///
+    /// Returns the state machines of this subnet and of `destination`, both
+    /// backed by this state machine's registry. As with [restart_node], the
+    /// configuration of this state machine is not preserved.
+    pub fn split(
+        self,
+        destination: SubnetId,
+        canister_range: std::ops::RangeInclusive<CanisterId>,
+    ) -> Result<(StateMachine, StateMachine), String> {
+        use ic_registry_client_helpers::routing_table::RoutingTableRegistry;
+        use registry_canister::mutations::split_subnet::{
+            SplitSubnetPayload, SubnetSplittingStatusUpdate, UpdateSubnetSplittingStatusPayload,
+        };
+
+        let source = self.subnet_id;
+        let canister_id_range = CanisterIdRange {
+            start: *canister_range.start(),
+            end: *canister_range.end(),
+        };
+        let canister_id_ranges = CanisterIdRanges::try_from(vec![canister_id_range])
+            .map_err(|e| format!("invalid canister range: {:?}", e))?;
+        let registry_data_provider = self.registry_data_provider.clone();
+        let update_status = |status| {
+            mutate_registry(&registry_data_provider, |registry| {
+                registry.update_subnet_splitting_status_mutations(
+                    UpdateSubnetSplittingStatusPayload {
+                        source_subnet: source,
+                        status,
+                    },
+                )
+            })
+        };
+
+        self.add_halted_subnet(destination);
+        mutate_registry(&registry_data_provider, |registry| {
+            registry.split_subnet_mutations(SplitSubnetPayload {
+                source_subnet: source,
+                destination_subnet: destination,
+                canister_id_ranges: vec![canister_id_range],
+            })
+        })?;
+
+        self.set_checkpoints_enabled(true);
+        self.tick();
+        let cup_height = self.state_manager.latest_state_height();
+        update_status(SubnetSplittingStatusUpdate::SourceHalted {
+            cup_height: cup_height.get(),
+        })?;
+        self.registry_client.update_to_latest_version();
+
+        let retained_ranges = self
+            .registry_client
+            .get_routing_table(self.registry_client.get_latest_version())
+            .expect("malformed routing table")
+            .expect("missing routing table")
+            .ranges(source);
+        let log = self.replica_logger.clone();
+        let (source_dir, nonce, time, _) = self.into_components();
+        let destination_dir = TempDir::new().map_err(|e| e.to_string())?;
+        copy_dir(
+            &source_dir.path().join("checkpoints"),
+            &destination_dir.path().join("checkpoints"),
+        )
+        .map_err(|e| format!("failed to copy the checkpoints: {}", e))?;
+        ic_state_manager::split::split(
+            source_dir.path().to_path_buf(),
+            source.get(),
+            retained_ranges,
+            None,
+            &MetricsRegistry::new(),
+            log.clone(),
+        )?;
+        ic_state_manager::split::split(
+            destination_dir.path().to_path_buf(),
+            destination.get(),
+            canister_id_ranges.clone(),
+            Some(time),
+            &MetricsRegistry::new(),
+            log,
+        )?;
+        update_status(SubnetSplittingStatusUpdate::StatesSplit)?;
+
+        let resume = |state_dir, subnet_id| {
+            StateMachineBuilder::new()
+                .with_state_dir(state_dir)
+                .with_nonce(nonce)
+                .with_time(time)
+                .with_checkpoints_enabled(true)
+                .with_subnet_id(subnet_id)
+                .with_registry_data_provider(registry_data_provider.clone())
+                .with_existing_subnet_records()
+        };
+        let source_env = resume(source_dir, source).build();
+        // The ECDSA signing subnet lists are already held by the source subnet.
+        let destination_env = resume(destination_dir, destination)
+            .with_ecdsa_keys(vec![])
+            .build();
+
+        update_status(SubnetSplittingStatusUpdate::Completed)?;
+        source_env.reload_registry();
+        destination_env.reload_registry();
+
+        Ok((source_env, destination_env))
+    }
+
     /// Return the subnet_ids from the internal RegistryClient
     pub fn get_subnet_ids(&self) -> Vec<SubnetId> {
         self.registry_client
@@ -0,0 +1,115 @@
+use ic_interfaces_registry::RegistryClient;
+use ic_protobuf::registry::routing_table::v1::SubnetSplittingStatus;
+use ic_registry_client_fake::FakeRegistryClient;
+use ic_registry_client_helpers::routing_table::RoutingTableRegistry;
+use ic_registry_client_helpers::subnet::SubnetRegistry;
+use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
+use ic_registry_routing_table::CANISTER_IDS_PER_SUBNET;
+use ic_state_machine_tests::StateMachineBuilder;
+use ic_test_utilities::types::ids::subnet_test_id;
+use ic_types::{ingress::WasmResult, CanisterId, Cycles};
+use ic_universal_canister::{wasm, UNIVERSAL_CANISTER_WASM};
+use std::sync::Arc;
+
+const INITIAL_CYCLES_BALANCE: Cycles = Cycles::new(100_000_000_000_000);
+
+#[test]
+fn split_subnet_moves_canisters_to_destination() {
+    let source = subnet_test_id(1);
+    let destination = subnet_test_id(2);
+    let registry_data_provider = Arc::new(ProtoRegistryDataProvider::new());
+
+    let env = StateMachineBuilder::new()
+        .with_subnet_id(source)
+        .with_subnet_list(vec![source, destination])
+        .with_registry_data_provider(registry_data_provider.clone())
+        .build();
+
+    let install = || {
+        env.install_canister_with_cycles(
+            UNIVERSAL_CANISTER_WASM.to_vec(),
+            vec![],
+            None,
+            INITIAL_CYCLES_BALANCE,
+        )
+        .unwrap()
+    };
+    let retained_canister = install();
+    let migrated_canister = install();
+    for (canister_id, data) in [
+        (retained_canister, b"retained"),
+        (migrated_canister, b"migrated"),
+    ] {
+        env.execute_ingress(
+            canister_id,
+            "update",
+            wasm().set_global_data(data).reply().build(),
+        )
+        .unwrap();
+    }
+
+    let (source_env, destination_env) = env
+        .split(
+            destination,
+            migrated_canister..=CanisterId::from_u64(CANISTER_IDS_PER_SUBNET - 1),
+        )
+        .unwrap();
+
+    // Each canister ended up on exactly one of the two subnets.
+    assert!(source_env.canister_exists(retained_canister));
+    assert!(!source_env.canister_exists(migrated_canister));
+    assert!(!destination_env.canister_exists(retained_canister));
+    assert!(destination_env.canister_exists(migrated_canister));
+
+    // And both subnets resumed execution.
+    let get_global_data = wasm().get_global_data().append_and_reply().build();
+    assert_eq!(
+        source_env.query(retained_canister, "query", get_global_data.clone()),
+        Ok(WasmResult::Reply(b"retained".to_vec()))
+    );
+    assert_eq!(
+        destination_env.query(migrated_canister, "query", get_global_data),
+        Ok(WasmResult::Reply(b"migrated".to_vec()))
+    );
+    destination_env
+        .execute_ingress(
+            migrated_canister,
+            "update",
+            wasm().set_global_data(b"updated").reply().build(),
+        )
+        .unwrap();
+
+    // The registry routes the migrated canisters to the destination subnet and
+    // records the completed split.
+    let registry_client = FakeRegistryClient::new(registry_data_provider);
+    registry_client.update_to_latest_version();
+    let version = registry_client.get_latest_version();
+    let routing_table = registry_client.get_routing_table(version).unwrap().unwrap();
+    assert_eq!(routing_table.route(retained_canister.get()), Some(source));
+    assert_eq!(
+        routing_table.route(migrated_canister.get()),
+        Some(destination)
+    );
+    assert_eq!(
+        registry_client
+            .get_canister_migrations(version)
+            .unwrap()
+            .unwrap()
+            .lookup(migrated_canister),
+        None
+    );
+    let record = registry_client
+        .get_subnet_splitting_record(source, version)
+        .unwrap()
+        .unwrap();
+    assert_eq!(record.status(), SubnetSplittingStatus::Completed);
+    assert!(record.cup_height > 0);
+    for subnet_id in [source, destination] {
+        let subnet_record = registry_client
+            .get_subnet_record(subnet_id, version)
+            .unwrap()
+            .unwrap();
+        assert!(!subnet_record.is_halted);
+        assert!(!subnet_record.halt_at_cup_height);
+    }
+}
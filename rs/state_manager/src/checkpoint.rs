@@ -402,6 +402,9 @@ pub fn load_canister_state<P: ReadPolicy>(
         canister_state_bits.canister_history,
         wasm_chunk_store_data,
         canister_state_bits.wasm_chunk_store_metadata,
+        canister_state_bits.wasm_memory_limit,
+        canister_state_bits.wasm_memory_threshold,
+        canister_state_bits.on_low_wasm_memory_hook_status,
     );
 
     let canister_state = CanisterState {
@@ -166,6 +166,7 @@ pub struct StateSyncMetrics {
     remaining: IntGauge,
     corrupted_chunks_critical: IntCounter,
     corrupted_chunks: IntCounterVec,
+    resumed_chunks: IntCounter,
 }
 
 #[derive(Clone)]
@@ -539,6 +540,11 @@ impl StateSyncMetrics {
             corrupted_chunks.with_label_values(&[*source]);
         }
 
+        let resumed_chunks = metrics_registry.int_counter(
+            "state_sync_resumed_chunks_total",
+            "Number of chunks recovered from the scratchpad of a state sync interrupted by a restart.",
+        );
+
         Self {
             size,
             duration,
@@ -546,6 +552,7 @@ impl StateSyncMetrics {
             remaining,
             corrupted_chunks_critical,
             corrupted_chunks,
+            resumed_chunks,
         }
     }
 }
@@ -2417,6 +2424,7 @@ impl StateManagerImpl {
                         target_height: height,
                         dirty_memory_pages: dirty_pages,
                         base_checkpoint: checkpoint_layout,
+                        dirty_chunks: None,
                     }
                 },
             )
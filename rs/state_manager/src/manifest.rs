@@ -19,8 +19,9 @@ use hash::{chunk_hasher, file_hasher, manifest_hasher, ManifestHash};
 use ic_crypto_sha2::Sha256;
 use ic_logger::{error, fatal, replica_logger::no_op_logger, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
+use ic_protobuf::{proxy::ProxyDecodeError, state::sync::v1 as pb};
 use ic_replicated_state::PageIndex;
-use ic_state_layout::{CheckpointLayout, ReadOnly, CANISTER_FILE};
+use ic_state_layout::{CheckpointLayout, ReadOnly, StateLayout, CANISTER_FILE};
 use ic_sys::{mmap::ScopedMmap, PAGE_SIZE};
 use ic_types::{
     crypto::CryptoHash,
@@ -235,6 +236,66 @@ pub struct ManifestDelta {
     /// state at `base_height`.
     pub(crate) dirty_memory_pages: DirtyPages,
     pub(crate) base_checkpoint: CheckpointLayout<ReadOnly>,
+    /// Chunks that might have changed since the state at `base_height`, if
+    /// they are already known, e.g. because they were persisted before a
+    /// restart. Takes precedence over `dirty_memory_pages`.
+    pub(crate) dirty_chunks: Option<DirtyChunks>,
+}
+
+/// Chunks of a checkpoint that might differ from the checkpoint at
+/// `base_height`. Files without an entry have to be rehashed completely.
+///
+/// Unlike dirty pages, dirty chunks are persisted next to the checkpoint, so
+/// that its manifest can be computed incrementally after a restart.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DirtyChunks {
+    pub base_height: Height,
+    pub files: BTreeMap<PathBuf, BitVec>,
+}
+
+impl From<&DirtyChunks> for pb::DirtyChunks {
+    fn from(dirty_chunks: &DirtyChunks) -> Self {
+        Self {
+            base_height: dirty_chunks.base_height.get(),
+            files: dirty_chunks
+                .files
+                .iter()
+                .map(|(relative_path, bitmap)| pb::FileDirtyChunks {
+                    relative_path: relative_path.to_string_lossy().into_owned(),
+                    num_chunks: bitmap.len() as u64,
+                    bitmap: bitmap.to_bytes(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<pb::DirtyChunks> for DirtyChunks {
+    type Error = ProxyDecodeError;
+
+    fn try_from(dirty_chunks: pb::DirtyChunks) -> Result<Self, Self::Error> {
+        let mut files = BTreeMap::new();
+        for file in dirty_chunks.files {
+            let mut bitmap = BitVec::from_bytes(&file.bitmap);
+            if (bitmap.len() as u64) < file.num_chunks {
+                return Err(ProxyDecodeError::ValueOutOfRange {
+                    typ: "FileDirtyChunks",
+                    err: format!(
+                        "bitmap of {} has {} bits, expected at least {}",
+                        file.relative_path,
+                        bitmap.len(),
+                        file.num_chunks
+                    ),
+                });
+            }
+            bitmap.truncate(file.num_chunks as usize);
+            files.insert(PathBuf::from(file.relative_path), bitmap);
+        }
+        Ok(Self {
+            base_height: Height::new(dirty_chunks.base_height),
+            files,
+        })
+    }
 }
 
 /// Groups small files into larger chunks.
@@ -853,6 +914,56 @@ fn dirty_pages_to_dirty_chunks(
     Ok(dirty_chunks)
 }
 
+/// Returns the files of the checkpoint together with their sizes, sorted by
+/// path.
+fn sorted_files_with_sizes(
+    checkpoint: &CheckpointLayout<ReadOnly>,
+) -> Result<Vec<FileWithSize>, CheckpointError> {
+    let mut files = Vec::new();
+    files_with_sizes(checkpoint.raw_path(), "".into(), &mut files)?;
+    // We sort the table to make sure that the table is the same on all replicas
+    files.sort_unstable_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+    Ok(files)
+}
+
+/// Returns the bitmaps of `dirty_chunks` that match the files of the
+/// checkpoint. A bitmap that doesn't match the size of its file is dropped, so
+/// that the file is rehashed completely.
+fn known_dirty_chunks(
+    dirty_chunks: &DirtyChunks,
+    files: &[FileWithSize],
+    max_chunk_size: u32,
+) -> BTreeMap<PathBuf, BitVec> {
+    files
+        .iter()
+        .filter_map(|FileWithSize(relative_path, size_bytes)| {
+            let bitmap = dirty_chunks.files.get(relative_path)?;
+            (bitmap.len() == count_chunks(*size_bytes, max_chunk_size))
+                .then(|| (relative_path.clone(), bitmap.clone()))
+        })
+        .collect()
+}
+
+/// Computes the chunks of `checkpoint` that might differ from the base
+/// checkpoint of `manifest_delta`.
+pub(crate) fn compute_dirty_chunks(
+    log: &ReplicaLogger,
+    manifest_delta: &ManifestDelta,
+    checkpoint: &CheckpointLayout<ReadOnly>,
+    max_chunk_size: u32,
+) -> Result<DirtyChunks, CheckpointError> {
+    let files = if uses_chunk_size(&manifest_delta.base_manifest, max_chunk_size) {
+        let files = sorted_files_with_sizes(checkpoint)?;
+        dirty_pages_to_dirty_chunks(log, manifest_delta, checkpoint, &files, max_chunk_size)?
+    } else {
+        Default::default()
+    };
+    Ok(DirtyChunks {
+        base_height: manifest_delta.base_height,
+        files,
+    })
+}
+
 /// Computes manifest for the checkpoint located at `checkpoint_root_path`.
 pub fn compute_manifest(
     thread_pool: &mut scoped_threadpool::Pool,
@@ -863,13 +974,7 @@ pub fn compute_manifest(
     max_chunk_size: u32,
     opt_manifest_delta: Option<ManifestDelta>,
 ) -> Result<Manifest, CheckpointError> {
-    let files = {
-        let mut files = Vec::new();
-        files_with_sizes(checkpoint.raw_path(), "".into(), &mut files)?;
-        // We sort the table to make sure that the table is the same on all replicas
-        files.sort_unstable_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
-        files
-    };
+    let files = sorted_files_with_sizes(checkpoint)?;
 
     let chunk_actions = match opt_manifest_delta {
         Some(manifest_delta) => {
@@ -879,13 +984,20 @@ pub fn compute_manifest(
             // new chunk size), but the manifest might be computed incorrectly
             // on the mainnet.
             if uses_chunk_size(&manifest_delta.base_manifest, max_chunk_size) {
-                let dirty_file_chunks = dirty_pages_to_dirty_chunks(
-                    log,
-                    &manifest_delta,
-                    checkpoint,
-                    &files,
-                    max_chunk_size,
-                )?;
+                let dirty_file_chunks = match manifest_delta.dirty_chunks {
+                    Some(ref dirty_chunks)
+                        if dirty_chunks.base_height == manifest_delta.base_height =>
+                    {
+                        known_dirty_chunks(dirty_chunks, &files, max_chunk_size)
+                    }
+                    _ => dirty_pages_to_dirty_chunks(
+                        log,
+                        &manifest_delta,
+                        checkpoint,
+                        &files,
+                        max_chunk_size,
+                    )?,
+                };
                 hash_plan(
                     &manifest_delta.base_manifest,
                     &files,
@@ -1391,6 +1503,44 @@ pub fn filter_out_zero_chunks(manifest: &Manifest) -> HashSet<usize> {
 /// Helper function to compute the manifest from a raw path.
 /// This function is intended for tests and external tools only.
 pub fn manifest_from_path(path: &Path) -> Result<Manifest, CheckpointError> {
+    manifest_from_path_with_delta(path, None)
+}
+
+/// Helper function to compute the manifest from a raw path, reusing the chunk
+/// hashes of `base_manifest` for the chunks not marked in `dirty_chunks`.
+/// The base checkpoint is expected next to the checkpoint at `path`, as in the
+/// `checkpoints` directory of a state root; if it's missing, only the hardlink
+/// detection is skipped.
+/// This function is intended for tests and external tools only.
+pub fn manifest_from_path_with_dirty_chunks(
+    path: &Path,
+    base_manifest: Manifest,
+    dirty_chunks: DirtyChunks,
+) -> Result<Manifest, CheckpointError> {
+    let base_height = dirty_chunks.base_height;
+    let base_path = path
+        .parent()
+        .unwrap_or(path)
+        .join(StateLayout::checkpoint_name(base_height));
+    let target_height = path
+        .file_name()
+        .and_then(|name| u64::from_str_radix(&name.to_string_lossy(), 16).ok())
+        .unwrap_or(0);
+    let manifest_delta = ManifestDelta {
+        base_manifest,
+        base_height,
+        target_height: Height::new(target_height),
+        dirty_memory_pages: Vec::new(),
+        base_checkpoint: CheckpointLayout::<ReadOnly>::new_untracked(base_path, base_height)?,
+        dirty_chunks: Some(dirty_chunks),
+    };
+    manifest_from_path_with_delta(path, Some(manifest_delta))
+}
+
+fn manifest_from_path_with_delta(
+    path: &Path,
+    opt_manifest_delta: Option<ManifestDelta>,
+) -> Result<Manifest, CheckpointError> {
     let cp_layout = CheckpointLayout::<ReadOnly>::new_untracked(path.to_owned(), Height::new(0))?;
 
     let metadata = cp_layout.system_metadata().deserialize()?;
@@ -1412,6 +1562,6 @@ pub fn manifest_from_path(path: &Path) -> Result<Manifest, CheckpointError> {
             })?,
         &cp_layout,
         DEFAULT_CHUNK_SIZE,
-        None,
+        opt_manifest_delta,
     )
 }
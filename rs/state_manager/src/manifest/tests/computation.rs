@@ -1089,6 +1089,7 @@ fn test_dirty_pages_to_dirty_chunks_accounts_for_hardlinks() {
                 Height::new(0),
             )
             .unwrap(),
+            dirty_chunks: None,
         },
         &CheckpointLayout::new_untracked(checkpoint1.to_path_buf(), Height::new(1)).unwrap(),
         &[
@@ -1106,6 +1107,88 @@ fn test_dirty_pages_to_dirty_chunks_accounts_for_hardlinks() {
     );
 }
 
+#[test]
+fn test_dirty_chunks_proto_round_trip() {
+    use crate::manifest::DirtyChunks;
+    use bit_vec::BitVec;
+    use ic_protobuf::state::sync::v1 as pb;
+    use maplit::btreemap;
+
+    let mut bitmap = BitVec::from_elem(11, false);
+    bitmap.set(3, true);
+    bitmap.set(10, true);
+    let dirty_chunks = DirtyChunks {
+        base_height: Height::new(42),
+        files: btreemap! {
+            PathBuf::from("canister_states/0/vmemory_0.bin") => bitmap,
+            PathBuf::from("canister_states/0/stable_memory.bin") => BitVec::new(),
+        },
+    };
+
+    let proto = pb::DirtyChunks::from(&dirty_chunks);
+    assert_eq!(DirtyChunks::try_from(proto.clone()).unwrap(), dirty_chunks);
+
+    // A bitmap shorter than its number of chunks is rejected.
+    let mut truncated = proto;
+    truncated.files[1].num_chunks = 17;
+    assert!(DirtyChunks::try_from(truncated).is_err());
+}
+
+#[test]
+fn test_manifest_from_dirty_chunks_matches_full_computation() {
+    use crate::manifest::{manifest_from_path, manifest_from_path_with_dirty_chunks, DirtyChunks};
+    use bit_vec::BitVec;
+    use ic_protobuf::state::system_metadata::v1::SystemMetadata;
+    use ic_state_layout::StateLayout;
+    use maplit::btreemap;
+    use prost::Message;
+
+    let dir = tempfile::TempDir::new().expect("failed to create a temporary directory");
+    let checkpoint1 = dir
+        .path()
+        .join(StateLayout::checkpoint_name(Height::new(1)));
+    let checkpoint2 = dir
+        .path()
+        .join(StateLayout::checkpoint_name(Height::new(2)));
+    let system_metadata = SystemMetadata {
+        state_sync_version: CURRENT_STATE_SYNC_VERSION as u32,
+        ..Default::default()
+    }
+    .encode_to_vec();
+
+    let mut memory = vec![1u8; 3 * DEFAULT_CHUNK_SIZE as usize];
+    for checkpoint in [&checkpoint1, &checkpoint2] {
+        fs::create_dir(checkpoint).expect("failed to create checkpoint dir");
+        fs::write(checkpoint.join("system_metadata.pbuf"), &system_metadata)
+            .expect("failed to write system metadata");
+        fs::write(checkpoint.join("stable_memory.bin"), vec![2u8; 1024])
+            .expect("failed to create file 'stable_memory.bin'");
+        fs::write(checkpoint.join("vmemory_0.bin"), &memory)
+            .expect("failed to create file 'vmemory_0.bin'");
+        // Change the second chunk of the memory in checkpoint @2.
+        memory[DEFAULT_CHUNK_SIZE as usize + 7] = 3;
+    }
+
+    let base_manifest = manifest_from_path(&checkpoint1).expect("failed to compute manifest");
+    let mut memory_chunks = BitVec::from_elem(3, false);
+    memory_chunks.set(1, true);
+    let dirty_chunks = DirtyChunks {
+        base_height: Height::new(1),
+        // `system_metadata.pbuf` has no entry, so it gets rehashed.
+        files: btreemap! {
+            PathBuf::from("stable_memory.bin") => BitVec::from_elem(1, false),
+            PathBuf::from("vmemory_0.bin") => memory_chunks,
+        },
+    };
+
+    let incremental_manifest =
+        manifest_from_path_with_dirty_chunks(&checkpoint2, base_manifest.clone(), dirty_chunks)
+            .expect("failed to compute manifest incrementally");
+    let full_manifest = manifest_from_path(&checkpoint2).expect("failed to compute manifest");
+    assert_eq!(incremental_manifest, full_manifest);
+    assert_ne!(incremental_manifest, base_manifest);
+}
+
 #[test]
 fn test_file_chunk_range() {
     let manifest = simple_manifest(CURRENT_STATE_SYNC_VERSION).1;
@@ -24,9 +24,20 @@ pub struct StateSync {
 
 impl StateSync {
     pub fn new(state_manager: Arc<StateManagerImpl>, log: ReplicaLogger) -> Self {
+        let state_sync_refs = StateSyncRefs::new(log.clone());
+        // Reuse the chunks fetched by a state sync that was interrupted by a restart.
+        let resumed_chunks = state_sync_refs
+            .cache
+            .write()
+            .recover(&state_manager.state_layout);
+        state_manager
+            .metrics
+            .state_sync_metrics
+            .resumed_chunks
+            .inc_by(resumed_chunks as u64);
         Self {
             state_manager,
-            state_sync_refs: StateSyncRefs::new(log.clone()),
+            state_sync_refs,
             log,
         }
     }
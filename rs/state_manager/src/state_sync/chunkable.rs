@@ -34,12 +34,44 @@ use std::{
 };
 
 pub mod cache;
+pub(crate) mod progress;
 
 // If set to true, we validate chunks even in situations where it might not be
 // necessary.
 const ALWAYS_VALIDATE: bool = false;
 
 type SubManifest = Vec<u8>;
+
+/// Converts `fetch_chunks`, as stored by `DownloadState::Loading`, to indices
+/// into the manifest's chunk table, replacing file group chunks by the chunks
+/// they contain.
+fn chunk_table_indices(
+    fetch_chunks: &HashSet<usize>,
+    state_sync_file_group: &FileGroupChunks,
+) -> HashSet<usize> {
+    // fetch_chunks considers the meta-manifest as chunk 0
+    debug_assert!(!fetch_chunks.contains(&0));
+    let mut indices: HashSet<usize> = Default::default();
+    for &i in fetch_chunks.iter() {
+        assert_ne!(0, i);
+        if i < FILE_GROUP_CHUNK_ID_OFFSET as usize {
+            indices.insert(i - FILE_CHUNK_ID_OFFSET);
+        } else {
+            // If it's a chunk group, the individual chunks are missing in the manifest,
+            // not the group
+            let chunks = state_sync_file_group
+                .get(&(i as u32))
+                .expect("Unknown chunk group");
+            indices.extend(chunks.iter().map(|i| *i as usize));
+        }
+    }
+
+    debug_assert!(indices
+        .iter()
+        .all(|i| *i + FILE_CHUNK_ID_OFFSET < FILE_GROUP_CHUNK_ID_OFFSET as usize));
+    indices
+}
+
 /// The state of the communication with up-to-date nodes.
 #[derive(Clone)]
 enum DownloadState {
@@ -89,6 +121,8 @@ pub struct IncompleteState {
     height: Height,
     root_hash: CryptoHashOfState,
     state: DownloadState,
+    /// The log of chunks fetched in the loading phase, if it could be created.
+    fetched_chunks_log: Option<progress::FetchedChunksLog>,
     manifest_with_checkpoint_layout: Option<(Manifest, CheckpointLayout<ReadOnly>)>,
     metrics: StateManagerMetrics,
     started_at: Instant,
@@ -167,6 +201,11 @@ impl Drop for IncompleteState {
 
         info!(self.log, "State sync @{} {}", self.height, description);
 
+        // The scratchpad either becomes a checkpoint, moves to the cache or gets
+        // deleted, so the recorded progress is not needed anymore.
+        self.fetched_chunks_log = None;
+        progress::remove_progress(&self.log, &self.state_layout, self.height);
+
         // Pass self to the cache, taking ownership of chunks on disk
         let cache = Arc::clone(&self.state_sync_refs.cache);
         cache.write().push(self);
@@ -212,6 +251,7 @@ impl IncompleteState {
             height,
             root_hash,
             state: DownloadState::Blank,
+            fetched_chunks_log: None,
             manifest_with_checkpoint_layout,
             metrics,
             started_at: Instant::now(),
@@ -968,7 +1008,7 @@ impl IncompleteState {
                         // StateSyncCacheEntry, so cloning the path is safe
                         root_old: cache_entry.path().to_path_buf(),
                         height_old: cache_entry.height,
-                        validate_data: false,
+                        validate_data: cache_entry.recovered_after_restart,
                     })
                 } else {
                     // This should be a special case that can only happen if the source of the
@@ -988,7 +1028,7 @@ impl IncompleteState {
                 missing_chunks: cache_entry.missing_chunks.clone(),
                 root_old: cache_entry.path().to_path_buf(),
                 height_old: cache_entry.height,
-                validate_data: false,
+                validate_data: cache_entry.recovered_after_restart,
             }),
             (None, Some((checkpoint_manifest, checkpoint_old))) => {
                 let checkpoint_height = checkpoint_old.height();
@@ -1355,6 +1395,22 @@ impl Chunkable for IncompleteState {
                             fetch_chunks.insert(chunk_id as usize);
                         }
                         let num_fetch_chunks = fetch_chunks.len();
+                        match progress::write_progress(
+                            &self.state_layout,
+                            self.height,
+                            &manifest,
+                            &chunk_table_indices(&fetch_chunks, &state_sync_file_group),
+                        ) {
+                            Ok(fetched_chunks_log) => {
+                                self.fetched_chunks_log = Some(fetched_chunks_log)
+                            }
+                            Err(err) => warn!(
+                                self.log,
+                                "Failed to record the progress of state sync @{}: {}",
+                                self.height,
+                                err
+                            ),
+                        }
                         self.state = DownloadState::Loading {
                             meta_manifest,
                             manifest,
@@ -1391,23 +1447,23 @@ impl Chunkable for IncompleteState {
                     return Err(ChunksMoreNeeded);
                 }
 
-                // Each index in `chunk_table_indices` is mapped to a piece of payload bytes
+                // Each index in `chunk_indices` is mapped to a piece of payload bytes
                 // with its corresponding start and end position.
-                let (chunk_table_indices, payload_pieces) = match state_sync_chunk_type(ix) {
+                let (chunk_indices, payload_pieces) = match state_sync_chunk_type(ix) {
                     StateSyncChunk::FileChunk(index) => {
                         // If it is a normal chunk, there is only one index mapped to the whole payload.
                         (vec![index], vec![(0, payload.len())])
                     }
                     StateSyncChunk::FileGroupChunk(index) => {
                         // If it is a file group chunk, divide it into pieces according to the `FileGroupChunks`.
-                        let chunk_table_indices = state_sync_file_group
+                        let chunk_indices = state_sync_file_group
                             .get(&index)
                             .ok_or(ChunkVerificationFailed)?
                             .clone();
 
                         let mut cur_offset = 0;
                         let mut payload_pieces: Vec<(usize, usize)> = Vec::new();
-                        for chunk_table_index in &chunk_table_indices {
+                        for chunk_table_index in &chunk_indices {
                             let chunk_size = manifest.chunk_table[*chunk_table_index as usize]
                                 .size_bytes as usize;
                             payload_pieces.push((cur_offset, cur_offset + chunk_size));
@@ -1418,7 +1474,7 @@ impl Chunkable for IncompleteState {
                             warn!(self.log, "Received invalid file group chunk {}", ix);
                             return Err(ChunkVerificationFailed);
                         }
-                        (chunk_table_indices, payload_pieces)
+                        (chunk_indices, payload_pieces)
                     }
                     _ => {
                         // meta-manifest/manifest chunks are not expected in the `Loading` phase.
@@ -1432,7 +1488,7 @@ impl Chunkable for IncompleteState {
                 // If any of the chunks is invalid, the whole file group chunk is considered as invalid.
                 // In this case, none of them will be applied.
                 for (chunk_table_index, &(start, end)) in
-                    chunk_table_indices.iter().zip(payload_pieces.iter())
+                    chunk_indices.iter().zip(payload_pieces.iter())
                 {
                     crate::manifest::validate_chunk(
                         *chunk_table_index as usize,
@@ -1451,7 +1507,7 @@ impl Chunkable for IncompleteState {
                 }
 
                 for (chunk_table_index, &(start, end)) in
-                    chunk_table_indices.iter().zip(payload_pieces.iter())
+                    chunk_indices.iter().zip(payload_pieces.iter())
                 {
                     Self::apply_chunk(
                         &self.log,
@@ -1463,6 +1519,20 @@ impl Chunkable for IncompleteState {
                     );
                 }
 
+                if let Some(Err(err)) = self
+                    .fetched_chunks_log
+                    .as_mut()
+                    .map(|fetched_chunks_log| fetched_chunks_log.record(&chunk_indices))
+                {
+                    warn!(
+                        self.log,
+                        "Failed to record fetched chunk {} of state sync @{}: {}",
+                        ix,
+                        self.height,
+                        err
+                    );
+                }
+
                 fetch_chunks.remove(&(ix as usize));
 
                 if fetch_chunks.is_empty() {
@@ -36,6 +36,10 @@ pub struct StateSyncCacheEntry {
     pub height: Height,
     path: PathBuf,
     pub missing_chunks: HashSet<usize>,
+    /// True if the entry was recovered from the scratchpad of a state sync
+    /// interrupted by a restart. The data of such entries is not necessarily
+    /// synced to disk and needs to be validated before use.
+    pub recovered_after_restart: bool,
     log: ReplicaLogger,
 }
 
@@ -97,28 +101,9 @@ impl StateSyncCache {
         fetch_chunks: HashSet<usize>,
         state_sync_file_group: FileGroupChunks,
     ) {
-        // fetch_chunks, as stored by IncompleteState considers the meta-manifest as chunk 0
         // For the cache we store indices into the manifest's chunk table as
         // missing_chunks.
-        debug_assert!(!fetch_chunks.contains(&0));
-        let mut missing_chunks: HashSet<usize> = Default::default();
-        for i in fetch_chunks.into_iter() {
-            assert_ne!(0, i);
-            if i < FILE_GROUP_CHUNK_ID_OFFSET as usize {
-                missing_chunks.insert(i - FILE_CHUNK_ID_OFFSET);
-            } else {
-                // If it's a chunk group, the individual chunks are missing in the manifest,
-                // not the group
-                let chunks = state_sync_file_group
-                    .get(&(i as u32))
-                    .expect("Unknown chunk group");
-                missing_chunks.extend(chunks.iter().map(|i| *i as usize));
-            }
-        }
-
-        debug_assert!(missing_chunks
-            .iter()
-            .all(|i| *i + FILE_CHUNK_ID_OFFSET < FILE_GROUP_CHUNK_ID_OFFSET as usize));
+        let missing_chunks = chunk_table_indices(&fetch_chunks, &state_sync_file_group);
 
         // We rename the folder to decouple the cache from active state syncs a bit.
         // Otherwise we'd have to assume that there won't be an active state sync at
@@ -147,11 +132,58 @@ impl StateSyncCache {
             height: sync.height,
             path: cache_root,
             missing_chunks,
+            recovered_after_restart: false,
             log: self.log.clone(),
         };
         self.entry = Some(Arc::new(entry));
     }
 
+    /// Populates the cache with the scratchpad of the most recent state sync
+    /// interrupted by a restart, if any. Returns the number of chunks that the
+    /// recovered scratchpad is expected to contain.
+    pub(crate) fn recover(&mut self, state_layout: &StateLayout) -> usize {
+        let Some(progress) = progress::recover_progress(&self.log, state_layout) else {
+            return 0;
+        };
+
+        let scratchpad = state_layout
+            .state_sync_scratchpad(progress.height)
+            .expect("failed to get the state sync scratchpad");
+        let cache_root = state_layout
+            .state_sync_cache(progress.height)
+            .expect("failed to create directory for state sync cache");
+        if let Err(err) = std::fs::rename(&scratchpad, &cache_root) {
+            warn!(
+                self.log,
+                "Failed to move the scratchpad at {} to the state sync cache: {}",
+                scratchpad.display(),
+                err
+            );
+            delete_folder(&self.log, &cache_root);
+            delete_folder(&self.log, &scratchpad);
+            return 0;
+        }
+
+        let num_chunks = progress.manifest.chunk_table.len() - progress.missing_chunks.len();
+        info!(
+            self.log,
+            "Recovered state sync @{} with {} out of {} chunks",
+            progress.height,
+            num_chunks,
+            progress.manifest.chunk_table.len()
+        );
+
+        self.entry = Some(Arc::new(StateSyncCacheEntry {
+            manifest: progress.manifest,
+            height: progress.height,
+            path: cache_root,
+            missing_chunks: progress.missing_chunks,
+            recovered_after_restart: true,
+            log: self.log.clone(),
+        }));
+        num_chunks
+    }
+
     /// Passes an `IncompleteState` `sync` to the cache, moving out any data
     /// relevant to caching.
     ///
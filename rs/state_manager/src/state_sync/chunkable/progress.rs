@@ -0,0 +1,258 @@
+//! Persists the progress of a state sync next to its scratchpad, so that a
+//! state sync interrupted by a restart can reuse the chunks it already fetched.
+//!
+//! The progress consists of two files:
+//!
+//!   * a header written when the state sync enters the loading phase. It
+//!     contains the manifest and the chunks missing from the scratchpad at
+//!     that point.
+//!
+//!   * an append-only log of the chunks written to the scratchpad since then.
+//!     Each entry is an index into the manifest's chunk table encoded as a
+//!     little-endian `u32`.
+//!
+//! Neither the scratchpad nor the log are synced to disk on every chunk, so
+//! the recovered chunks must be validated before they are used.
+
+use super::*;
+use ic_protobuf::state::sync::v1 as pb;
+use prost::Message;
+use std::io::Write;
+
+#[cfg(test)]
+mod tests;
+
+const PROGRESS_PREFIX: &str = "state_sync_progress_";
+const PROGRESS_SUFFIX: &str = ".pbuf";
+const FETCHED_CHUNK_ENTRY_SIZE: usize = std::mem::size_of::<u32>();
+
+/// The progress of a state sync recovered after a restart.
+pub(crate) struct StateSyncProgress {
+    pub height: Height,
+    pub manifest: Manifest,
+    /// Indices into the manifest's chunk table of the chunks that are not in
+    /// the scratchpad.
+    pub missing_chunks: HashSet<usize>,
+}
+
+/// The log of chunks fetched by a state sync, kept open for the duration of
+/// the loading phase.
+pub(crate) struct FetchedChunksLog(std::fs::File);
+
+impl FetchedChunksLog {
+    /// Appends the chunk table indices `chunks` to the log.
+    pub(crate) fn record(&mut self, chunks: &[u32]) -> std::io::Result<()> {
+        let bytes: Vec<u8> = chunks.iter().flat_map(|i| i.to_le_bytes()).collect();
+        self.0.write_all(&bytes)
+    }
+}
+
+/// Records that the state sync at `height` entered the loading phase with
+/// `missing_chunks` (indices into the chunk table of `manifest`) still to be
+/// fetched and returns the log to record the fetched chunks in. Any previously
+/// recorded progress at `height` is discarded.
+pub(crate) fn write_progress(
+    state_layout: &StateLayout,
+    height: Height,
+    manifest: &Manifest,
+    missing_chunks: &HashSet<usize>,
+) -> std::io::Result<FetchedChunksLog> {
+    let fetched_chunks_path = state_layout.state_sync_fetched_chunks(height);
+    remove_if_exists(&fetched_chunks_path)?;
+
+    let mut missing_chunks: Vec<u32> = missing_chunks.iter().map(|i| *i as u32).collect();
+    missing_chunks.sort_unstable();
+    let progress = pb::StateSyncProgress {
+        height: height.get(),
+        manifest: Some(manifest.clone().into()),
+        missing_chunks,
+    };
+
+    // Write the header under a temporary name first so that a crash never
+    // leaves a truncated header behind.
+    let path = state_layout.state_sync_progress(height);
+    let tmp_path = path.with_extension("tmp");
+    let mut file = std::fs::File::create(&tmp_path)?;
+    file.write_all(&progress.encode_to_vec())?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, &path)?;
+
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(fetched_chunks_path)
+        .map(FetchedChunksLog)
+}
+
+/// Removes the progress recorded for the state sync at `height`.
+pub(crate) fn remove_progress(log: &ReplicaLogger, state_layout: &StateLayout, height: Height) {
+    for path in [
+        state_layout.state_sync_progress(height),
+        state_layout.state_sync_fetched_chunks(height),
+    ] {
+        if let Err(err) = remove_if_exists(&path) {
+            warn!(
+                log,
+                "Failed to remove state sync progress at {}: {}",
+                path.display(),
+                err
+            );
+        }
+    }
+}
+
+/// Returns the progress of the most recent state sync interrupted by a
+/// restart, if its scratchpad is still usable.
+///
+/// All other scratchpads and progress files are deleted. The scratchpad of the
+/// returned state sync is left in place and its progress files are removed,
+/// so the caller takes ownership of the scratchpad.
+pub(crate) fn recover_progress(
+    log: &ReplicaLogger,
+    state_layout: &StateLayout,
+) -> Option<StateSyncProgress> {
+    let dir = state_layout.state_sync_dir();
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                warn!(
+                    log,
+                    "Failed to list state sync directory {}: {}",
+                    dir.display(),
+                    err
+                );
+            }
+            return None;
+        }
+    };
+
+    let mut heights: Vec<Height> = entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            let hex = name
+                .to_str()?
+                .strip_prefix(PROGRESS_PREFIX)?
+                .strip_suffix(PROGRESS_SUFFIX)?;
+            u64::from_str_radix(hex, 16).ok().map(Height::new)
+        })
+        .collect();
+    heights.sort_unstable();
+
+    let mut recovered = None;
+    while let Some(height) = heights.pop() {
+        match read_progress(state_layout, height) {
+            Ok(progress)
+                if state_layout
+                    .state_sync_scratchpad(height)
+                    .map_or(false, |path| path.exists()) =>
+            {
+                recovered = Some(progress);
+                break;
+            }
+            Ok(_) => {
+                info!(
+                    log,
+                    "Ignoring state sync progress @{} without a scratchpad", height
+                );
+            }
+            Err(err) => {
+                warn!(
+                    log,
+                    "Ignoring invalid state sync progress @{}: {}", height, err
+                );
+            }
+        }
+    }
+
+    // Delete everything except the scratchpad we are about to reuse.
+    let keep = recovered
+        .as_ref()
+        .and_then(|progress| state_layout.state_sync_scratchpad(progress.height).ok());
+    if let Ok(entries) = std::fs::read_dir(&dir) {
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if Some(&path) == keep.as_ref() {
+                continue;
+            }
+            let result = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+            if let Err(err) = result {
+                warn!(
+                    log,
+                    "Failed to remove stale state sync data at {}: {}",
+                    path.display(),
+                    err
+                );
+            }
+        }
+    }
+
+    recovered
+}
+
+fn read_progress(state_layout: &StateLayout, height: Height) -> Result<StateSyncProgress, String> {
+    let path = state_layout.state_sync_progress(height);
+    let bytes = std::fs::read(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let progress = pb::StateSyncProgress::decode(bytes.as_slice())
+        .map_err(|err| format!("failed to decode {}: {}", path.display(), err))?;
+    if progress.height != height.get() {
+        return Err(format!(
+            "{} records the progress of height {}",
+            path.display(),
+            progress.height
+        ));
+    }
+    let manifest: Manifest = progress
+        .manifest
+        .ok_or_else(|| format!("{} does not contain a manifest", path.display()))?
+        .try_into()
+        .map_err(|err| {
+            format!(
+                "failed to decode the manifest in {}: {:?}",
+                path.display(),
+                err
+            )
+        })?;
+
+    let num_chunks = manifest.chunk_table.len();
+    let mut missing_chunks: HashSet<usize> = progress
+        .missing_chunks
+        .into_iter()
+        .map(|i| i as usize)
+        .collect();
+    if missing_chunks.iter().any(|i| *i >= num_chunks) {
+        return Err(format!(
+            "{} refers to chunks outside of the manifest",
+            path.display()
+        ));
+    }
+
+    // The log may be missing or end with a partially written entry if the
+    // replica was stopped in the middle of an append; both are fine since we
+    // only lose the knowledge about chunks we will fetch again.
+    let fetched = match std::fs::read(state_layout.state_sync_fetched_chunks(height)) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => vec![],
+        Err(err) => return Err(err.to_string()),
+    };
+    for entry in fetched.chunks_exact(FETCHED_CHUNK_ENTRY_SIZE) {
+        let ix = u32::from_le_bytes(entry.try_into().unwrap()) as usize;
+        missing_chunks.remove(&ix);
+    }
+
+    Ok(StateSyncProgress {
+        height,
+        manifest,
+        missing_chunks,
+    })
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,148 @@
+use super::*;
+use crate::state_sync::chunkable::cache::StateSyncCache;
+use ic_metrics::MetricsRegistry;
+use ic_test_utilities_logger::with_test_replica_logger;
+use ic_types::state_sync::{ChunkInfo, FileInfo, CURRENT_STATE_SYNC_VERSION};
+use tempfile::TempDir;
+
+fn new_state_layout(log: ReplicaLogger) -> (TempDir, StateLayout) {
+    let root_dir = tempfile::TempDir::new().expect("failed to create a temporary directory");
+    let state_layout =
+        StateLayout::try_new(log, root_dir.path().to_owned(), &MetricsRegistry::new()).unwrap();
+    (root_dir, state_layout)
+}
+
+/// Creates a manifest of a single file consisting of `num_chunks` chunks.
+/// Only the structure of the manifest matters for these tests.
+fn fake_manifest(num_chunks: usize) -> Manifest {
+    let file_table = vec![FileInfo {
+        relative_path: "system_metadata.pbuf".into(),
+        size_bytes: num_chunks as u64,
+        hash: [0; 32],
+    }];
+    let chunk_table = (0..num_chunks)
+        .map(|i| ChunkInfo {
+            file_index: 0,
+            size_bytes: 1,
+            offset: i as u64,
+            hash: [i as u8; 32],
+        })
+        .collect();
+    Manifest::new(CURRENT_STATE_SYNC_VERSION, file_table, chunk_table)
+}
+
+/// Creates the scratchpad and records the progress of a state sync at
+/// `height` that started with `missing_chunks` and fetched `fetched_chunks`.
+/// Returns the log of fetched chunks.
+fn interrupted_sync(
+    state_layout: &StateLayout,
+    height: Height,
+    manifest: &Manifest,
+    missing_chunks: &[usize],
+    fetched_chunks: &[u32],
+) -> FetchedChunksLog {
+    std::fs::create_dir_all(state_layout.state_sync_scratchpad(height).unwrap()).unwrap();
+    let mut fetched_chunks_log = write_progress(
+        state_layout,
+        height,
+        manifest,
+        &missing_chunks.iter().copied().collect(),
+    )
+    .unwrap();
+    fetched_chunks_log.record(fetched_chunks).unwrap();
+    fetched_chunks_log
+}
+
+fn state_sync_dir_entries(state_layout: &StateLayout) -> Vec<PathBuf> {
+    let mut entries: Vec<_> = std::fs::read_dir(state_layout.state_sync_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+    entries
+}
+
+#[test]
+fn recovered_progress_excludes_fetched_chunks() {
+    with_test_replica_logger(|log| {
+        let (_root_dir, state_layout) = new_state_layout(log.clone());
+        let height = Height::new(7);
+        let manifest = fake_manifest(5);
+        let mut fetched_chunks_log =
+            interrupted_sync(&state_layout, height, &manifest, &[0, 2, 3, 4], &[2]);
+        fetched_chunks_log.record(&[4]).unwrap();
+        drop(fetched_chunks_log);
+
+        // Simulate a crash in the middle of appending to the log.
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(state_layout.state_sync_fetched_chunks(height))
+            .unwrap()
+            .write_all(&[3, 0])
+            .unwrap();
+
+        let progress = recover_progress(&log, &state_layout).expect("no progress recovered");
+        assert_eq!(progress.height, height);
+        assert_eq!(progress.manifest, manifest);
+        assert_eq!(progress.missing_chunks, maplit::hashset! {0, 3});
+
+        // Only the scratchpad is left, the caller owns it now.
+        assert_eq!(
+            state_sync_dir_entries(&state_layout),
+            vec![state_layout.state_sync_scratchpad(height).unwrap()]
+        );
+    })
+}
+
+#[test]
+fn recovery_picks_highest_usable_sync_and_deletes_the_rest() {
+    with_test_replica_logger(|log| {
+        let (_root_dir, state_layout) = new_state_layout(log.clone());
+        let manifest = fake_manifest(3);
+
+        interrupted_sync(&state_layout, Height::new(3), &manifest, &[0, 1, 2], &[1]);
+        interrupted_sync(&state_layout, Height::new(5), &manifest, &[0, 1, 2], &[0]);
+        // The scratchpad at height 9 is gone, e.g. because it became a checkpoint.
+        interrupted_sync(&state_layout, Height::new(9), &manifest, &[1], &[]);
+        std::fs::remove_dir(state_layout.state_sync_scratchpad(Height::new(9)).unwrap()).unwrap();
+        // The progress at height 11 is corrupted.
+        std::fs::create_dir_all(state_layout.state_sync_scratchpad(Height::new(11)).unwrap())
+            .unwrap();
+        std::fs::write(state_layout.state_sync_progress(Height::new(11)), [0xff; 4]).unwrap();
+
+        let progress = recover_progress(&log, &state_layout).expect("no progress recovered");
+        assert_eq!(progress.height, Height::new(5));
+        assert_eq!(progress.missing_chunks, maplit::hashset! {1, 2});
+        assert_eq!(
+            state_sync_dir_entries(&state_layout),
+            vec![state_layout.state_sync_scratchpad(Height::new(5)).unwrap()]
+        );
+    })
+}
+
+#[test]
+fn recovered_sync_populates_cache() {
+    with_test_replica_logger(|log| {
+        let (_root_dir, state_layout) = new_state_layout(log.clone());
+        let height = Height::new(5);
+        let manifest = fake_manifest(4);
+        interrupted_sync(&state_layout, height, &manifest, &[0, 1, 2, 3], &[1, 2]);
+
+        let mut cache = StateSyncCache::new(log.clone());
+        assert_eq!(cache.recover(&state_layout), 2);
+
+        let entry = cache.get().expect("cache is empty");
+        assert_eq!(entry.height, height);
+        assert_eq!(entry.manifest, manifest);
+        assert_eq!(entry.missing_chunks, maplit::hashset! {0, 3});
+        assert!(entry.recovered_after_restart);
+        assert_eq!(entry.path(), state_layout.state_sync_cache(height).unwrap());
+        assert!(entry.path().exists());
+        assert!(state_sync_dir_entries(&state_layout).is_empty());
+
+        // Nothing left to recover.
+        let mut cache = StateSyncCache::new(log);
+        assert_eq!(cache.recover(&state_layout), 0);
+        assert!(cache.get().is_none());
+    })
+}
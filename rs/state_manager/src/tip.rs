@@ -6,7 +6,7 @@ use crate::{
 use crossbeam_channel::{unbounded, Sender};
 use ic_base_types::subnet_id_into_protobuf;
 use ic_config::flag_status::FlagStatus;
-use ic_logger::{error, fatal, info, ReplicaLogger};
+use ic_logger::{error, fatal, info, warn, ReplicaLogger};
 use ic_protobuf::state::{
     stats::v1::Stats,
     system_metadata::v1::{SplitFrom, SystemMetadata},
@@ -624,6 +624,11 @@ fn serialize_canister_to_tip(
                 .metadata()
                 .clone(),
             total_query_stats: canister_state.scheduler_state.total_query_stats.clone(),
+            wasm_memory_limit: canister_state.system_state.wasm_memory_limit,
+            wasm_memory_threshold: canister_state.system_state.wasm_memory_threshold,
+            on_low_wasm_memory_hook_status: canister_state
+                .system_state
+                .on_low_wasm_memory_hook_status,
         }
         .into(),
     )?;
@@ -719,6 +724,99 @@ fn truncate_pagemap(log: &ReplicaLogger, path: &Path, overlays: &[PathBuf]) {
     }
 }
 
+/// Computes the dirty chunks of `manifest_delta` and persists them before the
+/// manifest computation starts, so that a restart in the middle of the
+/// computation doesn't force a full rehash of the checkpoint.
+fn with_persisted_dirty_chunks(
+    log: &ReplicaLogger,
+    state_layout: &StateLayout,
+    checkpoint_layout: &CheckpointLayout<ReadOnly>,
+    mut manifest_delta: crate::manifest::ManifestDelta,
+) -> crate::manifest::ManifestDelta {
+    match crate::manifest::compute_dirty_chunks(
+        log,
+        &manifest_delta,
+        checkpoint_layout,
+        crate::manifest::DEFAULT_CHUNK_SIZE,
+    ) {
+        Ok(dirty_chunks) => {
+            if let Err(err) = state_layout
+                .dirty_chunks(checkpoint_layout.height())
+                .serialize((&dirty_chunks).into())
+            {
+                warn!(
+                    log,
+                    "Failed to persist dirty chunks of checkpoint @{}: {}",
+                    checkpoint_layout.height(),
+                    err
+                );
+            }
+            manifest_delta.dirty_chunks = Some(dirty_chunks);
+        }
+        Err(err) => {
+            warn!(
+                log,
+                "Failed to compute dirty chunks of checkpoint @{}: {}",
+                checkpoint_layout.height(),
+                err
+            );
+        }
+    }
+    manifest_delta
+}
+
+/// Restores the `ManifestDelta` of a checkpoint from the dirty chunks persisted
+/// before a restart, provided that the base checkpoint and its manifest are
+/// still available.
+fn persisted_manifest_delta(
+    log: &ReplicaLogger,
+    states: &parking_lot::RwLock<SharedState>,
+    state_layout: &StateLayout,
+    checkpoint_layout: &CheckpointLayout<ReadOnly>,
+) -> Option<crate::manifest::ManifestDelta> {
+    let height = checkpoint_layout.height();
+    let dirty_chunks = match state_layout.dirty_chunks(height).deserialize() {
+        // An absent file deserializes as an empty message.
+        Ok(dirty_chunks) if dirty_chunks.files.is_empty() => return None,
+        Ok(dirty_chunks) => crate::manifest::DirtyChunks::try_from(dirty_chunks),
+        Err(err) => {
+            warn!(log, "Failed to read dirty chunks @{}: {}", height, err);
+            return None;
+        }
+    };
+    let dirty_chunks = match dirty_chunks {
+        Ok(dirty_chunks) => dirty_chunks,
+        Err(err) => {
+            warn!(log, "Failed to decode dirty chunks @{}: {}", height, err);
+            return None;
+        }
+    };
+
+    let base_height = dirty_chunks.base_height;
+    let base_manifest = states
+        .read()
+        .states_metadata
+        .get(&base_height)?
+        .manifest()?
+        .clone();
+    let base_checkpoint = state_layout.checkpoint(base_height).ok()?;
+
+    info!(
+        log,
+        "Computing manifest @{} from the dirty chunks persisted relative to @{}",
+        height,
+        base_height
+    );
+    Some(crate::manifest::ManifestDelta {
+        base_manifest,
+        base_height,
+        target_height: height,
+        dirty_memory_pages: Vec::new(),
+        base_checkpoint,
+        dirty_chunks: Some(dirty_chunks),
+    })
+}
+
 #[allow(clippy::too_many_arguments)]
 fn handle_compute_manifest_request(
     thread_pool: &mut scoped_threadpool::Pool,
@@ -753,6 +851,16 @@ fn handle_compute_manifest_request(
         MAX_SUPPORTED_STATE_SYNC_VERSION
     );
 
+    let manifest_delta = match manifest_delta {
+        Some(manifest_delta) => Some(with_persisted_dirty_chunks(
+            log,
+            state_layout,
+            checkpoint_layout,
+            manifest_delta,
+        )),
+        None => persisted_manifest_delta(log, states, state_layout, checkpoint_layout),
+    };
+
     let start = Instant::now();
     let manifest = crate::manifest::compute_manifest(
         thread_pool,
@@ -1,12 +1,38 @@
 //! Computes manifest of a checkpoint.
 
-use ic_state_manager::manifest::{manifest_from_path, manifest_hash};
+use crate::commands::verify_manifest::parse_manifest;
+use ic_protobuf::state::sync::v1 as pb;
+use ic_state_layout::{ProtoFileWith, ReadOnly};
+use ic_state_manager::manifest::{
+    manifest_from_path, manifest_from_path_with_dirty_chunks, manifest_hash, DirtyChunks,
+};
+use ic_types::state_sync::Manifest;
+use std::fs::File;
 use std::path::{Path, PathBuf};
 
 /// Computes the manifest (chunk hashes, file hashes and root hash) of the
 /// checkpoint rooted at `path`.
-pub fn do_compute_manifest(path: PathBuf) -> Result<(), String> {
-    println!("{}", compute_manifest(&path)?);
+///
+/// If `base_manifest` and `dirty_chunks` are provided, only the chunks marked
+/// as dirty are rehashed and the hashes of all other chunks are taken from the
+/// base manifest.
+pub fn do_compute_manifest(
+    path: PathBuf,
+    base_manifest: Option<PathBuf>,
+    dirty_chunks: Option<PathBuf>,
+) -> Result<(), String> {
+    match (base_manifest, dirty_chunks) {
+        (Some(base_manifest), Some(dirty_chunks)) => println!(
+            "{}",
+            compute_manifest_with_dirty_chunks(&path, &base_manifest, &dirty_chunks)?
+        ),
+        (None, None) => println!("{}", compute_manifest(&path)?),
+        _ => {
+            return Err(
+                "--base-manifest and --dirty-chunks have to be provided together".to_string(),
+            )
+        }
+    }
 
     Ok(())
 }
@@ -20,9 +46,46 @@ pub fn compute_manifest(path: &Path) -> Result<String, String> {
         )
     })?;
 
-    Ok(format!(
+    Ok(format_manifest(&manifest))
+}
+
+/// Computes the manifest of the checkpoint at `path` from the textual
+/// manifest of its base checkpoint and the dirty chunks persisted in the
+/// state root (`<state_root>/dirty_chunks/<height>.pbuf`).
+pub fn compute_manifest_with_dirty_chunks(
+    path: &Path,
+    base_manifest_path: &Path,
+    dirty_chunks_path: &Path,
+) -> Result<String, String> {
+    let (version, file_table, chunk_table, _) = parse_manifest(
+        File::open(base_manifest_path)
+            .map_err(|e| format!("Failed to open {}: {}", base_manifest_path.display(), e))?,
+    )?;
+    let base_manifest = Manifest::new(version, file_table, chunk_table);
+
+    let dirty_chunks: DirtyChunks =
+        ProtoFileWith::<pb::DirtyChunks, ReadOnly>::from(dirty_chunks_path.to_path_buf())
+            .deserialize()
+            .map_err(|e| format!("Failed to read {}: {}", dirty_chunks_path.display(), e))?
+            .try_into()
+            .map_err(|e| format!("Failed to decode {}: {}", dirty_chunks_path.display(), e))?;
+
+    let manifest = manifest_from_path_with_dirty_chunks(path, base_manifest, dirty_chunks)
+        .map_err(|e| {
+            format!(
+                "Failed to compute manifest of checkpoint at {}: {}",
+                path.display(),
+                e
+            )
+        })?;
+
+    Ok(format_manifest(&manifest))
+}
+
+fn format_manifest(manifest: &Manifest) -> String {
+    format!(
         "{}\n\nROOT HASH: {}",
         manifest,
-        hex::encode(manifest_hash(&manifest))
-    ))
+        hex::encode(manifest_hash(manifest))
+    )
 }
@@ -49,6 +49,17 @@ enum Opt {
         /// Path to a checkpoint.
         #[clap(long = "state")]
         path: PathBuf,
+
+        /// Path to the manifest of the base checkpoint, as printed by this
+        /// command. Requires `--dirty-chunks`.
+        #[clap(long)]
+        base_manifest: Option<PathBuf>,
+
+        /// Path to the dirty chunks of the checkpoint relative to the base
+        /// checkpoint (`<state_root>/dirty_chunks/<height>.pbuf`). Only the
+        /// dirty chunks are rehashed. Requires `--base-manifest`.
+        #[clap(long)]
+        dirty_chunks: Option<PathBuf>,
     },
 
     /// Verifies whether the textual representation
@@ -159,7 +170,11 @@ fn main() {
             config,
             height,
         } => commands::import_state::do_import(state, config, height),
-        Opt::Manifest { path } => commands::manifest::do_compute_manifest(path),
+        Opt::Manifest {
+            path,
+            base_manifest,
+            dirty_chunks,
+        } => commands::manifest::do_compute_manifest(path, base_manifest, dirty_chunks),
         Opt::VerifyManifest { file } => commands::verify_manifest::do_verify_manifest(&file),
         Opt::ListStates { config } => commands::list::do_list(config),
         Opt::Decode { file } => commands::decode::do_decode(file),
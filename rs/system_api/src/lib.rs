@@ -8,11 +8,12 @@ use ic_base_types::PrincipalIdBlobParseError;
 use ic_config::flag_status::FlagStatus;
 use ic_cycles_account_manager::ResourceSaturation;
 use ic_error_types::RejectCode;
+use ic_ic00_types::{EcdsaCurve, EcdsaKeyId};
 use ic_interfaces::execution_environment::{
     ExecutionMode,
     HypervisorError::{self, *},
     HypervisorResult, OutOfInstructionsHandler, PerformanceCounterType, StableGrowOutcome,
-    StableMemoryApi, SubnetAvailableMemory, SystemApi,
+    StableMemoryApi, StateReads, SubnetAvailableMemory, SystemApi,
     TrapCode::{self, CyclesAmountTooBigFor64Bit},
 };
 use ic_logger::{error, ReplicaLogger};
@@ -21,7 +22,7 @@ use ic_replicated_state::{
     canister_state::WASM_PAGE_SIZE_IN_BYTES, memory_required_to_push_request, Memory, NumWasmPages,
     PageIndex,
 };
-use ic_sys::PageBytes;
+use ic_sys::{PageBytes, PAGE_SIZE};
 use ic_types::{
     ingress::WasmResult,
     messages::{CallContextId, RejectContext, Request, MAX_INTER_CANISTER_PAYLOAD_IN_BYTES},
@@ -35,6 +36,8 @@ use sandbox_safe_system_state::{CanisterStatusView, SandboxSafeSystemState, Syst
 use serde::{Deserialize, Serialize};
 use stable_memory::StableMemory;
 use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeSet,
     convert::{From, TryFrom},
     rc::Rc,
 };
@@ -43,6 +46,11 @@ pub const MULTIPLIER_MAX_SIZE_LOCAL_SUBNET: u64 = 5;
 const MAX_NON_REPLICATED_QUERY_REPLY_SIZE: NumBytes = NumBytes::new(3 << 20);
 const CERTIFIED_DATA_MAX_LENGTH: u32 = 32;
 
+// The results of the cost introspection calls that can fail.
+const COST_SUCCESS: u32 = 0;
+const COST_UNKNOWN_ECDSA_CURVE: u32 = 1;
+const COST_UNKNOWN_ECDSA_KEY: u32 = 2;
+
 // Enables tracing of system calls for local debugging.
 const TRACE_SYSCALLS: bool = false;
 
@@ -154,6 +162,8 @@ impl InstructionLimits {
 pub struct ExecutionParameters {
     pub instruction_limits: InstructionLimits,
     pub canister_memory_limit: NumBytes,
+    /// The `wasm_memory_limit` setting of the canister, if any.
+    pub wasm_memory_limit: Option<NumBytes>,
     pub memory_allocation: MemoryAllocation,
     pub compute_allocation: ComputeAllocation,
     pub subnet_type: SubnetType,
@@ -322,11 +332,13 @@ pub enum ApiType {
         message_accepted: bool,
     },
 
-    // For executing the `canister_heartbeat` or `canister_global_timer` methods
+    // For executing the `canister_heartbeat`, `canister_global_timer` or
+    // `canister_on_low_wasm_memory` methods
     SystemTask {
         caller: PrincipalId,
         /// System task to execute.
-        /// Only `canister_heartbeat` and `canister_global_timer` are allowed.
+        /// Only `canister_heartbeat`, `canister_global_timer` and
+        /// `canister_on_low_wasm_memory` are allowed.
         system_task: SystemMethod,
         time: Time,
         call_context_id: CallContextId,
@@ -550,7 +562,11 @@ impl ApiType {
             ApiType::SystemTask { system_task, .. } => match system_task {
                 SystemMethod::CanisterHeartbeat => "heartbeat",
                 SystemMethod::CanisterGlobalTimer => "global timer",
-                _ => panic!("Only `canister_heartbeat` and `canister_global_timer` are allowed."),
+                SystemMethod::CanisterOnLowWasmMemory => "on low wasm memory",
+                _ => panic!(
+                    "Only `canister_heartbeat`, `canister_global_timer` and \
+                     `canister_on_low_wasm_memory` are allowed."
+                ),
             },
             ApiType::Update { .. } => "update",
             ApiType::ReplicatedQuery { .. } => "replicated query",
@@ -786,6 +802,17 @@ impl MemoryUsage {
     }
 }
 
+/// The parts of the canister state read through the System API. The stable
+/// memory pages are only recorded in non-replicated queries, since only their
+/// results are cached.
+#[derive(Default)]
+struct RecordedStateReads {
+    time: Cell<bool>,
+    cycles_balance: Cell<bool>,
+    canister_version: Cell<bool>,
+    stable_memory_pages: RefCell<BTreeSet<PageIndex>>,
+}
+
 /// Struct that implements the SystemApi trait. This trait enables a canister to
 /// have mediated access to its system state.
 pub struct SystemApiImpl {
@@ -833,6 +860,9 @@ pub struct SystemApiImpl {
     /// is initialized to 0 and updated after each out-of-instructions call that
     /// starts a new slice.
     instructions_executed_before_current_slice: i64,
+
+    /// The parts of the canister state that the execution read so far.
+    state_reads: RecordedStateReads,
 }
 
 impl SystemApiImpl {
@@ -874,9 +904,57 @@ impl SystemApiImpl {
             log,
             current_slice_instruction_limit: i64::try_from(slice_limit).unwrap_or(i64::MAX),
             instructions_executed_before_current_slice: 0,
+            state_reads: RecordedStateReads::default(),
         }
     }
 
+    /// Returns the parts of the canister state that the execution read through
+    /// the System API.
+    ///
+    /// The accessed Wasm memory pages are not known here and are left as `None`
+    /// for the embedder to fill in. With Wasm-native stable memory only the
+    /// stable memory pages read on their first access go through the System
+    /// API, so the embedder has to add the pages accessed by the Wasm code.
+    pub fn state_reads(&self) -> StateReads {
+        let stable_memory_pages = match &self.api_type {
+            ApiType::NonReplicatedQuery { .. } => Some(
+                self.state_reads
+                    .stable_memory_pages
+                    .borrow()
+                    .iter()
+                    .copied()
+                    .collect(),
+            ),
+            _ => None,
+        };
+        StateReads {
+            wasm_memory_pages: None,
+            stable_memory_pages,
+            time: self.state_reads.time.get(),
+            cycles_balance: self.state_reads.cycles_balance.get(),
+            canister_version: self.state_reads.canister_version.get(),
+        }
+    }
+
+    /// Records the stable memory pages covered by a read of `size` bytes at
+    /// `offset`. Out of bounds reads trap, so only the pages within the
+    /// current stable memory are recorded.
+    fn record_stable_memory_read(&self, offset: u64, size: u64) {
+        if !matches!(self.api_type, ApiType::NonReplicatedQuery { .. }) {
+            return;
+        }
+        let stable_memory_bytes =
+            self.stable_memory.stable_memory_size.get() as u64 * WASM_PAGE_SIZE_IN_BYTES as u64;
+        let end = offset.saturating_add(size).min(stable_memory_bytes);
+        if offset >= end {
+            return;
+        }
+        let first_page = offset / PAGE_SIZE as u64;
+        let last_page = (end - 1) / PAGE_SIZE as u64;
+        let mut pages = self.state_reads.stable_memory_pages.borrow_mut();
+        pages.extend((first_page..=last_page).map(PageIndex::new));
+    }
+
     /// Refunds any cycles used for an outgoing request that doesn't get sent
     /// and returns the result of execution.
     pub fn take_execution_result(
@@ -1117,6 +1195,7 @@ impl SystemApiImpl {
     }
 
     fn ic0_canister_cycle_balance_helper(&self, method_name: &str) -> HypervisorResult<Cycles> {
+        self.state_reads.cycles_balance.set(true);
         match &self.api_type {
             ApiType::Start { .. } => Err(self.error_for(method_name)),
             ApiType::Init { .. }
@@ -1135,6 +1214,36 @@ impl SystemApiImpl {
         }
     }
 
+    /// Copies the result of a cost introspection call to the heap. These calls
+    /// only depend on the subnet, so they are available everywhere except in
+    /// `canister_start`, which runs before the canister is fully set up.
+    fn ic0_cost_helper(
+        &self,
+        method_name: &str,
+        cost: Cycles,
+        dst: u32,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        self.ic0_cost_supported(method_name)?;
+        copy_cycles_to_heap(cost, dst, heap, method_name)
+    }
+
+    fn ic0_cost_supported(&self, method_name: &str) -> HypervisorResult<()> {
+        match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for(method_name)),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::InspectMessage { .. } => Ok(()),
+        }
+    }
+
     fn ic0_msg_cycles_available_helper(&self, method_name: &str) -> HypervisorResult<Cycles> {
         match &self.api_type {
             ApiType::Start { .. }
@@ -1251,6 +1360,25 @@ impl SystemApiImpl {
         self.stable_memory().stable_memory_size
     }
 
+    /// Checks that a Wasm memory of `wasm_memory_size` fits into the
+    /// `wasm_memory_limit` setting of the canister.
+    ///
+    /// Returns `Err(HypervisorError::WasmMemoryLimitExceeded)` if the limit is
+    /// set and exceeded.
+    pub fn check_wasm_memory_limit(&self, wasm_memory_size: NumWasmPages) -> HypervisorResult<()> {
+        if let Some(limit) = self.execution_parameters.wasm_memory_limit {
+            let bytes =
+                (wasm_memory_size.get() as u64).saturating_mul(WASM_PAGE_SIZE_IN_BYTES as u64);
+            if bytes > limit.get() {
+                return Err(HypervisorError::WasmMemoryLimitExceeded {
+                    bytes: NumBytes::new(bytes),
+                    limit,
+                });
+            }
+        }
+        Ok(())
+    }
+
     /// Wrapper around `self.sandbox_safe_system_state.push_output_request()` that
     /// tries to allocate memory for the `Request` before pushing it.
     ///
@@ -2129,6 +2257,7 @@ impl SystemApi for SystemApiImpl {
         size: u32,
         heap: &mut [u8],
     ) -> HypervisorResult<()> {
+        self.record_stable_memory_read(offset as u64, size as u64);
         let result = self.stable_memory().stable_read(dst, offset, size, heap);
         trace_syscall!(
             self,
@@ -2198,6 +2327,7 @@ impl SystemApi for SystemApiImpl {
         size: u64,
         heap: &mut [u8],
     ) -> HypervisorResult<()> {
+        self.record_stable_memory_read(offset, size);
         let result = self.stable_memory().stable64_read(dst, offset, size, heap);
         trace_syscall!(
             self,
@@ -2218,6 +2348,7 @@ impl SystemApi for SystemApiImpl {
         size: u64,
         heap: &mut [u8],
     ) -> HypervisorResult<()> {
+        self.record_stable_memory_read(offset, size);
         self.stable_memory
             .stable_read_without_bounds_checks(dst, offset, size, heap)
     }
@@ -2257,6 +2388,7 @@ impl SystemApi for SystemApiImpl {
     }
 
     fn ic0_time(&self) -> HypervisorResult<Time> {
+        self.state_reads.time.set(true);
         let result = match &self.api_type {
             ApiType::Start { .. } => Err(self.error_for("ic0_time")),
             ApiType::Init { time, .. }
@@ -2324,6 +2456,7 @@ impl SystemApi for SystemApiImpl {
     }
 
     fn ic0_canister_version(&self) -> HypervisorResult<u64> {
+        self.state_reads.canister_version.set(true);
         let result = match &self.api_type {
             ApiType::Start { .. } => Err(self.error_for("ic0_canister_version")),
             ApiType::Init { .. }
@@ -2562,6 +2695,8 @@ impl SystemApi for SystemApiImpl {
     }
 
     fn ic0_data_certificate_present(&self) -> HypervisorResult<i32> {
+        // The data certificate changes with every certified state.
+        self.state_reads.time.set(true);
         let result = match &self.api_type {
             ApiType::Start { .. } => Err(self.error_for("ic0_data_certificate_present")),
             ApiType::Init { .. }
@@ -2587,6 +2722,7 @@ impl SystemApi for SystemApiImpl {
     }
 
     fn ic0_data_certificate_size(&self) -> HypervisorResult<i32> {
+        self.state_reads.time.set(true);
         let result = match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
@@ -2618,6 +2754,7 @@ impl SystemApi for SystemApiImpl {
         size: u32,
         heap: &mut [u8],
     ) -> HypervisorResult<()> {
+        self.state_reads.time.set(true);
         let result = match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
@@ -2830,6 +2967,8 @@ impl SystemApi for SystemApiImpl {
     }
 
     fn ic0_is_controller(&self, src: u32, size: u32, heap: &[u8]) -> HypervisorResult<u32> {
+        // Controller changes also bump the canister version.
+        self.state_reads.canister_version.set(true);
         let result = match &self.api_type {
             ApiType::Start { .. }
             | ApiType::Init { .. }
@@ -2922,6 +3061,81 @@ impl SystemApi for SystemApiImpl {
         trace_syscall!(self, CyclesBurn128, result, amount);
         result
     }
+
+    fn ic0_cost_call(
+        &self,
+        method_name_size: u64,
+        payload_size: u64,
+        dst: u32,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let cost = self.sandbox_safe_system_state.call_fee(NumBytes::from(
+            method_name_size.saturating_add(payload_size),
+        ));
+        let result = self.ic0_cost_helper("ic0_cost_call", cost, dst, heap);
+        trace_syscall!(self, CostCall, result, method_name_size, payload_size, cost);
+        result
+    }
+
+    fn ic0_cost_create_canister(&self, dst: u32, heap: &mut [u8]) -> HypervisorResult<()> {
+        let cost = self.sandbox_safe_system_state.canister_creation_fee();
+        let result = self.ic0_cost_helper("ic0_cost_create_canister", cost, dst, heap);
+        trace_syscall!(self, CostCreateCanister, result, cost);
+        result
+    }
+
+    fn ic0_cost_http_request(
+        &self,
+        request_size: u64,
+        max_res_bytes: u64,
+        dst: u32,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let cost = self
+            .sandbox_safe_system_state
+            .http_request_fee(NumBytes::from(request_size), NumBytes::from(max_res_bytes));
+        let result = self.ic0_cost_helper("ic0_cost_http_request", cost, dst, heap);
+        trace_syscall!(
+            self,
+            CostHttpRequest,
+            result,
+            request_size,
+            max_res_bytes,
+            cost
+        );
+        result
+    }
+
+    fn ic0_cost_sign_with_ecdsa(
+        &self,
+        src: u32,
+        size: u32,
+        ecdsa_curve: u32,
+        dst: u32,
+        heap: &mut [u8],
+    ) -> HypervisorResult<u32> {
+        let method_name = "ic0_cost_sign_with_ecdsa";
+        let result = self.ic0_cost_supported(method_name).and_then(|()| {
+            let key_name = valid_subslice(method_name, src, size, heap)?;
+            let curve = match ecdsa_curve {
+                0 => EcdsaCurve::Secp256k1,
+                _ => return Ok(COST_UNKNOWN_ECDSA_CURVE),
+            };
+            let key_id = EcdsaKeyId {
+                curve,
+                name: String::from_utf8_lossy(key_name).to_string(),
+            };
+            match self.sandbox_safe_system_state.ecdsa_signature_fee(&key_id) {
+                Some(cost) => {
+                    copy_cycles_to_heap(cost, dst, heap, method_name)?;
+                    Ok(COST_SUCCESS)
+                }
+                None => Ok(COST_UNKNOWN_ECDSA_KEY),
+            }
+        });
+        trace_syscall!(self, CostSignWithEcdsa, result, src, size, ecdsa_curve);
+        result
+    }
 }
 
 /// The default implementation of the `OutOfInstructionHandler` trait.
@@ -8,8 +8,9 @@ use ic_cycles_account_manager::{
 };
 use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_ic00_types::{
-    CreateCanisterArgs, InstallChunkedCodeArgs, InstallCodeArgsV2, Method as Ic00Method, Payload,
-    ProvisionalCreateCanisterWithCyclesArgs, UninstallCodeArgs, UpdateSettingsArgs, IC_00,
+    CreateCanisterArgs, EcdsaKeyId, InstallChunkedCodeArgs, InstallCodeArgsV2,
+    Method as Ic00Method, Payload, ProvisionalCreateCanisterWithCyclesArgs, UninstallCodeArgs,
+    UpdateSettingsArgs, IC_00,
 };
 use ic_interfaces::execution_environment::{HypervisorError, HypervisorResult};
 use ic_logger::{info, ReplicaLogger};
@@ -542,6 +543,9 @@ pub struct SandboxSafeSystemState {
     global_timer: CanisterTimer,
     canister_version: u64,
     controllers: BTreeSet<PrincipalId>,
+    /// The fee for a signature with each ECDSA key that some subnet is enabled
+    /// to sign with.
+    ecdsa_signature_fees: BTreeMap<EcdsaKeyId, Cycles>,
 }
 
 impl SandboxSafeSystemState {
@@ -568,6 +572,7 @@ impl SandboxSafeSystemState {
         global_timer: CanisterTimer,
         canister_version: u64,
         controllers: BTreeSet<PrincipalId>,
+        ecdsa_signature_fees: BTreeMap<EcdsaKeyId, Cycles>,
     ) -> Self {
         Self {
             canister_id,
@@ -591,6 +596,7 @@ impl SandboxSafeSystemState {
             global_timer,
             canister_version,
             controllers,
+            ecdsa_signature_fees,
         }
     }
 
@@ -632,6 +638,7 @@ impl SandboxSafeSystemState {
         let subnet_size = network_topology
             .get_subnet_size(&cycles_account_manager.get_subnet_id())
             .unwrap_or(SMALL_APP_SUBNET_MAX_SIZE);
+        let ecdsa_signature_fees = ecdsa_signature_fees(&cycles_account_manager, network_topology);
 
         Self::new_internal(
             system_state.canister_id,
@@ -655,6 +662,7 @@ impl SandboxSafeSystemState {
             system_state.global_timer,
             system_state.canister_version,
             system_state.controllers.clone(),
+            ecdsa_signature_fees,
         )
     }
 
@@ -774,6 +782,39 @@ impl SandboxSafeSystemState {
         result
     }
 
+    /// Returns the fee for sending a call with `payload_size` bytes of method
+    /// name and argument.
+    pub(super) fn call_fee(&self, payload_size: NumBytes) -> Cycles {
+        self.cycles_account_manager
+            .xnet_call_performed_fee(self.subnet_size)
+            + self
+                .cycles_account_manager
+                .xnet_call_bytes_transmitted_fee(payload_size, self.subnet_size)
+    }
+
+    pub(super) fn canister_creation_fee(&self) -> Cycles {
+        self.cycles_account_manager
+            .canister_creation_fee(self.subnet_size)
+    }
+
+    pub(super) fn http_request_fee(
+        &self,
+        request_size: NumBytes,
+        response_size_limit: NumBytes,
+    ) -> Cycles {
+        self.cycles_account_manager.http_request_fee(
+            request_size,
+            Some(response_size_limit),
+            self.subnet_size,
+        )
+    }
+
+    /// Returns the fee for a signature with the given key, or `None` if no
+    /// subnet is enabled to sign with it.
+    pub(super) fn ecdsa_signature_fee(&self, key_id: &EcdsaKeyId) -> Option<Cycles> {
+        self.ecdsa_signature_fees.get(key_id).cloned()
+    }
+
     /// Burns min(balance - freezing_treshold, amount_to_burn) cycles from the canister's
     /// balance and returns the number of burned cycles.
     pub(super) fn cycles_burn128(
@@ -1154,6 +1195,33 @@ impl SandboxSafeSystemState {
     }
 }
 
+/// Computes the fee of a signature with each key that some subnet is enabled
+/// to sign with. Like the actual charge, the fee scales with the size of the
+/// signing subnet the request is routed to, and requests from the NNS subnet
+/// are free.
+fn ecdsa_signature_fees(
+    cycles_account_manager: &CyclesAccountManager,
+    network_topology: &NetworkTopology,
+) -> BTreeMap<EcdsaKeyId, Cycles> {
+    let from_nns = cycles_account_manager.get_subnet_id() == network_topology.nns_subnet_id;
+    network_topology
+        .ecdsa_signing_subnets
+        .iter()
+        .filter_map(|(key_id, subnets)| {
+            let signing_subnet = subnets.first()?;
+            let fee = if from_nns {
+                Cycles::zero()
+            } else {
+                let subnet_size = network_topology
+                    .get_subnet_size(signing_subnet)
+                    .unwrap_or(SMALL_APP_SUBNET_MAX_SIZE);
+                cycles_account_manager.ecdsa_signature_fee(subnet_size)
+            };
+            Some((key_id.clone(), fee))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeMap;
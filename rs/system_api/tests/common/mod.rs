@@ -41,6 +41,7 @@ pub fn execution_parameters() -> ExecutionParameters {
             NumInstructions::from(5_000_000_000),
         ),
         canister_memory_limit: NumBytes::new(4 << 30),
+        wasm_memory_limit: None,
         memory_allocation: MemoryAllocation::default(),
         compute_allocation: ComputeAllocation::default(),
         subnet_type: SubnetType::Application,
@@ -128,10 +129,26 @@ pub fn get_system_api(
     system_state: &SystemState,
     cycles_account_manager: CyclesAccountManager,
 ) -> SystemApiImpl {
-    let sandbox_safe_system_state = SandboxSafeSystemState::new(
+    get_system_api_with_network_topology(
+        api_type,
         system_state,
         cycles_account_manager,
         &NetworkTopology::default(),
+    )
+}
+
+// Not used in all test crates
+#[allow(dead_code)]
+pub fn get_system_api_with_network_topology(
+    api_type: ApiType,
+    system_state: &SystemState,
+    cycles_account_manager: CyclesAccountManager,
+    network_topology: &NetworkTopology,
+) -> SystemApiImpl {
+    let sandbox_safe_system_state = SandboxSafeSystemState::new(
+        system_state,
+        cycles_account_manager,
+        network_topology,
         SchedulerConfig::application_subnet().dirty_page_overhead,
         execution_parameters().compute_allocation,
     );
@@ -4,6 +4,7 @@ use ic_config::{
 };
 use ic_constants::SMALL_APP_SUBNET_MAX_SIZE;
 use ic_error_types::RejectCode;
+use ic_ic00_types::{EcdsaCurve, EcdsaKeyId};
 use ic_interfaces::execution_environment::{
     CanisterOutOfCyclesError, HypervisorError, HypervisorResult, PerformanceCounterType,
     SubnetAvailableMemory, SystemApi, TrapCode,
@@ -11,7 +12,8 @@ use ic_interfaces::execution_environment::{
 use ic_logger::replica_logger::no_op_logger;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    testing::CanisterQueuesTesting, CallOrigin, Memory, NetworkTopology, SystemState,
+    testing::CanisterQueuesTesting, CallOrigin, Memory, NetworkTopology, SubnetTopology,
+    SystemState,
 };
 use ic_system_api::{
     sandbox_safe_system_state::SandboxSafeSystemState, ApiType, DefaultOutOfInstructionsHandler,
@@ -22,15 +24,17 @@ use ic_test_utilities::{
     mock_time,
     state::SystemStateBuilder,
     types::{
-        ids::{call_context_test_id, canister_test_id, subnet_test_id, user_test_id},
+        ids::{call_context_test_id, canister_test_id, node_test_id, subnet_test_id, user_test_id},
         messages::RequestBuilder,
     },
 };
 use ic_types::{
     messages::{CallContextId, CallbackId, RejectContext, MAX_RESPONSE_COUNT_BYTES},
     methods::{Callback, WasmClosure},
-    time, CanisterTimer, CountBytes, Cycles, NumInstructions, PrincipalId, Time,
+    time, CanisterTimer, CountBytes, Cycles, NumBytes, NumInstructions, PrincipalId, SubnetId,
+    Time,
 };
+use maplit::btreemap;
 use std::{
     collections::BTreeSet,
     convert::{From, TryInto},
@@ -182,6 +186,10 @@ fn test_canister_init_support() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -240,6 +248,10 @@ fn test_canister_update_support() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -298,6 +310,10 @@ fn test_canister_replicated_query_support() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_not_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -356,6 +372,10 @@ fn test_canister_pure_query_support() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_not_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -424,6 +444,10 @@ fn test_canister_stateful_query_support() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_not_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -482,6 +506,10 @@ fn test_reply_api_support_on_nns() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -540,6 +568,10 @@ fn test_reply_api_support_non_nns() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -599,6 +631,10 @@ fn test_reject_api_support_on_nns() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -658,6 +694,10 @@ fn test_reject_api_support_non_nns() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -716,6 +756,10 @@ fn test_pre_upgrade_support() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -774,6 +818,10 @@ fn test_start_support() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_not_supported(api.ic0_in_replicated_execution());
     assert_api_not_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_not_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_not_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_not_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_not_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -836,6 +884,10 @@ fn test_cleanup_support() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -899,6 +951,10 @@ fn test_inspect_message_support() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_not_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -958,6 +1014,10 @@ fn test_canister_system_task_support() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -1016,6 +1076,10 @@ fn test_canister_system_task_support_nns() {
     assert_api_supported(api.ic0_is_controller(0, 0, &[]));
     assert_api_supported(api.ic0_in_replicated_execution());
     assert_api_supported(api.ic0_cycles_burn128(Cycles::zero(), 0, &mut []));
+    assert_api_supported(api.ic0_cost_call(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_create_canister(0, &mut []));
+    assert_api_supported(api.ic0_cost_http_request(0, 0, 0, &mut []));
+    assert_api_supported(api.ic0_cost_sign_with_ecdsa(0, 0, 0, 0, &mut []));
     check_stable_apis_support(api);
 }
 
@@ -1854,3 +1918,168 @@ fn test_ic0_cycles_burn() {
     // There are no more cycles that can be burned.
     assert_eq!(Cycles::new(0), Cycles::from(&heap));
 }
+
+fn cost_introspection_results(api: &SystemApiImpl) -> [Cycles; 3] {
+    let mut heap = vec![0; 48];
+    api.ic0_cost_call(10, 1_000, 0, &mut heap).unwrap();
+    api.ic0_cost_create_canister(16, &mut heap).unwrap();
+    api.ic0_cost_http_request(100, 2_000, 32, &mut heap)
+        .unwrap();
+    [0, 16, 32].map(|offset| {
+        Cycles::from(u128::from_le_bytes(
+            heap[offset..offset + 16].try_into().unwrap(),
+        ))
+    })
+}
+
+/// Returns the result of `ic0.cost_sign_with_ecdsa` and the copied fee.
+fn ecdsa_signature_cost(api: &SystemApiImpl, key_name: &str, ecdsa_curve: u32) -> (u32, Cycles) {
+    let mut heap = vec![0; 16];
+    heap.extend_from_slice(key_name.as_bytes());
+    let result = api
+        .ic0_cost_sign_with_ecdsa(16, key_name.len() as u32, ecdsa_curve, 0, &mut heap)
+        .unwrap();
+    let fee = Cycles::from(u128::from_le_bytes(heap[0..16].try_into().unwrap()));
+    (result, fee)
+}
+
+/// Returns a topology where a subnet of `signing_subnet_size` nodes signs with
+/// the key `ecdsa_key_name`.
+fn network_topology_with_ecdsa_key(
+    own_subnet_id: SubnetId,
+    signing_subnet_size: usize,
+) -> NetworkTopology {
+    let signing_subnet_id = subnet_test_id(2);
+    let key_id = EcdsaKeyId {
+        curve: EcdsaCurve::Secp256k1,
+        name: "ecdsa_key_name".to_string(),
+    };
+    NetworkTopology {
+        subnets: btreemap! {
+            own_subnet_id => SubnetTopology::default(),
+            signing_subnet_id => SubnetTopology {
+                nodes: (0..signing_subnet_size as u64).map(node_test_id).collect(),
+                ..SubnetTopology::default()
+            },
+        },
+        ecdsa_signing_subnets: btreemap! { key_id => vec![signing_subnet_id] },
+        ..NetworkTopology::default()
+    }
+}
+
+#[test]
+fn test_ic0_cost_introspection() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let api = get_system_api(
+        ApiTypeBuilder::build_update_api(),
+        &SystemStateBuilder::default().build(),
+        cycles_account_manager.clone(),
+    );
+    let subnet_size = SMALL_APP_SUBNET_MAX_SIZE;
+    assert_eq!(
+        cost_introspection_results(&api),
+        [
+            cycles_account_manager.xnet_call_performed_fee(subnet_size)
+                + cycles_account_manager
+                    .xnet_call_bytes_transmitted_fee(NumBytes::from(1_010), subnet_size),
+            cycles_account_manager.canister_creation_fee(subnet_size),
+            cycles_account_manager.http_request_fee(
+                NumBytes::from(100),
+                Some(NumBytes::from(2_000)),
+                subnet_size
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_ic0_cost_sign_with_ecdsa_uses_signing_subnet_size() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let signing_subnet_size = 2 * SMALL_APP_SUBNET_MAX_SIZE;
+    let api = get_system_api_with_network_topology(
+        ApiTypeBuilder::build_update_api(),
+        &SystemStateBuilder::default().build(),
+        cycles_account_manager.clone(),
+        &network_topology_with_ecdsa_key(
+            cycles_account_manager.get_subnet_id(),
+            signing_subnet_size,
+        ),
+    );
+
+    assert_eq!(
+        ecdsa_signature_cost(&api, "ecdsa_key_name", 0),
+        (
+            0,
+            cycles_account_manager.ecdsa_signature_fee(signing_subnet_size)
+        )
+    );
+    // Nothing is copied for unknown keys and curves.
+    assert_eq!(
+        ecdsa_signature_cost(&api, "other_key_name", 0),
+        (2, Cycles::zero())
+    );
+    assert_eq!(
+        ecdsa_signature_cost(&api, "ecdsa_key_name", 1),
+        (1, Cycles::zero())
+    );
+}
+
+#[test]
+fn test_ic0_cost_sign_with_ecdsa_is_free_on_nns() {
+    let cycles_account_manager = CyclesAccountManagerBuilder::new().build();
+    let nns_subnet_id = cycles_account_manager.get_subnet_id();
+    let api = get_system_api_with_network_topology(
+        ApiTypeBuilder::build_update_api(),
+        &SystemStateBuilder::default().build(),
+        cycles_account_manager,
+        &NetworkTopology {
+            nns_subnet_id,
+            ..network_topology_with_ecdsa_key(nns_subnet_id, SMALL_APP_SUBNET_MAX_SIZE)
+        },
+    );
+
+    assert_eq!(
+        ecdsa_signature_cost(&api, "ecdsa_key_name", 0),
+        (0, Cycles::zero())
+    );
+}
+
+#[test]
+fn test_ic0_cost_introspection_scales_with_subnet_size() {
+    let subnet_id = subnet_test_id(1);
+    let cycles_account_manager = CyclesAccountManagerBuilder::new()
+        .with_subnet_id(subnet_id)
+        .build();
+    let small_subnet_api = get_system_api(
+        ApiTypeBuilder::build_update_api(),
+        &SystemStateBuilder::default().build(),
+        cycles_account_manager.clone(),
+    );
+
+    let subnet_size = 2 * SMALL_APP_SUBNET_MAX_SIZE;
+    let network_topology = NetworkTopology {
+        subnets: btreemap! {
+            subnet_id => SubnetTopology {
+                nodes: (0..subnet_size as u64).map(node_test_id).collect(),
+                ..SubnetTopology::default()
+            }
+        },
+        ..NetworkTopology::default()
+    };
+    let large_subnet_api = get_system_api_with_network_topology(
+        ApiTypeBuilder::build_update_api(),
+        &SystemStateBuilder::default().build(),
+        cycles_account_manager.clone(),
+        &network_topology,
+    );
+
+    let small_subnet_costs = cost_introspection_results(&small_subnet_api);
+    let large_subnet_costs = cost_introspection_results(&large_subnet_api);
+    assert_eq!(
+        large_subnet_costs[1],
+        cycles_account_manager.canister_creation_fee(subnet_size)
+    );
+    for (small, large) in small_subnet_costs.iter().zip(large_subnet_costs.iter()) {
+        assert!(small < large, "{} < {}", small, large);
+    }
+}
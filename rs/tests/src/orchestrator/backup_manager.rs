@@ -191,6 +191,7 @@ pub fn test(env: TestEnv) {
         replay_period_secs: 30,
         thread_id: 0,
         disable_cold_storage: false,
+        verify_state_hashes: true,
     };
     let cold_storage = Some(ColdStorage {
         cold_storage_dir: cold_storage_dir.clone(),
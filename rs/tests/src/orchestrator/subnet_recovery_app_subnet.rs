@@ -248,6 +248,7 @@ pub fn app_subnet_recovery_test(env: TestEnv, subnet_size: usize, upgrade: bool,
         key_file: Some(ssh_authorized_priv_keys_dir.join(SSH_USERNAME)),
         test_mode: true,
         skip_prompts: true,
+        unattended: false,
     };
 
     let mut unassigned_nodes = env.topology_snapshot().unassigned_nodes();
@@ -137,6 +137,7 @@ pub fn subnet_splitting_test(env: TestEnv) {
         ),
         test_mode: true,
         skip_prompts: true,
+        unattended: false,
     };
 
     let subnet_splitting_args = SubnetSplittingArgs {
@@ -113,6 +113,7 @@ impl From<ErrorCode> for RejectCode {
             ReservedCyclesLimitExceededInMemoryAllocation => CanisterError,
             ReservedCyclesLimitExceededInMemoryGrow => CanisterError,
             InsufficientCyclesInMessageMemoryGrow => CanisterError,
+            CanisterWasmMemoryLimitExceeded => CanisterError,
         }
     }
 }
@@ -178,6 +179,7 @@ pub enum ErrorCode {
     ReservedCyclesLimitExceededInMemoryAllocation = 533,
     ReservedCyclesLimitExceededInMemoryGrow = 534,
     InsufficientCyclesInMessageMemoryGrow = 535,
+    CanisterWasmMemoryLimitExceeded = 536,
 }
 
 impl TryFrom<u64> for ErrorCode {
@@ -235,6 +237,7 @@ impl TryFrom<u64> for ErrorCode {
             533 => Ok(ErrorCode::ReservedCyclesLimitExceededInMemoryAllocation),
             534 => Ok(ErrorCode::ReservedCyclesLimitExceededInMemoryGrow),
             535 => Ok(ErrorCode::InsufficientCyclesInMessageMemoryGrow),
+            536 => Ok(ErrorCode::CanisterWasmMemoryLimitExceeded),
             _ => Err(TryFromError::ValueOutOfRange(err)),
         }
     }
@@ -352,7 +355,8 @@ impl UserError {
             | ErrorCode::InsufficientCyclesInMemoryGrow
             | ErrorCode::ReservedCyclesLimitExceededInMemoryAllocation
             | ErrorCode::ReservedCyclesLimitExceededInMemoryGrow
-            | ErrorCode::InsufficientCyclesInMessageMemoryGrow => false,
+            | ErrorCode::InsufficientCyclesInMessageMemoryGrow
+            | ErrorCode::CanisterWasmMemoryLimitExceeded => false,
         }
     }
 
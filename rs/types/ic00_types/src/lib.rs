@@ -252,6 +252,74 @@ impl CanisterControllersChangeRecord {
     }
 }
 
+/// `CandidType` for `CanisterComputeAllocationChangeRecord`
+/// ```text
+/// record {
+///   compute_allocation : nat64;
+/// }
+/// ```
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CanisterComputeAllocationChangeRecord {
+    compute_allocation: u64,
+}
+
+impl CanisterComputeAllocationChangeRecord {
+    pub fn compute_allocation(&self) -> u64 {
+        self.compute_allocation
+    }
+}
+
+/// `CandidType` for `CanisterMemoryAllocationChangeRecord`
+/// ```text
+/// record {
+///   memory_allocation : nat64;
+/// }
+/// ```
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CanisterMemoryAllocationChangeRecord {
+    memory_allocation: u64,
+}
+
+impl CanisterMemoryAllocationChangeRecord {
+    pub fn memory_allocation(&self) -> u64 {
+        self.memory_allocation
+    }
+}
+
+/// `CandidType` for `CanisterFreezingThresholdChangeRecord`
+/// ```text
+/// record {
+///   freezing_threshold : nat64;
+/// }
+/// ```
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CanisterFreezingThresholdChangeRecord {
+    freezing_threshold: u64,
+}
+
+impl CanisterFreezingThresholdChangeRecord {
+    pub fn freezing_threshold(&self) -> u64 {
+        self.freezing_threshold
+    }
+}
+
+/// `CandidType` for `CanisterReservedCyclesLimitChangeRecord`
+/// ```text
+/// record {
+///   reserved_cycles_limit : nat;
+/// }
+/// ```
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CanisterReservedCyclesLimitChangeRecord {
+    reserved_cycles_limit: u128,
+}
+
+impl CanisterReservedCyclesLimitChangeRecord {
+    pub fn reserved_cycles_limit(&self) -> u128 {
+        self.reserved_cycles_limit
+    }
+}
+
 /// `CandidType` for `CanisterChangeDetails`
 /// ```text
 /// variant {
@@ -266,6 +334,18 @@ impl CanisterControllersChangeRecord {
 ///   controllers_change : record {
 ///     controllers : vec principal;
 ///   };
+///   compute_allocation_change : record {
+///     compute_allocation : nat64;
+///   };
+///   memory_allocation_change : record {
+///     memory_allocation : nat64;
+///   };
+///   freezing_threshold_change : record {
+///     freezing_threshold : nat64;
+///   };
+///   reserved_cycles_limit_change : record {
+///     reserved_cycles_limit : nat;
+///   };
 /// }
 /// ```
 #[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -278,6 +358,50 @@ pub enum CanisterChangeDetails {
     CanisterCodeDeployment(CanisterCodeDeploymentRecord),
     #[serde(rename = "controllers_change")]
     CanisterControllersChange(CanisterControllersChangeRecord),
+    #[serde(rename = "compute_allocation_change")]
+    CanisterComputeAllocationChange(CanisterComputeAllocationChangeRecord),
+    #[serde(rename = "memory_allocation_change")]
+    CanisterMemoryAllocationChange(CanisterMemoryAllocationChangeRecord),
+    #[serde(rename = "freezing_threshold_change")]
+    CanisterFreezingThresholdChange(CanisterFreezingThresholdChangeRecord),
+    #[serde(rename = "reserved_cycles_limit_change")]
+    CanisterReservedCyclesLimitChange(CanisterReservedCyclesLimitChangeRecord),
+}
+
+/// The type of a canister change, i.e., `CanisterChangeDetails` without the payload.
+/// Used to filter the canister history.
+///
+/// `CandidType` for `CanisterChangeType`
+/// ```text
+/// variant {
+///   creation;
+///   code_uninstall;
+///   code_deployment;
+///   controllers_change;
+///   compute_allocation_change;
+///   memory_allocation_change;
+///   freezing_threshold_change;
+///   reserved_cycles_limit_change;
+/// }
+/// ```
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
+pub enum CanisterChangeType {
+    #[serde(rename = "creation")]
+    Creation,
+    #[serde(rename = "code_uninstall")]
+    CodeUninstall,
+    #[serde(rename = "code_deployment")]
+    CodeDeployment,
+    #[serde(rename = "controllers_change")]
+    ControllersChange,
+    #[serde(rename = "compute_allocation_change")]
+    ComputeAllocationChange,
+    #[serde(rename = "memory_allocation_change")]
+    MemoryAllocationChange,
+    #[serde(rename = "freezing_threshold_change")]
+    FreezingThresholdChange,
+    #[serde(rename = "reserved_cycles_limit_change")]
+    ReservedCyclesLimitChange,
 }
 
 impl CanisterChangeDetails {
@@ -300,9 +424,59 @@ impl CanisterChangeDetails {
             controllers,
         })
     }
+
+    pub fn compute_allocation_change(compute_allocation: u64) -> CanisterChangeDetails {
+        CanisterChangeDetails::CanisterComputeAllocationChange(
+            CanisterComputeAllocationChangeRecord { compute_allocation },
+        )
+    }
+
+    pub fn memory_allocation_change(memory_allocation: u64) -> CanisterChangeDetails {
+        CanisterChangeDetails::CanisterMemoryAllocationChange(
+            CanisterMemoryAllocationChangeRecord { memory_allocation },
+        )
+    }
+
+    pub fn freezing_threshold_change(freezing_threshold: u64) -> CanisterChangeDetails {
+        CanisterChangeDetails::CanisterFreezingThresholdChange(
+            CanisterFreezingThresholdChangeRecord { freezing_threshold },
+        )
+    }
+
+    pub fn reserved_cycles_limit_change(reserved_cycles_limit: u128) -> CanisterChangeDetails {
+        CanisterChangeDetails::CanisterReservedCyclesLimitChange(
+            CanisterReservedCyclesLimitChangeRecord {
+                reserved_cycles_limit,
+            },
+        )
+    }
+
+    pub fn change_type(&self) -> CanisterChangeType {
+        match self {
+            CanisterChangeDetails::CanisterCreation(_) => CanisterChangeType::Creation,
+            CanisterChangeDetails::CanisterCodeUninstall => CanisterChangeType::CodeUninstall,
+            CanisterChangeDetails::CanisterCodeDeployment(_) => CanisterChangeType::CodeDeployment,
+            CanisterChangeDetails::CanisterControllersChange(_) => {
+                CanisterChangeType::ControllersChange
+            }
+            CanisterChangeDetails::CanisterComputeAllocationChange(_) => {
+                CanisterChangeType::ComputeAllocationChange
+            }
+            CanisterChangeDetails::CanisterMemoryAllocationChange(_) => {
+                CanisterChangeType::MemoryAllocationChange
+            }
+            CanisterChangeDetails::CanisterFreezingThresholdChange(_) => {
+                CanisterChangeType::FreezingThresholdChange
+            }
+            CanisterChangeDetails::CanisterReservedCyclesLimitChange(_) => {
+                CanisterChangeType::ReservedCyclesLimitChange
+            }
+        }
+    }
 }
 
-/// Every canister change (canister creation, code uninstallation, code deployment, or controllers change) consists of
+/// Every canister change (canister creation, code uninstallation, code deployment, controllers change,
+/// or a change of the compute allocation, memory allocation, freezing threshold, or reserved cycles limit) consists of
 ///
 /// 1. the system timestamp (in nanoseconds since Unix Epoch) at which the change was performed,
 /// 2. the canister version after performing the change,
@@ -318,6 +492,8 @@ impl CanisterChangeDetails {
 ///
 /// Controllers changes are described by the full new set of the canister controllers after the change.
 ///
+/// Settings changes are described by the new value of the respective setting after the change.
+///
 /// `CandidType` for `CanisterChange`
 /// ```text
 /// record {
@@ -350,6 +526,18 @@ impl CanisterChange {
         }
     }
 
+    pub fn timestamp_nanos(&self) -> u64 {
+        self.timestamp_nanos
+    }
+
+    pub fn canister_version(&self) -> u64 {
+        self.canister_version
+    }
+
+    pub fn details(&self) -> &CanisterChangeDetails {
+        &self.details
+    }
+
     /// Returns the number of bytes to represent a canister change in memory.
     /// The vector of controllers in `CanisterCreation` and `CanisterControllersChange`
     /// is counted separately because the controllers are stored on heap
@@ -363,7 +551,11 @@ impl CanisterChange {
                 std::mem::size_of_val(canister_controllers_change.controllers())
             }
             CanisterChangeDetails::CanisterCodeDeployment(_)
-            | CanisterChangeDetails::CanisterCodeUninstall => 0,
+            | CanisterChangeDetails::CanisterCodeUninstall
+            | CanisterChangeDetails::CanisterComputeAllocationChange(_)
+            | CanisterChangeDetails::CanisterMemoryAllocationChange(_)
+            | CanisterChangeDetails::CanisterFreezingThresholdChange(_)
+            | CanisterChangeDetails::CanisterReservedCyclesLimitChange(_) => 0,
         };
         NumBytes::from((size_of::<CanisterChange>() + controllers_memory_size) as u64)
     }
@@ -452,6 +644,108 @@ impl CanisterInfoResponse {
 
 impl Payload<'_> for CanisterInfoResponse {}
 
+/// `CandidType` for `CanisterHistoryArgs`
+/// ```text
+/// record {
+///   canister_id : principal;
+///   change_types : opt vec change_type;
+///   start_timestamp_nanos : opt nat64;
+///   end_timestamp_nanos : opt nat64;
+///   start_index : opt nat64;
+///   max_changes : opt nat64;
+/// }
+/// ```
+///
+/// Only changes whose type is in `change_types` (all types if not set) and whose
+/// timestamp is in the range `[start_timestamp_nanos, end_timestamp_nanos)` are returned.
+/// Changes are indexed by their position in the canister history since canister creation;
+/// `start_index` is the index of the first change to consider.
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CanisterHistoryArgs {
+    pub canister_id: PrincipalId,
+    pub change_types: Option<Vec<CanisterChangeType>>,
+    pub start_timestamp_nanos: Option<u64>,
+    pub end_timestamp_nanos: Option<u64>,
+    pub start_index: Option<u64>,
+    pub max_changes: Option<u64>,
+}
+
+impl CanisterHistoryArgs {
+    pub fn new(canister_id: CanisterId) -> Self {
+        Self {
+            canister_id: canister_id.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn get_canister_id(&self) -> CanisterId {
+        CanisterId::unchecked_from_principal(self.canister_id)
+    }
+
+    /// Returns true if the given canister change passes the type and time range filters.
+    pub fn matches(&self, change: &CanisterChange) -> bool {
+        let type_matches = self
+            .change_types
+            .as_ref()
+            .map_or(true, |types| types.contains(&change.details.change_type()));
+        let start_matches = self
+            .start_timestamp_nanos
+            .map_or(true, |start| start <= change.timestamp_nanos);
+        let end_matches = self
+            .end_timestamp_nanos
+            .map_or(true, |end| change.timestamp_nanos < end);
+        type_matches && start_matches && end_matches
+    }
+}
+
+impl Payload<'_> for CanisterHistoryArgs {}
+
+/// `CandidType` for `CanisterHistoryResponse`
+/// ```text
+/// record {
+///   total_num_changes : nat64;
+///   changes : vec change;
+///   next_start_index : opt nat64;
+/// }
+/// ```
+///
+/// `next_start_index` is set if there are more matching changes than returned
+/// and should be used as `start_index` to fetch the next page.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CanisterHistoryResponse {
+    total_num_changes: u64,
+    changes: Vec<CanisterChange>,
+    next_start_index: Option<u64>,
+}
+
+impl CanisterHistoryResponse {
+    pub fn new(
+        total_num_changes: u64,
+        changes: Vec<CanisterChange>,
+        next_start_index: Option<u64>,
+    ) -> Self {
+        Self {
+            total_num_changes,
+            changes,
+            next_start_index,
+        }
+    }
+
+    pub fn total_num_changes(&self) -> u64 {
+        self.total_num_changes
+    }
+
+    pub fn changes(&self) -> Vec<CanisterChange> {
+        self.changes.clone()
+    }
+
+    pub fn next_start_index(&self) -> Option<u64> {
+        self.next_start_index
+    }
+}
+
+impl Payload<'_> for CanisterHistoryResponse {}
+
 impl From<&CanisterChangeOrigin> for pb_canister_state_bits::canister_change::ChangeOrigin {
     fn from(item: &CanisterChangeOrigin) -> Self {
         match item {
@@ -535,6 +829,36 @@ impl From<&CanisterChangeDetails> for pb_canister_state_bits::canister_change::C
                     },
                 )
             }
+            CanisterChangeDetails::CanisterComputeAllocationChange(change) => {
+                pb_canister_state_bits::canister_change::ChangeDetails::CanisterComputeAllocationChange(
+                    pb_canister_state_bits::CanisterComputeAllocationChange {
+                        compute_allocation: change.compute_allocation,
+                    },
+                )
+            }
+            CanisterChangeDetails::CanisterMemoryAllocationChange(change) => {
+                pb_canister_state_bits::canister_change::ChangeDetails::CanisterMemoryAllocationChange(
+                    pb_canister_state_bits::CanisterMemoryAllocationChange {
+                        memory_allocation: change.memory_allocation,
+                    },
+                )
+            }
+            CanisterChangeDetails::CanisterFreezingThresholdChange(change) => {
+                pb_canister_state_bits::canister_change::ChangeDetails::CanisterFreezingThresholdChange(
+                    pb_canister_state_bits::CanisterFreezingThresholdChange {
+                        freezing_threshold: change.freezing_threshold,
+                    },
+                )
+            }
+            CanisterChangeDetails::CanisterReservedCyclesLimitChange(change) => {
+                pb_canister_state_bits::canister_change::ChangeDetails::CanisterReservedCyclesLimitChange(
+                    pb_canister_state_bits::CanisterReservedCyclesLimitChange {
+                        reserved_cycles_limit: Some(pb_canister_state_bits::Unsigned128 {
+                            raw: change.reserved_cycles_limit.to_le_bytes().to_vec(),
+                        }),
+                    },
+                )
+            }
         }
     }
 }
@@ -578,6 +902,39 @@ impl TryFrom<pb_canister_state_bits::canister_change::ChangeDetails> for Caniste
                     .map(TryInto::try_into)
                     .collect::<Result<Vec<PrincipalId>, _>>()?,
             )),
+            pb_canister_state_bits::canister_change::ChangeDetails::CanisterComputeAllocationChange(
+                change,
+            ) => Ok(CanisterChangeDetails::compute_allocation_change(
+                change.compute_allocation,
+            )),
+            pb_canister_state_bits::canister_change::ChangeDetails::CanisterMemoryAllocationChange(
+                change,
+            ) => Ok(CanisterChangeDetails::memory_allocation_change(
+                change.memory_allocation,
+            )),
+            pb_canister_state_bits::canister_change::ChangeDetails::CanisterFreezingThresholdChange(
+                change,
+            ) => Ok(CanisterChangeDetails::freezing_threshold_change(
+                change.freezing_threshold,
+            )),
+            pb_canister_state_bits::canister_change::ChangeDetails::CanisterReservedCyclesLimitChange(
+                change,
+            ) => {
+                let raw: [u8; 16] = change
+                    .reserved_cycles_limit
+                    .ok_or(ProxyDecodeError::MissingField(
+                        "CanisterReservedCyclesLimitChange::reserved_cycles_limit",
+                    ))?
+                    .raw
+                    .try_into()
+                    .map_err(|raw: Vec<u8>| ProxyDecodeError::ValueOutOfRange {
+                        typ: "CanisterReservedCyclesLimitChange::reserved_cycles_limit",
+                        err: format!("Expected 16 bytes, got {}", raw.len()),
+                    })?;
+                Ok(CanisterChangeDetails::reserved_cycles_limit_change(
+                    u128::from_le_bytes(raw),
+                ))
+            }
         }
     }
 }
@@ -645,6 +1002,8 @@ impl Payload<'_> for UninstallCodeArgs {}
 ///     memory_allocation: nat;
 ///     freezing_threshold: nat;
 ///     reserved_cycles_limit: nat;
+///     wasm_memory_limit: nat;
+///     wasm_memory_threshold: nat;
 /// })`
 #[derive(CandidType, Clone, Deserialize, Debug, Eq, PartialEq)]
 pub struct DefiniteCanisterSettingsArgs {
@@ -654,6 +1013,8 @@ pub struct DefiniteCanisterSettingsArgs {
     memory_allocation: candid::Nat,
     freezing_threshold: candid::Nat,
     reserved_cycles_limit: candid::Nat,
+    wasm_memory_limit: candid::Nat,
+    wasm_memory_threshold: candid::Nat,
 }
 
 impl DefiniteCanisterSettingsArgs {
@@ -664,9 +1025,13 @@ impl DefiniteCanisterSettingsArgs {
         memory_allocation: Option<u64>,
         freezing_threshold: u64,
         reserved_cycles_limit: Option<u128>,
+        wasm_memory_limit: Option<u64>,
+        wasm_memory_threshold: u64,
     ) -> Self {
         let memory_allocation = candid::Nat::from(memory_allocation.unwrap_or(0));
         let reserved_cycles_limit = candid::Nat::from(reserved_cycles_limit.unwrap_or(0));
+        // A limit of 0 means that the Wasm memory is not limited.
+        let wasm_memory_limit = candid::Nat::from(wasm_memory_limit.unwrap_or(0));
         Self {
             controller,
             controllers,
@@ -674,6 +1039,8 @@ impl DefiniteCanisterSettingsArgs {
             memory_allocation,
             freezing_threshold: candid::Nat::from(freezing_threshold),
             reserved_cycles_limit,
+            wasm_memory_limit,
+            wasm_memory_threshold: candid::Nat::from(wasm_memory_threshold),
         }
     }
 
@@ -684,6 +1051,14 @@ impl DefiniteCanisterSettingsArgs {
     pub fn reserved_cycles_limit(&self) -> candid::Nat {
         self.reserved_cycles_limit.clone()
     }
+
+    pub fn wasm_memory_limit(&self) -> candid::Nat {
+        self.wasm_memory_limit.clone()
+    }
+
+    pub fn wasm_memory_threshold(&self) -> candid::Nat {
+        self.wasm_memory_threshold.clone()
+    }
 }
 
 impl Payload<'_> for DefiniteCanisterSettingsArgs {}
@@ -799,6 +1174,8 @@ impl CanisterStatusResultV2 {
         memory_allocation: Option<u64>,
         freezing_threshold: u64,
         reserved_cycles_limit: Option<u128>,
+        wasm_memory_limit: Option<u64>,
+        wasm_memory_threshold: u64,
         idle_cycles_burned_per_day: u128,
         reserved_cycles: u128,
         query_num_calls: u128,
@@ -822,6 +1199,8 @@ impl CanisterStatusResultV2 {
                 memory_allocation,
                 freezing_threshold,
                 reserved_cycles_limit,
+                wasm_memory_limit,
+                wasm_memory_threshold,
             ),
             freezing_threshold: candid::Nat::from(freezing_threshold),
             idle_cycles_burned_per_day: candid::Nat::from(idle_cycles_burned_per_day),
@@ -1428,6 +1807,8 @@ fn verify_max_bounded_controllers_length() {
 ///     memory_allocation: opt nat;
 ///     freezing_threshold: opt nat;
 ///     reserved_cycles_limit: opt nat;
+///     wasm_memory_limit: opt nat;
+///     wasm_memory_threshold: opt nat;
 /// })`
 #[derive(Default, Clone, CandidType, Deserialize, Debug, PartialEq, Eq)]
 pub struct CanisterSettingsArgs {
@@ -1438,6 +1819,8 @@ pub struct CanisterSettingsArgs {
     pub memory_allocation: Option<candid::Nat>,
     pub freezing_threshold: Option<candid::Nat>,
     pub reserved_cycles_limit: Option<candid::Nat>,
+    pub wasm_memory_limit: Option<candid::Nat>,
+    pub wasm_memory_threshold: Option<candid::Nat>,
 }
 
 impl Payload<'_> for CanisterSettingsArgs {}
@@ -1449,6 +1832,8 @@ impl CanisterSettingsArgs {
         memory_allocation: Option<u64>,
         freezing_threshold: Option<u64>,
         reserved_cycles_limit: Option<u128>,
+        wasm_memory_limit: Option<u64>,
+        wasm_memory_threshold: Option<u64>,
     ) -> Self {
         Self {
             controller: None,
@@ -1457,6 +1842,8 @@ impl CanisterSettingsArgs {
             memory_allocation: memory_allocation.map(candid::Nat::from),
             freezing_threshold: freezing_threshold.map(candid::Nat::from),
             reserved_cycles_limit: reserved_cycles_limit.map(candid::Nat::from),
+            wasm_memory_limit: wasm_memory_limit.map(candid::Nat::from),
+            wasm_memory_threshold: wasm_memory_threshold.map(candid::Nat::from),
         }
     }
 
@@ -1473,6 +1860,8 @@ pub struct CanisterSettingsArgsBuilder {
     memory_allocation: Option<candid::Nat>,
     freezing_threshold: Option<candid::Nat>,
     reserved_cycles_limit: Option<candid::Nat>,
+    wasm_memory_limit: Option<candid::Nat>,
+    wasm_memory_threshold: Option<candid::Nat>,
 }
 
 #[allow(dead_code)]
@@ -1489,6 +1878,8 @@ impl CanisterSettingsArgsBuilder {
             memory_allocation: self.memory_allocation,
             freezing_threshold: self.freezing_threshold,
             reserved_cycles_limit: self.reserved_cycles_limit,
+            wasm_memory_limit: self.wasm_memory_limit,
+            wasm_memory_threshold: self.wasm_memory_threshold,
         }
     }
 
@@ -1556,6 +1947,24 @@ impl CanisterSettingsArgsBuilder {
             ..self
         }
     }
+
+    /// Sets the Wasm memory limit in bytes. A limit of 0 removes the limit.
+    pub fn with_wasm_memory_limit(self, wasm_memory_limit: u64) -> Self {
+        Self {
+            wasm_memory_limit: Some(candid::Nat::from(wasm_memory_limit)),
+            ..self
+        }
+    }
+
+    /// Sets the threshold of free Wasm memory in bytes below which the
+    /// `canister_on_low_wasm_memory` hook runs. A threshold of 0 disables
+    /// the hook.
+    pub fn with_wasm_memory_threshold(self, wasm_memory_threshold: u64) -> Self {
+        Self {
+            wasm_memory_threshold: Some(candid::Nat::from(wasm_memory_threshold)),
+            ..self
+        }
+    }
 }
 
 /// Struct used for encoding/decoding
@@ -2166,6 +2575,7 @@ impl Payload<'_> for BitcoinSendTransactionInternalArgs {}
 pub enum QueryMethod {
     BitcoinGetUtxosQuery,
     BitcoinGetBalanceQuery,
+    CanisterHistory,
 }
 
 /// `CandidType` for `NodeMetricsHistoryArgs`
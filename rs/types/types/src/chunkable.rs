@@ -69,7 +69,10 @@ pub trait Chunkable {
 impl From<ArtifactChunk> for p2p_pb::StateSyncChunkResponse {
     fn from(chunk: ArtifactChunk) -> Self {
         match chunk.artifact_chunk_data {
-            ArtifactChunkData::SemiStructuredChunkData(chunk_data) => Self { data: chunk_data },
+            ArtifactChunkData::SemiStructuredChunkData(chunk_data) => Self {
+                data: chunk_data,
+                compression: p2p_pb::ChunkCompression::None as i32,
+            },
         }
     }
 }
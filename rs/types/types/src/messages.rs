@@ -413,11 +413,13 @@ impl TryFrom<CanisterMessage> for CanisterCall {
 }
 
 /// A canister task can be thought of as a special system message that the IC
-/// sends to the canister to execute its heartbeat or the global timer method.
+/// sends to the canister to execute its heartbeat, global timer or low Wasm
+/// memory method.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum CanisterTask {
     Heartbeat,
     GlobalTimer,
+    OnLowWasmMemory,
 }
 
 impl From<CanisterTask> for SystemMethod {
@@ -425,6 +427,7 @@ impl From<CanisterTask> for SystemMethod {
         match task {
             CanisterTask::Heartbeat => SystemMethod::CanisterHeartbeat,
             CanisterTask::GlobalTimer => SystemMethod::CanisterGlobalTimer,
+            CanisterTask::OnLowWasmMemory => SystemMethod::CanisterOnLowWasmMemory,
         }
     }
 }
@@ -434,6 +437,7 @@ impl Display for CanisterTask {
         match self {
             Self::Heartbeat => write!(f, "Heartbeat task"),
             Self::GlobalTimer => write!(f, "Global timer task"),
+            Self::OnLowWasmMemory => write!(f, "On low Wasm memory task"),
         }
     }
 }
@@ -107,6 +107,9 @@ impl From<&WasmMethod> for pb::WasmMethod {
                     SystemMethod::CanisterHeartbeat => PbSystemMethod::CanisterHeartbeat,
                     SystemMethod::Empty => PbSystemMethod::Empty,
                     SystemMethod::CanisterGlobalTimer => PbSystemMethod::CanisterGlobalTimer,
+                    SystemMethod::CanisterOnLowWasmMemory => {
+                        PbSystemMethod::CanisterOnLowWasmMemory
+                    }
                 } as i32)),
             },
         }
@@ -142,6 +145,9 @@ impl TryFrom<pb::WasmMethod> for WasmMethod {
                     PbSystemMethod::CanisterHeartbeat => SystemMethod::CanisterHeartbeat,
                     PbSystemMethod::Empty => SystemMethod::Empty,
                     PbSystemMethod::CanisterGlobalTimer => SystemMethod::CanisterGlobalTimer,
+                    PbSystemMethod::CanisterOnLowWasmMemory => {
+                        SystemMethod::CanisterOnLowWasmMemory
+                    }
                 }))
             }
         }
@@ -166,6 +172,9 @@ pub enum SystemMethod {
     CanisterHeartbeat,
     /// A system method that is run after a specified time.
     CanisterGlobalTimer,
+    /// A system method that is run when the free Wasm memory of the canister
+    /// drops below its `wasm_memory_threshold`.
+    CanisterOnLowWasmMemory,
     /// This is introduced as temporary scaffolding to aid in construction of
     /// the initial ExecutionState. This isn't used to execute any actual wasm
     /// but as a way to get to the wasm embedder from execution. Eventually, we
@@ -186,6 +195,7 @@ impl TryFrom<&str> for SystemMethod {
             "canister_inspect_message" => Ok(SystemMethod::CanisterInspectMessage),
             "canister_heartbeat" => Ok(SystemMethod::CanisterHeartbeat),
             "canister_global_timer" => Ok(SystemMethod::CanisterGlobalTimer),
+            "canister_on_low_wasm_memory" => Ok(SystemMethod::CanisterOnLowWasmMemory),
             "empty" => Ok(SystemMethod::Empty),
             _ => Err(format!("Cannot convert {} to SystemMethod.", value)),
         }
@@ -203,6 +213,7 @@ impl fmt::Display for SystemMethod {
             Self::CanisterHeartbeat => write!(f, "canister_heartbeat"),
             Self::Empty => write!(f, "empty"),
             Self::CanisterGlobalTimer => write!(f, "canister_global_timer"),
+            Self::CanisterOnLowWasmMemory => write!(f, "canister_on_low_wasm_memory"),
         }
     }
 }
@@ -67,7 +67,8 @@ fn config_node_not_found() {
 
         assert_eq!(
             XNetEndpointConfig {
-                address: SocketAddr::from(([127, 0, 0, 1], 0))
+                address: SocketAddr::from(([127, 0, 0, 1], 0)),
+                public_endpoint: None,
             },
             XNetEndpointConfig::from(registry, node_test_id(NODE_ID), &log)
         );
@@ -103,7 +104,8 @@ fn config_node_record_xnet_is_none() {
 
         assert_eq!(
             XNetEndpointConfig {
-                address: SocketAddr::from(([127, 0, 0, 1], 0))
+                address: SocketAddr::from(([127, 0, 0, 1], 0)),
+                public_endpoint: None,
             },
             XNetEndpointConfig::from(registry, node_test_id(NODE_ID), &log)
         );
@@ -127,7 +129,8 @@ fn config_ipv4_success() {
 
         assert_eq!(
             XNetEndpointConfig {
-                address: SocketAddr::from(([192, 168, 0, 4], 2197))
+                address: SocketAddr::from(([192, 168, 0, 4], 2197)),
+                public_endpoint: None,
             },
             XNetEndpointConfig::from(registry, node_test_id(NODE_ID), &log)
         );
@@ -151,7 +154,8 @@ fn config_ipv6_success() {
 
         assert_eq!(
             XNetEndpointConfig {
-                address: SocketAddr::from(([0xfde4, 0x8dba, 0x82e1, 0, 0, 0, 0, 0xc4], 2197))
+                address: SocketAddr::from(([0xfde4, 0x8dba, 0x82e1, 0, 0, 0, 0, 0xc4], 2197)),
+                public_endpoint: None,
             },
             XNetEndpointConfig::from(registry, node_test_id(NODE_ID), &log)
         );
@@ -1,9 +1,12 @@
 #[cfg(test)]
 mod config_tests;
+mod rate_limiter;
 #[cfg(test)]
 mod tests;
 
-use hyper::{Body, Request, Response, StatusCode};
+use crate::rate_limiter::RateLimiter;
+
+use hyper::{server::conn::AddrStream, Body, Method, Request, Response, StatusCode};
 use ic_crypto_tls_interfaces::TlsHandshake;
 use ic_interfaces_certified_stream_store::{CertifiedStreamStore, EncodeStreamError};
 use ic_interfaces_registry::RegistryClient;
@@ -22,7 +25,7 @@ use std::sync::Arc;
 use threadpool::ThreadPool;
 use tokio::{
     runtime,
-    sync::{oneshot, Notify},
+    sync::{oneshot, Notify, Semaphore},
 };
 use url::Url;
 
@@ -47,6 +50,17 @@ const RESOURCE_UNKNOWN: &str = "unknown";
 
 const XNET_ENDPOINT_NUM_WORKER_THREADS: usize = 4;
 
+/// The number of requests from public clients that may be queued or handled by
+/// the background workers at any time, leaving the rest of the workers' queue to
+/// other replicas.
+const PUBLIC_MAX_REQUESTS_IN_FLIGHT: usize = XNET_ENDPOINT_NUM_WORKER_THREADS / 2;
+
+/// The maximum number of messages in a slice returned to a public client.
+const PUBLIC_MAX_MSG_LIMIT: usize = 1_000;
+
+/// The maximum payload size, in bytes, of a slice returned to a public client.
+const PUBLIC_MAX_BYTE_LIMIT: usize = 1 << 20;
+
 impl XNetEndpointMetrics {
     pub fn new(metrics_registry: &MetricsRegistry) -> Self {
         Self {
@@ -74,6 +88,15 @@ impl XNetEndpointMetrics {
     }
 }
 
+/// Where a request comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Caller {
+    /// Another replica, over TLS.
+    Replica,
+    /// An external verifier, over the public endpoint.
+    Public,
+}
+
 /// The messages processed by the background worker.
 #[allow(clippy::large_enum_variant)]
 enum WorkerMessage {
@@ -81,6 +104,8 @@ enum WorkerMessage {
     HandleRequest {
         /// Incoming XNet HTTP request.
         request: Request<Body>,
+        /// Where the request comes from.
+        caller: Caller,
         /// The channel that should be used to handle the reply to the user.
         response_sender: oneshot::Sender<Response<Body>>,
     },
@@ -95,6 +120,12 @@ enum WorkerMessage {
 /// incoming requests. The two are connected via a bounded `crossbeam::channel`,
 /// also used for signaling shutdown on drop.
 ///
+/// If configured, a second, public HTTP server serves the same read-only APIs
+/// to external verifiers (e.g. auditors or bridges), rate limited across all
+/// clients. The certified slices it returns include the certification and
+/// witness required to verify them against the subnet's public key, and are
+/// capped at `PUBLIC_MAX_MSG_LIMIT` messages and `PUBLIC_MAX_BYTE_LIMIT` bytes.
+///
 /// Exposed APIs:
 /// * `/api/v1/streams`
 ///   - Produces a list of all `SubnetIds` with available streams.
@@ -105,6 +136,7 @@ enum WorkerMessage {
 ///     (`msg_begin` if missing), of up to `byte_limit` bytes.
 pub struct XNetEndpoint {
     server_address: SocketAddr,
+    public_server: Option<PublicServer>,
     handler_thread_pool: threadpool::ThreadPool,
     shutdown_notify: Arc<Notify>,
     request_sender: crossbeam_channel::Sender<WorkerMessage>,
@@ -117,8 +149,11 @@ impl Drop for XNetEndpoint {
     fn drop(&mut self) {
         info!(self.log, "Shutting down XNet endpoint");
 
-        // Request graceful shutdown of the HTTP server and the background thread.
+        // Request graceful shutdown of the HTTP servers and the background thread.
         self.shutdown_notify.notify_one();
+        if let Some(public_server) = &self.public_server {
+            public_server.shutdown_notify.notify_one();
+        }
 
         for _ in 0..XNET_ENDPOINT_NUM_WORKER_THREADS {
             self.request_sender
@@ -202,33 +237,13 @@ impl XNetEndpoint {
                             let ctx = ctx.clone();
 
                             async move {
-                                let _ = &ctx;
-                                let (response_sender, response_receiver) = oneshot::channel();
-                                let task = WorkerMessage::HandleRequest {
+                                ok(dispatch_request(
                                     request,
-                                    response_sender,
-                                };
-
-                                // NOTE: we must use non-blocking send here, otherwise we might
-                                // delay the event thread.
-                                if ctx.request_sender.try_send(task).is_err() {
-                                    ctx.metrics
-                                        .request_duration
-                                        .with_label_values(&[
-                                            RESOURCE_UNKNOWN,
-                                            StatusCode::SERVICE_UNAVAILABLE.as_str(),
-                                        ])
-                                        .observe(0.0);
-
-                                    return ok(Response::builder()
-                                        .status(StatusCode::SERVICE_UNAVAILABLE)
-                                        .body(Body::from("Queue full"))
-                                        .unwrap());
-                                }
-
-                                ok(response_receiver.await.unwrap_or_else(|e| {
-                                    panic!("XNet Endpoint Handler shut down unexpectedly: {}", e)
-                                }))
+                                    Caller::Replica,
+                                    &ctx.request_sender,
+                                    &ctx.metrics,
+                                )
+                                .await)
                             }
                         }
                     }))
@@ -278,6 +293,16 @@ impl XNetEndpoint {
             }
         });
 
+        let public_server = config.public_endpoint.map(|public_config| {
+            PublicServer::start(
+                &runtime_handle,
+                public_config,
+                request_sender.clone(),
+                Arc::clone(&metrics),
+                log.clone(),
+            )
+        });
+
         // Spawn a request handler. We pass the certified stream store, which is
         // currently realized by the state manager.
         let handler_log = log.clone();
@@ -295,11 +320,13 @@ impl XNetEndpoint {
             handler_thread_pool.execute(move || {
                 while let Ok(WorkerMessage::HandleRequest {
                     request,
+                    caller,
                     response_sender,
                 }) = request_receiver.recv()
                 {
                     let response = handle_http_request(
                         request,
+                        caller,
                         certified_stream_store.as_ref(),
                         &base_url,
                         &metrics,
@@ -319,6 +346,7 @@ impl XNetEndpoint {
 
         Self {
             server_address: address,
+            public_server,
             shutdown_notify,
             handler_thread_pool,
             request_sender,
@@ -335,12 +363,161 @@ impl XNetEndpoint {
     pub fn server_port(&self) -> u16 {
         self.server_address.port()
     }
+
+    /// Returns the port that the public HTTP server is listening on, if the
+    /// public endpoint is enabled.
+    pub fn public_server_port(&self) -> Option<u16> {
+        self.public_server
+            .as_ref()
+            .map(|public_server| public_server.address.port())
+    }
+}
+
+/// Plain HTTP server serving certified stream slices to external verifiers.
+///
+/// Requests are handled by the same background workers as those from other
+/// replicas, but are rate limited beforehand and at most
+/// `PUBLIC_MAX_REQUESTS_IN_FLIGHT` of them are handed over to the workers at a
+/// time, so that public clients cannot crowd out XNet traffic.
+struct PublicServer {
+    address: SocketAddr,
+    shutdown_notify: Arc<Notify>,
+}
+
+impl PublicServer {
+    fn start(
+        runtime_handle: &runtime::Handle,
+        config: XNetPublicEndpointConfig,
+        request_sender: crossbeam_channel::Sender<WorkerMessage>,
+        metrics: Arc<XNetEndpointMetrics>,
+        log: ReplicaLogger,
+    ) -> Self {
+        use hyper::service::{make_service_fn, service_fn};
+
+        let rate_limiter = Arc::new(RateLimiter::new(config.max_requests_per_second));
+        let in_flight = Arc::new(Semaphore::new(PUBLIC_MAX_REQUESTS_IN_FLIGHT));
+
+        let make_service = make_service_fn(move |_conn: &AddrStream| {
+            let request_sender = request_sender.clone();
+            let metrics = Arc::clone(&metrics);
+            let rate_limiter = Arc::clone(&rate_limiter);
+            let in_flight = Arc::clone(&in_flight);
+
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let request_sender = request_sender.clone();
+                    let metrics = Arc::clone(&metrics);
+                    let rate_limiter = Arc::clone(&rate_limiter);
+                    let in_flight = Arc::clone(&in_flight);
+
+                    async move {
+                        let permit = if request.method() != Method::GET {
+                            Err(StatusCode::METHOD_NOT_ALLOWED)
+                        } else if !rate_limiter.try_acquire() {
+                            Err(StatusCode::TOO_MANY_REQUESTS)
+                        } else {
+                            in_flight
+                                .try_acquire_owned()
+                                .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)
+                        };
+                        let _permit = match permit {
+                            Ok(permit) => permit,
+                            Err(status) => {
+                                metrics
+                                    .request_duration
+                                    .with_label_values(&[RESOURCE_UNKNOWN, status.as_str()])
+                                    .observe(0.0);
+                                return Ok::<_, Infallible>(
+                                    Response::builder()
+                                        .status(status)
+                                        .body(Body::empty())
+                                        .unwrap(),
+                                );
+                            }
+                        };
+
+                        Ok(
+                            dispatch_request(request, Caller::Public, &request_sender, &metrics)
+                                .await,
+                        )
+                    }
+                }))
+            }
+        });
+
+        let server = {
+            let _guard = runtime_handle.enter();
+            hyper::Server::try_bind(&config.address)
+                .unwrap_or_else(|e| {
+                    panic!(
+                        "failed to bind public XNet socket, address {:?}: {}",
+                        config.address, e
+                    )
+                })
+                .serve(make_service)
+        };
+        let address = server.local_addr();
+
+        info!(log, "Public XNet Endpoint listening on {}", address);
+
+        let shutdown_notify = Arc::new(Notify::new());
+        let shutdown = server.with_graceful_shutdown({
+            let shutdown_notify = Arc::clone(&shutdown_notify);
+            async move { shutdown_notify.notified().await }
+        });
+        runtime_handle.spawn(async move {
+            if let Err(e) = shutdown.await {
+                warn!(log, "Public XNet http server failed: {}", e);
+            }
+        });
+
+        Self {
+            address,
+            shutdown_notify,
+        }
+    }
+}
+
+/// Hands over a request to the background workers and waits for the response.
+///
+/// Responds with 503 Service Unavailable if the workers' queue is full.
+async fn dispatch_request(
+    request: Request<Body>,
+    caller: Caller,
+    request_sender: &crossbeam_channel::Sender<WorkerMessage>,
+    metrics: &XNetEndpointMetrics,
+) -> Response<Body> {
+    let (response_sender, response_receiver) = oneshot::channel();
+    let task = WorkerMessage::HandleRequest {
+        request,
+        caller,
+        response_sender,
+    };
+
+    // NOTE: we must use non-blocking send here, otherwise we might
+    // delay the event thread.
+    if request_sender.try_send(task).is_err() {
+        metrics
+            .request_duration
+            .with_label_values(&[RESOURCE_UNKNOWN, StatusCode::SERVICE_UNAVAILABLE.as_str()])
+            .observe(0.0);
+
+        return Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .body(Body::from("Queue full"))
+            .unwrap();
+    }
+
+    response_receiver
+        .await
+        .unwrap_or_else(|e| panic!("XNet Endpoint Handler shut down unexpectedly: {}", e))
 }
 
 /// Handles an incoming HTTP request by parsing the URL, handing over to
 /// `route_request()` and replying with the produced response.
 fn handle_http_request(
     request: Request<Body>,
+    caller: Caller,
     certified_stream_store: &dyn CertifiedStreamStore,
     base_url: &Url,
     metrics: &XNetEndpointMetrics,
@@ -353,7 +530,7 @@ fn handle_http_request(
             .map(|pq| pq.as_str())
             .unwrap_or(""),
     ) {
-        Ok(url) => route_request(url, certified_stream_store, metrics),
+        Ok(url) => route_request(url, caller, certified_stream_store, metrics),
         Err(e) => {
             let msg = format!("Invalid URL {}: {}", request.uri(), e);
             warn!(log, "{}", msg);
@@ -366,6 +543,7 @@ fn handle_http_request(
 /// HTTP 404 Not Found response if the URL doesn't match any handler.
 fn route_request(
     url: Url,
+    caller: Caller,
     certified_stream_store: &dyn CertifiedStreamStore,
     metrics: &XNetEndpointMetrics,
 ) -> Response<Body> {
@@ -412,6 +590,10 @@ fn route_request(
                     }
                 }
             }
+            if caller == Caller::Public {
+                msg_limit = Some(cap_limit(msg_limit, PUBLIC_MAX_MSG_LIMIT));
+                byte_limit = Some(cap_limit(byte_limit, PUBLIC_MAX_BYTE_LIMIT));
+            }
 
             handle_stream(
                 subnet_id,
@@ -434,6 +616,11 @@ fn route_request(
     response
 }
 
+/// Returns `limit`, capped at `max`; or `max` if there is no limit.
+fn cap_limit(limit: Option<usize>, max: usize) -> usize {
+    limit.map_or(max, |limit| limit.min(max))
+}
+
 /// Returns a list of all subnets with available streams.
 fn handle_streams(
     certified_stream_store: &dyn CertifiedStreamStore,
@@ -567,6 +754,18 @@ fn range_not_satisfiable<T: Into<Body>>(msg: T) -> Response<Body> {
 #[derive(Debug, PartialEq, Eq)]
 pub struct XNetEndpointConfig {
     address: SocketAddr,
+    public_endpoint: Option<XNetPublicEndpointConfig>,
+}
+
+/// Configuration of the public, read-only mode of `XNetEndpoint`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XNetPublicEndpointConfig {
+    /// The socket address to serve certified stream slices on, over plain
+    /// HTTP.
+    pub address: SocketAddr,
+    /// The number of requests served per second across all clients. Excess
+    /// requests are rejected with 429 Too Many Requests.
+    pub max_requests_per_second: u32,
 }
 
 impl XNetEndpointConfig {
@@ -593,6 +792,13 @@ impl XNetEndpointConfig {
             })
     }
 
+    /// Additionally serves certified stream slices to external verifiers
+    /// according to the given configuration.
+    pub fn with_public_endpoint(mut self, public_endpoint: XNetPublicEndpointConfig) -> Self {
+        self.public_endpoint = Some(public_endpoint);
+        self
+    }
+
     fn try_from(registry: Arc<dyn RegistryClient>, node_id: NodeId) -> Option<XNetEndpointConfig> {
         let version = registry.get_latest_version();
         let node_record = registry
@@ -616,7 +822,10 @@ impl XNetEndpointConfig {
             u16::try_from(endpoint.port).unwrap(),
         );
 
-        Some(XNetEndpointConfig { address })
+        Some(XNetEndpointConfig {
+            address,
+            public_endpoint: None,
+        })
     }
 }
 
@@ -625,6 +834,7 @@ impl Default for XNetEndpointConfig {
     fn default() -> XNetEndpointConfig {
         XNetEndpointConfig {
             address: SocketAddr::from(([127, 0, 0, 1], 0)),
+            public_endpoint: None,
         }
     }
 }
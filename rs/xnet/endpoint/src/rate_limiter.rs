@@ -0,0 +1,100 @@
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Token bucket limiting the rate of requests served by the public endpoint.
+///
+/// The bucket holds up to `requests_per_second` tokens and is refilled at a
+/// rate of `requests_per_second` tokens per second, i.e. it allows bursts of
+/// up to one second's worth of requests.
+pub(crate) struct RateLimiter {
+    requests_per_second: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(requests_per_second: u32) -> Self {
+        Self::new_at(requests_per_second, Instant::now())
+    }
+
+    fn new_at(requests_per_second: u32, now: Instant) -> Self {
+        let requests_per_second = requests_per_second as f64;
+        Self {
+            requests_per_second,
+            bucket: Mutex::new(Bucket {
+                tokens: requests_per_second,
+                last_refill: now,
+            }),
+        }
+    }
+
+    /// Takes a token from the bucket. Returns `false` if the bucket is empty,
+    /// i.e. the request should be rejected.
+    pub(crate) fn try_acquire(&self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> bool {
+        let mut bucket = self.bucket.lock().unwrap();
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.requests_per_second)
+            .min(self.requests_per_second);
+        bucket.last_refill = now;
+
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn allows_burst_up_to_rate() {
+        let now = Instant::now();
+        let rate_limiter = RateLimiter::new_at(3, now);
+
+        assert!(rate_limiter.try_acquire_at(now));
+        assert!(rate_limiter.try_acquire_at(now));
+        assert!(rate_limiter.try_acquire_at(now));
+        assert!(!rate_limiter.try_acquire_at(now));
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let now = Instant::now();
+        let rate_limiter = RateLimiter::new_at(2, now);
+        assert!(rate_limiter.try_acquire_at(now));
+        assert!(rate_limiter.try_acquire_at(now));
+        assert!(!rate_limiter.try_acquire_at(now));
+
+        // Half a second refills one token.
+        let later = now + Duration::from_millis(500);
+        assert!(rate_limiter.try_acquire_at(later));
+        assert!(!rate_limiter.try_acquire_at(later));
+
+        // The bucket never holds more than one second's worth of tokens.
+        let much_later = later + Duration::from_secs(60);
+        assert!(rate_limiter.try_acquire_at(much_later));
+        assert!(rate_limiter.try_acquire_at(much_later));
+        assert!(!rate_limiter.try_acquire_at(much_later));
+    }
+
+    #[test]
+    fn zero_rate_rejects_all_requests() {
+        let now = Instant::now();
+        let rate_limiter = RateLimiter::new_at(0, now);
+
+        assert!(!rate_limiter.try_acquire_at(now));
+        assert!(!rate_limiter.try_acquire_at(now + Duration::from_secs(1)));
+    }
+}
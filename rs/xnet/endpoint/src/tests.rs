@@ -221,6 +221,53 @@ fn query_stream_parallel() {
     });
 }
 
+/// Tests that the public endpoint serves the `/api/v1/streams` API over plain
+/// HTTP and rejects requests beyond its rate limit.
+///
+/// Heavyweight test that starts an `XNetEndpoint` and queries it over HTTP.
+#[test]
+fn query_streams_public_rate_limited() {
+    with_test_replica_logger(|log| {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let fixture = EndpointTestFixture::with_replicated_state();
+
+        let config = XNetEndpointConfig::default().with_public_endpoint(XNetPublicEndpointConfig {
+            address: SocketAddr::from(([127, 0, 0, 1], 0)),
+            max_requests_per_second: 1,
+        });
+        let xnet_endpoint = XNetEndpoint::new(
+            rt.handle().clone(),
+            fixture.state_manager.clone(),
+            fixture.tls_handshake.clone(),
+            fixture.registry_client.clone(),
+            config,
+            &fixture.metrics,
+            log,
+        );
+
+        let url = format!(
+            "http://localhost:{}/api/v1/streams",
+            xnet_endpoint.public_server_port().unwrap()
+        );
+        let (first, second) = rt.block_on(async move {
+            let first = reqwest::get(&url).await.unwrap();
+            let first = (first.status().as_u16(), first.bytes().await.unwrap());
+            let second = reqwest::get(&url).await.unwrap().status().as_u16();
+            (first, second)
+        });
+
+        assert_eq!((200, format!("[\"{}\"]", DST_SUBNET).into()), first);
+        assert_eq!(429, second);
+        assert_eq!(
+            metric_vec(&[
+                (&[("resource", "streams"), ("status", "200")], 1),
+                (&[("resource", "unknown"), ("status", "429")], 1)
+            ]),
+            fixture.request_counts()
+        );
+    });
+}
+
 #[tokio::test]
 async fn handle_streams() {
     let fixture = EndpointTestFixture::with_replicated_state();
@@ -229,6 +276,7 @@ async fn handle_streams() {
 
     let response = route_request(
         url,
+        Caller::Replica,
         &*fixture.state_manager,
         &XNetEndpointMetrics::new(&fixture.metrics),
     );
@@ -265,6 +313,7 @@ async fn handle_existing_stream_impl(
 
     let response = route_request(
         url,
+        Caller::Replica,
         &*fixture.state_manager,
         &XNetEndpointMetrics::new(&fixture.metrics),
     );
@@ -416,6 +465,7 @@ async fn handle_stream_with_witness_begin() {
 
     let response = route_request(
         url,
+        Caller::Replica,
         &*fixture.state_manager,
         &XNetEndpointMetrics::new(&fixture.metrics),
     );
@@ -446,6 +496,7 @@ async fn handle_stream_no_index() {
 
     let response = route_request(
         url,
+        Caller::Replica,
         &*fixture.state_manager,
         &XNetEndpointMetrics::new(&fixture.metrics),
     );
@@ -483,6 +534,7 @@ async fn handle_stream_with_byte_limit() {
 
     let response = route_request(
         url,
+        Caller::Replica,
         &*fixture.state_manager,
         &XNetEndpointMetrics::new(&fixture.metrics),
     );
@@ -507,6 +559,16 @@ async fn handle_stream_with_byte_limit() {
     );
 }
 
+#[test]
+fn public_slice_limits_are_capped() {
+    assert_eq!(PUBLIC_MAX_MSG_LIMIT, cap_limit(None, PUBLIC_MAX_MSG_LIMIT));
+    assert_eq!(10, cap_limit(Some(10), PUBLIC_MAX_MSG_LIMIT));
+    assert_eq!(
+        PUBLIC_MAX_BYTE_LIMIT,
+        cap_limit(Some(usize::MAX), PUBLIC_MAX_BYTE_LIMIT)
+    );
+}
+
 #[tokio::test]
 async fn handle_stream_nonexistent() {
     let fixture = EndpointTestFixture::with_replicated_state();
@@ -519,6 +581,7 @@ async fn handle_stream_nonexistent() {
 
     let response = route_request(
         url,
+        Caller::Replica,
         &*fixture.state_manager,
         &XNetEndpointMetrics::new(&fixture.metrics),
     );
@@ -540,6 +603,7 @@ async fn handle_bad_api_path() {
 
     let response = route_request(
         url,
+        Caller::Replica,
         &*fixture.state_manager,
         &XNetEndpointMetrics::new(&fixture.metrics),
     );
@@ -0,0 +1,231 @@
+//! Verification of certified stream slices outside of the replica.
+//!
+//! A `CertifiedStreamSlice`, as served by the XNet endpoint, consists of a
+//! payload (the part of the source subnet's state tree holding the stream
+//! header and messages, in canonical form); a witness; and the certification
+//! of the source subnet's state at some height. This crate recomputes the root
+//! hash of the source subnet's state from the payload and witness; checks that
+//! it matches the certified hash and that the certification is signed by the
+//! source subnet's threshold key; and finally decodes the stream slice.
+//!
+//! It does not depend on any replica component, so it can be used by external
+//! verifiers (e.g. auditors or bridges) to check stream slices fetched from the
+//! XNet endpoint's public server.
+
+use ic_canonical_state::encoding::{decode_message, decode_stream_header};
+use ic_crypto_tree_hash::{recompute_digest, LabeledTree};
+use ic_crypto_utils_threshold_sig::verify_combined;
+use ic_interfaces_registry::RegistryClient;
+use ic_protobuf::messaging::xnet::v1;
+use ic_protobuf::proxy::ProtoProxy;
+use ic_registry_client_helpers::crypto::CryptoRegistry;
+use ic_types::{
+    crypto::threshold_sig::ThresholdSigPublicKey,
+    xnet::{CertifiedStreamSlice, StreamHeader, StreamIndex, StreamIndexedQueue, StreamSlice},
+    Height, RegistryVersion, SubnetId,
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[cfg(test)]
+mod tests;
+
+/// Reasons why a certified stream slice may fail verification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerificationError {
+    /// The payload, witness, stream header or a message could not be decoded.
+    DecodeError(String),
+    /// The root hash recomputed from the payload and witness does not match
+    /// the certified hash.
+    HashMismatch,
+    /// The certification is not signed by the given public key.
+    InvalidSignature(String),
+    /// The registry holds no threshold public key for the source subnet.
+    PublicKeyNotFound {
+        subnet_id: SubnetId,
+        registry_version: RegistryVersion,
+    },
+    /// The source subnet's public key could not be read from the registry.
+    RegistryError(String),
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DecodeError(err) => write!(f, "Failed to decode stream slice: {}", err),
+            Self::HashMismatch => {
+                write!(f, "Recomputed root hash does not match the certified hash")
+            }
+            Self::InvalidSignature(err) => {
+                write!(f, "Invalid certification signature: {}", err)
+            }
+            Self::PublicKeyNotFound {
+                subnet_id,
+                registry_version,
+            } => write!(
+                f,
+                "No threshold public key for subnet {} at registry version {}",
+                subnet_id, registry_version
+            ),
+            Self::RegistryError(err) => write!(f, "Failed to read from the registry: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+/// A stream slice whose certification has been verified.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifiedStreamSlice {
+    /// The subnet that the stream is directed to.
+    pub destination_subnet: SubnetId,
+    /// The height of the source subnet's certified state.
+    pub certified_height: Height,
+    pub slice: StreamSlice,
+}
+
+/// Decodes a `CertifiedStreamSlice` from the protobuf encoding served by the
+/// XNet endpoint.
+pub fn decode_certified_stream_slice(
+    bytes: &[u8],
+) -> Result<CertifiedStreamSlice, VerificationError> {
+    v1::CertifiedStreamSlice::proxy_decode(bytes).map_err(|err| {
+        VerificationError::DecodeError(format!("failed to decode certified slice: {}", err))
+    })
+}
+
+/// Verifies that `certified_slice` is certified by the subnet holding the
+/// threshold key `public_key` and decodes it.
+pub fn verify_certified_stream_slice(
+    certified_slice: &CertifiedStreamSlice,
+    public_key: &ThresholdSigPublicKey,
+) -> Result<VerifiedStreamSlice, VerificationError> {
+    let tree = v1::LabeledTree::proxy_decode(&certified_slice.payload).map_err(|err| {
+        VerificationError::DecodeError(format!("failed to decode payload: {}", err))
+    })?;
+    let witness = v1::Witness::proxy_decode(&certified_slice.merkle_proof).map_err(|err| {
+        VerificationError::DecodeError(format!("failed to decode witness: {}", err))
+    })?;
+    let digest = recompute_digest(&tree, &witness).map_err(|err| {
+        VerificationError::DecodeError(format!("failed to recompute digest: {:?}", err))
+    })?;
+
+    let certification = &certified_slice.certification;
+    if digest.as_bytes() != certification.signed.content.hash.get_ref().0 {
+        return Err(VerificationError::HashMismatch);
+    }
+    verify_combined(
+        &certification.signed.content,
+        &certification.signed.signature.signature,
+        public_key,
+    )
+    .map_err(|err| VerificationError::InvalidSignature(err.to_string()))?;
+
+    let (destination_subnet, slice) = decode_slice_from_tree(&tree)?;
+    Ok(VerifiedStreamSlice {
+        destination_subnet,
+        certified_height: certification.height,
+        slice,
+    })
+}
+
+/// Verifies `certified_slice` against the threshold public key of
+/// `source_subnet` recorded in the registry at `registry_version`.
+pub fn verify_certified_stream_slice_with_registry(
+    certified_slice: &CertifiedStreamSlice,
+    source_subnet: SubnetId,
+    registry: &dyn RegistryClient,
+    registry_version: RegistryVersion,
+) -> Result<VerifiedStreamSlice, VerificationError> {
+    let public_key = registry
+        .get_threshold_signing_public_key_for_subnet(source_subnet, registry_version)
+        .map_err(|err| VerificationError::RegistryError(err.to_string()))?
+        .ok_or(VerificationError::PublicKeyNotFound {
+            subnet_id: source_subnet,
+            registry_version,
+        })?;
+    verify_certified_stream_slice(certified_slice, &public_key)
+}
+
+// Note: the structs below mirror the ones used by the state manager to decode
+// the canonical tree into a form that is convenient to work with in Rust.
+
+/// Mirrors the XNet streams encoded in canonical form, starting from the root
+/// of the tree.
+///
+/// Fails on unknown fields, so that a slice cannot be padded with arbitrary
+/// certified data.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EncodedStreams<'a> {
+    #[serde(borrow)]
+    streams: BTreeMap<SubnetId, EncodedStream<'a>>,
+}
+
+/// Mirrors a single XNet stream slice encoded in canonical form.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EncodedStream<'a> {
+    #[serde(borrow)]
+    header: &'a serde_bytes::Bytes,
+
+    #[serde(borrow)]
+    #[serde(default)]
+    messages: BTreeMap<StreamIndex, &'a serde_bytes::Bytes>,
+}
+
+/// Decodes the destination subnet and stream slice from a canonical tree.
+fn decode_slice_from_tree(
+    tree: &LabeledTree<Vec<u8>>,
+) -> Result<(SubnetId, StreamSlice), VerificationError> {
+    let streams =
+        EncodedStreams::deserialize(tree_deserializer::LabeledTreeDeserializer::new(tree))
+            .map_err(|err| {
+                VerificationError::DecodeError(format!("failed to deserialize streams: {}", err))
+            })?;
+    if streams.streams.len() != 1 {
+        return Err(VerificationError::DecodeError(format!(
+            "expected a single stream, got {}",
+            streams.streams.len()
+        )));
+    }
+    let (subnet, encoded_stream) = streams.streams.into_iter().next().unwrap();
+
+    let header: StreamHeader = decode_stream_header(encoded_stream.header).map_err(|err| {
+        VerificationError::DecodeError(format!("failed to decode stream header: {}", err))
+    })?;
+
+    let mut messages = encoded_stream
+        .messages
+        .keys()
+        .next()
+        .map(|idx| StreamIndexedQueue::with_begin(*idx));
+    if let Some(ref mut queue) = messages {
+        for (idx, bytes) in encoded_stream.messages.into_iter() {
+            let msg = decode_message(bytes).map_err(|err| {
+                VerificationError::DecodeError(format!("failed to decode message {}: {}", idx, err))
+            })?;
+            if idx != queue.end() {
+                return Err(VerificationError::DecodeError(format!(
+                    "non-consecutive message indices: {} follows {}",
+                    idx,
+                    queue.end()
+                )));
+            }
+            queue.push(msg);
+        }
+
+        if queue.begin() < header.begin || header.end < queue.end() {
+            return Err(VerificationError::DecodeError(format!(
+                "message indices [{}, {}) outside of the header range [{}, {})",
+                queue.begin(),
+                queue.end(),
+                header.begin,
+                header.end
+            )));
+        }
+    }
+
+    Ok((subnet, StreamSlice::from_parts(header, messages)))
+}
@@ -0,0 +1,162 @@
+use super::*;
+use assert_matches::assert_matches;
+use ic_canonical_state::{
+    encoding::{encode_message, encode_stream_header},
+    LabelLike, CURRENT_CERTIFICATION_VERSION,
+};
+use ic_certification_test_utils::{generate_root_of_trust, hash_full_tree};
+use ic_crypto_internal_threshold_sig_bls12381::api::{combine_signatures, sign_message};
+use ic_crypto_internal_threshold_sig_bls12381::types::SecretKeyBytes;
+use ic_crypto_tree_hash::{flatmap, HashTreeBuilder, HashTreeBuilderImpl, Label, WitnessGenerator};
+use ic_test_utilities::consensus::fake::Fake;
+use ic_test_utilities::types::ids::{canister_test_id, subnet_test_id};
+use ic_test_utilities::types::messages::RequestBuilder;
+use ic_types::{
+    consensus::certification::{Certification, CertificationContent},
+    crypto::{CombinedThresholdSig, CombinedThresholdSigOf, CryptoHash, Signable, Signed},
+    messages::RequestOrResponse,
+    signature::ThresholdSignature,
+    CryptoHashOfPartialState, NumberOfNodes,
+};
+use rand::thread_rng;
+use std::collections::VecDeque;
+
+const CERTIFIED_HEIGHT: Height = Height::new(42);
+
+fn destination_subnet() -> SubnetId {
+    subnet_test_id(2)
+}
+
+fn test_slice() -> StreamSlice {
+    let header = StreamHeader {
+        begin: StreamIndex::new(3),
+        end: StreamIndex::new(5),
+        signals_end: StreamIndex::new(7),
+        reject_signals: VecDeque::new(),
+    };
+    let mut messages = StreamIndexedQueue::with_begin(StreamIndex::new(3));
+    for i in 0..2 {
+        messages.push(RequestOrResponse::Request(
+            RequestBuilder::new()
+                .sender(canister_test_id(i))
+                .receiver(canister_test_id(100))
+                .build()
+                .into(),
+        ));
+    }
+    StreamSlice::new(header, messages)
+}
+
+/// Encodes `slice` as a canonical state tree.
+fn encode_slice(slice: &StreamSlice) -> LabeledTree<Vec<u8>> {
+    let mut messages = flatmap!();
+    for (idx, msg) in slice.messages().unwrap().iter() {
+        messages
+            .try_append(
+                idx.to_label(),
+                LabeledTree::Leaf(encode_message(msg, CURRENT_CERTIFICATION_VERSION)),
+            )
+            .unwrap();
+    }
+    LabeledTree::SubTree(flatmap!(
+        Label::from("streams") => LabeledTree::SubTree(flatmap!(
+            destination_subnet().to_label() => LabeledTree::SubTree(flatmap!(
+                Label::from("header") => LabeledTree::Leaf(
+                    encode_stream_header(slice.header(), CURRENT_CERTIFICATION_VERSION)
+                ),
+                Label::from("messages") => LabeledTree::SubTree(messages),
+            )),
+        )),
+    ))
+}
+
+/// Builds a `CertifiedStreamSlice` for `tree`, with a witness covering the
+/// whole tree and a certification signed with `secret_key`.
+fn certify(tree: LabeledTree<Vec<u8>>, secret_key: &SecretKeyBytes) -> CertifiedStreamSlice {
+    let mut builder = HashTreeBuilderImpl::new();
+    hash_full_tree(&mut builder, &tree);
+    let witness_generator = builder.witness_generator().unwrap();
+    let witness = witness_generator.witness(&tree).unwrap();
+    let content = CertificationContent::new(CryptoHashOfPartialState::from(CryptoHash(
+        witness_generator.hash_tree().digest().to_vec(),
+    )));
+
+    let signature = sign_message(&content.as_signed_bytes(), secret_key).unwrap();
+    let signature = combine_signatures(&[Some(signature)], NumberOfNodes::new(1)).unwrap();
+    let mut threshold_signature = ThresholdSignature::fake();
+    threshold_signature.signature =
+        CombinedThresholdSigOf::new(CombinedThresholdSig(signature.0.to_vec()));
+
+    CertifiedStreamSlice {
+        payload: v1::LabeledTree::proxy_encode(tree),
+        merkle_proof: v1::Witness::proxy_encode(witness),
+        certification: Certification {
+            height: CERTIFIED_HEIGHT,
+            signed: Signed {
+                content,
+                signature: threshold_signature,
+            },
+        },
+    }
+}
+
+#[test]
+fn verifies_and_decodes_certified_slice() {
+    let (public_key, secret_key) = generate_root_of_trust(&mut thread_rng());
+    let slice = test_slice();
+    let certified_slice = certify(encode_slice(&slice), &secret_key);
+
+    let verified = verify_certified_stream_slice(&certified_slice, &public_key).unwrap();
+
+    assert_eq!(
+        VerifiedStreamSlice {
+            destination_subnet: destination_subnet(),
+            certified_height: CERTIFIED_HEIGHT,
+            slice,
+        },
+        verified
+    );
+}
+
+#[test]
+fn rejects_tampered_payload() {
+    let (public_key, secret_key) = generate_root_of_trust(&mut thread_rng());
+    let mut certified_slice = certify(encode_slice(&test_slice()), &secret_key);
+
+    // Replace the payload with a slice that has a different header but the
+    // same shape, so that the witness still applies.
+    let slice = test_slice();
+    let mut header = slice.header().clone();
+    header.signals_end = StreamIndex::new(8);
+    let tampered_slice = StreamSlice::new(header, slice.messages().unwrap().clone());
+    certified_slice.payload = v1::LabeledTree::proxy_encode(encode_slice(&tampered_slice));
+
+    assert_matches!(
+        verify_certified_stream_slice(&certified_slice, &public_key),
+        Err(VerificationError::HashMismatch)
+    );
+}
+
+#[test]
+fn rejects_slice_certified_by_other_subnet() {
+    let (_, secret_key) = generate_root_of_trust(&mut thread_rng());
+    let (other_public_key, _) = generate_root_of_trust(&mut thread_rng());
+    let certified_slice = certify(encode_slice(&test_slice()), &secret_key);
+
+    assert_matches!(
+        verify_certified_stream_slice(&certified_slice, &other_public_key),
+        Err(VerificationError::InvalidSignature(_))
+    );
+}
+
+#[test]
+fn round_trips_through_protobuf_encoding() {
+    let (public_key, secret_key) = generate_root_of_trust(&mut thread_rng());
+    let certified_slice = certify(encode_slice(&test_slice()), &secret_key);
+
+    let bytes = v1::CertifiedStreamSlice::proxy_encode(certified_slice.clone());
+    let decoded = decode_certified_stream_slice(&bytes).unwrap();
+
+    assert_eq!(certified_slice, decoded);
+    assert!(verify_certified_stream_slice(&decoded, &public_key).is_ok());
+}